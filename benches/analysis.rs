@@ -0,0 +1,79 @@
+//! Analysis throughput and heap allocations per minute of audio
+//!
+//! Run with `cargo bench --bench analysis`. The allocation count should stay nearly the
+//! same from the short input to the long one: per-frame work reuses its buffers, and only
+//! the per-band frame histories grow (amortized) with the length.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use bandstat::{AnalysisConfig, Analyzer};
+
+/// System allocator that counts allocations
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+const SAMPLE_RATE: u32 = 48000;
+
+/// White noise at -10 dBFS peak from a fixed seed
+fn noise(minutes: usize) -> Vec<f32> {
+    let mut state = 0x2545_F491_4F6C_DD1Du64;
+    (0..minutes * 60 * SAMPLE_RATE as usize)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            ((state as f64 / u64::MAX as f64) as f32 * 2.0 - 1.0) * 0.316
+        })
+        .collect()
+}
+
+fn main() {
+    let mut analyzer = Analyzer::new(AnalysisConfig::default());
+    // Plan the FFT before measuring
+    analyzer
+        .analyze_samples(&noise(1), SAMPLE_RATE)
+        .expect("warm-up analysis failed");
+
+    println!(
+        "{:>8} {:>10} {:>14} {:>16}",
+        "minutes", "seconds", "x realtime", "allocations"
+    );
+    for minutes in [1, 10, 60] {
+        let samples = noise(minutes);
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+        let start = Instant::now();
+        analyzer
+            .analyze_samples(&samples, SAMPLE_RATE)
+            .expect("analysis failed");
+        let seconds = start.elapsed().as_secs_f64();
+        println!(
+            "{:>8} {:>10.2} {:>14.0} {:>16}",
+            minutes,
+            seconds,
+            minutes as f64 * 60.0 / seconds,
+            ALLOCATIONS.load(Ordering::Relaxed) - allocations
+        );
+    }
+}
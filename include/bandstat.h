@@ -0,0 +1,95 @@
+#ifndef BANDSTAT_H
+#define BANDSTAT_H
+
+/* Generated with cbindgen from src/ffi.rs; do not edit by hand. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+/**
+ * Band analysis of one input; all arrays hold `num_bands` values, lowest band first
+ */
+typedef struct BandstatResult {
+  size_t num_bands;
+  /**
+   * Band labels as NUL-terminated UTF-8 strings
+   */
+  const char *const *labels;
+  /**
+   * Lower band edges in Hz
+   */
+  const float *low_hz;
+  /**
+   * Share of the total power in percent
+   */
+  const double *raw_pct;
+  /**
+   * Share of the K-weighted (BS.1770) power in percent
+   */
+  const double *k_pct;
+  /**
+   * Spread of each band's frame levels in dB (NaN when the band holds too little energy)
+   */
+  const double *dynamics_db;
+  /**
+   * BS.1770 integrated loudness in LUFS (NaN when unknown)
+   */
+  double integrated_lufs;
+  double duration_secs;
+  /**
+   * Sample rate of the input, before resampling to 48 kHz
+   */
+  uint32_t sample_rate;
+  /**
+   * Channels of the input, before the mono downmix
+   */
+  uint16_t channels;
+} BandstatResult;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Analyze a WAV, AIFF, MP3, or FLAC file (or an http(s):// URL).
+ * Returns NULL on failure; `bandstat_last_error` then describes the problem.
+ *
+ * # Safety
+ *
+ * `path` must be NULL or a valid NUL-terminated string.
+ */
+struct BandstatResult *bandstat_analyze_file(const char *path);
+
+/**
+ * Analyze `len` mono samples in -1.0..=1.0 at any sample rate.
+ * Returns NULL on failure; `bandstat_last_error` then describes the problem.
+ *
+ * # Safety
+ *
+ * `samples` must point to `len` readable floats (or may be NULL when `len` is 0).
+ */
+struct BandstatResult *bandstat_analyze_samples(const float *samples,
+                                                size_t len,
+                                                uint32_t sample_rate);
+
+/**
+ * Release a result returned by `bandstat_analyze_file` or `bandstat_analyze_samples`.
+ * NULL is ignored.
+ *
+ * # Safety
+ *
+ * `result` must be NULL or a result from this library that was not freed yet.
+ */
+void bandstat_result_free(struct BandstatResult *result);
+
+/**
+ * Message of the last failed call on this thread, or NULL if there was none.
+ * The string stays valid until the next call on the same thread; do not free it.
+ */
+const char *bandstat_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* BANDSTAT_H */
@@ -0,0 +1,81 @@
+//! Energy-based activity detection, for analyzing only the parts where the program plays
+
+use std::f32::consts::PI;
+use std::ops::Range;
+
+/// Length of the blocks whose levels decide activity, in seconds
+const BLOCK_SECS: f64 = 0.1;
+
+/// Percentile of block levels taken as the program level
+const PROGRAM_LEVEL_PERCENTILE: f64 = 0.95;
+
+/// Blocks more than this far below the program level are pauses
+const ACTIVITY_RANGE_DB: f64 = 35.0;
+
+/// Blocks below this level are never active, however quiet the program
+const ACTIVITY_FLOOR_DBFS: f64 = -70.0;
+
+/// Audio kept on each side of an active block, so pauses shorter than twice this stay in
+const HANGOVER_SECS: f64 = 0.3;
+
+/// Fade applied at each cut so joined regions don't click
+const FADE_SECS: f64 = 0.01;
+
+/// Sample ranges where the program is active, merged across short pauses
+///
+/// A 100 ms block is active when its level is within 35 dB of the program level
+/// (the 95th percentile block) and above -70 dBFS, relative to a full-scale sine.
+pub(super) fn active_regions(samples: &[f32], sample_rate: u32) -> Vec<Range<usize>> {
+    let block = ((sample_rate as f64 * BLOCK_SECS) as usize).max(1);
+    let levels: Vec<f64> = samples
+        .chunks(block)
+        .map(|chunk| {
+            let mean_square =
+                chunk.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / chunk.len() as f64;
+            10.0 * (2.0 * mean_square).log10()
+        })
+        .collect();
+    if levels.is_empty() {
+        return Vec::new();
+    }
+
+    let mut sorted = levels.clone();
+    sorted.sort_by(f64::total_cmp);
+    let program = sorted[((sorted.len() - 1) as f64 * PROGRAM_LEVEL_PERCENTILE).round() as usize];
+    let threshold = (program - ACTIVITY_RANGE_DB).max(ACTIVITY_FLOOR_DBFS);
+    let hangover = (HANGOVER_SECS / BLOCK_SECS).round() as usize;
+
+    let mut regions: Vec<Range<usize>> = Vec::new();
+    for (i, _) in levels.iter().enumerate().filter(|(_, l)| **l >= threshold) {
+        let start = i.saturating_sub(hangover) * block;
+        let end = ((i + 1 + hangover) * block).min(samples.len());
+        match regions.last_mut() {
+            Some(last) if start <= last.end => last.end = end,
+            _ => regions.push(start..end),
+        }
+    }
+    regions
+}
+
+/// The active regions of `samples` joined together, faded in and out at each cut
+pub fn active_samples(samples: &[f32], sample_rate: u32) -> Vec<f32> {
+    let fade_len = (sample_rate as f64 * FADE_SECS) as usize;
+    let mut joined = Vec::with_capacity(samples.len());
+    for region in active_regions(samples, sample_rate) {
+        let start = joined.len();
+        joined.extend_from_slice(&samples[region.clone()]);
+        let part = &mut joined[start..];
+        let len = part.len();
+        let fade = fade_len.min(len / 2);
+        for i in 0..fade {
+            let gain = 0.5 - 0.5 * (PI * i as f32 / fade as f32).cos();
+            if region.start > 0 {
+                part[i] *= gain;
+            }
+            if region.end < samples.len() {
+                part[len - 1 - i] *= gain;
+            }
+        }
+    }
+    joined
+}
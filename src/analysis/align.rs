@@ -0,0 +1,110 @@
+//! Time alignment of compared files by cross-correlation, so offsets from encoder
+//! delay or trimmed lead-ins don't change which part of the program each file covers
+
+use rustfft::num_complex::Complex;
+
+use super::fft::{plan_fft_forward, plan_fft_inverse};
+
+/// Audio from the start of each file compared when searching for the offset, in seconds
+const WINDOW_SECS: usize = 30;
+
+/// Largest offset searched in either direction, in seconds
+const MAX_OFFSET_SECS: usize = 10;
+
+/// Confidence below which the found offset is likely wrong (files with different content)
+pub const MIN_CONFIDENCE: f64 = 0.5;
+
+/// Offset of one file relative to the reference
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Alignment {
+    /// Samples by which the file lags the reference: its sample `n + offset` matches
+    /// reference sample `n` (negative when the file starts later in the program)
+    pub offset: isize,
+    /// Normalized cross-correlation at the offset: 1 for identical waveforms, near 0
+    /// when nothing lines up
+    pub confidence: f64,
+}
+
+/// Find the offset of `other` relative to `reference` from the first seconds of both
+/// (with scalar FFTs when `deterministic`)
+pub fn find_alignment(
+    reference: &[f32],
+    other: &[f32],
+    sample_rate: u32,
+    deterministic: bool,
+) -> Alignment {
+    let max_lag = MAX_OFFSET_SECS * sample_rate as usize;
+    let window = WINDOW_SECS * sample_rate as usize + max_lag;
+    let a = &reference[..reference.len().min(window)];
+    let b = &other[..other.len().min(window)];
+    if a.is_empty() || b.is_empty() {
+        return Alignment {
+            offset: 0,
+            confidence: 0.0,
+        };
+    }
+
+    // Circular cross-correlation without wrap-around: c[lag] = sum a[n] * b[n + lag]
+    let len = (a.len() + b.len()).next_power_of_two();
+    let spectrum = |x: &[f32]| {
+        let mut buffer: Vec<Complex<f32>> = x.iter().map(|&s| Complex::new(s, 0.0)).collect();
+        buffer.resize(len, Complex::new(0.0, 0.0));
+        plan_fft_forward(len, deterministic).process(&mut buffer);
+        buffer
+    };
+    let mut correlation = spectrum(a);
+    for (c, b) in correlation.iter_mut().zip(spectrum(b)) {
+        *c = c.conj() * b;
+    }
+    plan_fft_inverse(len, deterministic).process(&mut correlation);
+
+    let max_lag = max_lag as isize;
+    let lags = -(max_lag.min(a.len() as isize - 1))..=max_lag.min(b.len() as isize - 1);
+    let value = |lag: isize| correlation[lag.rem_euclid(len as isize) as usize].re;
+    let offset = lags
+        .max_by(|&x, &y| value(x).total_cmp(&value(y)))
+        .unwrap_or(0);
+
+    Alignment {
+        offset,
+        confidence: correlation_coefficient(a, b, offset),
+    }
+}
+
+/// Normalized correlation of `a[n]` with `b[n + lag]` over the samples both cover
+fn correlation_coefficient(a: &[f32], b: &[f32], lag: isize) -> f64 {
+    let start = (-lag).max(0) as usize;
+    let end = (b.len() as isize - lag).clamp(0, a.len() as isize) as usize;
+    let (mut ab, mut aa, mut bb) = (0.0f64, 0.0f64, 0.0f64);
+    for n in start..end {
+        let (x, y) = (a[n] as f64, b[(n as isize + lag) as usize] as f64);
+        ab += x * y;
+        aa += x * x;
+        bb += y * y;
+    }
+    if aa > 0.0 && bb > 0.0 {
+        (ab / (aa * bb).sqrt()).max(0.0)
+    } else {
+        0.0
+    }
+}
+
+/// Sample range of each file covering the same span of the program, given each file's
+/// alignment to the first (whose own offset is 0)
+pub fn common_spans(lengths: &[usize], offsets: &[isize]) -> Vec<std::ops::Range<usize>> {
+    // Position in the first file's timeline where every file has started
+    let start = offsets.iter().map(|&o| -o).max().unwrap_or(0).max(0);
+    let len = lengths
+        .iter()
+        .zip(offsets)
+        .map(|(&len, &offset)| (len as isize - (start + offset)).max(0))
+        .min()
+        .unwrap_or(0);
+    offsets
+        .iter()
+        .map(|&offset| {
+            let begin = (start + offset) as usize;
+            begin..begin + len as usize
+        })
+        .collect()
+}
@@ -1,82 +1,616 @@
 //! Frequency band definitions
 
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+use super::fields::open_edge;
+use crate::audio::TARGET_SAMPLE_RATE;
+
+/// Maximum length of a custom band label
+const MAX_LABEL_LEN: usize = 12;
+
+/// Maximum crossover width between adjacent bands
+const MAX_CROSSOVER_OCTAVES: f64 = 1.0;
+
+/// Labels of the condensed band groups
+pub const GROUP_LABELS: [&str; 3] = ["LOW", "MID", "HIGH"];
+
+/// Default lower edges of the MID and HIGH groups (LOW=DC-UBAS, MID=LMID-HMID, HIGH=PRES-AIR)
+pub const DEFAULT_GROUP_EDGES_HZ: [f32; 2] = [250.0, 4000.0];
+
+/// Check a `--crossover` width in octaves
+pub(super) fn check_crossover(octaves: f64) -> Result<(), String> {
+    if !(0.0..=MAX_CROSSOVER_OCTAVES).contains(&octaves) {
+        return Err(format!(
+            "--crossover must be between 0 and {} octaves",
+            MAX_CROSSOVER_OCTAVES
+        ));
+    }
+    Ok(())
+}
+
+/// FFT bins contributing to one band: weights for consecutive bins from `start`
+pub(crate) struct BandBins {
+    pub(crate) start: usize,
+    pub(crate) weights: Vec<f64>,
+}
+
+/// Compute per-band weights for bins `0..num_bins`.
+/// With a zero crossover each bin belongs to the band whose `[low, high)` range contains
+/// its centre frequency, so every bin is counted exactly once; otherwise bins within half
+/// the crossover width of an edge are split between the two neighbouring bands with
+/// raised-cosine weights that sum to 1.
+pub(crate) fn band_bins(
+    bands: &[Band],
+    freq_per_bin: f32,
+    num_bins: usize,
+    crossover_octaves: f64,
+) -> Vec<BandBins> {
+    if crossover_octaves <= 0.0 {
+        return bands
+            .iter()
+            .map(|band| {
+                let low_bin = edge_bin(band.low_hz, freq_per_bin, num_bins);
+                let high_bin = edge_bin(band.high_hz, freq_per_bin, num_bins);
+                BandBins {
+                    start: low_bin,
+                    weights: vec![1.0; high_bin.saturating_sub(low_bin)],
+                }
+            })
+            .collect();
+    }
+
+    bands
+        .iter()
+        .map(|band| {
+            let weights: Vec<f64> = (0..num_bins)
+                .map(|bin| {
+                    let freq = bin as f64 * freq_per_bin as f64;
+                    rise(freq, band.low_hz as f64, crossover_octaves)
+                        - rise(freq, band.high_hz as f64, crossover_octaves)
+                })
+                .collect();
+            let start = weights.iter().position(|&w| w > 0.0).unwrap_or(0);
+            let end = weights
+                .iter()
+                .rposition(|&w| w > 0.0)
+                .map_or(start, |i| i + 1);
+            BandBins {
+                start,
+                weights: weights[start..end].to_vec(),
+            }
+        })
+        .collect()
+}
+
+/// The layout's runs of adjacent bands, each merged into one band: the parts of the
+/// spectrum the bands cover. Gaps between bands (`--ignore-bands`, `--bands-file`) and the
+/// spectrum outside the outer edges (`--range`, `--bands octave`) are not covered.
+pub(crate) fn covered_spans(bands: &[Band]) -> Vec<Band> {
+    let mut spans: Vec<Band> = Vec::new();
+    for band in bands {
+        match spans.last_mut() {
+            Some(span) if span.high_hz == band.low_hz => span.high_hz = band.high_hz,
+            _ => spans.push(Band {
+                label: Cow::Borrowed(""),
+                low_hz: band.low_hz,
+                high_hz: band.high_hz,
+            }),
+        }
+    }
+    spans
+}
+
+/// First bin whose centre frequency is at or above `edge_hz`
+fn edge_bin(edge_hz: f32, freq_per_bin: f32, num_bins: usize) -> usize {
+    if edge_hz >= f32::MAX / 2.0 {
+        return num_bins;
+    }
+    // Tolerance keeps edges that land exactly on a bin centre from rounding up a bin
+    let position = edge_hz as f64 / freq_per_bin as f64;
+    ((position - 1e-9).ceil().max(0.0) as usize).min(num_bins)
+}
+
+/// Raised-cosine step from 0 to 1 centred on `edge_hz`, spanning `width` octaves
+fn rise(freq: f64, edge_hz: f64, width: f64) -> f64 {
+    if edge_hz <= 0.0 {
+        return 1.0;
+    }
+    if edge_hz >= f32::MAX as f64 / 2.0 || freq <= 0.0 {
+        return 0.0;
+    }
+    let x = (freq / edge_hz).log2() / width;
+    if x <= -0.5 {
+        0.0
+    } else if x >= 0.5 {
+        1.0
+    } else {
+        0.5 * (1.0 - (std::f64::consts::PI * (x + 0.5)).cos())
+    }
+}
+
+pub(super) fn parse_group_edges(spec: &str, bands: &[Band]) -> Result<[f32; 2], String> {
+    let edges: Vec<f32> = spec
+        .split(',')
+        .map(|s| s.trim().parse::<f32>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("--group-edges: invalid frequency list '{}'", spec))?;
+    let [low_mid, mid_high] = edges[..] else {
+        return Err(format!(
+            "--group-edges needs 2 frequencies (LOW/MID,MID/HIGH), got {}",
+            edges.len()
+        ));
+    };
+    if let Some(&edge) = edges
+        .iter()
+        .find(|&&e| !bands.iter().skip(1).any(|b| b.low_hz == e))
+    {
+        return Err(format!(
+            "--group-edges: {} Hz is not a band edge (see the band list)",
+            edge
+        ));
+    }
+    if low_mid >= mid_high {
+        return Err("--group-edges must be in increasing order".to_string());
+    }
+    Ok([low_mid, mid_high])
+}
+
+/// Index into `GROUP_LABELS` of the group containing a band, given the lower edges in Hz
+/// of the MID and HIGH groups
+pub fn band_group(band: &Band, group_edges: [f32; 2]) -> usize {
+    group_edges.iter().filter(|&&e| band.low_hz >= e).count()
+}
+
+/// Sum per-band values (percentages or powers) into the LOW/MID/HIGH groups, skipping
+/// NaN (excluded bands)
+pub fn group_totals(bands: &[Band], group_edges: [f32; 2], values: &[f64]) -> Vec<f64> {
+    let mut totals = vec![0.0; GROUP_LABELS.len()];
+    for (band, value) in bands.iter().zip(values) {
+        if !value.is_nan() {
+            totals[band_group(band, group_edges)] += value;
+        }
+    }
+    totals
+}
+
+pub(super) fn parse_band_labels(spec: &str, count: usize) -> Result<Vec<String>, String> {
+    let labels: Vec<String> = spec.split(',').map(|l| l.trim().to_string()).collect();
+    if labels.len() != count {
+        return Err(format!(
+            "--band-labels needs {} labels, got {}",
+            count,
+            labels.len()
+        ));
+    }
+    check_labels(&labels).map_err(|e| format!("--band-labels: {}", e))?;
+    Ok(labels)
+}
+
+fn check_labels(labels: &[impl AsRef<str>]) -> Result<(), String> {
+    for (i, label) in labels.iter().map(AsRef::as_ref).enumerate() {
+        if label.is_empty() {
+            return Err(format!("label {} is empty", i + 1));
+        }
+        if label.chars().count() > MAX_LABEL_LEN {
+            return Err(format!(
+                "'{}' must be at most {} characters",
+                label, MAX_LABEL_LEN
+            ));
+        }
+        if labels[..i]
+            .iter()
+            .any(|l| l.as_ref().eq_ignore_ascii_case(label))
+        {
+            return Err(format!("duplicate label '{}'", label));
+        }
+    }
+    Ok(())
+}
+
+/// Built-in band layouts (`--bands`)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum BandPreset {
+    /// The 14 mixer-style bands from DC to AIR
+    #[default]
+    Standard,
+    /// The 10 ISO 266 octave bands from 31.5 Hz to 16 kHz
+    Octave,
+    /// N bands equally spaced on the mel scale from DC to Nyquist
+    Mel(usize),
+    /// 8 broad bands along the sections of a channel-strip EQ
+    Mixer,
+    /// 11 bands splitting the low mids and the top end, where mastering moves are made
+    Mastering,
+    /// 8 bands around the speech range, with the energy above FM's 15 kHz cutoff on its own
+    Broadcast,
+    /// 10 bands with 5 below 120 Hz, for kick and bass work
+    SubFocus,
+}
+
+/// Most bands of a generated layout (`mel:N`, `--num-bands`)
+const MAX_GENERATED_BANDS: usize = 128;
+
+/// Span of `--num-bands` without `--range`
+const DEFAULT_LOG_RANGE_HZ: (f32, f32) = (20.0, 20000.0);
+
+/// Nominal ISO 266 octave band centres
+const OCTAVE_LABELS: [&str; 10] = [
+    "31.5", "63", "125", "250", "500", "1k", "2k", "4k", "8k", "16k",
+];
+
+/// Label, lower edge, and upper edge (Hz) of each band of the workflow presets
+type PresetTable = [(&'static str, f32, f32)];
+
+const MIXER_BANDS: &PresetTable = &[
+    ("DC", 0.0, 20.0),
+    ("SUB", 20.0, 60.0),
+    ("LOW", 60.0, 250.0),
+    ("LMID", 250.0, 800.0),
+    ("MID", 800.0, 2500.0),
+    ("HMID", 2500.0, 5000.0),
+    ("HIGH", 5000.0, 10000.0),
+    ("AIR", 10000.0, f32::MAX),
+];
+
+const MASTERING_BANDS: &PresetTable = &[
+    ("DC", 0.0, 20.0),
+    ("SUB", 20.0, 40.0),
+    ("LOW", 40.0, 100.0),
+    ("PNCH", 100.0, 200.0),
+    ("MUD", 200.0, 400.0),
+    ("BODY", 400.0, 1000.0),
+    ("MID", 1000.0, 2500.0),
+    ("PRES", 2500.0, 5000.0),
+    ("SIB", 5000.0, 8000.0),
+    ("AIR", 8000.0, 16000.0),
+    ("TOP", 16000.0, f32::MAX),
+];
+
+const BROADCAST_BANDS: &PresetTable = &[
+    ("RMBL", 0.0, 80.0),
+    ("WARM", 80.0, 250.0),
+    ("LMID", 250.0, 1000.0),
+    ("MID", 1000.0, 2000.0),
+    ("PRES", 2000.0, 4000.0),
+    ("SIB", 4000.0, 8000.0),
+    ("AIR", 8000.0, 15000.0),
+    ("HF", 15000.0, f32::MAX),
+];
+
+const SUB_FOCUS_BANDS: &PresetTable = &[
+    ("DC", 0.0, 20.0),
+    ("SUB1", 20.0, 30.0),
+    ("SUB2", 30.0, 45.0),
+    ("SUB3", 45.0, 65.0),
+    ("BAS1", 65.0, 90.0),
+    ("BAS2", 90.0, 120.0),
+    ("UBAS", 120.0, 250.0),
+    ("LMID", 250.0, 1000.0),
+    ("MID", 1000.0, 4000.0),
+    ("HIGH", 4000.0, f32::MAX),
+];
+
+impl BandPreset {
+    pub fn bands(self) -> Vec<Band> {
+        match self {
+            BandPreset::Standard => default_bands(),
+            BandPreset::Octave => octave_bands(),
+            BandPreset::Mel(count) => mel_bands(count),
+            BandPreset::Mixer => table_bands(MIXER_BANDS),
+            BandPreset::Mastering => table_bands(MASTERING_BANDS),
+            BandPreset::Broadcast => table_bands(BROADCAST_BANDS),
+            BandPreset::SubFocus => table_bands(SUB_FOCUS_BANDS),
+        }
+    }
+}
+
+fn table_bands(table: &PresetTable) -> Vec<Band> {
+    table
+        .iter()
+        .map(|&(label, low_hz, high_hz)| Band {
+            label: Cow::Borrowed(label),
+            low_hz,
+            high_hz,
+        })
+        .collect()
+}
+
+/// Parse a `--bands` layout: `standard`, `octave`, `mel:N`, or a workflow preset
+pub fn parse_band_preset(spec: &str) -> Result<BandPreset, String> {
+    let lower = spec.trim().to_ascii_lowercase();
+    match lower.as_str() {
+        "standard" => return Ok(BandPreset::Standard),
+        "octave" => return Ok(BandPreset::Octave),
+        "mixer" => return Ok(BandPreset::Mixer),
+        "mastering" => return Ok(BandPreset::Mastering),
+        "broadcast" => return Ok(BandPreset::Broadcast),
+        "sub-focus" => return Ok(BandPreset::SubFocus),
+        _ => {}
+    }
+    let count = lower.strip_prefix("mel:").ok_or_else(|| {
+        format!(
+            "unknown band layout '{}' (expected standard, octave, mel:N, mixer, mastering, broadcast, or sub-focus)",
+            spec
+        )
+    })?;
+    match count.trim().parse() {
+        Ok(count @ 2..=MAX_GENERATED_BANDS) => Ok(BandPreset::Mel(count)),
+        _ => Err(format!(
+            "mel:N takes 2 to {} bands, not '{}'",
+            MAX_GENERATED_BANDS, count
+        )),
+    }
+}
+
+impl TryFrom<String> for BandPreset {
+    type Error = String;
+
+    fn try_from(spec: String) -> Result<Self, String> {
+        parse_band_preset(&spec)
+    }
+}
+
+/// Mel scale (HTK formula) of a frequency in Hz
+fn hz_to_mel(hz: f64) -> f64 {
+    2595.0 * (1.0 + hz / 700.0).log10()
+}
+
+fn mel_to_hz(mel: f64) -> f64 {
+    700.0 * (10f64.powf(mel / 2595.0) - 1.0)
+}
+
+/// `count` bands of equal width in mel from DC to the Nyquist frequency of the analysis
+/// rate, labelled MEL1, MEL2, ... (edges rounded to 0.1 Hz; the top band stays open)
+fn mel_bands(count: usize) -> Vec<Band> {
+    let top_mel = hz_to_mel(TARGET_SAMPLE_RATE as f64 / 2.0);
+    let edge = |i: usize| {
+        let hz = mel_to_hz(top_mel * i as f64 / count as f64);
+        ((hz * 10.0).round() / 10.0) as f32
+    };
+    (0..count)
+        .map(|i| Band {
+            label: numbered_label("MEL", i),
+            low_hz: edge(i),
+            high_hz: if i + 1 == count {
+                f32::MAX
+            } else {
+                edge(i + 1)
+            },
+        })
+        .collect()
+}
+
+/// `count` bands of equal width in octaves across `range` (`--range`, default 20 Hz to
+/// 20 kHz), labelled LOG1, LOG2, ... (edges rounded to 0.1 Hz)
+pub fn log_bands(count: usize, range: Option<&str>) -> Result<Vec<Band>, String> {
+    if !(2..=MAX_GENERATED_BANDS).contains(&count) {
+        return Err(format!(
+            "--num-bands takes 2 to {} bands",
+            MAX_GENERATED_BANDS
+        ));
+    }
+    let (low, high) = range
+        .map(parse_range)
+        .transpose()?
+        .unwrap_or(DEFAULT_LOG_RANGE_HZ);
+    if low <= 0.0 {
+        return Err("--num-bands needs a --range starting above 0 Hz".to_string());
+    }
+    let octaves = (high as f64 / low as f64).log2();
+    let edge = |i: usize| {
+        let hz = low as f64 * 2f64.powf(octaves * i as f64 / count as f64);
+        ((hz * 10.0).round() / 10.0) as f32
+    };
+    Ok((0..count)
+        .map(|i| Band {
+            label: numbered_label("LOG", i),
+            low_hz: edge(i),
+            high_hz: edge(i + 1),
+        })
+        .collect())
+}
+
+/// Label of the `index`th generated band, e.g. MEL1
+fn numbered_label(prefix: &str, index: usize) -> Cow<'static, str> {
+    Cow::Owned(format!("{}{}", prefix, index + 1))
+}
+
+/// Octave bands on the base-2 grid around 1 kHz, with edges a half octave either side of
+/// each exact centre (rounded to 0.1 Hz, so 22.1 Hz to 22.6 kHz)
+fn octave_bands() -> Vec<Band> {
+    let edge = |octave: i32| {
+        let hz = 1000.0 * 2f64.powf(octave as f64 - 5.5);
+        ((hz * 10.0).round() / 10.0) as f32
+    };
+    OCTAVE_LABELS
+        .iter()
+        .zip(0..)
+        .map(|(&label, octave)| Band {
+            label: Cow::Borrowed(label),
+            low_hz: edge(octave),
+            high_hz: edge(octave + 1),
+        })
+        .collect()
+}
+
+/// Bands must be in increasing order without overlapping; gaps between them are not analyzed
+pub(super) fn check_layout(bands: &[Band]) -> Result<(), String> {
+    if bands.is_empty() {
+        return Err("no bands given".to_string());
+    }
+    let labels: Vec<&str> = bands.iter().map(|b| &*b.label).collect();
+    check_labels(&labels)?;
+    for (i, band) in bands.iter().enumerate() {
+        if !(band.low_hz >= 0.0 && band.low_hz < band.high_hz) {
+            return Err(format!("band '{}' needs 0 <= low_hz < high_hz", band.label));
+        }
+        if i > 0 && band.low_hz < bands[i - 1].high_hz {
+            return Err(format!(
+                "band '{}' overlaps '{}' (bands go from lowest to highest)",
+                band.label,
+                bands[i - 1].label
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub(super) fn parse_ignored_bands(spec: &str, bands: &[Band]) -> Result<Vec<usize>, String> {
+    band_indices("--ignore-bands", spec, bands)
+}
+
+pub(super) fn parse_excluded_bands(spec: &str, bands: &[Band]) -> Result<Vec<usize>, String> {
+    band_indices("--exclude-bands", spec, bands)
+}
+
+/// Indices of a comma-separated list of band labels, which must leave at least one band
+fn band_indices(option: &str, spec: &str, bands: &[Band]) -> Result<Vec<usize>, String> {
+    let mut indices = Vec::new();
+    for label in spec.split(',').map(str::trim) {
+        let index = bands
+            .iter()
+            .position(|b| b.label.eq_ignore_ascii_case(label))
+            .ok_or_else(|| format!("{}: unknown band '{}'", option, label))?;
+        if !indices.contains(&index) {
+            indices.push(index);
+        }
+    }
+    if indices.len() >= bands.len() {
+        return Err(format!("{} must leave at least one band", option));
+    }
+    Ok(indices)
+}
+
+pub(super) fn parse_range(spec: &str) -> Result<(f32, f32), String> {
+    let invalid = || format!("invalid --range '{}' (expected e.g. 20-16000)", spec);
+    let (low, high) = spec.split_once('-').ok_or_else(invalid)?;
+    let low: f32 = low.trim().parse().map_err(|_| invalid())?;
+    let high: f32 = high.trim().parse().map_err(|_| invalid())?;
+    if !(low >= 0.0 && high.is_finite() && low < high) {
+        return Err("--range needs 0 <= LOW < HIGH".to_string());
+    }
+    Ok((low, high))
+}
+
+/// Position of a band in `base`, the layout it was picked or clipped from (stable when
+/// bands are ignored or clipped)
+pub fn band_index(base: &[Band], band: &Band) -> usize {
+    base.iter()
+        .position(|b| b.low_hz <= band.low_hz && band.low_hz < b.high_hz)
+        .unwrap_or(0)
+}
+
 /// Frequency band with label and range
-pub(crate) struct Band {
-    pub(crate) label: &'static str,
-    pub(crate) low_hz: f32,
-    pub(crate) high_hz: f32,
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Band {
+    /// Built-in labels are borrowed; custom and loaded ones are owned
+    pub label: Cow<'static, str>,
+    pub low_hz: f32,
+    /// Upper edge in Hz (`f32::MAX` for the open top band, saved as null)
+    #[serde(with = "open_edge")]
+    pub high_hz: f32,
+}
+
+/// The bands of `base` that are analyzed: all but the `ignored` ones (indices into `base`),
+/// clipped to the analyzed `range` in Hz
+pub(super) fn analyzed_bands(
+    base: &[Band],
+    ignored: &[usize],
+    range: Option<(f32, f32)>,
+) -> Vec<Band> {
+    let mut bands: Vec<Band> = base
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !ignored.contains(i))
+        .map(|(_, band)| band.clone())
+        .collect();
+    if let Some((low, high)) = range {
+        bands = bands
+            .into_iter()
+            .filter(|b| b.low_hz < high && b.high_hz > low)
+            .map(|b| Band {
+                low_hz: b.low_hz.max(low),
+                high_hz: b.high_hz.min(high),
+                ..b
+            })
+            .collect();
+    }
+    bands
 }
 
-/// Get the 14 standard frequency bands from DC to AIR
-pub(crate) fn get_bands() -> Vec<Band> {
+/// The 14 standard frequency bands from DC to AIR
+pub fn default_bands() -> Vec<Band> {
     vec![
         Band {
-            label: "DC",
+            label: Cow::Borrowed("DC"),
             low_hz: 0.0,
             high_hz: 20.0,
         },
         Band {
-            label: "SUB1",
+            label: Cow::Borrowed("SUB1"),
             low_hz: 20.0,
             high_hz: 40.0,
         },
         Band {
-            label: "SUB2",
+            label: Cow::Borrowed("SUB2"),
             low_hz: 40.0,
             high_hz: 60.0,
         },
         Band {
-            label: "BASS",
+            label: Cow::Borrowed("BASS"),
             low_hz: 60.0,
             high_hz: 120.0,
         },
         Band {
-            label: "UBAS",
+            label: Cow::Borrowed("UBAS"),
             low_hz: 120.0,
             high_hz: 250.0,
         },
         Band {
-            label: "LMID",
+            label: Cow::Borrowed("LMID"),
             low_hz: 250.0,
             high_hz: 500.0,
         },
         Band {
-            label: "MID",
+            label: Cow::Borrowed("MID"),
             low_hz: 500.0,
             high_hz: 1000.0,
         },
         Band {
-            label: "UMID",
+            label: Cow::Borrowed("UMID"),
             low_hz: 1000.0,
             high_hz: 2000.0,
         },
         Band {
-            label: "HMID",
+            label: Cow::Borrowed("HMID"),
             low_hz: 2000.0,
             high_hz: 4000.0,
         },
         Band {
-            label: "PRES",
+            label: Cow::Borrowed("PRES"),
             low_hz: 4000.0,
             high_hz: 6000.0,
         },
         Band {
-            label: "BRIL",
+            label: Cow::Borrowed("BRIL"),
             low_hz: 6000.0,
             high_hz: 10000.0,
         },
         Band {
-            label: "HIGH",
+            label: Cow::Borrowed("HIGH"),
             low_hz: 10000.0,
             high_hz: 14000.0,
         },
         Band {
-            label: "UHIG",
+            label: Cow::Borrowed("UHIG"),
             low_hz: 14000.0,
             high_hz: 18000.0,
         },
         Band {
-            label: "AIR",
+            label: Cow::Borrowed("AIR"),
             low_hz: 18000.0,
             high_hz: f32::MAX,
         },
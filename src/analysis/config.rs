@@ -0,0 +1,209 @@
+//! Settings of an analysis, from the band layout to the FFT
+
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use super::bands::{
+    Band, DEFAULT_GROUP_EDGES_HZ, analyzed_bands, check_crossover, check_layout, default_bands,
+    parse_band_labels, parse_excluded_bands, parse_group_edges, parse_ignored_bands, parse_range,
+};
+use super::fft::{KWeightMode, Precision, check_zero_pad, parse_noise_gate};
+use super::metrics::EnergyMetric;
+use super::target::TargetCurve;
+use super::weighting::Weighting;
+use crate::audio::{ResampleQuality, Resampling};
+
+/// Settings for an [`Analyzer`](crate::Analyzer) and every analysis pass of the command
+/// line tool. Settings that need checking are set through the `with_` methods, which
+/// return an error for values the analysis can't use.
+#[derive(Clone, Debug)]
+pub struct AnalysisConfig {
+    /// Analyze only the first this many seconds of each input
+    pub max_duration: Option<f64>,
+    /// Numeric precision of the FFT and per-bin power computation
+    pub precision: Precision,
+    /// Weighting of the weighted (K-wt) shares
+    pub weighting: Weighting,
+    /// How K-weighted band powers are computed (`Filter` applies to K-weighting only)
+    pub kweight_mode: KWeightMode,
+    /// Leave quiet frames out of the weighted shares, like BS.1770 loudness gating
+    pub gating: bool,
+    /// Take the bands up to 250 Hz from a four times longer FFT
+    pub multi_resolution: bool,
+    /// Restrict FFT and resampling to portable scalar code, so results are bit-identical
+    /// across machines
+    pub deterministic: bool,
+    /// Quality of the resampling of inputs to 48 kHz
+    pub resample_quality: ResampleQuality,
+    /// Energy split metrics reported besides the bands (`--metric`)
+    pub metrics: Vec<EnergyMetric>,
+    /// Curve the single-file and comparison shares are measured against (`--target-curve`)
+    pub target_curve: Option<Arc<TargetCurve>>,
+    /// Analyze only the active regions of each file, skipping pauses (`--active-only`)
+    pub active_only: bool,
+    /// Check every FFT frame for energy conservation (`--verify`)
+    pub verify: bool,
+    /// Layout the analyzed bands are picked from, with any custom labels
+    base_bands: Vec<Band>,
+    /// Indices into `base_bands` of bands left out of the analysis
+    ignored_bands: Vec<usize>,
+    /// Analyzed frequency range in Hz; bands are clipped to it
+    range: Option<(f32, f32)>,
+    /// `base_bands` minus the ignored ones, clipped to `range`
+    bands: Vec<Band>,
+    /// Indices into `bands` of bands kept out of the percentages
+    excluded_bands: Vec<usize>,
+    /// Crossover width in octaves; 0 means hard band edges
+    crossover_octaves: f64,
+    /// Lower edges in Hz of the MID and HIGH groups
+    group_edges: [f32; 2],
+    /// Transform length over frame length
+    zero_pad: usize,
+    /// Broadband level in dBFS below which frames count toward no statistic
+    noise_gate_dbfs: Option<f64>,
+}
+
+impl Default for AnalysisConfig {
+    fn default() -> Self {
+        let bands = default_bands();
+        Self {
+            max_duration: None,
+            precision: Precision::default(),
+            weighting: Weighting::default(),
+            kweight_mode: KWeightMode::default(),
+            gating: true,
+            multi_resolution: false,
+            deterministic: false,
+            resample_quality: ResampleQuality::default(),
+            metrics: Vec::new(),
+            target_curve: None,
+            active_only: false,
+            verify: false,
+            base_bands: bands.clone(),
+            ignored_bands: Vec::new(),
+            range: None,
+            bands,
+            excluded_bands: Vec::new(),
+            crossover_octaves: 0.0,
+            group_edges: DEFAULT_GROUP_EDGES_HZ,
+            zero_pad: 1,
+            noise_gate_dbfs: None,
+        }
+    }
+}
+
+impl AnalysisConfig {
+    /// Analyze `bands` instead of the 14 standard ones. They must go from lowest to
+    /// highest without overlapping; gaps between them are not analyzed. Set the layout
+    /// before the other band settings, which refer to its bands.
+    pub fn with_bands(mut self, bands: Vec<Band>) -> Result<Self, String> {
+        check_layout(&bands)?;
+        self.base_bands = bands;
+        self.ignored_bands.clear();
+        self.excluded_bands.clear();
+        self.update_bands();
+        Ok(self)
+    }
+
+    /// Rename the bands of the layout from a comma-separated list, one label per band
+    pub fn with_band_labels(mut self, spec: &str) -> Result<Self, String> {
+        let labels = parse_band_labels(spec, self.base_bands.len())?;
+        for (band, label) in self.base_bands.iter_mut().zip(labels) {
+            band.label = Cow::Owned(label);
+        }
+        self.update_bands();
+        Ok(self)
+    }
+
+    /// Leave the bands of a comma-separated list of labels out of the analysis
+    pub fn with_ignored_bands(mut self, spec: &str) -> Result<Self, String> {
+        self.ignored_bands = parse_ignored_bands(spec, &self.base_bands)?;
+        self.update_bands();
+        Ok(self)
+    }
+
+    /// Restrict the analysis to a frequency range such as `20-16000`, clipping the bands
+    pub fn with_range(mut self, spec: &str) -> Result<Self, String> {
+        self.range = Some(parse_range(spec)?);
+        self.update_bands();
+        Ok(self)
+    }
+
+    /// Keep the bands of a comma-separated list of labels out of the percentage
+    /// normalization while still reporting them (set after the other band settings)
+    pub fn with_excluded_bands(mut self, spec: &str) -> Result<Self, String> {
+        self.excluded_bands = parse_excluded_bands(spec, &self.bands)?;
+        Ok(self)
+    }
+
+    /// Share energy near band edges using crossovers `octaves` wide (0 to 1)
+    pub fn with_crossover(mut self, octaves: f64) -> Result<Self, String> {
+        check_crossover(octaves)?;
+        self.crossover_octaves = octaves;
+        Ok(self)
+    }
+
+    /// Move the LOW/MID and MID/HIGH group boundaries to two band edges, e.g. `120,2000`
+    pub fn with_group_edges(mut self, spec: &str) -> Result<Self, String> {
+        self.group_edges = parse_group_edges(spec, &self.base_bands)?;
+        Ok(self)
+    }
+
+    /// Zero-pad each frame to `factor` (a power of two up to 16) times its length
+    pub fn with_zero_pad(mut self, factor: usize) -> Result<Self, String> {
+        check_zero_pad(factor)?;
+        self.zero_pad = factor;
+        Ok(self)
+    }
+
+    /// Drop frames below a broadband level such as `-60dB` from all statistics
+    pub fn with_noise_gate(mut self, spec: &str) -> Result<Self, String> {
+        self.noise_gate_dbfs = Some(parse_noise_gate(spec)?);
+        Ok(self)
+    }
+
+    fn update_bands(&mut self) {
+        self.bands = analyzed_bands(&self.base_bands, &self.ignored_bands, self.range);
+    }
+
+    /// Bands that are analyzed, lowest first
+    pub fn bands(&self) -> &[Band] {
+        &self.bands
+    }
+
+    /// Layout the analyzed bands were picked or clipped from
+    pub fn base_bands(&self) -> &[Band] {
+        &self.base_bands
+    }
+
+    /// Indices into `bands()` of the bands kept out of the percentages
+    pub fn excluded_bands(&self) -> &[usize] {
+        &self.excluded_bands
+    }
+
+    pub fn crossover_octaves(&self) -> f64 {
+        self.crossover_octaves
+    }
+
+    /// Lower edges in Hz of the MID and HIGH groups
+    pub fn group_edges(&self) -> [f32; 2] {
+        self.group_edges
+    }
+
+    pub fn zero_pad(&self) -> usize {
+        self.zero_pad
+    }
+
+    /// Level in dBFS of the noise gate, if any
+    pub fn noise_gate_dbfs(&self) -> Option<f64> {
+        self.noise_gate_dbfs
+    }
+
+    /// How inputs are resampled to the analysis rate
+    pub fn resampling(&self) -> Resampling {
+        Resampling {
+            quality: self.resample_quality,
+            deterministic: self.deterministic,
+        }
+    }
+}
@@ -0,0 +1,45 @@
+//! Per-frame band share distributions, compared by matching their sorted values
+//! (histogram matching) so edits, loops, and tempo changes don't shift the comparison
+
+/// Quantile levels kept per band: 5%, 10%, ... 95%
+const SHARE_QUANTILES: usize = 19;
+
+/// Quantiles of each band's per-frame share, from its values over all frames
+/// (NaN for every level when there were no frames)
+pub(super) fn share_quantiles(mut band_shares: Vec<Vec<f64>>) -> Vec<Vec<f64>> {
+    for shares in &mut band_shares {
+        shares.sort_by(f64::total_cmp);
+    }
+    band_shares
+        .iter()
+        .map(|sorted| {
+            (1..=SHARE_QUANTILES)
+                .map(|level| quantile(sorted, level as f64 / (SHARE_QUANTILES + 1) as f64))
+                .collect()
+        })
+        .collect()
+}
+
+/// Linearly interpolated quantile of sorted values
+fn quantile(sorted: &[f64], level: f64) -> f64 {
+    let Some(last) = sorted.len().checked_sub(1) else {
+        return f64::NAN;
+    };
+    let position = level * last as f64;
+    let low = position.floor() as usize;
+    let high = (low + 1).min(last);
+    sorted[low] + (sorted[high] - sorted[low]) * (position - low as f64)
+}
+
+/// Typical per-frame share of a band: the mean of its quantiles, so the difference of two
+/// files' typical shares is the mean of their quantile differences (the matched shift)
+pub fn typical_share(quantiles: &[f64]) -> f64 {
+    quantiles.iter().sum::<f64>() / quantiles.len() as f64
+}
+
+/// Mean absolute quantile difference of a band between two files (earth mover's distance),
+/// which also catches a distribution that widened or narrowed without moving
+pub fn share_distance(base: &[f64], other: &[f64]) -> f64 {
+    let total: f64 = other.iter().zip(base).map(|(o, b)| (o - b).abs()).sum();
+    total / base.len() as f64
+}
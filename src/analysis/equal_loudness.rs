@@ -0,0 +1,122 @@
+//! Perceptual importance of bands from the equal-loudness contours (ISO 226:2003)
+
+use std::ops::RangeInclusive;
+use std::sync::OnceLock;
+
+use super::bands::Band;
+
+/// Loudness levels ISO 226:2003 gives contours for, in phon
+pub(crate) const PHON_RANGE: RangeInclusive<u8> = 20..=90;
+
+/// ISO 226:2003 Table 1: frequency (Hz), exponent for loudness perception a_f, magnitude of
+/// the linear transfer function normalized at 1 kHz L_U (dB), and threshold of hearing T_f
+/// (dB)
+const CONTOUR_PARAMETERS: [(f64, f64, f64, f64); 29] = [
+    (20.0, 0.532, -31.6, 78.5),
+    (25.0, 0.506, -27.2, 68.7),
+    (31.5, 0.480, -23.0, 59.5),
+    (40.0, 0.455, -19.1, 51.1),
+    (50.0, 0.432, -15.9, 44.0),
+    (63.0, 0.409, -13.0, 37.5),
+    (80.0, 0.387, -10.3, 31.5),
+    (100.0, 0.367, -8.1, 26.5),
+    (125.0, 0.349, -6.2, 22.1),
+    (160.0, 0.330, -4.5, 17.9),
+    (200.0, 0.315, -3.1, 14.4),
+    (250.0, 0.301, -2.0, 11.4),
+    (315.0, 0.288, -1.1, 8.6),
+    (400.0, 0.276, -0.4, 6.2),
+    (500.0, 0.267, 0.0, 4.4),
+    (630.0, 0.259, 0.3, 3.0),
+    (800.0, 0.253, 0.5, 2.2),
+    (1000.0, 0.250, 0.0, 2.4),
+    (1250.0, 0.246, -2.7, 3.5),
+    (1600.0, 0.244, -4.1, 1.7),
+    (2000.0, 0.243, -1.0, -1.3),
+    (2500.0, 0.243, 1.7, -4.2),
+    (3150.0, 0.243, 2.5, -6.0),
+    (4000.0, 0.242, 1.2, -5.4),
+    (5000.0, 0.242, -2.1, -1.5),
+    (6300.0, 0.245, -7.1, 6.0),
+    (8000.0, 0.254, -11.2, 12.6),
+    (10000.0, 0.271, -10.7, 13.9),
+    (12500.0, 0.301, -3.1, 12.3),
+];
+
+/// Equal-loudness contour at `phon`: (frequency Hz, SPL dB) (ISO 226:2003 equation 1)
+pub(super) fn contour(phon: f64) -> Vec<(f64, f64)> {
+    CONTOUR_PARAMETERS
+        .iter()
+        .map(|&(freq, a_f, l_u, t_f)| {
+            let a = 4.47e-3 * (10f64.powf(0.025 * phon) - 1.15)
+                + (0.4 * 10f64.powf((t_f + l_u) / 10.0 - 9.0)).powf(a_f);
+            (freq, 10.0 / a_f * a.log10() - l_u + 94.0)
+        })
+        .collect()
+}
+
+/// Level of a contour at a frequency, interpolated on a log-frequency axis (clamped at the
+/// ends)
+pub(super) fn contour_spl(contour: &[(f64, f64)], freq: f64) -> f64 {
+    let (first, last) = (contour[0], contour[contour.len() - 1]);
+    if freq <= first.0 {
+        return first.1;
+    }
+    if freq >= last.0 {
+        return last.1;
+    }
+    let upper = contour.iter().position(|&(f, _)| f >= freq).unwrap();
+    let (f0, l0) = contour[upper - 1];
+    let (f1, l1) = contour[upper];
+    let t = (freq / f0).ln() / (f1 / f0).ln();
+    l0 + t * (l1 - l0)
+}
+
+/// Relative importance of a band (1.0 at the ear's most sensitive frequency).
+/// Evaluated at the band's geometric centre: a band needing N dB more level to sound
+/// equally loud counts 2^(-N/10) as much, as loudness in sones doubles every 10 phon.
+pub(crate) fn band_importance(band: &Band) -> f64 {
+    let low = band.low_hz.max(20.0) as f64;
+    let high = (band.high_hz as f64).min(20000.0).max(low);
+    let centre = (low * high).sqrt();
+    static CONTOUR_40_PHON: OnceLock<Vec<(f64, f64)>> = OnceLock::new();
+    let contour_40 = CONTOUR_40_PHON.get_or_init(|| contour(40.0));
+    let most_sensitive = contour_40
+        .iter()
+        .map(|&(_, spl)| spl)
+        .fold(f64::INFINITY, f64::min);
+    2f64.powf(-(contour_spl(contour_40, centre) - most_sensitive) / 10.0)
+}
+
+/// How far apart two band distributions are, in percentage points
+pub struct DifferenceScore {
+    /// Mean absolute difference over all bands
+    pub flat: f64,
+    /// Mean absolute difference weighted by band importance
+    pub weighted: f64,
+}
+
+/// Flat and importance-weighted mean absolute difference of two band distributions.
+/// Bands without a share in either distribution (excluded bands) don't count
+pub fn difference_score(bands: &[Band], a: &[f64], b: &[f64]) -> DifferenceScore {
+    let (mut flat, mut weighted, mut total_weight, mut counted) = (0.0, 0.0, 0.0, 0);
+    for (band, (va, vb)) in bands.iter().zip(a.iter().zip(b)) {
+        let diff = (vb - va).abs();
+        if diff.is_nan() {
+            continue;
+        }
+        let w = band_importance(band);
+        flat += diff;
+        weighted += w * diff;
+        total_weight += w;
+        counted += 1;
+    }
+    DifferenceScore {
+        flat: flat / counted.max(1) as f64,
+        weighted: if total_weight > 0.0 {
+            weighted / total_weight
+        } else {
+            0.0
+        },
+    }
+}
@@ -1,15 +1,40 @@
 //! FFT-based audio analysis
 
+use std::ops::Range;
 use std::sync::Arc;
 
-use rustfft::FftPlanner;
+use rayon::prelude::*;
+use realfft::{FftError, RealFftPlanner, RealToComplex};
 use rustfft::num_complex::Complex;
+use rustfft::num_traits::Zero;
+use rustfft::{Fft, FftNum, FftPlanner, FftPlannerScalar};
+use serde::{Deserialize, Serialize};
 
-use super::bands::Band;
+use super::bands::{Band, BandBins, band_bins, covered_spans};
+use super::config::AnalysisConfig;
+use super::distribution::share_quantiles;
+use super::fields::{nan_as_null, nan_vec_as_null};
+use super::kweight::{KWeightFilter, create_k_weight_table};
+use super::loudness::{gate_frames, integrated_loudness, mean_square_scale};
+use super::metrics::{EnergyMetric, metric_bins};
+use super::verify::{self, FrameCheck, VerifySummary};
+use super::weighting::{Weighting, weight_table};
 use crate::audio::AudioData;
 
-pub(crate) const FFT_SIZE: usize = 16384;
-pub(crate) const HOP_SIZE: usize = 2048;
+pub const FFT_SIZE: usize = 16384;
+pub const HOP_SIZE: usize = 2048;
+
+/// Number of non-negative frequency bins (DC through Nyquist inclusive)
+pub(crate) const SPECTRUM_BINS: usize = FFT_SIZE / 2 + 1;
+
+/// FFT length of the low bands with `--multi-resolution`: a quarter of the bin width
+pub(crate) const LONG_FFT_SIZE: usize = 4 * FFT_SIZE;
+
+/// Bands ending at or below this frequency come from the long FFT with `--multi-resolution`
+pub(crate) const LONG_FFT_MAX_HZ: f32 = 250.0;
+
+/// Largest `--zero-pad` factor
+const MAX_ZERO_PAD: usize = 16;
 
 /// Minimum power threshold to avoid log(0) in dB calculations
 const MIN_POWER: f64 = 1e-20;
@@ -18,154 +43,1315 @@ const MIN_POWER: f64 = 1e-20;
 const DYNAMICS_THRESHOLD_DB: f64 = 60.0;
 
 /// Minimum band power percentage to display dynamics (bands below this show "-")
-pub(crate) const DYNAMICS_DISPLAY_THRESHOLD_PCT: f64 = 0.5;
+pub const DYNAMICS_DISPLAY_THRESHOLD_PCT: f64 = 0.5;
+
+/// Numeric precision of the FFT and per-bin power computation
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Precision {
+    /// Single precision (fast default)
+    #[default]
+    F32,
+    /// Double precision end-to-end (for validating against reference implementations)
+    F64,
+}
+
+/// How the K-weighted band powers are computed
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum KWeightMode {
+    /// Scale each FFT bin's power by the filter's response at its frequency (fast default)
+    #[default]
+    Spectral,
+    /// Run the BS.1770 biquads on the samples and transform them again, so window leakage
+    /// into the low bands is weighted like the signal it leaked from
+    Filter,
+}
+
+/// Plan a forward FFT, avoiding CPU-specific SIMD kernels when results must be
+/// bit-identical across runs and machines (`--deterministic`)
+///
+/// Frames are transformed in parallel on rayon's threads but accumulated in order either
+/// way, so results don't depend on the thread count and the thread pool needs no pinning;
+/// what varies is the SIMD code path picked from the CPU's features at runtime.
+pub fn plan_fft_forward<T: FftNum>(len: usize, deterministic: bool) -> Arc<dyn Fft<T>> {
+    if deterministic {
+        FftPlannerScalar::new().plan_fft_forward(len)
+    } else {
+        FftPlanner::new().plan_fft_forward(len)
+    }
+}
+
+/// Plan an inverse FFT, avoiding CPU-specific SIMD kernels in deterministic mode
+pub(crate) fn plan_fft_inverse<T: FftNum>(len: usize, deterministic: bool) -> Arc<dyn Fft<T>> {
+    if deterministic {
+        FftPlannerScalar::new().plan_fft_inverse(len)
+    } else {
+        FftPlanner::new().plan_fft_inverse(len)
+    }
+}
+
+/// Plan a forward FFT of real input, returning bins DC through Nyquist
+///
+/// realfft runs a half-length complex FFT from the SIMD-picking planner, so deterministic
+/// mode falls back to a full-length scalar one.
+pub(crate) fn plan_real_fft_forward<T: FftNum>(
+    len: usize,
+    deterministic: bool,
+) -> Arc<dyn RealToComplex<T>> {
+    if deterministic {
+        Arc::new(ScalarRealToComplex::new(len))
+    } else {
+        RealFftPlanner::new().plan_fft_forward(len)
+    }
+}
+
+/// Real-to-complex transform on a full-length complex FFT (imaginary parts zero)
+pub(super) struct ScalarRealToComplex<T> {
+    fft: Arc<dyn Fft<T>>,
+}
+
+impl<T: FftNum> ScalarRealToComplex<T> {
+    pub(super) fn new(len: usize) -> Self {
+        Self {
+            fft: FftPlannerScalar::new().plan_fft_forward(len),
+        }
+    }
+}
+
+impl<T: FftNum> RealToComplex<T> for ScalarRealToComplex<T> {
+    fn process(&self, input: &mut [T], output: &mut [Complex<T>]) -> Result<(), FftError> {
+        let mut scratch = self.make_scratch_vec();
+        self.process_with_scratch(input, output, &mut scratch)
+    }
+
+    fn process_with_scratch(
+        &self,
+        input: &mut [T],
+        output: &mut [Complex<T>],
+        scratch: &mut [Complex<T>],
+    ) -> Result<(), FftError> {
+        if input.len() != self.len() {
+            return Err(FftError::InputBuffer(self.len(), input.len()));
+        }
+        if output.len() != self.complex_len() {
+            return Err(FftError::OutputBuffer(self.complex_len(), output.len()));
+        }
+        if scratch.len() < self.get_scratch_len() {
+            return Err(FftError::ScratchBuffer(
+                self.get_scratch_len(),
+                scratch.len(),
+            ));
+        }
+        // The first `len` scratch values hold the complex signal, the rest the FFT's scratch
+        let (buffer, fft_scratch) = scratch.split_at_mut(self.len());
+        for (b, &x) in buffer.iter_mut().zip(input.iter()) {
+            *b = Complex::new(x, T::zero());
+        }
+        self.fft.process_with_scratch(
+            buffer,
+            &mut fft_scratch[..self.fft.get_inplace_scratch_len()],
+        );
+        output.copy_from_slice(&buffer[..output.len()]);
+        Ok(())
+    }
+
+    fn get_scratch_len(&self) -> usize {
+        self.len() + self.fft.get_inplace_scratch_len()
+    }
+
+    fn len(&self) -> usize {
+        self.fft.len()
+    }
+
+    fn make_input_vec(&self) -> Vec<T> {
+        vec![T::zero(); self.len()]
+    }
+
+    fn make_output_vec(&self) -> Vec<Complex<T>> {
+        vec![Complex::zero(); self.complex_len()]
+    }
+
+    fn make_scratch_vec(&self) -> Vec<Complex<T>> {
+        vec![Complex::zero(); self.get_scratch_len()]
+    }
+}
+
+/// Check a `--zero-pad` factor: the transform length over FFT_SIZE
+pub(super) fn check_zero_pad(factor: usize) -> Result<(), String> {
+    if !factor.is_power_of_two() || factor > MAX_ZERO_PAD {
+        return Err(format!(
+            "--zero-pad must be a power of two from 1 to {}",
+            MAX_ZERO_PAD
+        ));
+    }
+    Ok(())
+}
+
+/// Parse a `--gate` level in dBFS, with or without a `dB`/`dBFS` suffix
+pub(super) fn parse_noise_gate(spec: &str) -> Result<f64, String> {
+    let lower = spec.trim().to_ascii_lowercase();
+    let number = lower
+        .strip_suffix("dbfs")
+        .or_else(|| lower.strip_suffix("db"))
+        .unwrap_or(&lower);
+    let db: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("--gate: invalid level '{}' (e.g. -60dB)", spec))?;
+    if !db.is_finite() || db >= 0.0 {
+        return Err("--gate must be below 0 dBFS".to_string());
+    }
+    Ok(db)
+}
+
+/// Whether a frame's power spectrum is loud enough for `gate`, a `--gate` threshold in
+/// dBFS with the factor turning summed spectrum power into mean square
+///
+/// Levels are relative to a full-scale sine, like `--verify-tone`.
+fn passes_noise_gate(power: &[f64], gate: Option<(f64, f64)>) -> bool {
+    gate.is_none_or(|(threshold, scale)| {
+        let mean_square = power.iter().sum::<f64>() * scale;
+        10.0 * (2.0 * mean_square).log10() >= threshold
+    })
+}
 
 /// Create a Hanning window of the given size
-pub(crate) fn create_hanning_window(size: usize) -> Vec<f32> {
+pub fn create_hanning_window(size: usize) -> Vec<f32> {
     let pi2 = 2.0 * std::f32::consts::PI;
     (0..size)
         .map(|i| 0.5 * (1.0 - (pi2 * i as f32 / (size - 1) as f32).cos()))
         .collect()
 }
 
+fn create_hanning_window_f64(size: usize) -> Vec<f64> {
+    let pi2 = 2.0 * std::f64::consts::PI;
+    (0..size)
+        .map(|i| 0.5 * (1.0 - (pi2 * i as f64 / (size - 1) as f64).cos()))
+        .collect()
+}
+
+/// Real-input FFT plan, window, and buffers for computing windowed power spectra
+#[derive(Clone)]
+pub(crate) enum SpectrumAnalyzer {
+    F32 {
+        fft: Arc<dyn RealToComplex<f32>>,
+        window: Vec<f32>,
+        input: Vec<f32>,
+        output: Vec<Complex<f32>>,
+        scratch: Vec<Complex<f32>>,
+    },
+    F64 {
+        fft: Arc<dyn RealToComplex<f64>>,
+        window: Vec<f64>,
+        input: Vec<f64>,
+        output: Vec<Complex<f64>>,
+        scratch: Vec<Complex<f64>>,
+    },
+}
+
+impl SpectrumAnalyzer {
+    /// Plan a transform of `size` points at the given precision
+    pub(super) fn with_size(precision: Precision, size: usize, deterministic: bool) -> Self {
+        Self::with_padding(precision, size, size, deterministic)
+    }
+
+    /// Plan a transform of `fft_len` points for frames of `size` samples, zero-padded
+    pub(super) fn with_padding(
+        precision: Precision,
+        size: usize,
+        fft_len: usize,
+        deterministic: bool,
+    ) -> Self {
+        match precision {
+            Precision::F32 => {
+                let fft = plan_real_fft_forward(fft_len, deterministic);
+                SpectrumAnalyzer::F32 {
+                    window: create_hanning_window(size),
+                    input: fft.make_input_vec(),
+                    output: fft.make_output_vec(),
+                    scratch: fft.make_scratch_vec(),
+                    fft,
+                }
+            }
+            Precision::F64 => {
+                let fft = plan_real_fft_forward(fft_len, deterministic);
+                SpectrumAnalyzer::F64 {
+                    window: create_hanning_window_f64(size),
+                    input: fft.make_input_vec(),
+                    output: fft.make_output_vec(),
+                    scratch: fft.make_scratch_vec(),
+                    fft,
+                }
+            }
+        }
+    }
+
+    /// Windowed power spectrum |X[k]|^2 of one frame of the window's size, DC through Nyquist.
+    /// A zero-padded transform interpolates the spectrum; its power is scaled down by the
+    /// padding so the bins sum to the same total as without it.
+    /// Returns the windowed frame's time-domain energy when `with_energy` is set.
+    pub(crate) fn power_spectrum(
+        &mut self,
+        frame: &[f32],
+        power: &mut [f64],
+        with_energy: bool,
+    ) -> Option<f64> {
+        match self {
+            SpectrumAnalyzer::F32 {
+                fft,
+                window,
+                input,
+                output,
+                scratch,
+            } => {
+                for ((i, &x), &w) in input.iter_mut().zip(frame).zip(window.iter()) {
+                    *i = x * w;
+                }
+                input[window.len()..].fill(0.0);
+                let energy = with_energy.then(|| input.iter().map(|&x| (x as f64).powi(2)).sum());
+                let scale = window.len() as f64 / input.len() as f64;
+                fft.process_with_scratch(input, output, scratch)
+                    .expect("buffers were made by the plan");
+                for (p, c) in power.iter_mut().zip(output.iter()) {
+                    *p = c.norm_sqr() as f64 * scale;
+                }
+                energy
+            }
+            SpectrumAnalyzer::F64 {
+                fft,
+                window,
+                input,
+                output,
+                scratch,
+            } => {
+                for ((i, &x), &w) in input.iter_mut().zip(frame).zip(window.iter()) {
+                    *i = x as f64 * w;
+                }
+                input[window.len()..].fill(0.0);
+                let energy = with_energy.then(|| input.iter().map(|x| x.powi(2)).sum());
+                let scale = window.len() as f64 / input.len() as f64;
+                fft.process_with_scratch(input, output, scratch)
+                    .expect("buffers were made by the plan");
+                for (p, c) in power.iter_mut().zip(output.iter()) {
+                    *p = c.norm_sqr() * scale;
+                }
+                energy
+            }
+        }
+    }
+}
+
+/// Analysis state reused across files and intervals: frame transforms and K-weight table,
+/// and the settings of the passes that use them
+pub struct AnalysisContext {
+    /// One per thread of `analyze_stats`, added as needed; the first serves the
+    /// sequential passes
+    transforms: Vec<FrameTransform>,
+    k_weights: Vec<f64>,
+    kweight_mode: KWeightMode,
+    /// Weighting of the weighted columns, with its table unless it is K
+    weighting: Weighting,
+    weights: Option<Vec<f64>>,
+    sample_rate: u32,
+    /// Transform length of the (possibly zero-padded) FFT_SIZE frames
+    fft_len: usize,
+    crossover_octaves: f64,
+    /// Indices of the bands kept out of the percentages
+    excluded_bands: Vec<usize>,
+    /// Whether weighted stats skip quiet frames, like BS.1770 loudness
+    gating: bool,
+    /// `--gate` threshold in dBFS with the factor turning summed spectrum power into mean
+    /// square
+    noise_gate: Option<(f64, f64)>,
+    metrics: Vec<EnergyMetric>,
+    /// Whether stats skip the pauses found by `active_regions` (`--active-only`)
+    active_only: bool,
+    /// Energy-conservation checks of the sequential passes, when every frame is checked
+    /// (`--verify`)
+    verify: Option<VerifySummary>,
+}
+
+/// FFT plan, window, and scratch spectrum for transforming frames on one thread
+#[derive(Clone)]
+struct FrameTransform {
+    spectrum: SpectrumAnalyzer,
+    power: Vec<f64>,
+    /// Long FFT of the low bands (`--multi-resolution`)
+    long: Option<LongSpectrum>,
+}
+
+impl AnalysisContext {
+    /// Build a context for audio at `sample_rate` analyzed with `config`
+    pub fn new(sample_rate: u32, config: &AnalysisConfig) -> Self {
+        let fft_len = FFT_SIZE * config.zero_pad();
+        let weighting = config.weighting.clone();
+        Self {
+            transforms: vec![FrameTransform {
+                spectrum: SpectrumAnalyzer::with_padding(
+                    config.precision,
+                    FFT_SIZE,
+                    fft_len,
+                    config.deterministic,
+                ),
+                power: vec![0.0; fft_len / 2 + 1],
+                long: config
+                    .multi_resolution
+                    .then(|| LongSpectrum::new(sample_rate, config)),
+            }],
+            k_weights: create_k_weight_table(fft_len, sample_rate),
+            kweight_mode: config.kweight_mode,
+            weights: (weighting != Weighting::K)
+                .then(|| weight_table(&weighting, fft_len, sample_rate)),
+            weighting,
+            sample_rate,
+            fft_len,
+            crossover_octaves: config.crossover_octaves(),
+            excluded_bands: config.excluded_bands().to_vec(),
+            gating: config.gating,
+            noise_gate: config
+                .noise_gate_dbfs()
+                .map(|threshold| (threshold, mean_square_scale())),
+            metrics: config.metrics.clone(),
+            active_only: config.active_only,
+            verify: config.verify.then(VerifySummary::default),
+        }
+    }
+
+    pub(crate) fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Indices of the bands kept out of the percentages (`--exclude-bands`)
+    pub fn excluded_bands(&self) -> &[usize] {
+        &self.excluded_bands
+    }
+
+    /// Checks of the frames `for_each_frame` and `analyze_interval` transformed so far
+    pub fn verify_summary(&self) -> Option<&VerifySummary> {
+        self.verify.as_ref()
+    }
+
+    /// Whether stats are taken over the active regions only (`--active-only`)
+    pub fn active_only(&self) -> bool {
+        self.active_only
+    }
+
+    /// Whether `weights` come from the K-weighting filter (`--kweight-mode filter`) rather
+    /// than a table
+    fn filters(&self, weights: FrameWeights) -> bool {
+        self.kweight_mode == KWeightMode::Filter
+            && match weights {
+                FrameWeights::Raw => false,
+                FrameWeights::Weighted => self.weighting == Weighting::K,
+                FrameWeights::Loudness => true,
+            }
+    }
+
+    /// Bins of the bands in the (possibly zero-padded) spectrum
+    fn band_bins(&self, bands: &[Band]) -> Vec<BandBins> {
+        band_bins(
+            bands,
+            self.freq_per_bin(),
+            self.spectrum_bins(),
+            self.crossover_octaves,
+        )
+    }
+
+    /// Bins of the parts of the spectrum the bands cover (see `covered_spans`), which
+    /// `--verify` expects the band powers to add up to; none without `--verify`
+    fn covered_bins(&self, bands: &[Band]) -> Vec<BandBins> {
+        if self.verify.is_none() {
+            return Vec::new();
+        }
+        self.band_bins(&covered_spans(bands))
+    }
+
+    /// Bin spacing of the (possibly zero-padded) spectrum in Hz
+    fn freq_per_bin(&self) -> f32 {
+        self.sample_rate as f32 / self.fft_len as f32
+    }
+
+    /// Number of bins of the (possibly zero-padded) spectrum, DC through Nyquist
+    fn spectrum_bins(&self) -> usize {
+        self.fft_len / 2 + 1
+    }
+
+    /// Bins of the bands that come from the long FFT, by band index (none without it)
+    fn long_band_bins(&self, bands: &[Band]) -> Vec<(usize, BandBins)> {
+        if self.transforms[0].long.is_none() {
+            return Vec::new();
+        }
+        let freq_per_bin = self.sample_rate as f32 / LONG_FFT_SIZE as f32;
+        band_bins(
+            bands,
+            freq_per_bin,
+            LONG_FFT_SIZE / 2 + 1,
+            self.crossover_octaves,
+        )
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| bands[*i].high_hz <= LONG_FFT_MAX_HZ)
+        .collect()
+    }
+}
+
+/// Power spectra of LONG_FFT_SIZE frames centered on the FFT_SIZE frames, for the low bands
+#[derive(Clone)]
+struct LongSpectrum {
+    spectrum: SpectrumAnalyzer,
+    k_weights: Vec<f64>,
+    /// Table of a `--weighting` other than K
+    weights: Option<Vec<f64>>,
+    power: Vec<f64>,
+    frame: Vec<f32>,
+    /// Running sums of the squared window, to rescale frames cut off at the ends
+    window_energy: Vec<f64>,
+}
+
+impl LongSpectrum {
+    fn new(sample_rate: u32, config: &AnalysisConfig) -> Self {
+        let mut window_energy = vec![0.0];
+        let mut total = 0.0;
+        for w in create_hanning_window_f64(LONG_FFT_SIZE) {
+            total += w * w;
+            window_energy.push(total);
+        }
+        Self {
+            spectrum: SpectrumAnalyzer::with_size(
+                config.precision,
+                LONG_FFT_SIZE,
+                config.deterministic,
+            ),
+            k_weights: create_k_weight_table(LONG_FFT_SIZE, sample_rate),
+            weights: (config.weighting != Weighting::K)
+                .then(|| weight_table(&config.weighting, LONG_FFT_SIZE, sample_rate)),
+            power: vec![0.0; LONG_FFT_SIZE / 2 + 1],
+            frame: vec![0.0; LONG_FFT_SIZE],
+            window_energy,
+        }
+    }
+
+    /// Power spectrum of the long frame centered on the FFT_SIZE frame at `pos`, scaled to
+    /// the level of an FFT_SIZE spectrum. Past the ends of `samples` the frame is padded
+    /// with silence and its power scaled up by the part of the window that is missing.
+    fn analyze(&mut self, samples: &[f32], pos: usize) {
+        let start = (pos + FFT_SIZE / 2) as isize - (LONG_FFT_SIZE / 2) as isize;
+        let first = (-start).max(0) as usize;
+        let last = (samples.len() as isize - start).clamp(0, LONG_FFT_SIZE as isize) as usize;
+        self.frame.fill(0.0);
+        if first < last {
+            let offset = (start + first as isize) as usize;
+            self.frame[first..last].copy_from_slice(&samples[offset..offset + last - first]);
+        }
+        self.spectrum
+            .power_spectrum(&self.frame, &mut self.power, false);
+
+        let covered = self.window_energy[last.max(first)] - self.window_energy[first];
+        let ratio = FFT_SIZE as f64 / LONG_FFT_SIZE as f64;
+        let scale = ratio * ratio * self.window_energy[LONG_FFT_SIZE] / covered;
+        self.power.iter_mut().for_each(|p| *p *= scale);
+    }
+}
+
+/// Which weighting the band powers of a pass over the frames get
+#[derive(Clone, Copy, PartialEq)]
+pub(super) enum FrameWeights {
+    Raw,
+    /// The `--weighting` of the weighted columns
+    Weighted,
+    /// K-weighting whatever `--weighting` is, for loudness
+    Loudness,
+}
+
+/// Per-bin power factors of `weights` out of a K table and the `--weighting` one (`None`
+/// when it is K)
+fn weight_table_of<'a>(
+    weights: FrameWeights,
+    k_weights: &'a [f64],
+    other: Option<&'a [f64]>,
+) -> Option<&'a [f64]> {
+    match weights {
+        FrameWeights::Raw => None,
+        FrameWeights::Weighted => Some(other.unwrap_or(k_weights)),
+        FrameWeights::Loudness => Some(k_weights),
+    }
+}
+
+/// Sum bin powers into bands, optionally applying per-bin weights
+fn sum_bands(power: &[f64], band_bins: &[BandBins], k_weights: Option<&[f64]>, out: &mut [f64]) {
+    for (total, bins) in out.iter_mut().zip(band_bins) {
+        *total = sum_band(power, bins, k_weights);
+    }
+}
+
+/// Replace the powers of the long-FFT bands with sums over the long spectrum
+fn sum_long_bands(
+    long: &LongSpectrum,
+    long_bins: &[(usize, BandBins)],
+    weights: FrameWeights,
+    out: &mut [f64],
+) {
+    let k_weights = weight_table_of(weights, &long.k_weights, long.weights.as_deref());
+    for (band, bins) in long_bins {
+        out[*band] = sum_band(&long.power, bins, k_weights);
+    }
+}
+
+/// Power of the parts of the spectrum the bands cover
+fn covered_power(power: &[f64], covered_bins: &[BandBins]) -> f64 {
+    covered_bins
+        .iter()
+        .map(|bins| sum_band(power, bins, None))
+        .sum()
+}
+
+fn sum_band(power: &[f64], bins: &BandBins, k_weights: Option<&[f64]>) -> f64 {
+    let bin_powers = power[bins.start..].iter().zip(&bins.weights);
+    match k_weights {
+        Some(weights) => bin_powers
+            .zip(&weights[bins.start..])
+            .map(|((p, share), k)| p * share * k)
+            .sum(),
+        None => bin_powers.map(|(p, share)| p * share).sum(),
+    }
+}
+
 /// Analyze a single time interval and return band powers
-pub(crate) fn analyze_interval(
+pub fn analyze_interval(
     samples: &[f32],
-    fft: &Arc<dyn rustfft::Fft<f32>>,
-    window: &[f32],
+    ctx: &mut AnalysisContext,
     bands: &[Band],
-    freq_per_bin: f32,
-    k_weights: Option<&[f64]>,
+    use_k_weighting: bool,
 ) -> Vec<f64> {
-    let nyquist_bin = FFT_SIZE / 2;
     let mut band_powers = vec![0.0f64; bands.len()];
-    let mut pos = 0;
+    for_each_frame(
+        samples,
+        ctx,
+        bands,
+        use_k_weighting,
+        |frame_bands, gated| {
+            if gated {
+                return;
+            }
+            for (total, p) in band_powers.iter_mut().zip(frame_bands) {
+                *total += p;
+            }
+        },
+    );
+    band_powers
+}
 
+/// Mean windowed power spectrum |X[k]|^2 of the FFT frames in `samples`, DC through Nyquist
+pub fn average_spectrum(samples: &[f32], ctx: &mut AnalysisContext) -> Vec<f64> {
+    let mut mean = vec![0.0f64; SPECTRUM_BINS];
+    let mut frames = 0usize;
+    let mut pos = 0;
+    let FrameTransform {
+        spectrum, power, ..
+    } = &mut ctx.transforms[0];
     while pos + FFT_SIZE <= samples.len() {
-        let mut buffer: Vec<Complex<f32>> = (0..FFT_SIZE)
-            .map(|j| Complex::new(samples[pos + j] * window[j], 0.0))
-            .collect();
+        spectrum.power_spectrum(&samples[pos..pos + FFT_SIZE], power, false);
+        for (m, p) in mean.iter_mut().zip(power.iter()) {
+            *m += p;
+        }
+        frames += 1;
+        pos += HOP_SIZE;
+    }
+    if frames > 0 {
+        mean.iter_mut().for_each(|m| *m /= frames as f64);
+    }
+    mean
+}
 
-        fft.process(&mut buffer);
+/// Call `on_frame` with the band powers of each FFT frame (frames advance by HOP_SIZE)
+/// and whether `--gate` drops the frame from statistics
+pub fn for_each_frame<F>(
+    samples: &[f32],
+    ctx: &mut AnalysisContext,
+    bands: &[Band],
+    use_k_weighting: bool,
+    on_frame: F,
+) where
+    F: FnMut(&[f64], bool),
+{
+    let weights = if use_k_weighting {
+        FrameWeights::Weighted
+    } else {
+        FrameWeights::Raw
+    };
+    for_each_weighted_frame(samples, ctx, bands, weights, on_frame);
+}
+
+/// `for_each_frame` with the band powers weighted by `weights`
+pub(super) fn for_each_weighted_frame<F>(
+    samples: &[f32],
+    ctx: &mut AnalysisContext,
+    bands: &[Band],
+    weights: FrameWeights,
+    mut on_frame: F,
+) where
+    F: FnMut(&[f64], bool),
+{
+    let band_bins = ctx.band_bins(bands);
+    let long_bins = ctx.long_band_bins(bands);
+    let covered_bins = ctx.covered_bins(bands);
+    // With --kweight-mode filter, the K-weighted bands come from the filtered samples
+    let k_samples = ctx
+        .filters(weights)
+        .then(|| KWeightFilter::filtered(ctx.sample_rate, samples));
+    let (weights, k_weights) = match k_samples {
+        Some(_) => (FrameWeights::Raw, None),
+        None => (
+            weights,
+            weight_table_of(weights, &ctx.k_weights, ctx.weights.as_deref()),
+        ),
+    };
+    let gate = ctx.noise_gate;
+    let mut checks = ctx.verify.as_mut();
+    let FrameTransform {
+        spectrum,
+        power,
+        long,
+    } = &mut ctx.transforms[0];
+    let mut frame_bands = vec![0.0f64; bands.len()];
+    let mut raw_bands = vec![0.0f64; bands.len()];
+    let mut pos = 0;
 
-        for (band_idx, band) in bands.iter().enumerate() {
-            let low_bin = ((band.low_hz / freq_per_bin) as usize).min(nyquist_bin);
-            let high_bin = ((band.high_hz / freq_per_bin) as usize).min(nyquist_bin);
+    while pos + FFT_SIZE <= samples.len() {
+        let energy =
+            spectrum.power_spectrum(&samples[pos..pos + FFT_SIZE], power, checks.is_some());
+        let gated = !passes_noise_gate(power, gate);
 
-            let power: f64 = buffer[low_bin..high_bin]
-                .iter()
-                .enumerate()
-                .map(|(i, c)| {
-                    let bin_power = c.norm_sqr() as f64;
-                    match k_weights {
-                        Some(weights) => bin_power * weights[low_bin + i],
-                        None => bin_power,
-                    }
-                })
-                .sum();
+        if let (Some(energy), Some(summary)) = (energy, checks.as_deref_mut()) {
+            // Unweighted band total for the energy-conservation check
+            sum_bands(power, &band_bins, None, &mut raw_bands);
+            summary.record(verify::check_frame(
+                energy,
+                power,
+                FFT_SIZE,
+                raw_bands.iter().sum(),
+                covered_power(power, &covered_bins),
+            ));
+        }
 
-            band_powers[band_idx] += power;
+        let frame_samples = match k_samples.as_deref() {
+            Some(k_samples) => {
+                spectrum.power_spectrum(&k_samples[pos..pos + FFT_SIZE], power, false);
+                k_samples
+            }
+            None => samples,
+        };
+        sum_bands(power, &band_bins, k_weights, &mut frame_bands);
+        if let Some(long) = long.as_mut() {
+            long.analyze(frame_samples, pos);
+            sum_long_bands(long, &long_bins, weights, &mut frame_bands);
         }
+        on_frame(&frame_bands, gated);
 
         pos += HOP_SIZE;
     }
+}
 
-    band_powers
+/// Percentages of the total of the powers not in `excluded` (the `--exclude-bands`), whose
+/// percentage is NaN
+pub fn powers_to_percentages(powers: &[f64], excluded: &[usize]) -> Vec<f64> {
+    let mut percentages = vec![0.0; powers.len()];
+    write_percentages(powers, excluded, &mut percentages);
+    percentages
 }
 
-/// Convert raw powers to percentages
-pub(crate) fn powers_to_percentages(powers: &[f64]) -> Vec<f64> {
-    let total: f64 = powers.iter().sum();
-    if total > 0.0 {
-        powers.iter().map(|p| (p / total) * 100.0).collect()
-    } else {
-        vec![0.0; powers.len()]
+/// `powers_to_percentages` into an existing buffer, for per-frame use
+fn write_percentages(powers: &[f64], excluded: &[usize], out: &mut [f64]) {
+    let total: f64 = powers
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !excluded.contains(i))
+        .map(|(_, p)| p)
+        .sum();
+    for (i, (pct, p)) in out.iter_mut().zip(powers).enumerate() {
+        *pct = if excluded.contains(&i) {
+            f64::NAN
+        } else if total > 0.0 {
+            (p / total) * 100.0
+        } else {
+            0.0
+        };
     }
 }
 
 /// Result of unified stats analysis
-pub(crate) struct StatsResult {
-    pub(crate) raw_powers: Vec<f64>,
-    pub(crate) k_powers: Vec<f64>,
-    pub(crate) dynamics: Vec<f64>,
+#[derive(Serialize, Deserialize)]
+pub struct StatsResult {
+    pub raw_powers: Vec<f64>,
+    pub k_powers: Vec<f64>,
+    #[serde(with = "nan_vec_as_null")]
+    pub dynamics: Vec<f64>,
+    /// Dynamics of the K-weighted band power over the frames K-wt counts
+    #[serde(with = "nan_vec_as_null")]
+    pub k_dynamics: Vec<f64>,
+    /// Standard error of each band percentage (NaN if the audio is too short to tell)
+    #[serde(with = "nan_vec_as_null")]
+    pub raw_pct_se: Vec<f64>,
+    #[serde(with = "nan_vec_as_null")]
+    pub k_pct_se: Vec<f64>,
+    /// Half-width of the 95% confidence interval of each band percentage (NaN if unknown)
+    #[serde(with = "nan_vec_as_null")]
+    pub raw_pct_ci: Vec<f64>,
+    #[serde(with = "nan_vec_as_null")]
+    pub k_pct_ci: Vec<f64>,
+    /// Quantiles of each band's per-frame Raw share in % (`SHARE_QUANTILES` levels; not saved)
+    #[serde(skip)]
+    pub share_quantiles: Vec<Vec<f64>>,
+    /// Share of the Raw energy in % of each `--metric` (NaN without energy; not saved)
+    #[serde(skip)]
+    pub metric_pct: Vec<f64>,
+    /// Frames that passed the noise gate
+    pub frames: usize,
+    /// BS.1770 integrated loudness (gated even with `--no-gate`)
+    #[serde(with = "nan_as_null")]
+    pub integrated_lufs: f64,
+    /// Energy-conservation checks of the frames, with `--verify` (not saved)
+    #[serde(skip)]
+    pub verify: Option<VerifySummary>,
 }
 
-/// Analyze all stats in a single FFT pass with optional progress callback
-pub(crate) fn analyze_stats<F>(
-    audio: &AudioData,
-    bands: &[Band],
-    k_weights: &[f64],
-    mut on_progress: F,
-) -> StatsResult
-where
-    F: FnMut(u8),
-{
-    let freq_per_bin = audio.sample_rate as f32 / FFT_SIZE as f32;
-    let window = create_hanning_window(FFT_SIZE);
-    let nyquist_bin = FFT_SIZE / 2;
+/// Frames per variation block: hops adding up to one FFT length, so blocks don't overlap
+const BLOCK_FRAMES: usize = FFT_SIZE / HOP_SIZE;
+
+/// Frames (about 10 s at 48 kHz) below which band shares are too unstable to read
+/// to a tenth of a percent; shorter files get a warning and their confidence intervals
+pub const MIN_STABLE_FRAMES: usize = 30 * BLOCK_FRAMES;
+
+/// Two-sided 95% quantiles of Student's t distribution for 1 to 30 degrees of freedom
+const T_95: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+
+/// Two-sided 95% quantile of Student's t distribution, which widens the interval
+/// when only a few blocks estimate the spread
+fn t_quantile_95(degrees_of_freedom: usize) -> f64 {
+    match degrees_of_freedom.checked_sub(1).map(|i| T_95.get(i)) {
+        Some(Some(&t)) => t,
+        // No spread can be estimated from a single block
+        None => f64::NAN,
+        // Cornish-Fisher expansion around the normal quantile, within 0.2% above 30
+        Some(None) => {
+            let z = 1.959964;
+            z + (z * z * z + z) / (4.0 * degrees_of_freedom as f64)
+        }
+    }
+}
+
+/// Spread of band percentages across consecutive blocks of frames, used to estimate
+/// how much a file's band shares would move between equally long excerpts
+struct BlockVariation {
+    /// Bands left out of the percentages
+    excluded: Vec<usize>,
+    current: Vec<f64>,
+    /// Percentages of the finished block
+    block_pct: Vec<f64>,
+    frames: usize,
+    sum: Vec<f64>,
+    sum_sq: Vec<f64>,
+    blocks: usize,
+}
+
+impl BlockVariation {
+    fn new(num_bands: usize, excluded: &[usize]) -> Self {
+        Self {
+            excluded: excluded.to_vec(),
+            current: vec![0.0; num_bands],
+            block_pct: vec![0.0; num_bands],
+            frames: 0,
+            sum: vec![0.0; num_bands],
+            sum_sq: vec![0.0; num_bands],
+            blocks: 0,
+        }
+    }
+
+    fn push_frame(&mut self, band_powers: &[f64]) {
+        for (acc, power) in self.current.iter_mut().zip(band_powers) {
+            *acc += power;
+        }
+        self.frames += 1;
+        if self.frames < BLOCK_FRAMES {
+            return;
+        }
+        // Silent blocks carry no balance information
+        if self.current.iter().sum::<f64>() > 0.0 {
+            write_percentages(&self.current, &self.excluded, &mut self.block_pct);
+            for (i, pct) in self.block_pct.iter().enumerate() {
+                self.sum[i] += pct;
+                self.sum_sq[i] += pct * pct;
+            }
+            self.blocks += 1;
+        }
+        self.current.fill(0.0);
+        self.frames = 0;
+    }
 
-    let mut planner = FftPlanner::new();
-    let fft = planner.plan_fft_forward(FFT_SIZE);
+    /// Half-width of the 95% confidence interval of the mean percentage per band
+    fn confidence_intervals(&self) -> Vec<f64> {
+        let t = t_quantile_95(self.blocks.saturating_sub(1));
+        self.standard_errors().iter().map(|se| t * se).collect()
+    }
+
+    /// Standard error of the mean percentage per band
+    fn standard_errors(&self) -> Vec<f64> {
+        if self.blocks < 2 {
+            return vec![f64::NAN; self.sum.len()];
+        }
+        let n = self.blocks as f64;
+        self.sum
+            .iter()
+            .zip(&self.sum_sq)
+            .map(|(&sum, &sum_sq)| {
+                let variance = ((sum_sq - sum * sum / n) / (n - 1.0)).max(0.0);
+                (variance / n).sqrt()
+            })
+            .collect()
+    }
+}
 
-    let mut raw_powers = vec![0.0f64; bands.len()];
-    let mut k_powers = vec![0.0f64; bands.len()];
-    let mut band_db_per_frame: Vec<Vec<f64>> = vec![Vec::new(); bands.len()];
+/// Frames `analyze_stats` transforms in parallel before accumulating them
+pub(super) const PARALLEL_FRAMES: usize = 1024;
+
+/// What `analyze_stats` computes from each frame, shared by its threads
+struct FramePlan<'a> {
+    band_bins: &'a [BandBins],
+    /// Bins the bands cover, for the `--verify` check
+    covered_bins: &'a [BandBins],
+    long_bins: &'a [(usize, BandBins)],
+    metric_bins: &'a [Range<usize>],
+    k_weights: &'a [f64],
+    /// Table of a `--weighting` other than K, used instead of `k_weights` for the weighted
+    /// band powers
+    weights: Option<&'a [f64]>,
+    /// K-filtered copy of the samples with `--kweight-mode filter`, used instead of `k_weights`
+    k_samples: Option<&'a [f32]>,
+    gate: Option<(f64, f64)>,
+    verify: bool,
+}
+
+/// Band powers of one frame, as `analyze_stats` accumulates them
+#[derive(Clone)]
+struct FrameResult {
+    raw: Vec<f64>,
+    /// Weighted band powers (only set when the frame passes the gate)
+    k: Vec<f64>,
+    /// K-weighted power of the frame, for loudness, and its band powers when the weighted
+    /// ones are not K-weighted
+    loudness: f64,
+    loudness_bands: Vec<f64>,
+    passes_gate: bool,
+    /// Energy-conservation check of the frame (only with `--verify`)
+    check: Option<FrameCheck>,
+    /// Whole-spectrum power and the part each `--metric` covers (only with metrics)
+    spectrum_power: f64,
+    metric_powers: Vec<f64>,
+}
+
+impl FrameResult {
+    fn new(num_bands: usize, num_metrics: usize) -> Self {
+        Self {
+            raw: vec![0.0; num_bands],
+            k: vec![0.0; num_bands],
+            loudness: 0.0,
+            loudness_bands: vec![0.0; num_bands],
+            passes_gate: false,
+            check: None,
+            spectrum_power: 0.0,
+            metric_powers: vec![0.0; num_metrics],
+        }
+    }
+}
 
-    let total_frames = if audio.samples.len() >= FFT_SIZE {
-        (audio.samples.len() - FFT_SIZE) / HOP_SIZE + 1
+impl FrameTransform {
+    /// Transform the FFT_SIZE frame at `pos` and sum it into `out`
+    fn analyze_frame(
+        &mut self,
+        samples: &[f32],
+        pos: usize,
+        plan: &FramePlan,
+        out: &mut FrameResult,
+    ) {
+        let power = &mut self.power;
+        let energy =
+            self.spectrum
+                .power_spectrum(&samples[pos..pos + FFT_SIZE], power, plan.verify);
+
+        sum_bands(power, plan.band_bins, None, &mut out.raw);
+        // Checked before the long FFT replaces the low bands, like `for_each_weighted_frame`
+        out.check = energy.map(|energy| {
+            let covered = covered_power(power, plan.covered_bins);
+            verify::check_frame(energy, power, FFT_SIZE, out.raw.iter().sum(), covered)
+        });
+        if let Some(long) = self.long.as_mut() {
+            long.analyze(samples, pos);
+            sum_long_bands(long, plan.long_bins, FrameWeights::Raw, &mut out.raw);
+        }
+
+        out.passes_gate = passes_noise_gate(power, plan.gate);
+        if !out.passes_gate {
+            return;
+        }
+        if !plan.metric_bins.is_empty() {
+            out.spectrum_power = power.iter().sum::<f64>();
+            for (total, bins) in out.metric_powers.iter_mut().zip(plan.metric_bins) {
+                *total = power[bins.clone()].iter().sum::<f64>();
+            }
+        }
+        match plan.k_samples {
+            Some(k_samples) => {
+                self.spectrum
+                    .power_spectrum(&k_samples[pos..pos + FFT_SIZE], power, false);
+                sum_bands(power, plan.band_bins, None, &mut out.k);
+                if let Some(long) = self.long.as_mut() {
+                    long.analyze(k_samples, pos);
+                    sum_long_bands(long, plan.long_bins, FrameWeights::Raw, &mut out.k);
+                }
+                out.loudness = out.k.iter().sum();
+            }
+            None => {
+                let weights = plan.weights.unwrap_or(plan.k_weights);
+                sum_bands(power, plan.band_bins, Some(weights), &mut out.k);
+                if let Some(long) = self.long.as_ref() {
+                    sum_long_bands(long, plan.long_bins, FrameWeights::Weighted, &mut out.k);
+                }
+                out.loudness = if plan.weights.is_some() {
+                    let bands = &mut out.loudness_bands;
+                    sum_bands(power, plan.band_bins, Some(plan.k_weights), bands);
+                    if let Some(long) = self.long.as_ref() {
+                        sum_long_bands(long, plan.long_bins, FrameWeights::Loudness, bands);
+                    }
+                    bands.iter().sum()
+                } else {
+                    out.k.iter().sum()
+                };
+            }
+        }
+    }
+}
+
+/// Frames of HOP_SIZE steps that fit in `samples` samples
+fn frame_count(samples: usize) -> usize {
+    if samples >= FFT_SIZE {
+        (samples - FFT_SIZE) / HOP_SIZE + 1
     } else {
         0
-    };
+    }
+}
 
-    let mut pos = 0;
-    let mut frame_idx = 0;
-    let mut last_progress: u8 = 0;
+/// Stats analysis fed a piece of audio at a time, e.g. as it is decoded, so a long file
+/// never has to be held in memory. Only the samples later frames still need are kept.
+pub struct StatsAccumulator<'a> {
+    ctx: &'a mut AnalysisContext,
+    band_bins: Vec<BandBins>,
+    long_bins: Vec<(usize, BandBins)>,
+    covered_bins: Vec<BandBins>,
+    metric_bins: Vec<Range<usize>>,
+    gate: Option<(f64, f64)>,
+    /// Checks of the frames so far, with `--verify`
+    verify: Option<VerifySummary>,
+    /// Samples past the end of an FFT_SIZE frame that its long frame covers (0 without one)
+    lookahead: usize,
+    /// Buffered samples, the first of which is sample `offset` of the audio
+    samples: Vec<f32>,
+    /// The buffered samples run through the K-weighting filter (`--kweight-mode filter`)
+    k_samples: Vec<f32>,
+    k_filter: Option<KWeightFilter>,
+    offset: usize,
+    /// Frames transformed so far
+    frames: usize,
+    results: Vec<FrameResult>,
+    raw_powers: Vec<f64>,
+    band_db_per_frame: Vec<Vec<f64>>,
+    frame_pct: Vec<f64>,
+    band_shares: Vec<Vec<f64>>,
+    /// Whole-spectrum energy and the part each --metric covers
+    spectrum_power: f64,
+    metric_powers: Vec<f64>,
+    raw_variation: BlockVariation,
+    /// Weighted band powers per frame (one row of bands.len() each), summed once the
+    /// loudness gate is known
+    k_frames: Vec<f64>,
+    /// K-weighted power of each frame, for the loudness gate
+    loudness: Vec<f64>,
+}
 
-    while pos + FFT_SIZE <= audio.samples.len() {
-        let mut buffer: Vec<Complex<f32>> = (0..FFT_SIZE)
-            .map(|j| Complex::new(audio.samples[pos + j] * window[j], 0.0))
-            .collect();
+impl<'a> StatsAccumulator<'a> {
+    /// Start analyzing audio at the context's sample rate. With `expected_samples`, the
+    /// per-frame histories are sized up front, so long files don't allocate once per hop.
+    pub fn new(
+        bands: &[Band],
+        ctx: &'a mut AnalysisContext,
+        expected_samples: Option<usize>,
+    ) -> Self {
+        let expected_frames = expected_samples.map_or(0, frame_count);
+        let metric_bins = metric_bins(&ctx.metrics, ctx.freq_per_bin(), ctx.spectrum_bins());
+        let lookahead = if ctx.transforms[0].long.is_some() {
+            (LONG_FFT_SIZE - FFT_SIZE) / 2
+        } else {
+            0
+        };
+        Self {
+            band_bins: ctx.band_bins(bands),
+            long_bins: ctx.long_band_bins(bands),
+            covered_bins: ctx.covered_bins(bands),
+            metric_powers: vec![0.0; metric_bins.len()],
+            metric_bins,
+            gate: ctx.noise_gate,
+            verify: ctx.verify.is_some().then(VerifySummary::default),
+            lookahead,
+            samples: Vec::new(),
+            k_samples: Vec::new(),
+            k_filter: ctx
+                .filters(FrameWeights::Weighted)
+                .then(|| KWeightFilter::new(ctx.sample_rate)),
+            offset: 0,
+            frames: 0,
+            results: Vec::new(),
+            raw_powers: vec![0.0; bands.len()],
+            band_db_per_frame: vec![Vec::with_capacity(expected_frames); bands.len()],
+            frame_pct: vec![0.0; bands.len()],
+            band_shares: vec![Vec::with_capacity(expected_frames); bands.len()],
+            spectrum_power: 0.0,
+            raw_variation: BlockVariation::new(bands.len(), &ctx.excluded_bands),
+            k_frames: Vec::with_capacity(expected_frames * bands.len()),
+            loudness: Vec::with_capacity(expected_frames),
+            ctx,
+        }
+    }
 
-        fft.process(&mut buffer);
+    /// Frames analyzed so far
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
 
-        for (band_idx, band) in bands.iter().enumerate() {
-            let low_bin = ((band.low_hz / freq_per_bin) as usize).min(nyquist_bin);
-            let high_bin = ((band.high_hz / freq_per_bin) as usize).min(nyquist_bin);
+    /// Add the next samples, analyzing the frames they complete a run at a time
+    pub fn push(&mut self, samples: &[f32]) {
+        self.samples.extend_from_slice(samples);
+        if let Some(filter) = self.k_filter.as_mut() {
+            filter.process_into(samples, &mut self.k_samples);
+        }
+        let end = self.offset + self.samples.len();
+        let ready = frame_count(end.saturating_sub(self.lookahead));
+        while ready - self.frames >= PARALLEL_FRAMES {
+            self.transform(PARALLEL_FRAMES);
+        }
+    }
 
-            let mut raw_power = 0.0f64;
-            let mut k_power = 0.0f64;
+    /// Analyze the frames left at the end of the audio and return the stats
+    pub fn finish(mut self) -> StatsResult {
+        let total_frames = frame_count(self.offset + self.samples.len());
+        while self.frames < total_frames {
+            self.transform(PARALLEL_FRAMES.min(total_frames - self.frames));
+        }
 
-            for (i, c) in buffer[low_bin..high_bin].iter().enumerate() {
-                let bin_power = c.norm_sqr() as f64;
-                raw_power += bin_power;
-                k_power += bin_power * k_weights[low_bin + i];
+        let num_bands = self.raw_powers.len();
+        let mut k_powers = vec![0.0f64; num_bands];
+        let mut k_variation = BlockVariation::new(num_bands, &self.ctx.excluded_bands);
+
+        // Like BS.1770 integrated loudness, leave silent and very quiet frames out of the
+        // weighted columns
+        let scale = mean_square_scale();
+        let mean_squares: Vec<f64> = self.loudness.iter().map(|p| p * scale).collect();
+        let loudness_gate = gate_frames(&mean_squares);
+        let integrated_lufs = integrated_loudness(&mean_squares, &loudness_gate);
+        let passed = if self.ctx.gating {
+            loudness_gate
+        } else {
+            vec![true; mean_squares.len()]
+        };
+        let mut k_db_per_frame: Vec<Vec<f64>> = vec![Vec::new(); num_bands];
+        for (frame, _) in self
+            .k_frames
+            .chunks_exact(num_bands)
+            .zip(&passed)
+            .filter(|(_, p)| **p)
+        {
+            for ((total, dbs), &power) in k_powers.iter_mut().zip(&mut k_db_per_frame).zip(frame) {
+                *total += power;
+                if power > MIN_POWER {
+                    dbs.push(10.0 * power.log10());
+                }
             }
+            k_variation.push_frame(frame);
+        }
 
-            raw_powers[band_idx] += raw_power;
-            k_powers[band_idx] += k_power;
+        let spectrum_power = self.spectrum_power;
+        StatsResult {
+            raw_powers: self.raw_powers,
+            k_powers,
+            dynamics: band_dynamics(&self.band_db_per_frame),
+            k_dynamics: band_dynamics(&k_db_per_frame),
+            raw_pct_se: self.raw_variation.standard_errors(),
+            k_pct_se: k_variation.standard_errors(),
+            raw_pct_ci: self.raw_variation.confidence_intervals(),
+            k_pct_ci: k_variation.confidence_intervals(),
+            share_quantiles: share_quantiles(self.band_shares),
+            metric_pct: self
+                .metric_powers
+                .iter()
+                .map(|p| {
+                    if spectrum_power > 0.0 {
+                        p / spectrum_power * 100.0
+                    } else {
+                        f64::NAN
+                    }
+                })
+                .collect(),
+            frames: mean_squares.len(),
+            integrated_lufs,
+            verify: self.verify,
+        }
+    }
 
-            // Collect dB for dynamics (using raw power)
-            if raw_power > MIN_POWER {
-                band_db_per_frame[band_idx].push(10.0 * raw_power.log10());
+    /// Transform the next `count` frames in parallel, then accumulate them in order, so
+    /// the sums come out the same whatever the thread count
+    fn transform(&mut self, count: usize) {
+        let plan = FramePlan {
+            band_bins: &self.band_bins,
+            covered_bins: &self.covered_bins,
+            long_bins: &self.long_bins,
+            metric_bins: &self.metric_bins,
+            k_weights: &self.ctx.k_weights,
+            weights: self.ctx.weights.as_deref(),
+            k_samples: self.k_filter.is_some().then_some(self.k_samples.as_slice()),
+            gate: self.gate,
+            verify: self.verify.is_some(),
+        };
+        // One transform per thread, each working through a contiguous run of frames
+        let threads = rayon::current_num_threads();
+        if self.ctx.transforms.len() < threads {
+            let first = self.ctx.transforms[0].clone();
+            self.ctx.transforms.resize(threads, first);
+        }
+        if self.results.len() < count {
+            let num_bands = self.raw_powers.len();
+            let row = FrameResult::new(num_bands, self.metric_bins.len());
+            self.results.resize(count, row);
+        }
+
+        let results = &mut self.results[..count];
+        let per_thread = count.div_ceil(threads);
+        let (samples, first_frame, offset) = (&self.samples, self.frames, self.offset);
+        results
+            .par_chunks_mut(per_thread)
+            .zip(self.ctx.transforms[..threads].par_iter_mut())
+            .enumerate()
+            .for_each(|(chunk, (rows, transform))| {
+                let first = first_frame + chunk * per_thread;
+                for (i, row) in rows.iter_mut().enumerate() {
+                    let pos = (first + i) * HOP_SIZE - offset;
+                    transform.analyze_frame(samples, pos, &plan, row);
+                }
+            });
+
+        for frame in results.iter() {
+            if let (Some(summary), Some(check)) = (self.verify.as_mut(), frame.check) {
+                summary.record(check);
+            }
+            // Frames below --gate (e.g. room tone between takes) count toward nothing
+            if !frame.passes_gate {
+                continue;
+            }
+            self.raw_variation.push_frame(&frame.raw);
+            self.k_frames.extend_from_slice(&frame.k);
+            self.loudness.push(frame.loudness);
+            let frame_total: f64 = frame.raw.iter().sum();
+            if frame_total > 0.0 {
+                write_percentages(&frame.raw, &self.ctx.excluded_bands, &mut self.frame_pct);
+                for (shares, &pct) in self.band_shares.iter_mut().zip(&self.frame_pct) {
+                    shares.push(pct);
+                }
+            }
+            if !self.metric_bins.is_empty() {
+                self.spectrum_power += frame.spectrum_power;
+                for (total, power) in self.metric_powers.iter_mut().zip(&frame.metric_powers) {
+                    *total += power;
+                }
+            }
+
+            for ((total, dbs), &raw_power) in self
+                .raw_powers
+                .iter_mut()
+                .zip(&mut self.band_db_per_frame)
+                .zip(&frame.raw)
+            {
+                *total += raw_power;
+                // Collect dB for dynamics (using raw power)
+                if raw_power > MIN_POWER {
+                    dbs.push(10.0 * raw_power.log10());
+                }
             }
         }
+        self.frames += count;
 
-        // Progress update
-        frame_idx += 1;
-        if total_frames > 0 {
-            let progress = ((frame_idx * 100) / total_frames) as u8;
+        // Drop the samples before the (long) frame of the next FFT_SIZE frame
+        let keep_from = (self.frames * HOP_SIZE).saturating_sub(self.lookahead);
+        self.samples.drain(..keep_from - self.offset);
+        if self.k_filter.is_some() {
+            self.k_samples.drain(..keep_from - self.offset);
+        }
+        self.offset = keep_from;
+    }
+}
+
+/// Analyze all stats in a single FFT pass with optional progress callback
+pub fn analyze_stats<F>(
+    audio: &AudioData,
+    bands: &[Band],
+    ctx: &mut AnalysisContext,
+    mut on_progress: F,
+) -> StatsResult
+where
+    F: FnMut(u8),
+{
+    let total_frames = frame_count(audio.samples.len());
+    let mut stats = StatsAccumulator::new(bands, ctx, Some(audio.samples.len()));
+    let mut last_progress: u8 = 0;
+    for chunk in audio.samples.chunks(PARALLEL_FRAMES * HOP_SIZE) {
+        stats.push(chunk);
+        if let Some(progress) = (stats.frames() * 100).checked_div(total_frames) {
+            let progress = progress as u8;
             if progress != last_progress {
                 on_progress(progress);
                 last_progress = progress;
             }
         }
+    }
+    let result = stats.finish();
+    if total_frames > 0 && last_progress != 100 {
+        on_progress(100);
+    }
+    result
+}
 
-        pos += HOP_SIZE;
+/// Dynamics of each band (see `band_dynamics`) over a run of per-frame band powers, e.g.
+/// the frames of the last few seconds
+pub fn frame_dynamics<'a, I>(frames: I, band_count: usize) -> Vec<f64>
+where
+    I: IntoIterator<Item = &'a [f64]>,
+{
+    let mut band_db_per_frame = vec![Vec::new(); band_count];
+    for frame in frames {
+        for (dbs, &power) in band_db_per_frame.iter_mut().zip(frame) {
+            if power > MIN_POWER {
+                dbs.push(10.0 * power.log10());
+            }
+        }
     }
+    band_dynamics(&band_db_per_frame)
+}
 
-    // Calculate dynamics (standard deviation of dB values)
-    // Filter out frames below threshold from band's max (inaudible in normal playback)
-    let dynamics: Vec<f64> = band_db_per_frame
+/// Standard deviation of each band's per-frame level in dB, leaving out frames more than
+/// `DYNAMICS_THRESHOLD_DB` below the band's loudest (inaudible in normal playback)
+fn band_dynamics(band_db_per_frame: &[Vec<f64>]) -> Vec<f64> {
+    band_db_per_frame
         .iter()
         .map(|dbs| {
             if dbs.is_empty() {
@@ -193,11 +1379,5 @@ where
             let variance = (sum_sq / n) - (mean * mean);
             variance.sqrt()
         })
-        .collect();
-
-    StatsResult {
-        raw_powers,
-        k_powers,
-        dynamics,
-    }
+        .collect()
 }
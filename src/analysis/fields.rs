@@ -0,0 +1,43 @@
+//! Serde field helpers for saved results
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Upper band edge, saved as null for the open top band (`f32::MAX`)
+pub(crate) mod open_edge {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(hz: &f32, serializer: S) -> Result<S::Ok, S::Error> {
+        (*hz < f32::MAX / 2.0).then_some(*hz).serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+        Ok(Option::<f32>::deserialize(deserializer)?.unwrap_or(f32::MAX))
+    }
+}
+
+/// Value that is NaN when unknown, saved as null
+pub mod nan_as_null {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        value.is_finite().then_some(*value).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        Ok(Option::<f64>::deserialize(deserializer)?.unwrap_or(f64::NAN))
+    }
+}
+
+/// Per-band values that are NaN when unknown, saved with nulls
+pub mod nan_vec_as_null {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(values: &[f64], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(values.iter().map(|v| v.is_finite().then_some(*v)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<f64>, D::Error> {
+        let values = Vec::<Option<f64>>::deserialize(deserializer)?;
+        Ok(values.into_iter().map(|v| v.unwrap_or(f64::NAN)).collect())
+    }
+}
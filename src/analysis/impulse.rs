@@ -0,0 +1,91 @@
+//! Per-band magnitude and group delay of an impulse response
+
+use rustfft::num_complex::Complex;
+
+use super::bands::band_bins;
+use super::config::AnalysisConfig;
+use super::fft::plan_fft_forward;
+
+/// Smallest FFT used for an impulse response, so short ones still get a few bins per
+/// band at the low end (0.73 Hz per bin at 48 kHz)
+const MIN_IR_FFT_SIZE: usize = 65536;
+
+/// Bands this far below the strongest one carry too little energy for a meaningful delay
+const GROUP_DELAY_FLOOR_DB: f64 = 80.0;
+
+/// Response of an impulse response within one band
+#[derive(Clone, Copy, Debug)]
+pub struct BandResponse {
+    /// Power-averaged gain in dB, 0 for unity (NaN when the band gets no energy)
+    pub gain_db: f64,
+    /// Power-weighted average group delay in milliseconds (NaN when the band is too quiet)
+    pub group_delay_ms: f64,
+}
+
+/// Gain and group delay of the impulse response in each band of `config`.
+///
+/// The group delay comes from the derivative of the phase without unwrapping it:
+/// with `H = FFT(h[n])` and `Y = FFT(n·h[n])`, the delay of each bin is `Re(Y / H)`.
+/// Averaging `Re(Y·conj(H))` over the band and dividing by the band's power weights
+/// each bin by its energy, so deep notches don't dominate the result.
+pub fn band_responses(ir: &[f32], sample_rate: u32, config: &AnalysisConfig) -> Vec<BandResponse> {
+    let len = ir.len().next_power_of_two().max(MIN_IR_FFT_SIZE);
+    let spectrum = |scale: &dyn Fn(usize) -> f64| {
+        let mut buffer: Vec<Complex<f64>> = ir
+            .iter()
+            .enumerate()
+            .map(|(n, &s)| Complex::new(s as f64 * scale(n), 0.0))
+            .collect();
+        buffer.resize(len, Complex::new(0.0, 0.0));
+        plan_fft_forward(len, config.deterministic).process(&mut buffer);
+        buffer
+    };
+    let h = spectrum(&|_| 1.0);
+    let y = spectrum(&|n| n as f64);
+
+    let num_bins = len / 2 + 1;
+    let freq_per_bin = sample_rate as f32 / len as f32;
+    let sums: Vec<(f64, f64, f64)> = band_bins(
+        config.bands(),
+        freq_per_bin,
+        num_bins,
+        config.crossover_octaves(),
+    )
+    .iter()
+    .map(|bins| {
+        let (mut weight, mut power, mut delay) = (0.0, 0.0, 0.0);
+        for (i, &w) in bins.weights.iter().enumerate() {
+            let bin = bins.start + i;
+            weight += w;
+            power += w * h[bin].norm_sqr();
+            delay += w * (y[bin] * h[bin].conj()).re;
+        }
+        (weight, power, delay)
+    })
+    .collect();
+
+    let strongest = sums
+        .iter()
+        .filter(|(weight, _, _)| *weight > 0.0)
+        .map(|(weight, power, _)| power / weight)
+        .fold(0.0f64, f64::max);
+    let floor = strongest * 10f64.powf(-GROUP_DELAY_FLOOR_DB / 10.0);
+
+    sums.iter()
+        .map(|&(weight, power, delay)| {
+            let mean_power = if weight > 0.0 { power / weight } else { 0.0 };
+            BandResponse {
+                gain_db: if mean_power > 0.0 {
+                    10.0 * mean_power.log10()
+                } else {
+                    f64::NAN
+                },
+                group_delay_ms: if mean_power > floor && mean_power > 0.0 {
+                    delay / power / sample_rate as f64 * 1000.0
+                } else {
+                    f64::NAN
+                },
+            }
+        })
+        .collect()
+}
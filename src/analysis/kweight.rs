@@ -2,13 +2,47 @@
 
 use std::f64::consts::PI;
 
-/// Sample rate tolerance for coefficient selection (Hz)
-const SAMPLE_RATE_TOLERANCE: f64 = 1.0;
+/// Analog prototype of the pre-filter (high shelf): gain in dB, Q, and center frequency in
+/// Hz, fitted to the BS.1770-4 Table 1 coefficients at 48 kHz (cf. pyloudnorm)
+const SHELF_GAIN_DB: f64 = 3.999843853973347;
+const SHELF_Q: f64 = 0.7071752369554196;
+const SHELF_HZ: f64 = 1681.974450955533;
+
+/// Analog prototype of the RLB high-pass: Q and corner frequency in Hz
+const HIGHPASS_Q: f64 = 0.5003270373238773;
+const HIGHPASS_HZ: f64 = 38.13547087602444;
+
+/// Biquad coefficients (b0, b1, b2, a1, a2), normalized to a0 = 1
+type Biquad = (f64, f64, f64, f64, f64);
+
+/// Pre-filter (shelving) and RLB (high-pass) stages of the K-weighting filter at any
+/// sample rate, by bilinear transform of the analog prototypes (reproducing Table 1 at 48 kHz)
+fn k_weight_stages(sample_rate: f64) -> [Biquad; 2] {
+    let k = (PI * SHELF_HZ / sample_rate).tan();
+    let vh = 10f64.powf(SHELF_GAIN_DB / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / SHELF_Q + k * k;
+    let shelf = (
+        (vh + vb * k / SHELF_Q + k * k) / a0,
+        2.0 * (k * k - vh) / a0,
+        (vh - vb * k / SHELF_Q + k * k) / a0,
+        2.0 * (k * k - 1.0) / a0,
+        (1.0 - k / SHELF_Q + k * k) / a0,
+    );
+
+    let k = (PI * HIGHPASS_HZ / sample_rate).tan();
+    let a0 = 1.0 + k / HIGHPASS_Q + k * k;
+    let highpass = (
+        1.0,
+        -2.0,
+        1.0,
+        2.0 * (k * k - 1.0) / a0,
+        (1.0 - k / HIGHPASS_Q + k * k) / a0,
+    );
+    [shelf, highpass]
+}
 
 /// K-weighting filter frequency response (ITU-R BS.1770-4)
-/// Coefficients:
-/// - 48kHz: ITU-R BS.1770-4 Table 1
-/// - 44.1kHz: derived via bilinear transform (cf. pyloudnorm, libebur128)
 fn k_weight(freq: f64, sample_rate: f64) -> f64 {
     if freq <= 0.0 {
         return 0.0;
@@ -20,64 +54,63 @@ fn k_weight(freq: f64, sample_rate: f64) -> f64 {
     let cos_2w = (2.0 * omega).cos();
     let sin_2w = (2.0 * omega).sin();
 
-    // Pre-filter (shelving) biquad coefficients from ITU-R BS.1770-4
-    let (b0_pre, b1_pre, b2_pre, a1_pre, a2_pre) =
-        if (sample_rate - 48000.0).abs() < SAMPLE_RATE_TOLERANCE {
-            (
-                1.53512485958697,
-                -2.69169618940638,
-                1.19839281085285,
-                -1.69065929318241,
-                0.73248077421585,
-            )
-        } else {
-            // 44100Hz coefficients
-            (
-                1.5308412300503478,
-                -2.6509799951547297,
-                1.1690790799215869,
-                -1.6636551132560204,
-                0.7125954280732254,
-            )
-        };
-
-    let pre_num_re = b0_pre + b1_pre * cos_w + b2_pre * cos_2w;
-    let pre_num_im = -b1_pre * sin_w - b2_pre * sin_2w;
-    let pre_den_re = 1.0 + a1_pre * cos_w + a2_pre * cos_2w;
-    let pre_den_im = -a1_pre * sin_w - a2_pre * sin_2w;
-    let pre_mag_sq = (pre_num_re * pre_num_re + pre_num_im * pre_num_im)
-        / (pre_den_re * pre_den_re + pre_den_im * pre_den_im);
-
-    // RLB (high-pass) biquad coefficients
-    let (b0_rlb, b1_rlb, b2_rlb, a1_rlb, a2_rlb) =
-        if (sample_rate - 48000.0).abs() < SAMPLE_RATE_TOLERANCE {
-            (1.0, -2.0, 1.0, -1.99004745483398, 0.99007225036621)
-        } else {
-            // 44100Hz coefficients
-            (
-                0.9994908682456236,
-                -1.9989817364912472,
-                0.9994908682456236,
-                -1.9989817364912472,
-                0.9989826099040272,
-            )
-        };
-
-    let rlb_num_re = b0_rlb + b1_rlb * cos_w + b2_rlb * cos_2w;
-    let rlb_num_im = -b1_rlb * sin_w - b2_rlb * sin_2w;
-    let rlb_den_re = 1.0 + a1_rlb * cos_w + a2_rlb * cos_2w;
-    let rlb_den_im = -a1_rlb * sin_w - a2_rlb * sin_2w;
-    let rlb_mag_sq = (rlb_num_re * rlb_num_re + rlb_num_im * rlb_num_im)
-        / (rlb_den_re * rlb_den_re + rlb_den_im * rlb_den_im);
-
-    (pre_mag_sq * rlb_mag_sq).sqrt()
+    let mag_sq = |(b0, b1, b2, a1, a2): Biquad| {
+        let num_re = b0 + b1 * cos_w + b2 * cos_2w;
+        let num_im = -b1 * sin_w - b2 * sin_2w;
+        let den_re = 1.0 + a1 * cos_w + a2 * cos_2w;
+        let den_im = -a1 * sin_w - a2 * sin_2w;
+        (num_re * num_re + num_im * num_im) / (den_re * den_re + den_im * den_im)
+    };
+    let [pre, rlb] = k_weight_stages(sample_rate);
+
+    (mag_sq(pre) * mag_sq(rlb)).sqrt()
+}
+
+/// The K-weighting biquad cascade run on the samples (`--kweight-mode filter`), keeping
+/// its state from one call to the next
+#[derive(Clone)]
+pub(super) struct KWeightFilter {
+    stages: [Biquad; 2],
+    /// Transposed direct form II state of each stage
+    state: [[f64; 2]; 2],
+}
+
+impl KWeightFilter {
+    pub(super) fn new(sample_rate: u32) -> Self {
+        Self {
+            stages: k_weight_stages(sample_rate as f64),
+            state: [[0.0; 2]; 2],
+        }
+    }
+
+    /// Append the filtered `samples` to `out`
+    pub(super) fn process_into(&mut self, samples: &[f32], out: &mut Vec<f32>) {
+        out.reserve(samples.len());
+        for &sample in samples {
+            let mut x = sample as f64;
+            for ((b0, b1, b2, a1, a2), state) in self.stages.iter().zip(&mut self.state) {
+                let y = b0 * x + state[0];
+                state[0] = b1 * x - a1 * y + state[1];
+                state[1] = b2 * x - a2 * y;
+                x = y;
+            }
+            out.push(x as f32);
+        }
+    }
+
+    /// Filtered copy of `samples`, starting from silence
+    pub(super) fn filtered(sample_rate: u32, samples: &[f32]) -> Vec<f32> {
+        let mut out = Vec::new();
+        Self::new(sample_rate).process_into(samples, &mut out);
+        out
+    }
 }
 
 /// Create a lookup table of K-weighting factors for FFT bins
 pub(crate) fn create_k_weight_table(fft_size: usize, sample_rate: u32) -> Vec<f64> {
     let freq_per_bin = sample_rate as f64 / fft_size as f64;
     let sr = sample_rate as f64;
-    (0..fft_size / 2)
+    (0..=fft_size / 2)
         .map(|bin| {
             let freq = bin as f64 * freq_per_bin;
             let weight = k_weight(freq, sr);
@@ -86,7 +119,17 @@ pub(crate) fn create_k_weight_table(fft_size: usize, sample_rate: u32) -> Vec<f6
         .collect()
 }
 
+/// K-weighting gain in dB at the given frequency
+pub fn k_weight_gain_db(freq: f64, sample_rate: u32) -> f64 {
+    20.0 * k_weight(freq, sample_rate as f64).log10()
+}
+
 #[cfg(test)]
 pub(super) fn k_weight_for_test(freq: f64, sample_rate: f64) -> f64 {
     k_weight(freq, sample_rate)
 }
+
+#[cfg(test)]
+pub(super) fn k_weight_stages_for_test(sample_rate: f64) -> [Biquad; 2] {
+    k_weight_stages(sample_rate)
+}
@@ -0,0 +1,233 @@
+//! Short-term loudness (BS.1770-style, computed from the K-weighted spectrum), and
+//! momentary/short-term loudness metering of the K-filtered samples
+
+use std::collections::VecDeque;
+
+use super::bands::{Band, GROUP_LABELS, band_group};
+use super::fft::{
+    AnalysisContext, FFT_SIZE, FrameWeights, HOP_SIZE, create_hanning_window,
+    for_each_weighted_frame,
+};
+use super::kweight::KWeightFilter;
+
+/// Short-term loudness window in seconds (EBU R128)
+const SHORT_TERM_SECS: f64 = 3.0;
+
+/// Blocks per second of `LoudnessMeter`, which updates every 100 ms
+const METER_BLOCKS_PER_SEC: u32 = 10;
+
+/// Momentary (400 ms) and short-term (3 s) windows in meter blocks (EBU R128)
+const MOMENTARY_BLOCKS: usize = 4;
+const SHORT_TERM_BLOCKS: usize = 30;
+
+/// Spacing between short-term loudness points in seconds
+const LOUDNESS_STEP_SECS: f64 = 1.0;
+
+/// Silent windows are reported at this level (the BS.1770 absolute gate)
+pub const LOUDNESS_FLOOR_LUFS: f64 = -70.0;
+
+/// Short-term loudness over time, overall and per band group
+pub struct LoudnessTimeline {
+    /// End of each 3 s window in seconds
+    pub times: Vec<f64>,
+    pub short_term: Vec<f64>,
+    /// Loudness per group: groups[group_idx][point_idx]
+    pub groups: Vec<Vec<f64>>,
+}
+
+/// Relative gate of integrated loudness, below the level of the absolute-gated frames
+const RELATIVE_GATE_LU: f64 = -10.0;
+
+/// Factor turning a frame's summed one-sided spectrum power into its mean square (Parseval)
+pub(super) fn mean_square_scale() -> f64 {
+    let window_energy: f64 = create_hanning_window(FFT_SIZE)
+        .iter()
+        .map(|&w| (w as f64).powi(2))
+        .sum();
+    2.0 / (FFT_SIZE as f64 * window_energy)
+}
+
+/// Which frames pass the BS.1770 absolute (-70 LUFS) and relative (-10 LU) gates,
+/// given each frame's K-weighted mean square
+pub(super) fn gate_frames(mean_squares: &[f64]) -> Vec<bool> {
+    let above_floor = |ms: f64| ms > 0.0 && lufs(ms) > LOUDNESS_FLOOR_LUFS;
+    let (sum, count) = mean_squares
+        .iter()
+        .filter(|&&ms| above_floor(ms))
+        .fold((0.0, 0usize), |(s, c), &ms| (s + ms, c + 1));
+    if count == 0 {
+        return vec![false; mean_squares.len()];
+    }
+    let relative_gate = lufs(sum / count as f64) + RELATIVE_GATE_LU;
+    mean_squares
+        .iter()
+        .map(|&ms| above_floor(ms) && lufs(ms) > relative_gate)
+        .collect()
+}
+
+/// Integrated loudness in LUFS: the mean of the frames that pass `gate_frames`
+pub(super) fn integrated_loudness(mean_squares: &[f64], passed: &[bool]) -> f64 {
+    let (sum, count) = mean_squares
+        .iter()
+        .zip(passed)
+        .filter(|(_, p)| **p)
+        .fold((0.0, 0usize), |(s, c), (&ms, _)| (s + ms, c + 1));
+    if count == 0 {
+        LOUDNESS_FLOOR_LUFS
+    } else {
+        lufs(sum / count as f64)
+    }
+}
+
+/// Loudness in LUFS of a mean-square K-weighted power
+fn lufs(mean_square: f64) -> f64 {
+    if mean_square > 0.0 {
+        (-0.691 + 10.0 * mean_square.log10()).max(LOUDNESS_FLOOR_LUFS)
+    } else {
+        LOUDNESS_FLOOR_LUFS
+    }
+}
+
+/// Short-term loudness every second, plus the loudness of each LOW/MID/HIGH band group
+/// (split at `group_edges`)
+pub fn short_term_loudness(
+    samples: &[f32],
+    ctx: &mut AnalysisContext,
+    bands: &[Band],
+    group_edges: [f32; 2],
+) -> LoudnessTimeline {
+    let group_of: Vec<usize> = bands.iter().map(|b| band_group(b, group_edges)).collect();
+
+    let scale = mean_square_scale();
+
+    // Running sums of per-frame group powers, so each window is a difference of two entries
+    let mut cumulative: Vec<[f64; GROUP_LABELS.len()]> = vec![[0.0; GROUP_LABELS.len()]];
+    // Frames below --gate stay in: quiet stretches should read as quiet on the curve
+    // K-weighted whatever --weighting the band columns use
+    for_each_weighted_frame(
+        samples,
+        ctx,
+        bands,
+        FrameWeights::Loudness,
+        |band_powers, _| {
+            let mut groups = *cumulative.last().unwrap();
+            for (power, &group) in band_powers.iter().zip(&group_of) {
+                groups[group] += power * scale;
+            }
+            cumulative.push(groups);
+        },
+    );
+    let num_frames = cumulative.len() - 1;
+
+    let rate = ctx.sample_rate() as f64;
+    // Index of the first frame whose centre lies at or after `t` seconds
+    let first_frame_from = |t: f64| {
+        let i = ((t * rate - (FFT_SIZE / 2) as f64) / HOP_SIZE as f64).ceil();
+        (i.max(0.0) as usize).min(num_frames)
+    };
+    let duration = samples.len() as f64 / rate;
+
+    let mut timeline = LoudnessTimeline {
+        times: Vec::new(),
+        short_term: Vec::new(),
+        groups: vec![Vec::new(); GROUP_LABELS.len()],
+    };
+
+    // Files shorter than one window get a single point over the whole file
+    let mut end = SHORT_TERM_SECS.min(duration);
+    while end <= duration {
+        let start = first_frame_from(end - SHORT_TERM_SECS);
+        let stop = first_frame_from(end);
+
+        if stop > start {
+            let n = (stop - start) as f64;
+            let group_means: Vec<f64> = (0..GROUP_LABELS.len())
+                .map(|g| (cumulative[stop][g] - cumulative[start][g]) / n)
+                .collect();
+            timeline.times.push(end);
+            timeline.short_term.push(lufs(group_means.iter().sum()));
+            for (series, mean) in timeline.groups.iter_mut().zip(&group_means) {
+                series.push(lufs(*mean));
+            }
+        }
+        end += LOUDNESS_STEP_SECS;
+    }
+
+    timeline
+}
+
+/// Momentary (400 ms) and short-term (3 s) loudness of audio fed a piece at a time, e.g.
+/// one timeline interval after another. The samples go through the BS.1770 K-weighting
+/// filter, and both windows slide in 100 ms steps, over the blocks so far at the start.
+pub struct LoudnessMeter {
+    filter: KWeightFilter,
+    filtered: Vec<f32>,
+    block_len: usize,
+    /// Sum of squares and length of the block being filled
+    block_sum: f64,
+    block_fill: usize,
+    /// Mean squares of the last SHORT_TERM_BLOCKS blocks, oldest first
+    blocks: VecDeque<f64>,
+    /// Loudest windows (as mean squares) since the last `take_max`
+    max_momentary: Option<f64>,
+    max_short_term: Option<f64>,
+}
+
+impl LoudnessMeter {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            filter: KWeightFilter::new(sample_rate),
+            filtered: Vec::new(),
+            block_len: (sample_rate / METER_BLOCKS_PER_SEC).max(1) as usize,
+            block_sum: 0.0,
+            block_fill: 0,
+            blocks: VecDeque::with_capacity(SHORT_TERM_BLOCKS + 1),
+            max_momentary: None,
+            max_short_term: None,
+        }
+    }
+
+    /// Meter the next samples
+    pub fn push(&mut self, samples: &[f32]) {
+        let mut filtered = std::mem::take(&mut self.filtered);
+        filtered.clear();
+        self.filter.process_into(samples, &mut filtered);
+        for &sample in &filtered {
+            self.block_sum += (sample as f64).powi(2);
+            self.block_fill += 1;
+            if self.block_fill == self.block_len {
+                self.finish_block();
+            }
+        }
+        self.filtered = filtered;
+    }
+
+    fn finish_block(&mut self) {
+        self.blocks
+            .push_back(self.block_sum / self.block_len as f64);
+        if self.blocks.len() > SHORT_TERM_BLOCKS {
+            self.blocks.pop_front();
+        }
+        self.block_sum = 0.0;
+        self.block_fill = 0;
+
+        let window = |blocks: usize| {
+            let recent = self.blocks.iter().rev().take(blocks);
+            recent.sum::<f64>() / self.blocks.len().min(blocks) as f64
+        };
+        let (momentary, short_term) = (window(MOMENTARY_BLOCKS), window(SHORT_TERM_BLOCKS));
+        self.max_momentary = Some(self.max_momentary.map_or(momentary, |m| m.max(momentary)));
+        self.max_short_term = Some(
+            self.max_short_term
+                .map_or(short_term, |s| s.max(short_term)),
+        );
+    }
+
+    /// Loudest momentary and short-term loudness in LUFS since the last call, or `None`
+    /// if no 100 ms step ended since
+    pub fn take_max(&mut self) -> Option<(f64, f64)> {
+        let momentary = self.max_momentary.take()?;
+        let short_term = self.max_short_term.take()?;
+        Some((lufs(momentary), lufs(short_term)))
+    }
+}
@@ -0,0 +1,66 @@
+//! Energy split metrics (`--metric below:100`): the share of the Raw energy below or
+//! above a frequency, in the terms delivery specs use
+
+use std::ops::Range;
+
+/// Which side of the frequency a metric measures
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MetricSide {
+    Below,
+    Above,
+}
+
+/// Share of the energy below or above a frequency
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EnergyMetric {
+    pub side: MetricSide,
+    pub hz: f32,
+}
+
+impl EnergyMetric {
+    /// The metric as given on the command line, e.g. `below:100`
+    pub fn spec(&self) -> String {
+        let side = match self.side {
+            MetricSide::Below => "below",
+            MetricSide::Above => "above",
+        };
+        format!("{}:{}", side, self.hz)
+    }
+
+    /// Spectrum bins the metric sums: those centered below the frequency, or the rest
+    pub(super) fn bins(&self, freq_per_bin: f32, spectrum_bins: usize) -> Range<usize> {
+        let edge = ((self.hz / freq_per_bin).ceil() as usize).min(spectrum_bins);
+        match self.side {
+            MetricSide::Below => 0..edge,
+            MetricSide::Above => edge..spectrum_bins,
+        }
+    }
+}
+
+/// Parse a `--metric` such as `below:100` or `above:10000` (Hz)
+pub fn parse_metric(spec: &str) -> Result<EnergyMetric, String> {
+    let invalid = || format!("invalid metric '{}' (expected below:HZ or above:HZ)", spec);
+    let (side, hz) = spec.trim().split_once(':').ok_or_else(invalid)?;
+    let side = match side.trim().to_ascii_lowercase().as_str() {
+        "below" => MetricSide::Below,
+        "above" => MetricSide::Above,
+        _ => return Err(invalid()),
+    };
+    let hz: f32 = hz.trim().parse().map_err(|_| invalid())?;
+    if !(hz.is_finite() && hz > 0.0) {
+        return Err(format!("metric frequency must be above 0 Hz in '{}'", spec));
+    }
+    Ok(EnergyMetric { side, hz })
+}
+
+/// Bin ranges of `metrics` at the given resolution
+pub(super) fn metric_bins(
+    metrics: &[EnergyMetric],
+    freq_per_bin: f32,
+    spectrum_bins: usize,
+) -> Vec<Range<usize>> {
+    metrics
+        .iter()
+        .map(|m| m.bins(freq_per_bin, spectrum_bins))
+        .collect()
+}
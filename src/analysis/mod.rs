@@ -1,15 +1,46 @@
 //! Audio frequency band analysis
 
+mod activity;
+mod align;
 mod bands;
+mod config;
+mod distribution;
+mod equal_loudness;
 mod fft;
+mod fields;
+mod impulse;
 mod kweight;
+mod loudness;
+mod metrics;
+mod target;
+mod verify;
+mod weighting;
 
-pub(crate) use bands::{Band, get_bands};
-pub(crate) use fft::{
-    DYNAMICS_DISPLAY_THRESHOLD_PCT, FFT_SIZE, analyze_interval, analyze_stats,
-    create_hanning_window, powers_to_percentages,
+pub use activity::active_samples;
+pub use align::{Alignment, MIN_CONFIDENCE, common_spans, find_alignment};
+pub use bands::{
+    Band, BandPreset, DEFAULT_GROUP_EDGES_HZ, GROUP_LABELS, band_group, band_index, default_bands,
+    group_totals, log_bands, parse_band_preset,
 };
-pub(crate) use kweight::create_k_weight_table;
+pub use config::AnalysisConfig;
+pub use distribution::{share_distance, typical_share};
+pub use equal_loudness::difference_score;
+pub use fft::{
+    AnalysisContext, DYNAMICS_DISPLAY_THRESHOLD_PCT, FFT_SIZE, HOP_SIZE, KWeightMode,
+    MIN_STABLE_FRAMES, Precision, StatsAccumulator, StatsResult, analyze_interval, analyze_stats,
+    average_spectrum, create_hanning_window, for_each_frame, frame_dynamics, plan_fft_forward,
+    powers_to_percentages,
+};
+pub use fields::{nan_as_null, nan_vec_as_null};
+pub use impulse::band_responses;
+pub use kweight::k_weight_gain_db;
+pub use loudness::{LOUDNESS_FLOOR_LUFS, LoudnessMeter, short_term_loudness};
+pub use metrics::{EnergyMetric, MetricSide, parse_metric};
+pub use target::{
+    TargetCurve, covered_shares, mean_abs_deviation, parse_target_curve, target_deviation_db,
+};
+pub use verify::{COVERAGE_TOLERANCE, PARSEVAL_TOLERANCE, VerifySummary};
+pub use weighting::{Weighting, parse_weighting};
 
 #[cfg(test)]
 mod tests;
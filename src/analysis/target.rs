@@ -0,0 +1,151 @@
+//! Target curves (`--target-curve curve.csv`): a frequency response exported from another
+//! analyzer or measurement, resampled onto the band layout as the band shares it implies
+
+use super::bands::band_bins;
+use super::config::AnalysisConfig;
+use super::fft::{FFT_SIZE, SPECTRUM_BINS};
+use crate::audio::TARGET_SAMPLE_RATE;
+
+/// Frequency/level pairs read as an FFT spectrum: power per Hz, so pink noise falls 3 dB
+/// per octave
+#[derive(Clone, Debug, PartialEq)]
+pub struct TargetCurve {
+    /// Name shown in table headings (the file name given on the command line)
+    pub name: String,
+    /// (Hz, dB) points in increasing frequency order
+    points: Vec<(f64, f64)>,
+}
+
+impl TargetCurve {
+    /// Level in dB at a frequency, interpolated linearly over log frequency and held at the
+    /// end levels outside the curve
+    pub(super) fn level_db(&self, hz: f64) -> f64 {
+        let (first, last) = (self.points[0], self.points[self.points.len() - 1]);
+        if hz <= first.0 {
+            return first.1;
+        }
+        if hz >= last.0 {
+            return last.1;
+        }
+        let upper = self.points.partition_point(|&(f, _)| f <= hz);
+        let (low, high) = (self.points[upper - 1], self.points[upper]);
+        let t = (hz / low.0).log2() / (high.0 / low.0).log2();
+        low.1 + (high.1 - low.1) * t
+    }
+
+    /// Share in % of the power of each band of `config` in the curve, over the bands the
+    /// curve reaches (NaN for bands entirely outside it and for excluded bands). Bins are
+    /// weighted like the analysis, so the shares line up with a file's Raw(%).
+    pub fn band_shares(&self, config: &AnalysisConfig) -> Vec<f64> {
+        let (low, high) = (self.points[0].0, self.points[self.points.len() - 1].0);
+        let freq_per_bin = TARGET_SAMPLE_RATE as f64 / FFT_SIZE as f64;
+        let powers: Vec<f64> = band_bins(
+            config.bands(),
+            freq_per_bin as f32,
+            SPECTRUM_BINS,
+            config.crossover_octaves(),
+        )
+        .iter()
+        .enumerate()
+        .map(|(i, bins)| {
+            let freqs =
+                (bins.start..bins.start + bins.weights.len()).map(|bin| bin as f64 * freq_per_bin);
+            if config.excluded_bands().contains(&i)
+                || !freqs.clone().any(|f| (low..=high).contains(&f))
+            {
+                return f64::NAN;
+            }
+            freqs
+                .zip(&bins.weights)
+                .map(|(f, w)| w * 10f64.powf(self.level_db(f) / 10.0))
+                .sum()
+        })
+        .collect();
+        covered_shares(&powers, &powers)
+    }
+}
+
+/// Parse a target curve or `--weighting-file`: one frequency (Hz) and level (dB) per line,
+/// separated by commas,
+/// semicolons, tabs, or spaces. Lines that don't start with a number (headers, comments)
+/// are skipped, and columns after the level (e.g. phase) are ignored.
+pub fn parse_target_curve(name: &str, text: &str) -> Result<TargetCurve, String> {
+    let mut points: Vec<(f64, f64)> = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let mut fields = line
+            .split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+            .filter(|field| !field.is_empty());
+        let Some(Ok(hz)) = fields.next().map(str::parse::<f64>) else {
+            continue;
+        };
+        let db = fields
+            .next()
+            .and_then(|field| field.parse::<f64>().ok())
+            .filter(|db| db.is_finite())
+            .ok_or_else(|| {
+                format!(
+                    "line {}: expected a frequency and a level in dB",
+                    number + 1
+                )
+            })?;
+        if !(hz.is_finite() && hz > 0.0) {
+            return Err(format!("line {}: frequency must be above 0 Hz", number + 1));
+        }
+        if points.last().is_some_and(|&(last, _)| hz <= last) {
+            return Err(format!("line {}: frequencies must increase", number + 1));
+        }
+        points.push((hz, db));
+    }
+    if points.len() < 2 {
+        return Err("a curve needs at least two frequency/dB points".to_string());
+    }
+    Ok(TargetCurve {
+        name: name.to_string(),
+        points,
+    })
+}
+
+/// Shares in % renormalized over the bands where `target` is finite (NaN elsewhere), so a
+/// file's Raw(%) and a curve that covers only part of the spectrum compare like for like
+pub fn covered_shares(shares: &[f64], target: &[f64]) -> Vec<f64> {
+    let total: f64 = shares
+        .iter()
+        .zip(target)
+        .filter(|(_, t)| t.is_finite())
+        .map(|(s, _)| s)
+        .sum();
+    shares
+        .iter()
+        .zip(target)
+        .map(|(s, t)| {
+            if t.is_finite() {
+                s / total * 100.0
+            } else {
+                f64::NAN
+            }
+        })
+        .collect()
+}
+
+/// Deviation in dB of each band's covered share from the target share (NaN where either
+/// is missing or zero)
+pub fn target_deviation_db(shares: &[f64], target: &[f64]) -> Vec<f64> {
+    covered_shares(shares, target)
+        .iter()
+        .zip(target)
+        .map(|(s, t)| {
+            let db = 10.0 * (s / t).log10();
+            if db.is_finite() { db } else { f64::NAN }
+        })
+        .collect()
+}
+
+/// Mean absolute deviation in dB over the bands that have one (NaN when none do)
+pub fn mean_abs_deviation(deviation_db: &[f64]) -> f64 {
+    let finite: Vec<f64> = deviation_db
+        .iter()
+        .filter(|d| d.is_finite())
+        .map(|d| d.abs())
+        .collect();
+    finite.iter().sum::<f64>() / finite.len() as f64
+}
@@ -1,7 +1,54 @@
 //! Unit tests for analysis module
 
-use super::fft::{create_hanning_window, powers_to_percentages};
-use super::kweight::k_weight_for_test;
+use super::activity::{active_regions, active_samples};
+use super::align::{common_spans, find_alignment};
+use super::bands::{
+    Band, BandPreset, DEFAULT_GROUP_EDGES_HZ, band_bins, check_layout, default_bands, group_totals,
+    log_bands, parse_band_labels, parse_band_preset, parse_excluded_bands, parse_group_edges,
+    parse_ignored_bands, parse_range,
+};
+use super::config::AnalysisConfig;
+use std::sync::Arc;
+
+use realfft::{RealFftPlanner, RealToComplex};
+use rustfft::FftPlanner;
+use rustfft::num_complex::Complex;
+
+use super::distribution::{share_distance, share_quantiles, typical_share};
+use super::equal_loudness::{band_importance, difference_score};
+use super::fft::{
+    AnalysisContext, FFT_SIZE, HOP_SIZE, KWeightMode, MIN_STABLE_FRAMES, PARALLEL_FRAMES,
+    Precision, SPECTRUM_BINS, ScalarRealToComplex, SpectrumAnalyzer, StatsAccumulator, StatsResult,
+    analyze_interval, analyze_stats, create_hanning_window, frame_dynamics, parse_noise_gate,
+    powers_to_percentages,
+};
+use super::impulse::band_responses;
+use super::kweight::{KWeightFilter, k_weight_for_test, k_weight_stages_for_test};
+use super::loudness::{LOUDNESS_FLOOR_LUFS, LoudnessMeter, gate_frames, short_term_loudness};
+use super::metrics::{MetricSide, parse_metric};
+use super::target::{covered_shares, mean_abs_deviation, parse_target_curve, target_deviation_db};
+use super::weighting::{Weighting, parse_weighting, weighting_gain_db_for_test};
+
+/// Context at 48 kHz with the given FFT settings and the default ones otherwise
+fn fft_context(
+    multi_resolution: bool,
+    zero_pad: usize,
+    kweight_mode: KWeightMode,
+    weighting: Weighting,
+) -> AnalysisContext {
+    let mut config = AnalysisConfig::default().with_zero_pad(zero_pad).unwrap();
+    config.multi_resolution = multi_resolution;
+    config.kweight_mode = kweight_mode;
+    config.weighting = weighting;
+    AnalysisContext::new(48000, &config)
+}
+
+/// Context at 48 kHz with the given precision and the default settings otherwise
+fn precision_context(precision: Precision) -> AnalysisContext {
+    let mut config = AnalysisConfig::default();
+    config.precision = precision;
+    AnalysisContext::new(48000, &config)
+}
 
 /// Calculate standard deviation of a slice (for testing)
 fn std_dev(values: &[f64]) -> f64 {
@@ -88,10 +135,279 @@ fn test_k_weight_low_freq_attenuation() {
     );
 }
 
+#[test]
+fn test_k_weight_coefficients_for_any_sample_rate() {
+    // ITU-R BS.1770-4 Table 1 (48 kHz), and the 44.1 kHz coefficients of pyloudnorm
+    let tables = [
+        (
+            48000.0,
+            [
+                [
+                    1.53512485958697,
+                    -2.69169618940638,
+                    1.19839281085285,
+                    -1.69065929318241,
+                    0.73248077421585,
+                ],
+                [1.0, -2.0, 1.0, -1.99004745483398, 0.99007225036621],
+            ],
+        ),
+        (
+            44100.0,
+            [
+                [
+                    1.5308412300503478,
+                    -2.6509799951547297,
+                    1.1690790799215869,
+                    -1.6636551132560204,
+                    0.7125954280732254,
+                ],
+                [1.0, -2.0, 1.0, -1.9891696736297957, 0.9891990357870394],
+            ],
+        ),
+    ];
+    for (sample_rate, stages) in tables {
+        for (stage, expected) in k_weight_stages_for_test(sample_rate).iter().zip(stages) {
+            let (b0, b1, b2, a1, a2) = *stage;
+            for (got, want) in [b0, b1, b2, a1, a2].iter().zip(expected) {
+                assert!(
+                    (got - want).abs() < 1e-6,
+                    "{}: {} vs {}",
+                    sample_rate,
+                    got,
+                    want
+                );
+            }
+        }
+    }
+
+    // High sample rates weight the audible range the same way
+    for freq in [50.0, 100.0, 1000.0, 4000.0, 10000.0] {
+        let base = 20.0 * k_weight_for_test(freq, 48000.0).log10();
+        for sample_rate in [88200.0, 96000.0, 192000.0] {
+            let db = 20.0 * k_weight_for_test(freq, sample_rate).log10();
+            assert!(
+                (db - base).abs() < 0.1,
+                "{} Hz at {}: {} vs {}",
+                freq,
+                sample_rate,
+                db,
+                base
+            );
+        }
+    }
+}
+
+#[test]
+fn test_k_weight_filter_matches_response() {
+    for sample_rate in [48000u32, 44100] {
+        for freq in [30.0f32, 100.0, 1000.0, 4000.0, 15000.0] {
+            let sine: Vec<f32> = (0..sample_rate as usize)
+                .map(|i| (2.0 * std::f32::consts::PI * freq * i as f32 / sample_rate as f32).sin())
+                .collect();
+            let filtered = KWeightFilter::filtered(sample_rate, &sine);
+            // Skip the filter's settling time
+            let rms = |samples: &[f32]| {
+                (samples.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / samples.len() as f64)
+                    .sqrt()
+            };
+            let half = sine.len() / 2;
+            let gain = rms(&filtered[half..]) / rms(&sine[half..]);
+            let expected = k_weight_for_test(freq as f64, sample_rate as f64);
+            assert!(
+                (20.0 * (gain / expected).log10()).abs() < 0.05,
+                "{} Hz at {}: {} vs {}",
+                freq,
+                sample_rate,
+                gain,
+                expected
+            );
+        }
+    }
+}
+
+#[test]
+fn test_kweight_modes_agree() {
+    let bands = default_bands();
+    let samples: Vec<f32> = (0..48000 * 3)
+        .map(|i| {
+            let t = i as f32 / 48000.0;
+            0.3 * (2.0 * std::f32::consts::PI * 80.0 * t).sin()
+                + 0.2 * (2.0 * std::f32::consts::PI * 3000.0 * t).sin()
+        })
+        .collect();
+    let k_shares = |mode: KWeightMode, chunk: usize| {
+        let mut ctx = fft_context(false, 1, mode, Weighting::K);
+        let mut stats = StatsAccumulator::new(&bands, &mut ctx, None);
+        samples.chunks(chunk).for_each(|c| stats.push(c));
+        powers_to_percentages(&stats.finish().k_powers, &[])
+    };
+
+    let spectral = k_shares(KWeightMode::Spectral, samples.len());
+    let filter = k_shares(KWeightMode::Filter, samples.len());
+    for (band, (s, f)) in bands.iter().zip(spectral.iter().zip(&filter)) {
+        assert!((s - f).abs() < 0.5, "{}: {} vs {}", band.label, s, f);
+    }
+    // The filter keeps its state from one pushed piece to the next
+    let pieces = k_shares(KWeightMode::Filter, 1000);
+    for (a, b) in filter.iter().zip(&pieces) {
+        assert!((a - b).abs() < 1e-6, "{} vs {}", a, b);
+    }
+
+    // Per-interval analysis weights the same way
+    let mut ctx = fft_context(false, 1, KWeightMode::Filter, Weighting::K);
+    let interval = powers_to_percentages(&analyze_interval(&samples, &mut ctx, &bands, true), &[]);
+    for (a, b) in filter.iter().zip(&interval) {
+        assert!((a - b).abs() < 0.5, "{} vs {}", a, b);
+    }
+}
+
+#[test]
+fn test_c_weight_response() {
+    // IEC 61672-1 Table 3, at the exact frequencies of the nominal 20, 31.5, 63 Hz, ...
+    for (freq, expected_db) in [
+        (19.953, -6.2),
+        (31.623, -3.0),
+        (63.096, -0.8),
+        (1000.0, 0.0),
+        (3981.1, -0.8),
+        (7943.3, -3.0),
+        (15849.0, -8.5),
+    ] {
+        let db = weighting_gain_db_for_test(&Weighting::C, freq);
+        assert!(
+            (db - expected_db).abs() < 0.1,
+            "{} Hz: {:.2} dB, expected {} dB",
+            freq,
+            db,
+            expected_db
+        );
+    }
+}
+
+#[test]
+fn test_equal_loudness_weighting() {
+    // ISO 226:2003 Table A.1 at 40 phon
+    let db = |phon: u8, freq: f64| weighting_gain_db_for_test(&Weighting::Iso226(phon), freq);
+    for (freq, spl) in [
+        (20.0, 99.85),
+        (100.0, 64.37),
+        (4000.0, 36.65),
+        (12500.0, 51.49),
+    ] {
+        let expected = 40.01 - spl;
+        assert!(
+            (db(40, freq) - expected).abs() < 0.02,
+            "{} Hz: {:.2} dB, expected {:.2} dB",
+            freq,
+            db(40, freq),
+            expected
+        );
+    }
+    // The ear flattens out as the level rises, most of all in the bass
+    assert!(db(80, 50.0) > db(40, 50.0) + 10.0);
+    assert!(db(90, 50.0) > db(80, 50.0));
+    // At 75 phon the bass is still cut far more than K-weighting cuts it
+    let k = |freq| weighting_gain_db_for_test(&Weighting::K, freq);
+    assert!(db(75, 50.0) < k(50.0) - 10.0);
+    assert!(db(75, 1000.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_parse_weighting() {
+    assert_eq!(parse_weighting("k"), Ok(Weighting::K));
+    assert_eq!(parse_weighting("C"), Ok(Weighting::C));
+    assert_eq!(parse_weighting("z"), Ok(Weighting::Z));
+    assert_eq!(parse_weighting("iso226:75"), Ok(Weighting::Iso226(75)));
+    assert_eq!(parse_weighting("ISO226:20"), Ok(Weighting::Iso226(20)));
+    for invalid in [
+        "a",
+        "iso226",
+        "iso226:",
+        "iso226:10",
+        "iso226:95",
+        "iso226:75.5",
+    ] {
+        assert!(parse_weighting(invalid).is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn test_curve_weighting_scales_bins_by_level() {
+    let bands = default_bands();
+    let samples: Vec<f32> = (0..48000 * 2)
+        .map(|i| {
+            let t = i as f32 / 48000.0;
+            0.3 * (2.0 * std::f32::consts::PI * 100.0 * t).sin()
+                + 0.3 * (2.0 * std::f32::consts::PI * 2000.0 * t).sin()
+        })
+        .collect();
+    let curve =
+        parse_target_curve("bass_cut.csv", "Hz,dB\n50,-10\n200,-10\n500,0\n20000,0\n").unwrap();
+    let k_powers = |weighting: Weighting| {
+        let mut ctx = fft_context(false, 1, KWeightMode::Spectral, weighting);
+        let mut stats = StatsAccumulator::new(&bands, &mut ctx, None);
+        stats.push(&samples);
+        stats.finish().k_powers
+    };
+    let band_of = |freq: f32| {
+        bands
+            .iter()
+            .position(|b| b.low_hz <= freq && freq < b.high_hz)
+            .unwrap()
+    };
+    let (low, high) = (band_of(100.0), band_of(2000.0));
+
+    let flat = k_powers(Weighting::Z);
+    let weighted = k_powers(Weighting::Curve(Arc::new(curve)));
+    let ratio = (weighted[low] / weighted[high]) / (flat[low] / flat[high]);
+    assert!((ratio - 0.1).abs() < 0.005, "ratio {}", ratio);
+}
+
+#[test]
+fn test_weighting_leaves_loudness_alone() {
+    let bands = default_bands();
+    let samples: Vec<f32> = (0..48000 * 3)
+        .map(|i| {
+            let t = i as f32 / 48000.0;
+            0.3 * (2.0 * std::f32::consts::PI * 40.0 * t).sin()
+                + 0.1 * (2.0 * std::f32::consts::PI * 10000.0 * t).sin()
+        })
+        .collect();
+    let stats = |weighting: Weighting| {
+        let mut ctx = fft_context(true, 1, KWeightMode::Spectral, weighting);
+        let mut stats = StatsAccumulator::new(&bands, &mut ctx, None);
+        stats.push(&samples);
+        stats.finish()
+    };
+
+    let k = stats(Weighting::K);
+    let c = stats(Weighting::C);
+    let z = stats(Weighting::Z);
+    assert_eq!(k.integrated_lufs, c.integrated_lufs);
+    assert_eq!(k.integrated_lufs, z.integrated_lufs);
+
+    // Z-weighting is flat; C-weighting cuts 40 Hz less and 10 kHz more than K-weighting
+    let shares = |s: &StatsResult| powers_to_percentages(&s.k_powers, &[]);
+    let band_of = |freq: f32| {
+        bands
+            .iter()
+            .position(|b| b.low_hz <= freq && freq < b.high_hz)
+            .unwrap()
+    };
+    let raw = powers_to_percentages(&z.raw_powers, &[]);
+    for (a, b) in shares(&z).iter().zip(&raw) {
+        assert!((a - b).abs() < 1e-6, "{} vs {}", a, b);
+    }
+    let (low, high) = (band_of(40.0), band_of(10000.0));
+    assert!(shares(&c)[low] > shares(&k)[low]);
+    assert!(shares(&c)[high] < shares(&k)[high]);
+}
+
 #[test]
 fn test_powers_to_percentages_sum_to_100() {
     let powers = vec![10.0, 20.0, 30.0, 40.0];
-    let pct = powers_to_percentages(&powers);
+    let pct = powers_to_percentages(&powers, &[]);
     let sum: f64 = pct.iter().sum();
     assert!(
         (sum - 100.0).abs() < 1e-10,
@@ -103,7 +419,7 @@ fn test_powers_to_percentages_sum_to_100() {
 #[test]
 fn test_powers_to_percentages_proportions() {
     let powers = vec![25.0, 25.0, 50.0];
-    let pct = powers_to_percentages(&powers);
+    let pct = powers_to_percentages(&powers, &[]);
     assert!((pct[0] - 25.0).abs() < 1e-10);
     assert!((pct[1] - 25.0).abs() < 1e-10);
     assert!((pct[2] - 50.0).abs() < 1e-10);
@@ -112,10 +428,20 @@ fn test_powers_to_percentages_proportions() {
 #[test]
 fn test_powers_to_percentages_zero_total() {
     let powers = vec![0.0, 0.0, 0.0];
-    let pct = powers_to_percentages(&powers);
+    let pct = powers_to_percentages(&powers, &[]);
     assert!(pct.iter().all(|&p| p == 0.0));
 }
 
+#[test]
+fn test_powers_to_percentages() {
+    // The excluded rumble doesn't count toward the total
+    let pct = powers_to_percentages(&[500.0, 25.0, 75.0], &[0]);
+    assert!(pct[0].is_nan());
+    assert_eq!(&pct[1..], &[25.0, 75.0]);
+    let silent = powers_to_percentages(&[500.0, 0.0, 0.0], &[0]);
+    assert_eq!(&silent[1..], &[0.0, 0.0]);
+}
+
 #[test]
 fn test_std_dev_constant_values() {
     // Constant values should have std dev = 0
@@ -141,3 +467,1041 @@ fn test_std_dev_empty() {
     let sd = std_dev(&[]);
     assert!(sd == 0.0);
 }
+
+#[test]
+fn test_frame_dynamics() {
+    // Band 0 alternates between 0 and 20 dB, band 1 stays put, band 2 is silent
+    let frames: Vec<Vec<f64>> = (0..10)
+        .map(|i| vec![if i % 2 == 0 { 1.0 } else { 100.0 }, 0.5, 0.0])
+        .collect();
+    let dynamics = frame_dynamics(frames.iter().map(Vec::as_slice), 3);
+    assert!((dynamics[0] - 10.0).abs() < 1e-9);
+    assert!(dynamics[1].abs() < 1e-9);
+    assert_eq!(dynamics[2], f64::NEG_INFINITY);
+}
+
+#[test]
+fn test_parse_band_labels() {
+    let spec = "D,S1,S2,LOW,MLOW,LM,M,UM,HM,P,B,H,UH,TOP";
+    let labels = parse_band_labels(spec, 14).unwrap();
+    assert_eq!(labels[3], "LOW");
+}
+
+#[test]
+fn test_parse_group_edges() {
+    let bands = default_bands();
+    assert_eq!(
+        parse_group_edges("120, 6000", &bands).unwrap(),
+        [120.0, 6000.0]
+    );
+    assert!(parse_group_edges("250", &bands).is_err());
+    assert!(parse_group_edges("300,4000", &bands).is_err());
+    assert!(parse_group_edges("0,4000", &bands).is_err());
+    assert!(parse_group_edges("4000,250", &bands).is_err());
+    assert!(parse_group_edges("250,x", &bands).is_err());
+}
+
+#[test]
+fn test_parse_ignored_bands() {
+    let bands = default_bands();
+    assert_eq!(
+        parse_ignored_bands("dc, AIR, DC", &bands).unwrap(),
+        vec![0, 13]
+    );
+    assert!(parse_ignored_bands("DC,TREBLE", &bands).is_err());
+    let all: Vec<&str> = bands.iter().map(|b| &*b.label).collect();
+    assert!(parse_ignored_bands(&all.join(","), &bands).is_err());
+}
+
+#[test]
+fn test_parse_excluded_bands() {
+    let bands = default_bands();
+    assert_eq!(parse_excluded_bands("DC,air", &bands).unwrap(), vec![0, 13]);
+    let err = parse_excluded_bands("DC,RUMBLE", &bands).unwrap_err();
+    assert!(err.starts_with("--exclude-bands"), "{}", err);
+    let all: Vec<&str> = bands.iter().map(|b| &*b.label).collect();
+    assert!(parse_excluded_bands(&all.join(","), &bands).is_err());
+}
+
+#[test]
+fn test_parse_range() {
+    assert_eq!(parse_range("20-16000").unwrap(), (20.0, 16000.0));
+    assert_eq!(parse_range(" 0 - 250.5").unwrap(), (0.0, 250.5));
+    assert!(parse_range("16000-20").is_err());
+    assert!(parse_range("20").is_err());
+    assert!(parse_range("a-b").is_err());
+}
+
+#[test]
+fn test_group_totals_default_edges() {
+    let bands = default_bands();
+    let totals = group_totals(&bands, DEFAULT_GROUP_EDGES_HZ, &vec![1.0; bands.len()]);
+    // LOW = DC-UBAS, MID = LMID-HMID, HIGH = PRES-AIR
+    assert_eq!(totals, vec![5.0, 4.0, 5.0]);
+
+    // Excluded bands (NaN) are skipped
+    let mut values = vec![1.0; bands.len()];
+    values[0] = f64::NAN;
+    assert_eq!(
+        group_totals(&bands, DEFAULT_GROUP_EDGES_HZ, &values),
+        vec![4.0, 4.0, 5.0]
+    );
+}
+
+#[test]
+fn test_parse_band_labels_rejects_invalid() {
+    // Wrong count, empty, duplicate (case-insensitive), too long
+    assert!(parse_band_labels("A,B,C", 14).is_err());
+    assert!(parse_band_labels("D,S1,S2,LOW,,LM,M,UM,HM,P,B,H,UH,TOP", 14).is_err());
+    assert!(parse_band_labels("D,S1,S2,LOW,low,LM,M,UM,HM,P,B,H,UH,TOP", 14).is_err());
+    assert!(parse_band_labels("D,S1,S2,VERYLONGBANDNAME,L,LM,M,UM,HM,P,B,H,UH,TOP", 14).is_err());
+}
+
+#[test]
+fn test_check_layout() {
+    let band = |label: &'static str, low_hz, high_hz| Band {
+        label: label.into(),
+        low_hz,
+        high_hz,
+    };
+    // Gaps are allowed, and only the top band may be open
+    assert!(check_layout(&[band("LOW", 0.0, 200.0), band("VOX", 300.0, f32::MAX)]).is_ok());
+    assert!(check_layout(&[]).is_err());
+    assert!(check_layout(&[band("LOW", 0.0, 200.0), band("low", 200.0, 400.0)]).is_err());
+    assert!(check_layout(&[band("LOW", 0.0, 200.0), band("MID", 150.0, 400.0)]).is_err());
+    assert!(check_layout(&[band("TOP", 200.0, f32::MAX), band("UP", 400.0, 800.0)]).is_err());
+    assert!(check_layout(&[band("BAD", 400.0, 200.0)]).is_err());
+}
+
+#[test]
+fn test_parse_metric() {
+    let below = parse_metric("below:100").unwrap();
+    assert_eq!(below.side, MetricSide::Below);
+    assert_eq!(below.hz, 100.0);
+    assert_eq!(
+        parse_metric(" Above : 10000 ").unwrap().spec(),
+        "above:10000"
+    );
+    assert!(parse_metric("under:100").is_err());
+    assert!(parse_metric("below").is_err());
+    assert!(parse_metric("below:0").is_err());
+    assert!(parse_metric("above:x").is_err());
+}
+
+#[test]
+fn test_metric_bins_split_the_spectrum() {
+    let below = parse_metric("below:100").unwrap().bins(3.0, 8193);
+    let above = parse_metric("above:100").unwrap().bins(3.0, 8193);
+    assert_eq!(below, 0..34);
+    assert_eq!(above, 34..8193);
+    let beyond = parse_metric("above:30000").unwrap().bins(3.0, 8193);
+    assert!(beyond.is_empty());
+}
+
+#[test]
+fn test_parse_target_curve() {
+    let text =
+        "* Measurement export\nFreq(Hz), SPL(dB), Phase\n20, -3.0, 10\n1000;0\n\n20000\t-6.5\n";
+    let curve = parse_target_curve("curve.csv", text).unwrap();
+    assert_eq!(curve.level_db(20.0), -3.0);
+    assert_eq!(curve.level_db(10.0), -3.0, "held below the first point");
+    assert_eq!(curve.level_db(24000.0), -6.5, "held above the last point");
+    // Interpolated over log frequency: 2000 Hz is a quarter of the way from 1 kHz to 20 kHz
+    let quarter = (2.0f64).log2() / (20.0f64).log2();
+    assert!((curve.level_db(2000.0) + 6.5 * quarter).abs() < 1e-9);
+
+    assert!(parse_target_curve("c", "100,0\n50,0\n").is_err());
+    assert!(parse_target_curve("c", "0,0\n50,0\n").is_err());
+    assert!(parse_target_curve("c", "100,0\n200,loud\n").is_err());
+    assert!(parse_target_curve("c", "Freq,dB\n100,0\n").is_err());
+}
+
+#[test]
+fn test_target_curve_band_shares() {
+    let bands = [
+        Band {
+            label: "LOW".into(),
+            low_hz: 0.0,
+            high_hz: 10.0,
+        },
+        Band {
+            label: "A".into(),
+            low_hz: 1000.0,
+            high_hz: 2000.0,
+        },
+        Band {
+            label: "B".into(),
+            low_hz: 2000.0,
+            high_hz: 4000.0,
+        },
+    ];
+    // A flat (white) curve gives bands power in proportion to their width
+    let config = AnalysisConfig::default()
+        .with_bands(bands.to_vec())
+        .unwrap();
+    let flat = parse_target_curve("flat", "100,0\n10000,0\n").unwrap();
+    let shares = flat.band_shares(&config);
+    assert!(shares[0].is_nan(), "below the curve");
+    assert!((shares[1] - 100.0 / 3.0).abs() < 0.2, "{:?}", shares);
+    // Falling 3 dB per octave (pink) gives octaves equal power
+    let pink = parse_target_curve("pink", "1000,0\n8000,-9\n").unwrap();
+    let shares = pink.band_shares(&config);
+    assert!((shares[1] - 50.0).abs() < 0.2, "{:?}", shares);
+
+    let file = [20.0, 40.0, 40.0];
+    assert_eq!(covered_shares(&file, &shares)[1], 50.0);
+    let deviation = target_deviation_db(&[0.0, 80.0, 20.0], &[f64::NAN, 50.0, 50.0]);
+    assert!(deviation[0].is_nan());
+    assert!((deviation[1] - 10.0 * 1.6f64.log10()).abs() < 1e-9);
+    assert!(
+        (mean_abs_deviation(&deviation) - (10.0 * 1.6f64.log10() + 10.0 * 2.5f64.log10()) / 2.0)
+            .abs()
+            < 1e-9
+    );
+}
+
+#[test]
+fn test_share_quantiles_ignore_order_and_repetition() {
+    let shares: Vec<f64> = (0..100).map(|i| i as f64).collect();
+    let mut stretched: Vec<f64> = shares.iter().rev().flat_map(|&v| [v, v]).collect();
+    stretched.rotate_left(37);
+    let a = share_quantiles(vec![shares]);
+    let b = share_quantiles(vec![stretched]);
+    assert!((typical_share(&a[0]) - 49.5).abs() < 1e-9);
+    assert!(share_distance(&a[0], &b[0]) < 0.5);
+
+    let louder: Vec<f64> = (0..100).map(|i| i as f64 + 10.0).collect();
+    let c = share_quantiles(vec![louder]);
+    assert!((typical_share(&c[0]) - typical_share(&a[0]) - 10.0).abs() < 1e-9);
+    assert!((share_distance(&a[0], &c[0]) - 10.0).abs() < 1e-9);
+    assert!(share_quantiles(vec![Vec::new()])[0][0].is_nan());
+}
+
+#[test]
+fn test_octave_preset() {
+    let bands = BandPreset::Octave.bands();
+    assert!(check_layout(&bands).is_ok());
+    assert_eq!(bands.len(), 10);
+    assert_eq!((bands[0].low_hz, bands[0].high_hz), (22.1, 44.2));
+    assert_eq!(bands[5].label, "1k");
+    assert_eq!((bands[5].low_hz, bands[5].high_hz), (707.1, 1414.2));
+    assert!(bands.windows(2).all(|w| w[0].high_hz == w[1].low_hz));
+}
+
+#[test]
+fn test_mel_preset() {
+    assert_eq!(parse_band_preset("Mel:40").unwrap(), BandPreset::Mel(40));
+    assert_eq!(parse_band_preset("octave").unwrap(), BandPreset::Octave);
+    assert!(parse_band_preset("mel:1").is_err());
+    assert!(parse_band_preset("mel:129").is_err());
+    assert!(parse_band_preset("mel").is_err());
+    assert!(parse_band_preset("bark:24").is_err());
+
+    let bands = BandPreset::Mel(40).bands();
+    assert!(check_layout(&bands).is_ok());
+    assert_eq!(bands.len(), 40);
+    assert_eq!((&*bands[0].label, &*bands[39].label), ("MEL1", "MEL40"));
+    assert_eq!(bands[0].low_hz, 0.0);
+    assert_eq!(bands[39].high_hz, f32::MAX);
+    // Equal in mel means ever wider in Hz
+    let widths: Vec<f32> = bands[..39].iter().map(|b| b.high_hz - b.low_hz).collect();
+    assert!(widths.windows(2).all(|w| w[1] > w[0]));
+    assert_eq!(bands[39].low_hz, 21894.7);
+}
+
+#[test]
+fn test_workflow_presets() {
+    for (name, preset, count) in [
+        ("mixer", BandPreset::Mixer, 8),
+        ("Mastering", BandPreset::Mastering, 11),
+        ("broadcast", BandPreset::Broadcast, 8),
+        ("sub-focus", BandPreset::SubFocus, 10),
+    ] {
+        assert_eq!(parse_band_preset(name).unwrap(), preset);
+        let bands = preset.bands();
+        assert!(check_layout(&bands).is_ok(), "{}", name);
+        assert_eq!(bands.len(), count, "{}", name);
+        assert_eq!(bands[0].low_hz, 0.0, "{}", name);
+        assert_eq!(bands[count - 1].high_hz, f32::MAX, "{}", name);
+        assert!(
+            bands.windows(2).all(|w| w[0].high_hz == w[1].low_hz),
+            "{}",
+            name
+        );
+    }
+    let sub = BandPreset::SubFocus.bands();
+    let above_dc_below_120 = sub
+        .iter()
+        .filter(|b| b.low_hz >= 20.0 && b.high_hz <= 120.0);
+    assert_eq!(above_dc_below_120.count(), 5);
+}
+
+#[test]
+fn test_log_bands() {
+    let bands = log_bands(10, None).unwrap();
+    assert!(check_layout(&bands).is_ok());
+    assert_eq!((&*bands[0].label, &*bands[9].label), ("LOG1", "LOG10"));
+    assert_eq!((bands[0].low_hz, bands[9].high_hz), (20.0, 20000.0));
+    assert!(bands.windows(2).all(|w| w[0].high_hz == w[1].low_hz));
+
+    let bands = log_bands(4, Some("100-1600")).unwrap();
+    let edges: Vec<f32> = bands.iter().map(|b| b.low_hz).collect();
+    assert_eq!(edges, [100.0, 200.0, 400.0, 800.0]);
+    assert_eq!(bands[3].high_hz, 1600.0);
+
+    assert!(log_bands(1, None).is_err());
+    assert!(log_bands(129, None).is_err());
+    assert!(log_bands(4, Some("0-1600")).is_err());
+}
+
+/// Sum of all band weights for each bin from DC through Nyquist
+fn bin_weight_totals(freq_per_bin: f32, num_bins: usize, crossover_octaves: f64) -> Vec<f64> {
+    let mut totals = vec![0.0; num_bins];
+    for bins in band_bins(&default_bands(), freq_per_bin, num_bins, crossover_octaves) {
+        for (i, w) in bins.weights.iter().enumerate() {
+            totals[bins.start + i] += w;
+        }
+    }
+    totals
+}
+
+/// Sample rate / FFT size combinations covering edges on, near, and between bin centres
+const BIN_LAYOUTS: [(f32, usize); 6] = [
+    (48000.0, 16384),
+    (44100.0, 16384),
+    (96000.0, 16384),
+    (48000.0, 4096),
+    (22050.0, 1024),
+    (40000.0, 40000),
+];
+
+#[test]
+fn test_hard_band_edges_count_each_bin_once() {
+    for (sample_rate, fft_size) in BIN_LAYOUTS {
+        let num_bins = fft_size / 2 + 1;
+        let totals = bin_weight_totals(sample_rate / fft_size as f32, num_bins, 0.0);
+        for (bin, total) in totals.iter().enumerate() {
+            assert_eq!(
+                *total, 1.0,
+                "bin {} counted {} times ({} Hz, FFT {})",
+                bin, total, sample_rate, fft_size
+            );
+        }
+    }
+}
+
+#[test]
+fn test_hard_band_edges_follow_bin_centres() {
+    // 1 Hz per bin puts band edges exactly on bin centres
+    let bins = band_bins(&default_bands(), 1.0, 24001, 0.0);
+    assert_eq!(bins[6].start, 500, "MID starts at the 500 Hz bin");
+    assert_eq!(bins[6].start + bins[6].weights.len(), 1000);
+    assert_eq!(bins[7].start, 1000, "1000 Hz belongs to UMID");
+    assert_eq!(
+        bins[13].start + bins[13].weights.len(),
+        24001,
+        "AIR includes Nyquist"
+    );
+}
+
+#[test]
+fn test_soft_band_edges_conserve_energy() {
+    for (sample_rate, fft_size) in BIN_LAYOUTS {
+        let num_bins = fft_size / 2 + 1;
+        for crossover in [1.0 / 6.0, 1.0 / 3.0, 1.0] {
+            let totals = bin_weight_totals(sample_rate / fft_size as f32, num_bins, crossover);
+            for (bin, total) in totals.iter().enumerate() {
+                assert!(
+                    (total - 1.0).abs() < 1e-9,
+                    "bin {} weight sum {} (crossover {}, {} Hz, FFT {})",
+                    bin,
+                    total,
+                    crossover,
+                    sample_rate,
+                    fft_size
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_band_power_equals_spectrum_power() {
+    let mut ctx = precision_context(Precision::F32);
+    let mut spectrum = SpectrumAnalyzer::with_size(Precision::F32, FFT_SIZE, false);
+    let mut power = vec![0.0; SPECTRUM_BINS];
+
+    let mut state = 0x2545_F491_4F6C_DD1Du64;
+    for _ in 0..4 {
+        // Random white noise, one FFT frame
+        let samples: Vec<f32> = (0..FFT_SIZE)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state as f64 / u64::MAX as f64) as f32 * 2.0 - 1.0
+            })
+            .collect();
+
+        spectrum.power_spectrum(&samples, &mut power, false);
+        let spectrum_power: f64 = power.iter().sum();
+
+        let band_power: f64 = analyze_interval(&samples, &mut ctx, &default_bands(), false)
+            .iter()
+            .sum();
+        assert!(
+            ((band_power - spectrum_power) / spectrum_power).abs() < 1e-9,
+            "band total {} vs spectrum total {}",
+            band_power,
+            spectrum_power
+        );
+    }
+}
+
+#[test]
+fn test_real_ffts_match_complex_fft() {
+    let frame: Vec<f64> = (0..FFT_SIZE)
+        .map(|i| ((i * 7919) % 1000) as f64 / 500.0 - 1.0)
+        .collect();
+    let mut expected: Vec<Complex<f64>> = frame.iter().map(|&x| Complex::new(x, 0.0)).collect();
+    FftPlanner::new()
+        .plan_fft_forward(FFT_SIZE)
+        .process(&mut expected);
+    let peak = expected.iter().map(|c| c.norm()).fold(0.0, f64::max);
+
+    let ffts: [std::sync::Arc<dyn RealToComplex<f64>>; 2] = [
+        RealFftPlanner::new().plan_fft_forward(FFT_SIZE),
+        std::sync::Arc::new(ScalarRealToComplex::new(FFT_SIZE)),
+    ];
+    for fft in ffts {
+        let mut input = frame.clone();
+        let mut output = fft.make_output_vec();
+        fft.process(&mut input, &mut output).unwrap();
+        assert_eq!(output.len(), SPECTRUM_BINS);
+        for (k, (a, b)) in output.iter().zip(&expected).enumerate() {
+            assert!((a - b).norm() < 1e-12 * peak, "bin {}: {} vs {}", k, a, b);
+        }
+    }
+}
+
+#[test]
+fn test_soft_band_edges_split_at_boundary() {
+    let bands = default_bands();
+    let freq_per_bin = 1.0;
+    let bins = band_bins(&bands, freq_per_bin, 24000, 1.0 / 3.0);
+    // MID (500-1000 Hz) and UMID (1000-2000 Hz) share 1000 Hz equally
+    let weight_at = |band: usize, bin: usize| {
+        let b = &bins[band];
+        bin.checked_sub(b.start)
+            .and_then(|i| b.weights.get(i))
+            .copied()
+            .unwrap_or(0.0)
+    };
+    assert!((weight_at(6, 1000) - 0.5).abs() < 1e-9);
+    assert!((weight_at(7, 1000) - 0.5).abs() < 1e-9);
+    // Well inside the band, weights are unchanged
+    assert_eq!(weight_at(6, 700), 1.0);
+    assert_eq!(weight_at(7, 700), 0.0);
+}
+
+#[test]
+fn test_f64_precision_matches_f32() {
+    let samples: Vec<f32> = (0..FFT_SIZE * 2)
+        .map(|i| {
+            let t = i as f32 / 48000.0;
+            (2.0 * std::f32::consts::PI * 100.0 * t).sin()
+                + 0.1 * (2.0 * std::f32::consts::PI * 3000.0 * t).sin()
+        })
+        .collect();
+
+    let analyze = |precision| {
+        let mut ctx = precision_context(precision);
+        let powers = analyze_interval(&samples, &mut ctx, &default_bands(), false);
+        powers_to_percentages(&powers, &[])
+    };
+    let single = analyze(Precision::F32);
+    let double = analyze(Precision::F64);
+    for (band, (a, b)) in single.iter().zip(&double).enumerate() {
+        assert!(
+            (a - b).abs() < 1e-3,
+            "band {}: f32 {} vs f64 {}",
+            band,
+            a,
+            b
+        );
+    }
+}
+
+#[test]
+fn test_f64_power_spectrum_satisfies_parseval() {
+    let frame: Vec<f32> = (0..FFT_SIZE)
+        .map(|i| ((i * 7919) % 1000) as f32 / 500.0 - 1.0)
+        .collect();
+    let mut power = vec![0.0; SPECTRUM_BINS];
+    let energy = SpectrumAnalyzer::with_size(Precision::F64, FFT_SIZE, false)
+        .power_spectrum(&frame, &mut power, true)
+        .unwrap();
+
+    let one_sided: f64 = power.iter().sum();
+    let full = 2.0 * one_sided - power[0] - power[FFT_SIZE / 2];
+    assert!(((full / FFT_SIZE as f64 - energy) / energy).abs() < 1e-12);
+}
+
+#[test]
+fn test_zero_padded_power_spectrum_keeps_total() {
+    let frame: Vec<f32> = (0..FFT_SIZE)
+        .map(|i| ((i * 7919) % 1000) as f32 / 500.0 - 1.0)
+        .collect();
+    let mut power = vec![0.0; SPECTRUM_BINS];
+    SpectrumAnalyzer::with_size(Precision::F64, FFT_SIZE, false)
+        .power_spectrum(&frame, &mut power, false);
+    let mut padded = vec![0.0; 4 * FFT_SIZE / 2 + 1];
+    let energy = SpectrumAnalyzer::with_padding(Precision::F64, FFT_SIZE, 4 * FFT_SIZE, false)
+        .power_spectrum(&frame, &mut padded, true)
+        .unwrap();
+
+    // Bins that line up with the unpadded ones keep their power over the padding
+    assert!((padded[4 * 100] * 4.0 - power[100]).abs() < 1e-9 * power[100]);
+    let one_sided: f64 = padded.iter().sum();
+    let full = 2.0 * one_sided - padded[0] - padded[padded.len() - 1];
+    assert!(((full / FFT_SIZE as f64 - energy) / energy).abs() < 1e-12);
+}
+
+#[test]
+fn test_zero_pad_splits_edge_tone_evenly() {
+    let bands = default_bands();
+    // 40 Hz is the SUB1/SUB2 edge, between two bins without padding
+    let tone: Vec<f32> = (0..FFT_SIZE * 4)
+        .map(|i| (2.0 * std::f32::consts::PI * 40.0 * i as f32 / 48000.0).sin())
+        .collect();
+    let sub1_share = |zero_pad: usize| {
+        let mut ctx = fft_context(false, zero_pad, KWeightMode::Spectral, Weighting::K);
+        let pct = powers_to_percentages(&analyze_interval(&tone, &mut ctx, &bands, false), &[]);
+        assert!((pct[1] + pct[2] - 100.0).abs() < 0.1);
+        pct[1]
+    };
+
+    let plain = sub1_share(1);
+    let padded = sub1_share(8);
+    assert!((padded - 50.0).abs() < 3.0, "SUB1 {}", padded);
+    assert!(
+        (padded - 50.0).abs() < (plain - 50.0).abs() / 2.0,
+        "{} vs {}",
+        padded,
+        plain
+    );
+}
+
+/// More frames than one parallel run of `analyze_stats`, in noise that swells and fades
+fn swelling_noise() -> crate::audio::AudioData {
+    let mut state = 0x2545_F491_4F6C_DD1Du64;
+    let len = (PARALLEL_FRAMES + 100) * HOP_SIZE + FFT_SIZE;
+    crate::audio::AudioData {
+        samples: (0..len)
+            .map(|i| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                let noise = (state as f64 / u64::MAX as f64) as f32 * 2.0 - 1.0;
+                noise * (0.5 + 0.45 * (i as f32 / 48000.0).sin())
+            })
+            .collect(),
+        sample_rate: 48000,
+        channels: 1,
+        original_sample_rate: 48000,
+    }
+}
+
+#[test]
+fn test_stats_identical_across_thread_counts() {
+    let audio = swelling_noise();
+    let stats = |threads: usize| {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        let mut ctx = precision_context(Precision::F32);
+        pool.install(|| analyze_stats(&audio, &default_bands(), &mut ctx, |_| {}))
+    };
+
+    let single = stats(1);
+    let parallel = stats(3);
+    assert_eq!(
+        serde_json::to_string(&single).unwrap(),
+        serde_json::to_string(&parallel).unwrap()
+    );
+    assert_eq!(single.share_quantiles, parallel.share_quantiles);
+}
+
+#[test]
+fn test_streamed_stats_match_whole_buffer() {
+    let audio = swelling_noise();
+    let bands = default_bands();
+    // The long frames reach past the end of the FFT_SIZE frames, so they need the most
+    // buffered audio
+    let context = || fft_context(true, 1, KWeightMode::Spectral, Weighting::K);
+
+    let whole = analyze_stats(&audio, &bands, &mut context(), |_| {});
+    let mut ctx = context();
+    let mut accumulator = StatsAccumulator::new(&bands, &mut ctx, None);
+    // Pieces the size of decoded MP3 frames
+    for piece in audio.samples.chunks(1152) {
+        accumulator.push(piece);
+    }
+    let streamed = accumulator.finish();
+    assert_eq!(
+        serde_json::to_string(&whole).unwrap(),
+        serde_json::to_string(&streamed).unwrap()
+    );
+    assert_eq!(whole.share_quantiles, streamed.share_quantiles);
+}
+
+#[test]
+fn test_reused_context_matches_fresh_context() {
+    let bands = default_bands();
+    let tone = |freq: f32| -> Vec<f32> {
+        (0..FFT_SIZE * 2)
+            .map(|i| (2.0 * std::f32::consts::PI * freq * i as f32 / 48000.0).sin())
+            .collect()
+    };
+    let signals = [tone(60.0), tone(5000.0), tone(440.0)];
+
+    let mut shared = precision_context(Precision::F32);
+    for samples in &signals {
+        for k_weighted in [false, true] {
+            let mut fresh = precision_context(Precision::F32);
+            assert_eq!(
+                analyze_interval(samples, &mut shared, &bands, k_weighted),
+                analyze_interval(samples, &mut fresh, &bands, k_weighted)
+            );
+        }
+    }
+}
+
+#[test]
+fn test_short_term_loudness_of_sine() {
+    // A -6 dBFS 1 kHz sine reads about -9 LUFS (K-weighting is ~+0.7 dB at 1 kHz)
+    let bands = default_bands();
+    let samples: Vec<f32> = (0..48000 * 5)
+        .map(|i| 0.5 * (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / 48000.0).sin())
+        .collect();
+    let mut ctx = precision_context(Precision::F32);
+    let loudness = short_term_loudness(&samples, &mut ctx, &bands, DEFAULT_GROUP_EDGES_HZ);
+
+    assert_eq!(loudness.times, vec![3.0, 4.0, 5.0]);
+    for (i, &lufs) in loudness.short_term.iter().enumerate() {
+        assert!((lufs + 9.03).abs() < 0.3, "short-term {} LUFS", lufs);
+        // All the energy sits in the MID group
+        assert!((loudness.groups[1][i] - lufs).abs() < 0.1);
+        assert_eq!(loudness.groups[0][i], LOUDNESS_FLOOR_LUFS);
+    }
+}
+
+#[test]
+fn test_integrated_loudness_of_sine_with_silence() {
+    // Gating leaves the silent second out, so the sine still reads about -9 LUFS
+    let bands = default_bands();
+    let mut samples: Vec<f32> = (0..48000 * 4)
+        .map(|i| 0.5 * (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / 48000.0).sin())
+        .collect();
+    samples.extend(std::iter::repeat_n(0.0, 48000));
+    let audio = crate::audio::AudioData {
+        samples,
+        sample_rate: 48000,
+        channels: 1,
+        original_sample_rate: 48000,
+    };
+    let mut ctx = precision_context(Precision::F32);
+    let stats = analyze_stats(&audio, &bands, &mut ctx, |_| {});
+    assert!(
+        (stats.integrated_lufs + 9.03).abs() < 0.3,
+        "integrated {} LUFS",
+        stats.integrated_lufs
+    );
+}
+
+#[test]
+fn test_verify_summary_stays_with_its_analysis() {
+    // Each analysis counts only its own frame checks, and only when it verifies
+    let bands = default_bands();
+    let audio = crate::audio::AudioData {
+        samples: (0..48000 * 2)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / 48000.0).sin())
+            .collect(),
+        sample_rate: 48000,
+        channels: 1,
+        original_sample_rate: 48000,
+    };
+    let mut config = AnalysisConfig::default();
+    config.verify = true;
+    let mut verified = AnalysisContext::new(48000, &config);
+    let mut plain = precision_context(Precision::F32);
+
+    let first = analyze_stats(&audio, &bands, &mut verified, |_| {});
+    let unchecked = analyze_stats(&audio, &bands, &mut plain, |_| {});
+    let second = analyze_stats(&audio, &bands, &mut verified, |_| {});
+
+    let summary = first.verify.expect("verified analysis has a summary");
+    assert_eq!(summary.frames, first.frames);
+    assert!(summary.passed());
+    assert_eq!(second.verify.map(|s| s.frames), Some(summary.frames));
+    assert!(unchecked.verify.is_none());
+    assert!(verified.verify_summary().is_some_and(|s| s.frames == 0));
+}
+
+#[test]
+fn test_verify_checks_multi_resolution_frames_before_the_long_bands() {
+    // Both passes check the FFT_SIZE frame, so the long low bands don't fail coverage
+    let bands = default_bands();
+    let samples: Vec<f32> = (0..48000 * 3)
+        .map(|i| 0.5 * (2.0 * std::f32::consts::PI * 60.0 * i as f32 / 48000.0).sin())
+        .collect();
+    let mut config = AnalysisConfig::default();
+    config.verify = true;
+    config.multi_resolution = true;
+    let mut ctx = AnalysisContext::new(48000, &config);
+
+    analyze_interval(&samples, &mut ctx, &bands, false);
+    let audio = crate::audio::AudioData {
+        samples,
+        sample_rate: 48000,
+        channels: 1,
+        original_sample_rate: 48000,
+    };
+    let stats = analyze_stats(&audio, &bands, &mut ctx, |_| {});
+
+    assert!(
+        ctx.verify_summary()
+            .is_some_and(|s| s.frames > 0 && s.passed())
+    );
+    assert!(stats.verify.is_some_and(|s| s.frames > 0 && s.passed()));
+}
+
+#[test]
+fn test_loudness_meter_momentary_and_short_term() {
+    // A 1 s burst of a -9 LUFS sine in silence: the 400 ms window fits inside it, the 3 s
+    // one averages it with 2 s of silence
+    let mut samples = vec![0.0f32; 48000 * 3];
+    samples.extend(
+        (0..48000).map(|i| 0.5 * (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / 48000.0).sin()),
+    );
+    samples.extend(std::iter::repeat_n(0.0, 48000 * 3));
+
+    let mut meter = LoudnessMeter::new(48000);
+    meter.push(&samples[..48000 * 3]);
+    let (momentary, short_term) = meter.take_max().unwrap();
+    assert_eq!(
+        (momentary, short_term),
+        (LOUDNESS_FLOOR_LUFS, LOUDNESS_FLOOR_LUFS)
+    );
+
+    meter.push(&samples[48000 * 3..]);
+    let (momentary, short_term) = meter.take_max().unwrap();
+    assert!(
+        (momentary + 9.03).abs() < 0.3,
+        "momentary {} LUFS",
+        momentary
+    );
+    let expected = -9.03 + 10.0 * (1.0f64 / 3.0).log10();
+    assert!(
+        (short_term - expected).abs() < 0.3,
+        "short-term {} LUFS",
+        short_term
+    );
+    assert_eq!(meter.take_max(), None);
+
+    // The windows slide across pushes, however the audio is split
+    let mut pieces = LoudnessMeter::new(48000);
+    samples.chunks(1000).for_each(|piece| pieces.push(piece));
+    let (m, s) = pieces.take_max().unwrap();
+    assert!((m - momentary).abs() < 1e-6 && (s - short_term).abs() < 1e-6);
+}
+
+#[test]
+fn test_multi_resolution_low_bands() {
+    let bands = default_bands();
+    let sines = |freqs: &[f32], secs: usize| -> Vec<f32> {
+        (0..48000 * secs)
+            .map(|i| {
+                freqs
+                    .iter()
+                    .map(|f| 0.25 * (2.0 * std::f32::consts::PI * f * i as f32 / 48000.0).sin())
+                    .sum()
+            })
+            .collect()
+    };
+    let shares = |samples: Vec<f32>, multi_resolution: bool| {
+        let audio = crate::audio::AudioData {
+            samples,
+            sample_rate: 48000,
+            channels: 1,
+            original_sample_rate: 48000,
+        };
+        let mut ctx = fft_context(multi_resolution, 1, KWeightMode::Spectral, Weighting::K);
+        powers_to_percentages(
+            &analyze_stats(&audio, &bands, &mut ctx, |_| {}).raw_powers,
+            &[],
+        )
+    };
+
+    // The long FFT's bands are scaled to the level of the short FFT's
+    let mixed = shares(sines(&[50.0, 700.0], 3), true);
+    assert!((mixed[2] - 50.0).abs() < 0.5, "SUB2 {}", mixed[2]);
+    assert!((mixed[6] - 50.0).abs() < 0.5, "MID {}", mixed[6]);
+
+    // Narrower bins leak far less of a tone near a band edge into the next band
+    let short = shares(sines(&[17.0], 3), false);
+    let long = shares(sines(&[17.0], 3), true);
+    assert!(
+        100.0 - long[0] < (100.0 - short[0]) / 4.0,
+        "{} vs {}",
+        long[0],
+        short[0]
+    );
+
+    // Long frames reaching past the ends of a short interval are scaled up to make up for it
+    let mut ctx = fft_context(true, 1, KWeightMode::Spectral, Weighting::K);
+    let interval = sines(&[50.0, 700.0], 1);
+    let pct = powers_to_percentages(&analyze_interval(&interval, &mut ctx, &bands, false), &[]);
+    assert!((pct[2] - 50.0).abs() < 2.0, "SUB2 {}", pct[2]);
+}
+
+#[test]
+fn test_standard_error_of_band_percentages() {
+    let bands = default_bands();
+    let stats = |samples: Vec<f32>| {
+        let audio = crate::audio::AudioData {
+            samples,
+            sample_rate: 48000,
+            channels: 1,
+            original_sample_rate: 48000,
+        };
+        let mut ctx = precision_context(Precision::F32);
+        analyze_stats(&audio, &bands, &mut ctx, |_| {})
+    };
+
+    // A steady tone has the same balance in every block
+    let tone = (0..48000 * 3)
+        .map(|i| (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / 48000.0).sin())
+        .collect();
+    let se = stats(tone).raw_pct_se;
+    assert!(se.iter().all(|&se| se < 1e-3), "{:?}", se);
+
+    // Noise balance fluctuates from block to block
+    let mut state = 1u32;
+    let noise = (0..48000 * 3)
+        .map(|_| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            state as f32 / u32::MAX as f32 - 0.5
+        })
+        .collect();
+    let result = stats(noise);
+    let se = &result.raw_pct_se;
+    assert!(se.iter().all(|&se| se.is_finite()));
+    assert!(se[13] > 0.0);
+
+    // Three seconds are only a few blocks, so the t quantile widens the interval
+    assert!(result.frames < MIN_STABLE_FRAMES);
+    let ratio = result.raw_pct_ci[13] / se[13];
+    assert!(ratio > 2.1 && ratio < 2.5, "CI / SE = {}", ratio);
+
+    // Shorter than two blocks: unknown
+    let short = stats(vec![0.1; FFT_SIZE]);
+    assert!(short.raw_pct_se[0].is_nan());
+    assert!(short.raw_pct_ci[0].is_nan());
+}
+
+#[test]
+fn test_alignment_offsets_and_common_spans() {
+    let mut state = 7u32;
+    let noise: Vec<f32> = (0..48000)
+        .map(|_| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            state as f32 / u32::MAX as f32 - 0.5
+        })
+        .collect();
+    let delayed: Vec<f32> = [vec![0.0; 300], noise.clone()].concat();
+
+    let alignment = find_alignment(&noise, &delayed, 48000, false);
+    assert_eq!(alignment.offset, 300);
+    assert!(alignment.confidence > 0.99);
+    assert_eq!(find_alignment(&delayed, &noise, 48000, false).offset, -300);
+
+    // The delayed copy skips its padding; the one starting late cuts the others' start
+    assert_eq!(
+        common_spans(&[1000, 1300, 800], &[0, 300, -200]),
+        vec![200..1000, 500..1300, 0..800]
+    );
+}
+
+#[test]
+fn test_saved_bands_and_stats_round_trip() {
+    let bands = default_bands();
+    let json = serde_json::to_value(&bands).unwrap();
+    assert!(json[bands.len() - 1]["high_hz"].is_null());
+    let parsed: Vec<super::Band> = serde_json::from_value(json).unwrap();
+    assert_eq!(parsed.last().unwrap().high_hz, f32::MAX);
+    assert_eq!(parsed[3].label, bands[3].label);
+
+    // Unknown values are saved as null and read back as NaN
+    let audio = crate::audio::AudioData {
+        samples: vec![0.1; FFT_SIZE],
+        sample_rate: 48000,
+        channels: 1,
+        original_sample_rate: 48000,
+    };
+    let mut ctx = precision_context(Precision::F32);
+    let stats = analyze_stats(&audio, &bands, &mut ctx, |_| {});
+    let json = serde_json::to_string(&stats).unwrap();
+    let parsed: super::fft::StatsResult = serde_json::from_str(&json).unwrap();
+    assert!(parsed.raw_pct_se[0].is_nan());
+    for (parsed, saved) in parsed.raw_powers.iter().zip(&stats.raw_powers) {
+        assert!((parsed - saved).abs() <= saved.abs() * 1e-12);
+    }
+}
+
+#[test]
+fn test_gate_frames() {
+    // -20 LUFS frames pass; silence and a frame 20 LU down are gated out
+    let loud = 10f64.powf((-20.0 + 0.691) / 10.0);
+    let quiet = loud / 100.0;
+    assert_eq!(
+        gate_frames(&[loud, 0.0, quiet, loud]),
+        vec![true, false, false, true]
+    );
+    // Only -75 LUFS: below the absolute gate
+    let faint = 10f64.powf((-75.0 + 0.691) / 10.0);
+    assert_eq!(gate_frames(&[faint, faint]), vec![false, false]);
+    assert!(gate_frames(&[]).is_empty());
+}
+
+#[test]
+fn test_gated_k_weighted_percentages_skip_quiet_passage() {
+    let bands = default_bands();
+    // Loud 1 kHz, then a long 100 Hz passage 20 dB down
+    let samples: Vec<f32> = (0..48000 * 6)
+        .map(|i| {
+            let t = i as f32 / 48000.0;
+            if i < 48000 * 2 {
+                (2.0 * std::f32::consts::PI * 1000.0 * t).sin()
+            } else {
+                0.1 * (2.0 * std::f32::consts::PI * 100.0 * t).sin()
+            }
+        })
+        .collect();
+    let audio = crate::audio::AudioData {
+        samples,
+        sample_rate: 48000,
+        channels: 1,
+        original_sample_rate: 48000,
+    };
+    let mut ctx = precision_context(Precision::F32);
+    let stats = analyze_stats(&audio, &bands, &mut ctx, |_| {});
+
+    let k_pct = powers_to_percentages(&stats.k_powers, &[]);
+    let band_100 = bands
+        .iter()
+        .position(|b| b.low_hz <= 100.0 && 100.0 < b.high_hz)
+        .unwrap();
+    // The quiet passage is still counted in Raw, but only its onset leaks into K-wt
+    assert!(powers_to_percentages(&stats.raw_powers, &[])[band_100] > 1.0);
+    assert!(k_pct[band_100] < 0.1, "{}", k_pct[band_100]);
+}
+
+#[test]
+fn test_parse_noise_gate() {
+    assert_eq!(parse_noise_gate("-60dB"), Ok(-60.0));
+    assert_eq!(parse_noise_gate(" -45.5 dBFS"), Ok(-45.5));
+    assert_eq!(parse_noise_gate("-70"), Ok(-70.0));
+    assert!(parse_noise_gate("0dB").is_err());
+    assert!(parse_noise_gate("loud").is_err());
+}
+
+#[test]
+fn test_active_regions_skip_long_pauses() {
+    let tone = |secs: f32| -> Vec<f32> {
+        (0..(48000.0 * secs) as usize)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 48000.0).sin())
+            .collect()
+    };
+    // Tone, 0.4 s breath pause, tone, 3 s of silence, tone
+    let mut samples = tone(2.0);
+    samples.extend(vec![0.0; 19200]);
+    samples.extend(tone(1.0));
+    samples.extend(vec![0.0; 48000 * 3]);
+    samples.extend(tone(1.0));
+
+    // The short pause is bridged; the long one is cut down to the 0.3 s hangovers
+    let regions = active_regions(&samples, 48000);
+    assert_eq!(regions, vec![0..177600, 292800..samples.len()]);
+    let joined = active_samples(&samples, 48000);
+    assert_eq!(joined.len(), samples.len() - (48000 * 3 - 2 * 14400));
+
+    assert!(active_regions(&vec![0.0; 48000], 48000).is_empty());
+    assert!(active_regions(&[], 48000).is_empty());
+}
+
+#[test]
+fn test_band_importance_follows_hearing_sensitivity() {
+    let bands = default_bands();
+    let importance: Vec<f64> = bands.iter().map(band_importance).collect();
+    // SUB1 (~28 Hz) counts far less than HMID (~2.8 kHz), the most sensitive region
+    assert!(importance[1] < 0.05);
+    assert!(importance[8] > 0.9);
+    assert!(importance.iter().all(|&w| w > 0.0 && w <= 1.0));
+}
+
+#[test]
+fn test_difference_score_weights_mid_over_sub() {
+    let bands = default_bands();
+    let base = vec![100.0 / 14.0; 14];
+    let shift = |from: usize, to: usize| {
+        let mut other = base.clone();
+        other[from] -= 3.0;
+        other[to] += 3.0;
+        other
+    };
+    let sub = difference_score(&bands, &base, &shift(1, 2));
+    let mid = difference_score(&bands, &base, &shift(7, 8));
+    assert!((sub.flat - mid.flat).abs() < 1e-12);
+    assert!(mid.weighted > 5.0 * sub.weighted);
+    assert_eq!(difference_score(&bands, &base, &base).weighted, 0.0);
+
+    // Excluded bands (NaN) leave the mean
+    let mut excluded = shift(7, 8);
+    excluded[0] = f64::NAN;
+    let score = difference_score(&bands, &base, &excluded);
+    assert!((score.flat - 6.0 / 13.0).abs() < 1e-12);
+}
+
+#[test]
+fn test_delayed_impulse_has_that_delay_in_every_band() {
+    let config = AnalysisConfig::default();
+    let bands = config.bands();
+    let mut ir = vec![0.0f32; 4800];
+    ir[480] = 1.0;
+    let responses = band_responses(&ir, 48000, &config);
+    assert_eq!(responses.len(), bands.len());
+    for (band, response) in bands.iter().zip(&responses) {
+        assert!(
+            response.gain_db.abs() < 0.01,
+            "{}: {}",
+            band.label,
+            response.gain_db
+        );
+        assert!(
+            (response.group_delay_ms - 10.0).abs() < 0.01,
+            "{}: {}",
+            band.label,
+            response.group_delay_ms
+        );
+    }
+
+    // A band the response does not reach gets no delay
+    let silent = band_responses(&vec![0.0; 4800], 48000, &config);
+    assert!(
+        silent
+            .iter()
+            .all(|r| r.gain_db.is_nan() && r.group_delay_ms.is_nan())
+    );
+}
@@ -0,0 +1,83 @@
+//! Energy-conservation checks for debugging (`--verify`)
+
+/// Allowed relative error between time-domain and spectral frame energy (f32 FFT rounding)
+pub const PARSEVAL_TOLERANCE: f64 = 1e-5;
+
+/// Allowed relative error between summed band power and the spectrum power the bands cover
+pub const COVERAGE_TOLERANCE: f64 = 1e-9;
+
+/// Frames with less energy than this are skipped (relative error is meaningless)
+const MIN_ENERGY: f64 = 1e-20;
+
+/// Relative errors of one frame's checks
+#[derive(Clone, Copy, Debug)]
+pub(super) struct FrameCheck {
+    parseval_error: f64,
+    coverage_error: f64,
+}
+
+/// Accumulated results of the frame checks of an analysis
+#[derive(Clone, Debug, Default)]
+pub struct VerifySummary {
+    pub frames: usize,
+    pub parseval_failures: usize,
+    pub coverage_failures: usize,
+    pub max_parseval_error: f64,
+    pub max_coverage_error: f64,
+}
+
+impl VerifySummary {
+    pub(super) fn record(&mut self, check: FrameCheck) {
+        self.frames += 1;
+        self.max_parseval_error = self.max_parseval_error.max(check.parseval_error);
+        self.max_coverage_error = self.max_coverage_error.max(check.coverage_error);
+        if check.parseval_error > PARSEVAL_TOLERANCE {
+            self.parseval_failures += 1;
+        }
+        if check.coverage_error > COVERAGE_TOLERANCE {
+            self.coverage_failures += 1;
+        }
+    }
+
+    /// Add the checks of another analysis, e.g. of the next file
+    pub fn merge(&mut self, other: &VerifySummary) {
+        self.frames += other.frames;
+        self.parseval_failures += other.parseval_failures;
+        self.coverage_failures += other.coverage_failures;
+        self.max_parseval_error = self.max_parseval_error.max(other.max_parseval_error);
+        self.max_coverage_error = self.max_coverage_error.max(other.max_coverage_error);
+    }
+
+    pub fn passed(&self) -> bool {
+        self.parseval_failures == 0 && self.coverage_failures == 0
+    }
+}
+
+/// Check one frame: Parseval's theorem on the full spectrum (reconstructed from the
+/// one-sided power of a real signal), and that the band powers add up to the power of
+/// the parts of the one-sided (DC through Nyquist) spectrum the layout covers
+pub(super) fn check_frame(
+    time_energy: f64,
+    power: &[f64],
+    fft_size: usize,
+    band_total: f64,
+    covered: f64,
+) -> FrameCheck {
+    let one_sided: f64 = power.iter().sum();
+    let nyquist = power[power.len() - 1];
+    // Bins 1..N/2 appear twice in the full spectrum; DC and Nyquist once
+    let full = 2.0 * one_sided - power[0] - nyquist;
+
+    FrameCheck {
+        parseval_error: relative_error(time_energy, full / fft_size as f64),
+        coverage_error: relative_error(covered, band_total),
+    }
+}
+
+fn relative_error(expected: f64, actual: f64) -> f64 {
+    if expected.abs() < MIN_ENERGY && actual.abs() < MIN_ENERGY {
+        0.0
+    } else {
+        (actual - expected).abs() / expected.abs().max(MIN_ENERGY)
+    }
+}
@@ -0,0 +1,139 @@
+//! Frequency weighting of the weighted columns (`--weighting`): K (BS.1770), C, Z, an
+//! ISO 226 equal-loudness contour, or a curve from a file (`--weighting-file`)
+
+use std::path::Path;
+use std::sync::Arc;
+
+use super::equal_loudness::{PHON_RANGE, contour, contour_spl};
+use super::kweight::create_k_weight_table;
+use super::target::TargetCurve;
+
+/// Weighting curve applied to the band powers of the weighted (K-wt) columns
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Weighting {
+    /// ITU-R BS.1770 K-weighting, as loudness meters use
+    #[default]
+    K,
+    /// IEC 61672 C-weighting, flat from about 60 Hz to 4 kHz
+    C,
+    /// Zero (flat) weighting: the Raw spectrum, gated like the other weightings
+    Z,
+    /// The inverse of the ISO 226:2003 equal-loudness contour at a loudness level in phon,
+    /// i.e. how loud each frequency sounds at that playback level
+    Iso226(u8),
+    /// The dB levels of a frequency response, e.g. a headphone correction, added to each
+    /// bin's level
+    Curve(Arc<TargetCurve>),
+}
+
+impl Weighting {
+    /// Column label, e.g. "K-wt" or "75ph"
+    pub fn label(&self) -> String {
+        match self {
+            Weighting::K => "K-wt".to_string(),
+            Weighting::C => "C-wt".to_string(),
+            Weighting::Z => "Z-wt".to_string(),
+            Weighting::Iso226(phon) => format!("{}ph", phon),
+            Weighting::Curve(_) => "File".to_string(),
+        }
+    }
+
+    /// Name in titles, e.g. "K-weighted" or "75-phon weighted"
+    pub fn name(&self) -> String {
+        match self {
+            Weighting::K => "K-weighted".to_string(),
+            Weighting::C => "C-weighted".to_string(),
+            Weighting::Z => "Z-weighted".to_string(),
+            Weighting::Iso226(phon) => format!("{}-phon weighted", phon),
+            Weighting::Curve(curve) => {
+                let file = Path::new(&curve.name)
+                    .file_name()
+                    .map_or(curve.name.clone(), |name| name.to_string_lossy().into());
+                format!("weighted by {}", file)
+            }
+        }
+    }
+}
+
+/// Parse a `--weighting`: "k", "c", "z", or "iso226:<phon>"
+pub fn parse_weighting(s: &str) -> Result<Weighting, String> {
+    let s = s.trim().to_ascii_lowercase();
+    match s.as_str() {
+        "k" => return Ok(Weighting::K),
+        "c" => return Ok(Weighting::C),
+        "z" => return Ok(Weighting::Z),
+        _ => {}
+    }
+    let Some(phon) = s.strip_prefix("iso226:") else {
+        return Err(format!(
+            "invalid weighting '{}' (expected k, c, z, or iso226:<phon>)",
+            s
+        ));
+    };
+    match phon.trim().parse::<u8>() {
+        Ok(phon) if PHON_RANGE.contains(&phon) => Ok(Weighting::Iso226(phon)),
+        _ => Err(format!(
+            "invalid loudness level '{}' (expected {} to {} phon)",
+            phon,
+            PHON_RANGE.start(),
+            PHON_RANGE.end()
+        )),
+    }
+}
+
+/// Pole frequencies of the C-weighting curve in Hz (IEC 61672-1)
+const C_WEIGHT_LOW_HZ: f64 = 20.598997;
+const C_WEIGHT_HIGH_HZ: f64 = 12194.217;
+
+/// C-weighting gain at 1 kHz before normalization, in dB
+const C_WEIGHT_1KHZ_DB: f64 = -0.062;
+
+/// C-weighting power factor at `freq` (0 dB at 1 kHz)
+fn c_weight_power(freq: f64) -> f64 {
+    let f2 = freq * freq;
+    let high2 = C_WEIGHT_HIGH_HZ * C_WEIGHT_HIGH_HZ;
+    let gain = high2 * f2 / ((f2 + C_WEIGHT_LOW_HZ * C_WEIGHT_LOW_HZ) * (f2 + high2));
+    gain * gain / 10f64.powf(C_WEIGHT_1KHZ_DB / 10.0)
+}
+
+/// Power factor of the equal-loudness `contour` at `freq` (0 dB at 1 kHz): a frequency
+/// that needs N dB more level to sound as loud as 1 kHz is N dB down. The contour is held
+/// at its ends outside 20 Hz to 12.5 kHz.
+fn equal_loudness_power(contour: &[(f64, f64)], freq: f64) -> f64 {
+    10f64.powf((contour_spl(contour, 1000.0) - contour_spl(contour, freq)) / 10.0)
+}
+
+/// Per-bin power factors of `weighting` for an FFT of `fft_size` points
+pub(super) fn weight_table(weighting: &Weighting, fft_size: usize, sample_rate: u32) -> Vec<f64> {
+    let freq_per_bin = sample_rate as f64 / fft_size as f64;
+    let bins = 0..=fft_size / 2;
+    match weighting {
+        Weighting::K => create_k_weight_table(fft_size, sample_rate),
+        Weighting::C => bins
+            .map(|bin| c_weight_power(bin as f64 * freq_per_bin))
+            .collect(),
+        Weighting::Z => vec![1.0; fft_size / 2 + 1],
+        Weighting::Iso226(phon) => {
+            let contour = contour(*phon as f64);
+            bins.map(|bin| equal_loudness_power(&contour, bin as f64 * freq_per_bin))
+                .collect()
+        }
+        Weighting::Curve(curve) => bins
+            .map(|bin| 10f64.powf(curve.level_db(bin as f64 * freq_per_bin) / 10.0))
+            .collect(),
+    }
+}
+
+/// Gain in dB of `weighting` at the given frequency
+#[cfg(test)]
+pub(super) fn weighting_gain_db_for_test(weighting: &Weighting, freq: f64) -> f64 {
+    match weighting {
+        Weighting::K => super::kweight::k_weight_gain_db(freq, 48000),
+        Weighting::C => 10.0 * c_weight_power(freq).log10(),
+        Weighting::Z => 0.0,
+        Weighting::Iso226(phon) => {
+            10.0 * equal_loudness_power(&contour(*phon as f64), freq).log10()
+        }
+        Weighting::Curve(curve) => curve.level_db(freq),
+    }
+}
@@ -0,0 +1,366 @@
+//! Library entry point: band analysis of audio files and sample buffers
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+#[cfg(feature = "decode")]
+use crate::analysis::StatsAccumulator;
+use crate::analysis::{
+    AnalysisConfig, AnalysisContext, Band, DYNAMICS_DISPLAY_THRESHOLD_PCT, StatsResult,
+    active_samples, analyze_stats, mean_abs_deviation, nan_as_null, powers_to_percentages,
+    target_deviation_db,
+};
+use crate::audio::{AudioData, TARGET_SAMPLE_RATE, resample};
+#[cfg(feature = "decode")]
+use crate::audio::{AudioStream, LoadOptions, load_audio};
+
+/// Version of the field layout of saved results and `--format json` documents.
+/// Adding fields keeps it; renaming or removing fields bumps it.
+pub const RESULT_FORMAT: u32 = 1;
+
+/// Share and dynamics of one frequency band
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BandResult {
+    pub label: String,
+    pub low_hz: f32,
+    /// Upper edge in Hz, `None` for the open top band
+    pub high_hz: Option<f32>,
+    /// Share of the total power in percent
+    pub raw_pct: f64,
+    /// Share of the K-weighted (BS.1770) power in percent
+    pub k_pct: f64,
+    /// Standard errors of the shares, when the audio is long enough to estimate them
+    pub raw_pct_se: Option<f64>,
+    pub k_pct_se: Option<f64>,
+    /// Half-widths of the 95% confidence intervals of the shares
+    #[serde(default)]
+    pub raw_pct_ci: Option<f64>,
+    #[serde(default)]
+    pub k_pct_ci: Option<f64>,
+    /// Spread of the band's frame levels in dB, when the band holds enough energy
+    pub dynamics_db: Option<f64>,
+    /// Share of the band in the target curve and the Raw share's deviation from it in dB,
+    /// with a `target_curve` that reaches the band
+    #[serde(default)]
+    pub target_pct: Option<f64>,
+    #[serde(default)]
+    pub target_dev_db: Option<f64>,
+}
+
+/// Share of the Raw energy on one side of a frequency (a config `metrics` entry)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MetricResult {
+    /// The metric in `--metric` form, e.g. `below:100`
+    pub metric: String,
+    /// Share in percent, `None` without energy
+    pub raw_pct: Option<f64>,
+}
+
+/// Analysis of one input
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AnalysisResult {
+    /// Field layout version, [`RESULT_FORMAT`] for new results
+    #[serde(deserialize_with = "supported_format")]
+    pub format: u32,
+    /// Sample rate of the input, before resampling to 48 kHz
+    pub sample_rate: u32,
+    /// Channels of the input, before the mono downmix
+    pub channels: u16,
+    pub duration_secs: f64,
+    /// Seconds left after skipping pauses, with `active_only`
+    #[serde(default)]
+    pub active_secs: Option<f64>,
+    /// BS.1770 integrated loudness in LUFS (NaN when unknown, saved as null)
+    #[serde(with = "nan_as_null")]
+    pub integrated_lufs: f64,
+    pub bands: Vec<BandResult>,
+    /// Energy split of each of the config's `metrics`
+    #[serde(default)]
+    pub metrics: Vec<MetricResult>,
+    /// Name of the config's `target_curve` and the mean absolute deviation of the bands
+    /// from it in dB
+    #[serde(default)]
+    pub target_curve: Option<String>,
+    #[serde(default)]
+    pub target_mean_dev_db: Option<f64>,
+    /// Signs that decoding went wrong or the input gives a meaningless band table, e.g.
+    /// digital silence (not saved)
+    #[serde(skip)]
+    pub warnings: Vec<String>,
+}
+
+/// Band analyzer; keeps its FFT plan between inputs, so reuse it for batches
+pub struct Analyzer {
+    config: AnalysisConfig,
+    bands: Vec<Band>,
+    ctx: AnalysisContext,
+}
+
+impl Analyzer {
+    pub fn new(config: AnalysisConfig) -> Self {
+        Self {
+            bands: config.bands().to_vec(),
+            ctx: AnalysisContext::new(TARGET_SAMPLE_RATE, &config),
+            config,
+        }
+    }
+
+    /// Bands that results are reported in, lowest first
+    pub fn bands(&self) -> &[Band] {
+        &self.bands
+    }
+
+    /// Decode and analyze a WAV, AIFF, MP3, or FLAC file, or an http(s):// URL, a packet
+    /// at a time, so long inputs need not fit in memory. `active_only` needs the whole
+    /// input to find its program level, so it decodes the input first.
+    #[cfg(feature = "decode")]
+    pub fn analyze_file(&mut self, path: &str) -> Result<AnalysisResult, String> {
+        let options = LoadOptions {
+            max_duration: self.config.max_duration,
+            resampling: self.config.resampling(),
+            ..LoadOptions::default()
+        };
+        if self.config.active_only {
+            let (audio, warnings) = load_audio(path, &options)?;
+            return Ok(AnalysisResult {
+                warnings,
+                ..self.analyze_audio(&audio)
+            });
+        }
+        let mut stream = AudioStream::open(path, &options)?;
+        let expected = stream.expected_samples();
+        let mut accumulator = StatsAccumulator::new(&self.bands, &mut self.ctx, expected);
+        let decoded = stream.decode_resampled(|samples| accumulator.push(samples))?;
+        let stats = accumulator.finish();
+        Ok(AnalysisResult {
+            warnings: stream.warnings(),
+            ..self.result(
+                stats,
+                stream.sample_rate,
+                stream.channels,
+                decoded as f64 / TARGET_SAMPLE_RATE as f64,
+                None,
+            )
+        })
+    }
+
+    /// Analyze mono samples in -1.0..=1.0 at any sample rate
+    pub fn analyze_samples(
+        &mut self,
+        samples: &[f32],
+        sample_rate: u32,
+    ) -> Result<AnalysisResult, String> {
+        if sample_rate == 0 {
+            return Err("sample rate must be positive".to_string());
+        }
+        let len = self
+            .config
+            .max_duration
+            .map_or(samples.len(), |secs| (secs * sample_rate as f64) as usize)
+            .min(samples.len());
+        let samples = &samples[..len];
+
+        let audio = AudioData {
+            samples: if sample_rate == TARGET_SAMPLE_RATE {
+                samples.to_vec()
+            } else {
+                resample(
+                    samples,
+                    sample_rate,
+                    TARGET_SAMPLE_RATE,
+                    self.config.resampling(),
+                )?
+            },
+            sample_rate: TARGET_SAMPLE_RATE,
+            channels: 1,
+            original_sample_rate: sample_rate,
+        };
+        Ok(self.analyze_audio(&audio))
+    }
+
+    /// Analyze audio at the target sample rate, only its active regions with `active_only`
+    fn analyze_audio(&mut self, audio: &AudioData) -> AnalysisResult {
+        let duration_secs = audio.samples.len() as f64 / TARGET_SAMPLE_RATE as f64;
+        let active = self.config.active_only.then(|| AudioData {
+            samples: active_samples(&audio.samples, audio.sample_rate),
+            sample_rate: audio.sample_rate,
+            channels: audio.channels,
+            original_sample_rate: audio.original_sample_rate,
+        });
+        let analyzed = active.as_ref().unwrap_or(audio);
+        let stats = analyze_stats(analyzed, &self.bands, &mut self.ctx, |_| {});
+        self.result(
+            stats,
+            audio.original_sample_rate,
+            audio.channels,
+            duration_secs,
+            active.map(|a| a.samples.len() as f64 / TARGET_SAMPLE_RATE as f64),
+        )
+    }
+
+    /// Result of an input with this original sample rate, channel count, and duration,
+    /// and the seconds left with `active_only`
+    fn result(
+        &self,
+        stats: StatsResult,
+        sample_rate: u32,
+        channels: u16,
+        duration_secs: f64,
+        active_secs: Option<f64>,
+    ) -> AnalysisResult {
+        let excluded = self.config.excluded_bands();
+        let raw_pct = powers_to_percentages(&stats.raw_powers, excluded);
+        let k_pct = powers_to_percentages(&stats.k_powers, excluded);
+        let finite = |value: f64| value.is_finite().then_some(value);
+        let target = self
+            .config
+            .target_curve
+            .as_ref()
+            .map(|curve| curve.band_shares(&self.config));
+        let deviation = target
+            .as_ref()
+            .map(|target| target_deviation_db(&raw_pct, target));
+        let bands = self
+            .bands
+            .iter()
+            .enumerate()
+            .map(|(i, band)| BandResult {
+                label: band.label.to_string(),
+                low_hz: band.low_hz,
+                high_hz: (band.high_hz < f32::MAX / 2.0).then_some(band.high_hz),
+                raw_pct: raw_pct[i],
+                k_pct: k_pct[i],
+                raw_pct_se: finite(stats.raw_pct_se[i]),
+                k_pct_se: finite(stats.k_pct_se[i]),
+                raw_pct_ci: finite(stats.raw_pct_ci[i]),
+                k_pct_ci: finite(stats.k_pct_ci[i]),
+                dynamics_db: (raw_pct[i] >= DYNAMICS_DISPLAY_THRESHOLD_PCT)
+                    .then(|| finite(stats.dynamics[i]))
+                    .flatten(),
+                target_pct: target.as_ref().and_then(|t| finite(t[i])),
+                target_dev_db: deviation.as_ref().and_then(|d| finite(d[i])),
+            })
+            .collect();
+        let metrics = self
+            .config
+            .metrics
+            .iter()
+            .zip(&stats.metric_pct)
+            .map(|(metric, &pct)| MetricResult {
+                metric: metric.spec(),
+                raw_pct: finite(pct),
+            })
+            .collect();
+
+        AnalysisResult {
+            format: RESULT_FORMAT,
+            sample_rate,
+            channels,
+            duration_secs,
+            active_secs,
+            integrated_lufs: stats.integrated_lufs,
+            bands,
+            metrics,
+            target_curve: self.config.target_curve.as_ref().map(|c| c.name.clone()),
+            target_mean_dev_db: deviation
+                .as_deref()
+                .and_then(|d| finite(mean_abs_deviation(d))),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+/// Reject results saved by a newer bandstat, whose fields may mean something else
+fn supported_format<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    let format = u32::deserialize(deserializer)?;
+    check_format(format).map_err(serde::de::Error::custom)?;
+    Ok(format)
+}
+
+/// Whether a result with this field layout version can be read
+pub fn check_format(format: u32) -> Result<(), String> {
+    if format > RESULT_FORMAT {
+        return Err(format!(
+            "result format {} is newer than this bandstat supports ({})",
+            format, RESULT_FORMAT
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{parse_metric, parse_target_curve};
+
+    #[test]
+    fn test_analyze_samples_resamples_input() {
+        // A 100 Hz tone at 44.1 kHz lands entirely in BASS (60-120 Hz)
+        let samples: Vec<f32> = (0..44100 * 2)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * 100.0 * i as f32 / 44100.0).sin())
+            .collect();
+        let mut config = AnalysisConfig::default();
+        config.max_duration = Some(1.5);
+        let mut analyzer = Analyzer::new(config);
+        let result = analyzer.analyze_samples(&samples, 44100).unwrap();
+
+        assert_eq!(result.sample_rate, 44100);
+        assert!((result.duration_secs - 1.5).abs() < 0.01);
+        let bass = result.bands.iter().find(|b| b.label == "BASS").unwrap();
+        assert!(bass.raw_pct > 99.0, "BASS {}%", bass.raw_pct);
+        assert!(result.integrated_lufs.is_finite());
+        assert_eq!(result.bands.last().unwrap().high_hz, None);
+        assert!(analyzer.analyze_samples(&samples, 0).is_err());
+    }
+
+    #[test]
+    fn test_analyze_samples_applies_cli_settings() {
+        // One second of 1 kHz tone between two seconds of silence
+        let mut samples = vec![0.0f32; 48000 * 3];
+        for (i, sample) in samples[48000..96000].iter_mut().enumerate() {
+            *sample = 0.5 * (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / 48000.0).sin();
+        }
+        let mut config = AnalysisConfig::default();
+        config.active_only = true;
+        config.metrics = vec![parse_metric("below:500").unwrap()];
+        let curve = parse_target_curve("flat.csv", "20,0\n20000,0\n").unwrap();
+        config.target_curve = Some(std::sync::Arc::new(curve));
+        let result = Analyzer::new(config)
+            .analyze_samples(&samples, 48000)
+            .unwrap();
+
+        assert!((result.duration_secs - 3.0).abs() < 0.01);
+        assert!(result.active_secs.is_some_and(|secs| secs < 2.0));
+        assert_eq!(result.metrics.len(), 1);
+        assert_eq!(result.metrics[0].metric, "below:500");
+        assert!(result.metrics[0].raw_pct.is_some_and(|pct| pct < 1.0));
+        assert_eq!(result.target_curve.as_deref(), Some("flat.csv"));
+        assert!(result.target_mean_dev_db.is_some());
+        assert!(result.bands.iter().any(|b| b.target_pct.is_some()));
+
+        let plain = Analyzer::new(AnalysisConfig::default())
+            .analyze_samples(&samples, 48000)
+            .unwrap();
+        assert_eq!(plain.active_secs, None);
+        assert!(plain.metrics.is_empty() && plain.target_curve.is_none());
+    }
+
+    #[test]
+    fn test_result_round_trips_through_json() {
+        let samples: Vec<f32> = (0..48000)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / 48000.0).sin())
+            .collect();
+        let result = Analyzer::new(AnalysisConfig::default())
+            .analyze_samples(&samples, 48000)
+            .unwrap();
+
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["format"], RESULT_FORMAT);
+        assert!(json["bands"][0]["dynamics_db"].is_null());
+        let parsed: AnalysisResult = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(parsed, result);
+
+        let mut newer = json;
+        newer["format"] = serde_json::json!(RESULT_FORMAT + 1);
+        assert!(serde_json::from_value::<AnalysisResult>(newer).is_err());
+    }
+}
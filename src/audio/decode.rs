@@ -0,0 +1,924 @@
+//! Decoding of audio files, URLs, and followed recordings (`cli` feature)
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use symphonia::core::audio::{Channels, SampleBuffer};
+use symphonia::core::codecs::{Decoder, DecoderOptions};
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::{MediaSource, MediaSourceStream, ReadOnlySource};
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use super::ffmpeg::{TempWav, convert_to_wav};
+use super::highpass::HighPass;
+use super::{AudioData, Resampling, StreamResampler, TARGET_SAMPLE_RATE, resample};
+
+/// Options controlling how much audio is decoded and how
+#[derive(Clone, Debug, Default)]
+pub struct LoadOptions {
+    /// Drop the audio before this position in seconds
+    pub start: Option<f64>,
+    /// Stop decoding after this many seconds from `start` (required for endless streams)
+    pub max_duration: Option<f64>,
+    /// Keep reading a growing file until it has not grown for this long
+    pub follow: Option<Duration>,
+    /// High-pass the decoded audio at this cutoff in Hz (`--highpass`)
+    pub highpass_hz: Option<f64>,
+    /// Decode inputs symphonia can't read through ffmpeg (`--ffmpeg-fallback`)
+    pub ffmpeg_fallback: bool,
+    /// Refuse to hold more than this many MB of decoded audio (`--max-memory`)
+    pub max_memory_mb: Option<usize>,
+    /// Read at most this many MB of an HTTP(S) input (`DEFAULT_MAX_DOWNLOAD_MB` if unset)
+    pub max_download_mb: Option<u64>,
+    pub resampling: Resampling,
+}
+
+/// Channel counts above this (22.2 audio uses 24) more likely come from a corrupt header
+const MAX_PLAUSIBLE_CHANNELS: u16 = 24;
+
+/// Mean sample value from which the DC offset swamps the rest of the spectrum (-20 dBFS)
+const EXTREME_DC_OFFSET: f64 = 0.1;
+
+/// How often a followed file is checked for new data
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Bytes at the start of a followed file searched for its WAV chunk headers
+const FOLLOW_HEADER_BYTES: u64 = 64 * 1024;
+
+/// Set by the SIGINT handler installed for `--follow`
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Smallest `--max-memory`; below this the streaming buffers alone would not fit
+const MIN_MAX_MEMORY_MB: usize = 16;
+
+/// Check a `--max-memory` limit in MB
+pub fn check_max_memory(mb: usize) -> Result<(), String> {
+    if mb < MIN_MAX_MEMORY_MB {
+        return Err(format!(
+            "--max-memory must be at least {} MB",
+            MIN_MAX_MEMORY_MB
+        ));
+    }
+    Ok(())
+}
+
+/// Default `--max-download`: HTTP(S) inputs larger than this many MB are refused
+pub const DEFAULT_MAX_DOWNLOAD_MB: u64 = 2048;
+
+/// Check a `--max-download` limit in MB
+pub fn check_max_download(mb: u64) -> Result<(), String> {
+    if mb == 0 {
+        return Err("--max-download must be at least 1 MB".to_string());
+    }
+    Ok(())
+}
+
+/// Parse a position in the audio given as seconds, `mm:ss`, or `hh:mm:ss` (the seconds
+/// may have a fraction), e.g. `--start 1:30`
+pub fn parse_position(s: &str) -> Result<f64, String> {
+    let invalid = || {
+        format!(
+            "invalid time '{}' (expected seconds, mm:ss, or hh:mm:ss)",
+            s
+        )
+    };
+    let parts: Vec<&str> = s.trim().split(':').collect();
+    let Some((seconds, larger)) = parts.split_last().filter(|_| parts.len() <= 3) else {
+        return Err(invalid());
+    };
+    let seconds: f64 = seconds
+        .parse()
+        .ok()
+        .filter(|secs: &f64| secs.is_finite() && *secs >= 0.0)
+        .ok_or_else(invalid)?;
+    if !larger.is_empty() && seconds >= 60.0 {
+        return Err(invalid());
+    }
+    let mut total = 0.0;
+    for (i, part) in larger.iter().enumerate() {
+        let value: u32 = part.parse().map_err(|_| invalid())?;
+        // Minutes after hours stay below an hour
+        if i > 0 && value >= 60 {
+            return Err(invalid());
+        }
+        total = total * 60.0 + value as f64;
+    }
+    Ok(total * 60.0 + seconds)
+}
+
+/// Whether the input refers to an HTTP(S) URL rather than a local file
+pub fn is_url(input: &str) -> bool {
+    input.starts_with("http://") || input.starts_with("https://")
+}
+
+/// Adapts a `Send`-only reader (e.g. an HTTP body) to the `Sync` bound symphonia requires
+struct SyncReader<R>(Mutex<R>);
+
+impl<R: Read> Read for SyncReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0
+            .get_mut()
+            .map_err(|_| std::io::Error::other("reader poisoned"))?
+            .read(buf)
+    }
+}
+
+/// Bytes of an HTTP(S) input read so far, shared with the progress display
+pub struct Download {
+    read: AtomicU64,
+    /// Content-Length of the response, if the server sent one
+    total: Option<u64>,
+}
+
+impl Download {
+    /// Percentage of the response read, when its length is known
+    pub fn percent(&self) -> Option<u8> {
+        let total = self.total.filter(|&total| total > 0)?;
+        let read = self.read.load(Ordering::Relaxed);
+        Some((read * 100 / total).min(100) as u8)
+    }
+}
+
+/// HTTP body that counts the bytes read and fails once more than `--max-download` came in
+struct LimitedBody<R> {
+    reader: R,
+    download: Arc<Download>,
+    limit_mb: u64,
+}
+
+impl<R: Read> Read for LimitedBody<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        let read = self.download.read.fetch_add(n as u64, Ordering::Relaxed) + n as u64;
+        if read > self.limit_mb * 1024 * 1024 {
+            return Err(std::io::Error::other(format!(
+                "stopped after --max-download {} MB",
+                self.limit_mb
+            )));
+        }
+        Ok(n)
+    }
+}
+
+/// Local file that is still being written: reads wait for new data instead of
+/// reporting EOF until the file stops growing or Ctrl-C is pressed
+struct FollowedFile {
+    file: File,
+    idle_timeout: Duration,
+    last_growth: Instant,
+    /// Read position in the file
+    position: u64,
+    /// Offsets of WAV size fields the recorder left at 0, read as unknown lengths
+    placeholder_sizes: Vec<u64>,
+}
+
+impl FollowedFile {
+    fn new(mut file: File, idle_timeout: Duration) -> std::io::Result<Self> {
+        let mut header = Vec::new();
+        (&mut file)
+            .take(FOLLOW_HEADER_BYTES)
+            .read_to_end(&mut header)?;
+        file.seek(SeekFrom::Start(0))?;
+
+        // Ctrl-C ends the follow like EOF so the summary is still printed
+        static HANDLER: std::sync::Once = std::sync::Once::new();
+        HANDLER.call_once(|| {
+            // SAFETY: the handler only stores to an atomic, which is async-signal-safe
+            let _ = unsafe {
+                signal_hook_registry::register(libc::SIGINT, || {
+                    INTERRUPTED.store(true, Ordering::SeqCst)
+                })
+            };
+        });
+
+        Ok(Self {
+            file,
+            idle_timeout,
+            last_growth: Instant::now(),
+            position: 0,
+            placeholder_sizes: placeholder_sizes(&header),
+        })
+    }
+}
+
+/// Offsets of the RIFF and data chunk sizes of a WAV header that are still 0, as some
+/// recorders write them until they finish; symphonia reads a 0 data size as no audio
+fn placeholder_sizes(header: &[u8]) -> Vec<u64> {
+    let field = |offset: usize| {
+        header
+            .get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    if header.get(..4) != Some(b"RIFF") || header.get(8..12) != Some(b"WAVE") {
+        return Vec::new();
+    }
+
+    let mut offsets = Vec::new();
+    if field(4) == Some(0) {
+        offsets.push(4);
+    }
+    let mut chunk = 12;
+    while let Some(size) = field(chunk + 4) {
+        if &header[chunk..chunk + 4] == b"data" {
+            if size == 0 {
+                offsets.push(chunk as u64 + 4);
+            }
+            break;
+        }
+        // Chunks are padded to an even length
+        chunk += 8 + size as usize + (size & 1) as usize;
+    }
+    offsets
+}
+
+impl Read for FollowedFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            if INTERRUPTED.load(Ordering::SeqCst) {
+                return Ok(0);
+            }
+            let n = self.file.read(buf)?;
+            if n > 0 || buf.is_empty() {
+                // Unknown lengths (all bits set) make symphonia read the data chunk to EOF
+                for &offset in &self.placeholder_sizes {
+                    for byte in offset..offset + 4 {
+                        if let Some(i) = byte.checked_sub(self.position)
+                            && i < n as u64
+                        {
+                            buf[i as usize] = 0xFF;
+                        }
+                    }
+                }
+                self.position += n as u64;
+                self.last_growth = Instant::now();
+                return Ok(n);
+            }
+            if self.last_growth.elapsed() >= self.idle_timeout {
+                return Ok(0);
+            }
+            std::thread::sleep(FOLLOW_POLL_INTERVAL);
+        }
+    }
+}
+
+/// Open a local file or HTTP(S) stream, filling in format hints where available and, for
+/// HTTP(S), the download, counted as it is read
+fn open_source(
+    input: &str,
+    hint: &mut Hint,
+    download: &mut Option<Arc<Download>>,
+    options: &LoadOptions,
+) -> Result<Box<dyn MediaSource>, String> {
+    // Extension hint from the path (query string stripped for URLs)
+    let path = input.split(['?', '#']).next().unwrap_or(input);
+    if let Some(ext) = std::path::Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+    {
+        if is_url(input) && ext.eq_ignore_ascii_case("m3u8") {
+            return Err(format!("{}: HLS playlists are not supported", input));
+        }
+        hint.with_extension(ext);
+    }
+
+    if !is_url(input) {
+        let file = File::open(input).map_err(|e| format!("{}: {}", input, e))?;
+        return Ok(match options.follow {
+            Some(idle) => Box::new(ReadOnlySource::new(
+                FollowedFile::new(file, idle).map_err(|e| format!("{}: {}", input, e))?,
+            )),
+            None => Box::new(file),
+        });
+    }
+
+    let response = ureq::get(input)
+        .call()
+        .map_err(|e| format!("{}: request failed ({})", input, e))?;
+    if let Some(mime) = response
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+    {
+        hint.mime_type(mime.split(';').next().unwrap_or(mime).trim());
+    }
+    let total = response
+        .headers()
+        .get("content-length")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    let limit_mb = options.max_download_mb.unwrap_or(DEFAULT_MAX_DOWNLOAD_MB);
+    // With --duration only the start is read, so a long file may still fit
+    if let Some(total) = total
+        && options.max_duration.is_none()
+        && total > limit_mb * 1024 * 1024
+    {
+        return Err(format!(
+            "{}: {} MB is over --max-download {} MB",
+            input,
+            total.div_ceil(1024 * 1024),
+            limit_mb
+        ));
+    }
+    let counted = Arc::new(Download {
+        read: AtomicU64::new(0),
+        total,
+    });
+    *download = Some(Arc::clone(&counted));
+    let reader = LimitedBody {
+        reader: response.into_body().into_reader(),
+        download: counted,
+        limit_mb,
+    };
+    Ok(Box::new(ReadOnlySource::new(SyncReader(Mutex::new(
+        reader,
+    )))))
+}
+
+/// Incremental decoder producing mono samples at the source sample rate
+pub struct AudioStream {
+    name: String,
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Position of the LFE channel among the interleaved channels, if the layout has one
+    pub(crate) lfe_channel: Option<usize>,
+    /// Total frame count reported by the container, if known
+    pub(crate) n_frames: Option<usize>,
+    /// Frames before `start`, dropped once decoded
+    start_frame: usize,
+    /// Frames still to drop before `start`
+    skip: usize,
+    /// Samples left before `max_duration` is reached
+    remaining: Option<usize>,
+    /// NaN or infinite samples decoded so far (replaced with silence)
+    non_finite: usize,
+    /// Packets that failed to decode and were skipped, with the first error
+    decode_errors: usize,
+    first_decode_error: Option<String>,
+    /// Totals of the mono samples decoded so far, for `decode_warnings`
+    totals: SampleTotals,
+    /// `--highpass` filter of the mono mix
+    highpass: Option<HighPass>,
+    /// `--highpass` filters of the mains and the LFE `read_split` splits out
+    split_highpass: Option<[HighPass; 2]>,
+    resampling: Resampling,
+    /// Notes on how the input was opened, reported with the decode warnings
+    notes: Vec<String>,
+    /// Download of an HTTP(S) input
+    download: Option<Arc<Download>>,
+    /// ffmpeg's conversion of an input symphonia couldn't read (`--ffmpeg-fallback`),
+    /// declared last so the reader closes it before it is removed
+    _converted: Option<TempWav>,
+}
+
+impl AudioStream {
+    pub fn open(filename: &str, options: &LoadOptions) -> Result<Self, String> {
+        let mut hint = Hint::new();
+        let mut download = None;
+        let source = open_source(filename, &mut hint, &mut download, options)?;
+        match Self::decode_source(filename, source, &hint, options) {
+            Ok(mut stream) => {
+                stream.download = download;
+                Ok(stream)
+            }
+            // Growing files and endless streams would never finish converting
+            Err(e)
+                if options.ffmpeg_fallback
+                    && options.follow.is_none()
+                    && (!is_url(filename) || options.max_duration.is_some()) =>
+            {
+                let end = options
+                    .max_duration
+                    .map(|secs| secs + options.start.unwrap_or(0.0));
+                let converted = convert_to_wav(filename, end)
+                    .map_err(|ffmpeg_error| format!("{}; {}", e, ffmpeg_error))?;
+                let file = File::open(converted.path())
+                    .map_err(|e| format!("{}: {}", converted.path().display(), e))?;
+                let mut hint = Hint::new();
+                hint.with_extension("wav");
+                let mut stream = Self::decode_source(filename, Box::new(file), &hint, options)?;
+                stream.notes.push(format!(
+                    "{}: not supported natively, decoded through ffmpeg (--ffmpeg-fallback)",
+                    filename
+                ));
+                stream._converted = Some(converted);
+                Ok(stream)
+            }
+            result => result,
+        }
+    }
+
+    /// Probe the container and set up the decoder of its first audio track
+    fn decode_source(
+        filename: &str,
+        source: Box<dyn MediaSource>,
+        hint: &Hint,
+        options: &LoadOptions,
+    ) -> Result<Self, String> {
+        let mss = MediaSourceStream::new(source, Default::default());
+
+        let probed = symphonia::default::get_probe()
+            .format(
+                hint,
+                mss,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )
+            .map_err(|e| format!("{}: unsupported format ({})", filename, e))?;
+
+        let format = probed.format;
+
+        let track = format
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != symphonia::core::codecs::CODEC_TYPE_NULL)
+            .ok_or_else(|| format!("{}: no audio track found", filename))?;
+
+        let sample_rate = track
+            .codec_params
+            .sample_rate
+            .ok_or_else(|| format!("{}: unknown sample rate", filename))?;
+        let layout = track
+            .codec_params
+            .channels
+            .ok_or_else(|| format!("{}: unknown channel count", filename))?;
+        let channels = layout.count() as u16;
+        // Interleaved channels follow the order of the layout bits (L, R, C, LFE, ... in WAV)
+        let lfe_channel = layout
+            .contains(Channels::LFE1)
+            .then(|| (layout.bits() & (Channels::LFE1.bits() - 1)).count_ones() as usize);
+        if channels == 0 {
+            return Err(format!("{}: no audio channels", filename));
+        }
+
+        let decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &DecoderOptions::default())
+            .map_err(|e| format!("{}: failed to create decoder ({})", filename, e))?;
+
+        let track_id = track.id;
+        let n_frames = track.codec_params.n_frames.map(|n| n as usize);
+        let start_frame = options
+            .start
+            .map_or(0, |secs| (secs * sample_rate as f64).round() as usize);
+        let remaining = options
+            .max_duration
+            .map(|secs| (secs * sample_rate as f64) as usize);
+        let highpass = options
+            .highpass_hz
+            .map(|hz| HighPass::new(hz, sample_rate))
+            .transpose()
+            .map_err(|e| format!("{}: {}", filename, e))?;
+
+        Ok(Self {
+            name: filename.to_string(),
+            format,
+            decoder,
+            track_id,
+            sample_rate,
+            channels,
+            lfe_channel,
+            n_frames,
+            start_frame,
+            skip: start_frame,
+            remaining,
+            non_finite: 0,
+            decode_errors: 0,
+            first_decode_error: None,
+            totals: SampleTotals::default(),
+            split_highpass: highpass.clone().map(|filter| [filter.clone(), filter]),
+            highpass,
+            resampling: options.resampling,
+            notes: Vec::new(),
+            download: None,
+            _converted: None,
+        })
+    }
+
+    /// Decode the next packet and append its mono samples to `out`
+    ///
+    /// Returns `false` once the stream has ended or `max_duration` has been reached.
+    pub fn read_into(&mut self, out: &mut Vec<f32>) -> Result<bool, String> {
+        self.read_split(out, None)
+    }
+
+    /// Like `read_into`, also appending the sum of the main channels and the LFE channel
+    /// on their own to `split` (when the layout has an LFE channel)
+    fn read_split(
+        &mut self,
+        out: &mut Vec<f32>,
+        mut split: Option<&mut LfeAudio>,
+    ) -> Result<bool, String> {
+        if self.remaining == Some(0) {
+            return Ok(false);
+        }
+
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(p) => p,
+                Err(symphonia::core::errors::Error::IoError(ref e))
+                    if e.kind() == std::io::ErrorKind::UnexpectedEof =>
+                {
+                    if self.skip > 0 {
+                        let rate = self.sample_rate as f64;
+                        return Err(format!(
+                            "{}: --start {:.1} s is past the end of the audio ({:.1} s)",
+                            self.name,
+                            self.start_frame as f64 / rate,
+                            (self.start_frame - self.skip) as f64 / rate
+                        ));
+                    }
+                    return Ok(false);
+                }
+                Err(e) => return Err(format!("{}: error reading packet ({})", self.name, e)),
+            };
+
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(d) => d,
+                Err(e) => {
+                    self.decode_errors += 1;
+                    self.first_decode_error.get_or_insert_with(|| e.to_string());
+                    continue;
+                }
+            };
+
+            let spec = *decoded.spec();
+            let num_channels = spec.channels.count();
+
+            let mut sample_buf = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+            sample_buf.copy_interleaved_ref(decoded);
+
+            let start = out.len();
+            for chunk in sample_buf.samples().chunks(num_channels) {
+                // A corrupt frame must not turn the whole analysis into NaN
+                let mut sum = 0.0f32;
+                for &sample in chunk {
+                    if sample.is_finite() {
+                        sum += sample;
+                    } else {
+                        self.non_finite += 1;
+                    }
+                }
+                out.push(sum / num_channels as f32);
+                if let (Some(split), Some(lfe)) = (split.as_deref_mut(), self.lfe_channel) {
+                    let lfe = chunk.get(lfe).copied().filter(|s| s.is_finite());
+                    let lfe = lfe.unwrap_or(0.0);
+                    split.mains.push(sum - lfe);
+                    split.lfe.push(lfe);
+                }
+            }
+
+            // Audio before --start is decoded only to be dropped
+            if self.skip > 0 {
+                let dropped = (out.len() - start).min(self.skip);
+                out.drain(start..start + dropped);
+                if let Some(split) = split.as_deref_mut() {
+                    split.mains.drain(start..start + dropped);
+                    split.lfe.drain(start..start + dropped);
+                }
+                self.skip -= dropped;
+                if out.len() == start {
+                    continue;
+                }
+            }
+
+            if let Some(remaining) = self.remaining.as_mut() {
+                let added = (out.len() - start).min(*remaining);
+                out.truncate(start + added);
+                if let Some(split) = split.as_deref_mut() {
+                    split.mains.truncate(start + added);
+                    split.lfe.truncate(start + added);
+                }
+                *remaining -= added;
+            }
+            if let Some(highpass) = self.highpass.as_mut() {
+                highpass.process(&mut out[start..]);
+            }
+            if let (Some(split), Some([mains, lfe])) =
+                (split.as_deref_mut(), self.split_highpass.as_mut())
+            {
+                mains.process(&mut split.mains[start..]);
+                lfe.process(&mut split.lfe[start..]);
+            }
+            self.totals.add(&out[start..]);
+
+            return Ok(true);
+        }
+    }
+
+    /// Samples `decode_resampled` will produce, when the container tells the length
+    pub fn expected_samples(&self) -> Option<usize> {
+        let frames = self.expected_frames()?;
+        Some((frames as f64 * TARGET_SAMPLE_RATE as f64 / self.sample_rate as f64) as usize)
+    }
+
+    /// Download of an HTTP(S) input of known length, for progress where the container
+    /// doesn't tell how many samples to expect (none with `--duration`, which stops early)
+    pub fn download(&self) -> Option<Arc<Download>> {
+        self.download
+            .clone()
+            .filter(|download| download.total.is_some() && self.remaining.is_none())
+    }
+
+    /// Mono samples left to decode at the stream's own rate, when the container tells
+    fn expected_frames(&self) -> Option<usize> {
+        self.n_frames
+            .map(|n| n.saturating_sub(self.start_frame))
+            .map(|n| self.remaining.map_or(n, |max| n.min(max)))
+    }
+
+    /// Decode the rest of the stream at the target sample rate, passing the samples to
+    /// `on_samples` a packet (or resampler chunk) at a time. Returns how many were passed.
+    pub fn decode_resampled<F>(&mut self, mut on_samples: F) -> Result<usize, String>
+    where
+        F: FnMut(&[f32]),
+    {
+        let mut resampler = (self.sample_rate != TARGET_SAMPLE_RATE)
+            .then(|| StreamResampler::new(self.sample_rate, TARGET_SAMPLE_RATE, self.resampling))
+            .transpose()?;
+        let mut total = 0;
+        let mut emit = |samples: &[f32]| {
+            total += samples.len();
+            on_samples(samples);
+        };
+
+        let mut raw = Vec::new();
+        loop {
+            raw.clear();
+            let more = self.read_into(&mut raw)?;
+            match resampler.as_mut() {
+                Some(r) => emit(&r.process(&raw)?),
+                None => emit(&raw),
+            }
+            if !more {
+                if let Some(r) = resampler {
+                    emit(&r.finish()?);
+                }
+                return Ok(total);
+            }
+        }
+    }
+
+    /// What opening and decoding the stream so far revealed (see `decode_warnings`), each
+    /// starting with the input's name
+    pub fn warnings(&self) -> Vec<String> {
+        let skipped = self.first_decode_error.as_ref().map(|e| {
+            format!(
+                "skipped {} packet(s) that failed to decode ({})",
+                self.decode_errors, e
+            )
+        });
+        let decoded = decode_warnings(&self.totals, self.channels, self.non_finite);
+        self.notes
+            .iter()
+            .cloned()
+            .chain(
+                skipped
+                    .iter()
+                    .chain(&decoded)
+                    .map(|w| format!("{}: {}", self.name, w)),
+            )
+            .collect()
+    }
+}
+
+/// Running totals of decoded mono samples
+#[derive(Default)]
+struct SampleTotals {
+    count: usize,
+    sum: f64,
+    /// Whether any sample is not exactly zero
+    audible: bool,
+}
+
+impl SampleTotals {
+    fn add(&mut self, samples: &[f32]) {
+        self.count += samples.len();
+        for &sample in samples {
+            self.sum += sample as f64;
+            self.audible |= sample != 0.0;
+        }
+    }
+}
+
+/// Decode a whole file at the target sample rate, with the warnings of decoding it (see
+/// `AudioStream::warnings`)
+pub fn load_audio(
+    filename: &str,
+    options: &LoadOptions,
+) -> Result<(AudioData, Vec<String>), String> {
+    load(filename, options, false).map(|(audio, _, warnings)| (audio, warnings))
+}
+
+/// Main channels and LFE of a surround file, at the target sample rate
+pub struct LfeAudio {
+    /// Position of the LFE among the file's channels (0-based)
+    pub channel: usize,
+    /// Sum of all channels but the LFE, as bass management feeds them to the subwoofer
+    pub mains: Vec<f32>,
+    pub lfe: Vec<f32>,
+}
+
+/// Load a file like `load_audio`, also splitting out its LFE channel (`--lfe`)
+pub fn load_audio_with_lfe(
+    filename: &str,
+    options: &LoadOptions,
+) -> Result<(AudioData, LfeAudio, Vec<String>), String> {
+    let (audio, lfe, warnings) = load(filename, options, true)?;
+    let lfe = lfe.ok_or_else(|| {
+        format!(
+            "{}: no LFE channel ({} channels; --lfe needs a layout such as 5.1)",
+            filename, audio.channels
+        )
+    })?;
+    Ok((audio, lfe, warnings))
+}
+
+fn load(
+    filename: &str,
+    options: &LoadOptions,
+    split_lfe: bool,
+) -> Result<(AudioData, Option<LfeAudio>, Vec<String>), String> {
+    let mut stream = AudioStream::open(filename, options)?;
+    let sample_rate = stream.sample_rate;
+    let fits_budget = |samples: usize| match options.max_memory_mb {
+        Some(max) if load_bytes(samples, sample_rate, split_lfe) > max << 20 => Err(format!(
+            "{}: the whole decoded file needs about {} MB, over --max-memory {} MB (loudness charts, --lfe, --align, and --active-only hold it in memory)",
+            filename,
+            load_bytes(samples, sample_rate, split_lfe).div_ceil(1 << 20),
+            max
+        )),
+        _ => Ok(()),
+    };
+    fits_budget(stream.expected_frames().unwrap_or(0))?;
+
+    let estimated_samples = stream.n_frames.unwrap_or(sample_rate as usize * 60); // Default: 1 minute estimate
+    let estimated_samples = match stream.remaining {
+        Some(max) => estimated_samples.min(max),
+        None => estimated_samples,
+    };
+    let mut samples: Vec<f32> = Vec::with_capacity(estimated_samples);
+    let mut split = stream
+        .lfe_channel
+        .filter(|_| split_lfe)
+        .map(|channel| LfeAudio {
+            channel,
+            mains: Vec::with_capacity(estimated_samples),
+            lfe: Vec::with_capacity(estimated_samples),
+        });
+
+    // The length may be unknown up front (e.g. for URLs)
+    while stream.read_split(&mut samples, split.as_mut())? {
+        fits_budget(samples.len())?;
+    }
+    // Resample to target sample rate if needed
+    let to_target = |samples: Vec<f32>| {
+        if sample_rate != TARGET_SAMPLE_RATE {
+            resample(
+                &samples,
+                sample_rate,
+                TARGET_SAMPLE_RATE,
+                options.resampling,
+            )
+        } else {
+            Ok(samples)
+        }
+    };
+    let split = match split {
+        Some(split) => Some(LfeAudio {
+            mains: to_target(split.mains)?,
+            lfe: to_target(split.lfe)?,
+            ..split
+        }),
+        None => None,
+    };
+
+    let audio = AudioData {
+        samples: to_target(samples)?,
+        sample_rate: TARGET_SAMPLE_RATE,
+        channels: stream.channels,
+        original_sample_rate: sample_rate,
+    };
+    Ok((audio, split, stream.warnings()))
+}
+
+/// Bytes `load` holds at its peak for this many mono samples at `sample_rate`: the
+/// downmix (and the LFE split), converted to the target rate one buffer at a time
+fn load_bytes(samples: usize, sample_rate: u32, split_lfe: bool) -> usize {
+    let buffers = if split_lfe { 3 } else { 1 };
+    let peak = if sample_rate != TARGET_SAMPLE_RATE {
+        let resampled = (samples as f64 * TARGET_SAMPLE_RATE as f64 / sample_rate as f64) as usize;
+        (buffers * samples + resampled).max(samples + buffers * resampled)
+    } else {
+        buffers * samples
+    };
+    peak * std::mem::size_of::<f32>()
+}
+
+/// Signs that decoding went wrong or the input will give a meaningless band table
+fn decode_warnings(totals: &SampleTotals, channels: u16, non_finite: usize) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if channels > MAX_PLAUSIBLE_CHANNELS {
+        warnings.push(format!(
+            "{} channels; the file header may be corrupt",
+            channels
+        ));
+    }
+    if non_finite > 0 {
+        warnings.push(format!(
+            "{} NaN or infinite samples were replaced with silence",
+            non_finite
+        ));
+    }
+    if totals.count > 0 && !totals.audible {
+        warnings.push("audio is digital silence, so every band is empty".to_string());
+    } else if totals.count > 0 {
+        let mean = totals.sum / totals.count as f64;
+        if mean.abs() >= EXTREME_DC_OFFSET {
+            warnings.push(format!(
+                "DC offset of {:.1} dBFS will dominate the DC band",
+                20.0 * mean.abs().log10()
+            ));
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    /// Generate a sine wave at the given frequency
+    fn generate_sine(freq: f32, sample_rate: u32, duration_secs: f32) -> Vec<f32> {
+        let num_samples = (sample_rate as f32 * duration_secs) as usize;
+        (0..num_samples)
+            .map(|i| (2.0 * PI * freq * i as f32 / sample_rate as f32).sin())
+            .collect()
+    }
+
+    /// Totals of samples decoded in two packets
+    fn totals(samples: &[f32]) -> SampleTotals {
+        let mut totals = SampleTotals::default();
+        let (first, second) = samples.split_at(samples.len() / 2);
+        totals.add(first);
+        totals.add(second);
+        totals
+    }
+
+    #[test]
+    fn test_decode_warnings() {
+        let sine = generate_sine(440.0, 48000, 0.1);
+        assert!(decode_warnings(&totals(&sine), 2, 0).is_empty());
+        assert!(decode_warnings(&totals(&[]), 2, 0).is_empty());
+
+        let silence = vec![0.0; 4800];
+        let warnings = decode_warnings(&totals(&silence), 2, 3);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].starts_with("3 NaN"));
+        assert!(warnings[1].contains("silence"));
+
+        let offset: Vec<f32> = sine.iter().map(|s| 0.1 * s + 0.5).collect();
+        let warnings = decode_warnings(&totals(&offset), 64, 0);
+        assert!(warnings[0].starts_with("64 channels"));
+        assert!(warnings[1].contains("-6.0 dBFS"));
+    }
+
+    #[test]
+    fn test_placeholder_sizes() {
+        let mut header = b"RIFF\0\0\0\0WAVE".to_vec();
+        header.extend(b"fmt \x10\0\0\0");
+        header.extend([0; 16]);
+        header.extend(b"LIST\x03\0\0\0abc\0");
+        header.extend(b"data\0\0\0\0");
+        assert_eq!(placeholder_sizes(&header), vec![4, 52]);
+
+        // Finished sizes and other formats are read as they are
+        header[4] = 1;
+        header[52] = 1;
+        assert!(placeholder_sizes(&header).is_empty());
+        assert!(placeholder_sizes(b"fLaC\0\0\0\0").is_empty());
+    }
+
+    #[test]
+    fn test_parse_position() {
+        assert_eq!(parse_position("90").unwrap(), 90.0);
+        assert_eq!(parse_position("1:30").unwrap(), 90.0);
+        assert_eq!(parse_position("0:07.5").unwrap(), 7.5);
+        assert_eq!(parse_position("1:02:03").unwrap(), 3723.0);
+        assert_eq!(parse_position("95:00").unwrap(), 5700.0);
+        for invalid in [
+            "", "-5", "1:75", "1:60:00", "1:2:3:4", "a:10", "inf", "1:-5",
+        ] {
+            assert!(parse_position(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! WAV export of generated and rendered audio (`cli` feature)
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// Sample format of written WAV files
+#[derive(Clone, Copy, Debug, Default, PartialEq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// 16-bit PCM with TPDF dither
+    #[default]
+    Pcm16,
+    /// 24-bit PCM with TPDF dither
+    Pcm24,
+    /// 32-bit IEEE float, undithered and unclipped
+    Float32,
+}
+
+impl ExportFormat {
+    fn bits(self) -> u16 {
+        match self {
+            ExportFormat::Pcm16 => 16,
+            ExportFormat::Pcm24 => 24,
+            ExportFormat::Float32 => 32,
+        }
+    }
+}
+
+/// Write mono samples as a WAV file in the given (`--export-format`) sample format
+pub fn write_wav(
+    path: &str,
+    samples: &[f32],
+    sample_rate: u32,
+    format: ExportFormat,
+) -> Result<(), String> {
+    let write = || -> std::io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        write_samples(&mut writer, samples, sample_rate, format)?;
+        writer.flush()
+    };
+    write().map_err(|e| format!("{}: {}", path, e))
+}
+
+fn write_samples(
+    writer: &mut impl Write,
+    samples: &[f32],
+    sample_rate: u32,
+    format: ExportFormat,
+) -> std::io::Result<()> {
+    let bytes_per_sample = format.bits() as u32 / 8;
+    let data_size = samples.len() as u32 * bytes_per_sample;
+    // Float files carry the extension size field and a fact chunk, as WAVE_FORMAT_IEEE_FLOAT requires
+    let float = format == ExportFormat::Float32;
+    let (fmt_size, fact_size) = if float { (18, 12) } else { (16, 0) };
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(4 + (8 + fmt_size) + fact_size + 8 + data_size).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&fmt_size.to_le_bytes())?;
+    writer.write_all(&(if float { 3u16 } else { 1u16 }).to_le_bytes())?; // PCM or IEEE float
+    writer.write_all(&1u16.to_le_bytes())?; // mono
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&(sample_rate * bytes_per_sample).to_le_bytes())?; // byte rate
+    writer.write_all(&(bytes_per_sample as u16).to_le_bytes())?; // block align
+    writer.write_all(&format.bits().to_le_bytes())?;
+    if float {
+        writer.write_all(&0u16.to_le_bytes())?; // no extension
+        writer.write_all(b"fact")?;
+        writer.write_all(&4u32.to_le_bytes())?;
+        writer.write_all(&(samples.len() as u32).to_le_bytes())?;
+    }
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())?;
+
+    let mut dither = Dither::new();
+    for &sample in samples {
+        match format {
+            ExportFormat::Pcm16 => {
+                let value = dither.quantize(sample, 32767.0, -32768.0) as i16;
+                writer.write_all(&value.to_le_bytes())?;
+            }
+            ExportFormat::Pcm24 => {
+                let value = dither.quantize(sample, 8388607.0, -8388608.0) as i32;
+                writer.write_all(&value.to_le_bytes()[..3])?;
+            }
+            ExportFormat::Float32 => writer.write_all(&sample.to_le_bytes())?,
+        }
+    }
+    Ok(())
+}
+
+/// TPDF dither: the sum of two uniform values of one LSB each, so quantization error is
+/// independent of the signal. Seeded the same way every time so exports are reproducible.
+struct Dither {
+    state: u64,
+}
+
+impl Dither {
+    fn new() -> Self {
+        Dither {
+            state: 0x9E37_79B9_7F4A_7C15,
+        }
+    }
+
+    /// Uniform value in [-0.5, 0.5)
+    fn next_uniform(&mut self) -> f64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state >> 11) as f64 / (1u64 << 53) as f64 - 0.5
+    }
+
+    /// Scale to integer steps, add dither, round, and clip to the format's range
+    fn quantize(&mut self, sample: f32, max: f64, min: f64) -> f64 {
+        let dither = self.next_uniform() + self.next_uniform();
+        (sample as f64 * max + dither).round().clamp(min, max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header_field(bytes: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+    }
+
+    #[test]
+    fn test_export_formats() {
+        let samples = [0.0f32, 0.5, -0.5, 1.5];
+        for (format, tag, bits) in [
+            (ExportFormat::Pcm16, 1, 16),
+            (ExportFormat::Pcm24, 1, 24),
+            (ExportFormat::Float32, 3, 32),
+        ] {
+            let mut bytes = Vec::new();
+            write_samples(&mut bytes, &samples, 48000, format).unwrap();
+            assert_eq!(header_field(&bytes, 20), tag, "{:?}", format);
+            assert_eq!(header_field(&bytes, 34), bits, "{:?}", format);
+            let riff_size = u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize;
+            assert_eq!(riff_size + 8, bytes.len(), "{:?}", format);
+            let data = &bytes[bytes.len() - samples.len() * bits as usize / 8..];
+            match format {
+                ExportFormat::Pcm16 => {
+                    let last = i16::from_le_bytes([data[6], data[7]]);
+                    assert_eq!(last, i16::MAX, "clipped to full scale");
+                }
+                ExportFormat::Pcm24 => {
+                    let half = i32::from_le_bytes([0, data[3], data[4], data[5]]) >> 8;
+                    assert!((half - 4194304).abs() <= 1, "{}", half);
+                }
+                ExportFormat::Float32 => {
+                    let last = f32::from_le_bytes(data[12..16].try_into().unwrap());
+                    assert_eq!(last, 1.5, "float keeps overs");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_dither_stays_within_one_step() {
+        let mut dither = Dither::new();
+        let values: Vec<f64> = (0..10000)
+            .map(|_| dither.quantize(0.0, 32767.0, -32768.0))
+            .collect();
+        assert!(values.iter().all(|v| v.abs() <= 1.0));
+        // Silence is dithered rather than truncated to zero
+        assert!(values.iter().any(|&v| v != 0.0));
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        assert!(mean.abs() < 0.05, "{}", mean);
+    }
+}
@@ -0,0 +1,72 @@
+//! Opt-in decoding through an external ffmpeg (`--ffmpeg-fallback`) for formats symphonia
+//! can't read: the input is converted to a temporary WAV file, which is then decoded natively
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// ffmpeg binary: `BANDSTAT_FFMPEG`, or `ffmpeg` on the PATH
+fn ffmpeg_binary() -> PathBuf {
+    std::env::var_os("BANDSTAT_FFMPEG")
+        .filter(|path| !path.is_empty())
+        .map_or_else(|| PathBuf::from("ffmpeg"), PathBuf::from)
+}
+
+/// Converted WAV file, removed when dropped
+pub(super) struct TempWav {
+    path: PathBuf,
+}
+
+impl TempWav {
+    pub(super) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempWav {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Convert the first audio stream of `input` (a path or URL) to a 32-bit float WAV file,
+/// keeping its sample rate and channels, and stopping after `max_duration` seconds
+pub(super) fn convert_to_wav(input: &str, max_duration: Option<f64>) -> Result<TempWav, String> {
+    // Parallel batch jobs each need their own file
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+    let wav = TempWav {
+        path: std::env::temp_dir().join(format!(
+            "bandstat-{}-{}.wav",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        )),
+    };
+
+    let mut command = Command::new(ffmpeg_binary());
+    command
+        .args(["-nostdin", "-hide_banner", "-loglevel", "error", "-y", "-i"])
+        .arg(input);
+    if let Some(secs) = max_duration {
+        command.arg("-t").arg(secs.to_string());
+    }
+    command
+        .args(["-map", "0:a:0", "-c:a", "pcm_f32le", "-f", "wav"])
+        .arg(wav.path())
+        .stdin(Stdio::null());
+
+    let output = command.output().map_err(|e| {
+        format!(
+            "could not run ffmpeg for --ffmpeg-fallback ({}; set BANDSTAT_FFMPEG to its path)",
+            e
+        )
+    })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().rfind(|l| !l.trim().is_empty()).unwrap_or("");
+        return Err(format!(
+            "ffmpeg could not decode it either ({})",
+            reason.trim()
+        ));
+    }
+    Ok(wav)
+}
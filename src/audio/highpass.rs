@@ -0,0 +1,98 @@
+//! High-pass filter applied to the decoded audio before analysis (`--highpass 20`), so
+//! subsonic noise counts toward neither the band shares nor the dynamics
+
+use std::f64::consts::{FRAC_1_SQRT_2, PI};
+
+/// Highest accepted cutoff; above this the filter would cut into program material
+const MAX_CUTOFF_HZ: f64 = 1000.0;
+
+/// Check a `--highpass` cutoff in Hz
+pub fn check_highpass(hz: f64) -> Result<(), String> {
+    if !(hz > 0.0 && hz <= MAX_CUTOFF_HZ) {
+        return Err(format!(
+            "--highpass must be above 0 and at most {} Hz",
+            MAX_CUTOFF_HZ
+        ));
+    }
+    Ok(())
+}
+
+/// Second-order Butterworth high-pass (a biquad from the RBJ cookbook, 12 dB per octave),
+/// keeping its state across calls so a stream can be filtered packet by packet
+#[derive(Clone)]
+pub struct HighPass {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl HighPass {
+    pub fn new(cutoff_hz: f64, sample_rate: u32) -> Result<Self, String> {
+        let nyquist = sample_rate as f64 / 2.0;
+        if cutoff_hz >= nyquist {
+            return Err(format!(
+                "--highpass {} Hz is not below the Nyquist frequency ({} Hz)",
+                cutoff_hz, nyquist
+            ));
+        }
+        let w0 = 2.0 * PI * cutoff_hz / sample_rate as f64;
+        let alpha = w0.sin() / (2.0 * FRAC_1_SQRT_2);
+        let cos = w0.cos();
+        let a0 = 1.0 + alpha;
+        Ok(Self {
+            b: [
+                (1.0 + cos) / 2.0 / a0,
+                -(1.0 + cos) / a0,
+                (1.0 + cos) / 2.0 / a0,
+            ],
+            a: [-2.0 * cos / a0, (1.0 - alpha) / a0],
+            z: [0.0; 2],
+        })
+    }
+
+    /// Filter samples in place (transposed direct form II)
+    pub fn process(&mut self, samples: &mut [f32]) {
+        for sample in samples {
+            let x = *sample as f64;
+            let y = self.b[0] * x + self.z[0];
+            self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+            self.z[1] = self.b[2] * x - self.a[1] * y;
+            *sample = y as f32;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Level in dB of a sine after the filter, measured after it has settled
+    fn gain_db(freq: f64, cutoff_hz: f64) -> f64 {
+        let rate = 48000;
+        let mut samples: Vec<f32> = (0..rate * 4)
+            .map(|i| (2.0 * PI * freq * i as f64 / rate as f64).sin() as f32)
+            .collect();
+        HighPass::new(cutoff_hz, rate as u32)
+            .unwrap()
+            .process(&mut samples);
+        let settled = &samples[rate * 2..];
+        let mean_square =
+            settled.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / settled.len() as f64;
+        10.0 * (mean_square * 2.0).log10()
+    }
+
+    #[test]
+    fn test_highpass_response() {
+        assert!((gain_db(20.0, 20.0) + 3.01).abs() < 0.05);
+        // 12 dB per octave below the cutoff
+        assert!((gain_db(5.0, 20.0) + 24.1).abs() < 0.3);
+        assert!(gain_db(1000.0, 20.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_highpass_rejects_cutoff_above_nyquist() {
+        assert!(HighPass::new(5000.0, 8000).is_err());
+        assert!(check_highpass(0.0).is_err());
+        assert!(check_highpass(5000.0).is_err());
+    }
+}
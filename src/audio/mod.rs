@@ -0,0 +1,33 @@
+//! Audio input: decoding, the ffmpeg fallback, and the `--highpass` filter (with the `decode`
+//! feature), WAV export (with the `cli` feature), and resampling
+
+#[cfg(feature = "decode")]
+mod decode;
+#[cfg(feature = "cli")]
+mod export;
+#[cfg(feature = "decode")]
+mod ffmpeg;
+#[cfg(feature = "decode")]
+mod highpass;
+mod resample;
+
+#[cfg(feature = "decode")]
+pub use decode::{
+    AudioStream, DEFAULT_MAX_DOWNLOAD_MB, LfeAudio, LoadOptions, check_max_download,
+    check_max_memory, is_url, load_audio, load_audio_with_lfe, parse_position,
+};
+#[cfg(feature = "cli")]
+pub use export::{ExportFormat, write_wav};
+#[cfg(feature = "decode")]
+pub use highpass::{HighPass, check_highpass};
+pub use resample::{ResampleQuality, Resampling, StreamResampler, resample};
+
+/// Target sample rate for analysis (ensures consistent FFT resolution)
+pub const TARGET_SAMPLE_RATE: u32 = 48000;
+
+pub struct AudioData {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub original_sample_rate: u32,
+}
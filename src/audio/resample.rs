@@ -0,0 +1,336 @@
+//! Sample rate conversion to the analysis rate
+
+use rubato::sinc_interpolator::ScalarInterpolator;
+use rubato::{
+    Resampler, SincFixedIn, SincInterpolationParameters, SincInterpolationType, WindowFunction,
+    calculate_cutoff,
+};
+
+/// Trade-off between speed and the flatness of the resampler's passband
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum ResampleQuality {
+    /// Short Hann-windowed sinc; rolls off the top ~2 kHz below the input's Nyquist frequency
+    #[default]
+    Fast,
+    /// Twice the sinc length with a squared Hann window, flat to within ~1 kHz of Nyquist
+    Balanced,
+    /// Eight times the sinc length with a Blackman-Harris window and cubic interpolation,
+    /// flat almost up to Nyquist (slowest)
+    High,
+}
+
+/// How audio is resampled to the analysis rate
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Resampling {
+    pub quality: ResampleQuality,
+    /// Use rubato's portable scalar interpolator, so the output is bit-identical across
+    /// machines (`--deterministic`)
+    pub deterministic: bool,
+}
+
+/// Incremental resampler for streamed audio
+///
+/// Uses the same settings and chunking as [`resample`], so the output matches
+/// the in-memory path apart from the final partial chunk.
+pub struct StreamResampler {
+    resampler: SincFixedIn<f32>,
+    pending: Vec<f32>,
+    ratio: f64,
+    total_in: usize,
+    total_out: usize,
+}
+
+impl StreamResampler {
+    pub fn new(from_rate: u32, to_rate: u32, resampling: Resampling) -> Result<Self, String> {
+        let ratio = to_rate as f64 / from_rate as f64;
+        Ok(Self {
+            resampler: sinc_resampler(ratio, resampling)?,
+            pending: Vec::with_capacity(RESAMPLE_CHUNK * 2),
+            ratio,
+            total_in: 0,
+            total_out: 0,
+        })
+    }
+
+    /// Feed input samples and return any resampled output that is ready
+    pub fn process(&mut self, input: &[f32]) -> Result<Vec<f32>, String> {
+        self.total_in += input.len();
+        self.pending.extend_from_slice(input);
+
+        let mut output = Vec::new();
+        while self.pending.len() >= RESAMPLE_CHUNK {
+            let resampled = self
+                .resampler
+                .process(&[&self.pending[..RESAMPLE_CHUNK]], None)
+                .map_err(|e| format!("Resample error: {}", e))?;
+            output.extend_from_slice(&resampled[0]);
+            self.pending.drain(..RESAMPLE_CHUNK);
+        }
+        self.total_out += output.len();
+        Ok(output)
+    }
+
+    /// Flush the final partial chunk (zero-padded) and trim to the expected length
+    pub fn finish(mut self) -> Result<Vec<f32>, String> {
+        let mut output = Vec::new();
+        if !self.pending.is_empty() {
+            self.pending.resize(RESAMPLE_CHUNK, 0.0);
+            let resampled = self
+                .resampler
+                .process(&[self.pending.as_slice()], None)
+                .map_err(|e| format!("Resample error: {}", e))?;
+            output.extend_from_slice(&resampled[0]);
+        }
+
+        let expected_len = (self.total_in as f64 * self.ratio) as usize;
+        output.truncate(expected_len.saturating_sub(self.total_out));
+        Ok(output)
+    }
+}
+
+/// Input chunk size for the sinc resampler
+const RESAMPLE_CHUNK: usize = 4096;
+
+fn resampler_params(quality: ResampleQuality) -> SincInterpolationParameters {
+    match quality {
+        // Fast settings suitable for analysis (not mastering quality)
+        ResampleQuality::Fast => SincInterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.91,
+            interpolation: SincInterpolationType::Linear,
+            oversampling_factor: 128,
+            window: WindowFunction::Hann,
+        },
+        ResampleQuality::Balanced => SincInterpolationParameters {
+            sinc_len: 128,
+            f_cutoff: calculate_cutoff(128, WindowFunction::Hann2),
+            interpolation: SincInterpolationType::Linear,
+            oversampling_factor: 256,
+            window: WindowFunction::Hann2,
+        },
+        ResampleQuality::High => SincInterpolationParameters {
+            sinc_len: 512,
+            f_cutoff: calculate_cutoff(512, WindowFunction::BlackmanHarris2),
+            interpolation: SincInterpolationType::Cubic,
+            oversampling_factor: 256,
+            window: WindowFunction::BlackmanHarris2,
+        },
+    }
+}
+
+/// Mono sinc resampler taking RESAMPLE_CHUNK input frames per call
+///
+/// rubato picks an AVX/SSE/Neon interpolator by CPU; deterministic mode builds
+/// the scalar one with the same (rounded) sinc length and cutoff instead.
+fn sinc_resampler(ratio: f64, resampling: Resampling) -> Result<SincFixedIn<f32>, String> {
+    let params = resampler_params(resampling.quality);
+    let resampler = if resampling.deterministic {
+        let sinc_len = params.sinc_len.div_ceil(8) * 8;
+        let f_cutoff = if ratio >= 1.0 {
+            params.f_cutoff
+        } else {
+            params.f_cutoff * ratio as f32
+        };
+        let interpolator = ScalarInterpolator::<f32>::new(
+            sinc_len,
+            params.oversampling_factor,
+            f_cutoff,
+            params.window,
+        );
+        SincFixedIn::new_with_interpolator(
+            ratio,
+            2.0,
+            params.interpolation,
+            Box::new(interpolator),
+            RESAMPLE_CHUNK,
+            1,
+        )
+    } else {
+        SincFixedIn::new(ratio, 2.0, params, RESAMPLE_CHUNK, 1)
+    };
+    resampler.map_err(|e| format!("Failed to create resampler: {}", e))
+}
+
+pub fn resample(
+    samples: &[f32],
+    from_rate: u32,
+    to_rate: u32,
+    resampling: Resampling,
+) -> Result<Vec<f32>, String> {
+    let ratio = to_rate as f64 / from_rate as f64;
+    let chunk_size = RESAMPLE_CHUNK;
+
+    let mut resampler = sinc_resampler(ratio, resampling)?;
+
+    let mut output = Vec::with_capacity((samples.len() as f64 * ratio) as usize + chunk_size);
+    let mut chunk = vec![0.0f32; chunk_size];
+    let mut pos = 0;
+
+    while pos < samples.len() {
+        let end = (pos + chunk_size).min(samples.len());
+        let len = end - pos;
+
+        chunk[..len].copy_from_slice(&samples[pos..end]);
+        // Zero-pad remainder if last chunk is short
+        if len < chunk_size {
+            chunk[len..].fill(0.0);
+        }
+
+        let input = [chunk.as_slice()];
+        let resampled = resampler
+            .process(&input, None)
+            .map_err(|e| format!("Resample error: {}", e))?;
+
+        output.extend_from_slice(&resampled[0]);
+        pos += chunk_size;
+    }
+
+    // Trim to expected length
+    let expected_len = (samples.len() as f64 * ratio) as usize;
+    output.truncate(expected_len);
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    /// Generate a sine wave at the given frequency
+    fn generate_sine(freq: f32, sample_rate: u32, duration_secs: f32) -> Vec<f32> {
+        let num_samples = (sample_rate as f32 * duration_secs) as usize;
+        (0..num_samples)
+            .map(|i| (2.0 * PI * freq * i as f32 / sample_rate as f32).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_resample_output_length() {
+        // 44100 -> 48000: ratio = 48000/44100 ≈ 1.0884
+        let input = generate_sine(440.0, 44100, 1.0);
+        let output = resample(&input, 44100, 48000, Resampling::default()).unwrap();
+
+        let expected_len = (input.len() as f64 * 48000.0 / 44100.0) as usize;
+        assert_eq!(output.len(), expected_len);
+    }
+
+    #[test]
+    fn test_resample_preserves_frequency() {
+        // Generate 1000Hz sine at 44100Hz, resample to 48000Hz
+        // The resampled signal should still have peaks at ~1000Hz
+        let freq = 1000.0;
+        let input = generate_sine(freq, 44100, 0.1);
+        let output = resample(&input, 44100, 48000, Resampling::default()).unwrap();
+
+        // Count zero crossings to estimate frequency
+        let zero_crossings: usize = output
+            .windows(2)
+            .filter(|w| (w[0] >= 0.0) != (w[1] >= 0.0))
+            .count();
+
+        // Expected crossings: 2 per cycle * freq * duration
+        let expected_crossings = (2.0 * freq * 0.1) as usize;
+        let tolerance = expected_crossings / 10; // 10% tolerance
+
+        assert!(
+            (zero_crossings as i32 - expected_crossings as i32).unsigned_abs() < tolerance as u32,
+            "Expected ~{} zero crossings, got {}",
+            expected_crossings,
+            zero_crossings
+        );
+    }
+
+    #[test]
+    fn test_resample_downsample() {
+        // 96000 -> 48000: ratio = 0.5
+        let input = generate_sine(440.0, 96000, 0.5);
+        let output = resample(&input, 96000, 48000, Resampling::default()).unwrap();
+
+        let expected_len = (input.len() as f64 * 0.5) as usize;
+        assert_eq!(output.len(), expected_len);
+    }
+
+    #[test]
+    fn test_stream_resampler_matches_batch() {
+        let input = generate_sine(440.0, 44100, 0.5);
+        let batch = resample(&input, 44100, 48000, Resampling::default()).unwrap();
+
+        let mut stream = StreamResampler::new(44100, 48000, Resampling::default()).unwrap();
+        let mut streamed = Vec::new();
+        for block in input.chunks(1000) {
+            streamed.extend(stream.process(block).unwrap());
+        }
+        streamed.extend(stream.finish().unwrap());
+
+        assert_eq!(streamed.len(), batch.len());
+        assert!(streamed.iter().zip(&batch).all(|(a, b)| a == b));
+    }
+
+    #[test]
+    fn test_resample_quality_extends_passband() {
+        // 20.5 kHz at 44.1 kHz, just below where the fast settings roll off
+        let input = generate_sine(20500.0, 44100, 1.0);
+        let level = |quality| {
+            let output = resample(
+                &input,
+                44100,
+                48000,
+                Resampling {
+                    quality,
+                    ..Resampling::default()
+                },
+            )
+            .unwrap();
+            // Skip the filter's delay at both ends
+            let middle = &output[4800..output.len() - 4800];
+            let rms = (middle.iter().map(|&s| (s as f64).powi(2)).sum::<f64>()
+                / middle.len() as f64)
+                .sqrt();
+            20.0 * (rms * 2f64.sqrt()).log10()
+        };
+        let fast = level(ResampleQuality::Fast);
+        let balanced = level(ResampleQuality::Balanced);
+        let high = level(ResampleQuality::High);
+        assert!(fast < -3.0, "fast {:.2} dB", fast);
+        assert!(
+            balanced > fast && high > balanced,
+            "{} {} {}",
+            fast,
+            balanced,
+            high
+        );
+        assert!(high > -1.0, "high {:.2} dB", high);
+
+        // Well inside the passband all three agree
+        let input = generate_sine(1000.0, 44100, 0.5);
+        for quality in [ResampleQuality::Balanced, ResampleQuality::High] {
+            let output = resample(
+                &input,
+                44100,
+                48000,
+                Resampling {
+                    quality,
+                    ..Resampling::default()
+                },
+            )
+            .unwrap();
+            assert_eq!(
+                output.len(),
+                (input.len() as f64 * 48000.0 / 44100.0) as usize
+            );
+            let peak = output.iter().fold(0.0f32, |m, &s| m.max(s.abs()));
+            assert!((peak - 1.0).abs() < 0.01, "{:?} peak {}", quality, peak);
+        }
+    }
+
+    #[test]
+    fn test_resample_same_rate() {
+        // Edge case: same rate should work (though load_audio skips this)
+        let input = generate_sine(440.0, 48000, 0.1);
+        let output = resample(&input, 48000, 48000, Resampling::default()).unwrap();
+
+        assert_eq!(output.len(), input.len());
+    }
+}
@@ -60,3 +60,46 @@ pub(super) const TIMELINE_BAND_COLORS: [&str; 14] = [
     "#C82828", // UHIG - Red
     "#982060", // AIR - Magenta
 ];
+
+/// The timeline band colors resampled along the gradient for a layout of `count` bands
+pub(super) fn spread_band_colors(count: usize) -> Vec<String> {
+    let rgb = |hex: &str| -> [f64; 3] {
+        [1, 3, 5].map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or(0) as f64)
+    };
+    let last = TIMELINE_BAND_COLORS.len() - 1;
+    (0..count)
+        .map(|i| {
+            let position = if count > 1 {
+                i as f64 * last as f64 / (count - 1) as f64
+            } else {
+                0.0
+            };
+            let low = (position.floor() as usize).min(last);
+            let high = (low + 1).min(last);
+            let (from, to) = (
+                rgb(TIMELINE_BAND_COLORS[low]),
+                rgb(TIMELINE_BAND_COLORS[high]),
+            );
+            let t = position - low as f64;
+            let [r, g, b] = [0, 1, 2].map(|c| (from[c] + (to[c] - from[c]) * t).round() as u8);
+            format!("#{:02X}{:02X}{:02X}", r, g, b)
+        })
+        .collect()
+}
+
+/// Heatmap color scale (low to high share)
+pub(super) const HEATMAP_GRADIENT: [&str; 5] = [
+    "#10182C", // Near black blue
+    "#2858A0", // Blue
+    "#48C878", // Green
+    "#E8D800", // Gold
+    "#F84800", // Red-orange
+];
+
+/// Loudness chart series colors: short-term, then LOW/MID/HIGH groups
+pub(super) const LOUDNESS_COLORS: [&str; 4] = [
+    "#FFFFFF", // Short-term - White
+    "#4898E0", // LOW - Light blue
+    "#78D848", // MID - Yellow-green
+    "#F87800", // HIGH - Dark orange
+];
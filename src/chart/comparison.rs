@@ -1,26 +1,50 @@
 //! Comparison chart rendering (bar chart with K-weighted overlay lines)
 
 use charming::{
-    Chart, ImageRenderer,
+    Chart,
     component::{Axis, Grid, Legend, Title},
     element::{
         AxisLabel, AxisType, Color, ColorStop, ItemStyle, Label, LabelPosition, LineStyle,
         SplitLine, Symbol, TextStyle,
     },
-    renderer::ImageFormat,
     series::{Bar, Line},
 };
 
 use super::colors::{COLOR_BACKGROUND, COLOR_GRID, COLOR_SETS, COLOR_TEXT};
-use super::{CHART_HEIGHT, CHART_WIDTH, FileChartData, build_band_label};
-use crate::analysis::Band;
+use super::{
+    ChartOptions, FileChartData, band_axis_font_size, build_band_label, render_svg, save_png,
+};
+use crate::analysis::{Band, Weighting};
 
 /// Render a comparison chart to a PNG file (supports 2-4 files)
 pub fn render_comparison_chart(
     files: &[FileChartData],
     bands: &[Band],
+    weighting: &Weighting,
     output_path: &str,
+    options: &ChartOptions,
 ) -> Result<(), String> {
+    let chart = comparison_chart(files, bands, weighting, options)?;
+    save_png(&chart, output_path, options)
+}
+
+/// Render a comparison chart as SVG, for embedding in HTML reports (supports 2-4 files)
+pub fn comparison_chart_svg(
+    files: &[FileChartData],
+    bands: &[Band],
+    weighting: &Weighting,
+    options: &ChartOptions,
+) -> Result<String, String> {
+    let chart = comparison_chart(files, bands, weighting, options)?;
+    render_svg(&chart, options)
+}
+
+fn comparison_chart(
+    files: &[FileChartData],
+    bands: &[Band],
+    weighting: &Weighting,
+    options: &ChartOptions,
+) -> Result<Chart, String> {
     if files.len() < 2 || files.len() > COLOR_SETS.len() {
         return Err(format!("Chart requires 2-{} files", COLOR_SETS.len()));
     }
@@ -28,8 +52,8 @@ pub fn render_comparison_chart(
     // Build band labels with frequency ranges (2 lines each)
     let band_labels: Vec<String> = bands.iter().map(build_band_label).collect();
 
-    // Round values to 1 decimal place for display
-    let round = |v: &f64| (v * 10.0).round() / 10.0;
+    // Round values to the printed decimal places for display
+    let round = |v: &f64| options.round(*v);
 
     // Build subtitle showing all files
     let subtitle = files
@@ -44,7 +68,10 @@ pub fn render_comparison_chart(
         .flat_map(|f| {
             vec![
                 (format!("[{}] Raw", f.label), "rect".to_string()),
-                (format!("[{}] K-wt", f.label), "rect".to_string()),
+                (
+                    format!("[{}] {}", f.label, weighting.label()),
+                    "rect".to_string(),
+                ),
             ]
         })
         .collect();
@@ -80,7 +107,11 @@ pub fn render_comparison_chart(
             Axis::new()
                 .type_(AxisType::Category)
                 .data(band_labels)
-                .axis_label(AxisLabel::new().color(COLOR_TEXT).font_size(24)),
+                .axis_label(
+                    AxisLabel::new()
+                        .color(COLOR_TEXT)
+                        .font_size(band_axis_font_size(bands, 24.0)),
+                ),
         )
         .y_axis(
             Axis::new()
@@ -100,7 +131,7 @@ pub fn render_comparison_chart(
 
         chart = chart.series(
             Line::new()
-                .name(format!("[{}] K-wt", file.label))
+                .name(format!("[{}] {}", file.label, weighting.label()))
                 .data(data_kwt)
                 .symbol(Symbol::Circle)
                 .symbol_size(10)
@@ -143,11 +174,5 @@ pub fn render_comparison_chart(
         );
     }
 
-    // Render to PNG
-    let mut renderer = ImageRenderer::new(CHART_WIDTH, CHART_HEIGHT);
-    renderer
-        .save_format(ImageFormat::Png, &chart, output_path)
-        .map_err(|e| format!("Failed to save chart: {}", e))?;
-
-    Ok(())
+    Ok(chart)
 }
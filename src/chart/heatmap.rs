@@ -0,0 +1,93 @@
+//! Heatmap rendering (band balance over a rolling time window)
+
+use charming::{
+    Chart,
+    component::{Axis, Grid, Title, VisualMap, VisualMapChannel, VisualMapType},
+    datatype::DataPoint,
+    element::{AxisLabel, AxisType, Color, Orient, SplitArea, TextStyle},
+    series::Heatmap,
+};
+
+use super::colors::{COLOR_BACKGROUND, COLOR_TEXT, HEATMAP_GRADIENT};
+use super::{ChartOptions, HeatmapChartData, save_png};
+use crate::analysis::Band;
+
+/// Render a band-by-time heatmap (time on X, bands on Y, low bands at the bottom)
+pub fn render_heatmap_chart(
+    data: &HeatmapChartData,
+    bands: &[Band],
+    title: &str,
+    output_path: &str,
+    options: &ChartOptions,
+) -> Result<(), String> {
+    if data.time_labels.is_empty() {
+        return Err("No data to render".to_string());
+    }
+
+    let band_labels: Vec<String> = bands.iter().map(|b| b.label.to_string()).collect();
+
+    let mut max_pct: f64 = 0.0;
+    let mut cells: Vec<Vec<DataPoint>> = Vec::new();
+    for (band_idx, slots) in data.band_percentages.iter().enumerate() {
+        for (slot_idx, pct) in slots.iter().enumerate() {
+            // Empty slots are left blank so gaps in the log stay visible
+            if let Some(pct) = pct {
+                max_pct = max_pct.max(*pct);
+                cells.push(vec![
+                    DataPoint::from(slot_idx as i64),
+                    DataPoint::from(band_idx as i64),
+                    DataPoint::from(options.round(*pct)),
+                ]);
+            }
+        }
+    }
+
+    let chart = Chart::new()
+        .background_color(Color::Value(COLOR_BACKGROUND.to_string()))
+        .title(
+            Title::new()
+                .text(title)
+                .subtext(&data.subtitle)
+                .left("center")
+                .top("3%")
+                .text_style(TextStyle::new().color(COLOR_TEXT).font_size(36))
+                .subtext_style(TextStyle::new().color(COLOR_TEXT).font_size(24)),
+        )
+        .grid(
+            Grid::new()
+                .left("5%")
+                .right("8%")
+                .bottom("8%")
+                .top("15%")
+                .contain_label(true),
+        )
+        .x_axis(
+            Axis::new()
+                .type_(AxisType::Category)
+                .data(data.time_labels.clone())
+                .axis_label(AxisLabel::new().color(COLOR_TEXT).font_size(18))
+                .split_area(SplitArea::new().show(false)),
+        )
+        .y_axis(
+            Axis::new()
+                .type_(AxisType::Category)
+                .data(band_labels)
+                .axis_label(AxisLabel::new().color(COLOR_TEXT).font_size(20)),
+        )
+        .visual_map(
+            VisualMap::new()
+                .type_(VisualMapType::Continuous)
+                .min(0)
+                .max(max_pct.ceil().max(1.0))
+                .calculable(true)
+                .orient(Orient::Vertical)
+                .right("2%")
+                .top("center")
+                .item_height(600)
+                .text_style(TextStyle::new().color(COLOR_TEXT).font_size(18))
+                .in_range(VisualMapChannel::new().color(HEATMAP_GRADIENT.to_vec())),
+        )
+        .series(Heatmap::new().name("%").data(cells));
+
+    save_png(&chart, output_path, options)
+}
@@ -0,0 +1,90 @@
+//! Line chart of short-term loudness over time
+
+use charming::{
+    Chart,
+    component::{Axis, Grid, Legend, Title},
+    element::{AxisLabel, AxisType, Color, ItemStyle, LineStyle, SplitLine, TextStyle},
+    series::Line,
+};
+
+use super::colors::{COLOR_BACKGROUND, COLOR_GRID, COLOR_TEXT, LOUDNESS_COLORS};
+use super::{ChartOptions, LoudnessChartData, save_png};
+use crate::analysis::LOUDNESS_FLOOR_LUFS;
+
+/// Render short-term loudness and the grouped band loudness as lines
+pub fn render_loudness_chart(
+    data: &LoudnessChartData,
+    title: &str,
+    output_path: &str,
+    options: &ChartOptions,
+) -> Result<(), String> {
+    if data.time_labels.is_empty() {
+        return Err("No data to render".to_string());
+    }
+
+    let series: Vec<(&str, &[f64])> = std::iter::once(("Short-term", data.short_term.as_slice()))
+        .chain(data.groups.iter().map(|(l, v)| (l.as_str(), v.as_slice())))
+        .collect();
+
+    let mut chart = Chart::new()
+        .background_color(Color::Value(COLOR_BACKGROUND.to_string()))
+        .title(
+            Title::new()
+                .text(title)
+                .subtext(&data.filename)
+                .left("center")
+                .top("3%")
+                .text_style(TextStyle::new().color(COLOR_TEXT).font_size(36))
+                .subtext_style(TextStyle::new().color(COLOR_TEXT).font_size(24)),
+        )
+        .legend(
+            Legend::new()
+                .data(series.iter().map(|(name, _)| *name).collect::<Vec<_>>())
+                .bottom("3%")
+                .item_gap(24)
+                .text_style(TextStyle::new().color(COLOR_TEXT).font_size(20)),
+        )
+        .grid(
+            Grid::new()
+                .left("5%")
+                .right("3%")
+                .bottom("10%")
+                .top("15%")
+                .contain_label(true),
+        )
+        .x_axis(
+            Axis::new()
+                .type_(AxisType::Category)
+                .boundary_gap(false)
+                .data(data.time_labels.clone())
+                .axis_label(AxisLabel::new().color(COLOR_TEXT).font_size(20)),
+        )
+        .y_axis(
+            Axis::new()
+                .type_(AxisType::Value)
+                .name("LUFS")
+                .min(LOUDNESS_FLOOR_LUFS)
+                .max(0)
+                .name_text_style(TextStyle::new().color(COLOR_TEXT).font_size(24))
+                .axis_label(AxisLabel::new().color(COLOR_TEXT).font_size(20))
+                .split_line(
+                    SplitLine::new().line_style(LineStyle::new().width(0.5).color(COLOR_GRID)),
+                ),
+        );
+
+    for (idx, (name, values)) in series.iter().enumerate() {
+        let color = LOUDNESS_COLORS[idx % LOUDNESS_COLORS.len()];
+        // The overall short-term line is drawn thicker than the group lines
+        let width = if idx == 0 { 4 } else { 2 };
+        chart = chart.series(
+            Line::new()
+                .name(*name)
+                .data(values.iter().map(|&x| options.round(x)).collect())
+                .show_symbol(false)
+                .item_style(ItemStyle::new().color(color))
+                .line_style(LineStyle::new().width(width).color(color)),
+        );
+    }
+
+    save_png(&chart, output_path, options)
+}
@@ -2,12 +2,26 @@
 
 mod colors;
 mod comparison;
+mod heatmap;
+mod loudness;
+mod render;
 mod stacked;
+mod trend;
 
-pub use comparison::render_comparison_chart;
-pub use stacked::render_stacked_chart;
+pub use comparison::{comparison_chart_svg, render_comparison_chart};
+pub use heatmap::render_heatmap_chart;
+pub use loudness::render_loudness_chart;
+pub use stacked::{render_stacked_chart, stacked_chart_svg};
+pub use trend::render_trend_chart;
 
-use crate::analysis::Band;
+use crate::analysis::{Band, band_index};
+use render::{render_svg, save_png};
+
+/// `value` rounded to `decimals` places, as charts and tables print it
+pub fn round_to(value: f64, decimals: usize) -> f64 {
+    let scale = 10f64.powi(decimals as i32);
+    (value * scale).round() / scale
+}
 
 /// Data for a single file in the comparison chart
 pub struct FileChartData {
@@ -23,19 +37,211 @@ pub struct TimelineChartData {
     pub time_labels: Vec<String>,
     /// Band percentages: band_percentages[band_idx][time_idx]
     pub band_percentages: Vec<Vec<f64>>,
+    /// Color of each band (see [`band_color`])
+    pub band_colors: Vec<String>,
+}
+
+/// Data for band-over-time heatmaps
+pub struct HeatmapChartData {
+    pub subtitle: String,
+    pub time_labels: Vec<String>,
+    /// Band percentages per time slot (`None` = no data): band_percentages[band_idx][slot_idx]
+    pub band_percentages: Vec<Vec<Option<f64>>>,
+}
+
+/// Data for the loudness-over-time line chart
+pub struct LoudnessChartData {
+    pub filename: String,
+    pub time_labels: Vec<String>,
+    /// Short-term loudness in LUFS per time point
+    pub short_term: Vec<f64>,
+    /// (group label, loudness in LUFS per time point)
+    pub groups: Vec<(String, Vec<f64>)>,
+}
+
+/// Data for the trend chart across saved runs
+pub struct TrendChartData {
+    pub run_labels: Vec<String>,
+    /// (label, color, percentage per run) of each plotted band or group
+    pub series: Vec<(String, String, Vec<f64>)>,
+    /// Integrated loudness in LUFS per run, drawn on a second axis
+    pub loudness: Option<Vec<f64>>,
+}
+
+/// Chart types that `--image` can produce
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChartKind {
+    /// Stacked band distribution of a single file
+    Stats,
+    /// Bar chart comparing 2-4 files
+    Compare,
+    /// Stacked band distribution over time
+    Timeline,
+    /// Short-term and grouped band loudness over time
+    Loudness,
+}
+
+impl ChartKind {
+    const ALL: [(&'static str, ChartKind); 4] = [
+        ("stats", ChartKind::Stats),
+        ("compare", ChartKind::Compare),
+        ("timeline", ChartKind::Timeline),
+        ("loudness", ChartKind::Loudness),
+    ];
+
+    pub fn name(self) -> &'static str {
+        Self::ALL.iter().find(|(_, k)| *k == self).unwrap().0
+    }
+}
+
+/// One `--image [KIND=]PATH` output; without a kind, the chart of the current mode
+#[derive(Clone, Debug)]
+pub struct ImageSpec {
+    pub kind: Option<ChartKind>,
+    pub path: String,
+}
+
+impl ImageSpec {
+    pub fn kind_or(&self, default: ChartKind) -> ChartKind {
+        self.kind.unwrap_or(default)
+    }
+}
+
+/// Parse `--image` values: `chart.png` or `timeline=tl.png`
+pub fn parse_image_spec(s: &str) -> Result<ImageSpec, String> {
+    if let Some((prefix, path)) = s.split_once('=')
+        && !prefix.is_empty()
+        && prefix.chars().all(|c| c.is_ascii_alphabetic())
+    {
+        let kind = ChartKind::ALL
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(prefix))
+            .map(|&(_, kind)| kind)
+            .ok_or_else(|| {
+                format!(
+                    "unknown chart kind '{}' (expected stats, compare, timeline, or loudness)",
+                    prefix
+                )
+            })?;
+        if path.is_empty() {
+            return Err(format!("missing path for {} chart", kind.name()));
+        }
+        return Ok(ImageSpec {
+            kind: Some(kind),
+            path: path.to_string(),
+        });
+    }
+    Ok(ImageSpec {
+        kind: None,
+        path: s.to_string(),
+    })
 }
 
 /// Chart dimensions (2x for Retina quality)
 pub(super) const CHART_WIDTH: u32 = 2800;
 pub(super) const CHART_HEIGHT: u32 = 1200;
 
+/// Smallest and largest width or height accepted by `--chart-size`
+const CHART_SIZE_RANGE: std::ops::RangeInclusive<u32> = 200..=10000;
+
+/// How charts are drawn: PNG size, label font, and the rounding of the values shown
+#[derive(Clone, Debug)]
+pub struct ChartOptions {
+    /// PNG size in pixels
+    size: (u32, u32),
+    /// Font family name or font file used for labels; the system default when unset
+    font: Option<String>,
+    /// Decimal places of the values shown on charts
+    pub decimals: usize,
+}
+
+impl Default for ChartOptions {
+    fn default() -> Self {
+        Self {
+            size: (CHART_WIDTH, CHART_HEIGHT),
+            font: None,
+            decimals: 1,
+        }
+    }
+}
+
+impl ChartOptions {
+    /// Render PNG charts at `WIDTHxHEIGHT` pixels, e.g. 1400x600
+    pub fn with_size(mut self, spec: &str) -> Result<Self, String> {
+        self.size = parse_chart_size(spec)?;
+        Ok(self)
+    }
+
+    /// Use the given font family or font file (.ttf/.otf/.ttc) for chart labels.
+    /// Loads fonts immediately so a bad font is reported before analysis starts.
+    pub fn with_font(mut self, font: &str) -> Result<Self, String> {
+        render::font_db(Some(font))?;
+        self.font = Some(font.to_string());
+        Ok(self)
+    }
+
+    /// Size charts are laid out at: the default width, with the aspect ratio of the PNG
+    /// size, so labels keep their proportions when the image is scaled to that size
+    fn layout_size(&self) -> (u32, u32) {
+        let (width, height) = self.size;
+        let layout_height = (CHART_WIDTH as u64 * height as u64 / width as u64) as u32;
+        (CHART_WIDTH, layout_height)
+    }
+
+    /// `value` rounded to the decimal places shown
+    fn round(&self, value: f64) -> f64 {
+        round_to(value, self.decimals)
+    }
+}
+
+fn parse_chart_size(spec: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("invalid --chart-size '{}' (expected e.g. 1400x600)", spec);
+    let (width, height) = spec.split_once(['x', 'X']).ok_or_else(invalid)?;
+    let width: u32 = width.trim().parse().map_err(|_| invalid())?;
+    let height: u32 = height.trim().parse().map_err(|_| invalid())?;
+    if !CHART_SIZE_RANGE.contains(&width) || !CHART_SIZE_RANGE.contains(&height) {
+        return Err(format!(
+            "--chart-size width and height must be between {} and {} pixels",
+            CHART_SIZE_RANGE.start(),
+            CHART_SIZE_RANGE.end()
+        ));
+    }
+    Ok((width, height))
+}
+
 /// Maximum number of files supported for chart rendering
 pub fn max_chart_files() -> usize {
     colors::COLOR_SETS.len()
 }
 
+/// Line color of a LOW/MID/HIGH group, as in the loudness chart
+pub fn group_color(group: usize) -> &'static str {
+    colors::LOUDNESS_COLORS[(group + 1) % colors::LOUDNESS_COLORS.len()]
+}
+
+/// Timeline color of a band of `layout`, stable when other bands are ignored or clipped.
+/// A `--bands-file` layout of another size gets the same gradient spread over its bands.
+pub fn band_color(band: &Band, layout: &[Band]) -> String {
+    let index = band_index(layout, band);
+    let color = if layout.len() == colors::TIMELINE_BAND_COLORS.len() {
+        colors::TIMELINE_BAND_COLORS
+            .get(index)
+            .map(|c| c.to_string())
+    } else {
+        colors::spread_band_colors(layout.len())
+            .into_iter()
+            .nth(index)
+    };
+    color.unwrap_or_else(|| colors::TIMELINE_BAND_COLORS[0].to_string())
+}
+
+/// Timeline colors of `bands`, picked from `layout` (see [`band_color`])
+pub fn band_colors(bands: &[Band], layout: &[Band]) -> Vec<String> {
+    bands.iter().map(|band| band_color(band, layout)).collect()
+}
+
 /// Format frequency for display (e.g., 1000 -> "1k", 500 -> "500")
-pub(super) fn format_freq(hz: f32) -> String {
+pub fn format_freq(hz: f32) -> String {
     if hz >= 1000.0 {
         let k = hz / 1000.0;
         if k == k.floor() {
@@ -58,8 +264,18 @@ pub(super) fn build_band_label(band: &Band) -> String {
     format!("{}\n{}", band.label, freq_range)
 }
 
+/// Axis label font size, shrunk for long custom band labels so categories don't overlap
+pub(super) fn band_axis_font_size(bands: &[Band], base: f64) -> f64 {
+    let longest = bands.iter().map(|b| b.label.len()).max().unwrap_or(0);
+    if longest <= 6 {
+        base
+    } else {
+        (base * 6.0 / longest as f64).max(14.0)
+    }
+}
+
 /// Build band label with frequency range (1 line, for legend)
-pub(super) fn build_band_legend_label(band: &Band) -> String {
+pub fn build_band_legend_label(band: &Band) -> String {
     let freq_range = if band.high_hz == f32::MAX {
         format!("{}+", format_freq(band.low_hz))
     } else {
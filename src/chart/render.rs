@@ -0,0 +1,145 @@
+//! PNG rasterization with a controllable label font
+
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
+
+use charming::{Chart, ImageRenderer};
+use resvg::tiny_skia::Pixmap;
+use resvg::usvg::{self, TreeParsing, TreeTextToPath, fontdb};
+
+use super::ChartOptions;
+
+/// Tuffy (public domain), for chart labels on systems without any fonts
+const FALLBACK_FONT: &[u8] = include_bytes!("fonts/Tuffy.ttf");
+
+/// Font databases by chart font, shared by all charts rendered in this process
+type FontDbs = Vec<(Option<String>, Arc<fontdb::Database>)>;
+
+static FONT_DBS: Mutex<FontDbs> = Mutex::new(Vec::new());
+
+thread_local! {
+    /// Chart renderer of this thread and its size, kept between charts because each one
+    /// starts a JavaScript runtime and loads the system fonts
+    static RENDERER: RefCell<Option<((u32, u32), ImageRenderer)>> = const { RefCell::new(None) };
+}
+
+/// Font database for labels in `font` (a family name or font file), loaded on first use
+pub(super) fn font_db(font: Option<&str>) -> Result<Arc<fontdb::Database>, String> {
+    let mut dbs = FONT_DBS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((_, db)) = dbs.iter().find(|(f, _)| f.as_deref() == font) {
+        return Ok(db.clone());
+    }
+    let db = Arc::new(build_font_db(font)?);
+    dbs.push((font.map(str::to_string), db.clone()));
+    Ok(db)
+}
+
+/// Build the font database: system fonts, plus the `--chart-font` file if given, or the
+/// bundled fallback font if there are none. Generic families are pointed at the chosen
+/// font so every label uses it.
+fn build_font_db(font: Option<&str>) -> Result<fontdb::Database, String> {
+    let mut db = fontdb::Database::new();
+    db.load_system_fonts();
+
+    let family = match font {
+        Some(font) if std::path::Path::new(font).is_file() => {
+            let before = db.len();
+            db.load_font_file(font)
+                .map_err(|e| format!("Failed to load chart font {}: {}", font, e))?;
+            db.faces()
+                .nth(before)
+                .and_then(|face| face.families.first())
+                .map(|(name, _)| name.clone())
+                .ok_or_else(|| format!("No usable font face in {}", font))?
+        }
+        Some(family) => {
+            if !has_family(&db, family) {
+                return Err(format!(
+                    "Chart font '{}' is not installed (pass a font file path instead)",
+                    family
+                ));
+            }
+            family.to_string()
+        }
+        None if has_family(&db, &default_sans_serif(&db)) => return Ok(db),
+        // Minimal systems often lack the usual defaults; fall back to any installed font,
+        // and containers without any fonts to the bundled one
+        None => {
+            if db.is_empty() {
+                db.load_font_data(FALLBACK_FONT.to_vec());
+            }
+            db.faces()
+                .next()
+                .and_then(|f| f.families.first())
+                .map(|(name, _)| name.clone())
+                .ok_or_else(|| "the bundled chart font failed to load".to_string())?
+        }
+    };
+
+    db.set_sans_serif_family(family.clone());
+    db.set_serif_family(family.clone());
+    db.set_monospace_family(family);
+    Ok(db)
+}
+
+fn default_sans_serif(db: &fontdb::Database) -> String {
+    db.family_name(&fontdb::Family::SansSerif).to_string()
+}
+
+fn has_family(db: &fontdb::Database, family: &str) -> bool {
+    db.faces()
+        .any(|face| face.families.iter().any(|(name, _)| name == family))
+}
+
+/// Render a chart to an SVG document (labels stay text, drawn with the viewer's fonts)
+pub(super) fn render_svg(chart: &Chart, options: &ChartOptions) -> Result<String, String> {
+    let (width, height) = options.layout_size();
+    RENDERER.with_borrow_mut(|renderer| {
+        if !matches!(renderer, Some((size, _)) if *size == (width, height)) {
+            // Drop the old renderer first: a thread's JavaScript runtimes must be
+            // dropped in reverse order of creation
+            *renderer = None;
+            *renderer = Some(((width, height), ImageRenderer::new(width, height)));
+        }
+        let (_, renderer) = renderer.as_mut().expect("renderer was just created");
+        renderer
+            .render(chart)
+            .map_err(|e| format!("Failed to render chart: {}", e))
+    })
+}
+
+/// Render a chart to a PNG file
+pub(super) fn save_png(chart: &Chart, path: &str, options: &ChartOptions) -> Result<(), String> {
+    let db = font_db(options.font.as_deref())?;
+
+    let svg = render_svg(chart, options)?;
+
+    let mut tree = usvg::Tree::from_data(svg.as_bytes(), &usvg::Options::default())
+        .map_err(|e| format!("Failed to render chart: {}", e))?;
+    tree.convert_text(&db);
+
+    let (width, height) = options.size;
+    let (layout_width, layout_height) = options.layout_size();
+    let scale = usvg::Transform::from_scale(
+        width as f32 / layout_width as f32,
+        height as f32 / layout_height as f32,
+    );
+    let mut pixmap = Pixmap::new(width, height).ok_or("Invalid chart dimensions")?;
+    resvg::Tree::from_usvg(&tree).render(scale, &mut pixmap.as_mut());
+    pixmap
+        .save_png(path)
+        .map_err(|e| format!("Failed to save chart: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fallback_font_loads() {
+        let mut db = fontdb::Database::new();
+        db.load_font_data(FALLBACK_FONT.to_vec());
+        let face = db.faces().next().expect("bundled font has a face");
+        assert_eq!(face.families[0].0, "Tuffy");
+    }
+}
@@ -1,23 +1,21 @@
 //! Stacked bar chart rendering (for timeline and single-file modes)
 
 use charming::{
-    Chart, ImageRenderer,
+    Chart,
     component::{Axis, Grid, Legend, Title},
     element::{
         AxisLabel, AxisType, Color, ItemStyle, Label, LabelPosition, LineStyle, SplitLine,
         TextStyle,
     },
-    renderer::ImageFormat,
     series::Bar,
 };
 
-use super::colors::{COLOR_BACKGROUND, COLOR_GRID, COLOR_TEXT, TIMELINE_BAND_COLORS};
-use super::{CHART_WIDTH, TimelineChartData, build_band_legend_label};
+use super::colors::{COLOR_BACKGROUND, COLOR_GRID, COLOR_TEXT};
+use super::{
+    CHART_WIDTH, ChartOptions, TimelineChartData, build_band_legend_label, render_svg, save_png,
+};
 use crate::analysis::Band;
 
-/// Chart height for stacked charts
-const STACKED_CHART_HEIGHT: u32 = 1200;
-
 /// Render a stacked bar chart for band distribution
 /// Used for both timeline mode (multiple time points) and single-file stats mode (single bar)
 pub fn render_stacked_chart(
@@ -25,7 +23,29 @@ pub fn render_stacked_chart(
     bands: &[Band],
     title: &str,
     output_path: &str,
+    options: &ChartOptions,
 ) -> Result<(), String> {
+    let chart = stacked_chart(data, bands, title, options)?;
+    save_png(&chart, output_path, options)
+}
+
+/// Render a stacked bar chart as SVG, for embedding in HTML reports
+pub fn stacked_chart_svg(
+    data: &TimelineChartData,
+    bands: &[Band],
+    title: &str,
+    options: &ChartOptions,
+) -> Result<String, String> {
+    let chart = stacked_chart(data, bands, title, options)?;
+    render_svg(&chart, options)
+}
+
+fn stacked_chart(
+    data: &TimelineChartData,
+    bands: &[Band],
+    title: &str,
+    options: &ChartOptions,
+) -> Result<Chart, String> {
     if data.time_labels.is_empty() {
         return Err("No data to render".to_string());
     }
@@ -104,14 +124,12 @@ pub fn render_stacked_chart(
 
     // Add stacked bar series for each band (low frequencies at bottom, high at top)
     for (band_idx, band) in bands.iter().enumerate() {
-        let color = TIMELINE_BAND_COLORS
-            .get(band_idx)
-            .unwrap_or(&TIMELINE_BAND_COLORS[0]);
+        let color = data.band_colors.get(band_idx).map_or("", String::as_str);
 
         let bar_data: Vec<f64> = data
             .band_percentages
             .get(band_idx)
-            .map(|v| v.iter().map(|x| (x * 10.0).round() / 10.0).collect())
+            .map(|v| v.iter().map(|&x| options.round(x)).collect())
             .unwrap_or_default();
 
         // Check if any value in this band exceeds threshold (to decide if we show labels)
@@ -125,7 +143,7 @@ pub fn render_stacked_chart(
             .data(bar_data)
             .stack("total")
             .bar_width(bar_width)
-            .item_style(ItemStyle::new().color(*color));
+            .item_style(ItemStyle::new().color(color));
 
         // Only add labels for bands that have significant values
         if has_significant_values {
@@ -143,11 +161,5 @@ pub fn render_stacked_chart(
         chart = chart.series(bar);
     }
 
-    // Render to PNG
-    let mut renderer = ImageRenderer::new(CHART_WIDTH, STACKED_CHART_HEIGHT);
-    renderer
-        .save_format(ImageFormat::Png, &chart, output_path)
-        .map_err(|e| format!("Failed to save chart: {}", e))?;
-
-    Ok(())
+    Ok(chart)
 }
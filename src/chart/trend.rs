@@ -0,0 +1,111 @@
+//! Line chart of band shares and loudness across saved runs
+
+use charming::{
+    Chart,
+    component::{Axis, Grid, Legend, Title},
+    element::{AxisLabel, AxisType, Color, ItemStyle, LineStyle, SplitLine, TextStyle},
+    series::Line,
+};
+
+use super::colors::{COLOR_BACKGROUND, COLOR_GRID, COLOR_TEXT, LOUDNESS_COLORS};
+use super::{ChartOptions, TrendChartData, save_png};
+
+/// Legend name of the loudness line
+const LOUDNESS_SERIES: &str = "Loudness (LUFS)";
+
+/// Render band or group percentages per run, with integrated loudness on a right axis
+pub fn render_trend_chart(
+    data: &TrendChartData,
+    title: &str,
+    output_path: &str,
+    options: &ChartOptions,
+) -> Result<(), String> {
+    if data.run_labels.is_empty() {
+        return Err("No data to render".to_string());
+    }
+
+    let mut legend: Vec<&str> = data.series.iter().map(|(l, _, _)| l.as_str()).collect();
+    if data.loudness.is_some() {
+        legend.push(LOUDNESS_SERIES);
+    }
+
+    let mut chart = Chart::new()
+        .background_color(Color::Value(COLOR_BACKGROUND.to_string()))
+        .title(
+            Title::new()
+                .text(title)
+                .subtext(format!("{} runs", data.run_labels.len()))
+                .left("center")
+                .top("3%")
+                .text_style(TextStyle::new().color(COLOR_TEXT).font_size(36))
+                .subtext_style(TextStyle::new().color(COLOR_TEXT).font_size(24)),
+        )
+        .legend(
+            Legend::new()
+                .data(legend)
+                .bottom("3%")
+                .item_gap(24)
+                .text_style(TextStyle::new().color(COLOR_TEXT).font_size(20)),
+        )
+        .grid(
+            Grid::new()
+                .left("5%")
+                .right("5%")
+                .bottom("10%")
+                .top("15%")
+                .contain_label(true),
+        )
+        .x_axis(
+            Axis::new()
+                .type_(AxisType::Category)
+                .data(data.run_labels.clone())
+                .axis_label(AxisLabel::new().color(COLOR_TEXT).font_size(20)),
+        )
+        .y_axis(
+            Axis::new()
+                .type_(AxisType::Value)
+                .name("%")
+                .min(0)
+                .name_text_style(TextStyle::new().color(COLOR_TEXT).font_size(24))
+                .axis_label(AxisLabel::new().color(COLOR_TEXT).font_size(20))
+                .split_line(
+                    SplitLine::new().line_style(LineStyle::new().width(0.5).color(COLOR_GRID)),
+                ),
+        );
+
+    for (label, color, values) in &data.series {
+        chart = chart.series(
+            Line::new()
+                .name(label.as_str())
+                .data(values.iter().map(|&x| options.round(x)).collect())
+                .symbol_size(12)
+                .item_style(ItemStyle::new().color(color.as_str()))
+                .line_style(LineStyle::new().width(3).color(color.as_str())),
+        );
+    }
+
+    if let Some(loudness) = &data.loudness {
+        // Loudness changes are small next to band shares, so it gets its own scale
+        chart = chart
+            .y_axis(
+                Axis::new()
+                    .type_(AxisType::Value)
+                    .name("LUFS")
+                    .scale(true)
+                    .name_text_style(TextStyle::new().color(COLOR_TEXT).font_size(24))
+                    .axis_label(AxisLabel::new().color(COLOR_TEXT).font_size(20))
+                    .split_line(SplitLine::new().show(false)),
+            )
+            .series(
+                Line::new()
+                    .name(LOUDNESS_SERIES)
+                    .y_axis_index(1)
+                    .data(loudness.iter().map(|&x| options.round(x)).collect())
+                    .symbol_size(12)
+                    .item_style(ItemStyle::new().color(LOUDNESS_COLORS[0]))
+                    .line_style(LineStyle::new().width(4).color(LOUDNESS_COLORS[0])),
+            );
+    }
+
+    save_png(&chart, output_path, options)
+}
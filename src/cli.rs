@@ -0,0 +1,2139 @@
+//! Command-line interface of the `bandstat` binary
+
+use std::io::IsTerminal;
+use std::sync::Arc;
+
+use clap::builder::FalseyValueParser;
+use clap::{ArgGroup, Parser, Subcommand};
+
+use crate::analysis::{
+    AnalysisConfig, BandPreset, EnergyMetric, KWeightMode, Weighting, parse_band_preset,
+    parse_metric,
+};
+use crate::audio::{ExportFormat, LoadOptions};
+use crate::chart::{self, ChartKind};
+use crate::config::{
+    BandConfig, Config, load_bands_file, load_config, load_target_curve, load_weighting_curve,
+};
+use crate::mode::{
+    BatchOptions, CompareOptions, MAX_COMPARE_FILES, MonitorOptions, NAME_PLACEHOLDER,
+    ReportOptions, Signal, SignalSpec, StatsOptions, TimelineOptions, ToneSpec, drop_report_path,
+    expand_file_patterns, is_cue_sheet, is_drop_launch, is_html_report, launched_from_desktop,
+    list_audio_files, open_report, parse_file_label, parse_period, read_file_list, run_bands,
+    run_batch, run_compare, run_cue, run_diff_results, run_generate, run_impulse, run_ipc,
+    run_monitor, run_selftest, run_stats, run_timeline, run_trend, run_tui, run_verify_tone,
+    run_watch,
+};
+use crate::output::{
+    DisplayOptions, HeaderStyle, Output, OutputFormat, Sink, TimeFormat, print_error, print_warning,
+};
+use crate::{analysis, audio};
+
+/// Interval of timeline rows and charts in seconds, unless `--interval` or the config
+/// file gives one
+const DEFAULT_INTERVAL: u32 = 20;
+
+/// Interval of `--live` rows in seconds, unless `--interval` gives one
+const DEFAULT_LIVE_INTERVAL: u32 = 1;
+
+/// Seconds `--follow` waits for a file to grow, unless `--follow-idle` is given
+const DEFAULT_FOLLOW_IDLE: f64 = 10.0;
+
+/// Most files compared, unless `--max-files` is given
+const DEFAULT_MAX_FILES: usize = 10;
+
+/// Directory of monitoring logs, unless `--monitor-dir` is given
+const DEFAULT_MONITOR_DIR: &str = ".";
+
+/// Flat-form modes that analyze something other than whole files, which the options
+/// reporting on a file's band shares cannot be used with
+const WHOLE_FILE_ONLY: [&str; 9] = [
+    "time",
+    "follow",
+    "monitor",
+    "batch",
+    "watch",
+    "live",
+    "tui",
+    "verify_tone",
+    "ir",
+];
+
+#[derive(Parser)]
+#[command(
+    name = "bandstat",
+    version,
+    about = "Audio frequency band analyzer with K-weighting and dynamics analysis",
+    after_help = "Examples:
+  bandstat analyze audio.wav                           Single file analysis
+  bandstat analyze audio.wav --image chart.png -w      Chart with K-weighting
+  bandstat compare my_mix.wav ref.wav                  Compare files (first is base)
+  bandstat compare a.wav b.wav --image chart.png       Comparison chart output
+  bandstat compare a.wav b.wav --sonify-diff diff.wav  Listen to what B changes relative to A
+  bandstat compare master.wav encoded.mp3 --align      Compare the same span despite encoder delay
+  bandstat compare a.wav b.wav --label B=\"Mix v3\"      Name files instead of showing basenames
+  bandstat timeline -i 10 -w audio.wav                 10s intervals, K-weighted
+  bandstat timeline --follow recording.wav             Timeline of a file still being recorded
+  bandstat timeline -i 5 --browse set.wav              Scroll through the intervals of a long set
+  bandstat timeline -i 5 --loudness mix.wav            Plus the loudest momentary and short-term LUFS per interval
+  bandstat chart a.wav --image timeline=t.png          Charts only, without the tables
+  bandstat gen sine tone.wav --freq 1000               1 kHz test tone at -20 dBFS
+  bandstat mix.wav --report delivery.md.hbs -o out.md  Delivery report from a template
+  bandstat --format json mix.wav                       Results as one JSON document
+  bandstat audio.wav --image-loudness lufs.png         Short-term LUFS chart (low/mid/high)
+  bandstat --duration 60 https://host/live.mp3         First minute of an HTTP stream
+  bandstat --start 1:30 --end 2:00 mix.wav             Only the chorus, without exporting a clip
+  bandstat --weighting c live_set.wav                  C-weighted band levels, as an SPL meter reads
+  bandstat --monitor --rotate 1h URL                   Log a live stream to hourly NDJSON files
+  bandstat --verify-tone 1000:-20 tone.wav             Check a 1 kHz / -20 dBFS calibration tone
+  bandstat --ipc                                       JSON-RPC server for GUI front-ends
+  ls *.wav | bandstat --batch -                        One row per file, paths read from stdin
+  bandstat --batch lib/*.flac --image c/{name}.png     Batch with a stats chart per file
+  bandstat batch -r --summary --csv lib.csv library/   Summary row per file in a folder tree, plus CSV
+  bandstat watch bounces/                              One row per file bounced into a folder
+  bandstat album.cue                                   Band shares and dynamics per track of an image
+  bandstat live --device Scarlett -w                  Band shares of an audio input (live feature)
+  bandstat analyze --tui -w mix.wav                    Terminal meter while the file plays through
+  bandstat diff-results a.json b.json                  Compare saved --format json results
+  bandstat trend v*.json --image trend.png             Chart how saved runs evolved
+  bandstat bands --format json                         Band layout for scripts (labels, edges, colors)
+  bandstat selftest                                    Verify the analysis pipeline
+  bandstat gui audio.wav                               Interactive viewer (gui feature)
+
+Without a subcommand, the mode follows from the files and flags: one file is analyzed,
+several are compared, and --time selects the timeline (bandstat a.wav b.wav, bandstat --time a.wav).",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    flat: FlatArgs,
+
+    /// Read defaults from this TOML file instead of ~/.config/bandstat/config.toml (or $BANDSTAT_CONFIG)
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<String>,
+
+    /// Ignore the config file
+    #[arg(long, global = true, conflicts_with = "config")]
+    no_config: bool,
+}
+
+/// Options of the flat invocation, where files and flags select the mode; the analysis
+/// subcommands resolve to the same options. Flags of modes that exclude each other conflict
+/// in clap; options that need another flag or depend on the files given are checked in
+/// `run`, since clap skips a `requires` whose flag conflicts with one that was given.
+#[derive(clap::Args)]
+struct FlatArgs {
+    /// Audio files or http(s):// URLs to analyze (WAV, AIFF, MP3, FLAC). Up to 10 files for comparison (see --max-files), any number with --batch. A single .cue sheet analyzes its image file track by track.
+    #[arg(required_unless_present_any = ["ipc", "live"])]
+    files: Vec<String>,
+
+    /// Most files accepted for comparison (up to 26); 5 or more use a band x file matrix layout
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_FILES)]
+    max_files: usize,
+
+    /// Analyze each file on its own instead of comparing them, one row per file;
+    /// a `-` file reads more paths from stdin, one per line
+    #[arg(long, conflicts_with_all = ["time", "follow", "monitor", "verify_tone", "report", "label", "sonify_diff", "align", "image_loudness"])]
+    batch: bool,
+
+    /// Batch mode: also analyze the audio files in subdirectories of directory arguments
+    #[arg(long)]
+    recursive: bool,
+
+    /// Batch mode: print each file's largest bands, K-weighted LOW/MID/HIGH split, and average
+    /// dynamics instead of every band
+    #[arg(long)]
+    summary: bool,
+
+    /// Batch mode: also write each file's summary and band shares to this CSV file
+    #[arg(long, value_name = "PATH")]
+    csv: Option<String>,
+
+    /// Watch a directory, printing one row per audio file added to or changed in it
+    #[arg(long, conflicts_with_all = ["time", "follow", "monitor", "batch", "verify_tone", "report", "image", "image_loudness"])]
+    watch: bool,
+
+    /// Capture from an audio input device instead of reading files, one row per interval
+    /// (default: 1 s; needs the `live` feature)
+    #[arg(long, conflicts_with_all = ["files", "ipc", "time", "follow", "monitor", "batch", "watch", "verify_tone", "ir", "image", "image_loudness", "report", "active_only", "lfe"])]
+    live: bool,
+
+    /// Live mode: input device to capture from, by name or a unique part of it (default:
+    /// the system's default input)
+    #[arg(long, value_name = "NAME")]
+    device: Option<String>,
+
+    /// Play a single file through the analysis in a full-screen terminal meter: animated
+    /// band bars, the rolling K-weighted LOW/MID/HIGH balance, and short-term dynamics
+    #[arg(long, conflicts_with_all = ["time", "follow", "monitor", "batch", "watch", "verify_tone", "ir", "image", "image_loudness", "report", "output", "active_only", "lfe"])]
+    tui: bool,
+
+    /// Files analyzed at once in comparisons and batches, and threads rendering batch
+    /// charts (default: one per CPU core)
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<usize>,
+
+    /// Timeline analysis mode (band distribution over time)
+    #[arg(short, long)]
+    time: bool,
+
+    /// Timeline interval in seconds (minimum: 1, default: 20)
+    #[arg(short, long, value_name = "SECONDS")]
+    interval: Option<u32>,
+
+    /// After the timeline, print one aggregated row per chapter of this length (e.g. 30m, 1h)
+    #[arg(long, value_name = "PERIOD", value_parser = parse_period)]
+    chapters_every: Option<u64>,
+
+    /// Timeline mode: browse the intervals interactively after the analysis (arrow keys
+    /// scroll, space marks a selection, e exports it as JSON)
+    #[arg(long, conflicts_with_all = ["follow", "output"])]
+    browse: bool,
+
+    /// Timeline mode: add columns with the loudest momentary (400 ms) and short-term (3 s)
+    /// loudness of each interval in LUFS
+    #[arg(long)]
+    loudness: bool,
+
+    /// Use K-weighted values for analysis/chart output
+    #[arg(short, long)]
+    weighted: bool,
+
+    #[command(flatten)]
+    tables: TableOptions,
+
+    #[command(flatten)]
+    charts: ChartOptions,
+
+    /// Name a compared file in tables, charts, and reports, e.g. A="My Mix v3" (repeatable)
+    #[arg(long, value_name = "LETTER=NAME", value_parser = parse_file_label)]
+    label: Vec<(usize, String)>,
+
+    /// Write a noise preview of the [B]-[A] band difference as WAV (comparison mode)
+    #[arg(long, value_name = "PATH")]
+    sonify_diff: Option<String>,
+
+    /// Sample format of written WAV files: dithered 16- or 24-bit PCM, or 32-bit float
+    #[arg(long, value_enum, default_value = "pcm16", value_name = "FORMAT")]
+    export_format: ExportFormat,
+
+    /// Align compared files to [A] (offsets up to 10 s) and analyze only the span all cover
+    #[arg(long)]
+    align: bool,
+
+    /// Render results through a Handlebars template, or write a self-contained HTML report
+    /// with embedded charts when given a .html path
+    #[arg(long, value_name = "TEMPLATE|PATH.html", conflicts_with_all = ["monitor", "verify_tone"])]
+    report: Option<String>,
+
+    #[command(flatten)]
+    settings: AnalysisSettings,
+
+    /// Add rows with the dynamics of the K-weighted band power to the dynamics tables
+    #[arg(long, conflicts_with_all = WHOLE_FILE_ONLY)]
+    weighted_dynamics: bool,
+
+    /// Report the % of Raw energy below or above a frequency, e.g. below:100 (repeatable)
+    #[arg(long = "metric", value_name = "below:HZ|above:HZ", value_parser = parse_metric, conflicts_with_all = WHOLE_FILE_ONLY)]
+    metrics: Vec<EnergyMetric>,
+
+    /// Compare the band shares with a frequency/dB curve from a CSV file, e.g. exported from another analyzer
+    #[arg(long, value_name = "CSV", conflicts_with_all = WHOLE_FILE_ONLY)]
+    target_curve: Option<String>,
+
+    /// Keep bands out of the percentage total but list them as "excl", e.g. DC,AIR
+    #[arg(long, value_name = "LABELS", conflicts_with_all = WHOLE_FILE_ONLY)]
+    exclude_bands: Option<String>,
+
+    /// Report the LFE channel of a surround file: its bands and its level against the main channels
+    #[arg(long, conflicts_with_all = ["time", "follow", "monitor", "batch", "watch", "verify_tone", "ir"])]
+    lfe: bool,
+
+    /// Also compare the sorted per-frame band shares, which edits and tempo changes don't move (comparison mode)
+    #[arg(long)]
+    match_distributions: bool,
+
+    /// Analyze only the regions where the program is active, skipping long pauses
+    #[arg(long, conflicts_with_all = ["time", "follow", "monitor", "verify_tone"])]
+    active_only: bool,
+
+    /// Keep analyzing a file that is still being written, appending timeline rows as audio arrives
+    #[arg(long, conflicts_with = "verify_tone")]
+    follow: bool,
+
+    /// Stop following once the file has not grown for this many seconds
+    #[arg(long, value_name = "SECONDS", default_value_t = DEFAULT_FOLLOW_IDLE)]
+    follow_idle: f64,
+
+    /// Continuously analyze the input, logging one NDJSON record per interval
+    #[arg(long, conflicts_with_all = ["time", "image", "verify_tone"])]
+    monitor: bool,
+
+    /// Start a new monitoring log file every period (e.g. 1h, 30m)
+    #[arg(long, value_name = "PERIOD", value_parser = parse_period)]
+    rotate: Option<u64>,
+
+    /// Directory for monitoring logs
+    #[arg(long, value_name = "DIR", default_value = DEFAULT_MONITOR_DIR)]
+    monitor_dir: String,
+
+    /// Keep a rolling 24-hour band heatmap PNG updated while monitoring
+    #[arg(long, value_name = "PATH")]
+    heatmap: Option<String>,
+
+    /// Alert rules (TOML) evaluated on every monitoring interval
+    #[arg(long, value_name = "FILE")]
+    alert_rules: Option<String>,
+
+    /// Serve JSON-RPC analysis requests on stdin/stdout (one message per line) until stdin closes
+    #[arg(long, conflicts_with_all = ["files", "time", "follow", "monitor", "image", "report", "verify", "verify_tone"])]
+    ipc: bool,
+
+    /// Verify a calibration tone (e.g. 1000:-20 for 1 kHz at -20 dBFS)
+    #[arg(long, value_name = "FREQ:DBFS", conflicts_with_all = ["time", "image"])]
+    verify_tone: Option<ToneSpec>,
+
+    /// Treat the file as an impulse response and report per-band gain and group delay
+    #[arg(long, conflicts_with_all = ["time", "follow", "monitor", "batch", "watch", "verify_tone", "image", "image_loudness", "report", "active_only"])]
+    ir: bool,
+
+    /// Drop the tables instead of printing them (`bandstat chart`)
+    #[arg(skip)]
+    discard_tables: bool,
+}
+
+impl FlatArgs {
+    /// Options of a subcommand run: the given files and groups, every mode option off
+    fn new(
+        files: Vec<String>,
+        tables: TableOptions,
+        charts: ChartOptions,
+        settings: AnalysisSettings,
+    ) -> Self {
+        FlatArgs {
+            files,
+            max_files: DEFAULT_MAX_FILES,
+            batch: false,
+            recursive: false,
+            summary: false,
+            csv: None,
+            watch: false,
+            live: false,
+            device: None,
+            tui: false,
+            jobs: None,
+            time: false,
+            interval: None,
+            chapters_every: None,
+            browse: false,
+            loudness: false,
+            weighted: false,
+            tables,
+            charts,
+            label: Vec::new(),
+            sonify_diff: None,
+            export_format: ExportFormat::default(),
+            align: false,
+            report: None,
+            settings,
+            weighted_dynamics: false,
+            metrics: Vec::new(),
+            target_curve: None,
+            exclude_bands: None,
+            lfe: false,
+            match_distributions: false,
+            active_only: false,
+            follow: false,
+            follow_idle: DEFAULT_FOLLOW_IDLE,
+            monitor: false,
+            rotate: None,
+            monitor_dir: DEFAULT_MONITOR_DIR.to_string(),
+            heatmap: None,
+            alert_rules: None,
+            ipc: false,
+            verify_tone: None,
+            ir: false,
+            discard_tables: false,
+        }
+    }
+}
+
+/// How results are printed
+#[derive(clap::Args, Default)]
+struct TableOptions {
+    /// Suppress explanations (show data only)
+    #[arg(short, long, env = "BANDSTAT_QUIET", value_parser = FalseyValueParser::new())]
+    quiet: bool,
+
+    /// Disable colored output
+    #[arg(long, env = "BANDSTAT_NO_COLOR", value_parser = FalseyValueParser::new())]
+    no_color: bool,
+
+    /// Plain ASCII output without colors, for legacy consoles that mangle the tables
+    #[arg(long, env = "BANDSTAT_ASCII", value_parser = FalseyValueParser::new())]
+    ascii: bool,
+
+    /// Decimal places of percentages and dB values in tables, reports, and charts (default: 1)
+    #[arg(long, env = "BANDSTAT_DECIMALS", value_name = "N", value_parser = clap::value_parser!(u8).range(0..=6))]
+    decimals: Option<u8>,
+
+    /// Round JSON and NDJSON values to the --decimals places instead of full precision
+    #[arg(long, env = "BANDSTAT_ROUND_OUTPUT", value_parser = FalseyValueParser::new())]
+    round_output: bool,
+
+    /// Print results as text tables or as one JSON document (stats, comparison, timeline, and impulse response modes)
+    #[arg(
+        long,
+        env = "BANDSTAT_FORMAT",
+        value_enum,
+        default_value = "text",
+        value_name = "FORMAT"
+    )]
+    format: OutputFormat,
+
+    /// Table column headers: band labels, band indices, or lower edge frequencies
+    #[arg(
+        long,
+        env = "BANDSTAT_HEADER_STYLE",
+        value_enum,
+        default_value = "labels",
+        value_name = "STYLE"
+    )]
+    header_style: HeaderStyle,
+
+    /// Time column of timeline tables and time axis of charts: mm:ss, hh:mm:ss, seconds, or samples
+    #[arg(
+        long,
+        env = "BANDSTAT_TIME_FORMAT",
+        value_enum,
+        default_value = "mm:ss",
+        value_name = "FORMAT"
+    )]
+    time_format: TimeFormat,
+
+    /// Write the tables (or JSON, or the --report template output) to a file instead of stdout
+    #[arg(short, long, value_name = "PATH")]
+    output: Option<String>,
+}
+
+/// Which charts are rendered
+#[derive(clap::Args, Default)]
+struct ChartOptions {
+    /// Output chart as PNG image; repeat with KIND=PATH (stats, compare, timeline, loudness) for several charts
+    #[arg(long, value_name = "[KIND=]PATH", value_parser = chart::parse_image_spec)]
+    image: Vec<chart::ImageSpec>,
+
+    /// Output a short-term LUFS chart with low/mid/high band loudness (same as --image loudness=PATH)
+    #[arg(long, value_name = "PATH")]
+    image_loudness: Option<String>,
+
+    /// Font family or font file (.ttf/.otf) for chart labels
+    #[arg(long, value_name = "FONT")]
+    chart_font: Option<String>,
+
+    /// Size of PNG charts in pixels, e.g. 1400x600 (default: 2800x1200)
+    #[arg(long, value_name = "WIDTHxHEIGHT")]
+    chart_size: Option<String>,
+}
+
+/// Band layout: labels, edges, and groups
+#[derive(clap::Args, Default)]
+struct BandOptions {
+    /// Built-in band layout to analyze with: standard, octave, mel:N (N mel-spaced bands), mixer, mastering, broadcast, or sub-focus
+    #[arg(
+        long = "bands",
+        value_name = "PRESET",
+        value_parser = parse_band_preset,
+        conflicts_with = "bands_file"
+    )]
+    preset: Option<BandPreset>,
+
+    /// Replace the standard bands with N log-spaced bands across --range (default: 20-20000)
+    #[arg(long, value_name = "N", conflicts_with_all = ["preset", "bands_file"])]
+    num_bands: Option<usize>,
+
+    /// Replace the 14 standard bands with the labels and Hz ranges in a TOML or JSON file
+    #[arg(long, value_name = "PATH")]
+    bands_file: Option<String>,
+
+    /// Custom band labels, comma-separated from lowest to highest band
+    #[arg(long, value_name = "LABELS")]
+    band_labels: Option<String>,
+
+    /// Leave bands out of percentages, tables, alert rules, and charts, e.g. DC,AIR
+    #[arg(long, value_name = "LABELS")]
+    ignore_bands: Option<String>,
+
+    /// Analyze only this frequency range in Hz, e.g. 20-16000 (bands are clipped to it)
+    #[arg(long, value_name = "LOW-HIGH")]
+    range: Option<String>,
+
+    /// Lower edges in Hz of the MID and HIGH summary groups (default: 250,4000)
+    #[arg(long, value_name = "HZ,HZ")]
+    group_edges: Option<String>,
+
+    /// Share energy near band edges over a crossover this many octaves wide (default: 0, hard edges)
+    #[arg(long, value_name = "OCTAVES")]
+    crossover: Option<f64>,
+}
+
+impl BandOptions {
+    /// Fill the options not given on the command line from the config file
+    fn or_config(self, config: &BandConfig) -> Self {
+        // The layout comes from the command line or the config file as a whole
+        let given = self.preset.is_some() || self.bands_file.is_some() || self.num_bands.is_some();
+        let (preset, bands_file, num_bands) = if given {
+            (self.preset, self.bands_file, self.num_bands)
+        } else {
+            (config.preset, config.file.clone(), config.count)
+        };
+        BandOptions {
+            preset,
+            bands_file,
+            num_bands,
+            band_labels: self.band_labels.or_else(|| config.labels()),
+            ignore_bands: self.ignore_bands.or_else(|| config.ignore()),
+            range: self.range.or_else(|| config.range.clone()),
+            group_edges: self.group_edges.or_else(|| config.group_edges()),
+            crossover: self.crossover.or(config.crossover),
+        }
+    }
+
+    /// Apply the band layout to `config`
+    fn apply(&self, mut config: AnalysisConfig) -> Result<AnalysisConfig, String> {
+        let layouts = [
+            self.preset.is_some(),
+            self.bands_file.is_some(),
+            self.num_bands.is_some(),
+        ];
+        if layouts.iter().filter(|&&given| given).count() > 1 {
+            return Err(
+                "only one of --bands, --bands-file, and --num-bands can be used".to_string(),
+            );
+        }
+        if let Some(preset) = self.preset {
+            config = config.with_bands(preset.bands())?;
+        }
+        if let Some(count) = self.num_bands {
+            config = config.with_bands(analysis::log_bands(count, self.range.as_deref())?)?;
+        }
+        if let Some(ref path) = self.bands_file {
+            let bands = load_bands_file(path)?;
+            config = config
+                .with_bands(bands)
+                .map_err(|e| format!("--bands-file {}: {}", path, e))?;
+        }
+        config = config.with_crossover(self.crossover.unwrap_or(0.0))?;
+        if let Some(ref labels) = self.band_labels {
+            config = config.with_band_labels(labels)?;
+        }
+        if let Some(ref labels) = self.ignore_bands {
+            config = config.with_ignored_bands(labels)?;
+        }
+        if let Some(ref range) = self.range {
+            config = config.with_range(range)?;
+        }
+        if let Some(ref edges) = self.group_edges {
+            config = config.with_group_edges(edges)?;
+        }
+        Ok(config)
+    }
+}
+
+/// How the audio is split into bands and which frames count
+#[derive(clap::Args)]
+struct AnalysisSettings {
+    #[command(flatten)]
+    bands: BandOptions,
+
+    /// Numeric precision of the FFT and power accumulation
+    #[arg(long, value_enum, default_value = "f32", value_name = "PRECISION")]
+    precision: analysis::Precision,
+
+    /// How K-weighting is applied: scale the power spectrum by the filter's response
+    /// (spectral), or run the BS.1770 filter on the samples before a second FFT (filter, slower)
+    #[arg(long, value_enum, default_value = "spectral", value_name = "MODE")]
+    kweight_mode: analysis::KWeightMode,
+
+    /// Weighting of the weighted (-w, K-wt) values: BS.1770 K, C, flat Z, or the ISO 226
+    /// equal-loudness contour at a playback level, e.g. iso226:75. LUFS stay K-weighted.
+    #[arg(long, default_value = "k", value_name = "WEIGHTING", value_parser = analysis::parse_weighting)]
+    weighting: analysis::Weighting,
+
+    /// Weight the weighted (-w, K-wt) values by a frequency response instead, e.g. a headphone
+    /// correction: a CSV of frequency/dB pairs, interpolated onto the FFT bins
+    #[arg(long, value_name = "FILE", conflicts_with = "weighting")]
+    weighting_file: Option<String>,
+
+    /// Use portable scalar FFT and resampling so output is identical across runs and machines
+    #[arg(long)]
+    deterministic: bool,
+
+    /// Take the bands below 250 Hz from a 4x longer FFT, for finer sub-bass resolution (slower)
+    #[arg(long)]
+    multi_resolution: bool,
+
+    /// Zero-pad each FFT frame to N times its length, for finer bin spacing at the band edges
+    /// (a power of two up to 16, slower)
+    #[arg(long, value_name = "FACTOR")]
+    zero_pad: Option<usize>,
+
+    /// Alias for the default BS.1770 gating of the K-weighted stats (-70 LUFS absolute,
+    /// -10 LU relative), for scripts that spell it out; changes nothing on its own
+    #[arg(long)]
+    gated: bool,
+
+    /// Keep silent and very quiet frames in the K-weighted stats (no BS.1770 gating)
+    #[arg(long, conflicts_with = "gated")]
+    no_gate: bool,
+
+    /// Drop frames quieter than this broadband level from all stats, e.g. -60dB
+    #[arg(long, value_name = "DB", allow_hyphen_values = true)]
+    gate: Option<String>,
+
+    /// Skip the audio before this position, as seconds or mm:ss (timeline times count from it)
+    #[arg(long, value_name = "TIME", value_parser = audio::parse_position)]
+    start: Option<f64>,
+
+    /// Stop analyzing at this position, as seconds or mm:ss
+    #[arg(long, value_name = "TIME", value_parser = audio::parse_position, conflicts_with = "duration")]
+    end: Option<f64>,
+
+    /// Analyze only N seconds from --start or the beginning (needed for endless HTTP streams)
+    #[arg(long, value_name = "SECONDS")]
+    duration: Option<f64>,
+
+    /// Quality of the conversion of other sample rates to 48 kHz; fast rolls off the top of
+    /// the AIR band of 44.1 kHz sources
+    #[arg(long, value_enum, default_value = "fast", value_name = "QUALITY")]
+    resample_quality: audio::ResampleQuality,
+
+    /// High-pass the audio at this cutoff before analysis (12 dB/octave), e.g. 20 to remove subsonic noise
+    #[arg(long, value_name = "HZ")]
+    highpass: Option<f64>,
+
+    /// Decode formats bandstat can't read through ffmpeg (BANDSTAT_FFMPEG or ffmpeg on the PATH)
+    #[arg(long)]
+    ffmpeg_fallback: bool,
+
+    /// Hold at most this many MB of decoded audio (e.g. on low-RAM CI runners); analyses
+    /// that need the whole file in memory fail when it doesn't fit
+    #[arg(long, value_name = "MB")]
+    max_memory: Option<usize>,
+
+    /// Stop reading an http(s):// input after this many MB; longer downloads are refused
+    /// up front when the server tells their size (unless --duration stops early)
+    #[arg(long, value_name = "MB", default_value_t = audio::DEFAULT_MAX_DOWNLOAD_MB)]
+    max_download: u64,
+
+    /// Debug: check energy conservation (Parseval, band coverage) on every FFT frame
+    #[arg(long, conflicts_with = "multi_resolution")]
+    verify: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Analyze one file: band shares, dynamics, and loudness
+    Analyze(AnalyzeArgs),
+
+    /// Compare files against the first one, [A]
+    Compare(CompareArgs),
+
+    /// Show how the band shares change over time
+    Timeline(TimelineArgs),
+
+    /// Analyze each file on its own, one row per file; directories add the audio files in them
+    Batch(BatchArgs),
+
+    /// Analyze audio files as they are added to or changed in a folder, one row per file
+    Watch(WatchArgs),
+
+    /// Capture from an audio interface and print the band shares as they come in (needs
+    /// the `live` feature)
+    Live(LiveArgs),
+
+    /// Render charts without printing the tables
+    Chart(ChartArgs),
+
+    /// Write a test signal as a mono WAV file
+    Gen(GenArgs),
+
+    /// Print the band layout a run with the same band options would use, without analyzing
+    Bands(BandsArgs),
+
+    /// Run the analysis pipeline on generated test signals and report pass/fail
+    Selftest {
+        /// Only show failed checks
+        #[arg(short, long)]
+        quiet: bool,
+
+        /// Disable colored output
+        #[arg(long, env = "BANDSTAT_NO_COLOR", value_parser = FalseyValueParser::new())]
+        no_color: bool,
+    },
+
+    /// Compare results saved with `--format json` without the audio files
+    DiffResults {
+        /// Two stats or compare results; their files become [A], [B], ... in order
+        #[arg(num_args = 2, required = true, value_name = "RESULT.json")]
+        results: Vec<String>,
+
+        /// Suppress explanations (show data only)
+        #[arg(short, long)]
+        quiet: bool,
+
+        /// Disable colored output
+        #[arg(long, env = "BANDSTAT_NO_COLOR", value_parser = FalseyValueParser::new())]
+        no_color: bool,
+
+        /// Decimal places of the printed values (default: 1)
+        #[arg(long, env = "BANDSTAT_DECIMALS", value_name = "N", value_parser = clap::value_parser!(u8).range(0..=6))]
+        decimals: Option<u8>,
+    },
+
+    /// Chart band shares and loudness across results saved with `--format json`
+    Trend {
+        /// Stats or compare results in run order (comparisons contribute their [A] file)
+        #[arg(required = true, value_name = "RESULT.json")]
+        results: Vec<String>,
+
+        /// Bands to follow instead of the LOW/MID/HIGH totals (e.g. BASS,PRES)
+        #[arg(long = "band", value_delimiter = ',', value_name = "LABEL")]
+        bands: Vec<String>,
+
+        /// Use K-weighted percentages
+        #[arg(short, long)]
+        weighted: bool,
+
+        /// Output the trend chart as PNG
+        #[arg(long, value_name = "PATH")]
+        image: Option<String>,
+
+        /// Decimal places of the printed values (default: 1)
+        #[arg(long, env = "BANDSTAT_DECIMALS", value_name = "N", value_parser = clap::value_parser!(u8).range(0..=6))]
+        decimals: Option<u8>,
+    },
+
+    /// Open an interactive window with spectrum, band table, and timeline (needs the `gui` feature)
+    Gui {
+        /// Audio file or http(s):// URL to view
+        file: String,
+    },
+}
+
+#[derive(clap::Args)]
+struct BandsArgs {
+    /// Print the layout as a table or as one JSON document
+    #[arg(
+        long,
+        env = "BANDSTAT_FORMAT",
+        value_enum,
+        default_value = "text",
+        value_name = "FORMAT"
+    )]
+    format: OutputFormat,
+
+    #[command(flatten)]
+    bands: BandOptions,
+}
+
+#[derive(clap::Args)]
+struct AnalyzeArgs {
+    /// Audio file or http(s):// URL to analyze (WAV, AIFF, MP3, FLAC)
+    file: String,
+
+    /// Use K-weighted values for the charts
+    #[arg(short, long)]
+    weighted: bool,
+
+    /// Treat the file as an impulse response and report per-band gain and group delay
+    #[arg(long, conflicts_with_all = ["tui", "lfe", "image", "image_loudness", "report", "active_only", "weighted_dynamics", "metrics", "target_curve", "exclude_bands"])]
+    ir: bool,
+
+    /// Play the file through the analysis in a full-screen terminal meter instead of
+    /// printing tables
+    #[arg(long, conflicts_with_all = ["lfe", "image", "image_loudness", "report", "output", "active_only", "weighted_dynamics", "metrics", "target_curve", "exclude_bands"])]
+    tui: bool,
+
+    /// Add rows with the dynamics of the K-weighted band power to the dynamics tables
+    #[arg(long)]
+    weighted_dynamics: bool,
+
+    /// Report the % of Raw energy below or above a frequency, e.g. below:100 (repeatable)
+    #[arg(long = "metric", value_name = "below:HZ|above:HZ", value_parser = parse_metric)]
+    metrics: Vec<EnergyMetric>,
+
+    /// Compare the band shares with a frequency/dB curve from a CSV file, e.g. exported from another analyzer
+    #[arg(long, value_name = "CSV")]
+    target_curve: Option<String>,
+
+    /// Keep bands out of the percentage total but list them as "excl", e.g. DC,AIR
+    #[arg(long, value_name = "LABELS")]
+    exclude_bands: Option<String>,
+
+    /// Report the LFE channel of a surround file: its bands and its level against the main channels
+    #[arg(long)]
+    lfe: bool,
+
+    /// Analyze only the regions where the program is active, skipping long pauses
+    #[arg(long)]
+    active_only: bool,
+
+    /// Render results through a Handlebars template, or write a self-contained HTML report
+    /// with embedded charts when given a .html path
+    #[arg(long, value_name = "TEMPLATE|PATH.html")]
+    report: Option<String>,
+
+    #[command(flatten)]
+    tables: TableOptions,
+
+    #[command(flatten)]
+    charts: ChartOptions,
+
+    #[command(flatten)]
+    settings: AnalysisSettings,
+}
+
+#[derive(clap::Args)]
+struct CompareArgs {
+    /// Audio files or http(s):// URLs to compare; the first is [A], the base
+    #[arg(num_args = 2.., required = true, value_name = "FILE")]
+    files: Vec<String>,
+
+    /// Most files accepted (up to 26); 5 or more use a band x file matrix layout
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_FILES)]
+    max_files: usize,
+
+    /// Name a file in tables, charts, and reports, e.g. A="My Mix v3" (repeatable)
+    #[arg(long, value_name = "LETTER=NAME", value_parser = parse_file_label)]
+    label: Vec<(usize, String)>,
+
+    /// Write a noise preview of the [B]-[A] band difference as WAV
+    #[arg(long, value_name = "PATH")]
+    sonify_diff: Option<String>,
+
+    /// Sample format of the --sonify-diff WAV: dithered 16- or 24-bit PCM, or 32-bit float
+    #[arg(long, value_enum, default_value = "pcm16", value_name = "FORMAT")]
+    export_format: ExportFormat,
+
+    /// Align the files to [A] (offsets up to 10 s) and analyze only the span all cover
+    #[arg(long)]
+    align: bool,
+
+    /// Files analyzed at once (default: one per CPU core)
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<usize>,
+
+    /// Add rows with the dynamics of the K-weighted band power to the dynamics tables
+    #[arg(long)]
+    weighted_dynamics: bool,
+
+    /// Report the % of Raw energy below or above a frequency, e.g. below:100 (repeatable)
+    #[arg(long = "metric", value_name = "below:HZ|above:HZ", value_parser = parse_metric)]
+    metrics: Vec<EnergyMetric>,
+
+    /// Compare the band shares with a frequency/dB curve from a CSV file, e.g. exported from another analyzer
+    #[arg(long, value_name = "CSV")]
+    target_curve: Option<String>,
+
+    /// Keep bands out of the percentage total but list them as "excl", e.g. DC,AIR
+    #[arg(long, value_name = "LABELS")]
+    exclude_bands: Option<String>,
+
+    /// Also compare the sorted per-frame band shares, which edits and tempo changes don't move
+    #[arg(long)]
+    match_distributions: bool,
+
+    /// Analyze only the regions where the program is active, skipping long pauses
+    #[arg(long)]
+    active_only: bool,
+
+    /// Render results through a Handlebars template, or write a self-contained HTML report
+    /// with embedded charts when given a .html path
+    #[arg(long, value_name = "TEMPLATE|PATH.html")]
+    report: Option<String>,
+
+    #[command(flatten)]
+    tables: TableOptions,
+
+    #[command(flatten)]
+    charts: ChartOptions,
+
+    #[command(flatten)]
+    settings: AnalysisSettings,
+}
+
+#[derive(clap::Args)]
+struct TimelineArgs {
+    /// Audio file or http(s):// URL to analyze (WAV, AIFF, MP3, FLAC)
+    file: String,
+
+    /// Interval of the rows in seconds (minimum: 1, default: 20)
+    #[arg(short, long, value_name = "SECONDS")]
+    interval: Option<u32>,
+
+    /// After the rows, print one aggregated row per chapter of this length (e.g. 30m, 1h)
+    #[arg(long, value_name = "PERIOD", value_parser = parse_period)]
+    chapters_every: Option<u64>,
+
+    /// Browse the intervals interactively after the analysis (arrow keys scroll, space
+    /// marks a selection, e exports it as JSON)
+    #[arg(long, conflicts_with_all = ["follow", "output"])]
+    browse: bool,
+
+    /// Add columns with the loudest momentary (400 ms) and short-term (3 s) loudness of
+    /// each interval in LUFS
+    #[arg(long)]
+    loudness: bool,
+
+    /// Use K-weighted values
+    #[arg(short, long)]
+    weighted: bool,
+
+    /// Keep analyzing a file that is still being written, appending rows as audio arrives
+    #[arg(long)]
+    follow: bool,
+
+    /// Stop following once the file has not grown for this many seconds
+    #[arg(long, value_name = "SECONDS", default_value_t = DEFAULT_FOLLOW_IDLE)]
+    follow_idle: f64,
+
+    /// Write a self-contained HTML report with embedded charts to this .html path
+    #[arg(long, value_name = "PATH.html")]
+    report: Option<String>,
+
+    #[command(flatten)]
+    tables: TableOptions,
+
+    #[command(flatten)]
+    charts: ChartOptions,
+
+    #[command(flatten)]
+    settings: AnalysisSettings,
+}
+
+#[derive(clap::Args)]
+struct BatchArgs {
+    /// Audio files, http(s):// URLs, or directories to analyze; `-` reads more paths from stdin
+    #[arg(required = true, value_name = "PATH")]
+    files: Vec<String>,
+
+    /// Also analyze the audio files in subdirectories of the directories
+    #[arg(short, long)]
+    recursive: bool,
+
+    /// Print each file's largest bands, K-weighted LOW/MID/HIGH split, and average dynamics
+    /// instead of every band
+    #[arg(long)]
+    summary: bool,
+
+    /// Also write each file's summary and band shares to this CSV file
+    #[arg(long, value_name = "PATH")]
+    csv: Option<String>,
+
+    /// Files analyzed at once, and threads rendering charts (default: one per CPU core)
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<usize>,
+
+    /// Use K-weighted values
+    #[arg(short, long)]
+    weighted: bool,
+
+    #[command(flatten)]
+    tables: TableOptions,
+
+    #[command(flatten)]
+    charts: ChartOptions,
+
+    #[command(flatten)]
+    settings: AnalysisSettings,
+}
+
+#[derive(clap::Args)]
+struct WatchArgs {
+    /// Folder to watch, e.g. the bounce folder of a mixing session
+    #[arg(value_name = "DIR")]
+    dir: String,
+
+    /// Use K-weighted values
+    #[arg(short, long)]
+    weighted: bool,
+
+    #[command(flatten)]
+    tables: TableOptions,
+
+    #[command(flatten)]
+    settings: AnalysisSettings,
+}
+
+#[derive(clap::Args)]
+struct LiveArgs {
+    /// Input device to capture from, by name or a unique part of it (default: the system's
+    /// default input)
+    #[arg(long, value_name = "NAME")]
+    device: Option<String>,
+
+    /// Interval of the rows in seconds (minimum: 1, default: 1)
+    #[arg(short, long, value_name = "SECONDS")]
+    interval: Option<u32>,
+
+    /// Use K-weighted values
+    #[arg(short, long)]
+    weighted: bool,
+
+    #[command(flatten)]
+    tables: TableOptions,
+
+    #[command(flatten)]
+    settings: AnalysisSettings,
+}
+
+#[derive(clap::Args)]
+#[command(group(ArgGroup::new("chart_image").args(["image", "image_loudness"]).required(true).multiple(true)))]
+struct ChartArgs {
+    /// One file for stats, timeline, and loudness charts, or several for a comparison chart
+    #[arg(required = true, value_name = "FILE")]
+    files: Vec<String>,
+
+    /// Use K-weighted values
+    #[arg(short, long)]
+    weighted: bool,
+
+    /// Interval of timeline charts in seconds (minimum: 1, default: 20)
+    #[arg(short, long, value_name = "SECONDS")]
+    interval: Option<u32>,
+
+    /// Name a compared file in the chart, e.g. A="My Mix v3" (repeatable)
+    #[arg(long, value_name = "LETTER=NAME", value_parser = parse_file_label)]
+    label: Vec<(usize, String)>,
+
+    /// Decimal places of the chart labels (default: 1)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u8).range(0..=6))]
+    decimals: Option<u8>,
+
+    /// Time axis labels of timeline and loudness charts: mm:ss, hh:mm:ss, seconds, or samples
+    #[arg(
+        long,
+        env = "BANDSTAT_TIME_FORMAT",
+        value_enum,
+        default_value = "mm:ss",
+        value_name = "FORMAT"
+    )]
+    time_format: TimeFormat,
+
+    #[command(flatten)]
+    charts: ChartOptions,
+
+    #[command(flatten)]
+    settings: AnalysisSettings,
+}
+
+#[derive(clap::Args)]
+struct GenArgs {
+    /// Signal to write
+    #[arg(value_enum)]
+    signal: Signal,
+
+    /// WAV file to write
+    path: String,
+
+    /// Frequency of the sine in Hz
+    #[arg(long, value_name = "HZ")]
+    freq: Option<f32>,
+
+    /// Length in seconds
+    #[arg(long, value_name = "SECONDS", default_value = "30")]
+    duration: f64,
+
+    /// Peak level in dBFS
+    #[arg(
+        long,
+        value_name = "DBFS",
+        default_value = "-20",
+        allow_hyphen_values = true
+    )]
+    level: f64,
+
+    /// Sample rate in Hz
+    #[arg(long, value_name = "HZ", default_value = "48000")]
+    sample_rate: u32,
+
+    /// Sample format: dithered 16- or 24-bit PCM, or 32-bit float
+    #[arg(long, value_enum, default_value = "pcm16", value_name = "FORMAT")]
+    export_format: ExportFormat,
+}
+
+/// Parse the command line and run the requested mode
+pub fn main() {
+    // Windows consoles interpret ANSI colors only once virtual terminal processing is
+    // enabled; consoles older than Windows 10 cannot, so they get plain text
+    #[cfg(windows)]
+    if colored::control::set_virtual_terminal(true).is_err() {
+        colored::control::set_override(false);
+    }
+
+    let args = Args::parse();
+
+    let mut config = if args.no_config {
+        Config::default()
+    } else {
+        load_config(args.config.as_deref()).unwrap_or_else(|e| {
+            print_error(&e);
+            std::process::exit(1);
+        })
+    };
+    if let Err(e) = config.apply_env(|name| std::env::var(name).ok()) {
+        print_error(&e);
+        std::process::exit(1);
+    }
+    if config.color == Some(false) {
+        colored::control::set_override(false);
+    }
+
+    // The analysis subcommands run like the flat invocation with their mode's options set
+    let (args, drop_report) = match args.command {
+        None => {
+            let drop_report = drop_launch_report(&args.flat.files);
+            (args.flat, drop_report)
+        }
+        Some(Command::Analyze(a)) => (
+            FlatArgs {
+                weighted: a.weighted,
+                ir: a.ir,
+                tui: a.tui,
+                weighted_dynamics: a.weighted_dynamics,
+                metrics: a.metrics,
+                target_curve: a.target_curve,
+                exclude_bands: a.exclude_bands,
+                lfe: a.lfe,
+                active_only: a.active_only,
+                report: a.report,
+                ..FlatArgs::new(vec![a.file], a.tables, a.charts, a.settings)
+            },
+            None,
+        ),
+        Some(Command::Compare(c)) => (
+            FlatArgs {
+                max_files: c.max_files,
+                label: c.label,
+                sonify_diff: c.sonify_diff,
+                export_format: c.export_format,
+                align: c.align,
+                jobs: c.jobs,
+                weighted_dynamics: c.weighted_dynamics,
+                metrics: c.metrics,
+                target_curve: c.target_curve,
+                exclude_bands: c.exclude_bands,
+                match_distributions: c.match_distributions,
+                active_only: c.active_only,
+                report: c.report,
+                ..FlatArgs::new(c.files, c.tables, c.charts, c.settings)
+            },
+            None,
+        ),
+        Some(Command::Timeline(t)) => (
+            FlatArgs {
+                time: true,
+                interval: t.interval,
+                chapters_every: t.chapters_every,
+                browse: t.browse,
+                loudness: t.loudness,
+                weighted: t.weighted,
+                follow: t.follow,
+                follow_idle: t.follow_idle,
+                report: t.report,
+                ..FlatArgs::new(vec![t.file], t.tables, t.charts, t.settings)
+            },
+            None,
+        ),
+        Some(Command::Batch(b)) => (
+            FlatArgs {
+                batch: true,
+                recursive: b.recursive,
+                summary: b.summary,
+                csv: b.csv,
+                jobs: b.jobs,
+                weighted: b.weighted,
+                ..FlatArgs::new(b.files, b.tables, b.charts, b.settings)
+            },
+            None,
+        ),
+        Some(Command::Watch(w)) => (
+            FlatArgs {
+                watch: true,
+                weighted: w.weighted,
+                ..FlatArgs::new(vec![w.dir], w.tables, ChartOptions::default(), w.settings)
+            },
+            None,
+        ),
+        Some(Command::Live(l)) => (
+            FlatArgs {
+                live: true,
+                device: l.device,
+                interval: l.interval,
+                weighted: l.weighted,
+                ..FlatArgs::new(Vec::new(), l.tables, ChartOptions::default(), l.settings)
+            },
+            None,
+        ),
+        Some(Command::Chart(c)) => {
+            // The charts come from the usual analysis; only its tables are dropped
+            let tables = TableOptions {
+                quiet: true,
+                decimals: c.decimals,
+                time_format: c.time_format,
+                ..TableOptions::default()
+            };
+            (
+                FlatArgs {
+                    discard_tables: true,
+                    weighted: c.weighted,
+                    interval: c.interval,
+                    label: c.label,
+                    ..FlatArgs::new(c.files, tables, c.charts, c.settings)
+                },
+                None,
+            )
+        }
+        Some(Command::Gen(g)) => {
+            if !run_gen(g) {
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::Bands(b)) => {
+            let display = DisplayOptions {
+                format: b.format,
+                ..DisplayOptions::default()
+            };
+            let analysis_config = b
+                .bands
+                .or_config(&config.bands)
+                .apply(AnalysisConfig::default())
+                .unwrap_or_else(|e| {
+                    print_error(&e);
+                    std::process::exit(1);
+                });
+            run_bands(&analysis_config, &display);
+            return;
+        }
+        Some(Command::Selftest { quiet, no_color }) => {
+            if no_color {
+                colored::control::set_override(false);
+            }
+            if !run_selftest(quiet) {
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(Command::DiffResults {
+            results,
+            quiet,
+            no_color,
+            decimals,
+        }) => {
+            if no_color {
+                colored::control::set_override(false);
+            }
+            let mut display = DisplayOptions::default();
+            if let Some(decimals) = decimals {
+                display.decimals = decimals as usize;
+            }
+            run_diff_results(&results, display, quiet);
+            return;
+        }
+        Some(Command::Trend {
+            results,
+            bands,
+            weighted,
+            image,
+            decimals,
+        }) => {
+            let mut display = DisplayOptions::default();
+            if let Some(decimals) = decimals {
+                display.decimals = decimals as usize;
+            }
+            let mut charts = chart::ChartOptions::default();
+            charts.decimals = display.decimals;
+            if let Some(ref size) = config.chart_size {
+                charts = charts.with_size(size).unwrap_or_else(|e| {
+                    print_error(&e);
+                    std::process::exit(1);
+                });
+            }
+            let weighted = weighted || config.weighted == Some(true);
+            run_trend(
+                &results,
+                &bands,
+                weighted,
+                image.as_deref(),
+                &display,
+                &charts,
+            );
+            return;
+        }
+        Some(Command::Gui { file }) => {
+            #[cfg(feature = "gui")]
+            {
+                if let Err(e) = crate::gui::run_gui(&file) {
+                    print_error(&e);
+                    std::process::exit(1);
+                }
+                return;
+            }
+            #[cfg(not(feature = "gui"))]
+            {
+                print_error(&format!(
+                    "cannot open {}: bandstat was built without the gui feature (rebuild with --features gui)",
+                    file
+                ));
+                std::process::exit(1);
+            }
+        }
+    };
+
+    run(args, &config, drop_report);
+}
+
+/// Analysis settings of the run; the band options have been merged with the config file
+fn analysis_config(
+    settings: &AnalysisSettings,
+    gate: Option<&str>,
+) -> Result<AnalysisConfig, String> {
+    let weighting = match &settings.weighting_file {
+        Some(path) => Weighting::Curve(Arc::new(load_weighting_curve(path)?)),
+        None => settings.weighting.clone(),
+    };
+    if weighting != Weighting::K && settings.kweight_mode == KWeightMode::Filter {
+        return Err("--kweight-mode filter only applies to --weighting k".to_string());
+    }
+
+    let mut config = settings.bands.apply(AnalysisConfig::default())?;
+    config.precision = settings.precision;
+    config.weighting = weighting;
+    config.kweight_mode = settings.kweight_mode;
+    // --gated only spells out the default; it conflicts with --no-gate
+    config.gating = !settings.no_gate;
+    config.multi_resolution = settings.multi_resolution;
+    config.deterministic = settings.deterministic;
+    config.resample_quality = settings.resample_quality;
+    config.verify = settings.verify;
+    if let Some(factor) = settings.zero_pad {
+        config = config.with_zero_pad(factor)?;
+    }
+    if let Some(gate) = gate {
+        config = config.with_noise_gate(gate)?;
+    }
+    Ok(config)
+}
+
+/// How the inputs are decoded; the mode sets the part of each input to read
+fn load_options(
+    settings: &AnalysisSettings,
+    config: &AnalysisConfig,
+) -> Result<LoadOptions, String> {
+    if let Some(hz) = settings.highpass {
+        audio::check_highpass(hz)?;
+    }
+    if let Some(mb) = settings.max_memory {
+        audio::check_max_memory(mb)?;
+    }
+    audio::check_max_download(settings.max_download)?;
+    Ok(LoadOptions {
+        highpass_hz: settings.highpass,
+        ffmpeg_fallback: settings.ffmpeg_fallback,
+        max_memory_mb: settings.max_memory,
+        max_download_mb: Some(settings.max_download),
+        resampling: config.resampling(),
+        ..LoadOptions::default()
+    })
+}
+
+/// Files dropped onto the binary have no console to show the tables in, so they get
+/// an HTML report next to the inputs, opened once it is written
+fn drop_launch_report(files: &[String]) -> Option<String> {
+    let raw_args: Vec<String> = std::env::args_os()
+        .skip(1)
+        .map(|a| a.to_string_lossy().into_owned())
+        .collect();
+    is_drop_launch(&raw_args, launched_from_desktop()).then(|| drop_report_path(files))
+}
+
+/// Check the gen options and write the signal
+fn run_gen(args: GenArgs) -> bool {
+    if !(args.duration > 0.0 && args.duration.is_finite()) {
+        print_error("--duration must be a positive number of seconds");
+        return false;
+    }
+    if !(args.level <= 0.0 && args.level.is_finite()) {
+        print_error("--level must be at most 0 dBFS");
+        return false;
+    }
+    if !(8000..=384000).contains(&args.sample_rate) {
+        print_error("--sample-rate must be between 8000 and 384000 Hz");
+        return false;
+    }
+    if args.freq.is_some() && args.signal != Signal::Sine {
+        print_error("--freq can only be used with the sine signal");
+        return false;
+    }
+    let freq = args.freq.unwrap_or(1000.0);
+    let nyquist = args.sample_rate as f32 / 2.0;
+    if !(freq > 0.0 && freq < nyquist) {
+        print_error(&format!(
+            "--freq must be above 0 and below {} Hz (half the sample rate)",
+            nyquist
+        ));
+        return false;
+    }
+    run_generate(
+        &args.path,
+        &SignalSpec {
+            signal: args.signal,
+            freq,
+            secs: args.duration,
+            level_dbfs: args.level,
+            sample_rate: args.sample_rate,
+            format: args.export_format,
+        },
+    )
+}
+
+/// Validate the options and run the mode they select
+fn run(mut args: FlatArgs, config: &Config, drop_report: Option<String>) {
+    // Handle --no-color
+    if args.tables.no_color || args.tables.ascii {
+        colored::control::set_override(false);
+    }
+
+    // Patterns the shell passed on unexpanded (e.g. on Windows) stand for the files they match
+    args.files = expand_file_patterns(std::mem::take(&mut args.files)).unwrap_or_else(|e| {
+        print_error(&e);
+        std::process::exit(1);
+    });
+
+    let mut display = DisplayOptions {
+        header_style: args.tables.header_style,
+        format: args.tables.format,
+        time_format: args.tables.time_format,
+        round_output: args.tables.round_output,
+        ascii: args.tables.ascii,
+        ..DisplayOptions::default()
+    };
+    if let Some(decimals) = args.tables.decimals {
+        display.decimals = decimals as usize;
+    }
+
+    // Band options not given on the command line come from the config file
+    args.settings.bands = std::mem::take(&mut args.settings.bands).or_config(&config.bands);
+
+    let gate = args.settings.gate.as_deref().or(config.gate.as_deref());
+    let mut analysis_config = analysis_config(&args.settings, gate).unwrap_or_else(|e| {
+        print_error(&e);
+        std::process::exit(1);
+    });
+    let mut load_options = load_options(&args.settings, &analysis_config).unwrap_or_else(|e| {
+        print_error(&e);
+        std::process::exit(1);
+    });
+
+    if args.device.is_some() && !args.live {
+        print_error("--device can only be used with --live");
+        std::process::exit(1);
+    }
+    if args.live && args.tables.format == OutputFormat::Json {
+        print_error(
+            "--live prints rows as the input comes in and cannot be used with --format json",
+        );
+        std::process::exit(1);
+    }
+
+    if args.ipc {
+        run_ipc(&load_options, &analysis_config);
+        return;
+    }
+
+    if args.batch {
+        // A `-` among the files stands for the list piped to stdin
+        if args.files.iter().any(|f| f == "-") {
+            let listed = read_file_list(std::io::stdin().lock()).unwrap_or_else(|e| {
+                print_error(&e);
+                std::process::exit(1);
+            });
+            let files = std::mem::take(&mut args.files);
+            for file in files {
+                if file == "-" {
+                    args.files.extend(listed.iter().cloned());
+                } else {
+                    args.files.push(file);
+                }
+            }
+        }
+        // Directories stand for the audio files in them
+        let files = std::mem::take(&mut args.files);
+        for file in files {
+            if std::path::Path::new(&file).is_dir() {
+                let listed = list_audio_files(&file, args.recursive).unwrap_or_else(|e| {
+                    print_error(&e);
+                    std::process::exit(1);
+                });
+                args.files.extend(listed);
+            } else {
+                args.files.push(file);
+            }
+        }
+        if args.files.is_empty() {
+            print_error(
+                "--batch got no files to analyze (the list on stdin or the directories are empty)",
+            );
+            std::process::exit(1);
+        }
+
+        for image in &args.charts.image {
+            if image.kind_or(ChartKind::Stats) != ChartKind::Stats {
+                print_error("--batch renders stats charts only");
+                std::process::exit(1);
+            }
+            if !image.path.contains(NAME_PLACEHOLDER) {
+                print_error(&format!(
+                    "--batch chart paths need {} for each file's name, e.g. charts/{}.png",
+                    NAME_PLACEHOLDER, NAME_PLACEHOLDER
+                ));
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if !args.batch && (args.recursive || args.summary || args.csv.is_some()) {
+        print_error("--recursive, --summary, and --csv can only be used with --batch");
+        std::process::exit(1);
+    }
+
+    if args.watch && (args.files.len() != 1 || !std::path::Path::new(&args.files[0]).is_dir()) {
+        print_error("--watch takes a single directory");
+        std::process::exit(1);
+    }
+
+    // A single CUE sheet is analyzed track by track from its image file
+    let cue = args.files.len() == 1 && is_cue_sheet(&args.files[0]);
+    if cue
+        && (args.time
+            || args.follow
+            || args.monitor
+            || args.batch
+            || args.watch
+            || args.verify_tone.is_some()
+            || args.ir
+            || !args.charts.image.is_empty()
+            || args.charts.image_loudness.is_some()
+            || args.report.is_some()
+            || args.active_only
+            || args.lfe
+            || args.weighted_dynamics
+            || !args.metrics.is_empty()
+            || args.target_curve.is_some()
+            || args.exclude_bands.is_some())
+    {
+        print_error(
+            "a CUE sheet is analyzed track by track and cannot be used with --time, --follow, --monitor, --batch, --watch, --verify-tone, --ir, --image, --image-loudness, --report, --active-only, --lfe, --weighted-dynamics, --metric, --target-curve, or --exclude-bands",
+        );
+        std::process::exit(1);
+    }
+
+    // A CUE sheet prints its tracks and writes no report, so dropping one opens none
+    let drop_report = drop_report.filter(|_| !cue);
+
+    if (args.settings.start.is_some() || args.settings.end.is_some()) && (args.live || cue) {
+        print_error("--start and --end cannot be used with --live or a CUE sheet");
+        std::process::exit(1);
+    }
+
+    if args.tui {
+        if args.files.len() != 1 || cue || args.tables.format == OutputFormat::Json {
+            print_error(
+                "--tui plays a single audio file and cannot be used with a CUE sheet or --format json",
+            );
+            std::process::exit(1);
+        }
+        if !std::io::stdout().is_terminal() {
+            print_error("--tui needs a terminal");
+            std::process::exit(1);
+        }
+    }
+
+    if args.jobs == Some(0) {
+        print_error("--jobs must be at least 1");
+        std::process::exit(1);
+    }
+
+    // Batch files are analyzed each on its own, so only comparisons are limited
+    let comparison = args.files.len() >= 2 && !args.batch;
+
+    if args.jobs.is_some() && !args.batch && !comparison {
+        print_error("--jobs can only be used with --batch or several files to compare");
+        std::process::exit(1);
+    }
+
+    // Validate file count
+    if !(2..=MAX_COMPARE_FILES).contains(&args.max_files) {
+        print_error(&format!(
+            "--max-files must be between 2 and {}",
+            MAX_COMPARE_FILES
+        ));
+        std::process::exit(1);
+    }
+
+    if comparison && args.files.len() > args.max_files {
+        print_error(&format!(
+            "Too many files specified (max {}; raise with --max-files)",
+            args.max_files
+        ));
+        std::process::exit(1);
+    }
+
+    // Validate interval; the config interval is for timelines, so live input keeps
+    // refreshing every second unless -i says otherwise
+    let interval = match args.interval {
+        Some(interval) => interval,
+        None if args.live => DEFAULT_LIVE_INTERVAL,
+        None => config.interval.unwrap_or(DEFAULT_INTERVAL),
+    };
+    if interval == 0 {
+        print_error("Interval must be at least 1 second");
+        std::process::exit(1);
+    }
+
+    if let Some(path) = args.charts.image_loudness.take() {
+        args.charts.image.push(chart::ImageSpec {
+            kind: Some(ChartKind::Loudness),
+            path,
+        });
+    }
+
+    // The config file's weighting only applies where --weighted has an effect
+    if config.weighted == Some(true)
+        && !comparison
+        && (!args.charts.image.is_empty()
+            || args.time
+            || args.follow
+            || args.monitor
+            || args.batch
+            || args.watch
+            || args.live
+            || args.tui
+            || cue)
+    {
+        args.weighted = true;
+    }
+
+    // Validate option combinations
+    if args.files.len() >= 2 && args.time {
+        print_error("--time cannot be used with multiple files");
+        std::process::exit(1);
+    }
+
+    if comparison && args.weighted {
+        print_error("--weighted cannot be used with comparison mode");
+        std::process::exit(1);
+    }
+
+    if args.weighted
+        && args.charts.image.is_empty()
+        && !args.time
+        && !args.follow
+        && !args.monitor
+        && !args.batch
+        && !args.watch
+        && !args.live
+        && !args.tui
+        && !cue
+    {
+        print_warning("--weighted has no effect without --image in single-file mode");
+    }
+
+    if args.monitor && args.files.len() >= 2 {
+        print_error("--monitor takes a single input");
+        std::process::exit(1);
+    }
+
+    if !args.monitor
+        && (args.rotate.is_some()
+            || args.monitor_dir != DEFAULT_MONITOR_DIR
+            || args.alert_rules.is_some()
+            || args.heatmap.is_some())
+    {
+        print_error(
+            "--rotate, --monitor-dir, --alert-rules, and --heatmap can only be used with --monitor",
+        );
+        std::process::exit(1);
+    }
+
+    if args.follow && (args.files.len() >= 2 || audio::is_url(&args.files[0])) {
+        print_error("--follow takes a single local file");
+        std::process::exit(1);
+    }
+
+    if !(args.follow_idle > 0.0 && args.follow_idle.is_finite()) {
+        print_error("--follow-idle must be a positive number of seconds");
+        std::process::exit(1);
+    }
+
+    if !args.follow && args.follow_idle != DEFAULT_FOLLOW_IDLE {
+        print_error("--follow-idle can only be used with --follow");
+        std::process::exit(1);
+    }
+
+    let timeline_image = args
+        .charts
+        .image
+        .iter()
+        .any(|i| i.kind == Some(ChartKind::Timeline));
+    if !args.time
+        && !args.monitor
+        && !args.follow
+        && !args.live
+        && !timeline_image
+        && args.interval.is_some()
+    {
+        print_error(
+            "--interval can only be used with --time, --follow, --monitor, --live, or a timeline= image",
+        );
+        std::process::exit(1);
+    }
+
+    if let Some(chapter_secs) = args.chapters_every {
+        if !args.time && !args.follow {
+            print_error("--chapters-every can only be used with --time or --follow");
+            std::process::exit(1);
+        }
+        if chapter_secs < interval as u64 {
+            print_error(&format!(
+                "--chapters-every must be at least the interval ({} s)",
+                interval
+            ));
+            std::process::exit(1);
+        }
+    }
+
+    if args.loudness && !args.time && !args.follow {
+        print_error("--loudness can only be used with --time or --follow");
+        std::process::exit(1);
+    }
+
+    if args.browse {
+        if !args.time {
+            print_error("--browse can only be used with --time");
+            std::process::exit(1);
+        }
+        if args.tables.format == OutputFormat::Json {
+            print_error(
+                "--browse opens once the timeline is printed and cannot be used with --format json",
+            );
+            std::process::exit(1);
+        }
+        if !std::io::stdout().is_terminal() {
+            print_error("--browse needs a terminal");
+            std::process::exit(1);
+        }
+    }
+
+    // `--report out.html` writes the built-in HTML report there instead of using a template
+    let html_report = args.report.as_deref().filter(|path| is_html_report(path));
+    if html_report.is_none()
+        && args.report.is_some()
+        && (args.time || args.follow || args.monitor || args.verify_tone.is_some())
+    {
+        print_error(
+            "--report templates support single-file and comparison analysis only (HTML reports also support --time and --follow)",
+        );
+        std::process::exit(1);
+    }
+
+    if args.tables.format == OutputFormat::Json {
+        if args.monitor || args.verify_tone.is_some() {
+            print_error(
+                "--format json supports single-file, comparison, timeline, and impulse response analysis only",
+            );
+            std::process::exit(1);
+        }
+        if args.report.is_some() && html_report.is_none() && args.tables.output.is_none() {
+            print_error(
+                "--format json and --report both write to stdout; send the report to a file with --output",
+            );
+            std::process::exit(1);
+        }
+    }
+
+    display.weighted_dynamics = args.weighted_dynamics;
+    analysis_config.metrics = args.metrics.clone();
+
+    if let Some(ref labels) = args.exclude_bands {
+        analysis_config = analysis_config
+            .with_excluded_bands(labels)
+            .unwrap_or_else(|e| {
+                print_error(&e);
+                std::process::exit(1);
+            });
+    }
+
+    if args.lfe && args.files.len() > 1 {
+        print_error("--lfe supports single-file analysis only");
+        std::process::exit(1);
+    }
+
+    if let Some(ref path) = args.target_curve {
+        match load_target_curve(path) {
+            Ok(curve) => analysis_config.target_curve = Some(Arc::new(curve)),
+            Err(e) => {
+                print_error(&e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    analysis_config.active_only = args.active_only;
+
+    if !args.label.is_empty() && args.files.len() < 2 {
+        print_error("--label requires comparison mode (two or more files)");
+        std::process::exit(1);
+    }
+
+    let mut names: Vec<Option<String>> = vec![None; args.files.len()];
+    for (index, name) in &args.label {
+        let letter = (b'A' + *index as u8) as char;
+        match names.get_mut(*index) {
+            None => {
+                print_error(&format!(
+                    "--label {}: there is no file [{}]",
+                    letter, letter
+                ));
+                std::process::exit(1);
+            }
+            Some(Some(_)) => {
+                print_error(&format!("--label {} is given more than once", letter));
+                std::process::exit(1);
+            }
+            Some(slot) => *slot = Some(name.clone()),
+        }
+    }
+
+    if args.sonify_diff.is_some() && args.files.len() < 2 {
+        print_error("--sonify-diff requires comparison mode (two or more files)");
+        std::process::exit(1);
+    }
+
+    if args.align && !comparison {
+        print_error("--align requires comparison mode (two or more files)");
+        std::process::exit(1);
+    }
+
+    if args.match_distributions && !comparison {
+        print_error("--match-distributions requires comparison mode (two or more files)");
+        std::process::exit(1);
+    }
+    display.match_distributions = args.match_distributions;
+
+    if args.export_format != ExportFormat::default() && args.sonify_diff.is_none() {
+        print_warning("--export-format has no effect without --sonify-diff");
+    }
+
+    if args.sonify_diff.is_some() && args.files.len() > 2 {
+        print_warning("--sonify-diff renders [B]-[A]; further files are not included");
+    }
+
+    // Charts default to the one matching the analysis mode
+    let default_chart = if comparison {
+        ChartKind::Compare
+    } else if args.time || args.follow {
+        ChartKind::Timeline
+    } else {
+        ChartKind::Stats
+    };
+    for image in &args.charts.image {
+        let kind = image.kind_or(default_chart);
+        if kind == ChartKind::Loudness && args.follow {
+            print_error("--image loudness= cannot be used with --follow");
+            std::process::exit(1);
+        }
+        if (kind == ChartKind::Compare) != comparison {
+            print_error(&format!(
+                "--image {}= is not available in {} mode",
+                kind.name(),
+                if comparison {
+                    "comparison"
+                } else {
+                    "single-file"
+                }
+            ));
+            std::process::exit(1);
+        }
+    }
+
+    if !args.charts.image.is_empty() && comparison && args.files.len() > chart::max_chart_files() {
+        print_error(&format!(
+            "--image supports up to {} files",
+            chart::max_chart_files()
+        ));
+        std::process::exit(1);
+    }
+
+    if let Some(end) = args.settings.end {
+        let start = args.settings.start.unwrap_or(0.0);
+        if end <= start {
+            print_error("--end must be after --start");
+            std::process::exit(1);
+        }
+        args.settings.duration = Some(end - start);
+    }
+
+    if let Some(secs) = args.settings.duration
+        && !(secs > 0.0 && secs.is_finite())
+    {
+        print_error("--duration must be a positive number of seconds");
+        std::process::exit(1);
+    }
+
+    if args.ir && args.files.len() >= 2 {
+        print_error("--ir analyzes a single impulse response");
+        std::process::exit(1);
+    }
+
+    if args.verify_tone.is_some() && args.files.len() >= 2 {
+        print_error("--verify-tone takes a single file");
+        std::process::exit(1);
+    }
+
+    let mut charts = chart::ChartOptions::default();
+    charts.decimals = display.decimals;
+    if let Some(size) = args
+        .charts
+        .chart_size
+        .as_deref()
+        .or(config.chart_size.as_deref())
+    {
+        charts = charts.with_size(size).unwrap_or_else(|e| {
+            print_error(&e);
+            std::process::exit(1);
+        });
+    }
+
+    if args.charts.chart_size.is_some() && args.charts.image.is_empty() && args.heatmap.is_none() {
+        print_warning("--chart-size has no effect without --image or --heatmap");
+    }
+
+    if let Some(ref font) = args.charts.chart_font {
+        if args.charts.image.is_empty() && args.heatmap.is_none() {
+            print_warning("--chart-font has no effect without --image or --heatmap");
+        } else {
+            charts = charts.with_font(font).unwrap_or_else(|e| {
+                print_error(&e);
+                std::process::exit(1);
+            });
+        }
+    }
+
+    // Validate output file paths
+    for path in args
+        .charts
+        .image
+        .iter()
+        .map(|i| i.path.as_str())
+        .chain(args.heatmap.as_deref())
+        .chain(args.sonify_diff.as_deref())
+        .chain(args.csv.as_deref())
+        .chain(args.tables.output.as_deref())
+        .chain(html_report)
+    {
+        use std::path::Path;
+        if let Some(parent) = Path::new(path).parent()
+            && !parent.as_os_str().is_empty()
+            && !parent.exists()
+        {
+            print_error(&format!("Directory does not exist: {}", parent.display()));
+            std::process::exit(1);
+        }
+    }
+
+    // Without a template report, --output takes what would go to stdout
+    let template_report = args.report.is_some() && html_report.is_none();
+    let sink = match args.tables.output.as_deref().filter(|_| !template_report) {
+        _ if args.discard_tables => Sink::null(),
+        Some(path) => {
+            colored::control::set_override(false);
+            Sink::create(path).unwrap_or_else(|e| {
+                print_error(&e);
+                std::process::exit(1);
+            })
+        }
+        None => Sink::stdout(),
+    };
+
+    let report = args
+        .report
+        .as_deref()
+        .map(|report| match html_report {
+            Some(path) => ReportOptions {
+                template: None,
+                output: Some(path),
+            },
+            None => ReportOptions {
+                template: Some(report),
+                output: args.tables.output.as_deref(),
+            },
+        })
+        .or_else(|| {
+            drop_report.as_deref().map(|path| ReportOptions {
+                template: None,
+                output: Some(path),
+            })
+        });
+
+    load_options.start = args.settings.start;
+    load_options.max_duration = args.settings.duration;
+    load_options.follow = args
+        .follow
+        .then(|| std::time::Duration::from_secs_f64(args.follow_idle));
+    let out = Output::new(
+        &analysis_config,
+        display,
+        charts,
+        sink,
+        args.weighted,
+        args.tables.quiet,
+    );
+    if args.settings.duration.is_none()
+        && let Some(url) = args.files.iter().find(|f| audio::is_url(f))
+    {
+        out.print_info(&format!(
+            "Reading {} until the stream ends (use --duration for live streams)",
+            url
+        ));
+    }
+    let jobs = args.jobs.unwrap_or_else(|| {
+        std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get)
+    });
+
+    // Dispatch to appropriate mode
+    if args.live {
+        #[cfg(feature = "live")]
+        crate::mode::run_live(
+            args.device.as_deref(),
+            interval,
+            &load_options,
+            &analysis_config,
+            &out,
+        );
+        #[cfg(not(feature = "live"))]
+        {
+            print_error(
+                "cannot capture audio: bandstat was built without the live feature (rebuild with --features live)",
+            );
+            std::process::exit(1);
+        }
+    } else if args.monitor {
+        run_monitor(
+            &args.files[0],
+            interval,
+            &load_options,
+            &MonitorOptions {
+                output_dir: &args.monitor_dir,
+                rotate_secs: args.rotate,
+                alert_rules: args.alert_rules.as_deref(),
+                heatmap_path: args.heatmap.as_deref(),
+            },
+            &analysis_config,
+            &out,
+        );
+    } else if args.ir {
+        run_impulse(&args.files[0], &load_options, &analysis_config, &out);
+    } else if let Some(spec) = args.verify_tone {
+        if !run_verify_tone(&args.files[0], spec, &load_options, &analysis_config, &out) {
+            std::process::exit(1);
+        }
+    } else if args.tui {
+        run_tui(
+            &args.files[0],
+            args.weighted,
+            &load_options,
+            &analysis_config,
+        );
+    } else if args.watch {
+        run_watch(&args.files[0], &load_options, &analysis_config, &out);
+    } else if cue {
+        run_cue(&args.files[0], &load_options, &analysis_config, &out);
+    } else if args.batch {
+        if !run_batch(
+            &args.files,
+            &load_options,
+            &BatchOptions {
+                images: &args.charts.image,
+                jobs,
+                summary: args.summary,
+                csv_path: args.csv.as_deref(),
+            },
+            &analysis_config,
+            &out,
+        ) {
+            std::process::exit(1);
+        }
+    } else if args.files.len() >= 2 {
+        run_compare(
+            &args.files,
+            &load_options,
+            &CompareOptions {
+                names: &names,
+                images: &args.charts.image,
+                sonify_path: args.sonify_diff.as_deref(),
+                export_format: args.export_format,
+                report: report.as_ref(),
+                jobs,
+                align: args.align,
+            },
+            &analysis_config,
+            &out,
+        );
+    } else if args.time || args.follow {
+        run_timeline(
+            &args.files[0],
+            interval,
+            &load_options,
+            &TimelineOptions {
+                chapter_secs: args.chapters_every,
+                images: &args.charts.image,
+                report: report.as_ref(),
+                browse: args.browse,
+                loudness: args.loudness,
+            },
+            &analysis_config,
+            &out,
+        );
+    } else {
+        run_stats(
+            &args.files[0],
+            &load_options,
+            &StatsOptions {
+                interval_secs: interval,
+                images: &args.charts.image,
+                report: report.as_ref(),
+                lfe: args.lfe,
+            },
+            &analysis_config,
+            &out,
+        );
+    }
+
+    if let Some(path) = drop_report
+        && let Err(e) = open_report(&path)
+    {
+        print_error(&e);
+        std::process::exit(1);
+    }
+
+    if args.settings.verify && !out.print_verify_summary() {
+        std::process::exit(1);
+    }
+}
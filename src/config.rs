@@ -0,0 +1,302 @@
+//! Defaults read from `~/.config/bandstat/config.toml` (or the file given with `--config`),
+//! overridden by `BANDSTAT_*` environment variables, band layouts read from `--bands-file`,
+//! and target curves read from `--target-curve`
+
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::analysis::{Band, BandPreset, TargetCurve, parse_target_curve};
+
+/// Settings of the config file; each one only applies where the option is not given
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Config {
+    /// Timeline interval in seconds (`--interval`)
+    pub(crate) interval: Option<u32>,
+    /// K-weighted values in modes where `--weighted` has an effect
+    pub(crate) weighted: Option<bool>,
+    /// `false` disables colored output (`--no-color`)
+    pub(crate) color: Option<bool>,
+    /// PNG chart size such as "1400x600" (`--chart-size`)
+    pub(crate) chart_size: Option<String>,
+    /// Broadband level such as "-60dB" below which frames leave all stats (`--gate`)
+    pub(crate) gate: Option<String>,
+    #[serde(default)]
+    pub(crate) bands: BandConfig,
+}
+
+/// The `[bands]` table
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct BandConfig {
+    /// Built-in band layout (`--bands`)
+    pub(crate) preset: Option<BandPreset>,
+    /// Band layout file (`--bands-file`)
+    pub(crate) file: Option<String>,
+    /// Number of log-spaced bands (`--num-bands`)
+    pub(crate) count: Option<usize>,
+    /// Band labels from lowest to highest (`--band-labels`)
+    labels: Option<Vec<String>>,
+    /// Bands left out (`--ignore-bands`)
+    ignore: Option<Vec<String>>,
+    /// Analyzed range such as "20-16000" (`--range`)
+    pub(crate) range: Option<String>,
+    /// Lower edges of the MID and HIGH groups in Hz (`--group-edges`)
+    group_edges: Option<Vec<f32>>,
+    /// Crossover width in octaves (`--crossover`)
+    pub(crate) crossover: Option<f64>,
+}
+
+impl BandConfig {
+    /// Labels in the comma-separated form `--band-labels` takes
+    pub(crate) fn labels(&self) -> Option<String> {
+        self.labels.as_ref().map(|labels| labels.join(","))
+    }
+
+    /// Ignored bands in the comma-separated form `--ignore-bands` takes
+    pub(crate) fn ignore(&self) -> Option<String> {
+        self.ignore.as_ref().map(|labels| labels.join(","))
+    }
+
+    /// Group edges in the comma-separated form `--group-edges` takes
+    pub(crate) fn group_edges(&self) -> Option<String> {
+        self.group_edges.as_ref().map(|edges| {
+            edges
+                .iter()
+                .map(f32::to_string)
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+    }
+}
+
+impl Config {
+    /// Override settings with the `BANDSTAT_INTERVAL`, `BANDSTAT_WEIGHTED`,
+    /// `BANDSTAT_CHART_SIZE`, and `BANDSTAT_GATE` environment variables (empty
+    /// ones are ignored)
+    pub(crate) fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<(), String> {
+        let var = |name: &str| var(name).filter(|value| !value.is_empty());
+        if let Some(value) = var("BANDSTAT_INTERVAL") {
+            self.interval = Some(value.trim().parse().map_err(|_| {
+                format!("BANDSTAT_INTERVAL: invalid number of seconds '{}'", value)
+            })?);
+        }
+        if let Some(value) = var("BANDSTAT_WEIGHTED") {
+            self.weighted = Some(parse_switch(&value).ok_or_else(|| {
+                format!(
+                    "BANDSTAT_WEIGHTED: expected 1/0, true/false, yes/no, or on/off, got '{}'",
+                    value
+                )
+            })?);
+        }
+        if let Some(value) = var("BANDSTAT_CHART_SIZE") {
+            self.chart_size = Some(value);
+        }
+        if let Some(value) = var("BANDSTAT_GATE") {
+            self.gate = Some(value);
+        }
+        Ok(())
+    }
+}
+
+/// Parse an on/off environment value
+fn parse_switch(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// A `--bands-file`: `[[bands]]` tables in TOML, or a `"bands"` array in JSON (so the output of
+/// `bandstat bands --format json` can be edited and read back)
+#[derive(Deserialize)]
+struct BandsFile {
+    bands: Vec<BandEntry>,
+}
+
+#[derive(Deserialize)]
+struct BandEntry {
+    label: String,
+    low_hz: f32,
+    /// Left out (or null) for an open top band
+    high_hz: Option<f32>,
+}
+
+/// Read a band layout from a TOML or JSON (`.json`) file
+pub(crate) fn load_bands_file(path: &str) -> Result<Vec<Band>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let json = Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    parse_bands_file(&text, json).map_err(|e| format!("--bands-file {}: {}", path, e))
+}
+
+fn parse_bands_file(text: &str, json: bool) -> Result<Vec<Band>, String> {
+    let file: BandsFile = if json {
+        serde_json::from_str(text).map_err(|e| e.to_string())?
+    } else {
+        toml::from_str(text).map_err(|e| e.to_string())?
+    };
+    Ok(file
+        .bands
+        .into_iter()
+        .map(|entry| Band {
+            label: Cow::Owned(entry.label),
+            low_hz: entry.low_hz,
+            high_hz: entry.high_hz.unwrap_or(f32::MAX),
+        })
+        .collect())
+}
+
+/// Read a `--target-curve` CSV of frequency/dB pairs
+pub(crate) fn load_target_curve(path: &str) -> Result<TargetCurve, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    parse_target_curve(path, &text).map_err(|e| format!("--target-curve {}: {}", path, e))
+}
+
+/// Read a `--weighting-file` CSV of frequency/dB pairs
+pub(crate) fn load_weighting_curve(path: &str) -> Result<TargetCurve, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    parse_target_curve(path, &text).map_err(|e| format!("--weighting-file {}: {}", path, e))
+}
+
+/// Read the config file given with `--config` or `BANDSTAT_CONFIG`, or the default one if
+/// it exists
+pub(crate) fn load_config(path: Option<&str>) -> Result<Config, String> {
+    let path = match path.map(PathBuf::from).or_else(|| {
+        std::env::var_os("BANDSTAT_CONFIG")
+            .filter(|p| !p.is_empty())
+            .map(PathBuf::from)
+    }) {
+        Some(path) => path,
+        None => match default_config_path() {
+            Some(path) if path.is_file() => path,
+            _ => return Ok(Config::default()),
+        },
+    };
+    let text = std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    parse_config(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn parse_config(text: &str) -> Result<Config, String> {
+    toml::from_str(text).map_err(|e| e.to_string())
+}
+
+/// `$XDG_CONFIG_HOME/bandstat/config.toml`, falling back to `~/.config` (`%APPDATA%` on Windows)
+fn default_config_path() -> Option<PathBuf> {
+    let dir = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            if cfg!(windows) {
+                std::env::var_os("APPDATA").map(PathBuf::from)
+            } else {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config"))
+            }
+        })?;
+    Some(dir.join("bandstat").join("config.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = parse_config(
+            r#"
+            interval = 10
+            weighted = true
+            color = false
+            chart_size = "1400x600"
+            gate = "-60dB"
+
+            [bands]
+            preset = "octave"
+            ignore = ["DC", "AIR"]
+            group_edges = [120, 2000]
+            crossover = 0.5
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.interval, Some(10));
+        assert_eq!(config.weighted, Some(true));
+        assert_eq!(config.color, Some(false));
+        assert_eq!(config.chart_size.as_deref(), Some("1400x600"));
+        assert_eq!(config.gate.as_deref(), Some("-60dB"));
+        assert_eq!(config.bands.ignore().as_deref(), Some("DC,AIR"));
+        assert_eq!(config.bands.group_edges().as_deref(), Some("120,2000"));
+        assert_eq!(config.bands.labels(), None);
+        assert_eq!(config.bands.crossover, Some(0.5));
+        assert_eq!(config.bands.preset, Some(BandPreset::Octave));
+
+        let config = parse_config("[bands]\npreset = \"mel:40\"").unwrap();
+        assert_eq!(config.bands.preset, Some(BandPreset::Mel(40)));
+        let config = parse_config("[bands]\ncount = 24").unwrap();
+        assert_eq!(config.bands.count, Some(24));
+        assert!(parse_config("[bands]\npreset = \"mel:0\"").is_err());
+
+        let error = parse_config("intervall = 10").err().unwrap();
+        assert!(error.contains("intervall"), "{}", error);
+    }
+
+    #[test]
+    fn test_env_overrides_config() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        let mut config =
+            parse_config("interval = 10\nweighted = true\ngate = \"-60dB\"\n").unwrap();
+        config
+            .apply_env(env(&[
+                ("BANDSTAT_INTERVAL", "5"),
+                ("BANDSTAT_WEIGHTED", "off"),
+                ("BANDSTAT_CHART_SIZE", ""),
+                ("BANDSTAT_GATE", "-50dB"),
+            ]))
+            .unwrap();
+        assert_eq!(config.interval, Some(5));
+        assert_eq!(config.weighted, Some(false));
+        assert_eq!(config.chart_size, None);
+        assert_eq!(config.gate.as_deref(), Some("-50dB"));
+
+        let error = config
+            .apply_env(env(&[("BANDSTAT_INTERVAL", "ten")]))
+            .err()
+            .unwrap();
+        assert!(error.contains("BANDSTAT_INTERVAL"), "{}", error);
+    }
+
+    #[test]
+    fn test_parse_bands_file() {
+        let toml = r#"
+            [[bands]]
+            label = "LOW"
+            low_hz = 0
+            high_hz = 300
+
+            [[bands]]
+            label = "TOP"
+            low_hz = 300
+            "#;
+        let json = r##"{"bands": [
+            {"label": "LOW", "low_hz": 0, "high_hz": 300, "color": "#1E3A5F"},
+            {"label": "TOP", "low_hz": 300, "high_hz": null}
+        ]}"##;
+        for (text, is_json) in [(toml, false), (json, true)] {
+            let bands = parse_bands_file(text, is_json).unwrap();
+            assert_eq!(bands.len(), 2);
+            assert_eq!(bands[0].label, "LOW");
+            assert_eq!(bands[0].high_hz, 300.0);
+            assert_eq!(bands[1].high_hz, f32::MAX);
+        }
+        assert!(parse_bands_file("[[bands]]\nlabel = \"X\"\n", false).is_err());
+    }
+}
@@ -0,0 +1,232 @@
+//! C ABI for embedding the analyzer in DAW plugins and C/C++ hosts (`ffi` feature)
+//!
+//! The declarations are in `include/bandstat.h`, regenerated from this file with
+//! `cbindgen --config cbindgen.toml --output include/bandstat.h`.
+//!
+//! Ownership: a non-null [`BandstatResult`] belongs to the caller until it is passed to
+//! [`bandstat_result_free`], which releases the result and every array and label it
+//! points to. Error messages belong to the library and stay valid until the next call
+//! on the same thread.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use crate::analysis::AnalysisConfig;
+use crate::analyzer::{AnalysisResult, Analyzer};
+
+/// Band analysis of one input; all arrays hold `num_bands` values, lowest band first
+#[repr(C)]
+pub struct BandstatResult {
+    pub num_bands: usize,
+    /// Band labels as NUL-terminated UTF-8 strings
+    pub labels: *const *const c_char,
+    /// Lower band edges in Hz
+    pub low_hz: *const f32,
+    /// Share of the total power in percent
+    pub raw_pct: *const f64,
+    /// Share of the K-weighted (BS.1770) power in percent
+    pub k_pct: *const f64,
+    /// Spread of each band's frame levels in dB (NaN when the band holds too little energy)
+    pub dynamics_db: *const f64,
+    /// BS.1770 integrated loudness in LUFS (NaN when unknown)
+    pub integrated_lufs: f64,
+    pub duration_secs: f64,
+    /// Sample rate of the input, before resampling to 48 kHz
+    pub sample_rate: u32,
+    /// Channels of the input, before the mono downmix
+    pub channels: u16,
+}
+
+/// A result together with the allocations its pointers refer to
+#[repr(C)]
+struct OwnedResult {
+    /// Must stay first: callers get a pointer to it, which `bandstat_result_free` casts back
+    result: BandstatResult,
+    labels: Vec<CString>,
+    label_ptrs: Vec<*const c_char>,
+    low_hz: Vec<f32>,
+    raw_pct: Vec<f64>,
+    k_pct: Vec<f64>,
+    dynamics_db: Vec<f64>,
+}
+
+thread_local! {
+    /// Analyzer of this thread, kept so repeated calls plan the FFT only once
+    static ANALYZER: RefCell<Option<Analyzer>> = const { RefCell::new(None) };
+
+    /// Message of the last failed call on this thread
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Analyze a WAV, AIFF, MP3, or FLAC file (or an http(s):// URL).
+/// Returns NULL on failure; `bandstat_last_error` then describes the problem.
+///
+/// # Safety
+///
+/// `path` must be NULL or a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bandstat_analyze_file(path: *const c_char) -> *mut BandstatResult {
+    if path.is_null() {
+        return fail("path is NULL".to_string());
+    }
+    // SAFETY: the caller passes a valid NUL-terminated string
+    let path = match unsafe { CStr::from_ptr(path) }.to_str() {
+        Ok(path) => path.to_string(),
+        Err(_) => return fail("path is not valid UTF-8".to_string()),
+    };
+    run(|analyzer| analyzer.analyze_file(&path))
+}
+
+/// Analyze `len` mono samples in -1.0..=1.0 at any sample rate.
+/// Returns NULL on failure; `bandstat_last_error` then describes the problem.
+///
+/// # Safety
+///
+/// `samples` must point to `len` readable floats (or may be NULL when `len` is 0).
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bandstat_analyze_samples(
+    samples: *const f32,
+    len: usize,
+    sample_rate: u32,
+) -> *mut BandstatResult {
+    let samples = if len == 0 {
+        &[][..]
+    } else if samples.is_null() {
+        return fail("samples is NULL".to_string());
+    } else {
+        // SAFETY: the caller passes `len` readable floats
+        unsafe { std::slice::from_raw_parts(samples, len) }
+    };
+    run(|analyzer| analyzer.analyze_samples(samples, sample_rate))
+}
+
+/// Release a result returned by `bandstat_analyze_file` or `bandstat_analyze_samples`.
+/// NULL is ignored.
+///
+/// # Safety
+///
+/// `result` must be NULL or a result from this library that was not freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn bandstat_result_free(result: *mut BandstatResult) {
+    if !result.is_null() {
+        // SAFETY: results are the first field of a boxed `OwnedResult` (both `repr(C)`)
+        drop(unsafe { Box::from_raw(result.cast::<OwnedResult>()) });
+    }
+}
+
+/// Message of the last failed call on this thread, or NULL if there was none.
+/// The string stays valid until the next call on the same thread; do not free it.
+#[unsafe(no_mangle)]
+pub extern "C" fn bandstat_last_error() -> *const c_char {
+    LAST_ERROR.with_borrow(|error| error.as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+/// Run an analysis on this thread's analyzer, turning errors and panics into NULL
+fn run<F>(analyze: F) -> *mut BandstatResult
+where
+    F: FnOnce(&mut Analyzer) -> Result<AnalysisResult, String>,
+{
+    LAST_ERROR.set(None);
+    // Unwinding must not cross into C, so panics become errors as well
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+        ANALYZER.with_borrow_mut(|analyzer| {
+            analyze(analyzer.get_or_insert_with(|| Analyzer::new(AnalysisConfig::default())))
+        })
+    }));
+    match outcome {
+        Ok(Ok(result)) => Box::into_raw(Box::new(owned_result(result))).cast(),
+        Ok(Err(e)) => fail(e),
+        Err(_) => {
+            // The analyzer may be mid-update; start the next call with a fresh one
+            ANALYZER.set(None);
+            fail("internal error during analysis".to_string())
+        }
+    }
+}
+
+fn owned_result(result: AnalysisResult) -> OwnedResult {
+    let labels: Vec<CString> = result
+        .bands
+        .iter()
+        .map(|b| CString::new(b.label.replace('\0', "")).unwrap_or_default())
+        .collect();
+    let mut owned = OwnedResult {
+        result: BandstatResult {
+            num_bands: result.bands.len(),
+            labels: ptr::null(),
+            low_hz: ptr::null(),
+            raw_pct: ptr::null(),
+            k_pct: ptr::null(),
+            dynamics_db: ptr::null(),
+            integrated_lufs: result.integrated_lufs,
+            duration_secs: result.duration_secs,
+            sample_rate: result.sample_rate,
+            channels: result.channels,
+        },
+        label_ptrs: labels.iter().map(|l| l.as_ptr()).collect(),
+        labels,
+        low_hz: result.bands.iter().map(|b| b.low_hz).collect(),
+        raw_pct: result.bands.iter().map(|b| b.raw_pct).collect(),
+        k_pct: result.bands.iter().map(|b| b.k_pct).collect(),
+        dynamics_db: result
+            .bands
+            .iter()
+            .map(|b| b.dynamics_db.unwrap_or(f64::NAN))
+            .collect(),
+    };
+    // Vec buffers stay put when the struct is moved into its box
+    owned.result.labels = owned.label_ptrs.as_ptr();
+    owned.result.low_hz = owned.low_hz.as_ptr();
+    owned.result.raw_pct = owned.raw_pct.as_ptr();
+    owned.result.k_pct = owned.k_pct.as_ptr();
+    owned.result.dynamics_db = owned.dynamics_db.as_ptr();
+    owned
+}
+
+/// Record `message` for `bandstat_last_error` and return NULL
+fn fail(message: String) -> *mut BandstatResult {
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.set(Some(message));
+    ptr::null_mut()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze_samples_and_free() {
+        let samples: Vec<f32> = (0..48000)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * 100.0 * i as f32 / 48000.0).sin())
+            .collect();
+        let result = unsafe { bandstat_analyze_samples(samples.as_ptr(), samples.len(), 48000) };
+        assert!(!result.is_null());
+        assert!(bandstat_last_error().is_null());
+
+        let r = unsafe { &*result };
+        let labels = unsafe { std::slice::from_raw_parts(r.labels, r.num_bands) };
+        let raw_pct = unsafe { std::slice::from_raw_parts(r.raw_pct, r.num_bands) };
+        let bass = labels
+            .iter()
+            .position(|&l| unsafe { CStr::from_ptr(l) }.to_str() == Ok("BASS"))
+            .unwrap();
+        assert!(raw_pct[bass] > 99.0, "BASS {}%", raw_pct[bass]);
+        assert_eq!(r.sample_rate, 48000);
+        unsafe { bandstat_result_free(result) };
+    }
+
+    #[test]
+    fn test_errors_are_reported() {
+        let path = CString::new("/nonexistent/mix.wav").unwrap();
+        let result = unsafe { bandstat_analyze_file(path.as_ptr()) };
+        assert!(result.is_null());
+        let error = unsafe { CStr::from_ptr(bandstat_last_error()) };
+        assert!(error.to_str().unwrap().contains("mix.wav"));
+
+        assert!(unsafe { bandstat_analyze_file(ptr::null()) }.is_null());
+        assert!(unsafe { bandstat_analyze_samples(ptr::null(), 0, 0) }.is_null());
+        unsafe { bandstat_result_free(ptr::null_mut()) };
+    }
+}
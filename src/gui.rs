@@ -0,0 +1,375 @@
+//! Interactive viewer window (`bandstat gui`, built with the `gui` feature)
+
+use eframe::egui::{self, Color32, RichText};
+use egui_plot::{GridMark, HoverPosition, Legend, Line, Plot, PlotPoints, VLine};
+
+use crate::analysis::{
+    self, AnalysisConfig, AnalysisContext, Band, DYNAMICS_DISPLAY_THRESHOLD_PCT, FFT_SIZE,
+    analyze_interval, average_spectrum, powers_to_percentages,
+};
+use crate::audio::{LoadOptions, TARGET_SAMPLE_RATE, load_audio};
+use crate::chart::{band_color, build_band_legend_label, format_freq};
+use crate::output::{format_time, get_display_name, print_warnings};
+
+/// Length of one timeline step in seconds
+const STEP_SECS: usize = 1;
+
+/// Log-spaced points drawn in the spectrum view
+const SPECTRUM_POINTS: usize = 480;
+
+/// Lowest frequency shown in the spectrum view
+const SPECTRUM_MIN_HZ: f64 = 20.0;
+
+/// Spectrum level floor in dB
+const SPECTRUM_FLOOR_DB: f64 = -120.0;
+
+/// Frequencies labelled on the spectrum's log axis
+const SPECTRUM_GRID_HZ: [f64; 10] = [
+    20.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0, 10000.0, 20000.0,
+];
+
+/// Band balance and spectrum of one timeline step
+struct Step {
+    raw_pct: Vec<f64>,
+    k_pct: Vec<f64>,
+    spectrum_db: Vec<f64>,
+}
+
+/// Analysis results of one file, browsed in the window
+struct Viewer {
+    name: String,
+    config: AnalysisConfig,
+    raw_pct: Vec<f64>,
+    k_pct: Vec<f64>,
+    dynamics: Vec<f64>,
+    /// log10 of each spectrum point's frequency (the plot's x axis)
+    log_freqs: Vec<f64>,
+    spectrum_db: Vec<f64>,
+    steps: Vec<Step>,
+    /// Selected timeline step
+    position: usize,
+    k_weighted: bool,
+}
+
+/// Analyze a file and open it in an interactive window
+pub(crate) fn run_gui(filename: &str) -> Result<(), String> {
+    let viewer = Viewer::load(filename)?;
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default().with_inner_size([1400.0, 860.0]),
+        ..Default::default()
+    };
+    eframe::run_native(
+        &format!("bandstat - {}", viewer.name),
+        options,
+        Box::new(|_| Ok(Box::new(viewer))),
+    )
+    .map_err(|e| format!("Failed to open window: {}", e))
+}
+
+impl Viewer {
+    fn load(filename: &str) -> Result<Self, String> {
+        let (audio, warnings) = load_audio(filename, &LoadOptions::default())?;
+        print_warnings(&warnings);
+        let config = AnalysisConfig::default();
+        let bands = config.bands();
+        let name = get_display_name(filename).to_string();
+        let mut ctx = AnalysisContext::new(TARGET_SAMPLE_RATE, &config);
+
+        eprint!("Analyzing {}... 0%", name);
+        let stats = analysis::analyze_stats(&audio, bands, &mut ctx, |progress| {
+            eprint!("\rAnalyzing {}... {}%", name, progress);
+        });
+        eprintln!("\rAnalyzing {}... done", name);
+
+        let sample_rate = audio.sample_rate as f64;
+        let log_freqs = log_frequencies(sample_rate / 2.0);
+        let spectrum_db = spectrum_points(
+            &average_spectrum(&audio.samples, &mut ctx),
+            &log_freqs,
+            sample_rate,
+        );
+
+        let steps = audio
+            .samples
+            .chunks(STEP_SECS * audio.sample_rate as usize)
+            .map(|chunk| Step {
+                raw_pct: powers_to_percentages(
+                    &analyze_interval(chunk, &mut ctx, bands, false),
+                    &[],
+                ),
+                k_pct: powers_to_percentages(&analyze_interval(chunk, &mut ctx, bands, true), &[]),
+                spectrum_db: spectrum_points(
+                    &average_spectrum(chunk, &mut ctx),
+                    &log_freqs,
+                    sample_rate,
+                ),
+            })
+            .collect();
+
+        Ok(Self {
+            name,
+            raw_pct: powers_to_percentages(&stats.raw_powers, &[]),
+            k_pct: powers_to_percentages(&stats.k_powers, &[]),
+            dynamics: stats.dynamics,
+            log_freqs,
+            spectrum_db,
+            steps,
+            position: 0,
+            k_weighted: false,
+            config,
+        })
+    }
+
+    fn color(&self, band: &Band) -> Color32 {
+        Color32::from_hex(&band_color(band, self.config.base_bands())).unwrap_or(Color32::GRAY)
+    }
+
+    /// Raw or K-wt percentages, following the K-weighted toggle
+    fn percentages<'a>(&self, raw: &'a [f64], k: &'a [f64]) -> &'a [f64] {
+        if self.k_weighted { k } else { raw }
+    }
+
+    fn position_label(&self) -> String {
+        format_time((self.position * STEP_SECS) as f32)
+            .trim_end()
+            .to_string()
+    }
+
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.heading(&self.name);
+            ui.separator();
+            ui.checkbox(&mut self.k_weighted, self.config.weighting.name());
+        });
+        if !self.steps.is_empty() {
+            let last = self.steps.len() - 1;
+            ui.spacing_mut().slider_width = (ui.available_width() - 120.0).max(100.0);
+            ui.add(
+                egui::Slider::new(&mut self.position, 0..=last)
+                    .text("Position")
+                    .custom_formatter(|step, _| {
+                        format_time((step as usize * STEP_SECS) as f32)
+                            .trim_end()
+                            .to_string()
+                    }),
+            );
+        }
+    }
+
+    fn band_table(&self, ui: &mut egui::Ui) {
+        ui.heading(if self.k_weighted {
+            format!("{} (%)", self.config.weighting.label())
+        } else {
+            "Raw (%)".to_string()
+        });
+        let whole = self.percentages(&self.raw_pct, &self.k_pct);
+        let step = self
+            .steps
+            .get(self.position)
+            .map(|s| self.percentages(&s.raw_pct, &s.k_pct));
+
+        egui::Grid::new("band_table")
+            .striped(true)
+            .num_columns(4)
+            .show(ui, |ui| {
+                ui.label("Band");
+                ui.label("File");
+                ui.label(format!("At {}", self.position_label()));
+                ui.label("Dyn(dB)");
+                ui.end_row();
+
+                for (i, band) in self.config.bands().iter().enumerate() {
+                    ui.label(RichText::new(build_band_legend_label(band)).color(self.color(band)));
+                    ui.label(RichText::new(format!("{:>5.1}", whole[i])).monospace());
+                    let at_step = step.map(|s| format!("{:>5.1}", s[i])).unwrap_or_default();
+                    ui.label(RichText::new(at_step).monospace());
+                    let dynamics = if self.raw_pct[i] >= DYNAMICS_DISPLAY_THRESHOLD_PCT
+                        && self.dynamics[i].is_finite()
+                    {
+                        format!("{:>5.1}", self.dynamics[i])
+                    } else {
+                        format!("{:>5}", "-")
+                    };
+                    ui.label(RichText::new(dynamics).monospace());
+                    ui.end_row();
+                }
+            });
+    }
+
+    /// Zoomable spectrum of the whole file and of the selected step
+    fn spectrum_plot(&self, ui: &mut egui::Ui, height: f32) {
+        let points = |db: &[f64]| -> PlotPoints {
+            self.log_freqs
+                .iter()
+                .zip(db)
+                .map(|(&x, &y)| [x, y])
+                .collect()
+        };
+
+        Plot::new("spectrum")
+            .height(height)
+            .legend(Legend::default())
+            .x_axis_label("Hz")
+            .y_axis_label("dB (full-scale sine = 0)")
+            .x_grid_spacer(|_| {
+                SPECTRUM_GRID_HZ
+                    .iter()
+                    .map(|hz| GridMark {
+                        value: hz.log10(),
+                        step_size: 1.0,
+                    })
+                    .collect()
+            })
+            .x_axis_formatter(|mark, _| format_freq(10f64.powf(mark.value).round() as f32))
+            .label_formatter(|hover| {
+                let point = match hover {
+                    HoverPosition::NearDataPoint { position, .. } => position,
+                    HoverPosition::Elsewhere { position } => position,
+                };
+                Some(format!(
+                    "{} Hz\n{:.1} dB",
+                    10f64.powf(point.x).round(),
+                    point.y
+                ))
+            })
+            .include_y(0.0)
+            .include_y(SPECTRUM_FLOOR_DB)
+            .show(ui, |plot_ui| {
+                for band in self.config.bands().iter().filter(|b| b.low_hz > 0.0) {
+                    plot_ui.vline(
+                        VLine::new("", (band.low_hz as f64).log10()).color(Color32::from_gray(60)),
+                    );
+                }
+                plot_ui.line(
+                    Line::new("Whole file", points(&self.spectrum_db))
+                        .color(Color32::from_gray(150)),
+                );
+                if let Some(step) = self.steps.get(self.position) {
+                    plot_ui.line(
+                        Line::new(
+                            format!("At {}", self.position_label()),
+                            points(&step.spectrum_db),
+                        )
+                        .color(Color32::from_rgb(0x68, 0xB4, 0xFF)),
+                    );
+                }
+            });
+    }
+
+    /// Band percentages over time; click or drag to move the position
+    fn timeline_plot(&mut self, ui: &mut egui::Ui) {
+        let response = Plot::new("timeline")
+            .legend(Legend::default())
+            .x_axis_label("Time")
+            .y_axis_label("%")
+            .x_axis_formatter(|mark, _| {
+                format_time(mark.value.max(0.0) as f32)
+                    .trim_end()
+                    .to_string()
+            })
+            .include_y(0.0)
+            .allow_drag(false)
+            .show(ui, |plot_ui| {
+                for (i, band) in self.config.bands().iter().enumerate() {
+                    let points: PlotPoints = self
+                        .steps
+                        .iter()
+                        .enumerate()
+                        .map(|(n, step)| {
+                            let pct = self.percentages(&step.raw_pct, &step.k_pct);
+                            [(n * STEP_SECS) as f64, pct[i]]
+                        })
+                        .collect();
+                    plot_ui.line(Line::new(&*band.label, points).color(self.color(band)));
+                }
+                plot_ui.vline(
+                    VLine::new("", (self.position * STEP_SECS) as f64).color(Color32::WHITE),
+                );
+
+                let clicked = plot_ui.response().clicked() || plot_ui.response().dragged();
+                clicked.then(|| plot_ui.pointer_coordinate()).flatten()
+            });
+
+        if let Some(point) = response.inner
+            && !self.steps.is_empty()
+        {
+            let step = (point.x / STEP_SECS as f64).round().max(0.0) as usize;
+            self.position = step.min(self.steps.len() - 1);
+        }
+    }
+}
+
+impl eframe::App for Viewer {
+    fn ui(&mut self, ui: &mut egui::Ui, _frame: &mut eframe::Frame) {
+        egui::Panel::top("controls").show(ui, |ui| self.controls(ui));
+        egui::Panel::right("bands").show(ui, |ui| self.band_table(ui));
+        egui::CentralPanel::default().show(ui, |ui| {
+            let height = ui.available_height() / 2.0;
+            self.spectrum_plot(ui, height);
+            self.timeline_plot(ui);
+        });
+    }
+}
+
+/// log10 frequencies of the spectrum points, evenly spaced from SPECTRUM_MIN_HZ to `max_hz`
+fn log_frequencies(max_hz: f64) -> Vec<f64> {
+    let (low, high) = (SPECTRUM_MIN_HZ.log10(), max_hz.log10());
+    let step = (high - low) / (SPECTRUM_POINTS - 1) as f64;
+    (0..SPECTRUM_POINTS)
+        .map(|i| low + step * i as f64)
+        .collect()
+}
+
+/// Reduce an FFT power spectrum to levels at the given log frequencies
+///
+/// Each point takes the peak bin within its share of the axis (so tones stay
+/// visible at high frequencies), or the nearest bin where bins are sparser than points.
+fn spectrum_points(spectrum: &[f64], log_freqs: &[f64], sample_rate: f64) -> Vec<f64> {
+    let hz_per_bin = sample_rate / FFT_SIZE as f64;
+    // Peak bin power of a full-scale sine through the Hann window: (N/4)^2
+    let full_scale = (FFT_SIZE as f64 / 4.0).powi(2);
+    let half_step = log_freqs
+        .get(1)
+        .map(|second| (second - log_freqs[0]) / 2.0)
+        .unwrap_or(0.0);
+    let last_bin = spectrum.len() - 1;
+
+    log_freqs
+        .iter()
+        .map(|&log_hz| {
+            let lo = (10f64.powf(log_hz - half_step) / hz_per_bin).ceil() as usize;
+            let hi = ((10f64.powf(log_hz + half_step) / hz_per_bin).floor() as usize).min(last_bin);
+            let power = if lo <= hi {
+                spectrum[lo..=hi].iter().copied().fold(0.0, f64::max)
+            } else {
+                spectrum[((10f64.powf(log_hz) / hz_per_bin).round() as usize).min(last_bin)]
+            };
+            (10.0 * (power / full_scale).log10()).max(SPECTRUM_FLOOR_DB)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_full_scale_sine_peaks_at_zero_db() {
+        let samples: Vec<f32> = (0..FFT_SIZE * 2)
+            .map(|i| (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / 48000.0).sin())
+            .collect();
+        let mut ctx = AnalysisContext::new(48000, &AnalysisConfig::default());
+        let log_freqs = log_frequencies(24000.0);
+        let levels = spectrum_points(&average_spectrum(&samples, &mut ctx), &log_freqs, 48000.0);
+
+        let (peak, db) = log_freqs
+            .iter()
+            .zip(&levels)
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .unwrap();
+        assert!((10f64.powf(*peak) - 1000.0).abs() < 20.0);
+        // Within the Hann window's scalloping loss
+        assert!(*db > -1.5 && *db < 0.1, "{} dB", db);
+        assert_eq!(levels[0], SPECTRUM_FLOOR_DB);
+    }
+}
@@ -0,0 +1,42 @@
+//! Audio frequency band analysis with K-weighting and dynamics
+//!
+//! [`Analyzer`] measures how the energy of a file or sample buffer splits across
+//! the bandstat bands, the numbers the `bandstat` command prints:
+//!
+//! ```no_run
+//! use bandstat::{AnalysisConfig, Analyzer};
+//!
+//! let mut analyzer = Analyzer::new(AnalysisConfig::default());
+//! let result = analyzer.analyze_file("mix.wav")?;
+//! for band in &result.bands {
+//!     println!("{:<5} {:5.1}%", band.label, band.raw_pct);
+//! }
+//! # Ok::<(), String>(())
+//! ```
+//!
+//! Results implement serde's `Serialize` and `Deserialize`, with the field layout
+//! versioned by [`RESULT_FORMAT`]. [`AnalysisConfig`] holds every analysis setting, from
+//! the band layout to the weighting, so analyzers with different settings can coexist.
+//! The [`analysis`] and [`audio`] modules expose the building blocks, and the [`chart`]
+//! module renders results as PNG charts.
+//! With the `ffi` feature, the `ffi` module exposes the analysis to C and C++ hosts.
+//!
+//! File decoding needs the `decode` feature and charts the `charts` feature; the default
+//! `cli` feature, which builds the `bandstat` command, enables both. Without them the
+//! crate builds for `wasm32-unknown-unknown`, where the `wasm` feature exports
+//! [`Analyzer::analyze_samples`] to JavaScript.
+
+pub mod analysis;
+mod analyzer;
+pub mod audio;
+#[cfg(feature = "charts")]
+pub mod chart;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use analysis::{AnalysisConfig, Band, Precision, Weighting, default_bands};
+pub use analyzer::{
+    AnalysisResult, Analyzer, BandResult, MetricResult, RESULT_FORMAT, check_format,
+};
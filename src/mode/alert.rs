@@ -0,0 +1,379 @@
+//! Alert rules for monitoring mode (band thresholds held over consecutive intervals)
+
+use std::process::Command;
+use std::thread::JoinHandle;
+
+use serde::Deserialize;
+use serde_json::json;
+
+use super::monitor::parse_period;
+use crate::analysis::Band;
+use crate::output::{format_time, print_warning};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleFile {
+    #[serde(default, rename = "rule")]
+    rules: Vec<RuleConfig>,
+}
+
+/// One `[[rule]]` table as written in the rules file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleConfig {
+    name: String,
+    bands: Vec<String>,
+    below: Option<f64>,
+    above: Option<f64>,
+    /// How long the condition must hold before the alert fires (default: one interval)
+    #[serde(rename = "for")]
+    hold: Option<String>,
+    /// How long the condition must be clear before the alert resets (default: same as `for`)
+    clear_after: Option<String>,
+    /// Margin (percentage points) the value must cross back over before counting as clear
+    #[serde(default)]
+    hysteresis: f64,
+    command: Option<String>,
+    webhook: Option<String>,
+}
+
+#[derive(Clone, Copy)]
+enum Threshold {
+    Below(f64),
+    Above(f64),
+}
+
+/// A resolved alert rule with its runtime state
+pub(crate) struct AlertRule {
+    name: String,
+    label: String,
+    band_indices: Vec<usize>,
+    threshold: Threshold,
+    trigger_count: usize,
+    clear_count: usize,
+    hysteresis: f64,
+    command: Option<String>,
+    webhook: Option<String>,
+    active: bool,
+    streak: usize,
+}
+
+impl AlertRule {
+    /// Whether the summed band share violates the rule, given the current state
+    fn violated(&self, value: f64) -> bool {
+        // While firing, the value must clear the threshold by the hysteresis margin
+        let margin = if self.active { self.hysteresis } else { 0.0 };
+        match self.threshold {
+            Threshold::Below(limit) => value < limit + margin,
+            Threshold::Above(limit) => value > limit - margin,
+        }
+    }
+
+    /// Advance the state machine by one interval; returns the new state on a transition
+    fn update(&mut self, value: f64) -> Option<AlertState> {
+        let violated = self.violated(value);
+        // Count consecutive intervals pointing away from the current state
+        if violated != self.active {
+            self.streak += 1;
+        } else {
+            self.streak = 0;
+        }
+
+        let needed = if self.active {
+            self.clear_count
+        } else {
+            self.trigger_count
+        };
+        if self.streak < needed {
+            return None;
+        }
+
+        self.active = !self.active;
+        self.streak = 0;
+        Some(if self.active {
+            AlertState::Triggered
+        } else {
+            AlertState::Cleared
+        })
+    }
+
+    fn condition(&self) -> String {
+        match self.threshold {
+            Threshold::Below(limit) => format!("{} < {}%", self.label, limit),
+            Threshold::Above(limit) => format!("{} > {}%", self.label, limit),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum AlertState {
+    Triggered,
+    Cleared,
+}
+
+impl AlertState {
+    fn as_str(self) -> &'static str {
+        match self {
+            AlertState::Triggered => "triggered",
+            AlertState::Cleared => "cleared",
+        }
+    }
+}
+
+/// Load alert rules from a TOML file, converting durations into interval counts
+pub(crate) fn load_rules(
+    path: &str,
+    bands: &[Band],
+    interval_secs: u32,
+) -> Result<Vec<AlertRule>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    parse_rules(&text, bands, interval_secs).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn parse_rules(text: &str, bands: &[Band], interval_secs: u32) -> Result<Vec<AlertRule>, String> {
+    let file: RuleFile = toml::from_str(text).map_err(|e| e.to_string())?;
+    if file.rules.is_empty() {
+        return Err("no [[rule]] entries found".to_string());
+    }
+
+    let intervals = |period: &str| -> Result<usize, String> {
+        let secs = parse_period(period)?;
+        Ok(secs.div_ceil(interval_secs as u64).max(1) as usize)
+    };
+
+    file.rules
+        .into_iter()
+        .map(|rule| {
+            let band_indices = rule
+                .bands
+                .iter()
+                .map(|name| {
+                    bands
+                        .iter()
+                        .position(|b| b.label.eq_ignore_ascii_case(name))
+                        .ok_or_else(|| format!("rule '{}': unknown band '{}'", rule.name, name))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if band_indices.is_empty() {
+                return Err(format!("rule '{}': no bands given", rule.name));
+            }
+
+            let threshold = match (rule.below, rule.above) {
+                (Some(limit), None) => Threshold::Below(limit),
+                (None, Some(limit)) => Threshold::Above(limit),
+                _ => {
+                    return Err(format!(
+                        "rule '{}': specify exactly one of 'below' or 'above'",
+                        rule.name
+                    ));
+                }
+            };
+            if rule.command.is_none() && rule.webhook.is_none() {
+                print_warning(&format!(
+                    "rule '{}' has no command or webhook (alerts are only printed)",
+                    rule.name
+                ));
+            }
+
+            let trigger_count = match &rule.hold {
+                Some(period) => intervals(period)?,
+                None => 1,
+            };
+            let clear_count = match &rule.clear_after {
+                Some(period) => intervals(period)?,
+                None => trigger_count,
+            };
+
+            Ok(AlertRule {
+                label: band_indices
+                    .iter()
+                    .map(|&i| &*bands[i].label)
+                    .collect::<Vec<_>>()
+                    .join("+"),
+                name: rule.name,
+                band_indices,
+                threshold,
+                trigger_count,
+                clear_count,
+                hysteresis: rule.hysteresis.max(0.0),
+                command: rule.command,
+                webhook: rule.webhook,
+                active: false,
+                streak: 0,
+            })
+        })
+        .collect()
+}
+
+/// Evaluates alert rules per interval and dispatches their actions
+pub(crate) struct Alerter {
+    rules: Vec<AlertRule>,
+    pending: Vec<JoinHandle<()>>,
+}
+
+impl Alerter {
+    pub(crate) fn new(rules: Vec<AlertRule>) -> Self {
+        Self {
+            rules,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Feed one interval of band percentages into every rule
+    pub(crate) fn evaluate(&mut self, percentages: &[f64], stream_secs: f64, timestamp: &str) {
+        // Commands and webhooks that are done need no joining; a monitor runs for weeks
+        self.pending.retain(|handle| !handle.is_finished());
+        for rule in &mut self.rules {
+            let value: f64 = rule.band_indices.iter().map(|&i| percentages[i]).sum();
+            let Some(state) = rule.update(value) else {
+                continue;
+            };
+
+            print_warning(&format!(
+                "Alert '{}' {} at {} ({}, now {:.2}%)",
+                rule.name,
+                state.as_str(),
+                format_time(stream_secs as f32).trim(),
+                rule.condition(),
+                value
+            ));
+
+            if let Some(command) = &rule.command {
+                self.pending
+                    .push(spawn_command(command, rule, state, value, stream_secs));
+            }
+            if let Some(url) = &rule.webhook {
+                let body = json!({
+                    "rule": rule.name,
+                    "state": state.as_str(),
+                    "condition": rule.condition(),
+                    "value": value,
+                    "stream_time": stream_secs,
+                    "timestamp": timestamp,
+                });
+                self.pending.push(spawn_webhook(url, body.to_string()));
+            }
+        }
+    }
+
+    /// Wait for outstanding commands and webhooks to finish
+    pub(crate) fn finish(self) {
+        for handle in self.pending {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn spawn_command(
+    command: &str,
+    rule: &AlertRule,
+    state: AlertState,
+    value: f64,
+    stream_secs: f64,
+) -> JoinHandle<()> {
+    let mut cmd = if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.arg("/C");
+        c
+    } else {
+        let mut c = Command::new("sh");
+        c.arg("-c");
+        c
+    };
+    cmd.arg(command)
+        .env("BANDSTAT_RULE", &rule.name)
+        .env("BANDSTAT_STATE", state.as_str())
+        .env("BANDSTAT_CONDITION", rule.condition())
+        .env("BANDSTAT_VALUE", format!("{:.2}", value))
+        .env("BANDSTAT_TIME", format!("{:.1}", stream_secs));
+
+    let command = command.to_string();
+    std::thread::spawn(move || match cmd.status() {
+        Ok(status) if !status.success() => {
+            print_warning(&format!(
+                "Alert command '{}' exited with {}",
+                command, status
+            ));
+        }
+        Ok(_) => {}
+        Err(e) => print_warning(&format!("Failed to run alert command '{}': {}", command, e)),
+    })
+}
+
+fn spawn_webhook(url: &str, body: String) -> JoinHandle<()> {
+    let url = url.to_string();
+    std::thread::spawn(move || {
+        if let Err(e) = ureq::post(&url)
+            .header("Content-Type", "application/json")
+            .send(body.as_bytes())
+        {
+            print_warning(&format!("Alert webhook {} failed: {}", url, e));
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::default_bands;
+
+    const DEAD_TWEETER: &str = r#"
+        [[rule]]
+        name = "dead tweeter"
+        bands = ["HIGH", "UHIG", "AIR"]
+        below = 0.5
+        for = "3s"
+        clear_after = "2s"
+        hysteresis = 0.2
+    "#;
+
+    fn feed(rule: &mut AlertRule, values: &[f64]) -> Vec<Option<AlertState>> {
+        values.iter().map(|&v| rule.update(v)).collect()
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let rules = parse_rules(DEAD_TWEETER, &default_bands(), 1).unwrap();
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].label, "HIGH+UHIG+AIR");
+        assert_eq!(rules[0].trigger_count, 3);
+        assert_eq!(rules[0].clear_count, 2);
+
+        let bad_band = "[[rule]]\nname = \"x\"\nbands = [\"NOPE\"]\nbelow = 1.0\n";
+        assert!(parse_rules(bad_band, &default_bands(), 1).is_err());
+        let both = "[[rule]]\nname = \"x\"\nbands = [\"AIR\"]\nbelow = 1.0\nabove = 2.0\n";
+        assert!(parse_rules(both, &default_bands(), 1).is_err());
+    }
+
+    #[test]
+    fn test_trigger_requires_consecutive_intervals() {
+        let mut rule = parse_rules(DEAD_TWEETER, &default_bands(), 1)
+            .unwrap()
+            .remove(0);
+        let events = feed(&mut rule, &[0.1, 0.1, 1.0, 0.1, 0.1, 0.1]);
+        assert_eq!(
+            events,
+            vec![None, None, None, None, None, Some(AlertState::Triggered)]
+        );
+    }
+
+    #[test]
+    fn test_hysteresis_prevents_flapping() {
+        let mut rule = parse_rules(DEAD_TWEETER, &default_bands(), 1)
+            .unwrap()
+            .remove(0);
+        feed(&mut rule, &[0.0, 0.0, 0.0]);
+        assert!(rule.active);
+
+        // Hovering just above the threshold stays inside the hysteresis band
+        assert!(
+            feed(&mut rule, &[0.6, 0.6, 0.6])
+                .iter()
+                .all(Option::is_none)
+        );
+        assert_eq!(
+            feed(&mut rule, &[0.8, 0.8]),
+            vec![None, Some(AlertState::Cleared)]
+        );
+    }
+}
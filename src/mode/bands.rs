@@ -0,0 +1,76 @@
+//! Band layout listing (`bandstat bands`), so scripts and front-ends can match a run's bands
+
+use serde_json::{Value, json};
+
+use super::monitor::{format_utc, unix_now};
+use crate::analysis::{AnalysisConfig, GROUP_LABELS, band_group};
+use crate::chart::band_color;
+use crate::output::{DisplayOptions, pad_end};
+use bandstat::RESULT_FORMAT;
+
+/// Print the bands of the configured layout with their group and chart color
+pub fn run_bands(config: &AnalysisConfig, display: &DisplayOptions) {
+    let bands = config.bands();
+    let group_edges = config.group_edges();
+
+    if display.json_output() {
+        let layout: Vec<Value> = bands
+            .iter()
+            .map(|band| {
+                let mut value = json!(band);
+                value["group"] = json!(GROUP_LABELS[band_group(band, group_edges)]);
+                value["color"] = json!(band_color(band, config.base_bands()));
+                value
+            })
+            .collect();
+        let document = json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "format": RESULT_FORMAT,
+            "generated": format_utc(unix_now()),
+            "mode": "bands",
+            "bands": layout,
+            "group_edges_hz": group_edges,
+            "crossover_octaves": config.crossover_octaves(),
+        });
+        println!("{:#}", document);
+        return;
+    }
+
+    let label_width = display.band_label_width(bands, 4);
+    println!(
+        "{:<w$}  {:>7}  {:>7}  {:<5}  COLOR",
+        "BAND",
+        "LOW Hz",
+        "HIGH Hz",
+        "GROUP",
+        w = label_width
+    );
+    for band in bands {
+        let high = if band.high_hz >= f32::MAX / 2.0 {
+            "-".to_string()
+        } else {
+            format!("{:.0}", band.high_hz)
+        };
+        println!(
+            "{}  {:>7.0}  {:>7}  {:<5}  {}",
+            pad_end(&display.band_label(band), label_width),
+            band.low_hz,
+            high,
+            GROUP_LABELS[band_group(band, group_edges)],
+            band_color(band, config.base_bands()),
+        );
+    }
+
+    let [mid, high] = group_edges;
+    println!();
+    println!(
+        "Groups: LOW below {} Hz, MID from {} Hz, HIGH from {} Hz",
+        mid, mid, high
+    );
+    let crossover = config.crossover_octaves();
+    if crossover > 0.0 {
+        println!("Crossover: {} octaves around each edge", crossover);
+    } else {
+        println!("Crossover: none (hard band edges)");
+    }
+}
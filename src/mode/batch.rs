@@ -0,0 +1,664 @@
+//! Batch mode: many files analyzed each on its own, e.g. a whole library
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::mpsc;
+use std::thread;
+
+use crate::analysis::{AnalysisConfig, Band, GROUP_LABELS, group_totals};
+use crate::audio::{LoadOptions, is_url};
+use crate::chart::{ChartOptions, ImageSpec};
+use crate::output::{DisplayOptions, Output, csv_field, pad_end, pad_start, print_error};
+
+use super::FileStats;
+use super::monitor::unix_now;
+use super::parallel::analyze_files;
+use super::report::report_context;
+use super::stats::render_distribution_chart;
+
+/// Placeholder in batch `--image` paths for each file's name
+pub const NAME_PLACEHOLDER: &str = "{name}";
+
+/// Width of the loudness column in front of the band percentages
+const LUFS_WIDTH: usize = 7;
+
+/// Largest bands named in summary rows
+const TOP_BANDS: usize = 3;
+
+/// Extensions of the files taken from directories and watched folders; compressed formats
+/// other than MP3 need `--ffmpeg-fallback`
+const AUDIO_EXTENSIONS: &[&str] = &[
+    "wav", "wave", "bwf", "rf64", "aif", "aiff", "aifc", "mp3", "flac", "ogg", "opus", "m4a",
+    "aac", "wma",
+];
+
+/// Batch output settings
+pub struct BatchOptions<'a> {
+    /// Stats charts, one per file (each path contains `NAME_PLACEHOLDER`)
+    pub images: &'a [ImageSpec],
+    /// Files analyzed at once, and threads rendering charts
+    pub jobs: usize,
+    /// Print a short summary row per file instead of every band
+    pub summary: bool,
+    /// CSV file receiving the summary and band shares of each file
+    pub csv_path: Option<&'a str>,
+}
+
+/// Stats chart of one file, waiting for a rendering thread
+struct ChartJob {
+    name: String,
+    percentages: Vec<f64>,
+    path: String,
+}
+
+/// Read file paths from stdin, one per line, for a `-` among the batch inputs
+pub fn read_file_list(input: impl std::io::BufRead) -> Result<Vec<String>, String> {
+    let mut files = Vec::new();
+    for line in input.lines() {
+        let line = line.map_err(|e| format!("cannot read the file list: {}", e))?;
+        let path = line.trim();
+        if !path.is_empty() {
+            files.push(path.to_string());
+        }
+    }
+    Ok(files)
+}
+
+/// Expand file arguments holding glob patterns (`*`, `?`, `[...]`), e.g. `mixes/*.wav`, for
+/// shells that pass them on unexpanded (Windows cmd and PowerShell, or quoted patterns).
+/// Existing files, URLs, and `-` are kept as they are, and matches are sorted by path.
+pub fn expand_file_patterns(files: Vec<String>) -> Result<Vec<String>, String> {
+    let options = glob::MatchOptions {
+        // Windows file names match regardless of case, like the shells there
+        case_sensitive: !cfg!(windows),
+        require_literal_separator: true,
+        require_literal_leading_dot: true,
+    };
+    let mut expanded = Vec::with_capacity(files.len());
+    for file in files {
+        if !file.contains(['*', '?', '[']) || is_url(&file) || Path::new(&file).exists() {
+            expanded.push(file);
+            continue;
+        }
+        let matches = glob::glob_with(&file, options)
+            .map_err(|e| format!("{}: invalid pattern ({})", file, e))?;
+        let count = expanded.len();
+        for path in matches {
+            let path = path.map_err(|e| e.to_string())?;
+            expanded.push(path.to_string_lossy().into_owned());
+        }
+        if expanded.len() == count {
+            return Err(format!("{}: no files match", file));
+        }
+    }
+    Ok(expanded)
+}
+
+/// Audio files in a directory (and with `recursive`, in its subdirectories), sorted by path.
+/// Hidden files and directories are skipped, and so are symlinked directories.
+pub fn list_audio_files(dir: &str, recursive: bool) -> Result<Vec<String>, String> {
+    let mut files = Vec::new();
+    collect_audio_files(Path::new(dir), recursive, &mut files)?;
+    files.sort();
+    Ok(files
+        .into_iter()
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect())
+}
+
+fn collect_audio_files(
+    dir: &Path,
+    recursive: bool,
+    files: &mut Vec<PathBuf>,
+) -> Result<(), String> {
+    let error = |e: std::io::Error| format!("{}: {}", dir.display(), e);
+    for entry in std::fs::read_dir(dir).map_err(error)? {
+        let entry = entry.map_err(error)?;
+        let path = entry.path();
+        if entry.file_type().map_err(error)?.is_dir() {
+            if recursive && !is_hidden(&path) {
+                collect_audio_files(&path, recursive, files)?;
+            }
+        } else if is_audio_file(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Whether a path names an audio file; hidden files such as macOS `._` metadata are skipped
+pub(super) fn is_audio_file(path: &Path) -> bool {
+    let audio = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            AUDIO_EXTENSIONS
+                .iter()
+                .any(|known| ext.eq_ignore_ascii_case(known))
+        });
+    audio && !is_hidden(path)
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_none_or(|name| name.starts_with('.'))
+}
+
+/// Analyze each file on its own, `jobs` files at a time, printing one row per file in
+/// input order as the files finish. Stats charts render on `jobs` threads meanwhile.
+/// Returns false if any file could not be analyzed or the CSV could not be written.
+pub fn run_batch(
+    filenames: &[String],
+    load_options: &LoadOptions,
+    batch: &BatchOptions,
+    config: &AnalysisConfig,
+    out: &Output,
+) -> bool {
+    let bands = config.bands();
+    let use_k_weighting = out.weighted;
+    let json = out.display.json_output();
+
+    let mut csv = match batch
+        .csv_path
+        .map(|path| CsvTable::create(path, bands, use_k_weighting, out.display))
+    {
+        None => None,
+        Some(Ok(csv)) => Some(csv),
+        Some(Err(e)) => {
+            print_error(&e);
+            return false;
+        }
+    };
+
+    if !json {
+        if !out.quiet {
+            writeln!(out.sink);
+            writeln!(
+                out.sink,
+                "Batch Analysis: {} files, {}",
+                filenames.len(),
+                out.share_title(use_k_weighting)
+            );
+            if batch.summary {
+                writeln!(
+                    out.sink,
+                    "Largest {} bands, LOW/MID/HIGH {}, and DYN, the band dynamics averaged by Raw share (dB)",
+                    TOP_BANDS,
+                    out.share_title(true)
+                );
+                writeln!(out.sink);
+            } else {
+                out.print_bands(bands);
+            }
+        }
+        if batch.summary {
+            print_summary_header(bands, out);
+        } else {
+            print_row_header(bands, out);
+        }
+    }
+
+    let (sender, receiver) = mpsc::channel::<ChartJob>();
+    let receiver = Mutex::new(receiver);
+    let mut chart_paths = ChartPaths::default();
+    let mut all_analyzed = true;
+    let images = batch.images;
+
+    thread::scope(|scope| {
+        if !images.is_empty() {
+            for _ in 0..batch.jobs {
+                scope.spawn(|| render_charts(&receiver, config, use_k_weighting, &out.charts));
+            }
+        }
+
+        analyze_files(
+            filenames,
+            config,
+            batch.jobs,
+            !out.quiet,
+            load_options,
+            |index, result| {
+                let stats = match result {
+                    Ok(stats) => stats,
+                    Err(e) => {
+                        print_error(&e);
+                        all_analyzed = false;
+                        return;
+                    }
+                };
+                out.record_verify(stats.verify.as_ref());
+                let summary = Summary::new(&stats, config, use_k_weighting);
+                if batch.summary && !json {
+                    print_summary_row(&stats, &summary, bands, out);
+                } else {
+                    print_result(&stats, config, out, load_options.highpass_hz, json);
+                }
+                if let Some(table) = csv.as_mut()
+                    && let Err(e) = table.write_row(&filenames[index], &stats, &summary)
+                {
+                    print_error(&e);
+                    all_analyzed = false;
+                    csv = None;
+                }
+
+                let percentages = if use_k_weighting {
+                    &stats.k_pct
+                } else {
+                    &stats.raw_pct
+                };
+                let name = chart_paths.unique_name(&filenames[index]);
+                for image in images {
+                    let job = ChartJob {
+                        name: stats.name.clone(),
+                        percentages: percentages.clone(),
+                        path: image.path.replace(NAME_PLACEHOLDER, &name),
+                    };
+                    // Rendering threads only stop once the sender is dropped
+                    sender.send(job).expect("chart threads stopped early");
+                }
+            },
+        );
+        drop(sender);
+    });
+
+    if let Some(table) = csv
+        && let Err(e) = table.finish()
+    {
+        print_error(&e);
+        all_analyzed = false;
+    }
+    all_analyzed
+}
+
+/// Short profile of one file for summary rows and the CSV table
+struct Summary {
+    /// Labels and shares of the largest bands, largest first
+    top: Vec<(String, f64)>,
+    /// K-weighted LOW/MID/HIGH shares
+    k_groups: Vec<f64>,
+    /// Band dynamics averaged with each band's Raw share as its weight (NaN when unknown)
+    dynamics_db: f64,
+}
+
+impl Summary {
+    fn new(stats: &FileStats, config: &AnalysisConfig, use_k_weighting: bool) -> Self {
+        let bands = config.bands();
+        let percentages = if use_k_weighting {
+            &stats.k_pct
+        } else {
+            &stats.raw_pct
+        };
+        let mut top: Vec<(String, f64)> = bands
+            .iter()
+            .zip(percentages)
+            .filter(|(_, pct)| pct.is_finite())
+            .map(|(band, &pct)| (band.label.to_string(), pct))
+            .collect();
+        // Stable, so equal shares keep the band order
+        top.sort_by(|a, b| b.1.total_cmp(&a.1));
+        top.truncate(TOP_BANDS);
+
+        let (sum, weight) = stats
+            .dynamics
+            .iter()
+            .zip(&stats.raw_pct)
+            .filter(|(db, pct)| db.is_finite() && pct.is_finite())
+            .fold((0.0, 0.0), |(sum, weight), (db, pct)| {
+                (sum + db * pct, weight + pct)
+            });
+
+        Summary {
+            top,
+            k_groups: group_totals(bands, config.group_edges(), &stats.k_pct),
+            dynamics_db: if weight > 0.0 { sum / weight } else { f64::NAN },
+        }
+    }
+}
+
+/// Width of the percentage and dB cells of summary rows
+fn summary_cell_width(out: &Output) -> usize {
+    (4 + out.display.decimals).max(5)
+}
+
+/// Width of the column of largest bands, e.g. `BASS  22.1  UBAS  18.0  LMID  12.3`
+fn top_column_width(bands: &[Band], out: &Output) -> usize {
+    let entry = out.display.band_label_width(bands, 0) + 1 + summary_cell_width(out);
+    (TOP_BANDS * entry + (TOP_BANDS - 1) * 2).max(format!("TOP {}", out.share_title(true)).len())
+}
+
+fn print_summary_header(bands: &[Band], out: &Output) {
+    let title = format!("TOP {}", out.share_title(out.weighted));
+    let top_width = top_column_width(bands, out);
+    let w = summary_cell_width(out);
+    write!(
+        out.sink,
+        "{:>lw$}  {:<tw$}",
+        "LUFS",
+        title,
+        lw = LUFS_WIDTH,
+        tw = top_width
+    );
+    for label in GROUP_LABELS {
+        write!(out.sink, " {:>w$}", label);
+    }
+    writeln!(out.sink, " {:>w$}", "DYN");
+    writeln!(
+        out.sink,
+        "{}",
+        "-".repeat(LUFS_WIDTH + 2 + top_width + (w + 1) * (GROUP_LABELS.len() + 1))
+    );
+}
+
+/// Print one summary row: loudness, largest bands, K-weighted groups, average dynamics, name
+fn print_summary_row(stats: &FileStats, summary: &Summary, bands: &[Band], out: &Output) {
+    let w = summary_cell_width(out);
+    let p = out.display.decimals;
+    let cell = |value: f64| {
+        if value.is_finite() {
+            format!(" {:>w$.p$}", value)
+        } else {
+            format!(" {:>w$}", "-")
+        }
+    };
+
+    if stats.integrated_lufs.is_finite() {
+        write!(out.sink, "{:>w$.1}", stats.integrated_lufs, w = LUFS_WIDTH);
+    } else {
+        write!(out.sink, "{:>w$}", "-", w = LUFS_WIDTH);
+    }
+    let label_width = out.display.band_label_width(bands, 0);
+    let top: Vec<String> = summary
+        .top
+        .iter()
+        .map(|(label, pct)| {
+            let label = pad_start(&out.display.display_text(label), label_width);
+            format!("{} {:>w$.p$}", label, pct)
+        })
+        .collect();
+    write!(
+        out.sink,
+        "  {}",
+        pad_end(&top.join("  "), top_column_width(bands, out))
+    );
+    let cells: String = summary
+        .k_groups
+        .iter()
+        .chain(std::iter::once(&summary.dynamics_db))
+        .map(|&value| cell(value))
+        .collect();
+    writeln!(
+        out.sink,
+        "{}  {}",
+        cells,
+        out.display.display_text(&stats.name)
+    );
+}
+
+/// CSV table of every file's summary and band shares, written as the files finish
+struct CsvTable {
+    path: String,
+    writer: BufWriter<File>,
+    use_k_weighting: bool,
+    display: DisplayOptions,
+}
+
+impl CsvTable {
+    /// Create the file and write the header row
+    fn create(
+        path: &str,
+        bands: &[Band],
+        use_k_weighting: bool,
+        display: DisplayOptions,
+    ) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut table = CsvTable {
+            path: path.to_string(),
+            writer: BufWriter::new(file),
+            use_k_weighting,
+            display,
+        };
+
+        let mut columns = vec!["file".to_string(), "lufs".to_string()];
+        for rank in 1..=TOP_BANDS {
+            columns.push(format!("top{}", rank));
+            columns.push(format!("top{}_pct", rank));
+        }
+        for label in GROUP_LABELS {
+            columns.push(format!("{}_k_pct", label.to_lowercase()));
+        }
+        columns.push("dynamics_db".to_string());
+        let weighting = if use_k_weighting { "k" } else { "raw" };
+        columns.extend(
+            bands
+                .iter()
+                .map(|band| csv_field(&format!("{}_{}_pct", band.label, weighting)).into_owned()),
+        );
+        table.write_line(&columns)?;
+        Ok(table)
+    }
+
+    fn write_row(
+        &mut self,
+        filename: &str,
+        stats: &FileStats,
+        summary: &Summary,
+    ) -> Result<(), String> {
+        let mut fields = vec![
+            csv_field(filename).into_owned(),
+            self.display.csv_number(stats.integrated_lufs),
+        ];
+        for rank in 0..TOP_BANDS {
+            match summary.top.get(rank) {
+                Some((label, pct)) => {
+                    fields.push(csv_field(label).into_owned());
+                    fields.push(self.display.csv_number(*pct));
+                }
+                None => fields.extend([String::new(), String::new()]),
+            }
+        }
+        fields.extend(
+            summary
+                .k_groups
+                .iter()
+                .map(|&pct| self.display.csv_number(pct)),
+        );
+        fields.push(self.display.csv_number(summary.dynamics_db));
+        let percentages = if self.use_k_weighting {
+            &stats.k_pct
+        } else {
+            &stats.raw_pct
+        };
+        fields.extend(percentages.iter().map(|&pct| self.display.csv_number(pct)));
+        self.write_line(&fields)
+    }
+
+    fn write_line(&mut self, fields: &[String]) -> Result<(), String> {
+        writeln!(self.writer, "{}", fields.join(",")).map_err(|e| format!("{}: {}", self.path, e))
+    }
+
+    /// Flush the rows still buffered
+    fn finish(mut self) -> Result<(), String> {
+        self.writer
+            .flush()
+            .map_err(|e| format!("{}: {}", self.path, e))
+    }
+}
+
+/// Print the column header of the per-file rows
+pub(super) fn print_row_header(bands: &[Band], out: &Output) {
+    out.print_header(bands, &format!("{:>w$}", "LUFS", w = LUFS_WIDTH));
+    out.print_separator(bands, LUFS_WIDTH);
+}
+
+/// Print the row (or JSON line) of one analyzed file; `highpass_hz` is noted in JSON lines
+pub(super) fn print_result(
+    stats: &FileStats,
+    config: &AnalysisConfig,
+    out: &Output,
+    highpass_hz: Option<f64>,
+    json: bool,
+) {
+    if json {
+        let mut document = report_context(
+            config,
+            std::slice::from_ref(stats),
+            out.weighted,
+            highpass_hz,
+            &out.display,
+            unix_now(),
+        );
+        out.display.round_json(&mut document);
+        writeln!(out.sink, "{}", document);
+    } else {
+        let percentages = if out.weighted {
+            &stats.k_pct
+        } else {
+            &stats.raw_pct
+        };
+        out.print_batch_row(stats.integrated_lufs, LUFS_WIDTH, percentages, &stats.name);
+    }
+}
+
+/// Render queued charts until the queue is closed; each thread keeps its own renderer
+fn render_charts(
+    jobs: &Mutex<mpsc::Receiver<ChartJob>>,
+    config: &AnalysisConfig,
+    use_k_weighting: bool,
+    charts: &ChartOptions,
+) {
+    loop {
+        // The lock is released before rendering, so other threads can take the next job
+        let job = jobs.lock().expect("chart queue poisoned").recv();
+        let Ok(job) = job else {
+            return;
+        };
+        render_distribution_chart(
+            &job.name,
+            &job.percentages,
+            config,
+            use_k_weighting,
+            &job.path,
+            charts,
+        );
+    }
+}
+
+/// File stems used for chart names, made unique so same-named files in different
+/// directories do not overwrite each other's charts
+#[derive(Default)]
+struct ChartPaths {
+    used: HashMap<String, usize>,
+}
+
+impl ChartPaths {
+    /// Stem of `filename`, with `-2`, `-3`, ... appended when it was used before
+    fn unique_name(&mut self, filename: &str) -> String {
+        let stem = Path::new(filename)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or(filename)
+            .to_string();
+        let count = self.used.entry(stem.clone()).or_insert(0);
+        *count += 1;
+        if *count == 1 {
+            stem
+        } else {
+            format!("{}-{}", stem, count)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_file_list_skips_blank_lines() {
+        let input = "a.wav\n\n  dir/b b.flac  \r\n";
+        assert_eq!(
+            read_file_list(input.as_bytes()).unwrap(),
+            vec!["a.wav".to_string(), "dir/b b.flac".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_is_audio_file() {
+        assert!(is_audio_file(Path::new("bounces/Mix v3.WAV")));
+        assert!(is_audio_file(Path::new("master.flac")));
+        assert!(!is_audio_file(Path::new("bounces/._Mix v3.wav")));
+        assert!(!is_audio_file(Path::new("session.ptx")));
+        assert!(!is_audio_file(Path::new("bounces/wav")));
+    }
+
+    #[test]
+    fn test_expand_file_patterns() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for name in ["b.wav", "a.wav", "c.flac", ".hidden.wav", "take [1].wav"] {
+            std::fs::write(dir.path().join(name), "").unwrap();
+        }
+        let root = dir.path().to_str().unwrap();
+        let path = |name: &str| format!("{}/{}", root, name);
+        let names = |files: Vec<String>| -> Vec<String> {
+            expand_file_patterns(files)
+                .unwrap()
+                .iter()
+                .map(|f| f[root.len() + 1..].to_string())
+                .collect()
+        };
+
+        assert_eq!(
+            names(vec![path("*.wav"), path("c.flac")]),
+            ["a.wav", "b.wav", "take [1].wav", "c.flac"]
+        );
+        // Existing names with pattern characters are files, not patterns
+        assert_eq!(names(vec![path("take [1].wav")]), ["take [1].wav"]);
+        assert!(
+            expand_file_patterns(vec![path("*.mp3")])
+                .unwrap_err()
+                .contains("no files match")
+        );
+        assert_eq!(
+            expand_file_patterns(vec!["-".to_string(), "https://host/a?b=1".to_string()]).unwrap(),
+            ["-", "https://host/a?b=1"]
+        );
+    }
+
+    #[test]
+    fn test_list_audio_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for name in ["b.wav", "a.flac", "notes.txt", "sub/c.mp3", ".git/d.wav"] {
+            let path = dir.path().join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        let root = dir.path().to_str().unwrap();
+        let names = |recursive| -> Vec<String> {
+            list_audio_files(root, recursive)
+                .unwrap()
+                .iter()
+                .map(|f| f[root.len() + 1..].replace('\\', "/"))
+                .collect()
+        };
+        assert_eq!(names(false), ["a.flac", "b.wav"]);
+        assert_eq!(names(true), ["a.flac", "b.wav", "sub/c.mp3"]);
+    }
+
+    #[test]
+    fn test_csv_field_quotes_separators() {
+        assert_eq!(csv_field("mix.wav"), "mix.wav");
+        assert_eq!(csv_field("a,b \"v2\".wav"), "\"a,b \"\"v2\"\".wav\"");
+    }
+
+    #[test]
+    fn test_chart_names_are_unique() {
+        let mut paths = ChartPaths::default();
+        assert_eq!(paths.unique_name("x/mix.wav"), "mix");
+        assert_eq!(paths.unique_name("y/mix.flac"), "mix-2");
+        assert_eq!(paths.unique_name("y/ref.wav"), "ref");
+        assert_eq!(paths.unique_name("z/mix.wav"), "mix-3");
+    }
+}
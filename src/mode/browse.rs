@@ -0,0 +1,364 @@
+//! Interactive browser of timeline intervals (`--browse`)
+
+use std::ops::Range;
+use std::path::Path;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use serde_json::json;
+
+use super::timeline::{Distribution, IntervalLoudness, TimelineInterval};
+use crate::analysis::{
+    AnalysisConfig, Band, DYNAMICS_DISPLAY_THRESHOLD_PCT, GROUP_LABELS, frame_dynamics,
+    group_totals, powers_to_percentages,
+};
+use crate::output::{DisplayOptions, print_error};
+
+/// Rows moved by Page Up and Page Down
+const PAGE_ROWS: usize = 10;
+
+/// Width of the share bars in the detail pane at 50%
+const BAR_WIDTH: usize = 25;
+
+/// One analyzed (non-silent) interval of the timeline
+pub(super) struct BrowseInterval {
+    pub(super) start_secs: f32,
+    /// Loudest momentary and short-term loudness with `--loudness`
+    pub(super) loudness: Option<IntervalLoudness>,
+    /// Band powers of the interval, weighted as for the table
+    pub(super) powers: Vec<f64>,
+    /// Raw band powers of each FFT frame in the interval, for dynamics
+    pub(super) frames: Vec<Vec<f64>>,
+}
+
+/// Timeline result to browse
+pub(super) struct BrowseTimeline<'a> {
+    pub(super) name: &'a str,
+    pub(super) config: &'a AnalysisConfig,
+    pub(super) use_k_weighting: bool,
+    /// Sample rate of the file, for `--time-format samples`
+    pub(super) sample_rate: u32,
+    pub(super) interval_secs: u32,
+    pub(super) duration_secs: f32,
+    pub(super) intervals: &'a [BrowseInterval],
+    /// The `--format json` document of the whole timeline, the template of exports
+    pub(super) document: &'a serde_json::Value,
+    pub(super) display: DisplayOptions,
+}
+
+impl BrowseTimeline<'_> {
+    /// Percentages of band powers, leaving out the `--exclude-bands` bands
+    fn shares(&self, powers: &[f64]) -> Vec<f64> {
+        powers_to_percentages(powers, self.config.excluded_bands())
+    }
+}
+
+/// Browse the intervals in a full-screen view until it is quit
+pub(super) fn browse_timeline(timeline: &BrowseTimeline) {
+    if timeline.intervals.is_empty() {
+        return;
+    }
+    let mut terminal = ratatui::try_init().unwrap_or_else(|e| {
+        ratatui::restore();
+        print_error(&format!("cannot start the timeline browser ({})", e));
+        std::process::exit(1);
+    });
+    let result = run(&mut terminal, timeline);
+    ratatui::restore();
+    if let Err(e) = result {
+        print_error(&e);
+        std::process::exit(1);
+    }
+}
+
+/// Cursor, selection mark, and the last export's outcome
+struct Browser {
+    table: TableState,
+    /// First interval of the selection; without one, the cursor row is the selection
+    mark: Option<usize>,
+    message: String,
+}
+
+impl Browser {
+    fn cursor(&self) -> usize {
+        self.table.selected().unwrap_or(0)
+    }
+
+    /// Indices of the selected intervals
+    fn selection(&self) -> Range<usize> {
+        let cursor = self.cursor();
+        let mark = self.mark.unwrap_or(cursor);
+        mark.min(cursor)..mark.max(cursor) + 1
+    }
+}
+
+fn run(terminal: &mut DefaultTerminal, timeline: &BrowseTimeline) -> Result<(), String> {
+    let error = |e: std::io::Error| format!("terminal: {}", e);
+    let last = timeline.intervals.len() - 1;
+    let mut browser = Browser {
+        table: TableState::default().with_selected(Some(0)),
+        mark: None,
+        message: "↑/↓ scroll  space: mark selection  e: export  q: quit".to_string(),
+    };
+
+    loop {
+        terminal
+            .draw(|frame| render(frame, timeline, &mut browser))
+            .map_err(error)?;
+        let Event::Key(key) = event::read().map_err(error)? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        let cursor = browser.cursor();
+        let moved = match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+            KeyCode::Up | KeyCode::Char('k') => cursor.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => (cursor + 1).min(last),
+            KeyCode::PageUp => cursor.saturating_sub(PAGE_ROWS),
+            KeyCode::PageDown => (cursor + PAGE_ROWS).min(last),
+            KeyCode::Home | KeyCode::Char('g') => 0,
+            KeyCode::End | KeyCode::Char('G') => last,
+            KeyCode::Char(' ') => {
+                browser.mark = match browser.mark {
+                    Some(_) => None,
+                    None => Some(cursor),
+                };
+                cursor
+            }
+            KeyCode::Char('e') => {
+                browser.message = match export_selection(timeline, browser.selection()) {
+                    Ok(path) => format!("Wrote {}", path),
+                    Err(e) => format!("Error: {}", e),
+                };
+                cursor
+            }
+            _ => cursor,
+        };
+        browser.table.select(Some(moved));
+    }
+}
+
+/// Start and end in seconds of a run of intervals (the last one may end early)
+fn span_secs(timeline: &BrowseTimeline, selection: &Range<usize>) -> (f64, f64) {
+    let intervals = &timeline.intervals[selection.clone()];
+    let start = intervals[0].start_secs as f64;
+    let last = intervals[intervals.len() - 1].start_secs as f64;
+    let end = (last + timeline.interval_secs as f64).min(timeline.duration_secs as f64);
+    (start, end)
+}
+
+/// Write the selected intervals as a timeline document to the working directory, returning
+/// its path
+fn export_selection(timeline: &BrowseTimeline, selection: Range<usize>) -> Result<String, String> {
+    let intervals = &timeline.intervals[selection.clone()];
+    let (start, end) = span_secs(timeline, &selection);
+    let stem = Path::new(timeline.name)
+        .file_stem()
+        .map_or("timeline".into(), |stem| stem.to_string_lossy());
+    let path = format!("{}_{}-{}s.json", stem, start.round(), end.round());
+
+    let mut document = timeline.document.clone();
+    document["intervals"] = json!(
+        intervals
+            .iter()
+            .map(|interval| TimelineInterval {
+                start_secs: interval.start_secs,
+                loudness: interval.loudness,
+                distribution: Distribution::new(
+                    &timeline.shares(&interval.powers),
+                    timeline.config.bands(),
+                    timeline.config.group_edges(),
+                ),
+            })
+            .collect::<Vec<_>>()
+    );
+    document["average"] = json!(Distribution::new(
+        &timeline.shares(&sum_powers(
+            intervals.iter().map(|i| &i.powers),
+            timeline.config.bands().len()
+        )),
+        timeline.config.bands(),
+        timeline.config.group_edges(),
+    ));
+    document["duration_secs"] = json!(end - start);
+    document["selection"] = json!({ "start_secs": start, "end_secs": end });
+    if let Some(document) = document.as_object_mut() {
+        document.remove("chapter_secs");
+        document.remove("chapters");
+    }
+    timeline.display.round_json(&mut document);
+
+    std::fs::write(&path, format!("{:#}\n", document))
+        .map_err(|e| format!("cannot write {} ({})", path, e))?;
+    Ok(path)
+}
+
+/// Band-by-band sum of runs of band powers
+fn sum_powers<'a>(powers: impl Iterator<Item = &'a Vec<f64>>, band_count: usize) -> Vec<f64> {
+    let mut totals = vec![0.0; band_count];
+    for powers in powers {
+        for (total, p) in totals.iter_mut().zip(powers) {
+            *total += p;
+        }
+    }
+    totals
+}
+
+fn render(frame: &mut Frame, timeline: &BrowseTimeline, browser: &mut Browser) {
+    let [header, body, status] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(8),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [list, detail] =
+        Layout::horizontal([Constraint::Length(40), Constraint::Min(40)]).areas(body);
+    let bands = timeline.config.bands();
+    let weighted = if timeline.use_k_weighting {
+        timeline.config.weighting.name()
+    } else {
+        "Raw".to_string()
+    };
+    let time = |secs: f64| {
+        timeline
+            .display
+            .format_timestamp(secs, timeline.sample_rate)
+    };
+
+    frame.render_widget(
+        Paragraph::new(format!(
+            "{} intervals of {} s, {}",
+            timeline.intervals.len(),
+            timeline.interval_secs,
+            weighted
+        ))
+        .block(Block::bordered().title(format!(" {} ", timeline.name))),
+        header,
+    );
+
+    let selection = browser.selection();
+    let marked = Style::new().bg(Color::DarkGray);
+    let rows = timeline.intervals.iter().enumerate().map(|(i, interval)| {
+        let groups = group_totals(
+            bands,
+            timeline.config.group_edges(),
+            &timeline.shares(&interval.powers),
+        );
+        let row = Row::new(
+            std::iter::once(time(interval.start_secs as f64))
+                .chain(groups.iter().map(|pct| format!("{:5.1}", pct))),
+        );
+        if browser.mark.is_some() && selection.contains(&i) {
+            row.style(marked)
+        } else {
+            row
+        }
+    });
+    let widths = [
+        Constraint::Length(12),
+        Constraint::Length(6),
+        Constraint::Length(6),
+        Constraint::Length(6),
+    ];
+    frame.render_stateful_widget(
+        Table::new(rows, widths)
+            .header(
+                Row::new(std::iter::once("TIME").chain(GROUP_LABELS))
+                    .style(Style::new().add_modifier(Modifier::BOLD)),
+            )
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .block(Block::bordered().title(format!(" Intervals (%, {}) ", weighted))),
+        list,
+        &mut browser.table,
+    );
+
+    let intervals = &timeline.intervals[selection.clone()];
+    let (start, end) = span_secs(timeline, &selection);
+    let title = if intervals.len() == 1 {
+        format!(" {} to {} ", time(start), time(end))
+    } else {
+        format!(
+            " {} to {} ({} intervals) ",
+            time(start),
+            time(end),
+            intervals.len()
+        )
+    };
+    let shares = timeline.shares(&sum_powers(
+        intervals.iter().map(|i| &i.powers),
+        bands.len(),
+    ));
+    let frames = || intervals.iter().flat_map(|i| i.frames.iter());
+    let raw_shares = timeline.shares(&sum_powers(frames(), bands.len()));
+    let dynamics = frame_dynamics(frames().map(Vec::as_slice), bands.len());
+    let band_rows = bands
+        .iter()
+        .zip(&shares)
+        .zip(dynamics.iter().zip(&raw_shares))
+        .map(|((band, &pct), (&db, &raw_pct))| {
+            let dynamics = if db.is_finite() && raw_pct >= DYNAMICS_DISPLAY_THRESHOLD_PCT {
+                format!("{:5.1}", db)
+            } else {
+                "    -".to_string()
+            };
+            let bar = "█".repeat(((pct / 50.0 * BAR_WIDTH as f64).round() as usize).min(BAR_WIDTH));
+            Row::new([
+                band.label.to_string(),
+                band_range(band),
+                format!("{:6.2}", pct),
+                dynamics,
+                bar,
+            ])
+        });
+    let group_rows = GROUP_LABELS
+        .iter()
+        .zip(group_totals(bands, timeline.config.group_edges(), &shares))
+        .map(|(label, pct)| {
+            Row::new([
+                label.to_string(),
+                String::new(),
+                format!("{:6.2}", pct),
+                String::new(),
+                String::new(),
+            ])
+            .style(Style::new().add_modifier(Modifier::BOLD))
+        });
+    let widths = [
+        Constraint::Length(12),
+        Constraint::Length(12),
+        Constraint::Length(7),
+        Constraint::Length(7),
+        Constraint::Min(0),
+    ];
+    frame.render_widget(
+        Table::new(
+            band_rows
+                .chain(std::iter::once(Row::new([""; 5])))
+                .chain(group_rows),
+            widths,
+        )
+        .header(
+            Row::new(["BAND", "HZ", "%", "DYN dB", ""])
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered().title(title)),
+        detail,
+    );
+
+    frame.render_widget(Paragraph::new(format!(" {}", browser.message)), status);
+}
+
+/// Frequency range of a band, e.g. "60-120" or "16000+"
+fn band_range(band: &Band) -> String {
+    if band.high_hz >= f32::MAX / 2.0 {
+        format!("{:.0}+", band.low_hz)
+    } else {
+        format!("{:.0}-{:.0}", band.low_hz, band.high_hz)
+    }
+}
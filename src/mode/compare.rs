@@ -1,119 +1,848 @@
 //! Multiple file comparison mode
 
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
 use colored::*;
 
-use crate::analysis::{DYNAMICS_DISPLAY_THRESHOLD_PCT, get_bands};
-use crate::chart;
-use crate::output::{
-    print_bands, print_diff_row_masked_styled, print_diff_row_styled, print_error, print_header,
-    print_legend, print_row_masked_styled, print_row_styled, print_separator,
+use super::load_audio;
+use crate::analysis::{
+    Alignment, AnalysisConfig, AnalysisContext, Band, DYNAMICS_DISPLAY_THRESHOLD_PCT,
+    MIN_CONFIDENCE, TargetCurve, Weighting, common_spans, covered_shares, difference_score,
+    find_alignment, group_totals, mean_abs_deviation, share_distance, target_deviation_db,
+    typical_share,
 };
+use crate::audio::{AudioData, ExportFormat, LoadOptions, TARGET_SAMPLE_RATE};
+use crate::chart::{self, ImageSpec};
+use crate::output::{Output, format_active, metric_label, print_error, print_warning};
+
+use super::parallel::analyze_files;
+use super::report::{ReportOptions, print_json, write_report};
+use super::sonify::write_diff_preview;
+use super::{FileStats, analyze_audio};
+
+/// Most files the A-Z labels can name
+pub const MAX_COMPARE_FILES: usize = 26;
+
+/// From this many files on, tables switch to a band x file matrix layout
+const MATRIX_LAYOUT_MIN_FILES: usize = 5;
+
+/// Parse `--label` values such as `A=My Mix v3` into (file index, name)
+pub fn parse_file_label(s: &str) -> Result<(usize, String), String> {
+    let (letter, name) = s
+        .split_once('=')
+        .ok_or_else(|| format!("invalid label '{}' (expected e.g. A=\"My Mix\")", s))?;
+    let index = match letter.trim().as_bytes() {
+        [c] if c.is_ascii_alphabetic() => (c.to_ascii_uppercase() - b'A') as usize,
+        _ => return Err(format!("invalid file letter '{}' (expected A-Z)", letter)),
+    };
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(format!("missing name for [{}]", letter.trim()));
+    }
+    Ok((index, name.to_string()))
+}
+
+/// File differences within this many combined standard errors are shown dimmed
+const NOISE_STANDARD_ERRORS: f64 = 2.0;
+
+/// Per-band difference that measurement variation alone could explain
+fn diff_noise(base_se: &[f64], other_se: &[f64]) -> Vec<f64> {
+    base_se
+        .iter()
+        .zip(other_se)
+        .map(|(a, b)| NOISE_STANDARD_ERRORS * a.hypot(*b))
+        .collect()
+}
 
-use super::analyze_file;
+/// Comparison output settings
+pub struct CompareOptions<'a> {
+    /// Names replacing the basenames of the files, where set
+    pub names: &'a [Option<String>],
+    /// Charts rendered after the analysis
+    pub images: &'a [ImageSpec],
+    /// Write a noise preview of the [B]-[A] difference here
+    pub sonify_path: Option<&'a str>,
+    /// Sample format of the noise preview (`--export-format`)
+    pub export_format: ExportFormat,
+    /// Template or HTML report of the result
+    pub report: Option<&'a ReportOptions<'a>>,
+    /// Files analyzed at once (without `--align`)
+    pub jobs: usize,
+    /// Trim the files to their common span, aligned to [A] (`--align`)
+    pub align: bool,
+}
 
 /// Run comparison analysis for multiple files
-pub fn run_compare(filenames: &[String], quiet: bool, image_path: Option<&str>) {
-    let bands = get_bands();
+pub fn run_compare(
+    filenames: &[String],
+    load_options: &LoadOptions,
+    compare: &CompareOptions,
+    config: &AnalysisConfig,
+    out: &Output,
+) {
+    let CompareOptions {
+        names,
+        images,
+        sonify_path,
+        export_format,
+        report,
+        jobs,
+        align,
+    } = *compare;
+    let quiet = out.quiet;
+    let bands = config.bands();
     let labels: Vec<char> = ('A'..='Z').collect();
 
-    let stats: Vec<_> = filenames
-        .iter()
-        .map(|f| {
-            analyze_file(f, &bands, !quiet).unwrap_or_else(|e| {
+    for dup in find_duplicates(filenames) {
+        let reason = match dup.kind {
+            DuplicateKind::SamePath => "is the same file as",
+            DuplicateKind::SameContent => "has identical content to",
+        };
+        print_warning(&format!(
+            "[{}] {} [{}] (its diff rows will be all zero)",
+            labels[dup.index], reason, labels[dup.original]
+        ));
+    }
+
+    let analyzed = if align {
+        let mut ctx = AnalysisContext::new(TARGET_SAMPLE_RATE, config);
+        analyze_aligned(filenames, config, &mut ctx, quiet, load_options)
+    } else {
+        let mut results = Vec::with_capacity(filenames.len());
+        analyze_files(
+            filenames,
+            config,
+            jobs,
+            !quiet,
+            load_options,
+            |_, result| results.push(result),
+        );
+        results.into_iter().collect()
+    };
+    let mut stats: Vec<FileStats> = analyzed.unwrap_or_else(|e| {
+        print_error(&e);
+        std::process::exit(1);
+    });
+    for (stats, name) in stats.iter_mut().zip(names) {
+        if let Some(name) = name {
+            stats.name = name.clone();
+        }
+        out.record_verify(stats.verify.as_ref());
+    }
+
+    if let Some(report) = report
+        && let Err(e) = write_report(report, config, &stats, false, load_options.highpass_hz, out)
+    {
+        print_error(&e);
+        std::process::exit(1);
+    }
+
+    if out.display.json_output() {
+        print_json(config, &stats, false, load_options.highpass_hz, out);
+    } else if !report.is_some_and(ReportOptions::to_stdout) {
+        print_tables(&stats, &labels, config, out);
+    }
+
+    // Output chart images if requested
+    if !images.is_empty() {
+        let file_data = chart_files(&stats);
+        for image in images {
+            let path = &image.path;
+            if let Err(e) = chart::render_comparison_chart(
+                &file_data,
+                bands,
+                &config.weighting,
+                path,
+                &out.charts,
+            ) {
                 print_error(&e);
-                std::process::exit(1);
-            })
+            } else {
+                eprintln!("Chart saved to: {}", path);
+            }
+        }
+    }
+
+    if let Some(path) = sonify_path {
+        if let Err(e) = write_diff_preview(
+            &stats[0].raw_pct,
+            &stats[1].raw_pct,
+            bands,
+            path,
+            export_format,
+        ) {
+            print_error(&e);
+        } else {
+            eprintln!("Difference preview ([B]-[A]) saved to: {}", path);
+        }
+    }
+}
+
+/// Decode all files, align them to [A], and analyze only the span of the program they
+/// all cover. The offsets are reported on stderr so they can be checked.
+fn analyze_aligned(
+    filenames: &[String],
+    config: &AnalysisConfig,
+    ctx: &mut AnalysisContext,
+    quiet: bool,
+    load_options: &LoadOptions,
+) -> Result<Vec<FileStats>, String> {
+    let mut audio = filenames
+        .iter()
+        .map(|f| load_audio(f, load_options))
+        .collect::<Result<Vec<AudioData>, String>>()?;
+
+    let reference = Alignment {
+        offset: 0,
+        confidence: 1.0,
+    };
+    let alignments: Vec<Alignment> = std::iter::once(reference)
+        .chain(audio[1..].iter().map(|a| {
+            find_alignment(
+                &audio[0].samples,
+                &a.samples,
+                TARGET_SAMPLE_RATE,
+                config.deterministic,
+            )
+        }))
+        .collect();
+    print_alignments(&alignments);
+
+    let lengths: Vec<usize> = audio.iter().map(|a| a.samples.len()).collect();
+    let offsets: Vec<isize> = alignments.iter().map(|a| a.offset).collect();
+    let spans = common_spans(&lengths, &offsets);
+    if spans[0].is_empty() {
+        return Err("the files share no audio once aligned".to_string());
+    }
+    eprintln!(
+        "Aligned span: {:.1} s of each file",
+        spans[0].len() as f64 / TARGET_SAMPLE_RATE as f64
+    );
+
+    for (a, span) in audio.iter_mut().zip(spans) {
+        a.samples.truncate(span.end);
+        a.samples.drain(..span.start);
+    }
+    Ok(audio
+        .iter()
+        .zip(filenames)
+        .map(|(a, f)| analyze_audio(a, f, config.bands(), ctx, !quiet))
+        .collect())
+}
+
+/// Report each file's offset from [A], warning where the match looks unreliable
+fn print_alignments(alignments: &[Alignment]) {
+    eprintln!("Alignment to [A] ({} Hz samples):", TARGET_SAMPLE_RATE);
+    for (alignment, label) in alignments.iter().zip('A'..='Z').skip(1) {
+        eprintln!(
+            "  [{}] offset {:+} samples ({:+.2} ms), confidence {:.1}%",
+            label,
+            alignment.offset,
+            alignment.offset as f64 * 1000.0 / TARGET_SAMPLE_RATE as f64,
+            alignment.confidence * 100.0
+        );
+    }
+    for (alignment, label) in alignments.iter().zip('A'..='Z').skip(1) {
+        if alignment.confidence < MIN_CONFIDENCE {
+            print_warning(&format!(
+                "[{}] does not line up well with [A]; its offset may be wrong",
+                label
+            ));
+        }
+    }
+}
+
+/// Comparison chart data of each file, labelled [A], [B], ...
+pub(super) fn chart_files(stats: &[FileStats]) -> Vec<chart::FileChartData> {
+    stats
+        .iter()
+        .zip('A'..='Z')
+        .map(|(s, label)| chart::FileChartData {
+            label,
+            name: s.name.clone(),
+            raw_pct: s.raw_pct.clone(),
+            k_pct: s.k_pct.clone(),
+        })
+        .collect()
+}
+
+/// Print the LOW/MID/HIGH totals of each file and their difference from [A]
+fn print_group_summary(
+    stats: &[FileStats],
+    labels: &[char],
+    config: &AnalysisConfig,
+    out: &Output,
+) {
+    let (bands, group_edges) = (config.bands(), config.group_edges());
+    let groups: Vec<(Vec<f64>, Vec<f64>)> = stats
+        .iter()
+        .map(|s| {
+            (
+                group_totals(bands, group_edges, &s.raw_pct),
+                group_totals(bands, group_edges, &s.k_pct),
+            )
         })
         .collect();
 
-    println!("Comparison (base: [A]):");
+    let weighted = config.weighting.label();
+    out.print_group_header(bands);
+    for (i, (raw, k)) in groups.iter().enumerate() {
+        out.print_group_row(&format!("[{}] Raw", labels[i]), raw);
+        out.print_group_row(&format!("[{}] {}", labels[i], weighted), k);
+    }
+    // "[B]-[A]" rather than the band table's "B-A", so row lookups still find the band rows
+    for (i, (_, k)) in groups.iter().enumerate().skip(1) {
+        out.print_group_diff_row(
+            &format!("[{}]-[A] {}", labels[i], weighted),
+            &groups[0].1,
+            k,
+        );
+    }
+}
+
+/// Print each file's `--metric` energy shares and their difference from [A]
+fn print_energy_split(stats: &[FileStats], labels: &[char], config: &AnalysisConfig, out: &Output) {
+    let names: Vec<String> = config.metrics.iter().map(metric_label).collect();
+    writeln!(out.sink);
+    writeln!(out.sink, "[Energy Split] % of Raw energy");
+    for (i, s) in stats.iter().enumerate() {
+        out.print_named_row(&format!("[{}] Raw", labels[i]), &names, &s.metric_pct);
+    }
+    for (i, s) in stats.iter().enumerate().skip(1) {
+        out.print_named_diff_row(
+            &format!("[{}]-[A] Raw", labels[i]),
+            &names,
+            &stats[0].metric_pct,
+            &s.metric_pct,
+        );
+    }
+}
+
+/// Print the target curve's band shares and each file's deviation from it
+fn print_target_curve(
+    curve: &TargetCurve,
+    stats: &[FileStats],
+    labels: &[char],
+    config: &AnalysisConfig,
+    out: &Output,
+) {
+    let bands = config.bands();
+    let target = curve.band_shares(config);
+    writeln!(out.sink);
+    writeln!(
+        out.sink,
+        "[Target Curve] {}",
+        out.display.display_text(&curve.name)
+    );
+    out.print_header(bands, "        ");
+    out.print_separator(bands, 8);
+    out.print_row("Tgt(%)   ", &target);
+    let mut means = Vec::new();
     for (i, s) in stats.iter().enumerate() {
         let label = format!("[{}]", labels[i]);
-        println!("  {} {}", label.bold(), s.name);
+        let deviation = target_deviation_db(&s.raw_pct, &target);
+        out.print_separator(bands, 8);
+        out.print_row_styled(&label, " Raw  ", &covered_shares(&s.raw_pct, &target));
+        out.print_signed_row_styled(&label, " Dev  ", &deviation);
+        means.push((label, mean_abs_deviation(&deviation)));
+    }
+    for (label, mean) in means.iter().filter(|(_, m)| m.is_finite()) {
+        writeln!(out.sink, "{} Mean deviation: {:.1} dB", label, mean);
     }
-    println!();
+}
 
-    if !quiet {
-        print_bands(&bands);
+/// Print the distribution and dynamics tables for all files
+pub(super) fn print_tables(
+    stats: &[FileStats],
+    labels: &[char],
+    config: &AnalysisConfig,
+    out: &Output,
+) {
+    let bands = config.bands();
+    writeln!(out.sink, "Comparison (base: [A]):");
+    for (i, s) in stats.iter().enumerate() {
+        let label = format!("[{}]", labels[i]);
+        match s.active_secs {
+            Some(active_secs) => writeln!(
+                out.sink,
+                "  {} {} (active {})",
+                label.bold(),
+                out.display.display_text(&s.name),
+                format_active(active_secs, s.duration_secs)
+            ),
+            None => writeln!(
+                out.sink,
+                "  {} {}",
+                label.bold(),
+                out.display.display_text(&s.name)
+            ),
+        }
     }
+    writeln!(out.sink);
 
-    println!("[Band Power Distribution]");
-    print_header(&bands, "        ");
-    print_separator(&bands, 8);
+    if !out.quiet {
+        out.print_bands(bands);
+    }
 
-    let ref_label = format!("[{}]", labels[0]);
-    print_row_styled(&ref_label, " Raw  ", &stats[0].raw_pct);
-    print_row_styled(&ref_label, " K-wt ", &stats[0].k_pct);
-    print_diff_row_styled(&ref_label, " Diff ", &stats[0].raw_pct, &stats[0].k_pct);
+    print_group_summary(stats, labels, config, out);
+    if !config.metrics.is_empty() {
+        print_energy_split(stats, labels, config, out);
+    }
+    print_difference_scores(stats, bands, labels, out);
 
-    for (i, s) in stats.iter().enumerate().skip(1) {
-        print_separator(&bands, 8);
-        let label = format!("[{}]", labels[i]);
-        print_row_styled(&label, " Raw  ", &s.raw_pct);
-        print_row_styled(&label, " K-wt ", &s.k_pct);
-        print_diff_row_styled(&label, " Diff ", &s.raw_pct, &s.k_pct);
-        print_separator(&bands, 8);
-        let diff_label = format!("{}-A", labels[i]);
-        print_diff_row_styled(&diff_label, " Raw  ", &stats[0].raw_pct, &s.raw_pct);
-        print_diff_row_styled(&diff_label, " K-wt ", &stats[0].k_pct, &s.k_pct);
+    if stats.len() >= MATRIX_LAYOUT_MIN_FILES {
+        print_matrix_tables(stats, labels, config, out);
+    } else {
+        print_row_tables(stats, labels, config, out);
+    }
+    if let Some(curve) = &config.target_curve {
+        print_target_curve(curve, stats, labels, config, out);
     }
 
-    println!();
-    println!("[Dynamics]");
-    print_header(&bands, "        ");
-    print_separator(&bands, 8);
+    if !out.quiet {
+        writeln!(out.sink);
+        out.print_legend();
+        writeln!(
+            out.sink,
+            "B-A: Difference from [A]; dimmed values are within measurement variation ({}{} standard errors)",
+            out.display.symbol("±", "+/-"),
+            NOISE_STANDARD_ERRORS
+        );
+        if out.display.weighted_dynamics {
+            writeln!(
+                out.sink,
+                "{0} (Dynamics): Same as dB, on {1} power over the frames {0} counts",
+                config.weighting.label(),
+                config.weighting.name()
+            );
+        }
+        if out.display.match_distributions {
+            writeln!(
+                out.sink,
+                "Typ: Typical Raw share of a single frame, from the sorted frames (insensitive to edits and tempo changes)"
+            );
+            writeln!(
+                out.sink,
+                "EMD: Mean gap between the sorted per-frame Raw shares of the file and [A]"
+            );
+        }
+        out.print_metric_legend();
+        out.print_target_legend();
+    }
+}
 
-    print_row_masked_styled(
-        &format!("[{}]", labels[0]),
-        " dB   ",
-        &stats[0].dynamics,
-        &stats[0].raw_pct,
-        DYNAMICS_DISPLAY_THRESHOLD_PCT,
-    );
+/// Typical per-frame share of every band in each file, and each file's distance from [A]
+fn distribution_match(stats: &[FileStats]) -> (Vec<Vec<f64>>, Vec<Vec<f64>>) {
+    let typical = stats
+        .iter()
+        .map(|s| s.share_quantiles.iter().map(|q| typical_share(q)).collect())
+        .collect();
+    let distances = stats
+        .iter()
+        .map(|s| {
+            stats[0]
+                .share_quantiles
+                .iter()
+                .zip(&s.share_quantiles)
+                .map(|(base, other)| share_distance(base, other))
+                .collect()
+        })
+        .collect();
+    (typical, distances)
+}
 
+/// Print one number per file summarizing how far its Raw balance is from [A]:
+/// the plain mean of the band differences, and the mean weighted by hearing sensitivity
+fn print_difference_scores(stats: &[FileStats], bands: &[Band], labels: &[char], out: &Output) {
+    writeln!(out.sink);
+    writeln!(
+        out.sink,
+        "[Difference Score] mean |diff| from [A] in % (Raw)"
+    );
     for (i, s) in stats.iter().enumerate().skip(1) {
-        print_separator(&bands, 8);
-        print_row_masked_styled(
-            &format!("[{}]", labels[i]),
-            " dB   ",
-            &s.dynamics,
-            &s.raw_pct,
-            DYNAMICS_DISPLAY_THRESHOLD_PCT,
+        let score = difference_score(bands, &stats[0].raw_pct, &s.raw_pct);
+        writeln!(
+            out.sink,
+            "  {:<12}  flat {:>5.2}  weighted {:>5.2}",
+            format!("[{}]-[A]", labels[i]),
+            score.flat,
+            score.weighted
         );
-        print_separator(&bands, 8);
-        print_diff_row_masked_styled(
-            &format!("{}-A", labels[i]),
-            "      ",
-            &stats[0].dynamics,
-            &s.dynamics,
+    }
+}
+
+/// Per-file row blocks, the layout for a handful of files
+fn print_row_tables(stats: &[FileStats], labels: &[char], config: &AnalysisConfig, out: &Output) {
+    let bands = config.bands();
+    writeln!(out.sink);
+    writeln!(out.sink, "[Band Power Distribution]");
+    out.print_header(bands, "        ");
+    out.print_separator(bands, 8);
+
+    let weighted = format!(" {} ", config.weighting.label());
+    let ref_label = format!("[{}]", labels[0]);
+    out.print_row_styled(&ref_label, " Raw  ", &stats[0].raw_pct);
+    out.print_row_styled(&ref_label, &weighted, &stats[0].k_pct);
+    out.print_diff_row_styled(&ref_label, " Diff ", &stats[0].raw_pct, &stats[0].k_pct);
+
+    for (i, s) in stats.iter().enumerate().skip(1) {
+        out.print_separator(bands, 8);
+        let label = format!("[{}]", labels[i]);
+        out.print_row_styled(&label, " Raw  ", &s.raw_pct);
+        out.print_row_styled(&label, &weighted, &s.k_pct);
+        out.print_diff_row_styled(&label, " Diff ", &s.raw_pct, &s.k_pct);
+        out.print_separator(bands, 8);
+        let diff_label = format!("{}-A", labels[i]);
+        out.print_diff_row_noise_styled(
+            &diff_label,
+            " Raw  ",
             &stats[0].raw_pct,
             &s.raw_pct,
+            &diff_noise(&stats[0].raw_pct_se, &s.raw_pct_se),
+        );
+        out.print_diff_row_noise_styled(
+            &diff_label,
+            &weighted,
+            &stats[0].k_pct,
+            &s.k_pct,
+            &diff_noise(&stats[0].k_pct_se, &s.k_pct_se),
+        );
+    }
+
+    writeln!(out.sink);
+    writeln!(out.sink, "[Dynamics]");
+    out.print_header(bands, "        ");
+    out.print_separator(bands, 8);
+
+    let rows = if out.display.weighted_dynamics { 2 } else { 1 };
+
+    for (suffix, values, mask) in dynamics_rows(&stats[0], &config.weighting)
+        .into_iter()
+        .take(rows)
+    {
+        out.print_row_masked_styled(
+            &format!("[{}]", labels[0]),
+            &suffix,
+            values,
+            mask,
             DYNAMICS_DISPLAY_THRESHOLD_PCT,
         );
     }
 
-    if !quiet {
-        println!();
-        print_legend();
+    for (i, s) in stats.iter().enumerate().skip(1) {
+        out.print_separator(bands, 8);
+        for (suffix, values, mask) in dynamics_rows(s, &config.weighting).into_iter().take(rows) {
+            out.print_row_masked_styled(
+                &format!("[{}]", labels[i]),
+                &suffix,
+                values,
+                mask,
+                DYNAMICS_DISPLAY_THRESHOLD_PCT,
+            );
+        }
+        out.print_separator(bands, 8);
+        for ((suffix, base, base_mask), (_, values, mask)) in
+            dynamics_rows(&stats[0], &config.weighting)
+                .into_iter()
+                .zip(dynamics_rows(s, &config.weighting))
+                .take(rows)
+        {
+            // The raw row keeps its blank suffix when it is the only one
+            let suffix = if rows == 1 { "      " } else { &suffix };
+            out.print_diff_row_masked_styled(
+                &format!("{}-A", labels[i]),
+                suffix,
+                base,
+                values,
+                [base_mask, mask],
+                DYNAMICS_DISPLAY_THRESHOLD_PCT,
+            );
+        }
+    }
+
+    if out.display.match_distributions {
+        let (typical, distances) = distribution_match(stats);
+        writeln!(out.sink);
+        writeln!(out.sink, "[Distribution Match] per-frame Raw(%), sorted");
+        out.print_header(bands, "        ");
+        out.print_separator(bands, 8);
+        out.print_row_styled(&format!("[{}]", labels[0]), " Typ  ", &typical[0]);
+        for i in 1..stats.len() {
+            out.print_separator(bands, 8);
+            out.print_row_styled(&format!("[{}]", labels[i]), " Typ  ", &typical[i]);
+            out.print_separator(bands, 8);
+            let diff_label = format!("{}-A", labels[i]);
+            out.print_diff_row_styled(&diff_label, " Typ  ", &typical[0], &typical[i]);
+            out.print_row_styled(&diff_label, " EMD  ", &distances[i]);
+        }
+    }
+}
+
+/// Row suffix, dynamics, and the shares hiding them of the raw and K-weighted dynamics rows
+fn dynamics_rows<'a>(
+    s: &'a FileStats,
+    weighting: &Weighting,
+) -> [(String, &'a [f64], &'a [f64]); 2] {
+    [
+        (" dB   ".to_string(), &s.dynamics, &s.raw_pct),
+        (format!(" {} ", weighting.label()), &s.k_dynamics, &s.k_pct),
+    ]
+}
+
+/// One column per file, so many files stay readable side by side
+fn print_matrix_tables(
+    stats: &[FileStats],
+    labels: &[char],
+    config: &AnalysisConfig,
+    out: &Output,
+) {
+    let bands = config.bands();
+    let all: Vec<String> = labels[..stats.len()]
+        .iter()
+        .map(|l| format!("[{}]", l))
+        .collect();
+    let others = &all[1..];
+    let column = |values: &[f64]| values.iter().map(|&v| Some(v)).collect::<Vec<_>>();
+    let base = &stats[0];
+
+    writeln!(out.sink);
+    writeln!(out.sink, "[Band Power Distribution: Raw(%)]");
+    let raw: Vec<_> = stats.iter().map(|s| column(&s.raw_pct)).collect();
+    out.print_matrix(bands, &all, &raw, false, None);
+
+    writeln!(out.sink);
+    writeln!(
+        out.sink,
+        "[Band Power Distribution: {}(%)]",
+        config.weighting.label()
+    );
+    let k: Vec<_> = stats.iter().map(|s| column(&s.k_pct)).collect();
+    out.print_matrix(bands, &all, &k, false, None);
+
+    writeln!(out.sink);
+    writeln!(
+        out.sink,
+        "[Difference from [A]: {}(%)]",
+        config.weighting.label()
+    );
+    let k_diff: Vec<_> = stats[1..]
+        .iter()
+        .map(|s| {
+            s.k_pct
+                .iter()
+                .zip(&base.k_pct)
+                .map(|(v, b)| Some(v - b))
+                .collect()
+        })
+        .collect();
+    let k_noise: Vec<_> = stats[1..]
+        .iter()
+        .map(|s| diff_noise(&base.k_pct_se, &s.k_pct_se))
+        .collect();
+    out.print_matrix(bands, others, &k_diff, true, Some(&k_noise));
+
+    print_dynamics_matrix(stats, bands, &all, "", out, |s| (&s.dynamics, &s.raw_pct));
+    if out.display.weighted_dynamics {
+        let prefix = format!("{} ", config.weighting.label());
+        print_dynamics_matrix(stats, bands, &all, &prefix, out, |s| {
+            (&s.k_dynamics, &s.k_pct)
+        });
     }
 
-    // Output chart image if requested
-    if let Some(path) = image_path {
-        let file_data: Vec<chart::FileChartData> = stats
+    if out.display.match_distributions {
+        let (typical, distances) = distribution_match(stats);
+        writeln!(out.sink);
+        writeln!(out.sink, "[Distribution Match: Typ(%)]");
+        let typical_columns: Vec<_> = typical.iter().map(|t| column(t)).collect();
+        out.print_matrix(bands, &all, &typical_columns, false, None);
+
+        writeln!(out.sink);
+        writeln!(
+            out.sink,
+            "[Distribution Match: Typ Difference from [A] (%)]"
+        );
+        let typical_diff: Vec<_> = typical[1..]
             .iter()
-            .enumerate()
-            .map(|(i, s)| chart::FileChartData {
-                label: labels[i],
-                name: s.name.clone(),
-                raw_pct: s.raw_pct.clone(),
-                k_pct: s.k_pct.clone(),
+            .map(|t| {
+                t.iter()
+                    .zip(&typical[0])
+                    .map(|(v, b)| Some(v - b))
+                    .collect()
             })
             .collect();
+        out.print_matrix(bands, others, &typical_diff, true, None);
 
-        if let Err(e) = chart::render_comparison_chart(&file_data, &bands, path) {
-            print_error(&e);
-        } else {
-            eprintln!("Chart saved to: {}", path);
+        writeln!(out.sink);
+        writeln!(out.sink, "[Distribution Match: EMD from [A] (%)]");
+        let distance_columns: Vec<_> = distances[1..].iter().map(|d| column(d)).collect();
+        out.print_matrix(bands, others, &distance_columns, false, None);
+    }
+}
+
+/// Dynamics of every file and their difference from [A], hiding bands below the display
+/// threshold of the share `values` returns alongside the dynamics
+fn print_dynamics_matrix(
+    stats: &[FileStats],
+    bands: &[Band],
+    all: &[String],
+    prefix: &str,
+    out: &Output,
+    values: impl Fn(&FileStats) -> (&[f64], &[f64]),
+) {
+    let shown = |s: &FileStats, i: usize| values(s).1[i] >= DYNAMICS_DISPLAY_THRESHOLD_PCT;
+    let base = &stats[0];
+    writeln!(out.sink);
+    writeln!(out.sink, "[Dynamics: {}dB]", prefix);
+    let dynamics: Vec<_> = stats
+        .iter()
+        .map(|s| {
+            (0..bands.len())
+                .map(|i| shown(s, i).then_some(values(s).0[i]))
+                .collect()
+        })
+        .collect();
+    out.print_matrix(bands, all, &dynamics, false, None);
+
+    writeln!(out.sink);
+    writeln!(out.sink, "[Dynamics: {}Difference from [A] (dB)]", prefix);
+    let dynamics_diff: Vec<_> = stats[1..]
+        .iter()
+        .map(|s| {
+            (0..bands.len())
+                .map(|i| {
+                    (shown(s, i) && shown(base, i)).then(|| values(s).0[i] - values(base).0[i])
+                })
+                .collect()
+        })
+        .collect();
+    out.print_matrix(bands, &all[1..], &dynamics_diff, true, None);
+}
+
+/// How a duplicated comparison input was detected
+#[derive(Debug, PartialEq)]
+enum DuplicateKind {
+    SamePath,
+    SameContent,
+}
+
+/// A comparison input that repeats an earlier one
+#[derive(Debug)]
+struct Duplicate {
+    index: usize,
+    original: usize,
+    kind: DuplicateKind,
+}
+
+/// Find inputs that refer to the same file or to files with identical content
+///
+/// Paths are compared after canonicalization; contents are only hashed for files
+/// whose sizes collide. Unreadable files are skipped (load errors are reported later).
+fn find_duplicates(filenames: &[String]) -> Vec<Duplicate> {
+    let canonical: Vec<Option<PathBuf>> = filenames
+        .iter()
+        .map(|f| std::fs::canonicalize(f).ok())
+        .collect();
+    let sizes: Vec<Option<u64>> = filenames
+        .iter()
+        .map(|f| std::fs::metadata(f).ok().map(|m| m.len()))
+        .collect();
+    let mut hashes: Vec<Option<u64>> = vec![None; filenames.len()];
+
+    let mut duplicates = Vec::new();
+    for i in 1..filenames.len() {
+        for j in 0..i {
+            if canonical[i].is_some() && canonical[i] == canonical[j] {
+                duplicates.push(Duplicate {
+                    index: i,
+                    original: j,
+                    kind: DuplicateKind::SamePath,
+                });
+                break;
+            }
+
+            if sizes[i].is_none() || sizes[i] != sizes[j] {
+                continue;
+            }
+            for k in [i, j] {
+                if hashes[k].is_none() {
+                    hashes[k] = hash_file(&filenames[k]);
+                }
+            }
+            if hashes[i].is_some() && hashes[i] == hashes[j] {
+                duplicates.push(Duplicate {
+                    index: i,
+                    original: j,
+                    kind: DuplicateKind::SameContent,
+                });
+                break;
+            }
         }
     }
+
+    duplicates
+}
+
+fn hash_file(filename: &str) -> Option<u64> {
+    let bytes = std::fs::read(filename).ok()?;
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_temp(dir: &std::path::Path, name: &str, content: &[u8]) -> String {
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_parse_file_label() {
+        assert_eq!(
+            parse_file_label("B=Ref: Artist - Song").unwrap(),
+            (1, "Ref: Artist - Song".to_string())
+        );
+        assert_eq!(parse_file_label("a= v3 ").unwrap(), (0, "v3".to_string()));
+        assert!(parse_file_label("A").is_err());
+        assert!(parse_file_label("AB=x").is_err());
+        assert!(parse_file_label("1=x").is_err());
+        assert!(parse_file_label("C=").is_err());
+    }
+
+    #[test]
+    fn test_find_duplicates_same_path() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let a = write_temp(dir.path(), "a.wav", b"aaaa");
+        let b = write_temp(dir.path(), "b.wav", b"bbbb");
+
+        let dups = find_duplicates(&[a.clone(), b, a]);
+        assert_eq!(dups.len(), 1);
+        assert_eq!((dups[0].index, dups[0].original), (2, 0));
+        assert_eq!(dups[0].kind, DuplicateKind::SamePath);
+    }
+
+    #[test]
+    fn test_find_duplicates_same_content() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let a = write_temp(dir.path(), "a.wav", b"same");
+        let b = write_temp(dir.path(), "b.wav", b"same");
+
+        let dups = find_duplicates(&[a, b]);
+        assert_eq!(dups.len(), 1);
+        assert_eq!(dups[0].kind, DuplicateKind::SameContent);
+    }
+
+    #[test]
+    fn test_find_duplicates_distinct_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let a = write_temp(dir.path(), "a.wav", b"aaaa");
+        let b = write_temp(dir.path(), "b.wav", b"bbbb");
+        let c = write_temp(dir.path(), "c.wav", b"longer");
+
+        assert!(find_duplicates(&[a, b, c]).is_empty());
+    }
 }
@@ -0,0 +1,454 @@
+//! CUE sheets: a long image file (a whole album or live set) analyzed track by track
+
+use std::path::{Path, PathBuf};
+
+use crate::analysis::{
+    AnalysisConfig, AnalysisContext, DYNAMICS_DISPLAY_THRESHOLD_PCT, StatsAccumulator, StatsResult,
+};
+use crate::audio::{AudioStream, LoadOptions, TARGET_SAMPLE_RATE, is_url};
+use crate::output::{Output, get_display_name, print_error, print_warning, print_warnings};
+
+use super::batch::{print_result, print_row_header};
+use super::{FileStats, file_stats, warn_if_unstable};
+
+/// CUE times count CD frames, 75 per second
+const CD_FRAMES_PER_SEC: u64 = 75;
+
+/// Width of the track number column of the dynamics table, lined up with the LUFS column
+const TRACK_WIDTH: usize = 7;
+
+/// One track of a CUE sheet
+#[derive(Debug, PartialEq)]
+struct CueTrack {
+    number: u32,
+    title: Option<String>,
+    /// Start (`INDEX 01`) in CD frames from the beginning of the image
+    start: u64,
+}
+
+impl CueTrack {
+    /// Start in samples at the analysis sample rate
+    fn start_sample(&self) -> usize {
+        (self.start * TARGET_SAMPLE_RATE as u64 / CD_FRAMES_PER_SEC) as usize
+    }
+
+    /// Name of the track's row: its number, then its title if the sheet has one
+    fn label(&self) -> String {
+        match &self.title {
+            Some(title) => format!("{:02} {}", self.number, title),
+            None => format!("{:02}", self.number),
+        }
+    }
+}
+
+/// Image file and tracks of a CUE sheet
+#[derive(Debug, PartialEq)]
+struct CueSheet {
+    /// Image file as written in the sheet
+    file: String,
+    tracks: Vec<CueTrack>,
+}
+
+/// Whether a file argument is a CUE sheet rather than audio
+pub fn is_cue_sheet(path: &str) -> bool {
+    !is_url(path)
+        && Path::new(path)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("cue"))
+}
+
+/// Value of a CUE command: the quoted text, or the first word when it isn't quoted
+fn cue_value(rest: &str) -> &str {
+    match rest.strip_prefix('"') {
+        Some(quoted) => quoted.split_once('"').map_or(quoted, |(value, _)| value),
+        None => rest.split_whitespace().next().unwrap_or(""),
+    }
+}
+
+/// Parse an `mm:ss:ff` CUE time into CD frames
+fn parse_cue_time(text: &str) -> Option<u64> {
+    let mut parts = text.split(':').map(|part| part.parse::<u64>().ok());
+    let (Some(Some(minutes)), Some(Some(seconds)), Some(Some(frames)), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return None;
+    };
+    (seconds < 60 && frames < CD_FRAMES_PER_SEC)
+        .then_some((minutes * 60 + seconds) * CD_FRAMES_PER_SEC + frames)
+}
+
+/// Parse a CUE sheet with a single image file. Each track starts at its `INDEX 01`, so
+/// pregaps count toward the track before; commands other than FILE, TRACK, TITLE, and
+/// INDEX are ignored.
+fn parse_cue_sheet(text: &str) -> Result<CueSheet, String> {
+    let mut file: Option<String> = None;
+    let mut tracks: Vec<CueTrack> = Vec::new();
+    // INDEX 01 of each track, checked once all tracks are read
+    let mut starts: Vec<Option<u64>> = Vec::new();
+
+    for (number, line) in text.trim_start_matches('\u{feff}').lines().enumerate() {
+        let line = line.trim();
+        let (command, rest) = line
+            .split_once(char::is_whitespace)
+            .map_or((line, ""), |(command, rest)| (command, rest.trim()));
+        match command.to_ascii_uppercase().as_str() {
+            "FILE" => {
+                if file.is_some() {
+                    return Err(format!(
+                        "line {}: tracks in several files are not supported; analyze the files directly",
+                        number + 1
+                    ));
+                }
+                // Unquoted names run up to the file type at the end of the line
+                let name = if rest.starts_with('"') {
+                    cue_value(rest)
+                } else {
+                    rest.rsplit_once(char::is_whitespace)
+                        .map_or(rest, |(name, _)| name.trim_end())
+                };
+                if name.is_empty() {
+                    return Err(format!("line {}: FILE needs a file name", number + 1));
+                }
+                file = Some(name.to_string());
+            }
+            "TRACK" => {
+                if file.is_none() {
+                    return Err(format!("line {}: TRACK before FILE", number + 1));
+                }
+                let track = cue_value(rest)
+                    .parse::<u32>()
+                    .map_err(|_| format!("line {}: expected a track number", number + 1))?;
+                tracks.push(CueTrack {
+                    number: track,
+                    title: None,
+                    start: 0,
+                });
+                starts.push(None);
+            }
+            "TITLE" => {
+                // The album title comes before the first track
+                if let Some(track) = tracks.last_mut() {
+                    let title = cue_value(rest).trim();
+                    track.title = (!title.is_empty()).then(|| title.to_string());
+                }
+            }
+            "INDEX" => {
+                let mut fields = rest.split_whitespace();
+                let (Some(index), Some(time)) = (fields.next(), fields.next()) else {
+                    return Err(format!(
+                        "line {}: expected an index number and a time",
+                        number + 1
+                    ));
+                };
+                let Some(start) = starts.last_mut() else {
+                    return Err(format!("line {}: INDEX before TRACK", number + 1));
+                };
+                if index.parse::<u32>() == Ok(1) {
+                    *start = Some(parse_cue_time(time).ok_or_else(|| {
+                        format!("line {}: expected a time as mm:ss:ff", number + 1)
+                    })?);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let file = file.ok_or("no FILE line")?;
+    if tracks.is_empty() {
+        return Err("no tracks".to_string());
+    }
+    for (track, start) in tracks.iter_mut().zip(starts) {
+        track.start = start.ok_or_else(|| format!("track {} has no INDEX 01", track.number))?;
+    }
+    if let Some(pair) = tracks
+        .windows(2)
+        .find(|pair| pair[1].start <= pair[0].start)
+    {
+        return Err(format!(
+            "track {} does not start after track {}",
+            pair[1].number, pair[0].number
+        ));
+    }
+    Ok(CueSheet { file, tracks })
+}
+
+/// Read a CUE sheet and resolve its image file next to it
+fn load_cue_sheet(path: &str) -> Result<(CueSheet, PathBuf), String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+    let sheet = parse_cue_sheet(&String::from_utf8_lossy(&bytes))
+        .map_err(|e| format!("{}: {}", path, e))?;
+    let image = Path::new(path)
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(&sheet.file);
+    Ok((sheet, image))
+}
+
+/// Analyze the image file of a CUE sheet and print one row of band shares, and one of
+/// dynamics, per track (or a JSON line per track)
+pub fn run_cue(path: &str, load_options: &LoadOptions, config: &AnalysisConfig, out: &Output) {
+    let (sheet, image) = load_cue_sheet(path).unwrap_or_else(|e| {
+        print_error(&e);
+        std::process::exit(1);
+    });
+    let image = image.to_string_lossy();
+    let bands = config.bands();
+    let use_k_weighting = out.weighted;
+
+    let tracks =
+        analyze_tracks(&sheet, &image, config, !out.quiet, load_options).unwrap_or_else(|e| {
+            print_error(&e);
+            std::process::exit(1);
+        });
+    for track in &tracks {
+        out.record_verify(track.verify.as_ref());
+    }
+    if tracks.is_empty() {
+        print_error(&format!(
+            "{}: the image ends before track {}",
+            path, sheet.tracks[0].number
+        ));
+        std::process::exit(1);
+    }
+
+    if out.display.json_output() {
+        for stats in &tracks {
+            print_result(stats, config, out, load_options.highpass_hz, true);
+        }
+        return;
+    }
+
+    if !out.quiet {
+        writeln!(out.sink);
+        writeln!(out.sink, "CUE Sheet Analysis");
+        writeln!(
+            out.sink,
+            "Sheet: {}",
+            out.display.display_text(get_display_name(path))
+        );
+        out.print_file_info(
+            get_display_name(&image),
+            tracks[0].original_sample_rate,
+            tracks[0].channels,
+            use_k_weighting,
+        );
+        out.print_bands(bands);
+    }
+
+    writeln!(
+        out.sink,
+        "[Band Power Distribution] {}",
+        out.share_title(use_k_weighting)
+    );
+    print_row_header(bands, out);
+    for stats in &tracks {
+        print_result(stats, config, out, load_options.highpass_hz, false);
+    }
+
+    writeln!(out.sink);
+    writeln!(out.sink, "[Dynamics]");
+    out.print_header(bands, &format!("{:>w$}", "TRACK", w = TRACK_WIDTH));
+    out.print_separator(bands, TRACK_WIDTH);
+    for (track, stats) in sheet.tracks.iter().zip(&tracks) {
+        out.print_row_masked(
+            &format!("{:>w$}", format!("{:02}", track.number), w = TRACK_WIDTH),
+            &stats.dynamics,
+            &stats.raw_pct,
+            DYNAMICS_DISPLAY_THRESHOLD_PCT,
+        );
+    }
+
+    if !out.quiet {
+        writeln!(out.sink);
+        out.print_legend();
+    }
+}
+
+/// Decode the image once, feeding each track's samples to its own accumulator. Audio
+/// before the first track is skipped; tracks starting after the end of the image are
+/// left out with a warning.
+fn analyze_tracks(
+    sheet: &CueSheet,
+    image: &str,
+    config: &AnalysisConfig,
+    show_progress: bool,
+    load_options: &LoadOptions,
+) -> Result<Vec<FileStats>, String> {
+    let display_name = get_display_name(image).to_string();
+    let mut stream = AudioStream::open(image, load_options)?;
+    let expected = stream.expected_samples();
+    let starts: Vec<usize> = sheet.tracks.iter().map(CueTrack::start_sample).collect();
+    let track_len = |index: usize| match starts.get(index + 1) {
+        Some(end) => Some(end - starts[index]),
+        None => expected.map(|n| n.saturating_sub(starts[index])),
+    };
+
+    // An accumulator holds its context until it finishes, so each track gets its own
+    let mut contexts: Vec<AnalysisContext> = starts
+        .iter()
+        .map(|_| AnalysisContext::new(TARGET_SAMPLE_RATE, config))
+        .collect();
+    let mut free_contexts = contexts.iter_mut();
+    // Finished tracks and the samples of each
+    let mut finished: Vec<(StatsResult, usize)> = Vec::new();
+    let mut current: Option<(StatsAccumulator, usize)> = None;
+    let mut position = 0usize;
+    let mut last_progress: Option<usize> = None;
+
+    if show_progress {
+        eprint!("Analyzing {}... 0%", display_name);
+    }
+    let decoded = stream
+        .decode_resampled(|mut samples| {
+            while !samples.is_empty() {
+                let next = finished.len() + usize::from(current.is_some());
+                let boundary = starts.get(next).copied().unwrap_or(usize::MAX);
+                if position == boundary {
+                    if let Some((accumulator, len)) = current.take() {
+                        finished.push((accumulator.finish(), len));
+                    }
+                    let ctx = free_contexts.next().expect("one context per track");
+                    current = Some((
+                        StatsAccumulator::new(config.bands(), ctx, track_len(next)),
+                        0,
+                    ));
+                    continue;
+                }
+                let take = samples.len().min(boundary - position);
+                if let Some((accumulator, len)) = &mut current {
+                    accumulator.push(&samples[..take]);
+                    *len += take;
+                }
+                position += take;
+                samples = &samples[take..];
+            }
+            if show_progress
+                && let Some(progress) = expected.and_then(|n| (position * 100).checked_div(n))
+                && last_progress != Some(progress.min(100))
+            {
+                let progress = progress.min(100);
+                eprint!("\rAnalyzing {}... {}%", display_name, progress);
+                last_progress = Some(progress);
+            }
+        })
+        .inspect_err(|_| {
+            if show_progress {
+                eprintln!();
+            }
+        })?;
+    if let Some((accumulator, len)) = current.take() {
+        finished.push((accumulator.finish(), len));
+    }
+    if show_progress {
+        eprintln!("\rAnalyzing {}... done", display_name);
+    }
+    print_warnings(&stream.warnings());
+    debug_assert_eq!(decoded, position);
+
+    match &sheet.tracks[finished.len()..] {
+        [] => {}
+        [track] => print_warning(&format!(
+            "{}: track {} starts after the end of the image and is left out",
+            display_name, track.number
+        )),
+        [first, .., last] => print_warning(&format!(
+            "{}: tracks {} to {} start after the end of the image and are left out",
+            display_name, first.number, last.number
+        )),
+    }
+
+    let tracks = sheet
+        .tracks
+        .iter()
+        .zip(finished)
+        .map(|(track, (result, len))| {
+            let mut stats = file_stats(
+                result,
+                config.excluded_bands(),
+                image,
+                stream.sample_rate,
+                stream.channels,
+                len as f64 / TARGET_SAMPLE_RATE as f64,
+                None,
+            );
+            stats.name = track.label();
+            warn_if_unstable(&stats, &stats.name);
+            stats
+        })
+        .collect();
+    Ok(tracks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cue_sheet() {
+        let text = "\u{feff}REM GENRE Rock\r\n\
+            PERFORMER \"Band\"\r\n\
+            TITLE \"Album\"\r\n\
+            FILE \"Album Image.flac\" WAVE\r\n\
+            \x20 TRACK 01 AUDIO\r\n\
+            \x20   TITLE \"Intro\"\r\n\
+            \x20   INDEX 01 00:00:00\r\n\
+            \x20 TRACK 02 AUDIO\r\n\
+            \x20   INDEX 00 03:20:00\r\n\
+            \x20   INDEX 01 03:22:37\r\n";
+        let sheet = parse_cue_sheet(text).unwrap();
+        assert_eq!(sheet.file, "Album Image.flac");
+        assert_eq!(
+            sheet.tracks,
+            vec![
+                CueTrack {
+                    number: 1,
+                    title: Some("Intro".to_string()),
+                    start: 0,
+                },
+                CueTrack {
+                    number: 2,
+                    title: None,
+                    start: (3 * 60 + 22) * 75 + 37,
+                },
+            ]
+        );
+        assert_eq!(sheet.tracks[0].label(), "01 Intro");
+        assert_eq!(sheet.tracks[1].label(), "02");
+        assert_eq!(sheet.tracks[1].start_sample(), 202 * 48_000 + 37 * 640);
+
+        let unquoted = parse_cue_sheet("FILE live set.wav WAVE\nTRACK 1 AUDIO\nINDEX 01 0:0:0");
+        assert_eq!(unquoted.unwrap().file, "live set.wav");
+    }
+
+    #[test]
+    fn test_parse_cue_sheet_errors() {
+        let error = |text: &str| parse_cue_sheet(text).unwrap_err();
+        assert_eq!(error("TRACK 01 AUDIO"), "line 1: TRACK before FILE");
+        assert_eq!(error("FILE \"a.wav\" WAVE"), "no tracks");
+        assert_eq!(
+            error("FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 00:60:00"),
+            "line 3: expected a time as mm:ss:ff"
+        );
+        assert_eq!(
+            error("FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 00 00:00:00"),
+            "track 1 has no INDEX 01"
+        );
+        assert_eq!(
+            error(
+                "FILE a.wav WAVE\nTRACK 01 AUDIO\nINDEX 01 01:00:00\nTRACK 02 AUDIO\nINDEX 01 00:30:00"
+            ),
+            "track 2 does not start after track 1"
+        );
+        assert!(
+            error("FILE a.wav WAVE\nTRACK 01 AUDIO\nFILE b.wav WAVE").contains("several files")
+        );
+    }
+
+    #[test]
+    fn test_is_cue_sheet() {
+        assert!(is_cue_sheet("album.cue"));
+        assert!(is_cue_sheet("dir/Album.CUE"));
+        assert!(!is_cue_sheet("album.flac"));
+        assert!(!is_cue_sheet("https://example.com/album.cue"));
+    }
+}
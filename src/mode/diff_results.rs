@@ -0,0 +1,258 @@
+//! Comparison of results saved with `--format json`, without the audio
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::FileStats;
+use super::compare::{MAX_COMPARE_FILES, print_tables};
+use crate::analysis::{AnalysisConfig, Band};
+use crate::chart::ChartOptions;
+use crate::output::{DisplayOptions, Output, Sink, print_error};
+use bandstat::check_format;
+
+/// Compare the files of two saved stats or compare results, as if analyzed together
+pub fn run_diff_results(paths: &[String], display: DisplayOptions, quiet: bool) {
+    let config = load_results(paths)
+        .and_then(|(bands, stats)| Ok((AnalysisConfig::default().with_bands(bands)?, stats)));
+    let (config, stats) = config.unwrap_or_else(|e| {
+        print_error(&e);
+        std::process::exit(1);
+    });
+    let labels: Vec<char> = ('A'..='Z').collect();
+    print_tables(
+        &stats,
+        &labels,
+        &config,
+        &Output::new(
+            &config,
+            display,
+            ChartOptions::default(),
+            Sink::stdout(),
+            false,
+            quiet,
+        ),
+    );
+}
+
+/// Bands and file stats of the saved results, checked to share the same bands
+fn load_results(paths: &[String]) -> Result<(Vec<Band>, Vec<FileStats>), String> {
+    let mut bands: Option<Vec<Band>> = None;
+    let mut stats = Vec::new();
+    for path in paths {
+        let (file_bands, file_stats) = load_result(path)?;
+
+        match &bands {
+            Some(bands) if !same_bands(bands, &file_bands) => {
+                return Err(format!(
+                    "{}: bands differ from {} (results must use the same band settings)",
+                    path, paths[0]
+                ));
+            }
+            Some(_) => {}
+            None => bands = Some(file_bands),
+        }
+        stats.extend(file_stats);
+    }
+
+    if stats.len() > MAX_COMPARE_FILES {
+        return Err(format!(
+            "the results hold {} files; at most {} can be compared",
+            stats.len(),
+            MAX_COMPARE_FILES
+        ));
+    }
+    Ok((bands.unwrap_or_default(), stats))
+}
+
+/// Bands and file stats of one saved stats or compare result
+pub(super) fn load_result(path: &str) -> Result<(Vec<Band>, Vec<FileStats>), String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let document: Value =
+        serde_json::from_str(&text).map_err(|e| format!("{}: not a JSON result: {}", path, e))?;
+    parse_result(&document).map_err(|e| format!("{}: {}", path, e))
+}
+
+pub(super) fn same_bands(a: &[Band], b: &[Band]) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .zip(b)
+            .all(|(a, b)| a.label == b.label && a.low_hz == b.low_hz && a.high_hz == b.high_hz)
+}
+
+/// Bands and file stats of one `--format json` stats or compare document
+fn parse_result(document: &Value) -> Result<(Vec<Band>, Vec<FileStats>), String> {
+    match document.get("mode").and_then(Value::as_str) {
+        Some("stats" | "compare") => {}
+        Some(mode) => return Err(format!("{} results cannot be compared", mode)),
+        None => return Err("not a bandstat JSON result (no mode)".to_string()),
+    }
+
+    if let Some(format) = document.get("format").and_then(Value::as_u64) {
+        check_format(u32::try_from(format).unwrap_or(u32::MAX))?;
+    }
+
+    let bands = Vec::<Band>::deserialize(
+        document
+            .get("bands")
+            .ok_or_else(|| "missing bands list".to_string())?,
+    )
+    .map_err(|e| format!("invalid bands: {}", e))?;
+
+    let stats = array(document, "files")?
+        .iter()
+        .map(|file| parse_file(file, bands.len()))
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok((bands, stats))
+}
+
+fn parse_file(file: &Value, num_bands: usize) -> Result<FileStats, String> {
+    let name = file
+        .get("name")
+        .and_then(Value::as_str)
+        .ok_or("file without a name")?
+        .to_string();
+    let file_bands = array(file, "bands")?;
+    if file_bands.len() != num_bands {
+        return Err(format!(
+            "{} has {} bands instead of {}",
+            name,
+            file_bands.len(),
+            num_bands
+        ));
+    }
+    // Unknown values (null) become NaN, which the tables show as missing
+    let column = |key: &str| -> Vec<f64> {
+        file_bands
+            .iter()
+            .map(|b| b.get(key).and_then(Value::as_f64).unwrap_or(f64::NAN))
+            .collect()
+    };
+
+    Ok(FileStats {
+        original_sample_rate: number(file, "sample_rate")? as u32,
+        channels: number(file, "channels")? as u16,
+        raw_pct: column("raw_pct"),
+        k_pct: column("k_pct"),
+        dynamics: column("dynamics_db"),
+        k_dynamics: column("k_dynamics_db"),
+        raw_pct_se: column("raw_pct_se"),
+        k_pct_se: column("k_pct_se"),
+        raw_pct_ci: column("raw_pct_ci"),
+        k_pct_ci: column("k_pct_ci"),
+        share_quantiles: Vec::new(),
+        metric_pct: Vec::new(),
+        lfe: None,
+        // Results saved before frames were recorded count as stable
+        frames: file
+            .get("frames")
+            .and_then(Value::as_u64)
+            .map_or(usize::MAX, |f| f as usize),
+        integrated_lufs: file
+            .get("integrated_lufs")
+            .and_then(Value::as_f64)
+            .unwrap_or(f64::NAN),
+        duration_secs: file
+            .get("duration_secs")
+            .and_then(Value::as_f64)
+            .unwrap_or(0.0),
+        active_secs: file.get("active_secs").and_then(Value::as_f64),
+        verify: None,
+        name,
+    })
+}
+
+fn array<'a>(value: &'a Value, key: &str) -> Result<&'a Vec<Value>, String> {
+    value
+        .get(key)
+        .and_then(Value::as_array)
+        .ok_or_else(|| format!("missing {} list", key))
+}
+
+fn number(value: &Value, key: &str) -> Result<f64, String> {
+    value
+        .get(key)
+        .and_then(Value::as_f64)
+        .ok_or_else(|| format!("missing {}", key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::default_bands;
+    use crate::mode::report::report_context;
+    use bandstat::RESULT_FORMAT;
+
+    fn file(name: &str, raw: f64) -> FileStats {
+        let bands = default_bands().len();
+        FileStats {
+            name: name.to_string(),
+            original_sample_rate: 44100,
+            channels: 2,
+            raw_pct: vec![raw; bands],
+            k_pct: vec![raw; bands],
+            dynamics: vec![f64::NAN; bands],
+            k_dynamics: vec![f64::NAN; bands],
+            raw_pct_se: vec![0.1; bands],
+            k_pct_se: vec![0.1; bands],
+            raw_pct_ci: vec![0.2; bands],
+            k_pct_ci: vec![0.2; bands],
+            share_quantiles: Vec::new(),
+            metric_pct: Vec::new(),
+            lfe: None,
+            frames: 230,
+            integrated_lufs: -14.0,
+            duration_secs: 10.0,
+            active_secs: Some(8.0),
+            verify: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_result_round_trip() {
+        let config = AnalysisConfig::default();
+        let bands = config.bands();
+        let document = report_context(
+            &config,
+            &[file("a.wav", 7.0), file("b.wav", 1.0)],
+            false,
+            None,
+            &DisplayOptions::default(),
+            0,
+        );
+        let (parsed_bands, stats) = parse_result(&document).unwrap();
+
+        assert!(same_bands(bands, &parsed_bands));
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[1].name, "b.wav");
+        assert_eq!(stats[1].original_sample_rate, 44100);
+        assert_eq!(stats[0].raw_pct, vec![7.0; bands.len()]);
+        assert_eq!(stats[0].active_secs, Some(8.0));
+        assert!(stats[0].dynamics.iter().all(|d| d.is_nan()));
+    }
+
+    #[test]
+    fn test_parse_result_rejects_timeline() {
+        let document = serde_json::json!({ "mode": "timeline", "bands": [] });
+        let Err(e) = parse_result(&document) else {
+            panic!("timeline result accepted");
+        };
+        assert!(e.contains("timeline"));
+    }
+
+    #[test]
+    fn test_parse_result_rejects_newer_format() {
+        let mut document = report_context(
+            &AnalysisConfig::default(),
+            &[file("a.wav", 7.0)],
+            false,
+            None,
+            &DisplayOptions::default(),
+            0,
+        );
+        document["format"] = serde_json::json!(RESULT_FORMAT + 1);
+        let Err(e) = parse_result(&document) else {
+            panic!("newer result format accepted");
+        };
+        assert!(e.contains("newer"));
+    }
+}
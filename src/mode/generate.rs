@@ -0,0 +1,185 @@
+//! Test signal generation (`bandstat gen`), for checking a playback chain or the analysis
+
+use std::f32::consts::PI;
+
+use crate::analysis::{Band, default_bands};
+use crate::audio::{ExportFormat, write_wav};
+use crate::output::print_error;
+
+/// Start of the sweep in Hz
+const SWEEP_LOW_HZ: f32 = 20.0;
+
+/// End of the sweep in Hz, lowered to stay below Nyquist at low sample rates
+const SWEEP_HIGH_HZ: f32 = 20000.0;
+
+/// Signals written by `bandstat gen`
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub enum Signal {
+    /// Sine tone at --freq
+    Sine,
+    /// Logarithmic sweep from 20 Hz to 20 kHz
+    Sweep,
+    /// White noise (equal power per Hz)
+    White,
+    /// Pink noise (equal power per octave)
+    Pink,
+    /// One sine per band at equal levels, well inside each band
+    Bands,
+}
+
+/// What `bandstat gen` writes
+pub struct SignalSpec {
+    pub signal: Signal,
+    /// Sine frequency in Hz
+    pub freq: f32,
+    pub secs: f64,
+    /// Peak level in dBFS (a sine at 0 dBFS peaks at full scale)
+    pub level_dbfs: f64,
+    pub sample_rate: u32,
+    /// Sample format of the file (`--export-format`)
+    pub format: ExportFormat,
+}
+
+/// Write the test signal to a mono WAV file in the `--export-format` sample format. Returns false if it could not be written.
+pub fn run_generate(path: &str, spec: &SignalSpec) -> bool {
+    let samples = generate(spec);
+    if let Err(e) = write_wav(path, &samples, spec.sample_rate, spec.format) {
+        print_error(&e);
+        return false;
+    }
+    eprintln!(
+        "Test signal saved to: {} ({:.1} s at {} Hz, peak {:.1} dBFS)",
+        path, spec.secs, spec.sample_rate, spec.level_dbfs
+    );
+    true
+}
+
+/// Samples of the signal, scaled so the largest one sits at the peak level
+fn generate(spec: &SignalSpec) -> Vec<f32> {
+    let len = (spec.secs * spec.sample_rate as f64).round() as usize;
+    let rate = spec.sample_rate as f32;
+    let mut samples = match spec.signal {
+        Signal::Sine => sine(spec.freq, rate, len),
+        Signal::Sweep => sweep(rate, len),
+        Signal::White => white_noise(len, 0x5EED),
+        Signal::Pink => pink_noise(len, 0x5EED),
+        Signal::Bands => {
+            let mut sum = vec![0.0; len];
+            let nyquist = rate / 2.0;
+            for band in default_bands() {
+                let freq = test_frequency(&band);
+                if freq < nyquist {
+                    for (s, t) in sum.iter_mut().zip(sine(freq, rate, len)) {
+                        *s += t;
+                    }
+                }
+            }
+            sum
+        }
+    };
+
+    let peak = samples.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+    if peak > 0.0 {
+        let scale = 10f32.powf(spec.level_dbfs as f32 / 20.0) / peak;
+        for s in &mut samples {
+            *s *= scale;
+        }
+    }
+    samples
+}
+
+/// Pick a test tone frequency well inside the band
+pub(super) fn test_frequency(band: &Band) -> f32 {
+    if band.high_hz >= f32::MAX / 2.0 {
+        // Open-ended top band: stay below Nyquist
+        (band.low_hz + crate::audio::TARGET_SAMPLE_RATE as f32 / 2.0) / 2.0
+    } else if band.low_hz <= 0.0 {
+        band.high_hz / 2.0
+    } else {
+        (band.low_hz * band.high_hz).sqrt()
+    }
+}
+
+fn sine(freq: f32, sample_rate: f32, len: usize) -> Vec<f32> {
+    (0..len)
+        .map(|i| (2.0 * PI * freq * i as f32 / sample_rate).sin())
+        .collect()
+}
+
+/// Exponential sweep: every octave gets the same time, so the spectrum is pink
+fn sweep(sample_rate: f32, len: usize) -> Vec<f32> {
+    let high = SWEEP_HIGH_HZ.min(0.45 * sample_rate);
+    let secs = len as f64 / sample_rate as f64;
+    let rate = (high as f64 / SWEEP_LOW_HZ as f64).ln();
+    (0..len)
+        .map(|i| {
+            let t = i as f64 / sample_rate as f64;
+            let phase = 2.0 * std::f64::consts::PI * SWEEP_LOW_HZ as f64 * secs / rate
+                * ((t / secs * rate).exp() - 1.0);
+            phase.sin() as f32
+        })
+        .collect()
+}
+
+/// Uniform white noise from a xorshift generator (the same samples for the same seed)
+fn white_noise(len: usize, seed: u64) -> Vec<f32> {
+    let mut state = seed.max(1);
+    (0..len).map(|_| next_uniform(&mut state)).collect()
+}
+
+fn next_uniform(state: &mut u64) -> f32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    (*state as f64 / u64::MAX as f64) as f32 * 2.0 - 1.0
+}
+
+/// Generate pink noise (Paul Kellet's refined filter over xorshift white noise)
+pub(super) fn pink_noise(len: usize, seed: u64) -> Vec<f32> {
+    let mut state = seed.max(1);
+    let mut b = [0.0f32; 7];
+
+    (0..len)
+        .map(|_| {
+            let white = next_uniform(&mut state);
+
+            b[0] = 0.99886 * b[0] + white * 0.0555179;
+            b[1] = 0.99332 * b[1] + white * 0.0750759;
+            b[2] = 0.96900 * b[2] + white * 0.153852;
+            b[3] = 0.86650 * b[3] + white * 0.3104856;
+            b[4] = 0.55000 * b[4] + white * 0.5329522;
+            b[5] = -0.7616 * b[5] - white * 0.0168980;
+            let pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + white * 0.5362;
+            b[6] = white * 0.115926;
+            pink * 0.05
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signals_peak_at_the_requested_level() {
+        for signal in [
+            Signal::Sine,
+            Signal::Sweep,
+            Signal::White,
+            Signal::Pink,
+            Signal::Bands,
+        ] {
+            let samples = generate(&SignalSpec {
+                signal,
+                freq: 1000.0,
+                secs: 0.5,
+                level_dbfs: -6.0,
+                sample_rate: 44100,
+                format: ExportFormat::default(),
+            });
+            assert_eq!(samples.len(), 22050);
+            let peak = samples.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+            assert!((20.0 * peak.log10() + 6.0).abs() < 0.01, "{:?}", signal);
+        }
+    }
+}
@@ -0,0 +1,260 @@
+//! Rolling 24-hour band-balance heatmap for monitoring mode
+
+use std::collections::VecDeque;
+use std::path::Path;
+
+use super::monitor::utc_parts;
+use crate::analysis::{AnalysisConfig, Band};
+use crate::chart::{self, ChartOptions, HeatmapChartData};
+
+/// Time span covered by the heatmap
+const WINDOW_SECS: u64 = 24 * 3600;
+
+/// Width of one heatmap column
+const SLOT_SECS: u64 = 300;
+
+/// Minimum wall-clock time between re-renders
+const REFRESH_SECS: u64 = 60;
+
+/// Interval history for the last 24 hours, re-rendered to a PNG as it grows
+pub(crate) struct DailyHeatmap {
+    path: String,
+    subtitle: String,
+    k_weighted: bool,
+    charts: ChartOptions,
+    entries: VecDeque<(u64, Vec<f64>)>,
+    last_render: Option<u64>,
+}
+
+impl DailyHeatmap {
+    /// Create the heatmap, seeding it from NDJSON logs already in `log_dir`
+    pub(crate) fn new(
+        path: &str,
+        source_name: &str,
+        log_dir: &str,
+        config: &AnalysisConfig,
+        k_weighted: bool,
+        charts: &ChartOptions,
+        now: u64,
+    ) -> Self {
+        let mut heatmap = Self {
+            path: path.to_string(),
+            subtitle: if k_weighted {
+                format!(
+                    "{} - last 24 h (UTC), {}",
+                    source_name,
+                    config.weighting.name()
+                )
+            } else {
+                format!("{} - last 24 h (UTC)", source_name)
+            },
+            k_weighted,
+            charts: charts.clone(),
+            entries: VecDeque::new(),
+            last_render: None,
+        };
+        heatmap.seed_from_logs(Path::new(log_dir), config.bands(), now);
+        heatmap
+    }
+
+    /// Record one interval; re-renders if the refresh period has elapsed
+    pub(crate) fn push(
+        &mut self,
+        unix_time: u64,
+        percentages: &[f64],
+        bands: &[Band],
+    ) -> Result<(), String> {
+        self.entries.push_back((unix_time, percentages.to_vec()));
+        self.prune(unix_time);
+
+        let due = self
+            .last_render
+            .is_none_or(|last| unix_time >= last + REFRESH_SECS);
+        if due {
+            self.render(unix_time, bands)?;
+        }
+        Ok(())
+    }
+
+    /// Render the current window to the PNG path
+    pub(crate) fn render(&mut self, now: u64, bands: &[Band]) -> Result<(), String> {
+        if self.entries.is_empty() {
+            return Ok(());
+        }
+        let data = self.slot_averages(now, bands.len());
+        chart::render_heatmap_chart(
+            &data,
+            bands,
+            "Band Balance (24 h)",
+            &self.path,
+            &self.charts,
+        )?;
+        self.last_render = Some(now);
+        Ok(())
+    }
+
+    fn prune(&mut self, now: u64) {
+        let cutoff = now.saturating_sub(WINDOW_SECS);
+        while self.entries.front().is_some_and(|(t, _)| *t < cutoff) {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Average intervals into fixed-width slots ending at the slot containing `now`
+    fn slot_averages(&self, now: u64, num_bands: usize) -> HeatmapChartData {
+        let num_slots = (WINDOW_SECS / SLOT_SECS) as usize;
+        let end = (now / SLOT_SECS + 1) * SLOT_SECS;
+        let start = end - WINDOW_SECS;
+
+        let mut sums = vec![vec![0.0f64; num_slots]; num_bands];
+        let mut counts = vec![0usize; num_slots];
+        for (t, percentages) in &self.entries {
+            if *t < start || *t >= end {
+                continue;
+            }
+            let slot = ((t - start) / SLOT_SECS) as usize;
+            counts[slot] += 1;
+            for (band_sums, pct) in sums.iter_mut().zip(percentages) {
+                band_sums[slot] += pct;
+            }
+        }
+
+        let time_labels = (0..num_slots as u64)
+            .map(|slot| {
+                let (_, _, _, h, m, _) = utc_parts(start + slot * SLOT_SECS);
+                format!("{:02}:{:02}", h, m)
+            })
+            .collect();
+        let band_percentages = sums
+            .into_iter()
+            .map(|band_sums| {
+                band_sums
+                    .into_iter()
+                    .zip(&counts)
+                    .map(|(sum, &n)| (n > 0).then(|| sum / n as f64))
+                    .collect()
+            })
+            .collect();
+
+        HeatmapChartData {
+            subtitle: self.subtitle.clone(),
+            time_labels,
+            band_percentages,
+        }
+    }
+
+    /// Load recent records from earlier monitoring logs (same bands and weighting only)
+    fn seed_from_logs(&mut self, dir: &Path, bands: &[Band], now: u64) {
+        let Ok(read_dir) = std::fs::read_dir(dir) else {
+            return;
+        };
+        let cutoff = now.saturating_sub(WINDOW_SECS);
+        let weighting = if self.k_weighted { "k" } else { "raw" };
+
+        let mut entries = Vec::new();
+        for path in read_dir.flatten().map(|e| e.path()) {
+            let is_log = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("bandstat-") && n.ends_with(".ndjson"));
+            if !is_log {
+                continue;
+            }
+            let Ok(text) = std::fs::read_to_string(&path) else {
+                continue;
+            };
+            for line in text.lines() {
+                if let Some(entry) = parse_record(line, bands, weighting)
+                    && entry.0 >= cutoff
+                    && entry.0 <= now
+                {
+                    entries.push(entry);
+                }
+            }
+        }
+
+        entries.sort_by_key(|(t, _)| *t);
+        self.entries.extend(entries);
+    }
+}
+
+/// Parse one NDJSON log record into (unix_time, percentages)
+fn parse_record(line: &str, bands: &[Band], weighting: &str) -> Option<(u64, Vec<f64>)> {
+    let record: serde_json::Value = serde_json::from_str(line).ok()?;
+    if record["weighting"].as_str()? != weighting {
+        return None;
+    }
+    let unix_time = record["unix_time"].as_u64()?;
+    let recorded = record["bands"].as_array()?;
+    if recorded.len() != bands.len() {
+        return None;
+    }
+
+    let percentages = recorded
+        .iter()
+        .zip(bands)
+        .map(|(entry, band)| {
+            (entry["label"].as_str()? == band.label)
+                .then(|| entry["pct"].as_f64())
+                .flatten()
+        })
+        .collect::<Option<Vec<f64>>>()?;
+    Some((unix_time, percentages))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(unix_time: u64, weighting: &str, bands: &[Band]) -> String {
+        let entries: Vec<String> = bands
+            .iter()
+            .map(|b| format!(r#"{{"label":"{}","pct":1.5}}"#, b.label))
+            .collect();
+        format!(
+            r#"{{"unix_time":{},"weighting":"{}","bands":[{}]}}"#,
+            unix_time,
+            weighting,
+            entries.join(",")
+        )
+    }
+
+    #[test]
+    fn test_parse_record() {
+        let config = AnalysisConfig::default();
+        let bands = config.bands();
+        let (t, pct) = parse_record(&record(100, "raw", bands), bands, "raw").unwrap();
+        assert_eq!(t, 100);
+        assert_eq!(pct.len(), bands.len());
+        assert!(parse_record(&record(100, "k", bands), bands, "raw").is_none());
+        assert!(parse_record("not json", bands, "raw").is_none());
+    }
+
+    #[test]
+    fn test_window_prunes_and_averages() {
+        let config = AnalysisConfig::default();
+        let bands = config.bands();
+        let mut heatmap = DailyHeatmap {
+            path: String::new(),
+            subtitle: String::new(),
+            k_weighted: false,
+            charts: ChartOptions::default(),
+            entries: VecDeque::new(),
+            // Suppress rendering in push()
+            last_render: Some(u64::MAX - REFRESH_SECS),
+        };
+        let day = WINDOW_SECS;
+        let mut pct = vec![0.0; bands.len()];
+        pct[0] = 10.0;
+        heatmap.push(day, &pct, bands).unwrap();
+        pct[0] = 20.0;
+        heatmap.push(day + 10, &pct, bands).unwrap();
+
+        let data = heatmap.slot_averages(day + 10, bands.len());
+        let filled: Vec<f64> = data.band_percentages[0].iter().flatten().copied().collect();
+        assert_eq!(filled, vec![15.0]);
+
+        heatmap.push(2 * day + 5, &pct, bands).unwrap();
+        assert_eq!(heatmap.entries.len(), 2, "entry older than 24 h is dropped");
+    }
+}
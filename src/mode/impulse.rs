@@ -0,0 +1,93 @@
+//! Impulse response mode: per-band gain and group delay, for checking speaker and DSP presets
+
+use serde_json::json;
+
+use super::load_audio;
+use super::monitor::{format_utc, unix_now};
+use super::report::finite;
+use crate::analysis::{AnalysisConfig, band_responses};
+use crate::audio::LoadOptions;
+use crate::output::{Output, get_display_name, print_error};
+use bandstat::RESULT_FORMAT;
+
+/// Analyze a file holding an impulse response
+pub fn run_impulse(
+    filename: &str,
+    load_options: &LoadOptions,
+    config: &AnalysisConfig,
+    out: &Output,
+) {
+    let bands = config.bands();
+    let display_name = get_display_name(filename).to_string();
+    let audio = load_audio(filename, load_options).unwrap_or_else(|e| {
+        print_error(&e);
+        std::process::exit(1);
+    });
+    if audio.samples.iter().all(|&s| s == 0.0) {
+        print_error(&format!("{}: the impulse response is silent", display_name));
+        std::process::exit(1);
+    }
+
+    let responses = band_responses(&audio.samples, audio.sample_rate, config);
+    let peak_index = audio
+        .samples
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()))
+        .map_or(0, |(i, _)| i);
+    let peak_ms = peak_index as f64 / audio.sample_rate as f64 * 1000.0;
+    let gains: Vec<f64> = responses.iter().map(|r| r.gain_db).collect();
+    let delays: Vec<f64> = responses.iter().map(|r| r.group_delay_ms).collect();
+
+    if out.display.json_output() {
+        let mut document = json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "format": RESULT_FORMAT,
+            "generated": format_utc(unix_now()),
+            "mode": "impulse",
+            "bands": bands,
+            "file": {
+                "name": display_name,
+                "sample_rate": audio.original_sample_rate,
+                "channels": audio.channels,
+            },
+            "peak_ms": peak_ms,
+            "gain_db": gains.iter().map(|&v| finite(v)).collect::<Vec<_>>(),
+            "group_delay_ms": delays.iter().map(|&v| finite(v)).collect::<Vec<_>>(),
+        });
+        out.display.round_json(&mut document);
+        writeln!(out.sink, "{:#}", document);
+        return;
+    }
+
+    if !out.quiet {
+        writeln!(out.sink);
+        writeln!(out.sink, "Impulse Response");
+        out.print_file_info(
+            &display_name,
+            audio.original_sample_rate,
+            audio.channels,
+            false,
+        );
+        out.print_bands(bands);
+    }
+
+    writeln!(out.sink, "Peak: {:.2} ms", peak_ms);
+    writeln!(out.sink);
+    out.print_header(bands, "        ");
+    out.print_separator(bands, 8);
+    out.print_row("Gain(dB)", &gains);
+    out.print_row("GD(ms)  ", &delays);
+
+    if !out.quiet {
+        writeln!(out.sink);
+        writeln!(
+            out.sink,
+            "Gain: Average power gain in each band (0 dB = unity)"
+        );
+        writeln!(
+            out.sink,
+            "GD: Energy-weighted average group delay (ms); a band more than 80 dB below the loudest has none"
+        );
+    }
+}
@@ -0,0 +1,318 @@
+//! Long-running JSON-RPC 2.0 mode for GUI front-ends
+//!
+//! Requests arrive one per line on stdin and responses leave one per line on
+//! stdout, so a parent process can analyze many files without respawning the
+//! binary. The FFT plan and the stats of unchanged files are kept between requests.
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::time::SystemTime;
+
+use serde_json::{Value, json};
+
+use super::monitor::unix_now;
+use super::report::report_context;
+use super::{FileStats, MAX_COMPARE_FILES, stream_stats};
+use crate::analysis::{AnalysisConfig, AnalysisContext};
+use crate::audio::{LoadOptions, TARGET_SAMPLE_RATE, is_url};
+use crate::output::{DisplayOptions, get_display_name, print_error, print_warnings};
+
+// JSON-RPC 2.0 error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Implementation-defined server error: the audio could not be analyzed
+const ANALYSIS_ERROR: i64 = -32000;
+
+/// JSON-RPC error object
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Modification time and size of a local file when its stats were cached
+type Fingerprint = (SystemTime, u64);
+
+/// Analysis state kept warm between requests
+struct IpcServer<'a> {
+    config: &'a AnalysisConfig,
+    /// Decoding settings from the command line; requests set the duration
+    load_options: &'a LoadOptions,
+    ctx: AnalysisContext,
+    /// Stats per (path, duration limit) with the file's fingerprint at the time
+    cache: HashMap<(String, Option<u64>), (Fingerprint, FileStats)>,
+}
+
+/// Serve JSON-RPC requests from stdin until it closes or `shutdown` is called
+///
+/// Methods:
+/// - `analyze` `{files, duration?, weighted?}`: the same data as a report
+///   context (see `--report`), with `progress` notifications while it runs
+/// - `shutdown`: reply with `null` and exit
+pub fn run_ipc(load_options: &LoadOptions, config: &AnalysisConfig) {
+    let mut server = IpcServer {
+        config,
+        load_options,
+        ctx: AnalysisContext::new(TARGET_SAMPLE_RATE, config),
+        cache: HashMap::new(),
+    };
+    let mut stdout = std::io::stdout().lock();
+    let mut send = |message: Value| {
+        if let Err(e) = writeln!(stdout, "{}", message).and_then(|_| stdout.flush()) {
+            print_error(&format!("Failed to write IPC response: {}", e));
+            std::process::exit(1);
+        }
+    };
+
+    for line in std::io::stdin().lock().lines() {
+        let line = line.unwrap_or_else(|e| {
+            print_error(&format!("Failed to read IPC request: {}", e));
+            std::process::exit(1);
+        });
+        if line.trim().is_empty() {
+            continue;
+        }
+        if !server.handle_line(&line, &mut send) {
+            break;
+        }
+    }
+}
+
+impl IpcServer<'_> {
+    /// Handle one request line; returns false once the client asked to shut down
+    fn handle_line(&mut self, line: &str, send: &mut dyn FnMut(Value)) -> bool {
+        let request: Value = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => {
+                send(error_response(
+                    Value::Null,
+                    RpcError::new(PARSE_ERROR, format!("Parse error: {}", e)),
+                ));
+                return true;
+            }
+        };
+
+        // Requests without an id are notifications and get no response
+        let id = request.get("id").cloned();
+        let method = match (request.get("jsonrpc"), request.get("method")) {
+            (Some(version), Some(Value::String(method))) if version == "2.0" => method.as_str(),
+            _ => {
+                send(error_response(
+                    id.unwrap_or(Value::Null),
+                    RpcError::new(INVALID_REQUEST, "Invalid request"),
+                ));
+                return true;
+            }
+        };
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+        let progress_id = id.clone().unwrap_or(Value::Null);
+
+        let (result, keep_running) = match method {
+            "analyze" => (self.analyze(&params, &progress_id, send), true),
+            "shutdown" => (Ok(Value::Null), false),
+            _ => (
+                Err(RpcError::new(
+                    METHOD_NOT_FOUND,
+                    format!("Method not found: {}", method),
+                )),
+                true,
+            ),
+        };
+
+        if let Some(id) = id {
+            send(match result {
+                Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                Err(error) => error_response(id, error),
+            });
+        }
+        keep_running
+    }
+
+    /// Analyze one file, or compare several against the first
+    fn analyze(
+        &mut self,
+        params: &Value,
+        id: &Value,
+        send: &mut dyn FnMut(Value),
+    ) -> Result<Value, RpcError> {
+        let files: Vec<&str> = params
+            .get("files")
+            .and_then(Value::as_array)
+            .and_then(|files| files.iter().map(Value::as_str).collect())
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, "files must be an array of strings"))?;
+        if files.is_empty() || files.len() > MAX_COMPARE_FILES {
+            return Err(RpcError::new(
+                INVALID_PARAMS,
+                format!("files must list 1 to {} inputs", MAX_COMPARE_FILES),
+            ));
+        }
+
+        let max_duration = match params.get("duration") {
+            None | Some(Value::Null) => None,
+            Some(value) => match value.as_f64() {
+                Some(secs) if secs > 0.0 && secs.is_finite() => Some(secs),
+                _ => {
+                    return Err(RpcError::new(
+                        INVALID_PARAMS,
+                        "duration must be a positive number of seconds",
+                    ));
+                }
+            },
+        };
+        let weighted = match params.get("weighted") {
+            None | Some(Value::Null) => false,
+            Some(value) => value
+                .as_bool()
+                .ok_or_else(|| RpcError::new(INVALID_PARAMS, "weighted must be a boolean"))?,
+        };
+
+        let load_options = LoadOptions {
+            start: None,
+            max_duration,
+            follow: None,
+            ..self.load_options.clone()
+        };
+        let mut stats = Vec::with_capacity(files.len());
+        for file in files {
+            let file_stats = self
+                .file_stats(file, &load_options, id, send)
+                .map_err(|e| RpcError::new(ANALYSIS_ERROR, e))?;
+            stats.push(file_stats);
+        }
+
+        Ok(report_context(
+            self.config,
+            &stats,
+            weighted,
+            load_options.highpass_hz,
+            &DisplayOptions::default(),
+            unix_now(),
+        ))
+    }
+
+    /// Stats of one input, reusing the cached result while a local file is unchanged
+    fn file_stats(
+        &mut self,
+        file: &str,
+        load_options: &LoadOptions,
+        id: &Value,
+        send: &mut dyn FnMut(Value),
+    ) -> Result<FileStats, String> {
+        let key = (
+            file.to_string(),
+            load_options.max_duration.map(f64::to_bits),
+        );
+        let current = (!is_url(file)).then(|| fingerprint(file)).flatten();
+        if let (Some(current), Some((cached, stats))) = (current, self.cache.get(&key))
+            && *cached == current
+        {
+            return Ok(stats.clone());
+        }
+
+        let name = get_display_name(file).to_string();
+        let (stats, warnings) = stream_stats(
+            file,
+            self.config.bands(),
+            &mut self.ctx,
+            load_options,
+            |percent| {
+                send(json!({
+                    "jsonrpc": "2.0",
+                    "method": "progress",
+                    "params": { "id": id, "file": name, "percent": percent },
+                }));
+            },
+        )?;
+        print_warnings(&warnings);
+
+        if let Some(current) = current {
+            self.cache.insert(key, (current, stats.clone()));
+        }
+        Ok(stats)
+    }
+}
+
+/// Modification time and size of a local file, if available
+fn fingerprint(path: &str) -> Option<Fingerprint> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": error.code, "message": error.message },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server<'a>(config: &'a AnalysisConfig, load_options: &'a LoadOptions) -> IpcServer<'a> {
+        IpcServer {
+            config,
+            load_options,
+            ctx: AnalysisContext::new(TARGET_SAMPLE_RATE, config),
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Messages sent in reply to one request line, and whether the server keeps running
+    fn exchange(server: &mut IpcServer, line: &str) -> (Vec<Value>, bool) {
+        let mut sent = Vec::new();
+        let keep_running = server.handle_line(line, &mut |message| sent.push(message));
+        (sent, keep_running)
+    }
+
+    #[test]
+    fn test_protocol_errors() {
+        let (config, load_options) = (AnalysisConfig::default(), LoadOptions::default());
+        let mut server = server(&config, &load_options);
+
+        let (sent, _) = exchange(&mut server, "{not json");
+        assert_eq!(sent[0]["error"]["code"], PARSE_ERROR);
+        assert!(sent[0]["id"].is_null());
+
+        let (sent, _) = exchange(&mut server, r#"{"id": 1, "method": "analyze"}"#);
+        assert_eq!(sent[0]["error"]["code"], INVALID_REQUEST);
+
+        let (sent, _) = exchange(&mut server, r#"{"jsonrpc": "2.0", "id": 2, "method": "x"}"#);
+        assert_eq!(sent[0]["id"], 2);
+        assert_eq!(sent[0]["error"]["code"], METHOD_NOT_FOUND);
+
+        let (sent, _) = exchange(
+            &mut server,
+            r#"{"jsonrpc": "2.0", "id": 3, "method": "analyze", "params": {"files": []}}"#,
+        );
+        assert_eq!(sent[0]["error"]["code"], INVALID_PARAMS);
+
+        // Notifications are never answered, even on error
+        let (sent, _) = exchange(&mut server, r#"{"jsonrpc": "2.0", "method": "x"}"#);
+        assert!(sent.is_empty());
+    }
+
+    #[test]
+    fn test_shutdown_stops_server() {
+        let (config, load_options) = (AnalysisConfig::default(), LoadOptions::default());
+        let mut server = server(&config, &load_options);
+        let (sent, keep_running) = exchange(
+            &mut server,
+            r#"{"jsonrpc": "2.0", "id": 9, "method": "shutdown"}"#,
+        );
+        assert!(!keep_running);
+        assert_eq!(sent[0]["id"], 9);
+        assert!(sent[0]["result"].is_null());
+    }
+}
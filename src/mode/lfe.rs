@@ -0,0 +1,120 @@
+//! LFE check for surround files (`--lfe`): the band distribution of the LFE channel and its
+//! level against the bass of the main channels, as bass management plays them back
+
+use serde_json::{Value, json};
+
+use super::report::finite;
+use crate::analysis::{AnalysisContext, Band, analyze_interval, powers_to_percentages};
+use crate::audio::LfeAudio;
+use crate::output::Output;
+
+/// Upper edge of the range the LFE channel is meant to carry
+const LFE_BAND_HZ: f32 = 120.0;
+
+/// In-band playback gain of the LFE channel over the main channels
+const LFE_GAIN_DB: f64 = 10.0;
+
+/// LFE measurements of one surround file
+#[derive(Clone, Debug)]
+pub struct LfeStats {
+    /// Position of the LFE among the file's channels (0-based)
+    pub channel: usize,
+    /// Share of the LFE channel's energy in each band
+    pub raw_pct: Vec<f64>,
+    /// Share of the LFE channel's energy above 120 Hz, which it shouldn't carry
+    pub above_band_pct: f64,
+    /// RMS level of the LFE channel in dBFS (NaN when silent)
+    pub rms_dbfs: f64,
+    /// Energy of the LFE below 120 Hz with its +10 dB gain, relative to the main channels'
+    /// energy below 120 Hz, in dB (NaN when either is silent)
+    pub relative_db: f64,
+}
+
+/// Measure the LFE channel against the summed main channels
+pub(super) fn measure_lfe(audio: &LfeAudio, bands: &[Band], ctx: &mut AnalysisContext) -> LfeStats {
+    let split = [
+        Band {
+            label: "IN".into(),
+            low_hz: 0.0,
+            high_hz: LFE_BAND_HZ,
+        },
+        Band {
+            label: "ABOVE".into(),
+            low_hz: LFE_BAND_HZ,
+            high_hz: f32::MAX,
+        },
+    ];
+    let lfe_split = analyze_interval(&audio.lfe, ctx, &split, false);
+    let mains_split = analyze_interval(&audio.mains, ctx, &split, false);
+    let mean_square =
+        audio.lfe.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / audio.lfe.len() as f64;
+    let relative_db = 10.0 * (lfe_split[0] / mains_split[0]).log10() + LFE_GAIN_DB;
+    let lfe_total = lfe_split[0] + lfe_split[1];
+    let rms_dbfs = 10.0 * mean_square.log10();
+    let lfe_powers = analyze_interval(&audio.lfe, ctx, bands, false);
+
+    LfeStats {
+        channel: audio.channel,
+        raw_pct: powers_to_percentages(&lfe_powers, ctx.excluded_bands()),
+        // Not `powers_to_percentages`, which would apply `--exclude-bands` to the split
+        above_band_pct: if lfe_total > 0.0 {
+            lfe_split[1] / lfe_total * 100.0
+        } else {
+            0.0
+        },
+        rms_dbfs: if rms_dbfs.is_finite() {
+            rms_dbfs
+        } else {
+            f64::NAN
+        },
+        relative_db: if relative_db.is_finite() {
+            relative_db
+        } else {
+            f64::NAN
+        },
+    }
+}
+
+/// Print the LFE table and levels
+pub(super) fn print_lfe(lfe: &LfeStats, bands: &[Band], out: &Output) {
+    let level = |db: f64| {
+        if db.is_finite() {
+            format!("{:+.1} dB", db)
+        } else {
+            "-".to_string()
+        }
+    };
+    writeln!(out.sink);
+    writeln!(out.sink, "[LFE] channel {}", lfe.channel + 1);
+    out.print_header(bands, "        ");
+    out.print_separator(bands, 8);
+    out.print_row("LFE(%)  ", &lfe.raw_pct);
+    writeln!(
+        out.sink,
+        "Above {} Hz: {:.1}% of the LFE energy",
+        LFE_BAND_HZ, lfe.above_band_pct
+    );
+    if lfe.rms_dbfs.is_finite() {
+        writeln!(out.sink, "Level: {:.1} dBFS RMS", lfe.rms_dbfs);
+    } else {
+        writeln!(out.sink, "Level: silent");
+    }
+    writeln!(
+        out.sink,
+        "Against the main channels below {} Hz: {} (with the +{} dB LFE gain)",
+        LFE_BAND_HZ,
+        level(lfe.relative_db),
+        LFE_GAIN_DB
+    );
+}
+
+/// LFE fields of a file in the JSON output and report templates
+pub(super) fn lfe_json(lfe: &LfeStats) -> Value {
+    json!({
+        "channel": lfe.channel + 1,
+        "raw_pct": lfe.raw_pct,
+        "above_120hz_pct": lfe.above_band_pct,
+        "rms_dbfs": finite(lfe.rms_dbfs),
+        "relative_db": finite(lfe.relative_db),
+    })
+}
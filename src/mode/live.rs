@@ -0,0 +1,234 @@
+//! Live mode: band shares of an audio input device, e.g. the end of a monitoring chain
+//! (`live` feature)
+
+use std::sync::mpsc;
+use std::time::Instant;
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{
+    Device, FromSample, Sample, SampleFormat, SizedSample, Stream, StreamConfig, StreamError,
+};
+
+use super::monitor::IntervalAnalyzer;
+use crate::analysis::AnalysisConfig;
+use crate::audio::{HighPass, LoadOptions, StreamResampler, TARGET_SAMPLE_RATE};
+use crate::output::{Output, print_error, print_warning};
+
+/// Capture from an input device (the default one without `device_name`) until interrupted
+/// or the `max_duration` of `load_options` has been captured, printing one row per interval
+pub fn run_live(
+    device_name: Option<&str>,
+    interval_secs: u32,
+    load_options: &LoadOptions,
+    config: &AnalysisConfig,
+    out: &Output,
+) {
+    let device = find_device(device_name).unwrap_or_else(|e| {
+        print_error(&e);
+        std::process::exit(1);
+    });
+    let name = device.name().unwrap_or_else(|_| "input device".to_string());
+    let input_config = device.default_input_config().unwrap_or_else(|e| {
+        print_error(&format!("{}: no usable input format ({})", name, e));
+        std::process::exit(1);
+    });
+    let sample_rate = input_config.sample_rate().0;
+    let channels = input_config.channels();
+
+    let (sender, receiver) = mpsc::channel();
+    // Capture stops once the stream is dropped, so it lives as long as the loop
+    let stream = match input_config.sample_format() {
+        SampleFormat::F32 => build_stream::<f32>(&device, &input_config.config(), sender),
+        SampleFormat::I16 => build_stream::<i16>(&device, &input_config.config(), sender),
+        SampleFormat::I32 => build_stream::<i32>(&device, &input_config.config(), sender),
+        SampleFormat::U16 => build_stream::<u16>(&device, &input_config.config(), sender),
+        format => Err(format!("sample format {} is not supported", format)),
+    }
+    .and_then(|stream| stream.play().map(|_| stream).map_err(|e| e.to_string()))
+    .unwrap_or_else(|e| {
+        print_error(&format!("{}: cannot capture ({})", name, e));
+        std::process::exit(1);
+    });
+
+    let bands = config.bands();
+    if !out.quiet {
+        writeln!(out.sink);
+        out.print_file_info(&name, sample_rate, channels, out.weighted);
+        out.print_bands(bands);
+        eprintln!("Listening (Ctrl-C to stop)");
+    }
+    out.print_header(bands, &out.display.time_column("TIME"));
+    out.print_separator(bands, out.display.time_column_width());
+
+    let mut resampler = (sample_rate != TARGET_SAMPLE_RATE)
+        .then(|| StreamResampler::new(sample_rate, TARGET_SAMPLE_RATE, load_options.resampling))
+        .transpose()
+        .unwrap_or_else(|e| {
+            print_error(&e);
+            std::process::exit(1);
+        });
+    let mut highpass = load_options
+        .highpass_hz
+        .map(|hz| HighPass::new(hz, sample_rate))
+        .transpose()
+        .unwrap_or_else(|e| {
+            print_error(&format!("{}: {}", name, e));
+            std::process::exit(1);
+        });
+    let mut analyzer = IntervalAnalyzer::new(config, out.weighted);
+    let samples_per_interval = (interval_secs as usize) * (TARGET_SAMPLE_RATE as usize);
+    let max_samples = load_options
+        .max_duration
+        .map(|secs| (secs * TARGET_SAMPLE_RATE as f64).round() as usize);
+    let mut pending: Vec<f32> = Vec::with_capacity(samples_per_interval * 2);
+    let mut interval_start = 0usize;
+    let started = Instant::now();
+    let mut print_row = |interval: &[f32], start: usize| {
+        if let Some(percentages) = analyzer.analyze(interval) {
+            let start_secs = start as f64 / TARGET_SAMPLE_RATE as f64;
+            out.print_interval_row(
+                &out.display.timestamp_label(start_secs, TARGET_SAMPLE_RATE),
+                &percentages,
+                bands,
+            );
+        }
+    };
+
+    for captured in receiver {
+        let mut captured = captured.unwrap_or_else(|e| {
+            print_error(&format!("{}: {}", name, e));
+            std::process::exit(1);
+        });
+        if let Some(highpass) = highpass.as_mut() {
+            highpass.process(&mut captured);
+        }
+        match resampler.as_mut() {
+            Some(resampler) => match resampler.process(&captured) {
+                Ok(resampled) => pending.extend(resampled),
+                Err(e) => {
+                    print_error(&e);
+                    std::process::exit(1);
+                }
+            },
+            None => pending.extend(captured),
+        }
+        let done = max_samples.is_some_and(|max| interval_start + pending.len() >= max);
+        if let Some(max) = max_samples {
+            pending.truncate(max.saturating_sub(interval_start));
+        }
+
+        while pending.len() >= samples_per_interval {
+            print_row(&pending[..samples_per_interval], interval_start);
+            pending.drain(..samples_per_interval);
+            interval_start += samples_per_interval;
+        }
+        if done {
+            // The last, shorter interval of a --duration capture
+            if !pending.is_empty() {
+                print_row(&pending, interval_start);
+            }
+            break;
+        }
+    }
+    drop(stream);
+    out.record_verify(analyzer.verify_summary());
+
+    if !out.quiet {
+        eprintln!(
+            "Captured {:.1} s from {}",
+            started.elapsed().as_secs_f64(),
+            name
+        );
+    }
+}
+
+/// Input device by name (case-insensitive; a unique part of the name is enough), or the
+/// default one
+fn find_device(name: Option<&str>) -> Result<Device, String> {
+    let host = cpal::default_host();
+    let Some(name) = name else {
+        return host
+            .default_input_device()
+            .ok_or_else(|| "no audio input device found".to_string());
+    };
+
+    let mut devices: Vec<(String, Device)> = host
+        .input_devices()
+        .map_err(|e| format!("cannot list audio input devices ({})", e))?
+        .filter_map(|device| Some((device.name().ok()?, device)))
+        .collect();
+    let wanted = name.to_lowercase();
+    if let Some(index) = devices
+        .iter()
+        .position(|(device, _)| device.to_lowercase() == wanted)
+    {
+        return Ok(devices.swap_remove(index).1);
+    }
+    let matching: Vec<usize> = (0..devices.len())
+        .filter(|&i| devices[i].0.to_lowercase().contains(&wanted))
+        .collect();
+    let names: Vec<String> = devices.iter().map(|(name, _)| name.clone()).collect();
+    match matching[..] {
+        [index] => Ok(devices.swap_remove(index).1),
+        [] if names.is_empty() => Err(format!(
+            "--device {}: there are no audio input devices",
+            name
+        )),
+        [] => Err(format!(
+            "--device {}: no such input device (available: {})",
+            name,
+            names.join(", ")
+        )),
+        _ => Err(format!(
+            "--device {} matches several input devices: {}",
+            name,
+            matching
+                .iter()
+                .map(|&i| names[i].as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Start capturing interleaved samples of type `T`, sending each buffer mixed down to mono,
+/// or the error that ended capture
+fn build_stream<T>(
+    device: &Device,
+    config: &StreamConfig,
+    sender: mpsc::Sender<Result<Vec<f32>, String>>,
+) -> Result<Stream, String>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let channels = config.channels.max(1) as usize;
+    let errors = sender.clone();
+    device
+        .build_input_stream(
+            config,
+            move |data: &[T], _: &cpal::InputCallbackInfo| {
+                let mono = data
+                    .chunks(channels)
+                    .map(|frame| {
+                        let sum: f32 = frame
+                            .iter()
+                            .map(|&s| f32::from_sample(s))
+                            .filter(|s| s.is_finite())
+                            .sum();
+                        sum / channels as f32
+                    })
+                    .collect();
+                // The receiver only goes away once capture is over
+                let _ = sender.send(Ok(mono));
+            },
+            move |e| match e {
+                StreamError::DeviceNotAvailable => {
+                    let _ = errors.send(Err(e.to_string()));
+                }
+                e => print_warning(&format!("audio input: {}", e)),
+            },
+            None,
+        )
+        .map_err(|e| e.to_string())
+}
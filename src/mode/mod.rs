@@ -1,43 +1,159 @@
 //! CLI mode implementations
 
+mod alert;
+mod bands;
+mod batch;
+mod browse;
 mod compare;
+mod cue;
+mod diff_results;
+mod generate;
+mod heatmap;
+mod impulse;
+mod ipc;
+mod lfe;
+#[cfg(feature = "live")]
+mod live;
+mod monitor;
+mod parallel;
+mod report;
+mod selftest;
+mod sonify;
 mod stats;
 mod timeline;
+mod trend;
+mod tui;
+mod verify_tone;
+mod watch;
 
-pub use compare::run_compare;
-pub use stats::run_stats;
-pub use timeline::run_timeline;
+pub use bands::run_bands;
+pub use batch::{
+    BatchOptions, NAME_PLACEHOLDER, expand_file_patterns, list_audio_files, read_file_list,
+    run_batch,
+};
+pub use compare::{CompareOptions, MAX_COMPARE_FILES, parse_file_label, run_compare};
+pub use cue::{is_cue_sheet, run_cue};
+pub use diff_results::run_diff_results;
+pub use generate::{Signal, SignalSpec, run_generate};
+pub use impulse::run_impulse;
+pub use ipc::run_ipc;
+pub use lfe::LfeStats;
+#[cfg(feature = "live")]
+pub use live::run_live;
+pub use monitor::{MonitorOptions, parse_period, run_monitor};
+pub use report::{
+    ReportOptions, drop_report_path, is_drop_launch, is_html_report, launched_from_desktop,
+    open_report,
+};
+pub use selftest::run_selftest;
+pub use stats::{StatsOptions, run_stats};
+pub use timeline::{TimelineOptions, run_timeline};
+pub use trend::run_trend;
+pub use tui::run_tui;
+pub use verify_tone::{ToneSpec, run_verify_tone};
+pub use watch::run_watch;
 
-use crate::analysis::{self, FFT_SIZE, powers_to_percentages};
-use crate::audio::{TARGET_SAMPLE_RATE, load_audio};
-use crate::output::get_display_name;
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::{
+    self, AnalysisContext, VerifySummary, nan_as_null, nan_vec_as_null, powers_to_percentages,
+};
+use crate::audio::{self, AudioData, AudioStream, LoadOptions, TARGET_SAMPLE_RATE};
+use crate::output::{get_display_name, print_warning, print_warnings};
 
 /// Stats analysis result for a single file
+#[derive(Clone, Serialize, Deserialize)]
 pub struct FileStats {
     pub name: String,
+    #[serde(rename = "sample_rate")]
     pub original_sample_rate: u32,
     pub channels: u16,
     pub raw_pct: Vec<f64>,
     pub k_pct: Vec<f64>,
+    #[serde(rename = "dynamics_db", with = "nan_vec_as_null")]
     pub dynamics: Vec<f64>,
+    /// Dynamics of the K-weighted band power (NaN when unknown)
+    #[serde(rename = "k_dynamics_db", with = "nan_vec_as_null")]
+    pub k_dynamics: Vec<f64>,
+    /// Standard error of each Raw / K-wt percentage (NaN when unknown)
+    #[serde(with = "nan_vec_as_null")]
+    pub raw_pct_se: Vec<f64>,
+    #[serde(with = "nan_vec_as_null")]
+    pub k_pct_se: Vec<f64>,
+    /// Half-width of the 95% confidence interval of each percentage (NaN when unknown)
+    #[serde(with = "nan_vec_as_null")]
+    pub raw_pct_ci: Vec<f64>,
+    #[serde(with = "nan_vec_as_null")]
+    pub k_pct_ci: Vec<f64>,
+    /// Quantiles of each band's per-frame Raw share (`--match-distributions`; not saved)
+    #[serde(skip)]
+    pub share_quantiles: Vec<Vec<f64>>,
+    /// Share of the Raw energy in % of each `--metric` (NaN without energy; not saved)
+    #[serde(skip)]
+    pub metric_pct: Vec<f64>,
+    /// LFE channel measurements of a surround file (`--lfe`; not saved)
+    #[serde(skip)]
+    pub lfe: Option<LfeStats>,
+    /// Analyzed FFT frames; below `MIN_STABLE_FRAMES` the shares are unstable
+    pub frames: usize,
+    /// BS.1770 integrated loudness in LUFS (NaN when unknown)
+    #[serde(with = "nan_as_null")]
+    pub integrated_lufs: f64,
+    /// Length of the decoded audio in seconds
+    pub duration_secs: f64,
+    /// Seconds left after skipping pauses (`--active-only`)
+    pub active_secs: Option<f64>,
+    /// Energy-conservation checks of the analysis (`--verify`; not saved)
+    #[serde(skip)]
+    pub verify: Option<VerifySummary>,
 }
 
-/// Analyze a single audio file and return its statistics
+/// Analyze a single audio file as it is decoded and return its statistics.
+/// The context is reused across calls so batches plan the FFT only once.
 pub fn analyze_file(
     filename: &str,
     bands: &[analysis::Band],
+    ctx: &mut AnalysisContext,
     show_progress: bool,
+    load_options: &LoadOptions,
 ) -> Result<FileStats, String> {
     let display_name = get_display_name(filename).to_string();
+    let mut started = false;
+    let analyzed = stream_stats(filename, bands, ctx, load_options, |progress| {
+        if show_progress {
+            eprint!("\rAnalyzing {}... {}%", display_name, progress);
+            started = true;
+        }
+    });
+    let (stats, warnings) = analyzed.inspect_err(|_| {
+        if started {
+            eprintln!();
+        }
+    })?;
 
-    let audio = load_audio(filename)?;
+    if show_progress {
+        eprintln!("\rAnalyzing {}... done", display_name);
+    }
+    print_warnings(&warnings);
+    warn_if_unstable(&stats, &display_name);
+    Ok(stats)
+}
+
+/// Compute statistics for audio that has already been decoded
+fn analyze_audio(
+    audio: &AudioData,
+    filename: &str,
+    bands: &[analysis::Band],
+    ctx: &mut AnalysisContext,
+    show_progress: bool,
+) -> FileStats {
+    let display_name = get_display_name(filename).to_string();
 
     if show_progress {
         eprint!("Analyzing {}... 0%", display_name);
     }
 
-    let k_weights = analysis::create_k_weight_table(FFT_SIZE, TARGET_SAMPLE_RATE);
-    let result = analysis::analyze_stats(&audio, bands, &k_weights, |progress| {
+    let stats = compute_stats(audio, filename, bands, ctx, |progress| {
         if show_progress {
             eprint!("\rAnalyzing {}... {}%", display_name, progress);
         }
@@ -46,13 +162,175 @@ pub fn analyze_file(
     if show_progress {
         eprintln!("\rAnalyzing {}... done", display_name);
     }
+    warn_if_unstable(&stats, &display_name);
 
-    Ok(FileStats {
-        name: display_name,
-        original_sample_rate: audio.original_sample_rate,
+    stats
+}
+
+/// Decode a whole file like `audio::load_audio`, printing the warnings of decoding it
+pub(super) fn load_audio(filename: &str, options: &LoadOptions) -> Result<AudioData, String> {
+    let (audio, warnings) = audio::load_audio(filename, options)?;
+    print_warnings(&warnings);
+    Ok(audio)
+}
+
+/// Warn when too little audio was analyzed for stable band shares
+fn warn_if_unstable(stats: &FileStats, display_name: &str) {
+    if stats.frames < analysis::MIN_STABLE_FRAMES {
+        print_warning(&format!(
+            "{}: only {:.1} s analyzed; band shares are unstable below about 10 s (see the 95% confidence intervals)",
+            display_name,
+            stats.active_secs.unwrap_or(stats.duration_secs)
+        ));
+    }
+}
+
+/// Compute statistics, passing the percentage done to `on_progress` as it changes
+pub(super) fn compute_stats<F>(
+    audio: &AudioData,
+    filename: &str,
+    bands: &[analysis::Band],
+    ctx: &mut AnalysisContext,
+    on_progress: F,
+) -> FileStats
+where
+    F: FnMut(u8),
+{
+    let rate = audio.sample_rate as f64;
+    let active = ctx.active_only().then(|| AudioData {
+        samples: analysis::active_samples(&audio.samples, audio.sample_rate),
+        sample_rate: audio.sample_rate,
         channels: audio.channels,
-        raw_pct: powers_to_percentages(&result.raw_powers),
-        k_pct: powers_to_percentages(&result.k_powers),
+        original_sample_rate: audio.original_sample_rate,
+    });
+    let analyzed = active.as_ref().unwrap_or(audio);
+    let result = analysis::analyze_stats(analyzed, bands, ctx, on_progress);
+    file_stats(
+        result,
+        ctx.excluded_bands(),
+        filename,
+        audio.original_sample_rate,
+        audio.channels,
+        audio.samples.len() as f64 / rate,
+        active.map(|a| a.samples.len() as f64 / rate),
+    )
+}
+
+/// Decode and analyze a file a packet at a time, so only a few seconds of its audio are
+/// held in memory, passing the percentage done to `on_progress` as it changes (when the
+/// length is known). `--active-only` needs the whole file to find the program level, so
+/// it decodes the file first. The warnings of decoding the file come back with the
+/// statistics, for the caller to print once its progress line is out of the way.
+pub(super) fn stream_stats<F>(
+    filename: &str,
+    bands: &[analysis::Band],
+    ctx: &mut AnalysisContext,
+    load_options: &LoadOptions,
+    mut on_progress: F,
+) -> Result<(FileStats, Vec<String>), String>
+where
+    F: FnMut(u8),
+{
+    if ctx.active_only() {
+        let (audio, warnings) = audio::load_audio(filename, load_options)?;
+        let stats = compute_stats(&audio, filename, bands, ctx, on_progress);
+        return Ok((stats, warnings));
+    }
+
+    let mut stream = AudioStream::open(filename, load_options)?;
+    let expected = stream.expected_samples();
+    // Without the length from the container, URLs show how much has been downloaded
+    let download = stream.download().filter(|_| expected.is_none());
+    on_progress(0);
+    let mut accumulator = analysis::StatsAccumulator::new(bands, ctx, expected);
+    let mut last_progress: u8 = 0;
+    let decoded = stream.decode_resampled(|samples| {
+        accumulator.push(samples);
+        let analyzed = accumulator.frames() * analysis::HOP_SIZE;
+        let progress = expected
+            .and_then(|n| (analyzed * 100).checked_div(n))
+            .map(|progress| progress.min(100) as u8)
+            .or_else(|| download.as_ref().and_then(|d| d.percent()));
+        if let Some(progress) = progress
+            && progress != last_progress
+        {
+            on_progress(progress);
+            last_progress = progress;
+        }
+    })?;
+
+    let result = accumulator.finish();
+    let stats = file_stats(
+        result,
+        ctx.excluded_bands(),
+        filename,
+        stream.sample_rate,
+        stream.channels,
+        decoded as f64 / TARGET_SAMPLE_RATE as f64,
+        None,
+    );
+    Ok((stats, stream.warnings()))
+}
+
+/// File statistics from an analysis result and the input it came from
+fn file_stats(
+    result: analysis::StatsResult,
+    excluded: &[usize],
+    filename: &str,
+    original_sample_rate: u32,
+    channels: u16,
+    duration_secs: f64,
+    active_secs: Option<f64>,
+) -> FileStats {
+    FileStats {
+        name: get_display_name(filename).to_string(),
+        original_sample_rate,
+        channels,
+        raw_pct: powers_to_percentages(&result.raw_powers, excluded),
+        k_pct: powers_to_percentages(&result.k_powers, excluded),
         dynamics: result.dynamics,
-    })
+        k_dynamics: result.k_dynamics,
+        raw_pct_se: result.raw_pct_se,
+        k_pct_se: result.k_pct_se,
+        raw_pct_ci: result.raw_pct_ci,
+        k_pct_ci: result.k_pct_ci,
+        share_quantiles: result.share_quantiles,
+        metric_pct: result.metric_pct,
+        lfe: None,
+        frames: result.frames,
+        integrated_lufs: result.integrated_lufs,
+        duration_secs,
+        active_secs,
+        verify: result.verify,
+    }
+}
+
+/// Decode a stream incrementally at the target sample rate, calling `on_interval`
+/// with each complete interval (and the final partial one) and its start offset
+/// in samples. Returns the total number of samples decoded.
+fn stream_intervals<F>(
+    stream: &mut AudioStream,
+    samples_per_interval: usize,
+    mut on_interval: F,
+) -> Result<usize, String>
+where
+    F: FnMut(&[f32], usize),
+{
+    let mut pending: Vec<f32> = Vec::with_capacity(samples_per_interval * 2);
+    let mut interval_start = 0usize;
+
+    stream.decode_resampled(|samples| {
+        pending.extend_from_slice(samples);
+        while pending.len() >= samples_per_interval {
+            on_interval(&pending[..samples_per_interval], interval_start);
+            pending.drain(..samples_per_interval);
+            interval_start += samples_per_interval;
+        }
+    })?;
+
+    if !pending.is_empty() {
+        on_interval(&pending, interval_start);
+        interval_start += pending.len();
+    }
+    Ok(interval_start)
 }
@@ -0,0 +1,391 @@
+//! Continuous monitoring mode (timeline rows for live streams, logged as NDJSON)
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::json;
+
+use super::alert::{Alerter, load_rules};
+use super::heatmap::DailyHeatmap;
+use super::stream_intervals;
+use crate::analysis::{
+    AnalysisConfig, AnalysisContext, GROUP_LABELS, VerifySummary, analyze_interval, group_totals,
+    powers_to_percentages,
+};
+use crate::audio::{AudioStream, LoadOptions, TARGET_SAMPLE_RATE};
+use crate::output::{
+    Output, format_time, get_display_name, print_error, print_warning, print_warnings,
+};
+
+/// Monitoring output settings
+pub struct MonitorOptions<'a> {
+    /// Directory receiving the NDJSON log files
+    pub output_dir: &'a str,
+    /// Start a new log file every N seconds (aligned to wall-clock multiples)
+    pub rotate_secs: Option<u64>,
+    /// TOML file of alert rules evaluated on every interval
+    pub alert_rules: Option<&'a str>,
+    /// PNG path for a rolling 24-hour heatmap, refreshed while monitoring
+    pub heatmap_path: Option<&'a str>,
+}
+
+/// Continuously analyze an input, printing one timeline row per interval and
+/// appending the same data as NDJSON records to (optionally rotating) log files
+pub fn run_monitor(
+    input: &str,
+    interval_secs: u32,
+    load_options: &LoadOptions,
+    monitor: &MonitorOptions,
+    config: &AnalysisConfig,
+    out: &Output,
+) {
+    let bands = config.bands();
+    let use_k_weighting = out.weighted;
+    let display_name = get_display_name(input).to_string();
+
+    let mut stream = AudioStream::open(input, load_options).unwrap_or_else(|e| {
+        print_error(&e);
+        std::process::exit(1);
+    });
+
+    let mut log = NdjsonLog::new(monitor.output_dir, monitor.rotate_secs).unwrap_or_else(|e| {
+        print_error(&e);
+        std::process::exit(1);
+    });
+
+    let mut alerter = monitor.alert_rules.map(|path| {
+        Alerter::new(load_rules(path, bands, interval_secs).unwrap_or_else(|e| {
+            print_error(&e);
+            std::process::exit(1);
+        }))
+    });
+
+    // Seed before this run's log file is created so history isn't read twice
+    let mut heatmap = monitor.heatmap_path.map(|path| {
+        DailyHeatmap::new(
+            path,
+            &display_name,
+            monitor.output_dir,
+            config,
+            use_k_weighting,
+            &out.charts,
+            unix_now(),
+        )
+    });
+
+    if !out.quiet {
+        out.print_file_info(
+            &display_name,
+            stream.sample_rate,
+            stream.channels,
+            use_k_weighting,
+        );
+        out.print_bands(bands);
+    }
+    out.print_info(&format!("Logging to: {}", monitor.output_dir));
+
+    let mut analyzer = IntervalAnalyzer::new(config, use_k_weighting);
+    let samples_per_interval = (interval_secs as usize) * (TARGET_SAMPLE_RATE as usize);
+
+    out.print_header(bands, &out.display.time_column("TIME"));
+    out.print_separator(bands, out.display.time_column_width());
+
+    let sample_rate = stream.sample_rate;
+    let total_samples = stream_intervals(&mut stream, samples_per_interval, |interval, start| {
+        let start_secs = start as f64 / TARGET_SAMPLE_RATE as f64;
+        let Some(percentages) = analyzer.analyze(interval) else {
+            // A dead stream holds every band at 0%, which "below" rules are there to catch
+            if let Some(alerter) = alerter.as_mut() {
+                let silent = vec![0.0; bands.len()];
+                alerter.evaluate(&silent, start_secs, &format_utc(unix_now()));
+            }
+            return;
+        };
+        out.print_interval_row(
+            &out.display.timestamp_label(start_secs, sample_rate),
+            &percentages,
+            bands,
+        );
+
+        let now = unix_now();
+        let mut record = interval_record(
+            config,
+            &percentages,
+            now,
+            start_secs,
+            interval.len() as f64 / TARGET_SAMPLE_RATE as f64,
+            use_k_weighting,
+        );
+        out.display.round_json(&mut record);
+        if let Err(e) = log.write(&record) {
+            print_error(&e);
+            std::process::exit(1);
+        }
+
+        if let Some(alerter) = alerter.as_mut() {
+            alerter.evaluate(&percentages, start_secs, &format_utc(now));
+        }
+
+        if let Some(heatmap) = heatmap.as_mut()
+            && let Err(e) = heatmap.push(now, &percentages, bands)
+        {
+            print_warning(&e);
+        }
+    })
+    .unwrap_or_else(|e| {
+        print_error(&e);
+        std::process::exit(1);
+    });
+    print_warnings(&stream.warnings());
+
+    if let Some(alerter) = alerter {
+        alerter.finish();
+    }
+    out.record_verify(analyzer.verify_summary());
+
+    if let Some(mut heatmap) = heatmap
+        && let Err(e) = heatmap.render(unix_now(), bands)
+    {
+        print_warning(&e);
+    }
+
+    out.print_separator(bands, out.display.time_column_width());
+    writeln!(out.sink);
+    let total_secs = total_samples as f32 / TARGET_SAMPLE_RATE as f32;
+    writeln!(out.sink, "Duration: {}", format_time(total_secs));
+}
+
+/// Reusable FFT state for per-interval analysis
+pub(super) struct IntervalAnalyzer<'a> {
+    config: &'a AnalysisConfig,
+    ctx: AnalysisContext,
+    use_k_weighting: bool,
+}
+
+impl<'a> IntervalAnalyzer<'a> {
+    pub(super) fn new(config: &'a AnalysisConfig, use_k_weighting: bool) -> Self {
+        Self {
+            config,
+            ctx: AnalysisContext::new(TARGET_SAMPLE_RATE, config),
+            use_k_weighting,
+        }
+    }
+
+    /// `--verify` checks of the intervals analyzed so far
+    pub(super) fn verify_summary(&self) -> Option<&VerifySummary> {
+        self.ctx.verify_summary()
+    }
+
+    /// Band percentages for one interval, or `None` if it was silent
+    pub(super) fn analyze(&mut self, samples: &[f32]) -> Option<Vec<f64>> {
+        let powers = analyze_interval(
+            samples,
+            &mut self.ctx,
+            self.config.bands(),
+            self.use_k_weighting,
+        );
+        if powers.iter().all(|&p| p == 0.0) {
+            return None;
+        }
+        Some(powers_to_percentages(&powers, self.config.excluded_bands()))
+    }
+}
+
+fn interval_record(
+    config: &AnalysisConfig,
+    percentages: &[f64],
+    now: u64,
+    start_secs: f64,
+    duration_secs: f64,
+    k_weighted: bool,
+) -> serde_json::Value {
+    json!({
+        "timestamp": format_utc(now),
+        "unix_time": now,
+        "stream_time": start_secs,
+        "duration": duration_secs,
+        "weighting": if k_weighted { "k" } else { "raw" },
+        "bands": config
+            .bands()
+            .iter()
+            .zip(percentages)
+            .map(|(band, pct)| json!({ "label": band.label, "pct": pct }))
+            .collect::<Vec<_>>(),
+        "groups": GROUP_LABELS
+            .iter()
+            .zip(group_totals(config.bands(), config.group_edges(), percentages))
+            .map(|(label, pct)| json!({ "label": label, "pct": pct }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// NDJSON log writer that starts a new file at each rotation boundary
+struct NdjsonLog {
+    dir: PathBuf,
+    rotate_secs: Option<u64>,
+    current: Option<(BufWriter<File>, u64)>,
+}
+
+impl NdjsonLog {
+    fn new(dir: &str, rotate_secs: Option<u64>) -> Result<Self, String> {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir, e))?;
+        Ok(Self {
+            dir: Path::new(dir).to_path_buf(),
+            rotate_secs,
+            current: None,
+        })
+    }
+
+    fn write(&mut self, record: &serde_json::Value) -> Result<(), String> {
+        let now = unix_now();
+        let expired = match (&self.current, self.rotate_secs) {
+            (None, _) => true,
+            (Some((_, started)), Some(period)) => now >= started + period,
+            (Some(_), None) => false,
+        };
+
+        if expired {
+            let started = match self.rotate_secs {
+                Some(period) => now - now % period,
+                None => now,
+            };
+            let path = self
+                .dir
+                .join(format!("bandstat-{}.ndjson", format_utc_compact(started)));
+            let file = File::options()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+            self.current = Some((BufWriter::new(file), started));
+        }
+
+        let (writer, _) = self.current.as_mut().expect("log file opened above");
+        writeln!(writer, "{}", record)
+            .and_then(|_| writer.flush())
+            .map_err(|e| format!("Failed to write monitoring log: {}", e))
+    }
+}
+
+pub(super) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Convert Unix seconds to a UTC (year, month, day, hour, minute, second) tuple
+pub(super) fn utc_parts(unix_secs: u64) -> (i64, u32, u32, u32, u32, u32) {
+    let days = (unix_secs / 86400) as i64;
+    let secs_of_day = unix_secs % 86400;
+
+    // Civil-from-days (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (
+        year,
+        month,
+        day,
+        (secs_of_day / 3600) as u32,
+        (secs_of_day % 3600 / 60) as u32,
+        (secs_of_day % 60) as u32,
+    )
+}
+
+/// ISO 8601 UTC timestamp (e.g. 2024-05-01T13:00:00Z)
+pub(super) fn format_utc(unix_secs: u64) -> String {
+    let (y, mo, d, h, mi, s) = utc_parts(unix_secs);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", y, mo, d, h, mi, s)
+}
+
+/// Filename-safe UTC timestamp (e.g. 20240501T130000Z)
+fn format_utc_compact(unix_secs: u64) -> String {
+    let (y, mo, d, h, mi, s) = utc_parts(unix_secs);
+    format!("{:04}{:02}{:02}T{:02}{:02}{:02}Z", y, mo, d, h, mi, s)
+}
+
+/// Parse a rotation period such as `1h`, `30m`, `90s`, or plain seconds
+pub fn parse_period(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let (number, unit) = match s.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+        Some((i, _)) => s.split_at(i),
+        None => (s, "s"),
+    };
+    let value: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{}' (expected e.g. 1h, 30m, 90s)", s))?;
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        _ => {
+            return Err(format!(
+                "invalid duration unit in '{}' (use s, m, h, or d)",
+                s
+            ));
+        }
+    };
+    if value == 0 {
+        return Err("duration must be greater than zero".to_string());
+    }
+    value
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("duration '{}' is too long", s))
+}
+
+/// Shortest `parse_period` spelling of a number of seconds, e.g. `30m` for 1800
+pub(super) fn format_period(secs: u64) -> String {
+    [(86400, "d"), (3600, "h"), (60, "m")]
+        .iter()
+        .find(|(unit, _)| secs.is_multiple_of(*unit))
+        .map_or_else(
+            || format!("{}s", secs),
+            |(unit, suffix)| format!("{}{}", secs / unit, suffix),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_period() {
+        assert_eq!(parse_period("1h").unwrap(), 3600);
+        assert_eq!(parse_period("30m").unwrap(), 1800);
+        assert_eq!(parse_period("90s").unwrap(), 90);
+        assert_eq!(parse_period("120").unwrap(), 120);
+        assert!(parse_period("0h").is_err());
+        assert!(parse_period("1w").is_err());
+        assert!(parse_period("h").is_err());
+        assert!(parse_period("999999999999999999d").is_err());
+    }
+
+    #[test]
+    fn test_format_period() {
+        assert_eq!(format_period(1800), "30m");
+        assert_eq!(format_period(7200), "2h");
+        assert_eq!(format_period(90), "90s");
+        assert_eq!(format_period(86400), "1d");
+        for secs in [45, 5400, 172800] {
+            assert_eq!(parse_period(&format_period(secs)).unwrap(), secs);
+        }
+    }
+
+    #[test]
+    fn test_format_utc() {
+        assert_eq!(format_utc(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_utc(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_utc_compact(1_700_000_000), "20231114T221320Z");
+    }
+}
@@ -0,0 +1,152 @@
+//! Several files analyzed at once (`--jobs`), sharing one progress line on stderr
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, mpsc};
+use std::thread;
+
+use crate::analysis::{AnalysisConfig, AnalysisContext};
+use crate::audio::{LoadOptions, TARGET_SAMPLE_RATE};
+use crate::output::{get_display_name, print_warnings};
+
+use super::{FileStats, analyze_file, stream_stats, warn_if_unstable};
+
+/// Analyze files on up to `jobs` threads. `on_result` gets each file's index and result in
+/// input order, as soon as the file and all before it are done; stderr is free while it runs.
+pub(super) fn analyze_files<F>(
+    filenames: &[String],
+    config: &AnalysisConfig,
+    jobs: usize,
+    show_progress: bool,
+    load_options: &LoadOptions,
+    mut on_result: F,
+) where
+    F: FnMut(usize, Result<FileStats, String>),
+{
+    let bands = config.bands();
+    let jobs = jobs.clamp(1, filenames.len().max(1));
+    if jobs == 1 {
+        let mut ctx = AnalysisContext::new(TARGET_SAMPLE_RATE, config);
+        for (index, filename) in filenames.iter().enumerate() {
+            on_result(
+                index,
+                analyze_file(filename, bands, &mut ctx, show_progress, load_options),
+            );
+        }
+        return;
+    }
+
+    let progress = Mutex::new(Progress {
+        enabled: show_progress,
+        percent: vec![0; filenames.len()],
+        shown: String::new(),
+    });
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            let sender = sender.clone();
+            let (progress, next) = (&progress, &next);
+            scope.spawn(move || {
+                let mut ctx = AnalysisContext::new(TARGET_SAMPLE_RATE, config);
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(filename) = filenames.get(index) else {
+                        return;
+                    };
+                    let result = stream_stats(filename, bands, &mut ctx, load_options, |percent| {
+                        lock(progress).update(index, percent)
+                    });
+                    lock(progress).finish(index, filename, result.as_ref().ok());
+                    let result = result.map(|(stats, _)| stats);
+                    // The receiver only goes away when the results are no longer wanted
+                    if sender.send((index, result)).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        let mut done: Vec<Option<Result<FileStats, String>>> =
+            filenames.iter().map(|_| None).collect();
+        let mut next_result = 0;
+        for (index, result) in receiver {
+            done[index] = Some(result);
+            while let Some(result) = done.get_mut(next_result).and_then(Option::take) {
+                let mut progress = lock(&progress);
+                progress.clear();
+                on_result(next_result, result);
+                progress.draw();
+                next_result += 1;
+            }
+        }
+    });
+    lock(&progress).clear();
+}
+
+fn lock(progress: &Mutex<Progress>) -> MutexGuard<'_, Progress> {
+    progress.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Progress line of all files together, redrawn in place; other stderr output clears it
+/// first and redraws it after
+struct Progress {
+    enabled: bool,
+    /// Percentage done of each file
+    percent: Vec<u8>,
+    /// Line on screen (empty when none)
+    shown: String,
+}
+
+impl Progress {
+    fn update(&mut self, index: usize, percent: u8) {
+        if self.percent[index] != percent {
+            self.percent[index] = percent;
+            self.draw();
+        }
+    }
+
+    /// Report a finished file on its own line, with its decode and stability warnings
+    fn finish(
+        &mut self,
+        index: usize,
+        filename: &str,
+        analyzed: Option<&(FileStats, Vec<String>)>,
+    ) {
+        self.percent[index] = 100;
+        self.clear();
+        let display_name = get_display_name(filename);
+        if let Some((stats, warnings)) = analyzed {
+            if self.enabled {
+                eprintln!("Analyzing {}... done", display_name);
+            }
+            print_warnings(warnings);
+            warn_if_unstable(stats, display_name);
+        }
+        self.draw();
+    }
+
+    fn draw(&mut self) {
+        if !self.enabled {
+            return;
+        }
+        let total: usize = self.percent.iter().map(|&p| p as usize).sum();
+        let line = format!(
+            "Analyzing {} files... {}%",
+            self.percent.len(),
+            total / self.percent.len()
+        );
+        if line != self.shown {
+            eprint!("\r{:<w$}", line, w = self.shown.len());
+            self.shown = line;
+        }
+    }
+
+    fn clear(&mut self) {
+        if !self.shown.is_empty() {
+            eprint!("\r{}\r", " ".repeat(self.shown.len()));
+            self.shown.clear();
+        }
+    }
+}
@@ -0,0 +1,554 @@
+//! Report rendering through user-supplied Handlebars templates, or the built-in
+//! self-contained HTML report with embedded SVG charts
+
+use std::path::Path;
+use std::process::Command;
+
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, RenderContext, RenderError, ScopedJson,
+    handlebars_helper, no_escape,
+};
+use serde_json::{Value, json};
+
+use super::FileStats;
+use super::compare::chart_files;
+use super::lfe::lfe_json;
+use super::monitor::{format_utc, unix_now};
+use super::stats::distribution_chart;
+use crate::analysis::{
+    AnalysisConfig, DYNAMICS_DISPLAY_THRESHOLD_PCT, GROUP_LABELS, difference_score, group_totals,
+    mean_abs_deviation, share_distance, target_deviation_db, typical_share,
+};
+use crate::audio::is_url;
+use crate::chart::{comparison_chart_svg, max_chart_files, stacked_chart_svg};
+use crate::output::{DisplayOptions, Output, format_time, print_warning};
+use bandstat::RESULT_FORMAT;
+
+/// Template report settings
+pub struct ReportOptions<'a> {
+    /// Handlebars template file (the built-in HTML report when unset)
+    pub template: Option<&'a str>,
+    /// Destination file (the report replaces the tables on stdout when unset)
+    pub output: Option<&'a str>,
+}
+
+impl ReportOptions<'_> {
+    /// Whether the report takes the place of the tables on stdout
+    pub(super) fn to_stdout(&self) -> bool {
+        self.output.is_none()
+    }
+}
+
+/// HTML report written when files are dropped onto the binary
+const BUILTIN_HTML_TEMPLATE: &str = include_str!("report.html.hbs");
+
+handlebars_helper!(fixed: |value: f64, digits: u64| format!("{:.*}", digits as usize, value));
+handlebars_helper!(time: |secs: f64| format_time(secs as f32).trim().to_string());
+
+/// `round` helper: a value with the decimal places of the tables (`--decimals`)
+struct Round(usize);
+
+impl HelperDef for Round {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'reg, 'rc>, RenderError> {
+        let value = h
+            .param(0)
+            .and_then(|p| p.value().as_f64())
+            .ok_or_else(|| RenderError::new("Invalid param type for helper \"round\""))?;
+        Ok(ScopedJson::Derived(json!(format!("{:.*}", self.0, value))))
+    }
+}
+
+/// Render the analysis results through the template and write them out
+pub(super) fn write_report(
+    options: &ReportOptions,
+    config: &AnalysisConfig,
+    stats: &[FileStats],
+    k_weighted: bool,
+    highpass_hz: Option<f64>,
+    out: &Output,
+) -> Result<(), String> {
+    let mut context = report_context(
+        config,
+        stats,
+        k_weighted,
+        highpass_hz,
+        &out.display,
+        unix_now(),
+    );
+    if options.template.is_none() {
+        let bands = config.bands();
+        let chart = if stats.len() == 1 {
+            let s = &stats[0];
+            let pct = if k_weighted { &s.k_pct } else { &s.raw_pct };
+            let (data, title) = distribution_chart(&s.name, pct, config, k_weighted);
+            Some(stacked_chart_svg(&data, bands, &title, &out.charts))
+        } else {
+            (stats.len() <= max_chart_files()).then(|| {
+                comparison_chart_svg(&chart_files(stats), bands, &config.weighting, &out.charts)
+            })
+        };
+        context["charts"] = embedded_charts(chart);
+    }
+    render_report(options, &context, out)
+}
+
+/// Write the built-in HTML report of a timeline, given the document `--format json`
+/// prints and the rendered timeline chart
+pub(super) fn write_timeline_report(
+    options: &ReportOptions,
+    mut document: Value,
+    chart: Result<String, String>,
+    out: &Output,
+) -> Result<(), String> {
+    document["charts"] = embedded_charts(Some(chart));
+    render_report(options, &document, out)
+}
+
+/// Charts for the built-in report; one that fails to render is left out with a warning
+fn embedded_charts(chart: Option<Result<String, String>>) -> Value {
+    match chart {
+        Some(Ok(svg)) => json!([{ "svg": svg }]),
+        Some(Err(e)) => {
+            print_warning(&format!("Chart left out of the report: {}", e));
+            json!([])
+        }
+        None => json!([]),
+    }
+}
+
+/// Handlebars registry with the report helpers; only HTML output is entity-escaped
+fn registry(html: bool, decimals: usize) -> Handlebars<'static> {
+    let mut registry = Handlebars::new();
+    registry.set_strict_mode(true);
+    registry.register_helper("fixed", Box::new(fixed));
+    registry.register_helper("round", Box::new(Round(decimals)));
+    registry.register_helper("time", Box::new(time));
+    // Only HTML reports need entity escaping; Markdown and text are written verbatim
+    if !html {
+        registry.register_escape_fn(no_escape);
+    }
+    registry
+}
+
+/// Render a report context through the configured template and write it out
+fn render_report(options: &ReportOptions, context: &Value, out: &Output) -> Result<(), String> {
+    let (template, template_name) = match options.template {
+        Some(path) => (
+            std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?,
+            path,
+        ),
+        None => (BUILTIN_HTML_TEMPLATE.to_string(), "report.html.hbs"),
+    };
+
+    let rendered = registry(
+        is_html(options.output.unwrap_or(template_name)),
+        out.display.decimals,
+    )
+    .render_template(&template, context)
+    .map_err(|e| format!("{}: {}", template_name, e))?;
+
+    match options.output {
+        Some(path) => {
+            std::fs::write(path, rendered).map_err(|e| format!("{}: {}", path, e))?;
+            eprintln!("Report saved to: {}", path);
+        }
+        None => write!(out.sink, "{}", rendered),
+    }
+    Ok(())
+}
+
+/// Whether bandstat was started by dropping files onto it (or through a file
+/// association): nothing but input paths as arguments, from the desktop rather than a shell
+pub fn is_drop_launch(args: &[String], from_desktop: bool) -> bool {
+    from_desktop && !args.is_empty() && args.iter().all(|a| !a.starts_with('-') && !is_url(a))
+}
+
+/// Whether Explorer started bandstat: a console program run from a shell shares the
+/// shell's console, while one run from Explorer gets a console of its own
+#[cfg(windows)]
+pub fn launched_from_desktop() -> bool {
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetConsoleProcessList(process_ids: *mut u32, count: u32) -> u32;
+    }
+    let mut process_ids = [0u32; 2];
+    // SAFETY: the buffer holds as many process ids as the count passed
+    let attached = unsafe { GetConsoleProcessList(process_ids.as_mut_ptr(), 2) };
+    attached == 1
+}
+
+/// Whether Finder started bandstat: it only opens files with an app bundle (a bare binary
+/// runs in Terminal), so bandstat must run from one, without a terminal
+#[cfg(target_os = "macos")]
+pub fn launched_from_desktop() -> bool {
+    use std::io::IsTerminal;
+
+    let in_bundle = std::env::current_exe()
+        .is_ok_and(|exe| exe.to_string_lossy().contains(".app/Contents/MacOS/"));
+    in_bundle
+        && !std::io::stdin().is_terminal()
+        && !std::io::stdout().is_terminal()
+        && !std::io::stderr().is_terminal()
+}
+
+/// Desktops elsewhere run command line tools in a terminal, so files are never dropped
+#[cfg(not(any(windows, target_os = "macos")))]
+pub fn launched_from_desktop() -> bool {
+    false
+}
+
+/// Whether `--report` names an HTML file to write rather than a template to read
+pub fn is_html_report(path: &str) -> bool {
+    let path = path.to_ascii_lowercase();
+    path.ends_with(".html") || path.ends_with(".htm")
+}
+
+/// Report path for a drop launch: `<first input>.bandstat.html` next to the first input
+pub fn drop_report_path(files: &[String]) -> String {
+    let first = Path::new(&files[0]);
+    let stem = first
+        .file_stem()
+        .map(|s| s.to_string_lossy())
+        .unwrap_or_default();
+    first
+        .with_file_name(format!("{}.bandstat.html", stem))
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Open a file with the platform's default application
+pub fn open_report(path: &str) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    let mut command = Command::new("open");
+    // Explorer takes the path as one argument, where `cmd /C start` would re-parse
+    // characters such as `&` in it
+    #[cfg(windows)]
+    let mut command = Command::new("explorer");
+    #[cfg(not(any(target_os = "macos", windows)))]
+    let mut command = Command::new("xdg-open");
+
+    command
+        .arg(path)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open {}: {}", path, e))
+}
+
+/// Whether a path names an HTML document (optionally with a template extension)
+fn is_html(path: &str) -> bool {
+    let path = path.to_ascii_lowercase();
+    let path = path
+        .strip_suffix(".hbs")
+        .or_else(|| path.strip_suffix(".handlebars"))
+        .unwrap_or(&path);
+    path.ends_with(".html") || path.ends_with(".htm")
+}
+
+/// Print the analysis results as a JSON document (`--format json`)
+pub(super) fn print_json(
+    config: &AnalysisConfig,
+    stats: &[FileStats],
+    k_weighted: bool,
+    highpass_hz: Option<f64>,
+    out: &Output,
+) {
+    let mut document = report_context(
+        config,
+        stats,
+        k_weighted,
+        highpass_hz,
+        &out.display,
+        unix_now(),
+    );
+    out.display.round_json(&mut document);
+    writeln!(out.sink, "{:#}", document);
+}
+
+/// A value for JSON, or null when it is NaN or infinite
+pub(super) fn finite(value: f64) -> Option<f64> {
+    value.is_finite().then_some(value)
+}
+
+/// Data exposed to report templates (and returned by IPC analysis requests)
+pub(super) fn report_context(
+    config: &AnalysisConfig,
+    stats: &[FileStats],
+    k_weighted: bool,
+    highpass_hz: Option<f64>,
+    display: &DisplayOptions,
+    now: u64,
+) -> Value {
+    let bands = config.bands();
+    let base = &stats[0];
+    let target = config
+        .target_curve
+        .as_ref()
+        .map(|curve| curve.band_shares(config));
+    let files: Vec<Value> = stats
+        .iter()
+        .zip('A'..='Z')
+        .map(|(s, label)| {
+            let deviation = target
+                .as_ref()
+                .map(|target| target_deviation_db(&s.raw_pct, target));
+            let file_bands: Vec<Value> = bands
+                .iter()
+                .enumerate()
+                .map(|(i, b)| {
+                    let dynamics = (s.raw_pct[i] >= DYNAMICS_DISPLAY_THRESHOLD_PCT)
+                        .then(|| finite(s.dynamics[i]))
+                        .flatten();
+                    let k_dynamics = (s.k_pct[i] >= DYNAMICS_DISPLAY_THRESHOLD_PCT)
+                        .then(|| finite(s.k_dynamics[i]))
+                        .flatten();
+                    let mut band = json!({
+                        "label": b.label,
+                        "raw_pct": s.raw_pct[i],
+                        "k_pct": s.k_pct[i],
+                        "raw_pct_se": finite(s.raw_pct_se[i]),
+                        "k_pct_se": finite(s.k_pct_se[i]),
+                        "raw_pct_ci": finite(s.raw_pct_ci[i]),
+                        "k_pct_ci": finite(s.k_pct_ci[i]),
+                        "raw_diff": s.raw_pct[i] - base.raw_pct[i],
+                        "k_diff": s.k_pct[i] - base.k_pct[i],
+                        "dynamics_db": dynamics,
+                        "k_dynamics_db": k_dynamics,
+                    });
+                    if display.match_distributions
+                        && let (Some(quantiles), Some(base_quantiles)) =
+                            (s.share_quantiles.get(i), base.share_quantiles.get(i))
+                    {
+                        let typical = typical_share(quantiles);
+                        band["frame_share_pct"] = json!(finite(typical));
+                        band["frame_share_diff"] =
+                            json!(finite(typical - typical_share(base_quantiles)));
+                        band["frame_share_emd"] =
+                            json!(finite(share_distance(base_quantiles, quantiles)));
+                    }
+                    if let (Some(target), Some(deviation)) = (&target, &deviation) {
+                        band["target_pct"] = json!(finite(target[i]));
+                        band["target_dev_db"] = json!(finite(deviation[i]));
+                    }
+                    if config.excluded_bands().contains(&i) {
+                        band["excluded"] = json!(true);
+                    }
+                    band
+                })
+                .collect();
+            let (raw_groups, k_groups) = (
+                group_totals(bands, config.group_edges(), &s.raw_pct),
+                group_totals(bands, config.group_edges(), &s.k_pct),
+            );
+            let score = difference_score(bands, &base.raw_pct, &s.raw_pct);
+            let file_groups: Vec<Value> = GROUP_LABELS
+                .iter()
+                .enumerate()
+                .map(|(g, label)| {
+                    json!({
+                        "label": label,
+                        "raw_pct": raw_groups[g],
+                        "k_pct": k_groups[g],
+                    })
+                })
+                .collect();
+            let mut file = json!({
+                "label": label.to_string(),
+                "name": s.name,
+                "sample_rate": s.original_sample_rate,
+                "channels": s.channels,
+                "duration_secs": s.duration_secs,
+                "active_secs": s.active_secs,
+                "frames": s.frames,
+                "integrated_lufs": finite(s.integrated_lufs),
+                "groups": file_groups,
+                "flat_diff": score.flat,
+                "weighted_diff": score.weighted,
+                "bands": file_bands,
+            });
+            if !config.metrics.is_empty() {
+                let file_metrics: Vec<Value> = config
+                    .metrics
+                    .iter()
+                    .enumerate()
+                    .map(|(m, metric)| {
+                        let pct = s.metric_pct.get(m).copied().unwrap_or(f64::NAN);
+                        let base_pct = base.metric_pct.get(m).copied().unwrap_or(f64::NAN);
+                        json!({
+                            "metric": metric.spec(),
+                            "raw_pct": finite(pct),
+                            "raw_diff": finite(pct - base_pct),
+                        })
+                    })
+                    .collect();
+                file["metrics"] = json!(file_metrics);
+            }
+            if let Some(lfe) = &s.lfe {
+                file["lfe"] = lfe_json(lfe);
+            }
+            if let Some(deviation) = &deviation {
+                file["target_mean_dev_db"] = json!(finite(mean_abs_deviation(deviation)));
+            }
+            file
+        })
+        .collect();
+
+    let mut context = json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "format": RESULT_FORMAT,
+        "generated": format_utc(now),
+        "mode": if stats.len() > 1 { "compare" } else { "stats" },
+        "k_weighted": k_weighted,
+        "bands": bands,
+        "files": files,
+    });
+    if let Some(curve) = &config.target_curve {
+        context["target_curve"] = json!(curve.name);
+    }
+    if let Some(hz) = highpass_hz {
+        context["highpass_hz"] = json!(hz);
+    }
+    context
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::default_bands;
+
+    fn file(name: &str, raw: f64) -> FileStats {
+        let bands = default_bands().len();
+        FileStats {
+            name: name.to_string(),
+            original_sample_rate: 44100,
+            channels: 2,
+            raw_pct: vec![raw; bands],
+            k_pct: vec![raw; bands],
+            dynamics: vec![3.0; bands],
+            k_dynamics: vec![2.0; bands],
+            raw_pct_se: vec![0.1; bands],
+            k_pct_se: vec![0.1; bands],
+            raw_pct_ci: vec![0.2; bands],
+            k_pct_ci: vec![0.2; bands],
+            share_quantiles: Vec::new(),
+            metric_pct: Vec::new(),
+            lfe: None,
+            frames: 1400,
+            integrated_lufs: -14.0,
+            duration_secs: 60.0,
+            active_secs: None,
+            verify: None,
+        }
+    }
+
+    #[test]
+    fn test_report_context_fields() {
+        let config = AnalysisConfig::default();
+        let bands = config.bands();
+        let ctx = report_context(
+            &config,
+            &[file("a.wav", 0.2), file("b.wav", 7.0)],
+            false,
+            None,
+            &DisplayOptions::default(),
+            0,
+        );
+
+        assert_eq!(ctx["mode"], "compare");
+        assert_eq!(ctx["generated"], "1970-01-01T00:00:00Z");
+        assert_eq!(ctx["bands"][0]["label"], "DC");
+        assert!(ctx["bands"][bands.len() - 1]["high_hz"].is_null());
+        assert_eq!(ctx["files"][1]["label"], "B");
+        assert_eq!(ctx["files"][1]["bands"][2]["raw_diff"], 6.8);
+        assert_eq!(ctx["files"][1]["groups"][0]["label"], "LOW");
+        assert_eq!(ctx["files"][1]["groups"][0]["raw_pct"], 35.0);
+        // Dynamics are hidden for bands below the display threshold, as in the tables
+        assert!(ctx["files"][0]["bands"][0]["dynamics_db"].is_null());
+        assert_eq!(ctx["files"][1]["bands"][0]["dynamics_db"], 3.0);
+        assert_eq!(ctx["files"][1]["bands"][0]["k_dynamics_db"], 2.0);
+    }
+
+    #[test]
+    fn test_builtin_html_report() {
+        let mut ctx = report_context(
+            &AnalysisConfig::default(),
+            &[file("a.wav", 0.2), file("b.wav", 7.0)],
+            false,
+            None,
+            &DisplayOptions::default(),
+            0,
+        );
+        ctx["charts"] = json!([{ "svg": "<svg id=\"chart\"></svg>" }]);
+        let html = registry(true, 1)
+            .render_template(BUILTIN_HTML_TEMPLATE, &ctx)
+            .unwrap();
+
+        assert!(html.contains("<h2>[B] b.wav</h2>"));
+        // Diff columns only for files compared against [A]
+        assert_eq!(html.matches("<th>Raw diff</th>").count(), 1);
+        assert!(html.contains("<td>6.8</td>"));
+        // Charts are embedded as markup, not escaped
+        assert!(html.contains("<svg id=\"chart\"></svg>"));
+    }
+
+    #[test]
+    fn test_builtin_html_timeline_report() {
+        let distribution = json!({
+            "bands": [{ "label": "SUB", "pct": 12.5 }],
+            "groups": [{ "label": "LOW", "pct": 12.5 }],
+        });
+        let mut interval = distribution.clone();
+        interval["start_secs"] = json!(65.0);
+        let ctx = json!({
+            "version": "0",
+            "generated": "now",
+            "mode": "timeline",
+            "k_weighted": false,
+            "bands": [{ "label": "SUB", "low_hz": 20.0, "high_hz": 60.0 }],
+            "file": { "name": "a.wav", "sample_rate": 48000, "channels": 2 },
+            "interval_secs": 1,
+            "intervals": [interval],
+            "average": distribution,
+            "duration_secs": 66.0,
+            "charts": [],
+        });
+        let html = registry(true, 1)
+            .render_template(BUILTIN_HTML_TEMPLATE, &ctx)
+            .unwrap();
+
+        assert!(html.contains("<title>bandstat: a.wav</title>"));
+        assert!(html.contains("<tr><td>01:05</td><td>12.5</td></tr>"));
+    }
+
+    #[test]
+    fn test_drop_launch_detection() {
+        let files = vec!["a.wav".to_string(), "dir/b.flac".to_string()];
+        assert!(is_drop_launch(&files, true));
+        assert!(!is_drop_launch(&files, false));
+        assert!(!is_drop_launch(&[], true));
+        assert!(!is_drop_launch(
+            &["-q".to_string(), "a.wav".to_string()],
+            true
+        ));
+        assert!(!is_drop_launch(&["https://host/a.mp3".to_string()], true));
+
+        assert_eq!(drop_report_path(&files), "a.bandstat.html");
+        assert_eq!(
+            drop_report_path(&["mixes/v3.wav".to_string()]),
+            "mixes/v3.bandstat.html"
+        );
+    }
+
+    #[test]
+    fn test_is_html() {
+        assert!(is_html("report.html"));
+        assert!(is_html("Delivery.HTML.hbs"));
+        assert!(!is_html("report.md"));
+        assert!(!is_html("template.hbs"));
+    }
+}
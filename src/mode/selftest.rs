@@ -0,0 +1,167 @@
+//! Self-test mode (runs the analysis pipeline on internally generated signals)
+
+use std::f32::consts::PI;
+
+use colored::*;
+
+use crate::analysis::{
+    self, AnalysisConfig, AnalysisContext, k_weight_gain_db, powers_to_percentages,
+};
+use crate::audio::{AudioData, TARGET_SAMPLE_RATE};
+
+use super::generate::{pink_noise, test_frequency};
+
+/// Minimum share of power a pure tone must place in its own band
+const PLACEMENT_THRESHOLD_PCT: f64 = 90.0;
+
+/// Allowed deviation of K-weighting gain from the reference response (dB)
+const K_WEIGHT_TOLERANCE_DB: f64 = 0.5;
+
+/// Allowed deviation of the percentage sum from 100%
+const SUM_TOLERANCE_PCT: f64 = 1e-6;
+
+/// Test signal duration in seconds
+const SIGNAL_SECS: f32 = 3.0;
+
+/// Reference K-weighting response at canonical frequencies (Hz, dB), per ITU-R BS.1770-4
+const K_WEIGHT_REFERENCE: [(f64, f64); 5] = [
+    (20.0, -13.3),
+    (100.0, -1.1),
+    (1000.0, 0.7),
+    (4000.0, 4.0),
+    (10000.0, 4.0),
+];
+
+/// Run all self-test checks and return whether every check passed
+pub fn run_selftest(quiet: bool) -> bool {
+    let config = AnalysisConfig::default();
+    let bands = config.bands();
+    let mut ctx = AnalysisContext::new(TARGET_SAMPLE_RATE, &config);
+    let mut report = Report::new(quiet);
+
+    println!("[Band Placement]");
+    for (band_idx, band) in bands.iter().enumerate() {
+        let freq = test_frequency(band);
+        let audio = to_audio(sine(freq));
+        let result = analysis::analyze_stats(&audio, bands, &mut ctx, |_| {});
+        let pct = powers_to_percentages(&result.raw_powers, &[])[band_idx];
+        report.check(
+            pct >= PLACEMENT_THRESHOLD_PCT,
+            &format!(
+                "{:>4} {:>7.0} Hz sine: {:5.1}% in band",
+                band.label, freq, pct
+            ),
+        );
+    }
+
+    println!();
+    println!("[K-weighting Response]");
+    for (freq, expected_db) in K_WEIGHT_REFERENCE {
+        let gain_db = k_weight_gain_db(freq, TARGET_SAMPLE_RATE);
+        report.check(
+            (gain_db - expected_db).abs() <= K_WEIGHT_TOLERANCE_DB,
+            &format!(
+                "{:>7.0} Hz: {:+6.2} dB (expected {:+.1} dB)",
+                freq, gain_db, expected_db
+            ),
+        );
+    }
+
+    println!();
+    println!("[Pink Noise]");
+    let audio = to_audio(pink_noise(signal_len(), 0x5EED));
+    let result = analysis::analyze_stats(&audio, bands, &mut ctx, |_| {});
+    let raw_pct = powers_to_percentages(&result.raw_powers, &[]);
+    let k_pct = powers_to_percentages(&result.k_powers, &[]);
+
+    for (name, pct) in [("Raw", &raw_pct), ("K-wt", &k_pct)] {
+        let sum: f64 = pct.iter().sum();
+        report.check(
+            (sum - 100.0).abs() <= SUM_TOLERANCE_PCT,
+            &format!("{} percentages sum to {:.4}%", name, sum),
+        );
+    }
+
+    // Pink noise has equal power per octave, so no single band should dominate
+    let max_raw = raw_pct.iter().copied().fold(0.0, f64::max);
+    report.check(
+        max_raw < 50.0,
+        &format!("Raw power spread across bands (max {:.1}%)", max_raw),
+    );
+
+    // K-weighting attenuates lows and boosts highs
+    let low_bands = bands.iter().take_while(|b| b.high_hz <= 250.0).count();
+    let raw_low: f64 = raw_pct[..low_bands].iter().sum();
+    let k_low: f64 = k_pct[..low_bands].iter().sum();
+    report.check(
+        k_low < raw_low,
+        &format!(
+            "K-weighting reduces low bands ({:.1}% -> {:.1}%)",
+            raw_low, k_low
+        ),
+    );
+
+    println!();
+    report.summary()
+}
+
+fn to_audio(samples: Vec<f32>) -> AudioData {
+    AudioData {
+        samples,
+        sample_rate: TARGET_SAMPLE_RATE,
+        channels: 1,
+        original_sample_rate: TARGET_SAMPLE_RATE,
+    }
+}
+
+fn signal_len() -> usize {
+    (SIGNAL_SECS * TARGET_SAMPLE_RATE as f32) as usize
+}
+
+fn sine(freq: f32) -> Vec<f32> {
+    (0..signal_len())
+        .map(|i| 0.5 * (2.0 * PI * freq * i as f32 / TARGET_SAMPLE_RATE as f32).sin())
+        .collect()
+}
+
+/// Collects pass/fail results and prints one line per check
+struct Report {
+    quiet: bool,
+    passed: usize,
+    failed: usize,
+}
+
+impl Report {
+    fn new(quiet: bool) -> Self {
+        Self {
+            quiet,
+            passed: 0,
+            failed: 0,
+        }
+    }
+
+    fn check(&mut self, ok: bool, description: &str) {
+        if ok {
+            self.passed += 1;
+            if !self.quiet {
+                println!("  {} {}", "PASS".green(), description);
+            }
+        } else {
+            self.failed += 1;
+            println!("  {} {}", "FAIL".red().bold(), description);
+        }
+    }
+
+    fn summary(&self) -> bool {
+        let total = self.passed + self.failed;
+        if self.failed == 0 {
+            println!("Self-test passed ({}/{} checks)", self.passed, total);
+        } else {
+            println!(
+                "Self-test failed ({} of {} checks failed)",
+                self.failed, total
+            );
+        }
+        self.failed == 0
+    }
+}
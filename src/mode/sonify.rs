@@ -0,0 +1,138 @@
+//! Sonification of band differences (noise shaped by the B-A balance)
+
+use std::f64::consts::PI;
+
+use rustfft::FftPlanner;
+use rustfft::num_complex::Complex;
+
+use crate::analysis::Band;
+use crate::audio::{ExportFormat, TARGET_SAMPLE_RATE, write_wav};
+
+/// Length of the rendered preview in seconds
+const PREVIEW_SECS: usize = 6;
+
+/// Band gains are limited to this many dB so near-empty bands don't dominate
+const MAX_GAIN_DB: f64 = 24.0;
+
+/// Peak sample level of the rendered preview (about -1 dBFS)
+const PEAK_LEVEL: f32 = 0.89;
+
+/// Fade-in/out length in seconds (avoids clicks at the edges)
+const FADE_SECS: f32 = 0.05;
+
+/// Per-band gain in dB: how much more (or less) of the total each band holds in B than in A
+/// (0 dB for excluded bands, which have no share)
+pub(crate) fn band_gains_db(base_pct: &[f64], other_pct: &[f64]) -> Vec<f64> {
+    base_pct
+        .iter()
+        .zip(other_pct)
+        .map(|(&a, &b)| {
+            if (a <= 0.0 && b <= 0.0) || a.is_nan() || b.is_nan() {
+                0.0
+            } else {
+                (10.0 * (b / a).log10()).clamp(-MAX_GAIN_DB, MAX_GAIN_DB)
+            }
+        })
+        .collect()
+}
+
+/// Synthesize pink noise with each band scaled by its gain, so equal
+/// distributions sound neutral and boosted bands stand out
+pub(crate) fn synthesize(gains_db: &[f64], bands: &[Band], sample_rate: u32) -> Vec<f32> {
+    let n = PREVIEW_SECS * sample_rate as usize;
+    let freq_per_bin = sample_rate as f64 / n as f64;
+    let mut spectrum = vec![Complex::<f64>::default(); n];
+
+    let mut state = 0x9E37_79B9_7F4A_7C15u64;
+    for bin in 1..n / 2 {
+        let freq = freq_per_bin * bin as f64;
+        let Some(band) = bands
+            .iter()
+            .position(|b| freq >= b.low_hz as f64 && freq < b.high_hz as f64)
+        else {
+            continue;
+        };
+
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let phase = 2.0 * PI * (state as f64 / u64::MAX as f64);
+
+        // 1/sqrt(f) amplitude gives equal power per octave
+        let amplitude = 10f64.powf(gains_db[band] / 20.0) / freq.sqrt();
+        spectrum[bin] = Complex::from_polar(amplitude, phase);
+        spectrum[n - bin] = spectrum[bin].conj();
+    }
+
+    FftPlanner::new().plan_fft_inverse(n).process(&mut spectrum);
+
+    let peak = spectrum.iter().map(|c| c.re.abs()).fold(0.0, f64::max);
+    let scale = if peak > 0.0 {
+        PEAK_LEVEL as f64 / peak
+    } else {
+        0.0
+    };
+    let fade = (FADE_SECS * sample_rate as f32) as usize;
+
+    spectrum
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let edge = i.min(n - 1 - i);
+            let ramp = (edge as f32 / fade as f32).min(1.0);
+            (c.re * scale) as f32 * ramp
+        })
+        .collect()
+}
+
+/// Render the B-A difference of two band distributions to a WAV file
+pub(crate) fn write_diff_preview(
+    base_pct: &[f64],
+    other_pct: &[f64],
+    bands: &[Band],
+    path: &str,
+    format: ExportFormat,
+) -> Result<(), String> {
+    let gains = band_gains_db(base_pct, other_pct);
+    let samples = synthesize(&gains, bands, TARGET_SAMPLE_RATE);
+    write_wav(path, &samples, TARGET_SAMPLE_RATE, format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{
+        AnalysisConfig, AnalysisContext, analyze_interval, powers_to_percentages,
+    };
+
+    #[test]
+    fn test_band_gains_follow_difference() {
+        let gains = band_gains_db(&[10.0, 10.0, 10.0, 0.0, 0.0], &[20.0, 10.0, 5.0, 1.0, 0.0]);
+        assert!((gains[0] - 3.0103).abs() < 1e-3);
+        assert_eq!(gains[1], 0.0);
+        assert!((gains[2] + 3.0103).abs() < 1e-3);
+        assert_eq!(gains[3], MAX_GAIN_DB);
+        assert_eq!(gains[4], 0.0);
+    }
+
+    #[test]
+    fn test_boosted_band_is_louder() {
+        let config = AnalysisConfig::default();
+        let bands = config.bands();
+        let mut ctx = AnalysisContext::new(TARGET_SAMPLE_RATE, &config);
+        let mut share = |gains: &[f64]| {
+            let samples = synthesize(gains, bands, TARGET_SAMPLE_RATE);
+            let peak = samples.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+            assert!(peak <= PEAK_LEVEL + 1e-6);
+            powers_to_percentages(&analyze_interval(&samples, &mut ctx, bands, false), &[])
+        };
+
+        let neutral = share(&vec![0.0; bands.len()]);
+        let mut gains = vec![0.0; bands.len()];
+        gains[6] = 12.0;
+        let boosted = share(&gains);
+
+        assert!(boosted[6] > neutral[6] * 5.0);
+        assert!(boosted[3] < neutral[3]);
+    }
+}
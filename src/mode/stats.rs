@@ -1,82 +1,328 @@
 //! Single file stats analysis mode
 
-use crate::analysis::{DYNAMICS_DISPLAY_THRESHOLD_PCT, get_bands};
-use crate::chart;
-use crate::output::{
-    print_bands, print_diff_row, print_error, print_file_info, print_header, print_legend,
-    print_row, print_row_masked, print_separator,
+use super::load_audio;
+use crate::analysis::{
+    AnalysisConfig, AnalysisContext, Band, DYNAMICS_DISPLAY_THRESHOLD_PCT, MIN_STABLE_FRAMES,
+    covered_shares, group_totals, mean_abs_deviation, target_deviation_db,
 };
+use crate::audio::{AudioData, LoadOptions, TARGET_SAMPLE_RATE, load_audio_with_lfe};
+use crate::chart::{self, ChartKind, ChartOptions, ImageSpec};
+use crate::output::{Output, format_active, metric_label, print_error, print_warnings};
 
-use super::{FileStats, analyze_file};
+use super::lfe::{measure_lfe, print_lfe};
+use super::report::{ReportOptions, print_json, write_report};
+use super::timeline::{TimelineSeries, render_loudness_chart};
+use super::{FileStats, analyze_audio, analyze_file};
+
+/// Single-file output settings
+pub struct StatsOptions<'a> {
+    /// Interval of timeline charts in seconds
+    pub interval_secs: u32,
+    /// Charts rendered after the analysis
+    pub images: &'a [ImageSpec],
+    /// Template or HTML report of the result
+    pub report: Option<&'a ReportOptions<'a>>,
+    /// Measure the LFE channel of surround files (`--lfe`)
+    pub lfe: bool,
+}
 
 /// Run single file stats analysis
-pub fn run_stats(filename: &str, use_k_weighting: bool, quiet: bool, image_path: Option<&str>) {
-    let bands = get_bands();
-    let stats: FileStats = analyze_file(filename, &bands, !quiet).unwrap_or_else(|e| {
+pub fn run_stats(
+    filename: &str,
+    load_options: &LoadOptions,
+    options: &StatsOptions,
+    config: &AnalysisConfig,
+    out: &Output,
+) {
+    let StatsOptions {
+        interval_secs,
+        images,
+        report,
+        lfe,
+    } = *options;
+    let use_k_weighting = out.weighted;
+    let quiet = out.quiet;
+    let bands = config.bands();
+    let mut ctx = AnalysisContext::new(TARGET_SAMPLE_RATE, config);
+    // Loudness charts and --lfe need the decoded audio, and so do timeline charts except
+    // under --max-memory, which decodes the file a second time for them; otherwise the file
+    // is analyzed as it is decoded
+    let keep_audio = lfe
+        || images
+            .iter()
+            .any(|image| match image.kind_or(ChartKind::Stats) {
+                ChartKind::Loudness => true,
+                ChartKind::Timeline => load_options.max_memory_mb.is_none(),
+                _ => false,
+            });
+    let analyzed = if keep_audio {
+        analyze_loaded(filename, bands, &mut ctx, quiet, load_options, lfe)
+            .map(|(stats, audio)| (stats, Some(audio)))
+    } else {
+        analyze_file(filename, bands, &mut ctx, !quiet, load_options).map(|stats| (stats, None))
+    };
+    let (stats, audio) = analyzed.unwrap_or_else(|e| {
         print_error(&e);
         std::process::exit(1);
     });
 
-    if !quiet {
-        println!();
-        println!("Stats Analysis");
-        print_file_info(
+    if let Some(report) = report
+        && let Err(e) = write_report(
+            report,
+            config,
+            std::slice::from_ref(&stats),
+            use_k_weighting,
+            load_options.highpass_hz,
+            out,
+        )
+    {
+        print_error(&e);
+        std::process::exit(1);
+    }
+
+    if out.display.json_output() {
+        print_json(
+            config,
+            std::slice::from_ref(&stats),
+            use_k_weighting,
+            load_options.highpass_hz,
+            out,
+        );
+    } else if !report.is_some_and(ReportOptions::to_stdout) {
+        print_tables(&stats, config, out);
+    }
+
+    // Output charts if requested, reusing the decoded audio (if kept) for timeline charts
+    let mut series = None;
+    for image in images {
+        let pct_data = if use_k_weighting {
+            &stats.k_pct
+        } else {
+            &stats.raw_pct
+        };
+        match image.kind_or(ChartKind::Stats) {
+            ChartKind::Timeline => {
+                let series = series.get_or_insert_with(|| match &audio {
+                    Some(audio) => Ok(TimelineSeries::from_samples(
+                        audio,
+                        &mut ctx,
+                        config,
+                        interval_secs,
+                        use_k_weighting,
+                    )),
+                    None => TimelineSeries::from_stream(
+                        filename,
+                        load_options,
+                        &mut ctx,
+                        config,
+                        interval_secs,
+                        use_k_weighting,
+                    ),
+                });
+                match series {
+                    Ok(series) => {
+                        series.render(&stats.name, config, use_k_weighting, &image.path, out)
+                    }
+                    Err(e) => print_error(e),
+                }
+            }
+            ChartKind::Loudness => {
+                let audio = audio
+                    .as_ref()
+                    .expect("the audio is kept for loudness charts");
+                render_loudness_chart(&stats.name, audio, &mut ctx, config, &image.path, out)
+            }
+            _ => render_distribution_chart(
+                &stats.name,
+                pct_data,
+                config,
+                use_k_weighting,
+                &image.path,
+                &out.charts,
+            ),
+        }
+    }
+    out.record_verify(stats.verify.as_ref());
+    out.record_verify(ctx.verify_summary());
+}
+
+/// Decode the whole file, then analyze it, measuring its LFE channel with `--lfe`
+fn analyze_loaded(
+    filename: &str,
+    bands: &[Band],
+    ctx: &mut AnalysisContext,
+    quiet: bool,
+    load_options: &LoadOptions,
+    lfe: bool,
+) -> Result<(FileStats, AudioData), String> {
+    let (audio, lfe_audio) = if lfe {
+        let (audio, lfe, warnings) = load_audio_with_lfe(filename, load_options)?;
+        print_warnings(&warnings);
+        (audio, Some(lfe))
+    } else {
+        (load_audio(filename, load_options)?, None)
+    };
+    let mut stats = analyze_audio(&audio, filename, bands, ctx, !quiet);
+    stats.lfe = lfe_audio.map(|lfe| measure_lfe(&lfe, bands, ctx));
+    Ok((stats, audio))
+}
+
+/// Data and title of a single stacked bar of the band distribution
+pub(super) fn distribution_chart(
+    name: &str,
+    percentages: &[f64],
+    config: &AnalysisConfig,
+    use_k_weighting: bool,
+) -> (chart::TimelineChartData, String) {
+    let chart_data = chart::TimelineChartData {
+        filename: name.to_string(),
+        time_labels: vec!["".to_string()], // Single bar, no label
+        band_percentages: percentages.iter().map(|&v| vec![v]).collect(),
+        band_colors: chart::band_colors(config.bands(), config.base_bands()),
+    };
+
+    let title = if use_k_weighting {
+        format!("Band Distribution ({})", config.weighting.name())
+    } else {
+        "Band Distribution".to_string()
+    };
+    (chart_data, title)
+}
+
+/// Render a single stacked bar of the band distribution, reporting the outcome on stderr
+pub(super) fn render_distribution_chart(
+    name: &str,
+    percentages: &[f64],
+    config: &AnalysisConfig,
+    use_k_weighting: bool,
+    path: &str,
+    charts: &ChartOptions,
+) {
+    let (chart_data, title) = distribution_chart(name, percentages, config, use_k_weighting);
+    if let Err(e) = chart::render_stacked_chart(&chart_data, config.bands(), &title, path, charts) {
+        print_error(&e);
+    } else {
+        eprintln!("Chart saved to: {}", path);
+    }
+}
+
+/// Print the distribution and dynamics tables
+fn print_tables(stats: &FileStats, config: &AnalysisConfig, out: &Output) {
+    let bands = config.bands();
+    if !out.quiet {
+        writeln!(out.sink);
+        writeln!(out.sink, "Stats Analysis");
+        out.print_file_info(
             &stats.name,
             stats.original_sample_rate,
             stats.channels,
-            use_k_weighting,
+            out.weighted,
+        );
+        out.print_bands(bands);
+    }
+
+    if let Some(active_secs) = stats.active_secs {
+        writeln!(
+            out.sink,
+            "Active: {}",
+            format_active(active_secs, stats.duration_secs)
+        );
+        writeln!(out.sink);
+    }
+
+    let group_edges = config.group_edges();
+    out.print_group_header(bands);
+    out.print_group_row("Raw(%)", &group_totals(bands, group_edges, &stats.raw_pct));
+    let weighted = config.weighting.label();
+    out.print_group_row(
+        &format!("{}(%)", weighted),
+        &group_totals(bands, group_edges, &stats.k_pct),
+    );
+    if !config.metrics.is_empty() {
+        let names: Vec<String> = config.metrics.iter().map(metric_label).collect();
+        writeln!(out.sink);
+        writeln!(out.sink, "[Energy Split] % of Raw energy");
+        out.print_named_row("Raw(%)", &names, &stats.metric_pct);
+    }
+
+    writeln!(out.sink);
+    writeln!(out.sink, "[Band Power Distribution]");
+    out.print_header(bands, "        ");
+    out.print_separator(bands, 8);
+    out.print_row("Raw(%)  ", &stats.raw_pct);
+    out.print_row(&format!("{}(%) ", weighted), &stats.k_pct);
+    out.print_separator(bands, 8);
+    out.print_diff_row("Diff    ", &stats.raw_pct, &stats.k_pct);
+    // Short files get the uncertainty next to the shares it applies to
+    let unstable = stats.frames < MIN_STABLE_FRAMES;
+    if unstable {
+        out.print_row("Raw CI  ", &stats.raw_pct_ci);
+        out.print_row(&format!("{} CI ", weighted), &stats.k_pct_ci);
+    }
+
+    if let Some(curve) = &config.target_curve {
+        let target = curve.band_shares(config);
+        let deviation = target_deviation_db(&stats.raw_pct, &target);
+        writeln!(out.sink);
+        writeln!(
+            out.sink,
+            "[Target Curve] {}",
+            out.display.display_text(&curve.name)
         );
-        print_bands(&bands);
+        out.print_header(bands, "        ");
+        out.print_separator(bands, 8);
+        out.print_row("Tgt(%)  ", &target);
+        out.print_row("Raw(%)  ", &covered_shares(&stats.raw_pct, &target));
+        out.print_signed_row_styled("Dev(dB)", " ", &deviation);
+        let mean = mean_abs_deviation(&deviation);
+        if mean.is_finite() {
+            writeln!(out.sink, "Mean deviation: {:.1} dB", mean);
+        }
     }
 
-    println!("[Band Power Distribution]");
-    print_header(&bands, "        ");
-    print_separator(&bands, 8);
-    print_row("Raw(%)  ", &stats.raw_pct);
-    print_row("K-wt(%) ", &stats.k_pct);
-    print_separator(&bands, 8);
-    print_diff_row("Diff    ", &stats.raw_pct, &stats.k_pct);
-
-    println!();
-    println!("[Dynamics]");
-    print_header(&bands, "        ");
-    print_separator(&bands, 8);
-    print_row_masked(
+    writeln!(out.sink);
+    writeln!(out.sink, "[Dynamics]");
+    out.print_header(bands, "        ");
+    out.print_separator(bands, 8);
+    out.print_row_masked(
         "Dyn(dB) ",
         &stats.dynamics,
         &stats.raw_pct,
         DYNAMICS_DISPLAY_THRESHOLD_PCT,
     );
-
-    if !quiet {
-        println!();
-        print_legend();
+    if out.display.weighted_dynamics {
+        out.print_row_masked(
+            &format!("{} Dyn", weighted),
+            &stats.k_dynamics,
+            &stats.k_pct,
+            DYNAMICS_DISPLAY_THRESHOLD_PCT,
+        );
     }
 
-    // Output chart if requested
-    if let Some(path) = image_path {
-        let pct_data = if use_k_weighting {
-            &stats.k_pct
-        } else {
-            &stats.raw_pct
-        };
-
-        let chart_data = chart::TimelineChartData {
-            filename: stats.name,
-            time_labels: vec!["".to_string()], // Single bar, no label
-            band_percentages: pct_data.iter().map(|&v| vec![v]).collect(),
-        };
-
-        let title = if use_k_weighting {
-            "Band Distribution (K-weighted)"
-        } else {
-            "Band Distribution"
-        };
+    if let Some(lfe) = &stats.lfe {
+        print_lfe(lfe, bands, out);
+    }
 
-        if let Err(e) = chart::render_stacked_chart(&chart_data, &bands, title, path) {
-            print_error(&e);
-        } else {
-            eprintln!("Chart saved to: {}", path);
+    if !out.quiet {
+        writeln!(out.sink);
+        out.print_legend();
+        if out.display.weighted_dynamics {
+            writeln!(
+                out.sink,
+                "{} Dyn: Same as Dyn, on {} power over the frames {} counts",
+                weighted,
+                config.weighting.name(),
+                weighted
+            );
+        }
+        if unstable {
+            writeln!(
+                out.sink,
+                "CI: Raw and {} are within this of their true value (95% confidence)",
+                weighted
+            );
         }
+        out.print_metric_legend();
+        out.print_target_legend();
     }
 }
@@ -1,156 +1,617 @@
 //! Timeline analysis mode (band distribution over time)
 
-use rustfft::FftPlanner;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 
+use super::browse::{BrowseInterval, BrowseTimeline, browse_timeline};
+use super::load_audio;
+use super::monitor::{format_period, format_utc, unix_now};
+use super::report::{ReportOptions, write_timeline_report};
+use super::stats::render_distribution_chart;
+use super::stream_intervals;
 use crate::analysis::{
-    FFT_SIZE, analyze_interval, create_hanning_window, create_k_weight_table, get_bands,
-    powers_to_percentages,
+    AnalysisConfig, AnalysisContext, Band, GROUP_LABELS, LoudnessMeter, analyze_interval,
+    for_each_frame, group_totals, powers_to_percentages, short_term_loudness,
 };
-use crate::audio::{TARGET_SAMPLE_RATE, load_audio};
-use crate::chart;
+use crate::audio::{AudioData, AudioStream, LoadOptions, TARGET_SAMPLE_RATE};
+use crate::chart::{self, ChartKind, ImageSpec};
 use crate::output::{
-    format_time, get_display_name, print_bands, print_error, print_file_info, print_header,
-    print_percentages, print_separator,
+    DisplayOptions, Output, format_time, get_display_name, print_error, print_warnings,
 };
+use bandstat::RESULT_FORMAT;
+
+/// Audio for a timeline: fully decoded, or a growing file read incrementally
+enum TimelineInput {
+    Loaded(AudioData),
+    Stream(Box<AudioStream>),
+}
+
+/// Timeline output settings
+pub struct TimelineOptions<'a> {
+    /// Also print one aggregated row per chapter of this many seconds
+    pub chapter_secs: Option<u64>,
+    /// Charts rendered after the analysis
+    pub images: &'a [ImageSpec],
+    /// Template or HTML report of the result
+    pub report: Option<&'a ReportOptions<'a>>,
+    /// Browse the intervals interactively once the analysis is done
+    pub browse: bool,
+    /// Add the loudest momentary and short-term loudness of each interval
+    pub loudness: bool,
+}
+
+/// Width of the `--loudness` columns after the time column
+const LOUDNESS_COLUMNS_WIDTH: usize = 14;
+
+/// The `--loudness` columns of a timeline row (`-` for an interval under 100 ms long)
+fn loudness_columns(loudness: Option<&IntervalLoudness>) -> String {
+    match loudness {
+        Some(l) => format!(
+            " {:>6.1} {:>6.1}",
+            l.momentary_max_lufs, l.short_term_max_lufs
+        ),
+        None => format!(" {:>6} {:>6}", "-", "-"),
+    }
+}
 
 /// Run timeline analysis showing band distribution over time
 pub fn run_timeline(
     filename: &str,
-    use_k_weighting: bool,
     interval_secs: u32,
-    quiet: bool,
-    image_path: Option<&str>,
+    load_options: &LoadOptions,
+    timeline: &TimelineOptions,
+    config: &AnalysisConfig,
+    out: &Output,
 ) {
-    let bands = get_bands();
+    let TimelineOptions {
+        chapter_secs,
+        images,
+        report,
+        browse,
+        loudness,
+    } = *timeline;
+    let use_k_weighting = out.weighted;
+    let bands = config.bands();
+    let excluded = config.excluded_bands();
+    let group_edges = config.group_edges();
     let display_name = get_display_name(filename).to_string();
 
-    let audio = load_audio(filename).unwrap_or_else(|e| {
-        print_error(&e.to_string());
-        std::process::exit(1);
-    });
+    // A followed file is decoded as it grows, and so is any file under --max-memory unless
+    // a loudness chart needs all of it; otherwise load it up front
+    let loudness_chart = images
+        .iter()
+        .any(|image| image.kind_or(ChartKind::Timeline) == ChartKind::Loudness);
+    let streamed =
+        load_options.follow.is_some() || (load_options.max_memory_mb.is_some() && !loudness_chart);
+    let mut input = if streamed {
+        TimelineInput::Stream(Box::new(
+            AudioStream::open(filename, load_options).unwrap_or_else(|e| {
+                print_error(&e);
+                std::process::exit(1);
+            }),
+        ))
+    } else {
+        TimelineInput::Loaded(load_audio(filename, load_options).unwrap_or_else(|e| {
+            print_error(&e.to_string());
+            std::process::exit(1);
+        }))
+    };
 
-    if !quiet {
-        print_file_info(
-            &display_name,
-            audio.original_sample_rate,
-            audio.channels,
-            use_k_weighting,
-        );
-        print_bands(&bands);
+    let json = out.display.json_output();
+    let (sample_rate, channels) = match &input {
+        TimelineInput::Loaded(audio) => (audio.original_sample_rate, audio.channels),
+        TimelineInput::Stream(stream) => (stream.sample_rate, stream.channels),
+    };
+    if !out.quiet && !json {
+        out.print_file_info(&display_name, sample_rate, channels, use_k_weighting);
+        out.print_bands(bands);
     }
 
-    if audio.samples.is_empty() {
+    if let TimelineInput::Loaded(audio) = &input
+        && audio.samples.is_empty()
+    {
         print_error("No samples found in file");
         std::process::exit(1);
     }
 
-    let freq_per_bin = TARGET_SAMPLE_RATE as f32 / FFT_SIZE as f32;
-    let window = create_hanning_window(FFT_SIZE);
-    let k_weights = if use_k_weighting {
-        Some(create_k_weight_table(FFT_SIZE, TARGET_SAMPLE_RATE))
-    } else {
-        None
-    };
-
-    let mut planner = FftPlanner::new();
-    let fft = planner.plan_fft_forward(FFT_SIZE);
+    let mut ctx = AnalysisContext::new(TARGET_SAMPLE_RATE, config);
 
     let samples_per_interval = (interval_secs as usize) * (TARGET_SAMPLE_RATE as usize);
-    let total_duration = audio.samples.len() as f32 / TARGET_SAMPLE_RATE as f32;
-    let num_intervals = audio.samples.len().div_ceil(samples_per_interval);
 
-    if num_intervals == 0 {
-        print_error("File too short for analysis");
-        std::process::exit(1);
+    // The loudness columns follow the time column, so the band columns line up as usual
+    let (loudness_heading, loudness_width) = if loudness {
+        (
+            format!(" {:>6} {:>6}", "M max", "S max"),
+            LOUDNESS_COLUMNS_WIDTH,
+        )
+    } else {
+        (String::new(), 0)
+    };
+    let mut meter = loudness.then(|| LoudnessMeter::new(TARGET_SAMPLE_RATE));
+    if !json {
+        out.print_header(
+            bands,
+            &format!("{}{}", out.display.time_column("TIME"), loudness_heading),
+        );
+        out.print_separator(bands, out.display.time_column_width() + loudness_width);
     }
 
-    print_header(&bands, "TIME  ");
-    print_separator(&bands, 6);
-
     let mut total_band_powers = vec![0.0f64; bands.len()];
+    // Summed band powers of each --chapters-every chapter, keyed by its first sample
+    let samples_per_chapter = chapter_secs.map(|secs| secs as usize * TARGET_SAMPLE_RATE as usize);
+    let mut chapters: Vec<(usize, Vec<f64>)> = Vec::new();
 
     // For chart: collect percentages per band per interval
-    let mut chart_time_labels: Vec<String> = Vec::new();
-    let mut chart_band_pcts: Vec<Vec<f64>> = vec![Vec::new(); bands.len()];
-
-    for interval_idx in 0..num_intervals {
-        let interval_start = interval_idx * samples_per_interval;
-        let interval_end = ((interval_idx + 1) * samples_per_interval).min(audio.samples.len());
-
-        if interval_end <= interval_start {
-            break;
-        }
+    let mut series = TimelineSeries::new(bands.len(), sample_rate);
+    // Intervals for --format json and the HTML report, written as one document at the end
+    let document_wanted = json || report.is_some() || browse;
+    let mut json_intervals = Vec::new();
+    let mut browse_intervals = Vec::new();
 
-        let interval_samples = &audio.samples[interval_start..interval_end];
-        let band_powers = analyze_interval(
-            interval_samples,
-            &fft,
-            &window,
-            &bands,
-            freq_per_bin,
-            k_weights.as_deref(),
-        );
+    let mut analyze = |interval_samples: &[f32], interval_start: usize| {
+        // Metered before silent intervals are skipped, so the windows run on across them
+        let interval_loudness = meter.as_mut().and_then(|meter| {
+            meter.push(interval_samples);
+            meter
+                .take_max()
+                .map(|(momentary, short_term)| IntervalLoudness {
+                    momentary_max_lufs: momentary,
+                    short_term_max_lufs: short_term,
+                })
+        });
+        let band_powers = analyze_interval(interval_samples, &mut ctx, bands, use_k_weighting);
 
         if band_powers.iter().all(|&p| p == 0.0) {
-            continue;
+            return;
         }
 
         for (total, power) in total_band_powers.iter_mut().zip(&band_powers) {
             *total += power;
         }
+        if let Some(per_chapter) = samples_per_chapter {
+            let chapter_start = interval_start / per_chapter * per_chapter;
+            if chapters
+                .last()
+                .is_none_or(|(start, _)| *start != chapter_start)
+            {
+                chapters.push((chapter_start, vec![0.0; bands.len()]));
+            }
+            let (_, chapter_powers) = chapters.last_mut().unwrap();
+            for (total, power) in chapter_powers.iter_mut().zip(&band_powers) {
+                *total += power;
+            }
+        }
 
         let time_secs = interval_start as f32 / TARGET_SAMPLE_RATE as f32;
-        print!("{}", format_time(time_secs));
 
         // Convert to percentages for display and chart
-        let percentages = powers_to_percentages(&band_powers);
-        for (pct, band) in percentages.iter().zip(&bands) {
-            let formatted = if *pct < 0.05 {
-                "   0.0".to_string()
-            } else {
-                format!("{:>6.1}", pct)
-            };
-            let width = band.label.len().max(4);
-            print!("{:>width$}", formatted, width = width + 2);
-        }
-        println!();
+        let percentages = powers_to_percentages(&band_powers, excluded);
+        if document_wanted {
+            json_intervals.push(TimelineInterval {
+                start_secs: time_secs,
+                loudness: interval_loudness,
+                distribution: Distribution::new(&percentages, bands, group_edges),
+            });
+        }
+        if !json {
+            let mut label = out.display.timestamp_label(time_secs as f64, sample_rate);
+            if loudness {
+                label.push_str(&loudness_columns(interval_loudness.as_ref()));
+            }
+            out.print_interval_row(&label, &percentages, bands);
+        }
 
         // Store for chart
-        if image_path.is_some() {
-            chart_time_labels.push(format_time(time_secs).trim().to_string());
-            for (band_idx, pct) in percentages.iter().enumerate() {
-                chart_band_pcts[band_idx].push(*pct);
+        if !images.is_empty() || report.is_some() {
+            series.push(time_secs, &percentages);
+        }
+
+        if browse {
+            let mut frames = Vec::new();
+            for_each_frame(interval_samples, &mut ctx, bands, false, |powers, gated| {
+                if !gated {
+                    frames.push(powers.to_vec());
+                }
+            });
+            browse_intervals.push(BrowseInterval {
+                start_secs: time_secs,
+                loudness: interval_loudness,
+                powers: band_powers,
+                frames,
+            });
+        }
+    };
+
+    let total_samples = match &mut input {
+        TimelineInput::Loaded(audio) => {
+            for (interval_idx, interval_samples) in
+                audio.samples.chunks(samples_per_interval).enumerate()
+            {
+                analyze(interval_samples, interval_idx * samples_per_interval);
+            }
+            audio.samples.len()
+        }
+        TimelineInput::Stream(stream) => {
+            let total =
+                stream_intervals(stream, samples_per_interval, analyze).unwrap_or_else(|e| {
+                    print_error(&e);
+                    std::process::exit(1);
+                });
+            if load_options.follow.is_none() {
+                print_warnings(&stream.warnings());
+            }
+            total
+        }
+    };
+    let total_duration = total_samples as f32 / TARGET_SAMPLE_RATE as f32;
+
+    let mut browse_document = None;
+    if document_wanted {
+        let mut document = json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "format": RESULT_FORMAT,
+            "generated": format_utc(unix_now()),
+            "mode": "timeline",
+            "k_weighted": use_k_weighting,
+            "bands": bands,
+            "file": TimelineFile {
+                name: display_name.clone(),
+                sample_rate,
+                channels,
+            },
+            "interval_secs": interval_secs,
+            "intervals": json_intervals,
+            "average": Distribution::new(
+                &powers_to_percentages(&total_band_powers, excluded),
+                bands,
+                group_edges,
+            ),
+            "duration_secs": total_duration,
+        });
+        if let Some(secs) = chapter_secs {
+            let chapters: Vec<TimelineInterval> = chapters
+                .iter()
+                .map(|(start, powers)| TimelineInterval {
+                    start_secs: *start as f32 / TARGET_SAMPLE_RATE as f32,
+                    loudness: None,
+                    distribution: Distribution::new(
+                        &powers_to_percentages(powers, excluded),
+                        bands,
+                        group_edges,
+                    ),
+                })
+                .collect();
+            document["chapter_secs"] = json!(secs);
+            document["chapters"] = json!(chapters);
+        }
+        if json {
+            let mut document = document.clone();
+            out.display.round_json(&mut document);
+            writeln!(out.sink, "{:#}", document);
+        }
+        if browse {
+            browse_document = Some(document.clone());
+        }
+        if let Some(report) = report {
+            let chart = series.svg(&display_name, config, use_k_weighting, out);
+            if let Err(e) = write_timeline_report(report, document, chart, out) {
+                print_error(&e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if !json {
+        out.print_separator(bands, out.display.time_column_width() + loudness_width);
+
+        write!(
+            out.sink,
+            "{:<w$}",
+            out.display.time_column("AVG"),
+            w = out.display.time_column_width() + loudness_width
+        );
+        out.print_percentages(&total_band_powers);
+        writeln!(out.sink);
+
+        // A recording within one chapter would only repeat the AVG row
+        if let Some(secs) = chapter_secs
+            && chapters.len() > 1
+        {
+            writeln!(out.sink);
+            writeln!(out.sink, "Chapters (every {})", format_period(secs));
+            out.print_header(bands, &out.display.time_column("TIME"));
+            out.print_separator(bands, out.display.time_column_width());
+            for (start, powers) in &chapters {
+                let start_secs = *start as f64 / TARGET_SAMPLE_RATE as f64;
+                out.print_interval_row(
+                    &out.display.timestamp_label(start_secs, sample_rate),
+                    &powers_to_percentages(powers, excluded),
+                    bands,
+                );
             }
         }
+
+        writeln!(out.sink);
+        writeln!(out.sink, "Duration: {}", format_time(total_duration));
     }
 
-    print_separator(&bands, 6);
+    // Output charts if requested
+    for image in images {
+        match image.kind_or(ChartKind::Timeline) {
+            ChartKind::Stats => render_distribution_chart(
+                &display_name,
+                &powers_to_percentages(&total_band_powers, excluded),
+                config,
+                use_k_weighting,
+                &image.path,
+                &out.charts,
+            ),
+            // Rejected with --follow, and keeps the audio loaded under --max-memory
+            ChartKind::Loudness => {
+                if let TimelineInput::Loaded(audio) = &input {
+                    render_loudness_chart(&display_name, audio, &mut ctx, config, &image.path, out);
+                }
+            }
+            _ => series.render(&display_name, config, use_k_weighting, &image.path, out),
+        }
+    }
+    out.record_verify(ctx.verify_summary());
 
-    print!("AVG   ");
-    print_percentages(&total_band_powers);
-    println!();
+    if let Some(document) = browse_document {
+        browse_timeline(&BrowseTimeline {
+            name: &display_name,
+            config,
+            use_k_weighting,
+            sample_rate,
+            interval_secs,
+            duration_secs: total_duration,
+            intervals: &browse_intervals,
+            document: &document,
+            display: out.display,
+        });
+    }
+}
+
+/// Input of a timeline result
+#[derive(Serialize, Deserialize)]
+pub(super) struct TimelineFile {
+    pub(super) name: String,
+    /// Sample rate before resampling
+    pub(super) sample_rate: u32,
+    pub(super) channels: u16,
+}
 
-    println!();
-    println!("Duration: {}", format_time(total_duration));
+/// Band and group distribution of one timeline interval
+#[derive(Serialize, Deserialize)]
+pub(super) struct TimelineInterval {
+    pub(super) start_secs: f32,
+    /// With `--loudness`
+    #[serde(flatten)]
+    pub(super) loudness: Option<IntervalLoudness>,
+    #[serde(flatten)]
+    pub(super) distribution: Distribution,
+}
 
-    // Output chart if requested
-    if let Some(path) = image_path {
-        let chart_data = chart::TimelineChartData {
-            filename: display_name,
-            time_labels: chart_time_labels,
-            band_percentages: chart_band_pcts,
+/// Loudest momentary (400 ms) and short-term (3 s) loudness in one timeline interval
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub(super) struct IntervalLoudness {
+    pub(super) momentary_max_lufs: f64,
+    pub(super) short_term_max_lufs: f64,
+}
+
+/// Band and group percentages of one distribution for `--format json`
+#[derive(Serialize, Deserialize)]
+pub(super) struct Distribution {
+    pub(super) bands: Vec<LabeledPct>,
+    pub(super) groups: Vec<LabeledPct>,
+}
+
+/// Percentage of one band or group
+#[derive(Serialize, Deserialize)]
+pub(super) struct LabeledPct {
+    pub(super) label: String,
+    pub(super) pct: f64,
+}
+
+impl Distribution {
+    pub(super) fn new(percentages: &[f64], bands: &[Band], group_edges: [f32; 2]) -> Self {
+        let labeled = |label: &str, pct: f64| LabeledPct {
+            label: label.to_string(),
+            pct,
         };
+        Self {
+            bands: bands
+                .iter()
+                .zip(percentages)
+                .map(|(b, &pct)| labeled(&b.label, pct))
+                .collect(),
+            groups: GROUP_LABELS
+                .iter()
+                .zip(group_totals(bands, group_edges, percentages))
+                .map(|(label, pct)| labeled(label, pct))
+                .collect(),
+        }
+    }
+}
+
+/// Per-interval band percentages collected for the timeline chart
+pub(super) struct TimelineSeries {
+    /// Sample rate of the file, for `--time-format samples`
+    sample_rate: u32,
+    /// Start of each interval in seconds
+    times: Vec<f32>,
+    /// band_percentages[band_idx][interval_idx]
+    band_percentages: Vec<Vec<f64>>,
+}
+
+impl TimelineSeries {
+    pub(super) fn new(num_bands: usize, sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            times: Vec::new(),
+            band_percentages: vec![Vec::new(); num_bands],
+        }
+    }
+
+    /// Collect the intervals of fully decoded audio (silent intervals are skipped)
+    pub(super) fn from_samples(
+        audio: &AudioData,
+        ctx: &mut AnalysisContext,
+        config: &AnalysisConfig,
+        interval_secs: u32,
+        use_k_weighting: bool,
+    ) -> Self {
+        let bands = config.bands();
+        let samples_per_interval = (interval_secs as usize) * (TARGET_SAMPLE_RATE as usize);
+        let mut series = Self::new(bands.len(), audio.original_sample_rate);
+        for (interval_idx, interval_samples) in
+            audio.samples.chunks(samples_per_interval).enumerate()
+        {
+            let band_powers = analyze_interval(interval_samples, ctx, bands, use_k_weighting);
+            if band_powers.iter().all(|&p| p == 0.0) {
+                continue;
+            }
+            let time_secs =
+                (interval_idx * samples_per_interval) as f32 / TARGET_SAMPLE_RATE as f32;
+            series.push(
+                time_secs,
+                &powers_to_percentages(&band_powers, config.excluded_bands()),
+            );
+        }
+        series
+    }
+
+    /// Collect the intervals like `from_samples`, decoding the file again a packet at a time
+    pub(super) fn from_stream(
+        filename: &str,
+        load_options: &LoadOptions,
+        ctx: &mut AnalysisContext,
+        config: &AnalysisConfig,
+        interval_secs: u32,
+        use_k_weighting: bool,
+    ) -> Result<Self, String> {
+        let bands = config.bands();
+        let samples_per_interval = (interval_secs as usize) * (TARGET_SAMPLE_RATE as usize);
+        let mut stream = AudioStream::open(filename, load_options)?;
+        let mut series = Self::new(bands.len(), stream.sample_rate);
+        stream_intervals(
+            &mut stream,
+            samples_per_interval,
+            |interval_samples, start| {
+                let band_powers = analyze_interval(interval_samples, ctx, bands, use_k_weighting);
+                if band_powers.iter().all(|&p| p == 0.0) {
+                    return;
+                }
+                let time_secs = start as f32 / TARGET_SAMPLE_RATE as f32;
+                series.push(
+                    time_secs,
+                    &powers_to_percentages(&band_powers, config.excluded_bands()),
+                );
+            },
+        )?;
+        print_warnings(&stream.warnings());
+        Ok(series)
+    }
+
+    pub(super) fn push(&mut self, time_secs: f32, percentages: &[f64]) {
+        self.times.push(time_secs);
+        for (band, pct) in self.band_percentages.iter_mut().zip(percentages) {
+            band.push(*pct);
+        }
+    }
+
+    fn chart_data(
+        &self,
+        name: &str,
+        config: &AnalysisConfig,
+        display: &DisplayOptions,
+    ) -> chart::TimelineChartData {
+        chart::TimelineChartData {
+            filename: name.to_string(),
+            time_labels: self
+                .times
+                .iter()
+                .map(|&t| display.format_timestamp(t as f64, self.sample_rate))
+                .collect(),
+            band_percentages: self.band_percentages.clone(),
+            band_colors: chart::band_colors(config.bands(), config.base_bands()),
+        }
+    }
 
-        let title = if use_k_weighting {
-            "Band Distribution Over Time (K-weighted)"
+    fn title(config: &AnalysisConfig, use_k_weighting: bool) -> String {
+        if use_k_weighting {
+            format!("Band Distribution Over Time ({})", config.weighting.name())
         } else {
-            "Band Distribution Over Time"
-        };
+            "Band Distribution Over Time".to_string()
+        }
+    }
 
-        if let Err(e) = chart::render_stacked_chart(&chart_data, &bands, title, path) {
+    /// Render as a stacked timeline chart in SVG markup
+    fn svg(
+        &self,
+        name: &str,
+        config: &AnalysisConfig,
+        use_k_weighting: bool,
+        out: &Output,
+    ) -> Result<String, String> {
+        chart::stacked_chart_svg(
+            &self.chart_data(name, config, &out.display),
+            config.bands(),
+            &Self::title(config, use_k_weighting),
+            &out.charts,
+        )
+    }
+
+    /// Render as a stacked timeline chart, reporting the outcome on stderr
+    pub(super) fn render(
+        &self,
+        name: &str,
+        config: &AnalysisConfig,
+        use_k_weighting: bool,
+        path: &str,
+        out: &Output,
+    ) {
+        let chart_data = self.chart_data(name, config, &out.display);
+        let title = Self::title(config, use_k_weighting);
+        if let Err(e) =
+            chart::render_stacked_chart(&chart_data, config.bands(), &title, path, &out.charts)
+        {
             print_error(&e);
         } else {
             eprintln!("Chart saved to: {}", path);
         }
     }
 }
+
+/// Render short-term and grouped band loudness over time, reporting the outcome on stderr
+pub(super) fn render_loudness_chart(
+    name: &str,
+    audio: &AudioData,
+    ctx: &mut AnalysisContext,
+    config: &AnalysisConfig,
+    path: &str,
+    out: &Output,
+) {
+    let loudness = short_term_loudness(&audio.samples, ctx, config.bands(), config.group_edges());
+    let chart_data = chart::LoudnessChartData {
+        filename: name.to_string(),
+        time_labels: loudness
+            .times
+            .iter()
+            .map(|&t| out.display.format_timestamp(t, audio.original_sample_rate))
+            .collect(),
+        short_term: loudness.short_term,
+        groups: GROUP_LABELS
+            .iter()
+            .map(|l| l.to_string())
+            .zip(loudness.groups)
+            .collect(),
+    };
+
+    if let Err(e) =
+        chart::render_loudness_chart(&chart_data, "Short-term Loudness (LUFS)", path, &out.charts)
+    {
+        print_error(&e);
+    } else {
+        eprintln!("Chart saved to: {}", path);
+    }
+}
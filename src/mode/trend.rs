@@ -0,0 +1,167 @@
+//! Band balance and loudness across a series of saved runs (e.g. mix v1...v9)
+
+use std::path::Path;
+
+use super::FileStats;
+use super::diff_results::{load_result, same_bands};
+use crate::analysis::{Band, DEFAULT_GROUP_EDGES_HZ, GROUP_LABELS, group_totals};
+use crate::chart::{self, ChartOptions, TrendChartData, band_color, group_color};
+use crate::output::{DisplayOptions, print_error};
+
+/// Print and optionally chart how the runs' band shares and loudness evolved
+///
+/// Each result is one run, named after its file stem; comparison results
+/// contribute their base file [A].
+pub fn run_trend(
+    paths: &[String],
+    band_labels: &[String],
+    k_weighted: bool,
+    image: Option<&str>,
+    display: &DisplayOptions,
+    charts: &ChartOptions,
+) {
+    let trend = load_trend(paths, band_labels, k_weighted).unwrap_or_else(|e| {
+        print_error(&e);
+        std::process::exit(1);
+    });
+    print_trend(&trend, k_weighted, display);
+
+    if let Some(path) = image {
+        let title = if k_weighted {
+            "Band Trend (K-weighted)"
+        } else {
+            "Band Trend"
+        };
+        if let Err(e) = chart::render_trend_chart(&trend, title, path, charts) {
+            print_error(&e);
+        } else {
+            eprintln!("Chart saved to: {}", path);
+        }
+    }
+}
+
+/// The chosen bands (or the LOW/MID/HIGH totals) and the loudness of each run
+fn load_trend(
+    paths: &[String],
+    band_labels: &[String],
+    k_weighted: bool,
+) -> Result<TrendChartData, String> {
+    let mut bands: Option<Vec<Band>> = None;
+    let mut runs: Vec<FileStats> = Vec::new();
+    for path in paths {
+        let (file_bands, stats) = load_result(path)?;
+        match &bands {
+            Some(bands) if !same_bands(bands, &file_bands) => {
+                return Err(format!(
+                    "{}: bands differ from {} (results must use the same band settings)",
+                    path, paths[0]
+                ));
+            }
+            Some(_) => {}
+            None => bands = Some(file_bands),
+        }
+        let base = stats.into_iter().next();
+        runs.push(base.ok_or_else(|| format!("{}: the result holds no files", path))?);
+    }
+    let bands = bands.unwrap_or_default();
+    let pct: fn(&FileStats) -> &[f64] = if k_weighted {
+        |s| &s.k_pct
+    } else {
+        |s| &s.raw_pct
+    };
+
+    let series = if band_labels.is_empty() {
+        let totals: Vec<Vec<f64>> = runs
+            .iter()
+            .map(|s| group_totals(&bands, DEFAULT_GROUP_EDGES_HZ, pct(s)))
+            .collect();
+        GROUP_LABELS
+            .iter()
+            .enumerate()
+            .map(|(g, label)| {
+                let values = totals.iter().map(|t| t[g]).collect();
+                (label.to_string(), group_color(g).to_string(), values)
+            })
+            .collect()
+    } else {
+        band_labels
+            .iter()
+            .map(|label| {
+                let i = bands
+                    .iter()
+                    .position(|b| b.label.eq_ignore_ascii_case(label))
+                    .ok_or_else(|| format!("--band: unknown band '{}'", label))?;
+                let values = runs.iter().map(|s| pct(s)[i]).collect();
+                Ok((
+                    bands[i].label.to_string(),
+                    band_color(&bands[i], &bands),
+                    values,
+                ))
+            })
+            .collect::<Result<Vec<_>, String>>()?
+    };
+
+    // Results saved before loudness was recorded leave the loudness line out
+    let loudness: Vec<f64> = runs.iter().map(|s| s.integrated_lufs).collect();
+    Ok(TrendChartData {
+        run_labels: paths.iter().map(|p| run_label(p)).collect(),
+        series,
+        loudness: loudness.iter().all(|l| l.is_finite()).then_some(loudness),
+    })
+}
+
+/// Name of a run: its result file without the extension
+fn run_label(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string())
+}
+
+fn print_trend(trend: &TrendChartData, k_weighted: bool, display: &DisplayOptions) {
+    let width = trend
+        .run_labels
+        .iter()
+        .map(|l| l.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(3);
+
+    println!("Trend ({}):", if k_weighted { "K-wt %" } else { "Raw %" });
+    // Columns widen to fit custom band labels
+    let columns: Vec<usize> = trend
+        .series
+        .iter()
+        .map(|(l, _, _)| l.len().max(6))
+        .collect();
+    print!("{:<width$}", "RUN");
+    for ((label, _, _), w) in trend.series.iter().zip(&columns) {
+        print!(" {:>w$}", label);
+    }
+    if trend.loudness.is_some() {
+        print!(" {:>6}", "LUFS");
+    }
+    println!();
+
+    for (run, label) in trend.run_labels.iter().enumerate() {
+        print!("{:<width$}", display.display_text(label));
+        for ((_, _, values), w) in trend.series.iter().zip(&columns) {
+            print!(" {:>w$.p$}", values[run], p = display.decimals);
+        }
+        if let Some(loudness) = &trend.loudness {
+            print!(" {:>6.1}", loudness[run]);
+        }
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_label() {
+        assert_eq!(run_label("results/mix v3.json"), "mix v3");
+        assert_eq!(run_label("v1"), "v1");
+    }
+}
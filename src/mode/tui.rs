@@ -0,0 +1,464 @@
+//! Terminal meter: a file played through the analysis at real-time speed, with animated
+//! band bars, the rolling K-weighted LOW/MID/HIGH balance, and short-term dynamics
+
+use std::collections::VecDeque;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Bar, BarChart, BarGroup, Block, LineGauge, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::analysis::{
+    AnalysisConfig, AnalysisContext, Band, DYNAMICS_DISPLAY_THRESHOLD_PCT, FFT_SIZE, GROUP_LABELS,
+    HOP_SIZE, for_each_frame, frame_dynamics, group_totals, powers_to_percentages,
+};
+use crate::audio::{AudioStream, LoadOptions, Resampling, StreamResampler, TARGET_SAMPLE_RATE};
+use crate::chart::band_color;
+use crate::output::{format_time, get_display_name, print_error, print_warnings};
+
+/// Samples analyzed per screen update (two FFT hops, about 85 ms)
+const STEP: usize = 2 * HOP_SIZE;
+
+/// FFT frames behind the band bars (about 170 ms)
+const BAR_FRAMES: usize = 4;
+
+/// Seconds of FFT frames behind the balance and dynamics
+const ROLLING_SECS: f64 = 3.0;
+
+/// Band share at the top of the bar chart (louder bands fill it)
+const BAR_SCALE_PCT: f64 = 50.0;
+
+/// Dynamics at the top of the dynamics chart
+const DYNAMICS_SCALE_DB: f64 = 20.0;
+
+/// Part of a bar's height kept per update once its band gets quieter
+const BAR_FALLOFF: f64 = 0.85;
+
+/// Play a file through the analysis in a full-screen terminal meter until it ends and a
+/// key is pressed, or until the meter is quit
+pub fn run_tui(
+    input: &str,
+    use_k_weighting: bool,
+    load_options: &LoadOptions,
+    config: &AnalysisConfig,
+) {
+    let mut stream = AudioStream::open(input, load_options).unwrap_or_else(|e| {
+        print_error(&e);
+        std::process::exit(1);
+    });
+    let mut meter = Meter::new(config, use_k_weighting);
+
+    let mut terminal = ratatui::try_init().unwrap_or_else(|e| {
+        ratatui::restore();
+        print_error(&format!("cannot start the terminal meter ({})", e));
+        std::process::exit(1);
+    });
+    let result = play(
+        &mut terminal,
+        &mut stream,
+        &mut meter,
+        get_display_name(input),
+        load_options.resampling,
+    );
+    ratatui::restore();
+
+    if let Err(e) = result {
+        print_error(&e);
+        std::process::exit(1);
+    }
+    print_warnings(&stream.warnings());
+}
+
+/// Band powers of one FFT frame
+struct FramePowers {
+    raw: Vec<f64>,
+    k: Vec<f64>,
+}
+
+/// Rolling analysis state behind the display
+struct Meter<'a> {
+    config: &'a AnalysisConfig,
+    ctx: AnalysisContext,
+    use_k_weighting: bool,
+    /// The last `ROLLING_SECS` of frames, oldest first
+    frames: VecDeque<FramePowers>,
+    capacity: usize,
+    /// Band shares of the last `BAR_FRAMES` frames
+    shares: Vec<f64>,
+    /// Bar heights in percent: the shares, falling off slowly
+    bars: Vec<f64>,
+}
+
+impl<'a> Meter<'a> {
+    fn new(config: &'a AnalysisConfig, use_k_weighting: bool) -> Self {
+        let bands = config.bands();
+        Self {
+            config,
+            ctx: AnalysisContext::new(TARGET_SAMPLE_RATE, config),
+            use_k_weighting,
+            frames: VecDeque::new(),
+            capacity: (ROLLING_SECS * TARGET_SAMPLE_RATE as f64 / HOP_SIZE as f64) as usize,
+            shares: vec![0.0; bands.len()],
+            bars: vec![0.0; bands.len()],
+        }
+    }
+
+    /// Analyze the frames of `window`
+    fn push(&mut self, window: &[f32]) {
+        let raw = self.band_frames(window, false);
+        let k = self.band_frames(window, true);
+        for (raw, k) in raw.into_iter().zip(k) {
+            if self.frames.len() == self.capacity {
+                self.frames.pop_front();
+            }
+            self.frames.push_back(FramePowers { raw, k });
+        }
+
+        let mut recent = vec![0.0; self.config.bands().len()];
+        for frame in self.frames.iter().rev().take(BAR_FRAMES) {
+            let powers = if self.use_k_weighting {
+                &frame.k
+            } else {
+                &frame.raw
+            };
+            for (total, p) in recent.iter_mut().zip(powers) {
+                *total += p;
+            }
+        }
+        self.shares = self.percentages(&recent);
+        for (bar, &share) in self.bars.iter_mut().zip(&self.shares) {
+            *bar = share.max(*bar * BAR_FALLOFF);
+        }
+    }
+
+    /// Band powers of each frame in `window`; frames dropped by `--gate` count as silent
+    fn band_frames(&mut self, window: &[f32], use_k_weighting: bool) -> Vec<Vec<f64>> {
+        let mut frames = Vec::new();
+        for_each_frame(
+            window,
+            &mut self.ctx,
+            self.config.bands(),
+            use_k_weighting,
+            |powers, gated| {
+                frames.push(if gated {
+                    vec![0.0; powers.len()]
+                } else {
+                    powers.to_vec()
+                });
+            },
+        );
+        frames
+    }
+
+    /// Sum of the rolling window's band powers
+    fn rolling_powers(&self, k_weighted: bool) -> Vec<f64> {
+        let mut totals = vec![0.0; self.config.bands().len()];
+        for frame in &self.frames {
+            let powers = if k_weighted { &frame.k } else { &frame.raw };
+            for (total, p) in totals.iter_mut().zip(powers) {
+                *total += p;
+            }
+        }
+        totals
+    }
+
+    /// K-weighted LOW/MID/HIGH shares of the rolling window, or `None` while it is silent
+    fn balance(&self) -> Option<Vec<f64>> {
+        let powers = self.rolling_powers(true);
+        if powers.iter().all(|&p| p == 0.0) {
+            return None;
+        }
+        Some(group_totals(
+            self.config.bands(),
+            self.config.group_edges(),
+            &self.percentages(&powers),
+        ))
+    }
+
+    /// Band shares of summed powers, leaving out the `--exclude-bands` bands
+    fn percentages(&self, powers: &[f64]) -> Vec<f64> {
+        powers_to_percentages(powers, self.config.excluded_bands())
+    }
+
+    /// Dynamics of each band over the rolling window, NaN for bands too quiet to tell
+    fn dynamics(&self) -> Vec<f64> {
+        let shares = self.percentages(&self.rolling_powers(false));
+        frame_dynamics(
+            self.frames.iter().map(|frame| frame.raw.as_slice()),
+            self.config.bands().len(),
+        )
+        .into_iter()
+        .zip(shares)
+        .map(|(db, share)| {
+            if db.is_finite() && share >= DYNAMICS_DISPLAY_THRESHOLD_PCT {
+                db
+            } else {
+                f64::NAN
+            }
+        })
+        .collect()
+    }
+}
+
+/// What the header shows about playback
+struct Status<'a> {
+    name: &'a str,
+    position_secs: f64,
+    duration_secs: Option<f64>,
+    state: &'static str,
+}
+
+/// A key press or terminal event the meter responds to
+enum Action {
+    Quit,
+    Pause,
+    Redraw,
+}
+
+/// Decode and analyze the stream a step at a time, drawing each step once its playback
+/// time has come
+fn play(
+    terminal: &mut DefaultTerminal,
+    stream: &mut AudioStream,
+    meter: &mut Meter,
+    name: &str,
+    resampling: Resampling,
+) -> Result<(), String> {
+    let mut status = Status {
+        name,
+        position_secs: 0.0,
+        duration_secs: stream
+            .expected_samples()
+            .map(|n| n as f64 / TARGET_SAMPLE_RATE as f64),
+        state: "playing",
+    };
+    let mut resampler = (stream.sample_rate != TARGET_SAMPLE_RATE)
+        .then(|| StreamResampler::new(stream.sample_rate, TARGET_SAMPLE_RATE, resampling))
+        .transpose()?;
+    // Samples from the start of the next frame on
+    let mut window: Vec<f32> = Vec::with_capacity(FFT_SIZE + STEP);
+    let mut pending: Vec<f32> = Vec::with_capacity(STEP * 2);
+    let mut decoded = Vec::new();
+    let mut ended = false;
+    let mut played = 0usize;
+    let mut started = Instant::now();
+    let mut paused = false;
+
+    loop {
+        while !ended && pending.len() < STEP {
+            decoded.clear();
+            let more = stream.read_into(&mut decoded)?;
+            match resampler.as_mut() {
+                Some(r) => pending.extend(r.process(&decoded)?),
+                None => pending.extend_from_slice(&decoded),
+            }
+            if !more {
+                if let Some(r) = resampler.take() {
+                    pending.extend(r.finish()?);
+                }
+                ended = true;
+            }
+        }
+        if pending.is_empty() {
+            break;
+        }
+
+        let n = pending.len().min(STEP);
+        window.extend(pending.drain(..n));
+        let frames = (window.len() + HOP_SIZE).saturating_sub(FFT_SIZE) / HOP_SIZE;
+        if frames > 0 {
+            meter.push(&window[..(frames - 1) * HOP_SIZE + FFT_SIZE]);
+            window.drain(..frames * HOP_SIZE);
+        }
+        played += n;
+        let step_end = Duration::from_secs_f64(played as f64 / TARGET_SAMPLE_RATE as f64);
+
+        // Draw the step once its playback time has come
+        loop {
+            let due = started + step_end;
+            let timeout = (!paused).then(|| due.saturating_duration_since(Instant::now()));
+            if timeout == Some(Duration::ZERO) {
+                break;
+            }
+            match next_action(timeout)? {
+                Some(Action::Quit) => return Ok(()),
+                Some(Action::Pause) => {
+                    paused = !paused;
+                    status.state = if paused { "paused" } else { "playing" };
+                    if !paused {
+                        let position = Duration::from_secs_f64(status.position_secs);
+                        started = Instant::now().checked_sub(position).unwrap_or(started);
+                    }
+                    draw(terminal, meter, &status)?;
+                }
+                Some(Action::Redraw) => draw(terminal, meter, &status)?,
+                None => {}
+            }
+        }
+        status.position_secs = step_end.as_secs_f64();
+        draw(terminal, meter, &status)?;
+    }
+
+    // Keep the last reading on screen until the meter is closed
+    status.state = "finished";
+    draw(terminal, meter, &status)?;
+    loop {
+        match next_action(None)? {
+            Some(Action::Quit) => return Ok(()),
+            Some(_) => draw(terminal, meter, &status)?,
+            None => {}
+        }
+    }
+}
+
+/// Wait up to `timeout` (without one, until something happens) for an action
+fn next_action(timeout: Option<Duration>) -> Result<Option<Action>, String> {
+    let error = |e: std::io::Error| format!("terminal: {}", e);
+    if let Some(timeout) = timeout
+        && !event::poll(timeout).map_err(error)?
+    {
+        return Ok(None);
+    }
+    Ok(match event::read().map_err(error)? {
+        Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => Some(Action::Quit),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                Some(Action::Quit)
+            }
+            KeyCode::Char(' ') | KeyCode::Char('p') => Some(Action::Pause),
+            _ => None,
+        },
+        Event::Resize(..) => Some(Action::Redraw),
+        _ => None,
+    })
+}
+
+fn draw(terminal: &mut DefaultTerminal, meter: &Meter, status: &Status) -> Result<(), String> {
+    terminal
+        .draw(|frame| render(frame, meter, status))
+        .map(|_| ())
+        .map_err(|e| format!("terminal: {}", e))
+}
+
+fn render(frame: &mut Frame, meter: &Meter, status: &Status) {
+    let [header, shares, dynamics, balance] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(8),
+        Constraint::Length(7),
+        Constraint::Length(3),
+    ])
+    .areas(frame.area());
+
+    let position = format_time(status.position_secs as f32);
+    let time = match status.duration_secs {
+        Some(duration) => format!("{}/ {}", position, format_time(duration as f32)),
+        None => position,
+    };
+    let config = meter.config;
+    let weighted = if meter.use_k_weighting {
+        config.weighting.name()
+    } else {
+        "Raw".to_string()
+    };
+    frame.render_widget(
+        Paragraph::new(format!(
+            "{} {:<8}  {}    space: pause  q: quit",
+            time, status.state, weighted
+        ))
+        .block(Block::bordered().title(format!(" {} ", status.name))),
+        header,
+    );
+
+    let bars: Vec<Bar> = config
+        .bands()
+        .iter()
+        .zip(&meter.bars)
+        .zip(&meter.shares)
+        .map(|((band, &height), &share)| {
+            band_bar(band, config, height * 10.0, format!("{:.0}", share))
+        })
+        .collect();
+    render_bars(
+        frame,
+        shares,
+        &bars,
+        BAR_SCALE_PCT * 10.0,
+        &format!(" Band shares (%, {}) ", weighted),
+    );
+
+    let bars: Vec<Bar> = config
+        .bands()
+        .iter()
+        .zip(meter.dynamics())
+        .map(|(band, db)| {
+            if db.is_nan() {
+                band_bar(band, config, 0.0, "-".to_string())
+            } else {
+                band_bar(band, config, db * 10.0, format!("{:.1}", db))
+            }
+        })
+        .collect();
+    render_bars(
+        frame,
+        dynamics,
+        &bars,
+        DYNAMICS_SCALE_DB * 10.0,
+        &format!(" Dynamics (dB, last {} s) ", ROLLING_SECS),
+    );
+
+    let block = Block::bordered().title(format!(
+        " Balance ({}, last {} s) ",
+        config.weighting.name(),
+        ROLLING_SECS
+    ));
+    let inner = block.inner(balance);
+    frame.render_widget(block, balance);
+    let groups = meter.balance();
+    let columns: [Rect; 3] = Layout::horizontal([Constraint::Ratio(1, 3); 3])
+        .spacing(2)
+        .areas(inner);
+    for (i, (label, area)) in GROUP_LABELS.iter().zip(columns).enumerate() {
+        let pct = groups.as_ref().map(|groups| groups[i]);
+        frame.render_widget(
+            LineGauge::default()
+                .filled_style(Style::new().fg(Color::Cyan))
+                .ratio(pct.map_or(0.0, |pct| (pct / 100.0).clamp(0.0, 1.0)))
+                .label(match pct {
+                    Some(pct) => format!("{:<4} {:5.1}%", label, pct),
+                    None => format!("{:<4}     -", label),
+                }),
+            area,
+        );
+    }
+}
+
+/// One band's bar, `value` in tenths of the chart's unit
+fn band_bar<'a>(band: &'a Band, config: &AnalysisConfig, value: f64, text: String) -> Bar<'a> {
+    let color = Color::from_str(&band_color(band, config.base_bands())).unwrap_or(Color::Cyan);
+    Bar::default()
+        .value(value.round().max(0.0) as u64)
+        .text_value(text)
+        .label(Line::from(band.label.clone()))
+        .style(Style::new().fg(color))
+        .value_style(Style::new().fg(Color::Black).bg(color))
+}
+
+/// Bar chart of one bar per band, spread over the width of `area`
+fn render_bars(frame: &mut Frame, area: Rect, bars: &[Bar], max: f64, title: &str) {
+    let count = bars.len().max(1) as u16;
+    let width = area.width.saturating_sub(2);
+    let bar_width = (width.saturating_sub(count - 1) / count).max(1);
+    frame.render_widget(
+        BarChart::default()
+            .block(Block::bordered().title(title))
+            .data(BarGroup::default().bars(bars))
+            .bar_width(bar_width)
+            .bar_gap(1)
+            .max(max as u64),
+        area,
+    );
+}
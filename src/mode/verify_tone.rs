@@ -0,0 +1,305 @@
+//! Calibration tone verification mode
+
+use std::str::FromStr;
+
+use colored::*;
+use rustfft::num_complex::Complex;
+
+use super::load_audio;
+use crate::analysis::{
+    AnalysisConfig, FFT_SIZE, HOP_SIZE, create_hanning_window, plan_fft_forward,
+};
+use crate::audio::LoadOptions;
+use crate::output::{Output, get_display_name, print_error};
+
+/// Allowed frequency deviation (percent of expected frequency)
+const FREQ_TOLERANCE_PCT: f64 = 1.0;
+
+/// Allowed level deviation (dB)
+const LEVEL_TOLERANCE_DB: f64 = 0.5;
+
+/// Minimum share of total power around the spectral peak for a clean tone
+const MIN_PURITY_PCT: f64 = 90.0;
+
+/// Bins on each side of the peak counted as part of the tone (Hann main lobe + margin)
+const PEAK_HALF_WIDTH_BINS: usize = 4;
+
+/// Blocks more than this far below the loudest one are silence around the tone (dB)
+const TONE_PRESENT_RANGE_DB: f64 = 20.0;
+
+/// Expected calibration tone (`FREQ:DBFS`, e.g. `1000:-20`)
+#[derive(Clone, Copy, Debug)]
+pub struct ToneSpec {
+    pub freq_hz: f64,
+    pub level_dbfs: f64,
+}
+
+impl FromStr for ToneSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (freq, level) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected FREQ:DBFS (e.g. 1000:-20), got '{}'", s))?;
+        let freq_hz: f64 = freq
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid frequency '{}'", freq))?;
+        let level_dbfs: f64 = level
+            .trim()
+            .trim_end_matches("dBFS")
+            .trim_end_matches("dB")
+            .parse()
+            .map_err(|_| format!("invalid level '{}'", level))?;
+        if !freq_hz.is_finite() || freq_hz <= 0.0 {
+            return Err("frequency must be positive".to_string());
+        }
+        if !level_dbfs.is_finite() {
+            return Err(format!("invalid level '{}'", level));
+        }
+        Ok(Self {
+            freq_hz,
+            level_dbfs,
+        })
+    }
+}
+
+/// Measured properties of the dominant tone in a file
+struct ToneMeasurement {
+    freq_hz: f64,
+    level_dbfs: f64,
+    purity_pct: f64,
+}
+
+/// Verify that a file contains the expected calibration tone; returns whether it passed
+pub fn run_verify_tone(
+    filename: &str,
+    expected: ToneSpec,
+    load_options: &LoadOptions,
+    config: &AnalysisConfig,
+    out: &Output,
+) -> bool {
+    let display_name = get_display_name(filename).to_string();
+    let audio = load_audio(filename, load_options).unwrap_or_else(|e| {
+        print_error(&e);
+        std::process::exit(1);
+    });
+
+    let measured = measure_tone(&audio.samples, audio.sample_rate, config.deterministic)
+        .unwrap_or_else(|e| {
+            print_error(&format!("{}: {}", display_name, e));
+            std::process::exit(1);
+        });
+
+    let freq_dev = measured.freq_hz - expected.freq_hz;
+    let freq_dev_pct = freq_dev / expected.freq_hz * 100.0;
+    let level_dev = measured.level_dbfs - expected.level_dbfs;
+
+    let freq_ok = freq_dev_pct.abs() <= FREQ_TOLERANCE_PCT;
+    let level_ok = level_dev.abs() <= LEVEL_TOLERANCE_DB;
+    let purity_ok = measured.purity_pct >= MIN_PURITY_PCT;
+
+    if !out.quiet {
+        writeln!(out.sink, "Tone Verification");
+        writeln!(
+            out.sink,
+            "File: {}",
+            out.display.display_text(&display_name)
+        );
+        writeln!(
+            out.sink,
+            "Expected: {:.1} Hz at {:.1} dBFS",
+            expected.freq_hz, expected.level_dbfs
+        );
+        writeln!(out.sink);
+    }
+
+    writeln!(
+        out.sink,
+        "Frequency: {:>8.1} Hz  (deviation {:+.1} Hz, {:+.2}%)  {}",
+        measured.freq_hz,
+        freq_dev,
+        freq_dev_pct,
+        status(freq_ok)
+    );
+    writeln!(
+        out.sink,
+        "Level:     {:>8.2} dBFS (deviation {:+.2} dB)  {}",
+        measured.level_dbfs,
+        level_dev,
+        status(level_ok)
+    );
+    writeln!(
+        out.sink,
+        "Purity:    {:>8.1} %   (power within the tone peak)  {}",
+        measured.purity_pct,
+        status(purity_ok)
+    );
+
+    if !out.quiet {
+        writeln!(out.sink);
+        writeln!(
+            out.sink,
+            "Tolerance: frequency {pm}{}%, level {pm}{} dB, purity {}{}%",
+            FREQ_TOLERANCE_PCT,
+            LEVEL_TOLERANCE_DB,
+            out.display.symbol("≥", ">="),
+            MIN_PURITY_PCT,
+            pm = out.display.symbol("±", "+/-")
+        );
+    }
+
+    let passed = freq_ok && level_ok && purity_ok;
+    writeln!(out.sink, "Result: {}", status(passed));
+    passed
+}
+
+fn status(ok: bool) -> ColoredString {
+    if ok {
+        "PASS".green()
+    } else {
+        "FAIL".red().bold()
+    }
+}
+
+/// Measure the dominant tone's frequency (interpolated spectral peak) and RMS level
+///
+/// Level is reported in dBFS relative to a full-scale sine (AES17), so a sine
+/// with peak amplitude 1.0 reads 0 dBFS. It is measured over the blocks where the tone
+/// is present, so silence before or after it doesn't lower the reading.
+fn measure_tone(
+    samples: &[f32],
+    sample_rate: u32,
+    deterministic: bool,
+) -> Result<ToneMeasurement, String> {
+    if samples.len() < FFT_SIZE {
+        return Err("file too short for tone verification".to_string());
+    }
+
+    let window = create_hanning_window(FFT_SIZE);
+    let fft = plan_fft_forward::<f32>(FFT_SIZE, deterministic);
+    let nyquist_bin = FFT_SIZE / 2;
+
+    let mut spectrum = vec![0.0f64; nyquist_bin];
+    let mut pos = 0;
+    while pos + FFT_SIZE <= samples.len() {
+        let mut buffer: Vec<Complex<f32>> = (0..FFT_SIZE)
+            .map(|j| Complex::new(samples[pos + j] * window[j], 0.0))
+            .collect();
+        fft.process(&mut buffer);
+        for (acc, c) in spectrum.iter_mut().zip(&buffer) {
+            *acc += c.norm_sqr() as f64;
+        }
+        pos += HOP_SIZE;
+    }
+
+    // Skip DC bin when searching for the peak
+    let peak_bin = (1..nyquist_bin)
+        .max_by(|&a, &b| spectrum[a].total_cmp(&spectrum[b]))
+        .unwrap_or(1);
+    let total_power: f64 = spectrum.iter().sum();
+    if total_power <= 0.0 {
+        return Err("no signal found".to_string());
+    }
+
+    // Parabolic interpolation on log power around the peak
+    let offset = if peak_bin + 1 < nyquist_bin {
+        let a = spectrum[peak_bin - 1].max(f64::MIN_POSITIVE).ln();
+        let b = spectrum[peak_bin].max(f64::MIN_POSITIVE).ln();
+        let c = spectrum[peak_bin + 1].max(f64::MIN_POSITIVE).ln();
+        let denom = a - 2.0 * b + c;
+        if denom.abs() > f64::EPSILON {
+            0.5 * (a - c) / denom
+        } else {
+            0.0
+        }
+    } else {
+        0.0
+    };
+    let freq_per_bin = sample_rate as f64 / FFT_SIZE as f64;
+    let freq_hz = (peak_bin as f64 + offset) * freq_per_bin;
+
+    let lo = peak_bin.saturating_sub(PEAK_HALF_WIDTH_BINS);
+    let hi = (peak_bin + PEAK_HALF_WIDTH_BINS + 1).min(nyquist_bin);
+    let peak_power: f64 = spectrum[lo..hi].iter().sum();
+    let purity_pct = peak_power / total_power * 100.0;
+
+    let level_dbfs = 10.0
+        * (tone_mean_square(samples) * 2.0)
+            .max(f64::MIN_POSITIVE)
+            .log10();
+
+    Ok(ToneMeasurement {
+        freq_hz,
+        level_dbfs,
+        purity_pct,
+    })
+}
+
+/// Median mean square of the FFT-sized blocks within `TONE_PRESENT_RANGE_DB` of the
+/// loudest one; the median leaves out the blocks the tone only partly fills
+fn tone_mean_square(samples: &[f32]) -> f64 {
+    let blocks: Vec<f64> = samples
+        .chunks(FFT_SIZE)
+        .map(|block| {
+            block.iter().map(|&s| (s as f64) * (s as f64)).sum::<f64>() / block.len() as f64
+        })
+        .collect();
+    let loudest = blocks.iter().copied().fold(0.0, f64::max);
+    let threshold = loudest * 10f64.powf(-TONE_PRESENT_RANGE_DB / 10.0);
+    let mut present: Vec<f64> = blocks.into_iter().filter(|&ms| ms >= threshold).collect();
+    present.sort_by(f64::total_cmp);
+    match present.len() {
+        0 => 0.0,
+        n if n % 2 == 1 => present[n / 2],
+        n => (present[n / 2 - 1] + present[n / 2]) / 2.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    fn sine(freq: f32, amplitude: f32, sample_rate: u32, secs: f32) -> Vec<f32> {
+        let n = (sample_rate as f32 * secs) as usize;
+        (0..n)
+            .map(|i| amplitude * (2.0 * PI * freq * i as f32 / sample_rate as f32).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_tone_spec_parse() {
+        let spec: ToneSpec = "1000:-20".parse().unwrap();
+        assert_eq!(spec.freq_hz, 1000.0);
+        assert_eq!(spec.level_dbfs, -20.0);
+
+        let spec: ToneSpec = "440:-18dBFS".parse().unwrap();
+        assert_eq!(spec.level_dbfs, -18.0);
+
+        assert!("1000".parse::<ToneSpec>().is_err());
+        assert!("abc:-20".parse::<ToneSpec>().is_err());
+        assert!("0:-20".parse::<ToneSpec>().is_err());
+        assert!("nan:-20".parse::<ToneSpec>().is_err());
+        assert!("inf:-20".parse::<ToneSpec>().is_err());
+    }
+
+    #[test]
+    fn test_measure_tone_frequency_and_level() {
+        // Peak amplitude 0.1 = -20 dBFS
+        let samples = sine(997.0, 0.1, 48000, 2.0);
+        let m = measure_tone(&samples, 48000, false).unwrap();
+        assert!((m.freq_hz - 997.0).abs() < 0.5, "freq {}", m.freq_hz);
+        assert!((m.level_dbfs + 20.0).abs() < 0.05, "level {}", m.level_dbfs);
+        assert!(m.purity_pct > 99.0, "purity {}", m.purity_pct);
+    }
+
+    #[test]
+    fn test_measure_tone_level_ignores_surrounding_silence() {
+        let mut samples = vec![0.0; 48000];
+        samples.extend(sine(1000.0, 0.1, 48000, 3.0));
+        samples.extend(vec![0.0; 96000]);
+        let m = measure_tone(&samples, 48000, false).unwrap();
+        assert!((m.level_dbfs + 20.0).abs() < 0.05, "level {}", m.level_dbfs);
+    }
+}
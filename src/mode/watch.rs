@@ -0,0 +1,114 @@
+//! Watch mode: audio files analyzed as they appear or change in a folder, e.g. a bounce folder
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant, SystemTime};
+
+use notify::{EventKind, RecursiveMode, Watcher};
+
+use crate::analysis::{AnalysisConfig, AnalysisContext};
+use crate::audio::{LoadOptions, TARGET_SAMPLE_RATE};
+use crate::output::{Output, print_error, print_warning};
+
+use super::analyze_file;
+use super::batch::{is_audio_file, print_result, print_row_header};
+
+/// Time a file must go without changes before it is analyzed, so bounces in
+/// progress are read once they are finished
+const SETTLE_TIME: Duration = Duration::from_secs(1);
+
+/// Watch a directory until interrupted, printing one row (or JSON line) for every audio
+/// file created or changed in it once the file has settled
+pub fn run_watch(dir: &str, load_options: &LoadOptions, config: &AnalysisConfig, out: &Output) {
+    let bands = config.bands();
+
+    let (sender, receiver) = mpsc::channel();
+    // Events stop once the watcher is dropped, so it lives as long as the loop
+    let _watcher = notify::recommended_watcher(sender)
+        .and_then(|mut watcher| {
+            watcher.watch(Path::new(dir), RecursiveMode::NonRecursive)?;
+            Ok(watcher)
+        })
+        .unwrap_or_else(|e| {
+            print_error(&format!("{}: cannot watch the directory ({})", dir, e));
+            std::process::exit(1);
+        });
+
+    if !out.display.json_output() {
+        if !out.quiet {
+            writeln!(out.sink);
+            writeln!(
+                out.sink,
+                "Watching {}, {}",
+                dir,
+                out.share_title(out.weighted)
+            );
+            out.print_bands(bands);
+        }
+        print_row_header(bands, out);
+    }
+    if !out.quiet {
+        eprintln!("Waiting for new or changed audio files (Ctrl-C to stop)");
+    }
+
+    let mut ctx = AnalysisContext::new(TARGET_SAMPLE_RATE, config);
+    // Last change seen of each file waiting to settle
+    let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+    // Size and modification time of each file when it was analyzed
+    let mut analyzed: HashMap<PathBuf, (u64, Option<SystemTime>)> = HashMap::new();
+
+    loop {
+        let timeout = pending
+            .values()
+            .map(|changed| SETTLE_TIME.saturating_sub(changed.elapsed()))
+            .min()
+            .unwrap_or(Duration::MAX);
+        match receiver.recv_timeout(timeout) {
+            Ok(Ok(event)) => {
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    for path in event.paths.into_iter().filter(|p| is_audio_file(p)) {
+                        pending.insert(path, Instant::now());
+                    }
+                }
+            }
+            Ok(Err(e)) => print_warning(&format!("{}: {}", dir, e)),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        let mut settled: Vec<PathBuf> = pending
+            .iter()
+            .filter(|(_, changed)| changed.elapsed() >= SETTLE_TIME)
+            .map(|(path, _)| path.clone())
+            .collect();
+        settled.sort();
+        for path in settled {
+            pending.remove(&path);
+            // Files moved away or deleted meanwhile are skipped
+            let Ok(metadata) = std::fs::metadata(&path) else {
+                continue;
+            };
+            let version = (metadata.len(), metadata.modified().ok());
+            if !metadata.is_file() || analyzed.get(&path) == Some(&version) {
+                continue;
+            }
+            analyzed.insert(path.clone(), version);
+
+            let filename = path.to_string_lossy();
+            match analyze_file(&filename, bands, &mut ctx, !out.quiet, load_options) {
+                Ok(stats) => {
+                    out.record_verify(stats.verify.as_ref());
+                    print_result(
+                        &stats,
+                        config,
+                        out,
+                        load_options.highpass_hz,
+                        out.display.json_output(),
+                    )
+                }
+                Err(e) => print_error(&e),
+            }
+        }
+    }
+}
@@ -1,34 +1,287 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+use std::sync::Mutex;
+
+use clap::ValueEnum;
 use colored::*;
+use serde_json::{Number, Value};
+use unicode_width::UnicodeWidthStr;
 
-use crate::analysis::Band;
+use crate::analysis::{
+    AnalysisConfig, Band, COVERAGE_TOLERANCE, EnergyMetric, GROUP_LABELS, MetricSide,
+    PARSEVAL_TOLERANCE, VerifySummary, Weighting, band_group,
+};
+use crate::chart::{ChartOptions, format_freq, round_to};
 
-fn style_label(label: &str) -> ColoredString {
-    label.bold()
+/// Column header style for band tables
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub(crate) enum HeaderStyle {
+    /// Band labels (DC, SUB1, ...)
+    #[default]
+    Labels,
+    /// Zero-based band indices
+    Indices,
+    /// Lower band edge frequency
+    Freqs,
 }
 
-pub(crate) fn print_error(msg: &str) {
-    eprintln!("{}: {}", "error".red().bold(), msg);
+/// Format of the analysis results on stdout
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub(crate) enum OutputFormat {
+    /// Column-aligned tables
+    #[default]
+    Text,
+    /// A single JSON document
+    Json,
+}
+
+/// How timeline rows and chart time axes show where an interval starts
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub(crate) enum TimeFormat {
+    /// Minutes and seconds (minutes keep counting past the hour)
+    #[default]
+    #[value(name = "mm:ss")]
+    MinutesSeconds,
+    /// Hours, minutes, and seconds
+    #[value(name = "hh:mm:ss")]
+    HoursMinutesSeconds,
+    /// Seconds from the start
+    Seconds,
+    /// Sample offset at the file's own sample rate
+    Samples,
 }
 
-pub(crate) fn print_percentages(powers: &[f64]) {
-    let total: f64 = powers.iter().sum();
-    if total > 0.0 {
-        for power in powers {
-            print!(" {:>5.1}", (power / total) * 100.0);
+/// How results are printed: table layout, number formatting, and optional tables (built
+/// once from the command line and passed to the modes)
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct DisplayOptions {
+    /// Column headers of band tables (`--header-style`)
+    pub(crate) header_style: HeaderStyle,
+    /// Tables or JSON on stdout (`--format`)
+    pub(crate) format: OutputFormat,
+    /// How interval start times are shown (`--time-format`)
+    pub(crate) time_format: TimeFormat,
+    /// Decimal places of table, report, and chart values (`--decimals`)
+    pub(crate) decimals: usize,
+    /// Round JSON and CSV numbers like the tables (`--round-output`)
+    pub(crate) round_output: bool,
+    /// Add K-weighted rows to the dynamics tables (`--weighted-dynamics`)
+    pub(crate) weighted_dynamics: bool,
+    /// Also match per-frame share distributions in comparisons (`--match-distributions`)
+    pub(crate) match_distributions: bool,
+    /// Restrict console output to plain ASCII (`--ascii`)
+    pub(crate) ascii: bool,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        Self {
+            header_style: HeaderStyle::default(),
+            format: OutputFormat::default(),
+            time_format: TimeFormat::default(),
+            decimals: 1,
+            round_output: false,
+            weighted_dynamics: false,
+            match_distributions: false,
+            ascii: false,
         }
+    }
+}
+
+impl DisplayOptions {
+    /// Width of the time column of timeline tables, up to the space before the first band
+    pub(crate) fn time_column_width(&self) -> usize {
+        match self.time_format {
+            TimeFormat::MinutesSeconds => 6,
+            TimeFormat::HoursMinutesSeconds => 9,
+            TimeFormat::Seconds => 8,
+            TimeFormat::Samples => 12,
+        }
+    }
+
+    /// Start of an interval in the `--time-format`; `sample_rate` is the file's own rate
+    pub(crate) fn format_timestamp(&self, seconds: f64, sample_rate: u32) -> String {
+        match self.time_format {
+            TimeFormat::MinutesSeconds => format_time(seconds as f32).trim_end().to_string(),
+            TimeFormat::HoursMinutesSeconds => {
+                let secs = seconds as u64;
+                format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+            }
+            TimeFormat::Seconds if seconds.fract() == 0.0 => format!("{}", seconds),
+            TimeFormat::Seconds => format!("{:.1}", seconds),
+            TimeFormat::Samples => format!("{}", (seconds * sample_rate as f64).round() as u64),
+        }
+    }
+
+    /// Time column label of a timeline row, padded to `time_column_width`
+    pub(crate) fn timestamp_label(&self, seconds: f64, sample_rate: u32) -> String {
+        format!(
+            "{:<w$}",
+            self.format_timestamp(seconds, sample_rate),
+            w = self.time_column_width()
+        )
+    }
+
+    /// Heading of a timeline table's time column, e.g. "TIME" or "AVG", padded to its width
+    pub(crate) fn time_column(&self, heading: &str) -> String {
+        format!("{:<w$}", heading, w = self.time_column_width())
+    }
+
+    /// Whether results go to stdout as JSON instead of tables
+    pub(crate) fn json_output(&self) -> bool {
+        self.format == OutputFormat::Json
+    }
+
+    /// `value` rounded to the printed decimal places
+    pub(crate) fn round_value(&self, value: f64) -> f64 {
+        round_to(value, self.decimals)
+    }
+
+    /// Round the fractional numbers of a JSON document to the printed decimal places;
+    /// without `--round-output`, JSON keeps full precision for further analysis
+    pub(crate) fn round_json(&self, value: &mut Value) {
+        if !self.round_output {
+            return;
+        }
+        match value {
+            Value::Number(n) if n.is_f64() => {
+                let rounded = n.as_f64().map(|v| self.round_value(v));
+                if let Some(rounded) = rounded.and_then(Number::from_f64) {
+                    *n = rounded;
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|v| self.round_json(v)),
+            Value::Object(fields) => fields.values_mut().for_each(|v| self.round_json(v)),
+            _ => {}
+        }
+    }
+
+    /// A number for CSV output: full precision unless `--round-output`, empty when unknown
+    pub(crate) fn csv_number(&self, value: f64) -> String {
+        if !value.is_finite() {
+            String::new()
+        } else if self.round_output {
+            self.round_value(value).to_string()
+        } else {
+            value.to_string()
+        }
+    }
+
+    /// `unicode`, or its `fallback` spelling in ASCII mode (e.g. "±" and "+/-")
+    pub(crate) fn symbol(&self, unicode: &'static str, fallback: &'static str) -> &'static str {
+        if self.ascii { fallback } else { unicode }
+    }
+
+    /// Text for the console; in ASCII mode non-ASCII characters are replaced with '?'
+    pub(crate) fn display_text<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        if self.ascii && !text.is_ascii() {
+            Cow::Owned(
+                text.chars()
+                    .map(|c| if c.is_ascii() { c } else { '?' })
+                    .collect(),
+            )
+        } else {
+            Cow::Borrowed(text)
+        }
+    }
+
+    /// A band's label as printed in tables, see `display_text`
+    pub(crate) fn band_label<'b>(&self, band: &'b Band) -> Cow<'b, str> {
+        self.display_text(&band.label)
+    }
+
+    /// Widest printed band label, at least `min` columns
+    pub(crate) fn band_label_width(&self, bands: &[Band], min: usize) -> usize {
+        bands
+            .iter()
+            .map(|b| text_width(&self.band_label(b)))
+            .max()
+            .unwrap_or(0)
+            .max(min)
+    }
+}
+
+/// Terminal columns taken by `text`; wide (e.g. CJK) characters take two. `{:w$}` pads by
+/// chars, so tables holding user text pad with `pad_start` and `pad_end` instead
+pub(crate) fn text_width(text: &str) -> usize {
+    text.width()
+}
+
+/// `text` right-aligned to `width` terminal columns
+pub(crate) fn pad_start(text: &str, width: usize) -> String {
+    format!(
+        "{}{}",
+        " ".repeat(width.saturating_sub(text_width(text))),
+        text
+    )
+}
+
+/// `text` left-aligned to `width` terminal columns
+pub(crate) fn pad_end(text: &str, width: usize) -> String {
+    format!(
+        "{}{}",
+        text,
+        " ".repeat(width.saturating_sub(text_width(text)))
+    )
+}
+
+/// A CSV field, quoted when it holds a comma, quote, or line break
+pub(crate) fn csv_field(text: &str) -> Cow<'_, str> {
+    if text.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", text.replace('"', "\"\"")))
     } else {
-        for _ in powers {
-            print!("     -");
+        Cow::Borrowed(text)
+    }
+}
+
+/// Where the results are printed: stdout, or the `--output` file. Progress, warnings,
+/// and errors always go to stderr
+pub(crate) struct Sink(Mutex<Box<dyn Write + Send>>);
+
+impl Sink {
+    pub(crate) fn stdout() -> Self {
+        Sink(Mutex::new(Box::new(std::io::stdout())))
+    }
+
+    /// Drop everything printed
+    pub(crate) fn null() -> Self {
+        Sink(Mutex::new(Box::new(std::io::sink())))
+    }
+
+    /// Print into a new file at `path`
+    pub(crate) fn create(path: &str) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("{}: {}", path, e))?;
+        Ok(Sink(Mutex::new(Box::new(file))))
+    }
+
+    /// Backs `write!` and `writeln!`; like `print!`, panics if the output can't be written
+    pub(crate) fn write_fmt(&self, args: fmt::Arguments) {
+        let mut writer = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = writer.write_fmt(args) {
+            panic!("failed printing output: {}", e);
         }
     }
 }
 
-pub(crate) fn print_separator(bands: &[Band], prefix_width: usize) {
-    print!("{}", "-".repeat(prefix_width));
-    for _ in bands {
-        print!("------");
+fn style_label(label: &str) -> ColoredString {
+    label.bold()
+}
+
+pub(crate) fn print_error(msg: &str) {
+    eprintln!("{}: {}", "error".red().bold(), msg);
+}
+
+pub(crate) fn print_warning(msg: &str) {
+    eprintln!("{} {}", "Warning:".yellow(), msg);
+}
+
+/// Print the warnings of decoding an input (see `AudioStream::warnings`)
+pub(crate) fn print_warnings(warnings: &[String]) {
+    for warning in warnings {
+        print_warning(warning);
     }
-    println!();
 }
 
 pub(crate) fn format_time(seconds: f32) -> String {
@@ -37,6 +290,21 @@ pub(crate) fn format_time(seconds: f32) -> String {
     format!("{:02}:{:02} ", mins, secs)
 }
 
+/// How much of a file `--active-only` kept, e.g. `12:30 of 15:00 (83%)`
+pub(crate) fn format_active(active_secs: f64, duration_secs: f64) -> String {
+    let pct = if duration_secs > 0.0 {
+        active_secs / duration_secs * 100.0
+    } else {
+        0.0
+    };
+    format!(
+        "{} of {} ({:.0}%)",
+        format_time(active_secs as f32).trim(),
+        format_time(duration_secs as f32).trim(),
+        pct
+    )
+}
+
 pub(crate) fn get_display_name(filename: &str) -> &str {
     std::path::Path::new(filename)
         .file_name()
@@ -44,167 +312,685 @@ pub(crate) fn get_display_name(filename: &str) -> &str {
         .unwrap_or(filename)
 }
 
-pub(crate) fn print_bands(bands: &[Band]) {
-    println!("Bands:");
-    for band in bands {
-        if band.high_hz >= f32::MAX / 2.0 {
-            println!("  {:>4}: {:5.0}+      Hz", band.label, band.low_hz);
-        } else {
-            println!(
-                "  {:>4}: {:5.0}-{:5.0} Hz",
-                band.label, band.low_hz, band.high_hz
-            );
+/// Column name of a `--metric`, e.g. `<100Hz` or `>10kHz`
+pub(crate) fn metric_label(metric: &EnergyMetric) -> String {
+    let side = match metric.side {
+        MetricSide::Below => "<",
+        MetricSide::Above => ">",
+    };
+    format!("{}{}Hz", side, format_freq(metric.hz))
+}
+
+/// Prints band tables laid out for the analyzed bands and labeled for the analysis
+/// settings (built once from the command line and passed to the modes)
+pub(crate) struct Output {
+    /// Show the weighted (-w) values
+    pub(crate) weighted: bool,
+    /// Leave out progress and informational messages
+    pub(crate) quiet: bool,
+    /// Formatting of the printed values and the optional tables
+    pub(crate) display: DisplayOptions,
+    /// How charts are drawn
+    pub(crate) charts: ChartOptions,
+    /// Where the results are printed
+    pub(crate) sink: Sink,
+    /// `--verify` checks of the analyses of this run
+    verify: Mutex<VerifySummary>,
+    /// Width of one band column, widened to fit custom band labels and extra decimals
+    cell_width: usize,
+    /// Bands kept out of the percentages (`--exclude-bands`)
+    excluded_bands: Vec<usize>,
+    weighting: Weighting,
+    /// Whether `--metric` columns are reported
+    has_metrics: bool,
+    /// Whether a `--target-curve` is set
+    has_target_curve: bool,
+    group_edges: [f32; 2],
+}
+
+impl Output {
+    pub(crate) fn new(
+        config: &AnalysisConfig,
+        display: DisplayOptions,
+        charts: ChartOptions,
+        sink: Sink,
+        weighted: bool,
+        quiet: bool,
+    ) -> Self {
+        let cell_width = display
+            .band_label_width(config.bands(), 5)
+            .max(4 + display.decimals);
+        Output {
+            weighted,
+            quiet,
+            display,
+            charts,
+            sink,
+            verify: Mutex::default(),
+            cell_width,
+            excluded_bands: config.excluded_bands().to_vec(),
+            weighting: config.weighting.clone(),
+            has_metrics: !config.metrics.is_empty(),
+            has_target_curve: config.target_curve.is_some(),
+            group_edges: config.group_edges(),
         }
     }
-    println!();
-}
 
-pub(crate) fn print_header(bands: &[Band], prefix: &str) {
-    print!("{}", prefix);
-    for band in bands {
-        print!(" {:>5}", band.label);
+    /// Print an informational message to stderr, unless quiet
+    pub(crate) fn print_info(&self, msg: &str) {
+        if !self.quiet {
+            eprintln!("{}", msg);
+        }
     }
-    println!();
-}
 
-pub(crate) fn print_row(label: &str, values: &[f64]) {
-    print!("{}", label);
-    for v in values {
-        if v.is_finite() {
-            print!(" {:>5.1}", v);
-        } else {
-            print!("     -");
+    pub(crate) fn print_bands(&self, bands: &[Band]) {
+        writeln!(self.sink, "Bands:");
+        let label_width = self.display.band_label_width(bands, 4);
+        for band in bands {
+            let label = pad_start(&self.display.band_label(band), label_width);
+            if band.high_hz >= f32::MAX / 2.0 {
+                writeln!(self.sink, "  {}: {:5.0}+      Hz", label, band.low_hz);
+            } else {
+                writeln!(
+                    self.sink,
+                    "  {}: {:5.0}-{:5.0} Hz",
+                    label, band.low_hz, band.high_hz
+                );
+            }
         }
+        writeln!(self.sink);
     }
-    println!();
-}
 
-pub(crate) fn print_row_styled(label_prefix: &str, label_suffix: &str, values: &[f64]) {
-    print!("{}{}", style_label(label_prefix), label_suffix);
-    for v in values {
-        if v.is_finite() {
-            print!(" {:>5.1}", v);
+    /// Print a placeholder cell for values that are hidden or undefined
+    fn print_missing(&self) {
+        write!(self.sink, " {:>w$}", "-", w = self.cell_width);
+    }
+
+    /// Print the placeholder cell of a band column: `excl` for `--exclude-bands`, otherwise "-"
+    fn print_missing_band(&self, band: usize) {
+        if self.excluded_bands.contains(&band) {
+            write!(self.sink, " {:>w$}", "excl", w = self.cell_width);
         } else {
-            print!("     -");
+            self.print_missing();
         }
     }
-    println!();
-}
 
-pub(crate) fn print_row_masked(label: &str, values: &[f64], mask: &[f64], threshold: f64) {
-    print!("{}", label);
-    for (v, m) in values.iter().zip(mask) {
-        if *m < threshold || !v.is_finite() {
-            print!("     -");
+    pub(crate) fn print_percentages(&self, powers: &[f64]) {
+        let total: f64 = powers.iter().sum();
+        if total > 0.0 {
+            for power in powers {
+                write!(
+                    self.sink,
+                    " {:>w$.p$}",
+                    (power / total) * 100.0,
+                    w = self.cell_width,
+                    p = self.display.decimals
+                );
+            }
         } else {
-            print!(" {:>5.1}", v);
+            for _ in powers {
+                self.print_missing();
+            }
         }
     }
-    println!();
-}
 
-pub(crate) fn print_row_masked_styled(
-    label_prefix: &str,
-    label_suffix: &str,
-    values: &[f64],
-    mask: &[f64],
-    threshold: f64,
-) {
-    print!("{}{}", style_label(label_prefix), label_suffix);
-    for (v, m) in values.iter().zip(mask) {
-        if *m < threshold || !v.is_finite() {
-            print!("     -");
-        } else {
-            print!(" {:>5.1}", v);
+    pub(crate) fn print_separator(&self, bands: &[Band], prefix_width: usize) {
+        write!(self.sink, "{}", "-".repeat(prefix_width));
+        for _ in bands {
+            write!(self.sink, "{}", "-".repeat(self.cell_width + 1));
         }
+        writeln!(self.sink);
     }
-    println!();
-}
 
-pub(crate) fn print_diff_row_styled(label_prefix: &str, label_suffix: &str, a: &[f64], b: &[f64]) {
-    print!("{}{}", style_label(label_prefix), label_suffix);
-    for (va, vb) in a.iter().zip(b) {
-        let diff = vb - va;
-        if diff.is_finite() {
-            print_colored_diff(diff);
+    /// Print one timeline row: time label followed by band percentages
+    pub(crate) fn print_interval_row(&self, time_label: &str, percentages: &[f64], bands: &[Band]) {
+        write!(self.sink, "{}", time_label);
+        for pct in percentages.iter().take(bands.len()) {
+            // Shares that round to zero print as 0.0 rather than -0.0
+            let value = if self.display.round_value(*pct) == 0.0 {
+                0.0
+            } else {
+                *pct
+            };
+            write!(
+                self.sink,
+                " {:>w$.p$}",
+                value,
+                w = self.cell_width,
+                p = self.display.decimals
+            );
+        }
+        writeln!(self.sink);
+    }
+
+    /// Print one batch row: integrated loudness, band percentages, then the file name
+    pub(crate) fn print_batch_row(
+        &self,
+        lufs: f64,
+        lufs_width: usize,
+        percentages: &[f64],
+        name: &str,
+    ) {
+        if lufs.is_finite() {
+            write!(self.sink, "{:>w$.1}", lufs, w = lufs_width);
         } else {
-            print!("     -");
+            write!(self.sink, "{:>w$}", "-", w = lufs_width);
         }
+        for pct in percentages {
+            write!(
+                self.sink,
+                " {:>w$.p$}",
+                pct,
+                w = self.cell_width,
+                p = self.display.decimals
+            );
+        }
+        writeln!(self.sink, "  {}", self.display.display_text(name));
     }
-    println!();
-}
 
-pub(crate) fn print_diff_row_masked_styled(
-    label_prefix: &str,
-    label_suffix: &str,
-    a: &[f64],
-    b: &[f64],
-    mask_a: &[f64],
-    mask_b: &[f64],
-    threshold: f64,
-) {
-    print!("{}{}", style_label(label_prefix), label_suffix);
-    for (((va, vb), ma), mb) in a.iter().zip(b).zip(mask_a).zip(mask_b) {
-        if *ma < threshold || *mb < threshold {
-            print!("     -");
+    pub(crate) fn print_header(&self, bands: &[Band], prefix: &str) {
+        let style = self.display.header_style;
+        write!(self.sink, "{}", prefix);
+        for (i, band) in bands.iter().enumerate() {
+            match style {
+                HeaderStyle::Labels => write!(
+                    self.sink,
+                    " {}",
+                    pad_start(&self.display.band_label(band), self.cell_width)
+                ),
+                HeaderStyle::Indices => write!(self.sink, " {:>w$}", i, w = self.cell_width),
+                HeaderStyle::Freqs if band.high_hz >= f32::MAX / 2.0 => {
+                    let label = format!("{}+", format_freq(band.low_hz));
+                    write!(self.sink, " {:>w$}", label, w = self.cell_width)
+                }
+                HeaderStyle::Freqs => {
+                    write!(
+                        self.sink,
+                        " {:>w$}",
+                        format_freq(band.low_hz),
+                        w = self.cell_width
+                    )
+                }
+            }
+        }
+        writeln!(self.sink);
+    }
+
+    /// Print a band x file matrix: one row per band, one column per file (`None` prints as "-").
+    /// With `signed`, values are differences and are colored like the diff rows; `noise`
+    /// (same shape as `columns`) dims differences within measurement variation.
+    pub(crate) fn print_matrix(
+        &self,
+        bands: &[Band],
+        column_labels: &[String],
+        columns: &[Vec<Option<f64>>],
+        signed: bool,
+        noise: Option<&[Vec<f64>]>,
+    ) {
+        let label_width = self.display.band_label_width(bands, 4);
+        write!(self.sink, "{:<w$}", "BAND", w = label_width);
+        for label in column_labels {
+            write!(
+                self.sink,
+                " {}",
+                style_label(&format!("{:>w$}", label, w = self.cell_width))
+            );
+        }
+        writeln!(self.sink);
+        writeln!(
+            self.sink,
+            "{}",
+            "-".repeat(label_width + column_labels.len() * (self.cell_width + 1))
+        );
+
+        for (i, band) in bands.iter().enumerate() {
+            write!(
+                self.sink,
+                "{}",
+                pad_end(&self.display.band_label(band), label_width)
+            );
+            for (c, column) in columns.iter().enumerate() {
+                match column[i] {
+                    Some(v) if v.is_finite() && signed => {
+                        self.print_diff_cell(i, v, noise.map_or(f64::NAN, |n| n[c][i]))
+                    }
+                    Some(v) if v.is_finite() => {
+                        write!(
+                            self.sink,
+                            " {:>w$.p$}",
+                            v,
+                            w = self.cell_width,
+                            p = self.display.decimals
+                        )
+                    }
+                    _ => self.print_missing_band(i),
+                }
+            }
+            writeln!(self.sink);
+        }
+    }
+
+    /// Print the title of the LOW/MID/HIGH group summary with each group's range
+    pub(crate) fn print_group_header(&self, bands: &[Band]) {
+        let ranges: Vec<String> = GROUP_LABELS
+            .iter()
+            .enumerate()
+            .map(|(group, label)| {
+                let members: Vec<&Band> = bands
+                    .iter()
+                    .filter(|b| band_group(b, self.group_edges) == group)
+                    .collect();
+                match (members.first(), members.last()) {
+                    (Some(first), Some(last)) if last.high_hz < f32::MAX / 2.0 => format!(
+                        "{} {}-{}",
+                        label,
+                        format_freq(first.low_hz),
+                        format_freq(last.high_hz)
+                    ),
+                    (Some(first), _) => format!("{} {}+", label, format_freq(first.low_hz)),
+                    _ => label.to_string(),
+                }
+            })
+            .collect();
+        writeln!(self.sink, "[Band Groups] {} Hz", ranges.join(", "));
+    }
+
+    /// Explain the `--metric` columns under the legend, when there are any
+    pub(crate) fn print_metric_legend(&self) {
+        if self.has_metrics {
+            writeln!(
+                self.sink,
+                "Energy Split: Share of the whole spectrum's Raw energy below (<) or above (>) each --metric frequency"
+            );
+        }
+    }
+
+    pub(crate) fn print_row(&self, label: &str, values: &[f64]) {
+        write!(self.sink, "{}", label);
+        for (i, v) in values.iter().enumerate() {
+            if v.is_finite() {
+                write!(
+                    self.sink,
+                    " {:>w$.p$}",
+                    v,
+                    w = self.cell_width,
+                    p = self.display.decimals
+                );
+            } else {
+                self.print_missing_band(i);
+            }
+        }
+        writeln!(self.sink);
+    }
+
+    pub(crate) fn print_row_styled(&self, label_prefix: &str, label_suffix: &str, values: &[f64]) {
+        write!(self.sink, "{}{}", style_label(label_prefix), label_suffix);
+        for (i, v) in values.iter().enumerate() {
+            if v.is_finite() {
+                write!(
+                    self.sink,
+                    " {:>w$.p$}",
+                    v,
+                    w = self.cell_width,
+                    p = self.display.decimals
+                );
+            } else {
+                self.print_missing_band(i);
+            }
+        }
+        writeln!(self.sink);
+    }
+
+    pub(crate) fn print_row_masked(
+        &self,
+        label: &str,
+        values: &[f64],
+        mask: &[f64],
+        threshold: f64,
+    ) {
+        write!(self.sink, "{}", label);
+        for (i, (v, m)) in values.iter().zip(mask).enumerate() {
+            // Excluded bands have a NaN share and are masked as well
+            if m.is_nan() || *m < threshold || !v.is_finite() {
+                self.print_missing_band(i);
+            } else {
+                write!(
+                    self.sink,
+                    " {:>w$.p$}",
+                    v,
+                    w = self.cell_width,
+                    p = self.display.decimals
+                );
+            }
+        }
+        writeln!(self.sink);
+    }
+
+    pub(crate) fn print_row_masked_styled(
+        &self,
+        label_prefix: &str,
+        label_suffix: &str,
+        values: &[f64],
+        mask: &[f64],
+        threshold: f64,
+    ) {
+        write!(self.sink, "{}{}", style_label(label_prefix), label_suffix);
+        for (i, (v, m)) in values.iter().zip(mask).enumerate() {
+            // Excluded bands have a NaN share and are masked as well
+            if m.is_nan() || *m < threshold || !v.is_finite() {
+                self.print_missing_band(i);
+            } else {
+                write!(
+                    self.sink,
+                    " {:>w$.p$}",
+                    v,
+                    w = self.cell_width,
+                    p = self.display.decimals
+                );
+            }
+        }
+        writeln!(self.sink);
+    }
+
+    pub(crate) fn print_diff_row_styled(
+        &self,
+        label_prefix: &str,
+        label_suffix: &str,
+        a: &[f64],
+        b: &[f64],
+    ) {
+        write!(self.sink, "{}{}", style_label(label_prefix), label_suffix);
+        for (i, (va, vb)) in a.iter().zip(b).enumerate() {
+            let diff = vb - va;
+            if diff.is_finite() {
+                self.print_colored_diff(diff);
+            } else {
+                self.print_missing_band(i);
+            }
+        }
+        writeln!(self.sink);
+    }
+
+    /// Print a row of signed values (e.g. deviations), colored like the diff rows
+    pub(crate) fn print_signed_row_styled(
+        &self,
+        label_prefix: &str,
+        label_suffix: &str,
+        values: &[f64],
+    ) {
+        write!(self.sink, "{}{}", style_label(label_prefix), label_suffix);
+        for (i, v) in values.iter().enumerate() {
+            if v.is_finite() {
+                self.print_colored_diff(*v);
+            } else {
+                self.print_missing_band(i);
+            }
+        }
+        writeln!(self.sink);
+    }
+
+    /// Like `print_diff_row_styled`, but differences no larger than `noise` (per band)
+    /// are dimmed instead of colored, as they are within measurement variation
+    pub(crate) fn print_diff_row_noise_styled(
+        &self,
+        label_prefix: &str,
+        label_suffix: &str,
+        a: &[f64],
+        b: &[f64],
+        noise: &[f64],
+    ) {
+        write!(self.sink, "{}{}", style_label(label_prefix), label_suffix);
+        for (i, ((va, vb), n)) in a.iter().zip(b).zip(noise).enumerate() {
+            self.print_diff_cell(i, vb - va, *n);
+        }
+        writeln!(self.sink);
+    }
+
+    /// One difference cell of a band, dimmed when within `noise` (NaN = unknown, never dimmed)
+    fn print_diff_cell(&self, band: usize, diff: f64, noise: f64) {
+        if !diff.is_finite() {
+            self.print_missing_band(band);
+        } else if diff.abs() <= noise && self.display.round_value(diff) != 0.0 {
+            let formatted = format!(
+                "{:>+w$.p$}",
+                diff,
+                w = self.cell_width,
+                p = self.display.decimals
+            );
+            write!(self.sink, " {}", formatted.dimmed());
         } else {
+            self.print_colored_diff(diff);
+        }
+    }
+
+    /// Like `print_diff_row_styled`, but masked where either of `masks` (the shares of
+    /// `a` and `b`) is below `threshold`
+    pub(crate) fn print_diff_row_masked_styled(
+        &self,
+        label_prefix: &str,
+        label_suffix: &str,
+        a: &[f64],
+        b: &[f64],
+        [mask_a, mask_b]: [&[f64]; 2],
+        threshold: f64,
+    ) {
+        write!(self.sink, "{}{}", style_label(label_prefix), label_suffix);
+        for (i, (((va, vb), ma), mb)) in a.iter().zip(b).zip(mask_a).zip(mask_b).enumerate() {
+            let diff = vb - va;
+            // Excluded bands have NaN shares and are masked as well
+            let masked = ma.is_nan() || mb.is_nan() || *ma < threshold || *mb < threshold;
+            if masked || !diff.is_finite() {
+                self.print_missing_band(i);
+            } else {
+                self.print_colored_diff(diff);
+            }
+        }
+        writeln!(self.sink);
+    }
+
+    fn print_colored_diff(&self, diff: f64) {
+        self.print_colored_diff_width(diff, self.cell_width);
+    }
+
+    /// Print one indented group summary line (e.g. `  Raw   LOW 42.1  MID 38.0  HIGH 19.9`)
+    pub(crate) fn print_group_row(&self, label: &str, groups: &[f64]) {
+        self.print_named_row(label, &GROUP_LABELS, groups);
+    }
+
+    /// Print one indented group difference line (b - a), colored like the band diff rows
+    pub(crate) fn print_group_diff_row(&self, label: &str, a: &[f64], b: &[f64]) {
+        self.print_named_diff_row(label, &GROUP_LABELS, a, b);
+    }
+
+    /// Print one indented line of named values, each after its name
+    pub(crate) fn print_named_row(&self, label: &str, names: &[impl AsRef<str>], values: &[f64]) {
+        write!(self.sink, "  {:<12}", label);
+        for (name, v) in names.iter().zip(values) {
+            write!(self.sink, "  {}", name.as_ref());
+            if v.is_finite() {
+                write!(
+                    self.sink,
+                    " {:>w$.p$}",
+                    v,
+                    w = 4 + self.display.decimals,
+                    p = self.display.decimals
+                );
+            } else {
+                write!(self.sink, " {:>w$}", "-", w = 4 + self.display.decimals);
+            }
+        }
+        writeln!(self.sink);
+    }
+
+    /// Print one indented line of named differences (b - a), colored like the band diff rows
+    pub(crate) fn print_named_diff_row(
+        &self,
+        label: &str,
+        names: &[impl AsRef<str>],
+        a: &[f64],
+        b: &[f64],
+    ) {
+        write!(self.sink, "  {:<12}", label);
+        for ((name, va), vb) in names.iter().zip(a).zip(b) {
+            write!(self.sink, "  {}", name.as_ref());
             let diff = vb - va;
             if diff.is_finite() {
-                print_colored_diff(diff);
+                self.print_colored_diff_width(diff, 4 + self.display.decimals);
             } else {
-                print!("     -");
+                write!(self.sink, " {:>w$}", "-", w = 4 + self.display.decimals);
             }
         }
+        writeln!(self.sink);
     }
-    println!();
-}
 
-fn print_colored_diff(diff: f64) {
-    let rounded = (diff * 10.0).round() / 10.0;
-    if rounded == 0.0 {
-        print!("   0.0");
-    } else {
-        let formatted = format!("{:>+5.1}", diff);
-        if rounded > 0.0 {
-            print!(" {}", formatted.green());
+    /// Legend line of the target curve table, when `--target-curve` is set
+    pub(crate) fn print_target_legend(&self) {
+        if self.has_target_curve {
+            writeln!(
+                self.sink,
+                "Target Curve: Raw(%) over the bands the curve covers; Dev is its level relative to the curve's share (dB)"
+            );
+        }
+    }
+
+    fn print_colored_diff_width(&self, diff: f64, width: usize) {
+        let rounded = self.display.round_value(diff);
+        if rounded == 0.0 {
+            write!(
+                self.sink,
+                " {:>w$.p$}",
+                0.0,
+                w = width,
+                p = self.display.decimals
+            );
         } else {
-            print!(" {}", formatted.red());
+            let formatted = format!("{:>+w$.p$}", diff, w = width, p = self.display.decimals);
+            if rounded > 0.0 {
+                write!(self.sink, " {}", formatted.green());
+            } else {
+                write!(self.sink, " {}", formatted.red());
+            }
         }
     }
-}
 
-pub(crate) fn print_diff_row(label: &str, a: &[f64], b: &[f64]) {
-    print!("{}", label);
-    for (va, vb) in a.iter().zip(b) {
-        let diff = vb - va;
-        if diff.is_finite() {
-            print_colored_diff(diff);
+    pub(crate) fn print_diff_row(&self, label: &str, a: &[f64], b: &[f64]) {
+        write!(self.sink, "{}", label);
+        for (i, (va, vb)) in a.iter().zip(b).enumerate() {
+            let diff = vb - va;
+            if diff.is_finite() {
+                self.print_colored_diff(diff);
+            } else {
+                self.print_missing_band(i);
+            }
+        }
+        writeln!(self.sink);
+    }
+
+    pub(crate) fn print_file_info(
+        &self,
+        display_name: &str,
+        sample_rate: u32,
+        channels: u16,
+        k_weighted: bool,
+    ) {
+        writeln!(
+            self.sink,
+            "File: {}",
+            self.display.display_text(display_name)
+        );
+        writeln!(
+            self.sink,
+            "Sample rate: {} Hz, Channels: {}",
+            sample_rate, channels
+        );
+        if k_weighted {
+            match &self.weighting {
+                Weighting::K => writeln!(self.sink, "Weighting: K-weighted (ITU-R BS.1770)"),
+                Weighting::C => writeln!(self.sink, "Weighting: C-weighted (IEC 61672)"),
+                Weighting::Z => writeln!(self.sink, "Weighting: Z-weighted (flat)"),
+                Weighting::Iso226(phon) => {
+                    writeln!(
+                        self.sink,
+                        "Weighting: {}-phon equal loudness (ISO 226:2003)",
+                        phon
+                    )
+                }
+                Weighting::Curve(curve) => {
+                    writeln!(
+                        self.sink,
+                        "Weighting: {}",
+                        self.display.display_text(&curve.name)
+                    )
+                }
+            }
+        }
+        writeln!(self.sink);
+    }
+
+    /// Title of a column of band shares, e.g. "K-wt(%)" or "Raw(%)"
+    pub(crate) fn share_title(&self, weighted: bool) -> String {
+        if weighted {
+            format!("{}(%)", self.weighting.label())
         } else {
-            print!("     -");
+            "Raw(%)".to_string()
         }
     }
-    println!();
-}
 
-pub(crate) fn print_file_info(
-    display_name: &str,
-    sample_rate: u32,
-    channels: u16,
-    k_weighted: bool,
-) {
-    println!("File: {}", display_name);
-    println!("Sample rate: {} Hz, Channels: {}", sample_rate, channels);
-    if k_weighted {
-        println!("Weighting: K-weighted (ITU-R BS.1770)");
+    pub(crate) fn print_legend(&self) {
+        writeln!(self.sink, "Raw: Percentage of total power in each band");
+        let weighted = self.weighting.label();
+        writeln!(
+            self.sink,
+            "{}: Same as Raw, but {}",
+            weighted,
+            self.weighting.name()
+        );
+        writeln!(self.sink, "Diff: Difference between {} and Raw", weighted);
+        writeln!(
+            self.sink,
+            "Dyn: Per-band dynamics - standard deviation of power (dB). Lower values suggest compression."
+        );
     }
-    println!();
-}
 
-pub(crate) fn print_legend() {
-    println!("Raw: Percentage of total power in each band");
-    println!("K-wt: Same as Raw, but with K-weighting applied");
-    println!("Diff: Difference between K-wt and Raw");
-    println!(
-        "Dyn: Per-band dynamics - standard deviation of power (dB). Lower values suggest compression."
-    );
+    /// Add the `--verify` checks of an analysis to the run's summary
+    pub(crate) fn record_verify(&self, summary: Option<&VerifySummary>) {
+        if let Some(summary) = summary {
+            let mut total = self.verify.lock().unwrap_or_else(|e| e.into_inner());
+            total.merge(summary);
+        }
+    }
+
+    /// Print the `--verify` energy-conservation summary to stderr; returns whether all checks passed
+    pub(crate) fn print_verify_summary(&self) -> bool {
+        let summary = self.verify.lock().unwrap_or_else(|e| e.into_inner());
+        eprintln!("[Verify]");
+        if summary.frames == 0 {
+            eprintln!("  No FFT frames were checked");
+            return true;
+        }
+
+        let status = |failures: usize| {
+            if failures == 0 {
+                "PASS".green()
+            } else {
+                "FAIL".red().bold()
+            }
+        };
+        eprintln!("  Frames checked: {}", summary.frames);
+        eprintln!(
+            "  {} Parseval (time vs spectral energy): max error {:.2e} (tolerance {:.0e}), {} frames over",
+            status(summary.parseval_failures),
+            summary.max_parseval_error,
+            PARSEVAL_TOLERANCE,
+            summary.parseval_failures
+        );
+        eprintln!(
+            "  {} Band coverage (bands vs spectrum): max error {:.2e} (tolerance {:.0e}), {} frames over",
+            status(summary.coverage_failures),
+            summary.max_coverage_error,
+            COVERAGE_TOLERANCE,
+            summary.coverage_failures
+        );
+        summary.passed()
+    }
 }
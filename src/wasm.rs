@@ -0,0 +1,30 @@
+//! JavaScript bindings for in-browser analysis (`wasm` feature)
+//!
+//! Browsers cannot hand files to the analyzer, so pages decode the audio themselves
+//! (e.g. with the Web Audio API's `decodeAudioData`) and pass one channel or a mono mix.
+
+use std::cell::RefCell;
+
+use wasm_bindgen::prelude::*;
+
+use crate::analysis::AnalysisConfig;
+use crate::analyzer::Analyzer;
+
+thread_local! {
+    /// Analyzer kept between calls so the FFT is planned only once
+    static ANALYZER: RefCell<Option<Analyzer>> = const { RefCell::new(None) };
+}
+
+/// Analyze mono samples in -1.0..=1.0 at any sample rate.
+/// Returns the result as a JSON string in the layout of [`crate::AnalysisResult`].
+#[wasm_bindgen]
+pub fn analyze_samples(samples: &[f32], sample_rate: u32) -> Result<String, JsError> {
+    let result = ANALYZER
+        .with_borrow_mut(|analyzer| {
+            analyzer
+                .get_or_insert_with(|| Analyzer::new(AnalysisConfig::default()))
+                .analyze_samples(samples, sample_rate)
+        })
+        .map_err(|e| JsError::new(&e))?;
+    serde_json::to_string(&result).map_err(|e| JsError::new(&e.to_string()))
+}
@@ -84,21 +84,31 @@ pub fn generate_multitone(
 
 /// Write samples as a WAV file to the given path
 pub fn write_wav(path: &Path, samples: &[f32], sample_rate: u32) -> std::io::Result<()> {
+    write_wav_channels(path, &[samples], sample_rate)
+}
+
+/// Write equally long channels (in WAV order, e.g. L R C LFE Ls Rs) as one WAV file
+pub fn write_wav_channels(
+    path: &Path,
+    channels: &[&[f32]],
+    sample_rate: u32,
+) -> std::io::Result<()> {
     let mut file = std::fs::File::create(path)?;
-    write_wav_to(&mut file, samples, sample_rate)
+    write_wav_to(&mut file, channels, sample_rate)
 }
 
-/// Write samples as WAV data to a writer
+/// Write interleaved channels as WAV data to a writer
 fn write_wav_to<W: Write>(
     writer: &mut W,
-    samples: &[f32],
+    channel_samples: &[&[f32]],
     sample_rate: u32,
 ) -> std::io::Result<()> {
-    let channels: u16 = 1;
+    let channels = channel_samples.len() as u16;
+    let frames = channel_samples[0].len();
     let bits_per_sample: u16 = 16;
     let byte_rate = sample_rate * channels as u32 * bits_per_sample as u32 / 8;
     let block_align = channels * bits_per_sample / 8;
-    let data_size = samples.len() as u32 * 2; // 16-bit = 2 bytes per sample
+    let data_size = (frames * channel_samples.len()) as u32 * 2; // 16-bit = 2 bytes per sample
     let file_size = 36 + data_size;
 
     // RIFF header
@@ -120,11 +130,13 @@ fn write_wav_to<W: Write>(
     writer.write_all(b"data")?;
     writer.write_all(&data_size.to_le_bytes())?;
 
-    // Convert f32 samples to i16 and write
-    for &sample in samples {
-        let clamped = sample.clamp(-1.0, 1.0);
-        let i16_sample = (clamped * 32767.0) as i16;
-        writer.write_all(&i16_sample.to_le_bytes())?;
+    // Convert f32 samples to i16 and write them frame by frame
+    for frame in 0..frames {
+        for samples in channel_samples {
+            let clamped = samples[frame].clamp(-1.0, 1.0);
+            let i16_sample = (clamped * 32767.0) as i16;
+            writer.write_all(&i16_sample.to_le_bytes())?;
+        }
     }
 
     Ok(())
@@ -14,9 +14,21 @@ fn bandstat_bin() -> std::path::PathBuf {
     path
 }
 
+/// Command running bandstat, isolated from the user's config file and `BANDSTAT_*` variables
+fn bandstat_command() -> Command {
+    let mut command = Command::new(bandstat_bin());
+    command.env("XDG_CONFIG_HOME", env!("CARGO_TARGET_TMPDIR"));
+    for (name, _) in std::env::vars_os() {
+        if name.to_string_lossy().starts_with("BANDSTAT_") {
+            command.env_remove(name);
+        }
+    }
+    command
+}
+
 /// Run bandstat with the given arguments
 fn run_bandstat(args: &[&str]) -> std::process::Output {
-    Command::new(bandstat_bin())
+    bandstat_command()
         .args(args)
         .output()
         .expect("failed to execute bandstat")
@@ -62,6 +74,18 @@ fn test_version_flag() {
     assert!(stdout.contains("bandstat"));
 }
 
+#[test]
+fn test_selftest_subcommand() {
+    let output = run_bandstat(&["selftest", "--no-color"]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("[Band Placement]"));
+    assert!(stdout.contains("[K-weighting Response]"));
+    assert!(stdout.contains("Self-test passed"));
+    assert!(!stdout.contains("FAIL"));
+}
+
 // =============================================================================
 // Single file analysis mode
 // =============================================================================
@@ -81,6 +105,30 @@ fn test_single_file_analysis() {
     assert!(stdout.contains("[Dynamics]"));
 }
 
+#[test]
+fn test_short_file_reports_confidence_intervals() {
+    let temp_dir = TempDir::new().unwrap();
+    let short = create_noise_wav(&temp_dir, "short", 2.0);
+    let long = create_noise_wav(&temp_dir, "long", 12.0);
+
+    let output = run_bandstat(&["-q", short.to_str().unwrap()]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Raw CI"));
+    assert!(stdout.contains("K-wt CI"));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("short.wav: only 2.0 s analyzed"),
+        "{}",
+        stderr
+    );
+
+    let output = run_bandstat(&["-q", long.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("Raw CI"));
+    assert!(String::from_utf8_lossy(&output.stderr).is_empty());
+}
+
 #[test]
 fn test_single_file_verbose() {
     let temp_dir = TempDir::new().unwrap();
@@ -150,6 +198,86 @@ fn test_weighted_without_image_warning() {
     );
 }
 
+#[test]
+fn test_active_only_skips_silence() {
+    let temp_dir = TempDir::new().unwrap();
+    // 2 s of tone, 6 s of silence, 2 s of tone
+    let mut samples = common::generate_sine(1000.0, 48000, 2.0);
+    samples.extend(vec![0.0; 48000 * 6]);
+    samples.extend(common::generate_sine(1000.0, 48000, 2.0));
+    let wav_path = temp_dir.path().join("chapter.wav");
+    common::write_wav(&wav_path, &samples, 48000).unwrap();
+
+    let output = run_bandstat(&["-q", "--active-only", wav_path.to_str().unwrap()]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Active: 00:04 of 00:10 (46%)"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn test_lfe_report() {
+    let temp_dir = TempDir::new().unwrap();
+    // 5.1 in WAV order (L R C LFE Ls Rs): the same 60 Hz tone in L, R, and the LFE
+    let bass = common::generate_sine(60.0, 48000, 4.0);
+    let silence = vec![0.0; bass.len()];
+    let surround = temp_dir.path().join("surround.wav");
+    common::write_wav_channels(
+        &surround,
+        &[&bass, &bass, &silence, &bass, &silence, &silence],
+        48000,
+    )
+    .unwrap();
+    let surround = surround.to_str().unwrap();
+
+    let output = run_bandstat(&["-q", "--lfe", surround]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("[LFE] channel 4"), "{}", stdout);
+
+    let json = run_json(&["--format", "json", "--lfe", surround]);
+    let lfe = &json["files"][0]["lfe"];
+    assert_eq!(lfe["channel"], 4);
+    // Half the amplitude of L+R (-6 dB) plus the +10 dB LFE gain
+    let relative = lfe["relative_db"].as_f64().unwrap();
+    assert!((relative - 4.0).abs() < 0.2, "{}", relative);
+    let rms = lfe["rms_dbfs"].as_f64().unwrap();
+    assert!((rms + 9.0).abs() < 0.2, "{}", rms);
+    assert!(lfe["above_120hz_pct"].as_f64().unwrap() < 1.0);
+
+    // --highpass filters the LFE like the mix: an 8 Hz rumble only in the LFE drops out
+    let rumble = common::generate_sine(8.0, 48000, 4.0);
+    let rumbling = temp_dir.path().join("rumbling.wav");
+    common::write_wav_channels(
+        &rumbling,
+        &[&bass, &bass, &silence, &rumble, &silence, &silence],
+        48000,
+    )
+    .unwrap();
+    let rumbling = rumbling.to_str().unwrap();
+    let lfe_rms = |json: serde_json::Value| json["files"][0]["lfe"]["rms_dbfs"].as_f64().unwrap();
+    let plain = lfe_rms(run_json(&["--format", "json", "--lfe", rumbling]));
+    let filtered = lfe_rms(run_json(&[
+        "--format",
+        "json",
+        "--lfe",
+        "--highpass",
+        "30",
+        rumbling,
+    ]));
+    assert!(filtered < plain - 15.0, "{} vs {}", filtered, plain);
+
+    let stereo = create_test_wav(&temp_dir, "stereo", 60.0, 2.0);
+    let output = run_bandstat(&["--lfe", stereo.to_str().unwrap()]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("no LFE channel"), "{}", stderr);
+}
+
 // =============================================================================
 // Comparison mode (multiple files)
 // =============================================================================
@@ -211,6 +339,65 @@ fn test_compare_with_image() {
     assert!(image_path.exists());
 }
 
+#[test]
+fn test_compare_duplicate_file_warning() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav1 = create_test_wav(&temp_dir, "mix", 440.0, 1.0);
+    let wav2 = create_test_wav(&temp_dir, "ref", 880.0, 1.0);
+    let copy = temp_dir.path().join("mix_copy.wav");
+    std::fs::copy(&wav1, &copy).unwrap();
+
+    let output = run_bandstat(&[
+        "-q",
+        wav1.to_str().unwrap(),
+        wav2.to_str().unwrap(),
+        wav1.to_str().unwrap(),
+        copy.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("[C] is the same file as [A]"));
+    assert!(stderr.contains("[D] has identical content to [A]"));
+    assert!(!stderr.contains("[B] is the same file"));
+}
+
+#[test]
+fn test_compare_align_reports_offsets() {
+    let temp_dir = TempDir::new().unwrap();
+    let noise = common::generate_noise(48000, 3.0, 7);
+    let mix = temp_dir.path().join("mix.wav");
+    common::write_wav(&mix, &noise, 48000).unwrap();
+    // An encoder's priming delay, and a copy missing the first half second
+    let delayed = temp_dir.path().join("delayed.wav");
+    let padded: Vec<f32> = std::iter::repeat_n(0.0, 1105)
+        .chain(noise.clone())
+        .collect();
+    common::write_wav(&delayed, &padded, 48000).unwrap();
+    let trimmed = temp_dir.path().join("trimmed.wav");
+    common::write_wav(&trimmed, &noise[24000..], 48000).unwrap();
+
+    let output = run_bandstat(&[
+        "-q",
+        "--align",
+        mix.to_str().unwrap(),
+        delayed.to_str().unwrap(),
+        trimmed.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("[B] offset +1105 samples (+23.02 ms), confidence 100.0%"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("[C] offset -24000 samples (-500.00 ms)"));
+    assert!(stderr.contains("Aligned span: 2.5 s"));
+
+    let output = run_bandstat(&["--align", mix.to_str().unwrap()]);
+    assert!(!output.status.success());
+}
+
 // =============================================================================
 // Timeline mode
 // =============================================================================
@@ -246,6 +433,121 @@ fn test_timeline_with_custom_interval() {
     assert!(stdout.contains("00:20"));
 }
 
+#[test]
+fn test_timeline_time_formats() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_noise_wav(&temp_dir, "long", 25.0);
+    let path = wav_path.to_str().unwrap();
+
+    let row_labels = |format: &str| -> Vec<String> {
+        let output = run_bandstat(&["-q", "-t", "-i", "10", "--time-format", format, path]);
+        assert!(output.status.success(), "{}", format);
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .skip(2)
+            .take(3)
+            .map(|line| line.split_whitespace().next().unwrap().to_string())
+            .collect()
+    };
+    assert_eq!(row_labels("hh:mm:ss"), ["00:00:00", "00:00:10", "00:00:20"]);
+    assert_eq!(row_labels("seconds"), ["0", "10", "20"]);
+    assert_eq!(row_labels("samples"), ["0", "480000", "960000"]);
+}
+
+#[test]
+fn test_timeline_chapters() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_noise_wav(&temp_dir, "long", 25.0);
+    let path = wav_path.to_str().unwrap();
+
+    let output = run_bandstat(&["-q", "-t", "-i", "5", "--chapters-every", "10s", path]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let chapters = stdout.split("Chapters (every 10s)").nth(1).unwrap();
+    let starts: Vec<&str> = chapters
+        .lines()
+        .skip(3)
+        .map_while(|line| line.split_whitespace().next())
+        .collect();
+    assert_eq!(starts, ["00:00", "00:10", "00:20"]);
+
+    let json = run_json(&[
+        "--format",
+        "json",
+        "-t",
+        "-i",
+        "5",
+        "--chapters-every",
+        "10s",
+        path,
+    ]);
+    assert_eq!(json["chapter_secs"], 10);
+    assert_eq!(json["chapters"].as_array().unwrap().len(), 3);
+    assert_eq!(json["chapters"][1]["start_secs"], 10.0);
+
+    let output = run_bandstat(&["-q", "-t", "-i", "20", "--chapters-every", "10s", path]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_timeline_loudness_columns() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_noise_wav(&temp_dir, "long", 8.0);
+    let path = wav_path.to_str().unwrap();
+
+    let output = run_bandstat(&["-q", "-t", "-i", "2", "--loudness", path]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("M max"));
+    assert!(stdout.contains("S max"));
+
+    let json = run_json(&["--format", "json", "-t", "-i", "2", "--loudness", path]);
+    let intervals = json["intervals"].as_array().unwrap();
+    assert_eq!(intervals.len(), 4);
+    for interval in intervals {
+        let momentary = interval["momentary_max_lufs"].as_f64().unwrap();
+        let short_term = interval["short_term_max_lufs"].as_f64().unwrap();
+        assert!(momentary < 0.0 && momentary >= short_term - 0.5);
+    }
+
+    // Without --loudness the intervals carry no loudness fields
+    let json = run_json(&["--format", "json", "-t", "-i", "2", path]);
+    assert!(json["intervals"][0].get("momentary_max_lufs").is_none());
+
+    let output = run_bandstat(&["--loudness", path]);
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .contains("--loudness can only be used with --time or --follow")
+    );
+}
+
+#[test]
+fn test_timeline_browse_errors() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav = create_test_wav(&temp_dir, "tone", 1000.0, 1.0);
+    let path = wav.to_str().unwrap();
+
+    let output = run_bandstat(&["--browse", path]);
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("--browse can only be used with --time")
+    );
+
+    let output = run_bandstat(&["timeline", "--browse", "--follow", path]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cannot be used with"));
+
+    let output = run_bandstat(&["timeline", "--browse", "--format", "json", path]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cannot be used with --format json"));
+
+    // The browser takes over the terminal, so it isn't opened with stdout piped
+    let output = run_bandstat(&["timeline", "--browse", path]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--browse needs a terminal"));
+}
+
 #[test]
 fn test_timeline_with_k_weighting() {
     let temp_dir = TempDir::new().unwrap();
@@ -255,6 +557,42 @@ fn test_timeline_with_k_weighting() {
     assert!(output.status.success());
 }
 
+#[test]
+fn test_timeline_gate_skips_room_tone() {
+    let temp_dir = TempDir::new().unwrap();
+    // 2 s of tone, then 3 s of room tone around -70 dBFS
+    let mut samples = common::generate_sine(1000.0, 48000, 2.0);
+    samples.extend(
+        common::generate_noise(48000, 3.0, 7)
+            .iter()
+            .map(|s| s * 0.0005),
+    );
+    let wav_path = temp_dir.path().join("takes.wav");
+    common::write_wav(&wav_path, &samples, 48000).unwrap();
+    let path = wav_path.to_str().unwrap();
+
+    let rows = |extra: &[&str]| {
+        let mut args = vec!["-q", "-t", "-i", "1", path];
+        args.extend(extra);
+        let output = run_bandstat(&args);
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter(|l| l.starts_with("00:"))
+            .count()
+    };
+    assert_eq!(rows(&[]), 5);
+    assert_eq!(rows(&["--gate", "-50dB"]), 2);
+}
+
+#[test]
+fn test_gate_invalid_level_error() {
+    let output = run_bandstat(&["--gate", "-6x", "audio.wav"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--gate: invalid level '-6x'"));
+}
+
 #[test]
 fn test_timeline_with_image() {
     let temp_dir = TempDir::new().unwrap();
@@ -272,6 +610,194 @@ fn test_timeline_with_image() {
     assert!(image_path.exists());
 }
 
+// =============================================================================
+// Input sources
+// =============================================================================
+
+/// Serve a single HTTP response with the given body on a local port and return its URL
+fn serve_once(body: Vec<u8>, path: &str) -> String {
+    use std::io::{BufRead, BufReader, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/{}", listener.local_addr().unwrap(), path);
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap() > 0 && line != "\r\n" {
+            line.clear();
+        }
+        let header = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: audio/wav\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        );
+        let _ = stream.write_all(header.as_bytes());
+        let _ = stream.write_all(&body);
+    });
+    url
+}
+
+#[test]
+fn test_http_url_input() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_test_wav(&temp_dir, "remote", 750.0, 2.0);
+    let url = serve_once(std::fs::read(&wav_path).unwrap(), "remote.wav?token=1");
+
+    let output = run_bandstat(&["-q", &url]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let values = parse_percentage_line(&stdout, "Raw(%)").expect("Should have Raw(%) line");
+    assert!(
+        values[6] > 90.0,
+        "Expected MID dominant, got {}%",
+        values[6]
+    );
+}
+
+#[test]
+fn test_max_download_refuses_large_urls() {
+    let temp_dir = TempDir::new().unwrap();
+    // 12 s of 16-bit mono at 48 kHz is just over 1 MB
+    let wav_path = create_test_wav(&temp_dir, "remote", 750.0, 12.0);
+    let body = std::fs::read(&wav_path).unwrap();
+
+    let url = serve_once(body.clone(), "remote.wav");
+    let output = run_bandstat(&["-q", "--max-download", "1", &url]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("over --max-download 1 MB"), "{}", stderr);
+
+    // Only the start is read with --duration, so the size is not checked up front
+    let url = serve_once(body, "remote.wav");
+    let output = run_bandstat(&["-q", "--max-download", "1", "--duration", "2", &url]);
+    assert!(output.status.success());
+
+    let output = run_bandstat(&["--max-download", "0", wav_path.to_str().unwrap()]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_duration_limits_analysis() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_noise_wav(&temp_dir, "long", 5.0);
+
+    let output = run_bandstat(&[
+        "-q",
+        "-t",
+        "-i",
+        "1",
+        "--duration",
+        "2",
+        wav_path.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.lines().any(|l| l.starts_with("00:01")));
+    assert!(!stdout.lines().any(|l| l.starts_with("00:02")));
+    assert!(stdout.contains("Duration: 00:02"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_ffmpeg_fallback() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_test_wav(&temp_dir, "decoded", 750.0, 2.0);
+    let exotic = temp_dir.path().join("take.exotic");
+    std::fs::write(&exotic, b"not a format bandstat reads").unwrap();
+    let exotic = exotic.to_str().unwrap();
+    // Stand-in ffmpeg that "converts" by copying a WAV to the output path (its last argument)
+    let fake_ffmpeg = temp_dir.path().join("ffmpeg");
+    std::fs::write(
+        &fake_ffmpeg,
+        format!(
+            "#!/bin/sh\nfor last; do :; done\ncp '{}' \"$last\"\n",
+            wav_path.display()
+        ),
+    )
+    .unwrap();
+    std::fs::set_permissions(&fake_ffmpeg, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let ffmpeg = fake_ffmpeg.to_str().unwrap();
+
+    let output = run_bandstat(&["-q", exotic]);
+    assert!(!output.status.success(), "off unless asked for");
+
+    let output = run_bandstat_env(
+        &[("BANDSTAT_FFMPEG", ffmpeg)],
+        &["-q", "--ffmpeg-fallback", exotic],
+    );
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("decoded through ffmpeg"), "{}", stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let values = parse_percentage_line(&stdout, "Raw(%)").expect("Should have Raw(%) line");
+    assert!(
+        values[6] > 90.0,
+        "Expected MID dominant, got {}%",
+        values[6]
+    );
+
+    let output = run_bandstat_env(
+        &[("BANDSTAT_FFMPEG", "/nonexistent/ffmpeg")],
+        &["-q", "--ffmpeg-fallback", exotic],
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("BANDSTAT_FFMPEG"), "{}", stderr);
+}
+
+// =============================================================================
+// Tone verification
+// =============================================================================
+
+/// Create a -20 dBFS 1 kHz calibration tone (peak amplitude 0.1)
+fn create_calibration_wav(dir: &TempDir) -> std::path::PathBuf {
+    let samples = common::generate_multitone(&[(1000.0, 0.2)], 48000, 2.0);
+    let path = dir.path().join("tone.wav");
+    common::write_wav(&path, &samples, 48000).unwrap();
+    path
+}
+
+#[test]
+fn test_verify_tone_pass() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_calibration_wav(&temp_dir);
+
+    let output = run_bandstat(&[
+        "--no-color",
+        "--verify-tone",
+        "1000:-20",
+        wav_path.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Tone Verification"));
+    assert!(stdout.contains("Result: PASS"));
+}
+
+#[test]
+fn test_verify_tone_level_mismatch_fails() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_calibration_wav(&temp_dir);
+
+    let output = run_bandstat(&[
+        "-q",
+        "--no-color",
+        "--verify-tone",
+        "1000:-18",
+        wav_path.to_str().unwrap(),
+    ]);
+    assert!(!output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("deviation -2.0"));
+    assert!(stdout.contains("Result: FAIL"));
+}
+
 // =============================================================================
 // Error cases
 // =============================================================================
@@ -369,6 +895,41 @@ fn test_image_invalid_directory_error() {
     assert!(stderr.contains("Directory does not exist"));
 }
 
+#[test]
+fn test_chart_font_unknown_family_error() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_test_wav(&temp_dir, "test", 440.0, 1.0);
+    let image_path = temp_dir.path().join("chart.png");
+
+    let output = run_bandstat(&[
+        "-q",
+        wav_path.to_str().unwrap(),
+        "--image",
+        image_path.to_str().unwrap(),
+        "--chart-font",
+        "No Such Font Family",
+    ]);
+    assert!(!output.status.success());
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("'No Such Font Family' is not installed"));
+    assert!(!image_path.exists());
+}
+
+#[test]
+fn test_silent_file_warning() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = temp_dir.path().join("silent.wav");
+    common::write_wav(&wav_path, &vec![0.0; 48000], 48000).unwrap();
+
+    let output = run_bandstat(&["-q", wav_path.to_str().unwrap()]);
+    assert!(output.status.success());
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Warning:"));
+    assert!(stderr.contains("digital silence"));
+}
+
 // =============================================================================
 // Output format tests
 // =============================================================================
@@ -390,23 +951,131 @@ fn test_no_color_option() {
 }
 
 #[test]
-fn test_quiet_mode_reduces_output() {
+fn test_ascii_output() {
     let temp_dir = TempDir::new().unwrap();
-    let wav_path = create_test_wav(&temp_dir, "test", 440.0, 1.0);
+    let a = create_test_wav(&temp_dir, "ミックス", 1000.0, 1.0);
+    let b = create_noise_wav(&temp_dir, "ref", 1.0);
 
-    let verbose_output = run_bandstat(&[wav_path.to_str().unwrap()]);
-    let quiet_output = run_bandstat(&["-q", wav_path.to_str().unwrap()]);
+    let output = run_bandstat(&["--ascii", a.to_str().unwrap(), b.to_str().unwrap()]);
+    assert!(output.status.success());
 
-    let verbose_stdout = String::from_utf8_lossy(&verbose_output.stdout);
-    let quiet_stdout = String::from_utf8_lossy(&quiet_output.stdout);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.is_ascii());
+    assert!(!stdout.contains("\x1b["));
+    assert!(stdout.contains("????.wav"));
+    assert!(stdout.contains("(+/-2 standard errors)"));
+}
 
-    // Quiet mode should have less output
-    assert!(quiet_stdout.len() < verbose_stdout.len());
+#[test]
+fn test_output_writes_tables_to_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_test_wav(&temp_dir, "test", 440.0, 1.0);
+    let tables = temp_dir.path().join("tables.txt");
+    let report = temp_dir.path().join("report.html");
+
+    let output = run_bandstat(&[
+        "-o",
+        tables.to_str().unwrap(),
+        "--report",
+        report.to_str().unwrap(),
+        wav_path.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    // Progress stays on stderr
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Report saved to:"));
+
+    let text = std::fs::read_to_string(&tables).unwrap();
+    assert!(text.contains("[Band Power Distribution]"));
+    assert!(!text.contains("\x1b["));
+    assert!(report.exists());
+}
+
+#[test]
+fn test_quiet_mode_reduces_output() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_test_wav(&temp_dir, "test", 440.0, 1.0);
+
+    let verbose_output = run_bandstat(&[wav_path.to_str().unwrap()]);
+    let quiet_output = run_bandstat(&["-q", wav_path.to_str().unwrap()]);
+
+    let verbose_stdout = String::from_utf8_lossy(&verbose_output.stdout);
+    let quiet_stdout = String::from_utf8_lossy(&quiet_output.stdout);
+
+    // Quiet mode should have less output
+    assert!(quiet_stdout.len() < verbose_stdout.len());
 
     // Quiet mode should not include legend
     assert!(!quiet_stdout.contains("Legend:") && !quiet_stdout.contains("Diff:"));
 }
 
+#[test]
+fn test_header_style_indices() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_test_wav(&temp_dir, "test", 440.0, 1.0);
+
+    let output = run_bandstat(&[
+        "-q",
+        "--header-style",
+        "indices",
+        wav_path.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let header = stdout
+        .lines()
+        .find(|l| l.trim_start().starts_with("0 "))
+        .expect("Should have an index header row");
+    let indices: Vec<usize> = header
+        .split_whitespace()
+        .map(|s| s.parse().unwrap())
+        .collect();
+    assert_eq!(indices, (0..14).collect::<Vec<_>>());
+    assert!(!stdout.contains("SUB1"));
+}
+
+#[test]
+fn test_header_style_freqs() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_noise_wav(&temp_dir, "long", 3.0);
+
+    let output = run_bandstat(&[
+        "-q",
+        "-t",
+        "-i",
+        "1",
+        "--header-style",
+        "freqs",
+        wav_path.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let header = stdout
+        .lines()
+        .find(|l| l.starts_with("TIME"))
+        .expect("Should have a header row");
+    assert!(header.contains("1k"));
+    assert!(header.contains("18k+"));
+}
+
+#[test]
+fn test_deterministic_output_is_repeatable() {
+    let temp_dir = TempDir::new().unwrap();
+    // 44.1 kHz input also exercises the resampler
+    let samples = common::generate_noise(44100, 2.0, 7);
+    let wav_path = temp_dir.path().join("noise_44k.wav");
+    common::write_wav(&wav_path, &samples, 44100).unwrap();
+
+    let run = || {
+        let output = run_bandstat(&["--deterministic", wav_path.to_str().unwrap()]);
+        assert!(output.status.success());
+        output.stdout
+    };
+    assert_eq!(run(), run());
+}
+
 // =============================================================================
 // Analysis accuracy tests
 // =============================================================================
@@ -602,6 +1271,117 @@ fn test_k_weighting_reduces_low_freq() {
     );
 }
 
+#[test]
+fn test_kweight_mode_filter_matches_spectral() {
+    let temp_dir = TempDir::new().unwrap();
+    let samples = common::generate_multitone(&[(100.0, 0.5), (2000.0, 0.5)], 48000, 2.0);
+    let wav_path = temp_dir.path().join("low_high_mix.wav");
+    common::write_wav(&wav_path, &samples, 48000).unwrap();
+    let wav = wav_path.to_str().unwrap();
+
+    let k_values = |mode: &str| {
+        let output = run_bandstat(&["-q", "--kweight-mode", mode, wav]);
+        assert!(output.status.success());
+        parse_percentage_line(&String::from_utf8_lossy(&output.stdout), "K-wt(%)")
+            .expect("Should have K-wt(%) line")
+    };
+    let spectral = k_values("spectral");
+    let filter = k_values("filter");
+    for (s, f) in spectral.iter().zip(&filter) {
+        assert!((s - f).abs() < 0.5, "{:?} vs {:?}", spectral, filter);
+    }
+
+    let output = run_bandstat(&["--kweight-mode", "table", wav]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_weighting_labels_weighted_column() {
+    let temp_dir = TempDir::new().unwrap();
+    let samples = common::generate_multitone(&[(100.0, 0.5), (2000.0, 0.5)], 48000, 2.0);
+    let wav_path = temp_dir.path().join("low_high_mix.wav");
+    common::write_wav(&wav_path, &samples, 48000).unwrap();
+    let wav = wav_path.to_str().unwrap();
+
+    let output = run_bandstat(&["-q", "--weighting", "z", wav]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("K-wt"), "{}", stdout);
+    let raw = parse_percentage_line(&stdout, "Raw(%)").expect("Should have Raw(%) line");
+    let flat = parse_percentage_line(&stdout, "Z-wt(%)").expect("Should have Z-wt(%) line");
+    for (r, z) in raw.iter().zip(&flat) {
+        assert!((r - z).abs() < 0.05, "{:?} vs {:?}", raw, flat);
+    }
+
+    // C-weighting cuts 100 Hz far less than K-weighting does
+    let low_share = |weighting: &str, label: &str| {
+        let output = run_bandstat(&["-q", "--weighting", weighting, wav]);
+        assert!(output.status.success());
+        let values = parse_percentage_line(&String::from_utf8_lossy(&output.stdout), label)
+            .unwrap_or_else(|| panic!("Should have {} line", label));
+        values.iter().take(4).sum::<f64>()
+    };
+    assert!(low_share("c", "C-wt(%)") > low_share("k", "K-wt(%)") + 1.0);
+    // The 75-phon contour still hears 100 Hz as much quieter than K-weighting does
+    assert!(low_share("iso226:75", "75ph(%)") + 1.0 < low_share("k", "K-wt(%)"));
+
+    for invalid in [
+        &["--weighting", "c", "--kweight-mode", "filter", wav][..],
+        &["--weighting", "iso226:100", wav],
+    ] {
+        let output = run_bandstat(invalid);
+        assert!(!output.status.success());
+    }
+}
+
+#[test]
+fn test_weighting_file_applies_curve() {
+    let temp_dir = TempDir::new().unwrap();
+    let samples = common::generate_multitone(&[(100.0, 0.5), (2000.0, 0.5)], 48000, 2.0);
+    let wav_path = temp_dir.path().join("low_high_mix.wav");
+    common::write_wav(&wav_path, &samples, 48000).unwrap();
+    let wav = wav_path.to_str().unwrap();
+    let curve_path = temp_dir.path().join("bass_cut.csv");
+    std::fs::write(&curve_path, "Hz,dB\n50,-20\n300,-20\n600,0\n20000,0\n").unwrap();
+    let curve = curve_path.to_str().unwrap();
+
+    let output = run_bandstat(&["-q", "--weighting-file", curve, wav]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let raw = parse_percentage_line(&stdout, "Raw(%)").expect("Should have Raw(%) line");
+    let weighted = parse_percentage_line(&stdout, "File(%)").expect("Should have File(%) line");
+    let low = |values: &[f64]| values.iter().take(5).sum::<f64>();
+    assert!(low(&raw) > 40.0, "{:?}", raw);
+    assert!(low(&weighted) < 5.0, "{:?}", weighted);
+
+    for invalid in [
+        &["--weighting-file", curve, "--weighting", "c", wav][..],
+        &["--weighting-file", wav, wav],
+    ] {
+        let output = run_bandstat(invalid);
+        assert!(!output.status.success());
+    }
+}
+
+#[test]
+fn test_resample_quality_keeps_air_band() {
+    let temp_dir = TempDir::new().unwrap();
+    // White noise at 44.1 kHz has energy right up to 22 kHz
+    let samples = common::generate_noise(44100, 2.0, 7);
+    let wav_path = temp_dir.path().join("noise_44k.wav");
+    common::write_wav(&wav_path, &samples, 44100).unwrap();
+    let wav = wav_path.to_str().unwrap();
+
+    let air = |quality: &str| {
+        let json = run_json(&["--format", "json", "--resample-quality", quality, wav]);
+        let bands = json["files"][0]["bands"].as_array().unwrap();
+        bands.last().unwrap()["raw_pct"].as_f64().unwrap()
+    };
+    let fast = air("fast");
+    let high = air("high");
+    assert!(high > fast * 1.02, "AIR fast {} high {}", fast, high);
+}
+
 #[test]
 fn test_k_weighting_boosts_presence() {
     let temp_dir = TempDir::new().unwrap();
@@ -628,6 +1408,43 @@ fn test_k_weighting_boosts_presence() {
     );
 }
 
+#[test]
+fn test_k_weighting_gates_quiet_passage() {
+    let temp_dir = TempDir::new().unwrap();
+    // Loud 2 kHz, then a long 100 Hz passage 20 dB down
+    let sr = 48000u32;
+    let samples: Vec<f32> = (0..sr * 8)
+        .map(|i| {
+            let t = i as f32 / sr as f32;
+            if i < sr * 2 {
+                (2.0 * std::f32::consts::PI * 2000.0 * t).sin() * 0.5
+            } else {
+                (2.0 * std::f32::consts::PI * 100.0 * t).sin() * 0.05
+            }
+        })
+        .collect();
+    let wav_path = temp_dir.path().join("loud_then_quiet.wav");
+    common::write_wav(&wav_path, &samples, sr).unwrap();
+
+    let k_bass = |extra: &[&str]| {
+        let mut args = vec!["-q"];
+        args.extend_from_slice(extra);
+        args.push(wav_path.to_str().unwrap());
+        let output = run_bandstat(&args);
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        parse_percentage_line(&stdout, "K-wt(%)").expect("Should have K-wt(%) line")[3]
+    };
+
+    // Gated: the quiet BASS passage drops out of K-wt; --no-gate keeps it
+    assert!(k_bass(&[]) < 0.1);
+    assert_eq!(k_bass(&["--gated"]), k_bass(&[]));
+    assert!(k_bass(&["--no-gate"]) > 1.0);
+
+    let output = run_bandstat(&["--gated", "--no-gate", wav_path.to_str().unwrap()]);
+    assert!(!output.status.success());
+}
+
 #[test]
 fn test_dynamics_constant_amplitude_low() {
     let temp_dir = TempDir::new().unwrap();
@@ -794,3 +1611,2237 @@ fn test_timeline_tracks_frequency_change() {
     assert!(found_00_00, "Should have 00:00 interval");
     assert!(found_00_05, "Should have 00:05 interval");
 }
+
+#[test]
+fn test_start_and_end_select_range() {
+    let temp_dir = TempDir::new().unwrap();
+    // 100 Hz (BASS) for 4 seconds, then 3 kHz (HMID) for 4 seconds
+    let mut samples = common::generate_sine(100.0, 48000, 4.0);
+    samples.extend(common::generate_sine(3000.0, 48000, 4.0));
+    let wav_path = temp_dir.path().join("two_tones.wav");
+    common::write_wav(&wav_path, &samples, 48000).unwrap();
+    let wav = wav_path.to_str().unwrap();
+    let pct = |json: &serde_json::Value, band: usize| {
+        json["files"][0]["bands"][band]["raw_pct"].as_f64().unwrap()
+    };
+
+    let json = run_json(&["--format", "json", "--start", "0:04.5", wav]);
+    assert!(pct(&json, 8) > 95.0, "{}", pct(&json, 8));
+    let json = run_json(&["--format", "json", "--end", "3.5", wav]);
+    assert!(pct(&json, 3) > 95.0, "{}", pct(&json, 3));
+    let json = run_json(&["--format", "json", "--start", "1", "--duration", "2", wav]);
+    assert!(pct(&json, 3) > 95.0, "{}", pct(&json, 3));
+
+    // Timeline times count from --start
+    let timeline = run_json(&["--format", "json", "--time", "-i", "1", "--start", "5", wav]);
+    assert_eq!(timeline["intervals"].as_array().unwrap().len(), 3);
+    assert_eq!(timeline["duration_secs"], 3.0);
+
+    let output = run_bandstat(&["--start", "10", wav]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("past the end"));
+    let output = run_bandstat(&["--start", "1:00", "--end", "0:30", wav]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--end must be after --start"));
+    let output = run_bandstat(&["--start", "1:75", wav]);
+    assert!(!output.status.success());
+    let output = run_bandstat(&["--end", "5", "--duration", "2", wav]);
+    assert!(!output.status.success());
+}
+
+// =============================================================================
+// Monitor Mode Tests
+// =============================================================================
+
+#[test]
+fn test_monitor_writes_ndjson() {
+    let temp_dir = TempDir::new().unwrap();
+    // 44.1kHz input exercises the streaming resampler
+    let samples = common::generate_sine(1000.0, 44100, 3.5);
+    let wav_path = temp_dir.path().join("stream.wav");
+    common::write_wav(&wav_path, &samples, 44100).unwrap();
+    let log_dir = temp_dir.path().join("logs");
+
+    let output = run_bandstat(&[
+        "-q",
+        "--monitor",
+        "-i",
+        "1",
+        "--rotate",
+        "1h",
+        "--monitor-dir",
+        log_dir.to_str().unwrap(),
+        wav_path.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.lines().any(|l| l.starts_with("00:03")));
+
+    let logs: Vec<_> = std::fs::read_dir(&log_dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    assert_eq!(logs.len(), 1);
+    let name = logs[0].file_name().unwrap().to_string_lossy().to_string();
+    assert!(name.starts_with("bandstat-") && name.ends_with("Z.ndjson"));
+
+    let content = std::fs::read_to_string(&logs[0]).unwrap();
+    let records: Vec<&str> = content.lines().collect();
+    assert_eq!(
+        records.len(),
+        4,
+        "3 full intervals plus the final partial one"
+    );
+    for record in &records {
+        assert!(record.contains("\"timestamp\""));
+        assert!(record.contains("\"label\":\"UMID\""));
+        assert!(record.contains("\"weighting\":\"raw\""));
+    }
+}
+
+#[test]
+fn test_monitor_options_require_monitor() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_noise_wav(&temp_dir, "noise", 1.0);
+
+    let output = run_bandstat(&["--rotate", "1h", wav_path.to_str().unwrap()]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("can only be used with --monitor"));
+
+    let output = run_bandstat(&["--monitor", "--rotate", "1w", wav_path.to_str().unwrap()]);
+    assert!(!output.status.success());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_monitor_alert_runs_command() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_test_wav(&temp_dir, "tone", 1000.0, 4.0);
+    let out_path = temp_dir.path().join("alerts.txt");
+    let rules_path = temp_dir.path().join("rules.toml");
+    std::fs::write(
+        &rules_path,
+        format!(
+            r#"
+[[rule]]
+name = "dead tweeter"
+bands = ["HIGH", "UHIG", "AIR"]
+below = 0.5
+for = "2s"
+command = 'echo "$BANDSTAT_RULE $BANDSTAT_STATE" >> {}'
+"#,
+            out_path.display()
+        ),
+    )
+    .unwrap();
+
+    let output = run_bandstat(&[
+        "-q",
+        "--monitor",
+        "-i",
+        "1",
+        "--monitor-dir",
+        temp_dir.path().join("logs").to_str().unwrap(),
+        "--alert-rules",
+        rules_path.to_str().unwrap(),
+        wav_path.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Alert 'dead tweeter' triggered at 00:01"));
+
+    // Fires once, not on every violating interval
+    let alerts = std::fs::read_to_string(&out_path).unwrap();
+    assert_eq!(alerts, "dead tweeter triggered\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_monitor_alert_fires_on_silence() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = temp_dir.path().join("dead.wav");
+    common::write_wav(&wav_path, &vec![0.0; 48000 * 3], 48000).unwrap();
+    let rules_path = temp_dir.path().join("rules.toml");
+    std::fs::write(
+        &rules_path,
+        "[[rule]]\nname = \"dead air\"\nbands = [\"MID\"]\nbelow = 1.0\nfor = \"2s\"\n",
+    )
+    .unwrap();
+
+    let output = run_bandstat(&[
+        "-q",
+        "--monitor",
+        "-i",
+        "1",
+        "--monitor-dir",
+        temp_dir.path().join("logs").to_str().unwrap(),
+        "--alert-rules",
+        rules_path.to_str().unwrap(),
+        wav_path.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Alert 'dead air' triggered at 00:01"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_monitor_renders_heatmap() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_noise_wav(&temp_dir, "noise", 3.0);
+    let heatmap_path = temp_dir.path().join("daily.png");
+
+    let output = run_bandstat(&[
+        "-q",
+        "--monitor",
+        "-i",
+        "1",
+        "--monitor-dir",
+        temp_dir.path().join("logs").to_str().unwrap(),
+        "--heatmap",
+        heatmap_path.to_str().unwrap(),
+        wav_path.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    assert!(std::fs::metadata(&heatmap_path).unwrap().len() > 0);
+}
+
+// =============================================================================
+// Band Label Tests
+// =============================================================================
+
+#[test]
+fn test_band_labels_rename_columns() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_test_wav(&temp_dir, "test", 1000.0, 2.0);
+
+    let output = run_bandstat(&[
+        "-q",
+        "--band-labels",
+        "DC,SUB1,SUB2,LOW,UPPERLOW,LMID,MID,UMID,HMID,PRES,BRIL,HIGH,UHIG,AIR",
+        wav_path.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let header = stdout
+        .lines()
+        .find(|l| l.contains("UPPERLOW"))
+        .expect("header with custom label");
+    assert!(header.contains(" LOW"));
+    assert!(!header.contains("BASS"));
+
+    // Every column widens to fit the longest label
+    let raw_row = stdout.lines().find(|l| l.starts_with("Raw")).unwrap();
+    assert_eq!(raw_row.len(), header.len());
+}
+
+#[test]
+fn test_band_labels_non_ascii_with_ascii_output() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_test_wav(&temp_dir, "test", 1000.0, 2.0);
+
+    let output = run_bandstat(&[
+        "-q",
+        "--ascii",
+        "--band-labels",
+        "DC,SUB1,SUB2,BASS,UBAS,LMID,MID,UMID,HMID,PRES,BRIL,HIGH,超高域,ÄIR",
+        wav_path.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.is_ascii());
+    let header = stdout
+        .lines()
+        .find(|l| l.contains("?IR"))
+        .expect("header with custom label");
+    assert!(header.contains(" ???"));
+
+    // Columns stay aligned with the replaced labels
+    let raw_row = stdout.lines().find(|l| l.starts_with("Raw")).unwrap();
+    assert_eq!(raw_row.len(), header.len());
+}
+
+#[test]
+fn test_band_labels_wrong_count_error() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_test_wav(&temp_dir, "test", 1000.0, 1.0);
+
+    let output = run_bandstat(&["--band-labels", "A,B,C", wav_path.to_str().unwrap()]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--band-labels needs 14 labels, got 3"));
+}
+
+// =============================================================================
+// Crossover Tests
+// =============================================================================
+
+/// Parse the Raw(%) row of single-file output into band values
+fn raw_row_values(stdout: &str) -> Vec<f64> {
+    stdout
+        .lines()
+        .find(|l| l.starts_with("Raw"))
+        .expect("Raw row")
+        .split_whitespace()
+        .skip(1)
+        .filter_map(|s| s.parse().ok())
+        .collect()
+}
+
+#[test]
+fn test_crossover_splits_boundary_tone() {
+    let temp_dir = TempDir::new().unwrap();
+    // 1000 Hz is the MID/UMID boundary
+    let wav_path = create_test_wav(&temp_dir, "edge", 1000.0, 2.0);
+
+    let output = run_bandstat(&["-q", "--no-color", wav_path.to_str().unwrap()]);
+    assert!(output.status.success());
+    let hard = raw_row_values(&String::from_utf8_lossy(&output.stdout));
+    assert!(
+        (hard[6] - hard[7]).abs() > 20.0,
+        "hard edges split the tone unevenly: {:?}",
+        hard
+    );
+
+    let output = run_bandstat(&[
+        "-q",
+        "--no-color",
+        "--crossover",
+        "0.33",
+        wav_path.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    let soft = raw_row_values(&String::from_utf8_lossy(&output.stdout));
+    assert!((soft[6] - 50.0).abs() < 2.0, "MID share: {:?}", soft);
+    assert!((soft[7] - 50.0).abs() < 2.0, "UMID share: {:?}", soft);
+}
+
+#[test]
+fn test_crossover_out_of_range_error() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_test_wav(&temp_dir, "test", 440.0, 1.0);
+
+    let output = run_bandstat(&["--crossover", "1.5", wav_path.to_str().unwrap()]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--crossover must be between 0 and 1 octaves"));
+}
+
+// =============================================================================
+// Verify Mode Tests
+// =============================================================================
+
+#[test]
+fn test_verify_reports_energy_conservation() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_noise_wav(&temp_dir, "noise", 2.0);
+
+    for extra in [&[][..], &["--crossover", "0.5", "-t", "-i", "1"][..]] {
+        let mut args = vec!["-q", "--no-color", "--verify"];
+        args.extend_from_slice(extra);
+        args.push(wav_path.to_str().unwrap());
+        let output = run_bandstat(&args);
+        assert!(output.status.success());
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("[Verify]"));
+        assert!(stderr.contains("PASS Parseval"));
+        assert!(stderr.contains("PASS Band coverage"));
+        assert!(!stderr.contains("FAIL"));
+    }
+}
+
+#[test]
+fn test_verify_passes_layouts_that_leave_the_spectrum_uncovered() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_noise_wav(&temp_dir, "noise", 2.0);
+
+    // The octave bands span 22.1 Hz to 22.6 kHz; the rest leave gaps or clip the edges
+    for layout in [
+        &["--bands", "octave"][..],
+        &["--bands", "octave", "--crossover", "0.5"][..],
+        &["--num-bands", "24"][..],
+        &["--ignore-bands", "DC,MID"][..],
+        &["--range", "50-12000"][..],
+    ] {
+        let mut args = vec!["-q", "--no-color", "--verify"];
+        args.extend_from_slice(layout);
+        args.push(wav_path.to_str().unwrap());
+        let output = run_bandstat(&args);
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success(), "{:?}: {}", layout, stderr);
+        assert!(
+            stderr.contains("PASS Band coverage"),
+            "{:?}: {}",
+            layout,
+            stderr
+        );
+    }
+}
+
+// =============================================================================
+// Precision Tests
+// =============================================================================
+
+#[test]
+fn test_f64_precision_matches_default() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_noise_wav(&temp_dir, "noise", 2.0);
+
+    let output = run_bandstat(&["-q", "--no-color", wav_path.to_str().unwrap()]);
+    assert!(output.status.success());
+    let single = raw_row_values(&String::from_utf8_lossy(&output.stdout));
+
+    let output = run_bandstat(&[
+        "-q",
+        "--no-color",
+        "--precision",
+        "f64",
+        "--verify",
+        wav_path.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    let double = raw_row_values(&String::from_utf8_lossy(&output.stdout));
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("FAIL"));
+
+    assert_eq!(single.len(), double.len());
+    for (a, b) in single.iter().zip(&double) {
+        assert!((a - b).abs() <= 0.1, "f32 {:?} vs f64 {:?}", single, double);
+    }
+}
+
+#[test]
+fn test_multi_resolution_keeps_balance() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_noise_wav(&temp_dir, "noise", 3.0);
+    let wav = wav_path.to_str().unwrap();
+
+    let output = run_bandstat(&["-q", "--no-color", wav]);
+    let single = raw_row_values(&String::from_utf8_lossy(&output.stdout));
+    let output = run_bandstat(&["-q", "--no-color", "--multi-resolution", wav]);
+    assert!(output.status.success());
+    let multi = raw_row_values(&String::from_utf8_lossy(&output.stdout));
+
+    // White noise has the same band energy at either resolution
+    assert_eq!(single.len(), multi.len());
+    for (a, b) in single.iter().zip(&multi) {
+        assert!(
+            (a - b).abs() <= 0.2,
+            "single {:?} vs multi {:?}",
+            single,
+            multi
+        );
+    }
+
+    let output = run_bandstat(&["--multi-resolution", "--verify", wav]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_zero_pad_keeps_balance() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_noise_wav(&temp_dir, "noise", 3.0);
+    let wav = wav_path.to_str().unwrap();
+
+    let output = run_bandstat(&["-q", "--no-color", wav]);
+    let plain = raw_row_values(&String::from_utf8_lossy(&output.stdout));
+    // The padded spectrum still sums to the frame energy
+    let output = run_bandstat(&["-q", "--no-color", "--zero-pad", "4", "--verify", wav]);
+    assert!(output.status.success());
+    let padded = raw_row_values(&String::from_utf8_lossy(&output.stdout));
+
+    assert_eq!(plain.len(), padded.len());
+    for (a, b) in plain.iter().zip(&padded) {
+        assert!(
+            (a - b).abs() <= 0.2,
+            "plain {:?} vs padded {:?}",
+            plain,
+            padded
+        );
+    }
+
+    let output = run_bandstat(&["--zero-pad", "3", wav]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("power of two"));
+}
+
+// =============================================================================
+// Follow Mode Tests
+// =============================================================================
+
+/// Append 16-bit PCM samples to the data chunk of a growing WAV file
+fn append_pcm(path: &std::path::Path, samples: &[f32]) {
+    use std::io::Write;
+    let bytes: Vec<u8> = samples
+        .iter()
+        .flat_map(|s| ((s.clamp(-1.0, 1.0) * 32767.0) as i16).to_le_bytes())
+        .collect();
+    let mut file = std::fs::OpenOptions::new().append(true).open(path).unwrap();
+    file.write_all(&bytes).unwrap();
+}
+
+#[test]
+fn test_follow_growing_file() {
+    use std::io::{Seek, SeekFrom, Write};
+
+    let temp_dir = TempDir::new().unwrap();
+
+    // Recorders write unknown-length or zero size fields while still recording
+    for placeholder in [u32::MAX, 0] {
+        let path = create_test_wav(&temp_dir, &format!("recording{}", placeholder), 440.0, 1.0);
+        let mut file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+        for offset in [4, 40] {
+            file.seek(SeekFrom::Start(offset)).unwrap();
+            file.write_all(&placeholder.to_le_bytes()).unwrap();
+        }
+        drop(file);
+
+        let child = bandstat_command()
+            .args(["-q", "--no-color", "--follow", "--follow-idle", "1.5"])
+            .args(["-i", "1", path.to_str().unwrap()])
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        for _ in 0..2 {
+            std::thread::sleep(std::time::Duration::from_millis(300));
+            append_pcm(&path, &common::generate_sine(440.0, 48000, 1.0));
+        }
+
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        for time in ["00:00", "00:01", "00:02"] {
+            assert!(
+                stdout.lines().any(|l| l.starts_with(time)),
+                "missing {} row:\n{}",
+                time,
+                stdout
+            );
+        }
+        assert!(stdout.contains("Duration: 00:03"));
+    }
+}
+
+#[test]
+fn test_follow_rejects_multiple_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let a = create_test_wav(&temp_dir, "a", 440.0, 1.0);
+    let b = create_test_wav(&temp_dir, "b", 440.0, 1.0);
+
+    let output = run_bandstat(&["--follow", a.to_str().unwrap(), b.to_str().unwrap()]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--follow takes a single local file"));
+}
+
+// =============================================================================
+// Sonification Tests
+// =============================================================================
+
+#[test]
+fn test_sonify_diff_writes_analyzable_wav() {
+    let temp_dir = TempDir::new().unwrap();
+    let a = create_noise_wav(&temp_dir, "a", 1.0);
+    let b = create_test_wav(&temp_dir, "b", 1000.0, 1.0);
+    let out = temp_dir.path().join("diff.wav");
+
+    let output = run_bandstat(&[
+        "-q",
+        a.to_str().unwrap(),
+        b.to_str().unwrap(),
+        "--sonify-diff",
+        out.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Difference preview"));
+
+    let output = run_bandstat(&["-q", "--no-color", out.to_str().unwrap()]);
+    assert!(output.status.success());
+    let raw = raw_row_values(&String::from_utf8_lossy(&output.stdout));
+    // B concentrates its energy around 1 kHz, so MID/UMID dominate the preview
+    let max_band = raw
+        .iter()
+        .enumerate()
+        .max_by(|x, y| x.1.total_cmp(y.1))
+        .unwrap()
+        .0;
+    assert!(max_band == 6 || max_band == 7, "{:?}", raw);
+}
+
+#[test]
+fn test_sonify_diff_requires_comparison() {
+    let temp_dir = TempDir::new().unwrap();
+    let a = create_test_wav(&temp_dir, "a", 440.0, 1.0);
+    let out = temp_dir.path().join("diff.wav");
+
+    let output = run_bandstat(&[a.to_str().unwrap(), "--sonify-diff", out.to_str().unwrap()]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--sonify-diff requires comparison mode"));
+}
+
+// =============================================================================
+// Report Template Tests
+// =============================================================================
+
+#[test]
+fn test_report_replaces_tables_on_stdout() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_test_wav(&temp_dir, "mix & master", 100.0, 1.0);
+    let template = temp_dir.path().join("report.md.hbs");
+    std::fs::write(
+        &template,
+        "# {{files.0.name}}\n{{#each files.0.bands}}| {{label}} | {{fixed raw_pct 1}} |\n{{/each}}",
+    )
+    .unwrap();
+
+    let output = run_bandstat(&[
+        "--report",
+        template.to_str().unwrap(),
+        wav_path.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // Markdown templates are not HTML-escaped
+    assert!(stdout.starts_with("# mix & master.wav\n"));
+    assert!(stdout.contains("| BASS | "));
+    assert!(!stdout.contains("[Band Power Distribution]"));
+}
+
+#[test]
+fn test_report_compare_to_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let a = create_test_wav(&temp_dir, "a", 100.0, 1.0);
+    let b = create_test_wav(&temp_dir, "b", 3000.0, 1.0);
+    let template = temp_dir.path().join("report.html.hbs");
+    std::fs::write(
+        &template,
+        "<p>{{mode}}</p>{{#each files}}<li>{{label}} {{name}} {{fixed bands.3.raw_diff 0}}</li>{{/each}}",
+    )
+    .unwrap();
+    let out = temp_dir.path().join("report.html");
+
+    let output = run_bandstat(&[
+        "-q",
+        "--report",
+        template.to_str().unwrap(),
+        "-o",
+        out.to_str().unwrap(),
+        a.to_str().unwrap(),
+        b.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    // Tables still go to stdout when the report is written to a file
+    assert!(String::from_utf8_lossy(&output.stdout).contains("[Band Power Distribution]"));
+
+    let report = std::fs::read_to_string(&out).unwrap();
+    assert!(report.starts_with("<p>compare</p>"));
+    assert!(report.contains("<li>A a.wav 0</li>"));
+    assert!(report.contains("<li>B b.wav -"));
+}
+
+#[test]
+fn test_report_unknown_field_error() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_test_wav(&temp_dir, "test", 440.0, 1.0);
+    let template = temp_dir.path().join("report.hbs");
+    std::fs::write(&template, "{{files.0.loudness}}").unwrap();
+
+    let output = run_bandstat(&[
+        "--report",
+        template.to_str().unwrap(),
+        wav_path.to_str().unwrap(),
+    ]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("report.hbs"));
+}
+
+#[test]
+fn test_html_report_embeds_chart() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_test_wav(&temp_dir, "test", 100.0, 3.0);
+    let out = temp_dir.path().join("report.html");
+
+    let output = run_bandstat(&[
+        "-q",
+        "--report",
+        out.to_str().unwrap(),
+        wav_path.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    let report = std::fs::read_to_string(&out).unwrap();
+    assert!(report.contains("<h2>[A] test.wav</h2>"));
+    assert!(report.contains("<svg"));
+
+    // Timelines get an interval table and the timeline chart
+    let output = run_bandstat(&[
+        "-q",
+        "--time",
+        "-i",
+        "1",
+        "--report",
+        out.to_str().unwrap(),
+        wav_path.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    let report = std::fs::read_to_string(&out).unwrap();
+    assert!(report.contains("<tr><td>00:02</td>"));
+    assert!(report.contains("<svg"));
+}
+
+// =============================================================================
+// Multiple Image Tests
+// =============================================================================
+
+#[test]
+fn test_multiple_images_single_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_test_wav(&temp_dir, "test", 440.0, 3.0);
+    let stats_png = temp_dir.path().join("stats.png");
+    let timeline_png = temp_dir.path().join("timeline.png");
+
+    let output = run_bandstat(&[
+        "-q",
+        "-i",
+        "1",
+        "--image",
+        stats_png.to_str().unwrap(),
+        "--image",
+        &format!("timeline={}", timeline_png.display()),
+        wav_path.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    assert!(stats_png.exists());
+    assert!(timeline_png.exists());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(stderr.matches("Chart saved to:").count(), 2);
+}
+
+#[test]
+fn test_image_kind_errors() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_test_wav(&temp_dir, "test", 440.0, 1.0);
+    let png = temp_dir.path().join("out.png");
+
+    let output = run_bandstat(&[
+        "--image",
+        &format!("spectrum={}", png.display()),
+        wav_path.to_str().unwrap(),
+    ]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown chart kind 'spectrum'"));
+
+    let output = run_bandstat(&[
+        "--image",
+        &format!("compare={}", png.display()),
+        wav_path.to_str().unwrap(),
+    ]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not available in single-file mode"));
+}
+
+// =============================================================================
+// Loudness Chart Tests
+// =============================================================================
+
+#[test]
+fn test_image_loudness_creates_png() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_test_wav(&temp_dir, "test", 1000.0, 4.0);
+    let png = temp_dir.path().join("loudness.png");
+
+    let output = run_bandstat(&[
+        "-q",
+        "--image-loudness",
+        png.to_str().unwrap(),
+        wav_path.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    assert!(png.exists());
+
+    let output = run_bandstat(&[
+        "--image-loudness",
+        png.to_str().unwrap(),
+        wav_path.to_str().unwrap(),
+        wav_path.to_str().unwrap(),
+    ]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not available in comparison mode"));
+}
+
+// =============================================================================
+// Band Group Tests
+// =============================================================================
+
+#[test]
+fn test_group_summary_above_band_table() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_test_wav(&temp_dir, "test", 1000.0, 1.0);
+
+    let output = run_bandstat(&["-q", wav_path.to_str().unwrap()]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let groups = stdout.find("[Band Groups]").expect("group summary");
+    assert!(groups < stdout.find("[Band Power Distribution]").unwrap());
+    assert!(stdout.contains("LOW 0-250, MID 250-4k, HIGH 4k+ Hz"));
+    assert!(stdout.contains("MID 100.0"));
+
+    let output = run_bandstat(&["-q", "--group-edges", "120,500", wav_path.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("HIGH 100.0"));
+}
+
+#[test]
+fn test_group_edges_must_be_band_edges() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_test_wav(&temp_dir, "test", 1000.0, 1.0);
+
+    let output = run_bandstat(&["--group-edges", "300,4000", wav_path.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("300 Hz is not a band edge"));
+}
+
+// =============================================================================
+// File Limit Tests
+// =============================================================================
+
+#[test]
+fn test_file_limit_and_matrix_layout() {
+    let temp_dir = TempDir::new().unwrap();
+    let freqs = [
+        100.0, 200.0, 400.0, 800.0, 1600.0, 3200.0, 6400.0, 9000.0, 11000.0, 13000.0, 15000.0,
+    ];
+    let paths: Vec<String> = freqs
+        .iter()
+        .enumerate()
+        .map(|(i, &f)| {
+            create_test_wav(&temp_dir, &format!("t{}", i), f, 1.0)
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect();
+    let mut args: Vec<&str> = vec!["-q"];
+    args.extend(paths.iter().map(String::as_str));
+
+    let output = run_bandstat(&args);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("max 10; raise with --max-files"));
+
+    args.splice(0..0, ["--max-files", "11"]);
+    let output = run_bandstat(&args);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("[Band Power Distribution: K-wt(%)]"));
+    let header = stdout.lines().find(|l| l.starts_with("BAND")).unwrap();
+    assert!(header.contains("[A]") && header.contains("[K]"));
+}
+
+// =============================================================================
+// File Label Tests
+// =============================================================================
+
+#[test]
+fn test_label_replaces_file_names() {
+    let temp_dir = TempDir::new().unwrap();
+    let a = create_test_wav(&temp_dir, "mixdown", 100.0, 1.0);
+    let b = create_test_wav(&temp_dir, "other", 1000.0, 1.0);
+
+    let output = run_bandstat(&[
+        "-q",
+        "--label",
+        "a=My Mix v3",
+        "--label",
+        "B=Ref: Artist - Song",
+        a.to_str().unwrap(),
+        b.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("[A] My Mix v3"));
+    assert!(stdout.contains("[B] Ref: Artist - Song"));
+    assert!(!stdout.contains("mixdown.wav"));
+
+    let output = run_bandstat(&["--label", "C=x", a.to_str().unwrap(), b.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("there is no file [C]"));
+}
+
+// =============================================================================
+// Band Selection Tests (--ignore-bands, --exclude-bands, --range, --highpass)
+// =============================================================================
+
+#[test]
+fn test_ignore_bands_renormalizes() {
+    let temp_dir = TempDir::new().unwrap();
+    let low = common::generate_sine(30.0, 48000, 2.0);
+    let mid = common::generate_sine(1000.0, 48000, 2.0);
+    let mixed: Vec<f32> = low.iter().zip(&mid).map(|(a, b)| 0.5 * (a + b)).collect();
+    let wav_path = temp_dir.path().join("mix.wav");
+    common::write_wav(&wav_path, &mixed, 48000).unwrap();
+
+    let output = run_bandstat(&["-q", "--ignore-bands", "SUB1", wav_path.to_str().unwrap()]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let header = stdout
+        .lines()
+        .find(|l| l.contains("SUB2"))
+        .expect("band header");
+    assert!(!header.contains("SUB1"));
+    // Without the 30 Hz band, the 1 kHz tone holds all the power
+    let total: f64 = raw_row_values(&stdout).iter().sum();
+    assert!((total - 100.0).abs() < 0.5);
+    assert!(stdout.contains("MID 100.0"));
+
+    let output = run_bandstat(&["--ignore-bands", "BOGUS", wav_path.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown band 'BOGUS'"));
+}
+
+#[test]
+fn test_exclude_bands_keeps_columns() {
+    let temp_dir = TempDir::new().unwrap();
+    let low = common::generate_sine(30.0, 48000, 2.0);
+    let mid = common::generate_sine(1000.0, 48000, 2.0);
+    let mixed: Vec<f32> = low.iter().zip(&mid).map(|(a, b)| 0.5 * (a + b)).collect();
+    let wav_path = temp_dir.path().join("mix.wav");
+    common::write_wav(&wav_path, &mixed, 48000).unwrap();
+    let wav = wav_path.to_str().unwrap();
+
+    let output = run_bandstat(&["-q", "--exclude-bands", "SUB1", wav]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let header = stdout
+        .lines()
+        .find(|l| l.contains("SUB2"))
+        .expect("band header");
+    assert!(header.contains("SUB1"));
+    let raw = stdout
+        .lines()
+        .find(|l| l.starts_with("Raw(%)"))
+        .expect("Raw row");
+    assert!(raw.contains("excl"), "{}", raw);
+    // The 30 Hz tone stays out of the total, so the 1 kHz tone holds all of it
+    assert!(stdout.contains("MID 100.0"), "{}", stdout);
+
+    let json = run_json(&["--format", "json", "--exclude-bands", "SUB1", wav]);
+    let bands = json["files"][0]["bands"].as_array().unwrap();
+    assert!(bands[1]["raw_pct"].is_null());
+    assert_eq!(bands[1]["excluded"], true);
+    assert!(bands[0].get("excluded").is_none());
+    let total: f64 = bands.iter().filter_map(|b| b["raw_pct"].as_f64()).sum();
+    assert!((total - 100.0).abs() < 1e-6);
+
+    let output = run_bandstat(&["--exclude-bands", "BOGUS", wav]);
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("--exclude-bands: unknown band 'BOGUS'")
+    );
+}
+
+#[test]
+fn test_range_clips_bands() {
+    let temp_dir = TempDir::new().unwrap();
+    let low = common::generate_sine(30.0, 48000, 2.0);
+    let mid = common::generate_sine(1000.0, 48000, 2.0);
+    let mixed: Vec<f32> = low.iter().zip(&mid).map(|(a, b)| 0.5 * (a + b)).collect();
+    let wav_path = temp_dir.path().join("mix.wav");
+    common::write_wav(&wav_path, &mixed, 48000).unwrap();
+
+    let output = run_bandstat(&["--range", "50-16000", wav_path.to_str().unwrap()]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // SUB2 is clipped to start at 50 Hz; DC, SUB1, and AIR fall outside
+    assert!(stdout.contains("SUB2:    50-   60 Hz"));
+    assert!(stdout.contains("UHIG: 14000-16000 Hz"));
+    assert!(!stdout.contains("SUB1"));
+    assert!(!stdout.contains("AIR"));
+    assert!(stdout.contains("MID 100.0"));
+}
+
+#[test]
+fn test_highpass_removes_subsonic() {
+    let temp_dir = TempDir::new().unwrap();
+    let rumble = common::generate_sine(8.0, 48000, 4.0);
+    let mid = common::generate_sine(1000.0, 48000, 4.0);
+    let mixed: Vec<f32> = rumble
+        .iter()
+        .zip(&mid)
+        .map(|(a, b)| 0.5 * (a + b))
+        .collect();
+    let wav_path = temp_dir.path().join("rumble.wav");
+    common::write_wav(&wav_path, &mixed, 48000).unwrap();
+    let wav = wav_path.to_str().unwrap();
+
+    let dc_share =
+        |json: &serde_json::Value| json["files"][0]["bands"][0]["raw_pct"].as_f64().unwrap();
+    let plain = run_json(&["--format", "json", wav]);
+    assert!(dc_share(&plain) > 40.0);
+    assert!(plain.get("highpass_hz").is_none());
+
+    // 12 dB per octave leaves the 8 Hz tone about 23 dB down
+    let filtered = run_json(&["--format", "json", "--highpass", "30", wav]);
+    assert!(dc_share(&filtered) < 1.0, "{}", dc_share(&filtered));
+    assert_eq!(filtered["highpass_hz"], 30.0);
+
+    let output = run_bandstat(&["--highpass", "0", wav]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--highpass must be above 0"));
+}
+
+// =============================================================================
+// JSON Output Tests
+// =============================================================================
+
+fn run_json(args: &[&str]) -> serde_json::Value {
+    let output = run_bandstat(args);
+    assert!(output.status.success());
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn test_json_stats_and_compare() {
+    let temp_dir = TempDir::new().unwrap();
+    let tone = create_test_wav(&temp_dir, "tone", 1000.0, 2.0);
+    let noise = create_noise_wav(&temp_dir, "noise", 2.0);
+    let tone = tone.to_str().unwrap();
+
+    let stats = run_json(&["--format", "json", tone]);
+    assert_eq!(stats["mode"], "stats");
+    assert_eq!(stats["files"][0]["name"], "tone.wav");
+    assert_eq!(stats["files"][0]["sample_rate"], 48000);
+    let bands = stats["files"][0]["bands"].as_array().unwrap();
+    assert_eq!(bands.len(), stats["bands"].as_array().unwrap().len());
+    let total: f64 = bands.iter().map(|b| b["raw_pct"].as_f64().unwrap()).sum();
+    assert!((total - 100.0).abs() < 0.01);
+
+    let compare = run_json(&["--format", "json", tone, noise.to_str().unwrap()]);
+    assert_eq!(compare["mode"], "compare");
+    assert_eq!(compare["files"][1]["label"], "B");
+    assert!(compare["files"][1]["flat_diff"].as_f64().unwrap() > 0.0);
+}
+
+#[test]
+fn test_json_timeline() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_test_wav(&temp_dir, "tone", 1000.0, 3.0);
+
+    let timeline = run_json(&[
+        "--format",
+        "json",
+        "--time",
+        "-i",
+        "1",
+        wav_path.to_str().unwrap(),
+    ]);
+    assert_eq!(timeline["mode"], "timeline");
+    assert_eq!(timeline["file"]["name"], "tone.wav");
+    assert_eq!(timeline["interval_secs"], 1);
+    let intervals = timeline["intervals"].as_array().unwrap();
+    assert_eq!(intervals.len(), 3);
+    assert_eq!(intervals[2]["start_secs"], 2.0);
+    assert_eq!(intervals[0]["groups"][1]["label"], "MID");
+    assert!(timeline["average"]["groups"][1]["pct"].as_f64().unwrap() > 99.0);
+    assert_eq!(timeline["duration_secs"], 3.0);
+}
+
+#[test]
+fn test_decimals_in_tables_and_json() {
+    let temp_dir = TempDir::new().unwrap();
+    let noise = create_noise_wav(&temp_dir, "noise", 1.0);
+    let noise = noise.to_str().unwrap();
+
+    let output = run_bandstat(&["--no-color", "-q", "--decimals", "3", noise]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let row = stdout.lines().find(|l| l.starts_with("Raw(%)")).unwrap();
+    let first = row.split_whitespace().nth(1).unwrap();
+    assert_eq!(first.split('.').nth(1).map(str::len), Some(3), "{}", row);
+
+    // JSON keeps full precision unless --round-output is given
+    let full = run_json(&["--format", "json", "--decimals", "2", noise]);
+    let rounded = run_json(&[
+        "--format",
+        "json",
+        "--decimals",
+        "2",
+        "--round-output",
+        noise,
+    ]);
+    let pct = |doc: &serde_json::Value| doc["files"][0]["bands"][5]["raw_pct"].as_f64().unwrap();
+    assert_ne!(pct(&full), (pct(&full) * 100.0).round() / 100.0);
+    assert_eq!(pct(&rounded), (pct(&full) * 100.0).round() / 100.0);
+
+    let output = run_bandstat(&["--decimals", "7", noise]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_json_rejects_report_on_stdout() {
+    let output = run_bandstat(&["--format", "json", "--report", "t.hbs", "a.wav"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--output"));
+}
+
+#[test]
+fn test_diff_results_compares_saved_json() {
+    let temp_dir = TempDir::new().unwrap();
+    let low = create_test_wav(&temp_dir, "low", 100.0, 1.0);
+    let high = create_test_wav(&temp_dir, "high", 3000.0, 1.0);
+    let mut saved = Vec::new();
+    for (wav, name) in [(&low, "low.json"), (&high, "high.json")] {
+        let output = run_bandstat(&["--format", "json", wav.to_str().unwrap()]);
+        assert!(output.status.success());
+        let path = temp_dir.path().join(name);
+        std::fs::write(&path, &output.stdout).unwrap();
+        saved.push(path);
+    }
+    // The audio is no longer needed
+    std::fs::remove_file(&low).unwrap();
+    std::fs::remove_file(&high).unwrap();
+
+    let output = run_bandstat(&[
+        "diff-results",
+        "--no-color",
+        saved[0].to_str().unwrap(),
+        saved[1].to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("[A] low.wav"));
+    assert!(stdout.contains("[B] high.wav"));
+    assert!(stdout.contains("[B]-[A] K-wt  LOW -100.0"));
+}
+
+#[test]
+fn test_diff_results_rejects_timeline() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_test_wav(&temp_dir, "test", 440.0, 1.0);
+    let output = run_bandstat(&["--format", "json", "--time", wav_path.to_str().unwrap()]);
+    let timeline = temp_dir.path().join("timeline.json");
+    std::fs::write(&timeline, &output.stdout).unwrap();
+
+    let output = run_bandstat(&[
+        "diff-results",
+        timeline.to_str().unwrap(),
+        timeline.to_str().unwrap(),
+    ]);
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("timeline results cannot be compared")
+    );
+}
+
+#[test]
+fn test_trend_across_saved_runs() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut saved = Vec::new();
+    for (name, freq) in [("v1", 100.0), ("v2", 3000.0)] {
+        let wav = create_test_wav(&temp_dir, "mix", freq, 1.0);
+        let output = run_bandstat(&["--format", "json", wav.to_str().unwrap()]);
+        assert!(output.status.success());
+        let path = temp_dir.path().join(format!("{}.json", name));
+        std::fs::write(&path, &output.stdout).unwrap();
+        saved.push(path.to_str().unwrap().to_string());
+    }
+    let chart = temp_dir.path().join("trend.png");
+
+    let output = run_bandstat(&[
+        "trend",
+        &saved[0],
+        &saved[1],
+        "--band",
+        "bass",
+        "--image",
+        chart.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let rows: Vec<&str> = stdout.lines().skip(2).collect();
+    assert!(rows[0].starts_with("v1   100.0"), "{}", stdout);
+    assert!(rows[1].starts_with("v2     0.0"), "{}", stdout);
+    assert!(stdout.contains("LUFS"));
+    assert!(chart.exists());
+}
+
+// =============================================================================
+// IPC Mode Tests
+// =============================================================================
+
+#[test]
+fn test_ipc_serves_requests_until_shutdown() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new().unwrap();
+    let wav_path = create_test_wav(&temp_dir, "tone", 1000.0, 1.0);
+    let analyze = format!(
+        r#"{{"jsonrpc":"2.0","id":1,"method":"analyze","params":{{"files":[{:?}]}}}}"#,
+        wav_path.to_str().unwrap()
+    );
+
+    let mut child = bandstat_command()
+        .arg("--ipc")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    // The second analyze is served from the cache; nothing after shutdown is answered
+    for line in [
+        analyze.as_str(),
+        analyze.as_str(),
+        r#"{"jsonrpc":"2.0","id":2,"method":"shutdown"}"#,
+        r#"{"jsonrpc":"2.0","id":3,"method":"shutdown"}"#,
+    ] {
+        writeln!(stdin, "{}", line).unwrap();
+    }
+    drop(stdin);
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let messages: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    let responses: Vec<&serde_json::Value> =
+        messages.iter().filter(|m| m.get("id").is_some()).collect();
+    assert_eq!(responses.len(), 3);
+    assert_eq!(
+        responses[0]["result"]["files"],
+        responses[1]["result"]["files"]
+    );
+    assert_eq!(responses[0]["result"]["files"][0]["name"], "tone.wav");
+    assert!(responses[2]["result"].is_null());
+
+    let progress = messages.iter().filter(|m| m["method"] == "progress");
+    assert!(progress.clone().count() > 0);
+    assert!(progress.into_iter().all(|m| m["params"]["id"] == 1));
+}
+
+// =============================================================================
+// Batch Mode Tests
+// =============================================================================
+
+#[test]
+fn test_batch_reads_file_list_from_stdin() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new().unwrap();
+    let tone = create_test_wav(&temp_dir, "tone", 1000.0, 1.0);
+    let noise = create_noise_wav(&temp_dir, "noise", 1.0);
+    let missing = temp_dir.path().join("missing.wav");
+
+    let mut child = bandstat_command()
+        .args(["--batch", "-q", "--no-color", tone.to_str().unwrap(), "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    writeln!(stdin, "{}", noise.to_str().unwrap()).unwrap();
+    writeln!(stdin).unwrap();
+    writeln!(stdin, "{}", missing.to_str().unwrap()).unwrap();
+    drop(stdin);
+    let output = child.wait_with_output().unwrap();
+
+    // The missing file is reported, the others still get their rows
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let rows: Vec<&str> = stdout.lines().filter(|l| l.ends_with(".wav")).collect();
+    assert_eq!(rows.len(), 2, "{}", stdout);
+    assert!(rows[0].ends_with("tone.wav"));
+    assert!(rows[1].ends_with("noise.wav"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("missing.wav"));
+}
+
+#[test]
+fn test_batch_directory_summary_and_csv() {
+    let temp_dir = TempDir::new().unwrap();
+    create_test_wav(&temp_dir, "tone", 1000.0, 1.0);
+    std::fs::create_dir(temp_dir.path().join("sub")).unwrap();
+    let noise = common::generate_noise(48000, 1.0, 7);
+    common::write_wav(&temp_dir.path().join("sub/noise.wav"), &noise, 48000).unwrap();
+    std::fs::write(temp_dir.path().join("notes.txt"), "not audio").unwrap();
+    let dir = temp_dir.path().to_str().unwrap();
+    let csv = temp_dir.path().join("out.csv");
+
+    let rows = |args: &[&str]| -> Vec<String> {
+        let output = run_bandstat(args);
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter(|l| l.ends_with(".wav"))
+            .map(String::from)
+            .collect()
+    };
+    assert_eq!(rows(&["batch", "-q", dir]).len(), 1);
+
+    let summary = rows(&[
+        "batch",
+        "-q",
+        "--recursive",
+        "--summary",
+        "--csv",
+        csv.to_str().unwrap(),
+        dir,
+    ]);
+    assert_eq!(summary.len(), 2, "{:?}", summary);
+    // The 1 kHz tone sits in MID and UMID
+    assert!(summary[1].contains("MID") && summary[1].ends_with("tone.wav"));
+
+    let table = std::fs::read_to_string(&csv).unwrap();
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("file,lufs,top1,top1_pct"));
+    assert!(lines[0].ends_with("AIR_raw_pct"));
+    assert!(lines[2].contains("tone.wav,") && lines[2].contains(",MID,"));
+
+    let output = run_bandstat(&["--csv", csv.to_str().unwrap(), dir]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("only be used with --batch"));
+}
+
+#[test]
+fn test_glob_patterns_select_files() {
+    let temp_dir = TempDir::new().unwrap();
+    create_test_wav(&temp_dir, "mix_a", 1000.0, 1.0);
+    create_test_wav(&temp_dir, "mix_b", 200.0, 1.0);
+    let pattern = temp_dir.path().join("mix_*.wav");
+
+    // Two matches make a comparison
+    let output = run_bandstat(&["-q", "--no-color", pattern.to_str().unwrap()]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("[A]") && stdout.contains("mix_b.wav"),
+        "{}",
+        stdout
+    );
+
+    let output = run_bandstat(&["-q", temp_dir.path().join("*.flac").to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no files match"));
+}
+
+#[test]
+fn test_batch_renders_chart_per_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut files = Vec::new();
+    for (i, freq) in [100.0, 1000.0, 5000.0].into_iter().enumerate() {
+        let path = create_test_wav(&temp_dir, &format!("tone{}", i), freq, 1.0);
+        files.push(path.to_str().unwrap().to_string());
+    }
+    let pattern = temp_dir.path().join("{name}.png");
+
+    let mut args = vec!["--batch", "-q", "--jobs", "2", "--image"];
+    args.push(pattern.to_str().unwrap());
+    args.extend(files.iter().map(String::as_str));
+    let output = run_bandstat(&args);
+    assert!(output.status.success());
+
+    for i in 0..3 {
+        let chart = temp_dir.path().join(format!("tone{}.png", i));
+        assert!(chart.exists(), "{} should be created", chart.display());
+    }
+}
+
+#[test]
+fn test_batch_option_errors() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav = create_test_wav(&temp_dir, "tone", 1000.0, 1.0);
+    let wav = wav.to_str().unwrap();
+
+    let output = run_bandstat(&["--batch", wav, "--image", "chart.png"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("{name}"));
+
+    let output = run_bandstat(&["--batch", "--time", wav]);
+    assert!(!output.status.success());
+
+    let output = run_bandstat(&["--jobs", "2", wav]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--batch"));
+}
+
+#[test]
+fn test_jobs_keep_results_in_input_order() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut files = Vec::new();
+    for (i, freq) in [100.0, 1000.0, 5000.0, 200.0].into_iter().enumerate() {
+        let path = create_test_wav(&temp_dir, &format!("tone{}", i), freq, 1.0);
+        files.push(path.to_str().unwrap().to_string());
+    }
+
+    for mode in [&["--batch", "-q"][..], &["compare", "-q"][..]] {
+        let run = |jobs: &str| {
+            let mut args = mode.to_vec();
+            args.extend(["--jobs", jobs]);
+            args.extend(files.iter().map(String::as_str));
+            let output = run_bandstat(&args);
+            assert!(output.status.success());
+            output.stdout
+        };
+        assert_eq!(run("1"), run("3"));
+    }
+}
+
+#[test]
+fn test_jobs_print_decode_warnings_on_their_own_line() {
+    let temp_dir = TempDir::new().unwrap();
+    let silent = temp_dir.path().join("silent.wav");
+    common::write_wav(&silent, &vec![0.0; 48000], 48000).unwrap();
+    let tone = create_test_wav(&temp_dir, "tone", 1000.0, 1.0);
+
+    let output = run_bandstat(&[
+        "--batch",
+        "--jobs",
+        "2",
+        silent.to_str().unwrap(),
+        tone.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+
+    // The warning follows its file's line, clear of the shared progress line
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let done = stderr.find("Analyzing silent.wav... done").unwrap();
+    let warning = stderr[done..].find("Warning:");
+    let line = &stderr[done + warning.expect("silence warning after the file's line")..];
+    assert!(
+        line.lines().next().unwrap().contains("digital silence"),
+        "{:?}",
+        stderr
+    );
+}
+
+#[test]
+fn test_max_memory_keeps_results() {
+    let temp_dir = TempDir::new().unwrap();
+    let noise = create_noise_wav(&temp_dir, "noise", 12.0);
+    let noise = noise.to_str().unwrap();
+
+    for mode in [&["timeline", "-i", "2"][..], &["-q"][..]] {
+        let run = |max_memory: Option<&str>| {
+            let mut args = mode.to_vec();
+            if let Some(mb) = max_memory {
+                args.extend(["--max-memory", mb]);
+            }
+            args.push(noise);
+            let output = run_bandstat(&args);
+            assert!(output.status.success());
+            output.stdout
+        };
+        assert_eq!(run(None), run(Some("16")));
+    }
+}
+
+#[test]
+fn test_max_memory_rejects_whole_file_analyses_that_do_not_fit() {
+    let temp_dir = TempDir::new().unwrap();
+    let tone = create_test_wav(&temp_dir, "long", 1000.0, 100.0);
+    let output = run_bandstat(&[
+        "--max-memory",
+        "16",
+        "--active-only",
+        tone.to_str().unwrap(),
+    ]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("over --max-memory 16 MB"));
+
+    let output = run_bandstat(&["--max-memory", "8", tone.to_str().unwrap()]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--max-memory must be at least 16 MB"));
+}
+
+#[test]
+fn test_watch_analyzes_new_files() {
+    use std::io::BufRead;
+
+    let temp_dir = TempDir::new().unwrap();
+    let mut child = bandstat_command()
+        .args([
+            "watch",
+            "-q",
+            "--no-color",
+            temp_dir.path().to_str().unwrap(),
+        ])
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let stdout = child.stdout.take().unwrap();
+    std::thread::spawn(move || {
+        for line in std::io::BufReader::new(stdout).lines() {
+            if sender.send(line.unwrap()).is_err() {
+                return;
+            }
+        }
+    });
+    let next_line = || {
+        receiver
+            .recv_timeout(std::time::Duration::from_secs(30))
+            .expect("no output from watch")
+    };
+
+    // The header is printed once the directory is watched
+    while !next_line().starts_with("---") {}
+    std::fs::write(temp_dir.path().join("notes.txt"), "not audio").unwrap();
+    create_test_wav(&temp_dir, "bounce", 1000.0, 1.0);
+
+    let row = next_line();
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert!(row.ends_with("bounce.wav"), "{}", row);
+}
+
+#[test]
+fn test_watch_option_errors() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav = create_test_wav(&temp_dir, "tone", 1000.0, 1.0);
+
+    let output = run_bandstat(&["watch", wav.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("single directory"));
+
+    let dir = temp_dir.path().to_str().unwrap();
+    let output = run_bandstat(&["--watch", "--time", dir]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("cannot be used with") && stderr.contains("--time"));
+}
+
+#[test]
+fn test_live_option_errors() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav = create_test_wav(&temp_dir, "tone", 1000.0, 1.0);
+
+    let output = run_bandstat(&["--live", wav.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("'--live' cannot be used with"));
+
+    let output = run_bandstat(&["--device", "USB", wav.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--device can only be used"));
+}
+
+#[test]
+#[cfg(not(feature = "live"))]
+fn test_live_requires_feature() {
+    let output = run_bandstat(&["live", "--device", "USB"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--features live"));
+}
+
+#[test]
+fn test_tui_option_errors() {
+    let temp_dir = TempDir::new().unwrap();
+    let wav = create_test_wav(&temp_dir, "tone", 1000.0, 1.0);
+    let path = wav.to_str().unwrap();
+
+    let output = run_bandstat(&["--tui", "--time", path]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("cannot be used with"));
+
+    let output = run_bandstat(&["--tui", path, path]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--tui plays a single audio file"));
+
+    // The meter takes over the terminal, so it isn't started with stdout piped
+    let output = run_bandstat(&["analyze", "--tui", path]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--tui needs a terminal"));
+}
+
+// =============================================================================
+// GUI Subcommand Tests
+// =============================================================================
+
+#[test]
+#[cfg(not(feature = "gui"))]
+fn test_gui_requires_feature() {
+    let output = run_bandstat(&["gui", "audio.wav"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--features gui"));
+}
+
+// =============================================================================
+// Subcommand Tests
+// =============================================================================
+
+/// JSON results without the time they were generated, for comparing invocations
+fn run_json_untimed(args: &[&str]) -> serde_json::Value {
+    let mut json = run_json(args);
+    json.as_object_mut().unwrap().remove("generated");
+    json
+}
+
+#[test]
+fn test_subcommands_match_flat_invocation() {
+    let temp_dir = TempDir::new().unwrap();
+    let tone = create_test_wav(&temp_dir, "tone", 1000.0, 3.0);
+    let noise = create_noise_wav(&temp_dir, "noise", 3.0);
+    let (tone, noise) = (tone.to_str().unwrap(), noise.to_str().unwrap());
+
+    assert_eq!(
+        run_json_untimed(&["analyze", "--format", "json", "-w", tone]),
+        run_json_untimed(&["--format", "json", "-w", tone])
+    );
+    assert_eq!(
+        run_json_untimed(&[
+            "compare", tone, noise, "--format", "json", "--label", "B=Noise"
+        ]),
+        run_json_untimed(&["--format", "json", "--label", "B=Noise", tone, noise])
+    );
+    assert_eq!(
+        run_json_untimed(&["timeline", "-i", "1", "--format", "json", tone]),
+        run_json_untimed(&["--time", "-i", "1", "--format", "json", tone])
+    );
+}
+
+#[test]
+fn test_subcommands_only_take_their_own_options() {
+    let temp_dir = TempDir::new().unwrap();
+    let tone = create_test_wav(&temp_dir, "tone", 1000.0, 1.0);
+    let tone = tone.to_str().unwrap();
+
+    for args in [
+        vec!["analyze", "-i", "5", tone],
+        vec!["analyze", "--align", tone],
+        vec!["timeline", "--label", "A=Mix", tone],
+        vec!["compare", tone],
+    ] {
+        let output = run_bandstat(&args);
+        assert!(!output.status.success(), "{:?} was accepted", args);
+    }
+
+    let output = run_bandstat(&["chart", tone]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--image"));
+}
+
+#[test]
+fn test_gen_writes_calibration_tone() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("tone.wav");
+    let path = path.to_str().unwrap();
+
+    let output = run_bandstat(&[
+        "gen",
+        "sine",
+        path,
+        "--freq",
+        "1000",
+        "--level",
+        "-20",
+        "--duration",
+        "2",
+    ]);
+    assert!(output.status.success());
+    let output = run_bandstat(&["--verify-tone", "1000:-20", path]);
+    assert!(output.status.success());
+
+    let output = run_bandstat(&["gen", "pink", path, "--freq", "1000"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--freq"));
+}
+
+#[test]
+fn test_gen_export_formats() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("tone.wav");
+    let path = path.to_str().unwrap();
+
+    for (format, tag, bits) in [("pcm24", 1u16, 24u16), ("float32", 3, 32)] {
+        let output = run_bandstat(&[
+            "gen",
+            "sine",
+            path,
+            "--duration",
+            "2",
+            "--export-format",
+            format,
+        ]);
+        assert!(output.status.success(), "{}", format);
+        let bytes = std::fs::read(path).unwrap();
+        assert_eq!(
+            u16::from_le_bytes([bytes[20], bytes[21]]),
+            tag,
+            "{}",
+            format
+        );
+        assert_eq!(
+            u16::from_le_bytes([bytes[34], bytes[35]]),
+            bits,
+            "{}",
+            format
+        );
+        let output = run_bandstat(&["--verify-tone", "1000:-20", path]);
+        assert!(output.status.success(), "{}", format);
+    }
+}
+
+// =============================================================================
+// Config File Tests
+// =============================================================================
+
+#[test]
+fn test_config_file_sets_defaults() {
+    let temp_dir = TempDir::new().unwrap();
+    let tone = create_test_wav(&temp_dir, "tone", 1000.0, 3.0);
+    let tone = tone.to_str().unwrap();
+    let config = temp_dir.path().join("config.toml");
+    std::fs::write(
+        &config,
+        "interval = 1\nchart_size = \"1400x600\"\n\n[bands]\nignore = [\"DC\", \"AIR\"]\n",
+    )
+    .unwrap();
+    let config = config.to_str().unwrap();
+
+    let timeline = run_json(&["--config", config, "--format", "json", "--time", tone]);
+    assert_eq!(timeline["interval_secs"], 1);
+    assert_eq!(timeline["bands"].as_array().unwrap().len(), 12);
+
+    // Options on the command line win
+    let timeline = run_json(&[
+        "--config", config, "--format", "json", "--time", "-i", "3", tone,
+    ]);
+    assert_eq!(timeline["interval_secs"], 3);
+
+    // The interval does not apply to single-file stats, so it is no error there
+    let stats = run_json(&["analyze", "--config", config, "--format", "json", tone]);
+    assert_eq!(stats["bands"].as_array().unwrap().len(), 12);
+
+    let stats = run_json(&["--no-config", "--format", "json", tone]);
+    assert_eq!(stats["bands"].as_array().unwrap().len(), 14);
+}
+
+#[test]
+fn test_config_file_errors() {
+    let temp_dir = TempDir::new().unwrap();
+    let tone = create_test_wav(&temp_dir, "tone", 1000.0, 1.0);
+    let config = temp_dir.path().join("config.toml");
+    std::fs::write(&config, "intervall = 10\n").unwrap();
+
+    let output = run_bandstat(&["--config", config.to_str().unwrap(), tone.to_str().unwrap()]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("config.toml") && stderr.contains("intervall"));
+
+    let missing = temp_dir.path().join("missing.toml");
+    let output = run_bandstat(&[
+        "--config",
+        missing.to_str().unwrap(),
+        tone.to_str().unwrap(),
+    ]);
+    assert!(!output.status.success());
+}
+
+// =============================================================================
+// Impulse Response Tests
+// =============================================================================
+
+#[test]
+fn test_ir_reports_gain_and_group_delay() {
+    let temp_dir = TempDir::new().unwrap();
+    // Unit impulse 5 ms in: every band passes at unity with a 5 ms delay
+    let mut samples = vec![0.0f32; 4800];
+    samples[240] = 0.5;
+    let path = temp_dir.path().join("ir.wav");
+    common::write_wav(&path, &samples, 48000).unwrap();
+    let path = path.to_str().unwrap();
+
+    let result = run_json(&["analyze", "--ir", "--format", "json", path]);
+    assert_eq!(result["mode"], "impulse");
+    assert!((result["peak_ms"].as_f64().unwrap() - 5.0).abs() < 0.01);
+    for (gain, delay) in result["gain_db"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .zip(result["group_delay_ms"].as_array().unwrap())
+    {
+        assert!((gain.as_f64().unwrap() + 6.02).abs() < 0.1, "{}", gain);
+        assert!((delay.as_f64().unwrap() - 5.0).abs() < 0.05, "{}", delay);
+    }
+
+    let output = run_bandstat(&["-q", "--ir", path]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Gain(dB)") && stdout.contains("GD(ms)"));
+
+    let output = run_bandstat(&["--ir", "--time", path]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--ir"));
+}
+
+// =============================================================================
+// Environment Variable Tests
+// =============================================================================
+
+/// Run bandstat with extra environment variables
+fn run_bandstat_env(vars: &[(&str, &str)], args: &[&str]) -> std::process::Output {
+    bandstat_command()
+        .envs(vars.iter().copied())
+        .args(args)
+        .output()
+        .expect("failed to execute bandstat")
+}
+
+#[test]
+fn test_env_vars_layer_under_flags() {
+    let temp_dir = TempDir::new().unwrap();
+    let tone = create_test_wav(&temp_dir, "tone", 1000.0, 3.0);
+    let tone = tone.to_str().unwrap();
+    let env = [("BANDSTAT_FORMAT", "json"), ("BANDSTAT_INTERVAL", "1")];
+
+    let output = run_bandstat_env(&env, &["--time", tone]);
+    assert!(output.status.success());
+    let timeline: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(timeline["interval_secs"], 1);
+
+    // The interval does not apply to single-file stats, so it is no error there
+    let output = run_bandstat_env(&env, &["analyze", tone]);
+    assert!(output.status.success());
+    let stats: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(stats["mode"], "stats");
+
+    // Flags win over the environment
+    let output = run_bandstat_env(&env, &["--format", "text", "--time", "-i", "3", tone]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("AVG"), "{}", stdout);
+
+    let output = run_bandstat_env(&[("BANDSTAT_INTERVAL", "ten")], &["--time", tone]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("BANDSTAT_INTERVAL"));
+
+    let output = run_bandstat_env(&[("BANDSTAT_FORMAT", "xml")], &[tone]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_gate_layers_flag_env_config() {
+    let temp_dir = TempDir::new().unwrap();
+    // 2 s of tone, then 3 s of room tone around -70 dBFS
+    let mut samples = common::generate_sine(1000.0, 48000, 2.0);
+    samples.extend(
+        common::generate_noise(48000, 3.0, 7)
+            .iter()
+            .map(|s| s * 0.0005),
+    );
+    let wav_path = temp_dir.path().join("takes.wav");
+    common::write_wav(&wav_path, &samples, 48000).unwrap();
+    let path = wav_path.to_str().unwrap();
+    let config = temp_dir.path().join("config.toml");
+    std::fs::write(&config, "gate = \"-50dB\"\n").unwrap();
+    let config = config.to_str().unwrap();
+
+    let rows = |vars: &[(&str, &str)], extra: &[&str]| {
+        let mut args = vec!["-q", "-t", "-i", "1", "--config", config, path];
+        args.extend(extra);
+        let output = run_bandstat_env(vars, &args);
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter(|l| l.starts_with("00:"))
+            .count()
+    };
+    // The config gate drops the room tone, the environment loosens it, and
+    // the flag wins over both
+    assert_eq!(rows(&[], &[]), 2);
+    assert_eq!(rows(&[("BANDSTAT_GATE", "-90dB")], &[]), 5);
+    assert_eq!(rows(&[("BANDSTAT_GATE", "-90dB")], &["--gate", "-50dB"]), 2);
+
+    let output = run_bandstat_env(&[("BANDSTAT_GATE", "-6x")], &["-t", path]);
+    assert!(!output.status.success());
+}
+
+// =============================================================================
+// Weighted Dynamics Tests
+// =============================================================================
+
+#[test]
+fn test_match_distributions_ignores_order_and_tempo() {
+    let temp_dir = TempDir::new().unwrap();
+    let section = |freq, secs| common::generate_sine(freq, 48000, secs);
+    let full = temp_dir.path().join("full.wav");
+    common::write_wav(
+        &full,
+        &[section(1500.0, 6.0), section(5000.0, 6.0)].concat(),
+        48000,
+    )
+    .unwrap();
+    // The same two sections in the other order, each half as long
+    let edit = temp_dir.path().join("edit.wav");
+    common::write_wav(
+        &edit,
+        &[section(5000.0, 3.0), section(1500.0, 3.0)].concat(),
+        48000,
+    )
+    .unwrap();
+    let (full, edit) = (full.to_str().unwrap(), edit.to_str().unwrap());
+
+    let json = run_json(&["--match-distributions", "--format", "json", full, edit]);
+    for band in json["files"][1]["bands"].as_array().unwrap() {
+        assert!(
+            band["frame_share_diff"].as_f64().unwrap().abs() < 2.0,
+            "{}",
+            band
+        );
+        assert!(band["frame_share_emd"].as_f64().unwrap() < 5.0, "{}", band);
+    }
+    let umid = &json["files"][0]["bands"][7];
+    assert!((umid["frame_share_pct"].as_f64().unwrap() - 50.0).abs() < 5.0);
+
+    let output = run_bandstat(&["--match-distributions", full, edit]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("[Distribution Match]"), "{}", stdout);
+    assert!(stdout.contains("B-A EMD"), "{}", stdout);
+
+    let output = run_bandstat(&["--match-distributions", full]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_weighted_dynamics_rows() {
+    let temp_dir = TempDir::new().unwrap();
+    let noise = create_noise_wav(&temp_dir, "noise", 2.0);
+    let tone = create_test_wav(&temp_dir, "tone", 1000.0, 2.0);
+    let (noise, tone) = (noise.to_str().unwrap(), tone.to_str().unwrap());
+
+    let output = run_bandstat(&["-q", noise]);
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("K-wt Dyn"));
+    let output = run_bandstat(&["-q", "--weighted-dynamics", noise]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("K-wt Dyn"));
+
+    let output = run_bandstat(&["compare", "-q", "--weighted-dynamics", noise, tone]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("[A] K-wt") && stdout.contains("B-A K-wt"),
+        "{}",
+        stdout
+    );
+
+    // JSON always carries both
+    let stats = run_json(&["--format", "json", noise]);
+    let band = &stats["files"][0]["bands"][7];
+    assert!(band["dynamics_db"].is_number() && band["k_dynamics_db"].is_number());
+
+    let output = run_bandstat(&["--weighted-dynamics", "--time", noise]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--weighted-dynamics"));
+}
+
+#[test]
+fn test_energy_split_metrics() {
+    let temp_dir = TempDir::new().unwrap();
+    let low = create_test_wav(&temp_dir, "low", 60.0, 2.0);
+    let high = create_test_wav(&temp_dir, "high", 12000.0, 2.0);
+    let (low, high) = (low.to_str().unwrap(), high.to_str().unwrap());
+
+    let output = run_bandstat(&["-q", "--metric", "below:100", low]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("[Energy Split]"), "{}", stdout);
+
+    let compare = run_json(&[
+        "--format",
+        "json",
+        "--metric",
+        "below:100",
+        "--metric",
+        "above:10000",
+        low,
+        high,
+    ]);
+    let metrics = |file: usize| {
+        compare["files"][file]["metrics"]
+            .as_array()
+            .unwrap()
+            .clone()
+    };
+    assert_eq!(metrics(0)[0]["metric"], "below:100");
+    assert!(metrics(0)[0]["raw_pct"].as_f64().unwrap() > 99.0);
+    assert!(metrics(0)[1]["raw_pct"].as_f64().unwrap() < 1.0);
+    assert!(metrics(1)[1]["raw_pct"].as_f64().unwrap() > 99.0);
+    assert!(metrics(1)[0]["raw_diff"].as_f64().unwrap() < -99.0);
+
+    let output = run_bandstat(&["--metric", "below:100", "--time", low]);
+    assert!(!output.status.success());
+    let output = run_bandstat(&["--metric", "around:100", low]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_target_curve_deviation() {
+    let temp_dir = TempDir::new().unwrap();
+    let noise = create_noise_wav(&temp_dir, "noise", 3.0);
+    let tone = create_test_wav(&temp_dir, "tone", 1000.0, 3.0);
+    let (noise, tone) = (noise.to_str().unwrap(), tone.to_str().unwrap());
+    // A flat curve, as another analyzer would export white noise, with a header and phase column
+    let curve = temp_dir.path().join("white.csv");
+    std::fs::write(&curve, "Freq(Hz),SPL(dB),Phase\n20,-40,0\n20000,-40,0\n").unwrap();
+    let curve = curve.to_str().unwrap();
+
+    let output = run_bandstat(&["-q", "--target-curve", curve, noise]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("[Target Curve]"), "{}", stdout);
+
+    let compare = run_json(&["--format", "json", "--target-curve", curve, noise, tone]);
+    assert_eq!(compare["target_curve"], curve);
+    let noise_dev = compare["files"][0]["target_mean_dev_db"].as_f64().unwrap();
+    let tone_dev = compare["files"][1]["target_mean_dev_db"].as_f64().unwrap();
+    assert!(noise_dev < 1.0, "{}", noise_dev);
+    assert!(tone_dev > noise_dev + 10.0, "{}", tone_dev);
+    // DC lies below the curve's first point
+    assert!(compare["files"][0]["bands"][0]["target_pct"].is_null());
+
+    let output = run_bandstat(&["--target-curve", curve, "--time", noise]);
+    assert!(!output.status.success());
+    let output = run_bandstat(&["--target-curve", "missing.csv", noise]);
+    assert!(!output.status.success());
+}
+
+// =============================================================================
+// Band Layout Tests
+// =============================================================================
+
+#[test]
+fn test_bands_lists_the_layout_of_a_run() {
+    let layout = run_json(&["bands", "--format", "json"]);
+    assert_eq!(layout["mode"], "bands");
+    let bands = layout["bands"].as_array().unwrap();
+    assert_eq!(bands.len(), 14);
+    assert_eq!(bands[0]["label"], "DC");
+    assert_eq!(bands[0]["group"], "LOW");
+    assert!(bands[0]["color"].as_str().unwrap().starts_with('#'));
+    assert!(bands[13]["high_hz"].is_null());
+
+    // The same band options as an analysis, and the same bands
+    let temp_dir = TempDir::new().unwrap();
+    let tone = create_test_wav(&temp_dir, "tone", 1000.0, 1.0);
+    let options = ["--ignore-bands", "DC,AIR", "--group-edges", "120,6000"];
+    let layout = run_json(&[&["bands", "--format", "json"][..], &options].concat());
+    let stats = run_json(&[&["--format", "json", tone.to_str().unwrap()][..], &options].concat());
+    let labels = |bands: &serde_json::Value| -> Vec<String> {
+        bands
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|b| b["label"].as_str().unwrap().to_string())
+            .collect()
+    };
+    assert_eq!(labels(&layout["bands"]), labels(&stats["bands"]));
+    assert_eq!(layout["bands"][3]["group"], "MID");
+
+    let output = run_bandstat(&["bands"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("GROUP") && stdout.contains("#982060"),
+        "{}",
+        stdout
+    );
+
+    let output = run_bandstat(&["bands", "--ignore-bands", "NOPE"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_bands_file_replaces_the_layout() {
+    let temp_dir = TempDir::new().unwrap();
+    let tone = create_test_wav(&temp_dir, "tone", 1000.0, 3.0);
+    let tone = tone.to_str().unwrap();
+    let toml = temp_dir.path().join("bands.toml");
+    std::fs::write(
+        &toml,
+        "[[bands]]\nlabel = \"LOW\"\nlow_hz = 20\nhigh_hz = 500\n\n\
+         [[bands]]\nlabel = \"VOICE\"\nlow_hz = 500\nhigh_hz = 2000\n\n\
+         [[bands]]\nlabel = \"TOP\"\nlow_hz = 2000\n",
+    )
+    .unwrap();
+    let toml = toml.to_str().unwrap();
+
+    let stats = run_json(&["--bands-file", toml, "--format", "json", tone]);
+    let bands = stats["bands"].as_array().unwrap();
+    assert_eq!(bands.len(), 3);
+    assert_eq!(bands[1]["label"], "VOICE");
+    assert!(bands[2]["high_hz"].is_null());
+    assert!(stats["files"][0]["bands"][1]["raw_pct"].as_f64().unwrap() > 99.0);
+
+    // The JSON layout listing reads back as a bands file
+    let json = temp_dir.path().join("bands.json");
+    let output = run_bandstat(&["bands", "--bands-file", toml, "--format", "json"]);
+    assert!(output.status.success());
+    std::fs::write(&json, &output.stdout).unwrap();
+    let timeline = run_json(&[
+        "--bands-file",
+        json.to_str().unwrap(),
+        "--format",
+        "json",
+        "--time",
+        tone,
+    ]);
+    assert_eq!(timeline["bands"].as_array().unwrap().len(), 3);
+
+    let overlapping = temp_dir.path().join("overlap.toml");
+    std::fs::write(
+        &overlapping,
+        "[[bands]]\nlabel = \"A\"\nlow_hz = 0\nhigh_hz = 500\n\n\
+         [[bands]]\nlabel = \"B\"\nlow_hz = 400\n",
+    )
+    .unwrap();
+    let output = run_bandstat(&["--bands-file", overlapping.to_str().unwrap(), tone]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("overlaps"));
+}
+
+#[test]
+fn test_octave_band_preset() {
+    let temp_dir = TempDir::new().unwrap();
+    let tone = create_test_wav(&temp_dir, "tone", 1000.0, 3.0);
+    let tone = tone.to_str().unwrap();
+
+    let stats = run_json(&["--bands", "octave", "--format", "json", tone]);
+    let bands = stats["bands"].as_array().unwrap();
+    assert_eq!(bands.len(), 10);
+    assert_eq!(bands[0]["label"], "31.5");
+    assert_eq!(bands[9]["label"], "16k");
+    assert_eq!(bands[5]["label"], "1k");
+    assert!(stats["files"][0]["bands"][5]["raw_pct"].as_f64().unwrap() > 99.0);
+
+    let config = temp_dir.path().join("config.toml");
+    std::fs::write(&config, "[bands]\npreset = \"octave\"\n").unwrap();
+    let layout = run_json(&[
+        "bands",
+        "--config",
+        config.to_str().unwrap(),
+        "--format",
+        "json",
+    ]);
+    assert_eq!(layout["bands"].as_array().unwrap().len(), 10);
+
+    let output = run_bandstat(&["--bands", "third", tone]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_num_bands_log_layout() {
+    let temp_dir = TempDir::new().unwrap();
+    let tone = create_test_wav(&temp_dir, "tone", 300.0, 3.0);
+    let tone = tone.to_str().unwrap();
+
+    let stats = run_json(&[
+        "--num-bands",
+        "4",
+        "--range",
+        "100-1600",
+        "--format",
+        "json",
+        tone,
+    ]);
+    let bands = stats["bands"].as_array().unwrap();
+    assert_eq!(bands.len(), 4);
+    assert_eq!(bands[1]["label"], "LOG2");
+    assert_eq!(
+        (bands[1]["low_hz"].as_f64(), bands[1]["high_hz"].as_f64()),
+        (Some(200.0), Some(400.0))
+    );
+    assert!(stats["files"][0]["bands"][1]["raw_pct"].as_f64().unwrap() > 99.0);
+
+    let layout = run_json(&["bands", "--num-bands", "24", "--format", "json"]);
+    assert_eq!(layout["bands"].as_array().unwrap().len(), 24);
+
+    let output = run_bandstat(&["--num-bands", "4", "--bands", "octave", tone]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_mel_band_preset() {
+    let temp_dir = TempDir::new().unwrap();
+    let tone = create_test_wav(&temp_dir, "tone", 1250.0, 3.0);
+    let tone = tone.to_str().unwrap();
+
+    let stats = run_json(&["--bands", "mel:40", "--format", "json", tone]);
+    let bands = stats["bands"].as_array().unwrap();
+    assert_eq!(bands.len(), 40);
+    assert_eq!(bands[0]["label"], "MEL1");
+    assert_eq!(bands[0]["low_hz"], 0.0);
+    assert!(bands[39]["high_hz"].is_null());
+    // 1250 Hz lies in MEL12 (1165 to 1338.8 Hz)
+    assert!(stats["files"][0]["bands"][11]["raw_pct"].as_f64().unwrap() > 99.0);
+
+    let output = run_bandstat(&["--bands", "mel:1", tone]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_cue_sheet_splits_image_into_tracks() {
+    let temp_dir = TempDir::new().unwrap();
+    let mut samples = common::generate_sine(100.0, 48000, 2.0);
+    samples.extend(common::generate_sine(5000.0, 48000, 2.0));
+    common::write_wav(&temp_dir.path().join("Live Set.wav"), &samples, 48000).unwrap();
+    let cue = temp_dir.path().join("live.cue");
+    std::fs::write(
+        &cue,
+        "TITLE \"Live\"\nFILE \"Live Set.wav\" WAVE\n  TRACK 01 AUDIO\n    TITLE \"Low\"\n    INDEX 01 00:00:00\n  TRACK 02 AUDIO\n    TITLE \"High\"\n    INDEX 01 00:02:00\n",
+    )
+    .unwrap();
+    let cue = cue.to_str().unwrap();
+
+    let output = run_bandstat(&["-q", "--no-color", cue]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("01 Low") && stdout.contains("02 High") && stdout.contains("[Dynamics]"),
+        "{}",
+        stdout
+    );
+
+    // One JSON line per track, each with its own band shares
+    let output = run_bandstat(&["-q", "--format", "json", cue]);
+    assert!(output.status.success());
+    let tracks: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(tracks.len(), 2);
+    assert_eq!(tracks[0]["files"][0]["name"], "01 Low");
+    assert!(
+        tracks[0]["files"][0]["bands"][3]["raw_pct"]
+            .as_f64()
+            .unwrap()
+            > 95.0
+    );
+    assert!(
+        tracks[1]["files"][0]["bands"][9]["raw_pct"]
+            .as_f64()
+            .unwrap()
+            > 95.0
+    );
+
+    let output = run_bandstat(&["--time", cue]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("track by track"));
+}